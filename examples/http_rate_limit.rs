@@ -42,11 +42,11 @@ use rama::{
     http::{matcher::HttpMatcher, response::Json, server::HttpServer, Request},
     rt::Executor,
     service::{
-        layer::{limit::policy::ConcurrentPolicy, LimitLayer},
+        layer::{limit::policy::ConcurrentPolicy, DelayLayer, LimitLayer},
         util::{backoff::ExponentialBackoff, combinators::Either},
         ServiceBuilder,
     },
-    stream::matcher::SocketMatcher,
+    stream::matcher::LoopbackFilter,
 };
 use serde_json::json;
 
@@ -78,7 +78,7 @@ async fn main() {
                     // when choosing to use backoff, they have to be of same type (generic B),
                     // but you can make them also optional to not use backoff for some, while using it for others
                     (
-                        HttpMatcher::socket(SocketMatcher::loopback()).negate(),
+                        HttpMatcher::from_peer(LoopbackFilter::new()).negate(),
                         Some(Either::A(ConcurrentPolicy::with_backoff(1, None))),
                     ),
                     // you can also use options for the policy itself, in case you want to disable
@@ -116,10 +116,12 @@ async fn main() {
                         ))),
                     ),
                 ])))
+                // artificial latency for the slow routes, such that the limits are easy to trigger
+                .layer(
+                    DelayLayer::fixed(Duration::from_secs(10))
+                        .when(HttpMatcher::path("/limit/slow").or_path("/api/slow")),
+                )
                 .service_fn(|req: Request| async move {
-                    if req.uri().path().ends_with("/slow") {
-                        tokio::time::sleep(Duration::from_secs(10)).await;
-                    }
                     Ok::<_, Infallible>(Json(json!({
                         "method": req.method().as_str(),
                         "path": req.uri().path(),
@@ -1,3 +1,269 @@
 //! Shutdown management for graceful shutdown of async-first applications.
 
+use crate::service::layer::in_flight::{InFlightGuard, InFlightHandle};
+use std::{fmt, pin::pin, sync::Arc, time::Duration};
+use tokio::{sync::watch, time::Instant};
+
 pub use tokio_graceful::{Shutdown, ShutdownGuard, WeakShutdownGuard};
+
+/// A shutdown token for a subtree of services,
+/// which can be signalled independently of the global [`Shutdown`].
+///
+/// This allows to shut down a single mounted service (e.g. an admin API)
+/// while the rest of the application keeps serving. Services observe the token
+/// using the [`GracefulLayer`], which rejects new requests with a
+/// [`ServiceShutdownError`] once the token is signalled, while in-flight requests
+/// are allowed to finish.
+///
+/// Cloning the token returns a handle to the same token.
+///
+/// [`GracefulLayer`]: crate::service::layer::GracefulLayer
+#[derive(Clone)]
+pub struct ServiceShutdown {
+    signalled: Arc<watch::Sender<bool>>,
+    in_flight: InFlightHandle,
+}
+
+impl ServiceShutdown {
+    /// Create a new [`ServiceShutdown`] token, which is not yet signalled.
+    pub fn new() -> Self {
+        Self {
+            signalled: Arc::new(watch::Sender::new(false)),
+            in_flight: InFlightHandle::new(),
+        }
+    }
+
+    /// Signal the shutdown, such that the services observing this token
+    /// stop accepting new requests.
+    ///
+    /// Use [`ServiceShutdown::shutdown`] to also wait for the in-flight requests to finish.
+    pub fn signal(&self) {
+        self.signalled.send_replace(true);
+    }
+
+    /// Signal the shutdown and wait until all in-flight requests
+    /// of the services observing this token are finished.
+    pub async fn shutdown(&self) {
+        self.signal();
+        self.in_flight.idle().await;
+    }
+
+    /// Returns `true` if the shutdown was signalled.
+    pub fn is_signalled(&self) -> bool {
+        *self.signalled.borrow()
+    }
+
+    /// Wait until the shutdown is signalled.
+    pub async fn cancelled(&self) {
+        let mut signalled = self.signalled.subscribe();
+        // the sender is owned by `self`, so this cannot fail
+        let _ = signalled.wait_for(|signalled| *signalled).await;
+    }
+
+    /// Returns the number of in-flight requests of the services observing this token.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.count()
+    }
+
+    /// Track a new in-flight request, returning `None` in case the shutdown was signalled.
+    pub(crate) fn track(&self) -> Option<InFlightGuard> {
+        // count the request before checking the signal, such that a concurrent
+        // call to `shutdown` always waits for the requests it let through
+        let guard = self.in_flight.track();
+        (!self.is_signalled()).then_some(guard)
+    }
+}
+
+impl Default for ServiceShutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ServiceShutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceShutdown")
+            .field("signalled", &self.is_signalled())
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+/// The error returned by services observing a [`ServiceShutdown`] token
+/// for requests received after the shutdown was signalled.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ServiceShutdownError;
+
+impl ServiceShutdownError {
+    /// Create a new [`ServiceShutdownError`].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl fmt::Display for ServiceShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "service is shutting down")
+    }
+}
+
+impl std::error::Error for ServiceShutdownError {}
+
+/// Escalation of a graceful [`Shutdown`] using two deadlines,
+/// for connections which do not finish in time by themselves.
+///
+/// The deadlines are relative to the moment the shutdown is triggered:
+///
+/// 1. until the soft deadline the shutdown is graceful as usual;
+/// 2. once the soft deadline is reached, http/1 responses are sent with a
+///    `Connection: close` header, such that kept-alive connections are closed
+///    after their in-flight request;
+/// 3. once the hard deadline is reached, the remaining connections are aborted.
+///
+/// The escalation is observed by the connections of the listeners it is registered with,
+/// using [`TcpListener::with_shutdown_escalation`], and it is driven by
+/// [`ShutdownEscalation::shutdown`], to be used instead of [`Shutdown::shutdown_with_limit`].
+///
+/// Cloning the escalation returns a handle to the same escalation.
+///
+/// [`TcpListener::with_shutdown_escalation`]: crate::tcp::server::TcpListener::with_shutdown_escalation
+#[derive(Clone)]
+pub struct ShutdownEscalation {
+    phase: Arc<watch::Sender<EscalationPhase>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EscalationPhase {
+    Graceful,
+    SoftDeadline,
+    HardDeadline,
+}
+
+impl ShutdownEscalation {
+    /// Create a new [`ShutdownEscalation`], of which no deadline is reached yet.
+    pub fn new() -> Self {
+        Self {
+            phase: Arc::new(watch::Sender::new(EscalationPhase::Graceful)),
+        }
+    }
+
+    /// Wait for the given [`Shutdown`] to be triggered and for all its guards to be dropped,
+    /// escalating once the soft and hard deadline (both relative to the trigger) are reached.
+    ///
+    /// Returns the time it took for all guards to be dropped, or a [`ShutdownAbortedError`]
+    /// in case the hard deadline is reached, after which the remaining connections are aborted.
+    /// Tasks which do not observe this escalation are left running, as is the case
+    /// for [`Shutdown::shutdown_with_limit`].
+    ///
+    /// A soft deadline past the hard deadline is ignored.
+    pub async fn shutdown(
+        &self,
+        shutdown: Shutdown,
+        soft_deadline: Duration,
+        hard_deadline: Duration,
+    ) -> Result<Duration, ShutdownAbortedError> {
+        shutdown.guard_weak().into_cancelled().await;
+        let start = Instant::now();
+        let mut drained = pin!(shutdown.shutdown());
+
+        if soft_deadline < hard_deadline {
+            tokio::select! {
+                _ = drained.as_mut() => return Ok(start.elapsed()),
+                _ = tokio::time::sleep_until(start + soft_deadline) => (),
+            }
+            tracing::trace!(
+                "graceful shutdown: soft deadline reached after {}s: close connections",
+                soft_deadline.as_secs_f64()
+            );
+            self.escalate(EscalationPhase::SoftDeadline);
+        }
+
+        tokio::select! {
+            _ = drained.as_mut() => return Ok(start.elapsed()),
+            _ = tokio::time::sleep_until(start + hard_deadline) => (),
+        }
+        tracing::trace!(
+            "graceful shutdown: hard deadline reached after {}s: abort connections",
+            hard_deadline.as_secs_f64()
+        );
+        self.escalate(EscalationPhase::HardDeadline);
+        Err(ShutdownAbortedError(hard_deadline))
+    }
+
+    fn escalate(&self, phase: EscalationPhase) {
+        self.phase.send_if_modified(|current| {
+            let escalated = phase > *current;
+            if escalated {
+                *current = phase;
+            }
+            escalated
+        });
+    }
+
+    /// Returns `true` if the soft deadline is reached (or passed).
+    pub fn is_soft_deadline_reached(&self) -> bool {
+        *self.phase.borrow() >= EscalationPhase::SoftDeadline
+    }
+
+    /// Returns `true` if the hard deadline is reached.
+    pub fn is_hard_deadline_reached(&self) -> bool {
+        *self.phase.borrow() >= EscalationPhase::HardDeadline
+    }
+
+    /// Wait until the soft deadline is reached (or passed).
+    pub async fn soft_deadline(&self) {
+        let mut phase = self.phase.subscribe();
+        // the sender is owned by `self`, so this cannot fail
+        let _ = phase
+            .wait_for(|phase| *phase >= EscalationPhase::SoftDeadline)
+            .await;
+    }
+
+    /// Wait until the hard deadline is reached.
+    pub async fn hard_deadline(&self) {
+        let mut phase = self.phase.subscribe();
+        // the sender is owned by `self`, so this cannot fail
+        let _ = phase
+            .wait_for(|phase| *phase >= EscalationPhase::HardDeadline)
+            .await;
+    }
+}
+
+impl Default for ShutdownEscalation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ShutdownEscalation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownEscalation")
+            .field("phase", &*self.phase.borrow())
+            .finish()
+    }
+}
+
+/// The error returned by [`ShutdownEscalation::shutdown`]
+/// in case the hard deadline is reached.
+#[derive(Debug, Clone)]
+pub struct ShutdownAbortedError(Duration);
+
+impl ShutdownAbortedError {
+    /// The hard deadline after which the shutdown got aborted.
+    pub fn deadline(&self) -> Duration {
+        self.0
+    }
+}
+
+impl fmt::Display for ShutdownAbortedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "graceful shutdown aborted after {}s",
+            self.0.as_secs_f64()
+        )
+    }
+}
+
+impl std::error::Error for ShutdownAbortedError {}
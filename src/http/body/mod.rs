@@ -8,11 +8,15 @@ use bytes::Bytes;
 use futures_util::stream::Stream;
 use futures_util::TryStream;
 use pin_project_lite::pin_project;
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use sync_wrapper::SyncWrapper;
 
 use crate::error::{BoxError, Error};
+use crate::http::HeaderMap;
+
+pub mod multipart;
 
 type BoxBody = http_body_util::combinators::BoxBody<Bytes, Error>;
 
@@ -79,6 +83,36 @@ impl Body {
     pub fn into_data_stream(self) -> BodyDataStream {
         BodyDataStream { inner: self }
     }
+
+    /// Attach the given trailers to the body,
+    /// sent as a trailers frame once all data of the body has been sent.
+    ///
+    /// See [`Body::with_trailers_future`] in case the trailers are only
+    /// known once the body has been sent.
+    pub fn with_trailers(self, trailers: HeaderMap) -> Self {
+        self.with_trailers_future(std::future::ready(Some(trailers)))
+    }
+
+    /// Attach the trailers produced by the given future to the body.
+    ///
+    /// The future is only polled once all data of the body has been sent,
+    /// such that it can compute its trailers (e.g. a `grpc-status`) based on the
+    /// outcome of producing the body. Trailers of the body itself are merged
+    /// with those produced by the future, the latter overwriting the former.
+    ///
+    /// Note that the body will never report an exact size hint,
+    /// as HTTP/1.1 requires a chunked body in order to send trailers.
+    pub fn with_trailers_future<F>(self, trailers: F) -> Self
+    where
+        F: Future<Output = Option<HeaderMap>> + Send + 'static,
+    {
+        Self::new(TrailersBody {
+            body: self,
+            body_done: false,
+            body_trailers: None,
+            trailers: Some(SyncWrapper::new(Box::pin(trailers))),
+        })
+    }
 }
 
 impl Default for Body {
@@ -213,6 +247,73 @@ where
     }
 }
 
+type TrailersFuture = Pin<Box<dyn Future<Output = Option<HeaderMap>> + Send + 'static>>;
+
+pin_project! {
+    struct TrailersBody {
+        #[pin]
+        body: Body,
+        body_done: bool,
+        body_trailers: Option<HeaderMap>,
+        trailers: Option<SyncWrapper<TrailersFuture>>,
+    }
+}
+
+impl http_body::Body for TrailersBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        let trailers = match this.trailers.as_mut() {
+            Some(trailers) => trailers,
+            None => return Poll::Ready(None),
+        };
+
+        if !*this.body_done {
+            loop {
+                match futures_util::ready!(this.body.as_mut().poll_frame(cx)) {
+                    Some(Ok(frame)) => match frame.into_trailers() {
+                        Ok(body_trailers) => match this.body_trailers {
+                            Some(existing) => existing.extend(body_trailers),
+                            None => *this.body_trailers = Some(body_trailers),
+                        },
+                        Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                    },
+                    Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    None => break,
+                }
+            }
+            *this.body_done = true;
+        }
+
+        let trailers = futures_util::ready!(trailers.get_mut().as_mut().poll(cx));
+        *this.trailers = None;
+        let trailers = match (this.body_trailers.take(), trailers) {
+            (Some(mut body_trailers), Some(trailers)) => {
+                body_trailers.extend(trailers);
+                body_trailers
+            }
+            (Some(trailers), None) | (None, Some(trailers)) => trailers,
+            (None, None) => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let mut hint = http_body::SizeHint::new();
+        hint.set_lower(self.body.size_hint().lower());
+        hint
+    }
+}
+
 #[test]
 fn test_try_downcast() {
     assert_eq!(try_downcast::<i32, _>(5_u32), Err(5_u32));
@@ -0,0 +1,692 @@
+//! Streaming parser for `multipart/form-data` bodies, as defined in RFC 7578.
+//!
+//! The [`Multipart`] parser yields the parts of the body one after another,
+//! each [`Part`] exposing its name, filename and content type, as well as its body
+//! as a stream of chunks. Part bodies are never buffered entirely in memory
+//! (unless explicitly requested using [`Part::bytes`] or [`Part::text`]),
+//! such that large file uploads can be processed by streaming them.
+//!
+//! Size limits can be configured per part and for the body as a whole,
+//! see [`Multipart::max_part_size`] and [`Multipart::max_total_size`].
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::{Body, Request};
+//! use rama::http::multipart::Multipart;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let body = "--X\r\n\
+//!     Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+//!     hello\r\n\
+//!     --X--\r\n";
+//! let request = Request::builder()
+//!     .header("content-type", "multipart/form-data; boundary=X")
+//!     .body(Body::from(body))?;
+//!
+//! let mut multipart = Multipart::from_request(request)?.max_part_size(1024);
+//! while let Some(mut part) = multipart.next_part().await? {
+//!     assert_eq!(part.name(), Some("title"));
+//!     while let Some(chunk) = part.chunk().await? {
+//!         assert_eq!(&chunk[..], b"hello");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{
+    dep::mime::{self, Mime},
+    header, Body, BodyDataStream, HeaderMap, HeaderName, HeaderValue, Request,
+};
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use std::fmt;
+
+/// The maximum size of the headers of a single part.
+const MAX_HEADERS_SIZE: usize = 8 * 1024;
+
+/// The maximum number of headers of a single part.
+const MAX_HEADERS_COUNT: usize = 32;
+
+/// The maximum length of a boundary, as defined in RFC 2046.
+const MAX_BOUNDARY_LEN: usize = 70;
+
+#[derive(Debug)]
+/// Error type for the [`Multipart`] parser.
+pub enum MultipartError {
+    /// The request is not a `multipart/form-data` request,
+    /// or its `Content-Type` header does not define a boundary.
+    MissingBoundary,
+    /// The boundary is invalid, or a delimiter in the body is malformed.
+    InvalidBoundary,
+    /// The headers of a part are malformed.
+    InvalidHeaders,
+    /// The headers of a part exceed the maximum size.
+    HeadersTooLarge,
+    /// The body ended before the closing delimiter was found.
+    IncompleteStream,
+    /// The body of a part exceeds the configured limit (in bytes).
+    PartTooLarge(u64),
+    /// The body exceeds the configured limit (in bytes).
+    TotalTooLarge(u64),
+    /// The body of a part is not valid UTF-8, as required for [`Part::text`].
+    InvalidUtf8(std::string::FromUtf8Error),
+    /// An error occurred while reading the body.
+    BodyError(crate::error::Error),
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultipartError::MissingBoundary => write!(f, "multipart: missing boundary"),
+            MultipartError::InvalidBoundary => write!(f, "multipart: invalid boundary"),
+            MultipartError::InvalidHeaders => write!(f, "multipart: invalid part headers"),
+            MultipartError::HeadersTooLarge => write!(f, "multipart: part headers too large"),
+            MultipartError::IncompleteStream => write!(f, "multipart: incomplete stream"),
+            MultipartError::PartTooLarge(limit) => {
+                write!(f, "multipart: part exceeds the limit of {} bytes", limit)
+            }
+            MultipartError::TotalTooLarge(limit) => {
+                write!(f, "multipart: body exceeds the limit of {} bytes", limit)
+            }
+            MultipartError::InvalidUtf8(err) => write!(f, "multipart: invalid utf-8: {}", err),
+            MultipartError::BodyError(err) => write!(f, "multipart: body error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MultipartError::InvalidUtf8(err) => Some(err),
+            MultipartError::BodyError(err) => Some(err.as_ref() as &dyn std::error::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Searching the first delimiter.
+    Preamble,
+    /// Right after a delimiter, expecting either a line break or the closing `--`.
+    Delimiter,
+    /// Reading the headers of a part.
+    Headers,
+    /// Reading the body of a part, with the number of bytes read so far.
+    Body(u64),
+    /// The closing delimiter was found.
+    Done,
+}
+
+/// Streaming parser for a `multipart/form-data` body.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug)]
+pub struct Multipart {
+    stream: BodyDataStream,
+    eof: bool,
+    buffer: BytesMut,
+    delimiter: Vec<u8>,
+    state: State,
+    max_part_size: Option<u64>,
+    max_total_size: Option<u64>,
+    total_size: u64,
+}
+
+impl Multipart {
+    /// Create a new [`Multipart`] parser for the given body,
+    /// with its parts separated by the given boundary.
+    pub fn new(body: Body, boundary: impl AsRef<str>) -> Result<Self, MultipartError> {
+        let boundary = boundary.as_ref();
+        if boundary.is_empty()
+            || boundary.len() > MAX_BOUNDARY_LEN
+            || boundary.bytes().any(|b| b == b'\r' || b == b'\n')
+        {
+            return Err(MultipartError::InvalidBoundary);
+        }
+
+        let mut delimiter = Vec::with_capacity(boundary.len() + 4);
+        delimiter.extend_from_slice(b"\r\n--");
+        delimiter.extend_from_slice(boundary.as_bytes());
+
+        // the first delimiter is not required to be preceded by a line break
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(b"\r\n");
+
+        Ok(Self {
+            stream: body.into_data_stream(),
+            eof: false,
+            buffer,
+            delimiter,
+            state: State::Preamble,
+            max_part_size: None,
+            max_total_size: None,
+            total_size: 0,
+        })
+    }
+
+    /// Create a new [`Multipart`] parser for the body of the given request,
+    /// using the boundary defined in its `multipart/form-data` `Content-Type` header.
+    pub fn from_request(req: Request) -> Result<Self, MultipartError> {
+        let boundary = boundary(req.headers())?;
+        Self::new(req.into_body(), boundary)
+    }
+
+    /// Limit the size (in bytes) of the body of each part.
+    ///
+    /// No limit is applied by default.
+    pub fn max_part_size(mut self, limit: u64) -> Self {
+        self.max_part_size = Some(limit);
+        self
+    }
+
+    /// Limit the size (in bytes) of the entire multipart body.
+    ///
+    /// No limit is applied by default.
+    pub fn max_total_size(mut self, limit: u64) -> Self {
+        self.max_total_size = Some(limit);
+        self
+    }
+
+    /// Returns the next part of the body, or `None` in case all parts have been read.
+    ///
+    /// The part which was returned previously is skipped in case it was not read entirely.
+    pub async fn next_part(&mut self) -> Result<Option<Part<'_>>, MultipartError> {
+        loop {
+            match self.state {
+                State::Preamble => match find(&self.buffer, &self.delimiter) {
+                    Some(index) => {
+                        let _ = self.buffer.split_to(index + self.delimiter.len());
+                        self.state = State::Delimiter;
+                    }
+                    None => {
+                        let keep = self.delimiter.len() - 1;
+                        if self.buffer.len() > keep {
+                            let _ = self.buffer.split_to(self.buffer.len() - keep);
+                        }
+                        self.fill().await?;
+                    }
+                },
+                State::Delimiter => {
+                    // skip the optional transport padding
+                    let padding = self
+                        .buffer
+                        .iter()
+                        .take_while(|b| **b == b' ' || **b == b'\t')
+                        .count();
+                    if self.buffer.len() < padding + 2 {
+                        self.fill().await?;
+                        continue;
+                    }
+                    match &self.buffer[padding..padding + 2] {
+                        b"--" if padding == 0 => {
+                            self.state = State::Done;
+                            return Ok(None);
+                        }
+                        b"\r\n" => {
+                            let _ = self.buffer.split_to(padding + 2);
+                            self.state = State::Headers;
+                        }
+                        _ => return Err(MultipartError::InvalidBoundary),
+                    }
+                }
+                State::Headers => {
+                    let len = if self.buffer.starts_with(b"\r\n") {
+                        2
+                    } else {
+                        match find(&self.buffer, b"\r\n\r\n") {
+                            Some(index) => index + 4,
+                            None => {
+                                if self.buffer.len() > MAX_HEADERS_SIZE {
+                                    return Err(MultipartError::HeadersTooLarge);
+                                }
+                                self.fill().await?;
+                                continue;
+                            }
+                        }
+                    };
+                    if len > MAX_HEADERS_SIZE {
+                        return Err(MultipartError::HeadersTooLarge);
+                    }
+                    let raw = self.buffer.split_to(len);
+                    let headers = parse_headers(&raw)?;
+                    self.state = State::Body(0);
+                    return Ok(Some(Part::new(self, headers)));
+                }
+                State::Body(_) => while self.read_chunk().await?.is_some() {},
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Read the next chunk of the body of the current part,
+    /// returning `None` once the part has been read entirely.
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        loop {
+            let size = match self.state {
+                State::Body(size) => size,
+                _ => return Ok(None),
+            };
+
+            let available = match find(&self.buffer, &self.delimiter) {
+                Some(0) => {
+                    let _ = self.buffer.split_to(self.delimiter.len());
+                    self.state = State::Delimiter;
+                    return Ok(None);
+                }
+                Some(index) => index,
+                // keep the tail of the buffer, as it might be the start of the delimiter
+                None => self.buffer.len().saturating_sub(self.delimiter.len() - 1),
+            };
+            if available == 0 {
+                self.fill().await?;
+                continue;
+            }
+
+            let size = size + available as u64;
+            if let Some(limit) = self.max_part_size {
+                if size > limit {
+                    return Err(MultipartError::PartTooLarge(limit));
+                }
+            }
+            self.state = State::Body(size);
+            return Ok(Some(self.buffer.split_to(available).freeze()));
+        }
+    }
+
+    /// Read the next chunk of the underlying body into the buffer.
+    async fn fill(&mut self) -> Result<(), MultipartError> {
+        if self.eof {
+            return Err(MultipartError::IncompleteStream);
+        }
+        match self.stream.next().await {
+            Some(Ok(chunk)) => {
+                self.total_size += chunk.len() as u64;
+                if let Some(limit) = self.max_total_size {
+                    if self.total_size > limit {
+                        return Err(MultipartError::TotalTooLarge(limit));
+                    }
+                }
+                self.buffer.extend_from_slice(&chunk);
+                Ok(())
+            }
+            Some(Err(err)) => Err(MultipartError::BodyError(err)),
+            None => {
+                self.eof = true;
+                Err(MultipartError::IncompleteStream)
+            }
+        }
+    }
+}
+
+/// A single part of a `multipart/form-data` body, returned by [`Multipart::next_part`].
+#[derive(Debug)]
+pub struct Part<'a> {
+    multipart: &'a mut Multipart,
+    headers: HeaderMap,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+}
+
+impl<'a> Part<'a> {
+    fn new(multipart: &'a mut Multipart, headers: HeaderMap) -> Self {
+        let (name, filename) = headers
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .map(parse_content_disposition)
+            .unwrap_or_default();
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        Self {
+            multipart,
+            headers,
+            name,
+            filename,
+            content_type,
+        }
+    }
+
+    /// The name of the form field of this part.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The filename of this part, in case it is a file upload.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// The content type of this part.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// The headers of this part.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the next chunk of the body of this part,
+    /// or `None` in case the body has been read entirely.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        self.multipart.read_chunk().await
+    }
+
+    /// Read the remaining body of this part into memory.
+    pub async fn bytes(mut self) -> Result<Bytes, MultipartError> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Read the remaining body of this part into memory, as UTF-8 text.
+    pub async fn text(self) -> Result<String, MultipartError> {
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.to_vec()).map_err(MultipartError::InvalidUtf8)
+    }
+}
+
+/// Get the boundary defined in the `multipart/form-data` `Content-Type` header.
+fn boundary(headers: &HeaderMap) -> Result<String, MultipartError> {
+    let content_type: Mime = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or(MultipartError::MissingBoundary)?;
+    if content_type.type_() != mime::MULTIPART || content_type.subtype() != mime::FORM_DATA {
+        return Err(MultipartError::MissingBoundary);
+    }
+    content_type
+        .get_param(mime::BOUNDARY)
+        .map(|boundary| boundary.as_str().to_owned())
+        .ok_or(MultipartError::MissingBoundary)
+}
+
+/// Find the first position of the needle in the haystack.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parse the raw headers of a part, including the empty line terminating them.
+fn parse_headers(raw: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let mut headers = HeaderMap::new();
+    if raw == b"\r\n" {
+        return Ok(headers);
+    }
+
+    let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS_COUNT];
+    match httparse::parse_headers(raw, &mut parsed) {
+        Ok(httparse::Status::Complete((_, parsed))) => {
+            for h in parsed {
+                let name = HeaderName::from_bytes(h.name.as_bytes())
+                    .map_err(|_| MultipartError::InvalidHeaders)?;
+                let value =
+                    HeaderValue::from_bytes(h.value).map_err(|_| MultipartError::InvalidHeaders)?;
+                headers.append(name, value);
+            }
+            Ok(headers)
+        }
+        Ok(httparse::Status::Partial) => Err(MultipartError::InvalidHeaders),
+        Err(httparse::Error::TooManyHeaders) => Err(MultipartError::HeadersTooLarge),
+        Err(_) => Err(MultipartError::InvalidHeaders),
+    }
+}
+
+/// Parse the `name` and `filename` parameters of a `Content-Disposition` header value,
+/// preferring the (RFC 5987 encoded) `filename*` parameter if present.
+fn parse_content_disposition(value: &str) -> (Option<String>, Option<String>) {
+    let mut name = None;
+    let mut filename = None;
+    let mut filename_ext = None;
+
+    let mut rest = match value.split_once(';') {
+        Some((_, params)) => params,
+        None => return (None, None),
+    };
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        if rest.is_empty() {
+            break;
+        }
+        let (key, after) = match rest.split_once('=') {
+            Some((key, after)) => (key.trim().to_ascii_lowercase(), after.trim_start()),
+            None => break,
+        };
+
+        let value;
+        (value, rest) = if let Some(quoted) = after.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = index + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            (value, &quoted[end..])
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+            (after[..end].trim().to_owned(), &after[end..])
+        };
+
+        match key.as_str() {
+            "name" => name = Some(value),
+            "filename" => filename = Some(value),
+            "filename*" => filename_ext = decode_ext_value(&value),
+            _ => (),
+        }
+    }
+
+    (name, filename_ext.or(filename))
+}
+
+/// Decode an RFC 5987 extended value, only supporting the `UTF-8` charset.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    if !charset.eq_ignore_ascii_case("utf-8") {
+        return None;
+    }
+    percent_encoding::percent_decode_str(encoded)
+        .decode_utf8()
+        .ok()
+        .map(|value| value.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "preamble\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        hello world\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\n--boundarx\r\nline two\r\n\
+        --boundary--\r\n\
+        epilogue";
+
+    fn chunked_body(body: &str, chunk_size: usize) -> Body {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = body
+            .as_bytes()
+            .chunks(chunk_size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        Body::from_stream(futures_util::stream::iter(chunks))
+    }
+
+    #[tokio::test]
+    async fn test_multipart_across_chunk_boundaries() {
+        for chunk_size in [1, 2, 3, 7, 16, BODY.len()] {
+            let mut multipart = Multipart::new(chunked_body(BODY, chunk_size), "boundary").unwrap();
+
+            let part = multipart.next_part().await.unwrap().unwrap();
+            assert_eq!(part.name(), Some("title"));
+            assert_eq!(part.filename(), None);
+            assert_eq!(part.content_type(), None);
+            assert_eq!(part.text().await.unwrap(), "hello world");
+
+            let mut part = multipart.next_part().await.unwrap().unwrap();
+            assert_eq!(part.name(), Some("file"));
+            assert_eq!(part.filename(), Some("a \"b\".txt"));
+            assert_eq!(part.content_type(), Some(&mime::TEXT_PLAIN));
+            let mut body = Vec::new();
+            while let Some(chunk) = part.chunk().await.unwrap() {
+                body.extend_from_slice(&chunk);
+            }
+            assert_eq!(body, b"line one\r\n--boundarx\r\nline two");
+
+            assert!(multipart.next_part().await.unwrap().is_none());
+            assert!(multipart.next_part().await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_skips_unread_parts() {
+        let mut multipart = Multipart::new(chunked_body(BODY, 5), "boundary").unwrap();
+
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("title"));
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("file"));
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_from_request() {
+        let request = Request::builder()
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=\"boundary\"",
+            )
+            .body(chunked_body(BODY, 4))
+            .unwrap();
+        let mut multipart = Multipart::from_request(request).unwrap();
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.bytes().await.unwrap(), "hello world");
+
+        for content_type in [None, Some("multipart/form-data"), Some("text/plain")] {
+            let mut builder = Request::builder();
+            if let Some(content_type) = content_type {
+                builder = builder.header(header::CONTENT_TYPE, content_type);
+            }
+            let request = builder.body(Body::empty()).unwrap();
+            assert!(matches!(
+                Multipart::from_request(request),
+                Err(MultipartError::MissingBoundary)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_limits() {
+        let mut multipart = Multipart::new(chunked_body(BODY, 3), "boundary")
+            .unwrap()
+            .max_part_size(16);
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.text().await.unwrap(), "hello world");
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert!(matches!(
+            part.bytes().await,
+            Err(MultipartError::PartTooLarge(16))
+        ));
+
+        let mut multipart = Multipart::new(chunked_body(BODY, 3), "boundary")
+            .unwrap()
+            .max_total_size(100);
+        assert!(multipart.next_part().await.unwrap().is_some());
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(MultipartError::TotalTooLarge(100))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_multipart_malformed() {
+        // missing closing delimiter
+        let body = "--boundary\r\n\r\nvalue";
+        let mut multipart = Multipart::new(chunked_body(body, 4), "boundary").unwrap();
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert!(matches!(
+            part.bytes().await,
+            Err(MultipartError::IncompleteStream)
+        ));
+
+        // no delimiter at all
+        let mut multipart = Multipart::new(chunked_body("value", 4), "boundary").unwrap();
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(MultipartError::IncompleteStream)
+        ));
+
+        // garbage after a delimiter
+        let body = "--boundary\r\n\r\nvalue\r\n--boundaryX\r\n";
+        let mut multipart = Multipart::new(chunked_body(body, 4), "boundary").unwrap();
+        assert!(multipart.next_part().await.unwrap().is_some());
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(MultipartError::InvalidBoundary)
+        ));
+
+        // malformed part headers
+        let body = "--boundary\r\nno colon\r\n\r\nvalue\r\n--boundary--";
+        let mut multipart = Multipart::new(chunked_body(body, 4), "boundary").unwrap();
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(MultipartError::InvalidHeaders)
+        ));
+
+        // invalid boundaries
+        for boundary in ["", "a\r\nb", &"x".repeat(71)] {
+            assert!(matches!(
+                Multipart::new(Body::empty(), boundary),
+                Err(MultipartError::InvalidBoundary)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_content_disposition() {
+        for (value, expected_name, expected_filename) in [
+            ("form-data", None, None),
+            ("form-data; name=field", Some("field"), None),
+            (
+                "form-data; name=\"field\"; filename=\"a;b.txt\"",
+                Some("field"),
+                Some("a;b.txt"),
+            ),
+            (
+                "form-data; filename=\"fallback.txt\"; filename*=UTF-8''%E2%82%AC.txt; name=f",
+                Some("f"),
+                Some("€.txt"),
+            ),
+        ] {
+            let (name, filename) = parse_content_disposition(value);
+            assert_eq!(name.as_deref(), expected_name, "{}", value);
+            assert_eq!(filename.as_deref(), expected_filename, "{}", value);
+        }
+    }
+}
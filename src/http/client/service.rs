@@ -1,13 +1,16 @@
 use crate::{
     error::Error,
     http::{
+        header,
         layer::dns::DnsResolvedSocketAddresses,
         service::web::extract::{FromRequestParts, Host},
-        Request, Response, Version,
+        HeaderValue, Request, Response, Uri, Version,
     },
+    net::connect::{ConnectError, ConnectTarget, TcpConnector},
     service::{Context, Service},
 };
 use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 #[derive(Debug, Clone)]
 #[non_exhaustive]
@@ -25,12 +28,26 @@ use hyper_util::rt::TokioIo;
 ///
 /// <https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html>
 /// might serve for some inspiration for some of the above features.
-pub struct HttpClient;
+///
+/// The connection to the target is established using a connector,
+/// which is a [`TcpConnector`] by default. See [`crate::net::connect`]
+/// for other connectors, e.g. to connect through an upstream proxy.
+pub struct HttpClient<C = TcpConnector> {
+    connector: C,
+}
 
 impl HttpClient {
     /// Create a new [`HttpClient`].
     pub fn new() -> Self {
-        HttpClient
+        Self::with_connector(TcpConnector::new())
+    }
+}
+
+impl<C> HttpClient<C> {
+    /// Create a new [`HttpClient`] which establishes its connections
+    /// using the given connector.
+    pub fn with_connector(connector: C) -> Self {
+        Self { connector }
     }
 }
 
@@ -60,6 +77,8 @@ pub enum HttpClientError {
     ///
     /// (e.g. during a handshake process)
     IoError(std::io::Error),
+    /// The connection to the target could not be established.
+    ConnectError(ConnectError),
     /// An HTTP error occurred during the http handshake or transfer process.
     HttpError(Error),
 }
@@ -70,6 +89,12 @@ impl From<std::io::Error> for HttpClientError {
     }
 }
 
+impl From<ConnectError> for HttpClientError {
+    fn from(err: ConnectError) -> Self {
+        HttpClientError::ConnectError(err)
+    }
+}
+
 impl From<hyper::Error> for HttpClientError {
     fn from(err: hyper::Error) -> Self {
         HttpClientError::HttpError(err.into())
@@ -91,6 +116,9 @@ impl std::fmt::Display for HttpClientError {
             HttpClientError::IoError(err) => {
                 write!(f, "IO error: {}", err)
             }
+            HttpClientError::ConnectError(err) => {
+                write!(f, "Connect error: {}", err)
+            }
             HttpClientError::HttpError(err) => {
                 write!(f, "HTTP error: {}", err)
             }
@@ -105,14 +133,17 @@ impl std::error::Error for HttpClientError {
             HttpClientError::MissingHost => None,
             HttpClientError::InvalidHost(_) => None,
             HttpClientError::IoError(err) => Some(err),
+            HttpClientError::ConnectError(err) => Some(err),
             HttpClientError::HttpError(err) => Some(err.as_ref()),
         }
     }
 }
 
-impl<State, Body> Service<State, Request<Body>> for HttpClient
+impl<State, Body, C> Service<State, Request<Body>> for HttpClient<C>
 where
     State: Send + Sync + 'static,
+    C: Service<State, ConnectTarget, Error = ConnectError>,
+    C::Response: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     Body: http_body::Body + Unpin + Send + 'static,
     Body::Data: Send + 'static,
     Body::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
@@ -128,11 +159,28 @@ where
         // TODO: should this service be able to support persistent connection?
         // TODO: should this service be able to support connection pooling?
 
-        let (parts, body) = req.into_parts();
+        let (mut parts, body) = req.into_parts();
+
+        let port = parts.uri.port().map(|p| p.as_u16()).unwrap_or_else(|| {
+            parts
+                .uri
+                .scheme()
+                .map(|s| match s.as_str() {
+                    // TODO is this scheme mapping complete enough?
+                    // and should we fail on unknown schemes?
+                    // should this be a shared utility somewhere?
+                    "http" => 80,
+                    _ => 443,
+                })
+                .unwrap_or(443)
+        });
 
         // get target address
         let address = if let Some(dns_info) = ctx.get::<DnsResolvedSocketAddresses>() {
             dns_info.address().to_string()
+        } else if let Some(host) = parts.uri.host() {
+            // an absolute uri defines the target, regardless of the (forwarded) host headers
+            format!("{}:{}", host, port)
         } else {
             let host = match Host::from_request_parts(&ctx, &parts).await {
                 Ok(host) => host.0,
@@ -141,40 +189,31 @@ where
             if host.contains(':') {
                 host
             } else {
-                let port = parts.uri.port().map(|p| p.as_u16()).unwrap_or_else(|| {
-                    parts
-                        .uri
-                        .scheme()
-                        .map(|s| match s.as_str() {
-                            // TODO is this scheme mapping complete enough?
-                            // and should we fail on unknown schemes?
-                            // should this be a shared utility somewhere?
-                            "http" => 80,
-                            _ => 443,
-                        })
-                        .unwrap_or(443)
-                });
                 format!("{}:{}", host, port)
             }
         };
 
-        // TODO: should this client support upstream proxies?
-
-        // create the tcp connection
-        tokio::net::TcpStream::connect(&address).await?;
+        let target: ConnectTarget = address
+            .parse()
+            .map_err(|_| HttpClientError::InvalidHost(address))?;
 
-        let tcp_stream = tokio::net::TcpStream::connect(address).await?;
+        // create the connection, using the configured connector
+        let stream = self.connector.serve(ctx.clone(), target).await?;
 
         // TODO: figure out how we wish to handle https here
 
-        let tcp_stream = TokioIo::new(Box::pin(tcp_stream));
+        let stream = TokioIo::new(Box::pin(stream));
+
+        if parts.version != Version::HTTP_2 {
+            into_origin_form(&mut parts);
+        }
 
         let req = Request::from_parts(parts, body);
         let resp = match req.version() {
             Version::HTTP_2 => {
                 let executor = ctx.executor().clone();
                 let (mut sender, conn) =
-                    hyper::client::conn::http2::handshake(executor, tcp_stream).await?;
+                    hyper::client::conn::http2::handshake(executor, stream).await?;
 
                 ctx.spawn(async move {
                     if let Err(err) = conn.await {
@@ -186,7 +225,7 @@ where
                 sender.send_request(req).await?
             }
             Version::HTTP_11 | Version::HTTP_10 | Version::HTTP_09 => {
-                let (mut sender, conn) = hyper::client::conn::http1::handshake(tcp_stream).await?;
+                let (mut sender, conn) = hyper::client::conn::http1::handshake(stream).await?;
 
                 ctx.spawn(async move {
                     if let Err(err) = conn.await {
@@ -204,3 +243,26 @@ where
         Ok(resp)
     }
 }
+
+/// Convert an absolute-form request target into the origin-form used for HTTP/1 requests
+/// sent directly to the target, setting the `Host` header to its authority if missing.
+fn into_origin_form(parts: &mut crate::http::dep::http::request::Parts) {
+    let authority = match parts.uri.authority() {
+        Some(authority) => authority.clone(),
+        None => return,
+    };
+    if !parts.headers.contains_key(header::HOST) {
+        if let Ok(host) = HeaderValue::from_str(authority.as_str()) {
+            parts.headers.insert(header::HOST, host);
+        }
+    }
+    let mut uri = Uri::builder();
+    if let Some(path_and_query) = parts.uri.path_and_query() {
+        uri = uri.path_and_query(path_and_query.clone());
+    } else {
+        uri = uri.path_and_query("/");
+    }
+    if let Ok(uri) = uri.build() {
+        parts.uri = uri;
+    }
+}
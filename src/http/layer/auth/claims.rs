@@ -0,0 +1,53 @@
+//! The claims of an authenticated request, e.g. as decoded from a bearer JWT.
+//!
+//! Authorization middleware (such as an [`AsyncAuthorizeRequest`] implementation)
+//! can insert the [`Claims`] it decoded into the [`Context`], such that they can be used
+//! by the services down the stack, for example to gate routes using the [`ClaimFilter`].
+//!
+//! [`AsyncAuthorizeRequest`]: crate::http::layer::auth::AsyncAuthorizeRequest
+//! [`Context`]: crate::service::Context
+//! [`ClaimFilter`]: crate::http::matcher::ClaimFilter
+
+use serde_json::{Map, Value};
+
+/// The (decoded) claims of an authenticated request,
+/// stored in the [`Context`] by the authorization middleware.
+///
+/// See the [module docs](self) for more details.
+///
+/// [`Context`]: crate::service::Context
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Claims(Map<String, Value>);
+
+impl Claims {
+    /// Create new [`Claims`] from the given claim set.
+    pub fn new(claims: Map<String, Value>) -> Self {
+        Self(claims)
+    }
+
+    /// Get the value of the claim with the given name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// Iterate over all claims.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.0.iter()
+    }
+}
+
+impl From<Map<String, Value>> for Claims {
+    fn from(claims: Map<String, Value>) -> Self {
+        Self::new(claims)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Claims {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        )
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod add_authorization;
 pub mod async_require_authorization;
+pub mod claims;
 pub mod require_authorization;
 
 #[doc(inline)]
@@ -10,4 +11,5 @@ pub use self::{
     async_require_authorization::{
         AsyncAuthorizeRequest, AsyncRequireAuthorization, AsyncRequireAuthorizationLayer,
     },
+    claims::Claims,
 };
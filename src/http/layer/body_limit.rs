@@ -0,0 +1,133 @@
+//! Middleware to configure the maximum size of the request bodies
+//! buffered by the body extractors of the [`WebService`].
+//!
+//! The [`Bytes`], [`Text`] and [`Json`] extractors buffer the entire request body,
+//! which is limited to the [`BodyLimit`] found in the [`Context`], if any.
+//! Requests with a body exceeding the limit are rejected with a `413 Payload Too Large`
+//! response, without buffering more than the limit.
+//!
+//! Without a [`BodyLimit`] the request bodies are buffered regardless of their size,
+//! it is therefore recommended to use this middleware for services exposed to untrusted clients.
+//!
+//! [`WebService`]: crate::http::service::web::WebService
+//! [`Bytes`]: crate::http::service::web::extract::Bytes
+//! [`Text`]: crate::http::service::web::extract::Text
+//! [`Json`]: crate::http::service::web::extract::Json
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::body_limit::BodyLimitLayer;
+//! use rama::http::service::web::{extract::Bytes, WebService};
+//! use rama::http::{Body, Request, StatusCode};
+//! use rama::service::{Context, Layer, Service};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = BodyLimitLayer::new(4).layer(
+//!     WebService::default().post("/", |Bytes(body): Bytes| async move { body.len().to_string() }),
+//! );
+//!
+//! let request = Request::post("/").body(Body::from("rama")).unwrap();
+//! let response = service.serve(Context::default(), request).await.unwrap();
+//! assert_eq!(response.status(), StatusCode::OK);
+//!
+//! let request = Request::post("/").body(Body::from("too large")).unwrap();
+//! let response = service.serve(Context::default(), request).await.unwrap();
+//! assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+//! # }
+//! ```
+
+use crate::http::Request;
+use crate::service::{Context, Layer, Service};
+
+/// The maximum size (in bytes) of the request bodies buffered by the body extractors,
+/// stored in the [`Context`] by the [`BodyLimitService`].
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimit(usize);
+
+impl BodyLimit {
+    /// Create a new [`BodyLimit`] of the given amount of bytes.
+    pub const fn new(limit: usize) -> Self {
+        Self(limit)
+    }
+
+    /// Get the limit as an amount of bytes.
+    pub const fn get(&self) -> usize {
+        self.0
+    }
+
+    /// Get the [`BodyLimit`] from the given [`Context`], if any.
+    pub fn from_context<State>(ctx: &Context<State>) -> Option<Self> {
+        ctx.get::<Self>().copied()
+    }
+}
+
+/// Layer that applies [`BodyLimitService`], configuring the maximum size
+/// of the request bodies buffered by the body extractors.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitLayer {
+    limit: BodyLimit,
+}
+
+impl BodyLimitLayer {
+    /// Create a new [`BodyLimitLayer`], limiting the buffered request bodies to the given amount of bytes.
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit: BodyLimit::new(limit),
+        }
+    }
+}
+
+impl<S> Layer<S> for BodyLimitLayer {
+    type Service = BodyLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyLimitService {
+            inner,
+            limit: self.limit,
+        }
+    }
+}
+
+/// Middleware storing a [`BodyLimit`] in the [`Context`].
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitService<S> {
+    inner: S,
+    limit: BodyLimit,
+}
+
+impl<S> BodyLimitService<S> {
+    /// Create a new [`BodyLimitService`], limiting the buffered request bodies to the given amount of bytes.
+    pub const fn new(inner: S, limit: usize) -> Self {
+        Self {
+            inner,
+            limit: BodyLimit::new(limit),
+        }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, S, ReqBody> Service<State, Request<ReqBody>> for BodyLimitService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>>,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        ctx.insert(self.limit);
+        self.inner.serve(ctx, req).await
+    }
+}
@@ -0,0 +1,335 @@
+//! Middleware that buffers a bounded prefix of the request body,
+//! such that routing decisions can be made based on the body content
+//! (e.g. the method of a JSON-RPC request).
+//!
+//! The buffered prefix is added to the [`Context`] as a [`BodyPrefix`],
+//! which can be matched on using the [`BodyPrefixFilter`].
+//! The request body passed to the inner service still contains the full body,
+//! starting with the buffered bytes, so nothing is lost.
+//!
+//! At most the configured number of bytes is buffered,
+//! the remainder of the body is streamed as is.
+//!
+//! [`Context`]: crate::service::Context
+//! [`BodyPrefixFilter`]: crate::http::matcher::BodyPrefixFilter
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{Body, Request, Response};
+//! use rama::http::layer::body_prefix::{BodyPrefix, BodyPrefixLayer};
+//!
+//! async fn handle(ctx: Context<()>, _req: Request) -> Result<Response, Infallible> {
+//!     let prefix = ctx.get::<BodyPrefix>().unwrap();
+//!     Ok(Response::new(Body::from(prefix.bytes().clone())))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Infallible> {
+//! let svc = ServiceBuilder::new()
+//!     .layer(BodyPrefixLayer::new(5))
+//!     .service_fn(handle);
+//!
+//! let response = svc.serve(Context::default(), Request::new(Body::from("hello, world"))).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{BoxError, Error};
+use crate::http::dep::http_body::{self, Frame};
+use crate::http::dep::http_body_util::BodyExt;
+use crate::http::{Body, Request};
+use crate::service::{Context, Layer, Service};
+use bytes::{Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::Poll;
+
+/// The buffered prefix of a request body,
+/// added to the [`Context`] by the [`BodyPrefixService`].
+///
+/// [`Context`]: crate::service::Context
+#[derive(Debug, Clone)]
+pub struct BodyPrefix {
+    bytes: Bytes,
+    complete: bool,
+}
+
+impl BodyPrefix {
+    /// The buffered bytes, at most the configured maximum size.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// Returns `true` if the buffered bytes are the full body.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Layer that applies the [`BodyPrefixService`] middleware.
+///
+/// See the [module docs](crate::http::layer::body_prefix) for more details.
+#[derive(Debug, Clone)]
+pub struct BodyPrefixLayer {
+    max_size: usize,
+}
+
+impl BodyPrefixLayer {
+    /// Create a new [`BodyPrefixLayer`], buffering at most `max_size` bytes of the request body.
+    pub fn new(max_size: usize) -> Self {
+        Self { max_size }
+    }
+}
+
+impl<S> Layer<S> for BodyPrefixLayer {
+    type Service = BodyPrefixService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyPrefixService {
+            inner,
+            max_size: self.max_size,
+        }
+    }
+}
+
+/// Middleware that buffers a bounded prefix of the request body.
+///
+/// See the [module docs](crate::http::layer::body_prefix) for more details.
+#[derive(Debug, Clone)]
+pub struct BodyPrefixService<S> {
+    inner: S,
+    max_size: usize,
+}
+
+impl<S> BodyPrefixService<S> {
+    /// Create a new [`BodyPrefixService`], buffering at most `max_size` bytes of the request body.
+    pub fn new(inner: S, max_size: usize) -> Self {
+        Self { inner, max_size }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<S, State, ReqBody> Service<State, Request<ReqBody>> for BodyPrefixService<S>
+where
+    S: Service<State, Request>,
+    State: Send + Sync + 'static,
+    ReqBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    ReqBody::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let (parts, body) = req.into_parts();
+        let mut body = Body::new(body);
+
+        let mut prefix = BytesMut::new();
+        let mut buffered = VecDeque::new();
+        let mut complete = false;
+        while prefix.len() < self.max_size {
+            match body.frame().await {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(mut data) => {
+                        let n = data.len().min(self.max_size - prefix.len());
+                        prefix.extend_from_slice(&data.split_to(n));
+                        if !data.is_empty() {
+                            buffered.push_back(Ok(Frame::data(data)));
+                        }
+                    }
+                    Err(frame) => {
+                        // only trailers can follow the data frames
+                        buffered.push_back(Ok(frame));
+                        complete = true;
+                        break;
+                    }
+                },
+                Some(Err(err)) => {
+                    buffered.push_back(Err(err));
+                    break;
+                }
+                None => {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+        let complete = complete || (buffered.is_empty() && http_body::Body::is_end_stream(&body));
+
+        let prefix = prefix.freeze();
+        if !prefix.is_empty() {
+            buffered.push_front(Ok(Frame::data(prefix.clone())));
+        }
+        ctx.insert(BodyPrefix {
+            bytes: prefix,
+            complete,
+        });
+
+        let body = Body::new(PrefixedBody::new(buffered, (!complete).then_some(body)));
+        self.inner
+            .serve(ctx, Request::from_parts(parts, body))
+            .await
+    }
+}
+
+/// A body yielding the buffered frames, followed by the remainder of the original body.
+pub(crate) struct PrefixedBody {
+    buffered: VecDeque<Result<Frame<Bytes>, Error>>,
+    inner: Option<Body>,
+}
+
+impl PrefixedBody {
+    /// Create a new [`PrefixedBody`] yielding the buffered frames,
+    /// followed by the frames of the inner body (if any).
+    pub(crate) fn new(
+        buffered: VecDeque<Result<Frame<Bytes>, Error>>,
+        inner: Option<Body>,
+    ) -> Self {
+        Self { buffered, inner }
+    }
+}
+
+impl http_body::Body for PrefixedBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(frame) = self.buffered.pop_front() {
+            if frame.is_err() {
+                self.inner = None;
+            }
+            return Poll::Ready(Some(frame));
+        }
+        match self.inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_frame(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.buffered.is_empty()
+            && self
+                .inner
+                .as_ref()
+                .map(http_body::Body::is_end_stream)
+                .unwrap_or(true)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let buffered = self
+            .buffered
+            .iter()
+            .filter_map(|frame| frame.as_ref().ok()?.data_ref())
+            .map(|data| data.len() as u64)
+            .sum::<u64>();
+        let inner = self
+            .inner
+            .as_ref()
+            .map(http_body::Body::size_hint)
+            .unwrap_or_else(|| http_body::SizeHint::with_exact(0));
+
+        let mut hint = http_body::SizeHint::new();
+        hint.set_lower(inner.lower() + buffered);
+        if let Some(upper) = inner.upper() {
+            hint.set_upper(upper + buffered);
+        }
+        hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::matcher::BodyPrefixFilter;
+    use crate::http::Response;
+    use crate::service::{service_fn, Matcher};
+    use std::convert::Infallible;
+
+    const JSON_RPC: &str =
+        r#"{"jsonrpc":"2.0","method":"eth_call","params":[{"to":"0x00","data":"0x00"}],"id":1}"#;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    async fn serve(max_size: usize, body: Body) -> Response<String> {
+        let service = BodyPrefixLayer::new(max_size).layer(service_fn(
+            |ctx: Context<()>, req: Request| async move {
+                let is_eth_call = BodyPrefixFilter::new(|prefix: &[u8]| {
+                    contains(prefix, br#""method":"eth_call""#)
+                })
+                .matches(None, &ctx, &req);
+                let prefix = ctx.get::<BodyPrefix>().cloned().unwrap();
+
+                let body = req.into_body().collect().await.unwrap().to_bytes();
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .header("x-eth-call", is_eth_call.to_string())
+                        .header("x-prefix-len", prefix.bytes().len())
+                        .header("x-prefix-complete", prefix.is_complete().to_string())
+                        .body(String::from_utf8(body.to_vec()).unwrap())
+                        .unwrap(),
+                )
+            },
+        ));
+        service
+            .serve(Context::default(), Request::new(body))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_body_prefix_json_rpc_method() {
+        let res = serve(40, Body::from(JSON_RPC)).await;
+        assert_eq!(res.headers()["x-eth-call"], "true");
+        assert_eq!(res.headers()["x-prefix-len"], "40");
+        assert_eq!(res.headers()["x-prefix-complete"], "false");
+        // the downstream service still reads the full body
+        assert_eq!(res.body(), JSON_RPC);
+
+        let res = serve(40, Body::from(JSON_RPC.replace("eth_call", "eth_send"))).await;
+        assert_eq!(res.headers()["x-eth-call"], "false");
+    }
+
+    #[tokio::test]
+    async fn test_body_prefix_cap_enforced() {
+        // the method is beyond the buffered prefix
+        let res = serve(16, Body::from(JSON_RPC)).await;
+        assert_eq!(res.headers()["x-eth-call"], "false");
+        assert_eq!(res.headers()["x-prefix-len"], "16");
+        assert_eq!(res.body(), JSON_RPC);
+
+        // a body smaller than the cap is buffered completely
+        let res = serve(1024, Body::from(JSON_RPC)).await;
+        assert_eq!(res.headers()["x-eth-call"], "true");
+        assert_eq!(res.headers()["x-prefix-len"], JSON_RPC.len().to_string());
+        assert_eq!(res.headers()["x-prefix-complete"], "true");
+        assert_eq!(res.body(), JSON_RPC);
+    }
+
+    #[tokio::test]
+    async fn test_body_prefix_streamed_body() {
+        let chunks = JSON_RPC
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok::<_, Infallible>(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        let res = serve(40, Body::from_stream(futures_util::stream::iter(chunks))).await;
+        assert_eq!(res.headers()["x-eth-call"], "true");
+        assert_eq!(res.headers()["x-prefix-len"], "40");
+        assert_eq!(res.body(), JSON_RPC);
+    }
+}
@@ -0,0 +1,591 @@
+//! Middleware that caches responses, as a shared cache defined by RFC 9111.
+//!
+//! Only responses to `GET` requests are cached, and only if they are cacheable
+//! according to their `Cache-Control` (or `Expires`) headers. A stored response is
+//! served directly from the cache while it is fresh, and once it got stale it is
+//! revalidated with the inner service, using a conditional request based on its
+//! `ETag` and/or `Last-Modified` validators. A `304 Not Modified` response allows
+//! the stored response to be served (and refreshed) without transferring it again.
+//!
+//! Responses with a `Vary` header are stored per variant, keyed by the values
+//! of the listed request headers (e.g. `Accept-Encoding`), such that a stored
+//! response is only served for requests with the same values for those headers.
+//! Responses with `Vary: *` are never stored, and neither are responses with a body
+//! larger than [`CacheLayer::max_body_size`], which are streamed to the client instead.
+//!
+//! The responses are stored in a [`CacheStore`], which is an [`InMemoryCacheStore`]
+//! evicting the least recently used responses by default.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::cache::CacheLayer;
+//! use rama::http::{header, Body, Request, Response};
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::error::BoxError;
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let service = ServiceBuilder::new()
+//!     .layer(CacheLayer::new())
+//!     .service_fn(|_: Request| async move {
+//!         Ok::<_, Infallible>(
+//!             Response::builder()
+//!                 .header(header::CACHE_CONTROL, "max-age=60")
+//!                 .body(Body::from("expensive report"))
+//!                 .unwrap(),
+//!         )
+//!     });
+//!
+//! let request = || Request::builder().uri("http://example.com/report").body(Body::empty()).unwrap();
+//! service.serve(Context::default(), request()).await?;
+//! // served from the cache
+//! let response = service.serve(Context::default(), request()).await?;
+//! assert!(response.headers().contains_key(header::AGE));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::BoxError,
+    http::{
+        header,
+        layer::util::body::{buffer_body, BufferedBody},
+        Body, HeaderValue, Request, Response, StatusCode,
+    },
+    service::{Context, Layer, Service},
+};
+use std::sync::Arc;
+
+mod policy;
+use policy::RequestDirectives;
+
+mod store;
+#[doc(inline)]
+pub use store::{CacheStore, CachedResponse, InMemoryCacheStore};
+
+/// The default maximum size of the body of a stored response: 1 MiB.
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// [`Layer`] that applies the [`Cache`] middleware.
+#[derive(Debug)]
+pub struct CacheLayer<St = InMemoryCacheStore> {
+    store: Arc<St>,
+    max_body_size: usize,
+}
+
+impl CacheLayer {
+    /// Create a new [`CacheLayer`], storing its responses in an [`InMemoryCacheStore`].
+    pub fn new() -> Self {
+        Self::with_store(InMemoryCacheStore::new())
+    }
+}
+
+impl Default for CacheLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<St> CacheLayer<St> {
+    /// Create a new [`CacheLayer`], storing its responses in the given [`CacheStore`].
+    ///
+    /// All services created by this layer share the same store.
+    pub fn with_store(store: St) -> Self {
+        Self {
+            store: Arc::new(store),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Only store responses with a body of at most the given amount of bytes,
+    /// instead of the default 1 MiB.
+    ///
+    /// Larger responses are streamed to the client without being stored,
+    /// such that they are never buffered entirely.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl<St> Clone for CacheLayer<St> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+impl<S, St> Layer<S> for CacheLayer<St> {
+    type Service = Cache<S, St>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Cache {
+            inner,
+            store: self.store.clone(),
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+/// Middleware that caches responses of the inner service.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug)]
+pub struct Cache<S, St = InMemoryCacheStore> {
+    inner: S,
+    store: Arc<St>,
+    max_body_size: usize,
+}
+
+impl<S, St> Cache<S, St> {
+    define_inner_service_accessors!();
+}
+
+impl<S> Cache<S> {
+    /// Returns a new [`Layer`] that wraps services with a [`Cache`] middleware.
+    pub fn layer() -> CacheLayer {
+        CacheLayer::new()
+    }
+}
+
+impl<S: Clone, St> Clone for Cache<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            store: self.store.clone(),
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+impl<State, S, St, ReqBody, ResBody> Service<State, Request<ReqBody>> for Cache<S, St>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    St: CacheStore,
+    ReqBody: Send + 'static,
+    ResBody: http_body::Body<Data = bytes::Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        mut req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let directives = RequestDirectives::new(&req);
+        if policy::is_bypassed(&req) || directives.no_store {
+            let key = cache_key(&req);
+            let invalidates = policy::invalidates(req.method());
+            let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+            if invalidates
+                && !response.status().is_client_error()
+                && !response.status().is_server_error()
+            {
+                self.store.remove(key).await;
+            }
+            return Ok(response.map(Body::new));
+        }
+
+        let key = cache_key(&req);
+        let request_headers = req.headers().clone();
+        let stored = self
+            .store
+            .get(key.clone())
+            .await
+            .into_iter()
+            .find(|variant| variant.matches(&request_headers));
+        if let Some(stored) = &stored {
+            if stored.is_fresh()
+                && !directives.no_cache
+                && !policy::requires_revalidation(stored.headers())
+            {
+                tracing::trace!(key, "cache: serve fresh response");
+                return Ok(cached_response(stored));
+            }
+            if policy::has_validator(stored.headers()) {
+                tracing::trace!(key, "cache: revalidate stale response");
+                policy::add_conditional_headers(req.headers_mut(), stored.headers());
+            }
+        }
+
+        let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+        let (parts, body) = response.into_parts();
+
+        if parts.status == StatusCode::NOT_MODIFIED {
+            if let Some(mut stored) = stored {
+                policy::update_headers(stored.headers_mut(), &parts.headers);
+                let freshness_lifetime = policy::freshness_lifetime(stored.headers());
+                let stored = stored
+                    .with_age(policy::initial_age(&parts.headers))
+                    .with_freshness_lifetime(freshness_lifetime);
+                let response = cached_response(&stored);
+                self.store.put(key, stored).await;
+                return Ok(response);
+            }
+        }
+
+        if !policy::is_storable(&directives, parts.status, &parts.headers) {
+            if stored.is_some() {
+                self.store.remove(key).await;
+            }
+            return Ok(Response::from_parts(parts, Body::new(body)));
+        }
+
+        let body = match buffer_body(body, self.max_body_size).await? {
+            BufferedBody::Complete(body) => body,
+            BufferedBody::TooLarge(body) => {
+                tracing::trace!(key, "cache: response body too large to store");
+                if stored.is_some() {
+                    self.store.remove(key).await;
+                }
+                return Ok(Response::from_parts(parts, body));
+            }
+        };
+        let stored = CachedResponse::new(
+            parts.status,
+            parts.version,
+            parts.headers.clone(),
+            body.clone(),
+        )
+        .with_age(policy::initial_age(&parts.headers))
+        .with_freshness_lifetime(policy::freshness_lifetime(&parts.headers))
+        .with_vary(policy::selecting_headers(&parts.headers, &request_headers));
+        tracing::trace!(key, "cache: store response");
+        self.store.put(key, stored).await;
+
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+/// The key used to store the response for the given request,
+/// based on its authority and path.
+fn cache_key<B>(req: &Request<B>) -> String {
+    let authority = req
+        .uri()
+        .authority()
+        .map(|authority| authority.as_str())
+        .or_else(|| {
+            req.headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+        })
+        .unwrap_or_default();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    format!("{authority}{path}")
+}
+
+/// Create a response for the given stored response.
+fn cached_response(stored: &CachedResponse) -> Response {
+    let mut response = Response::new(Body::from(stored.body().clone()));
+    *response.status_mut() = stored.status();
+    *response.version_mut() = stored.version();
+    *response.headers_mut() = stored.headers().clone();
+    response
+        .headers_mut()
+        .insert(header::AGE, HeaderValue::from(stored.age().as_secs()));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::service::service_fn;
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    fn request(path: &str) -> Request {
+        Request::builder()
+            .uri(format!("http://example.com{path}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    /// Create a cached service, which counts the calls to its inner service,
+    /// responding with the given headers, or `304 Not Modified` for a matching etag.
+    fn cached_service(
+        calls: Arc<AtomicUsize>,
+        headers: &'static [(&'static str, &'static str)],
+    ) -> impl Service<(), Request, Response = Response, Error = BoxError> {
+        cached_service_with(CacheLayer::new(), calls, headers)
+    }
+
+    fn cached_service_with(
+        layer: CacheLayer,
+        calls: Arc<AtomicUsize>,
+        headers: &'static [(&'static str, &'static str)],
+    ) -> impl Service<(), Request, Response = Response, Error = BoxError> {
+        layer.layer(service_fn(move |req: Request| {
+            let calls = calls.clone();
+            async move {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                let etag = headers.iter().find(|(name, _)| *name == "etag");
+                if let (Some(if_none_match), Some((_, etag))) =
+                    (req.headers().get(header::IF_NONE_MATCH), etag)
+                {
+                    if if_none_match == etag {
+                        return Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(StatusCode::NOT_MODIFIED)
+                                .header(header::CACHE_CONTROL, "max-age=60")
+                                .header(header::ETAG, *etag)
+                                .body(Body::empty())
+                                .unwrap(),
+                        );
+                    }
+                }
+                let mut response = Response::builder();
+                for (name, value) in headers {
+                    response = response.header(*name, *value);
+                }
+                Ok(response
+                    .body(Body::from(format!("response #{call}")))
+                    .unwrap())
+            }
+        }))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_hit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service(calls.clone(), &[("cache-control", "max-age=60")]);
+
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::AGE));
+        assert_eq!(body_string(response).await, "response #0");
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::AGE], "10");
+        assert_eq!(body_string(response).await, "response #0");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // a different target is not served from the cache
+        let response = service
+            .serve(Context::default(), request("/other"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "response #1");
+
+        // once stale (and without validator) it is fetched again
+        tokio::time::advance(Duration::from_secs(60)).await;
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "response #2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_stale_revalidation() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service(
+            calls.clone(),
+            &[("cache-control", "max-age=1"), ("etag", "\"v1\"")],
+        );
+
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "response #0");
+
+        // stale: revalidated by the inner service, which responds with 304
+        tokio::time::advance(Duration::from_secs(2)).await;
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=60");
+        assert_eq!(body_string(response).await, "response #0");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // refreshed by the revalidation
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "response #0");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_no_store_bypass() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service(calls.clone(), &[("cache-control", "no-store")]);
+
+        for call in 0..3 {
+            let response = service
+                .serve(Context::default(), request("/"))
+                .await
+                .unwrap();
+            assert_eq!(body_string(response).await, format!("response #{call}"));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_max_body_size() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service_with(
+            CacheLayer::new().max_body_size(5),
+            calls.clone(),
+            &[("cache-control", "max-age=60")],
+        );
+
+        for call in 0..2 {
+            let response = service
+                .serve(Context::default(), request("/"))
+                .await
+                .unwrap();
+            assert!(!response.headers().contains_key(header::AGE));
+            assert_eq!(body_string(response).await, format!("response #{call}"));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_request_directives() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service(
+            calls.clone(),
+            &[("cache-control", "max-age=60"), ("etag", "\"v1\"")],
+        );
+        service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+
+        // no-cache: fresh response is revalidated
+        let mut req = request("/");
+        req.headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        let response = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(body_string(response).await, "response #0");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // no-store: passed through as-is
+        let mut req = request("/");
+        req.headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        let response = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(body_string(response).await, "response #2");
+
+        // the client's own conditional request is passed through as-is
+        let mut req = request("/");
+        req.headers_mut()
+            .insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"v1\""));
+        let response = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // an unsafe request invalidates the stored response
+        let mut req = request("/");
+        *req.method_mut() = crate::http::Method::POST;
+        service.serve(Context::default(), req).await.unwrap();
+        let response = service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "response #5");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_vary_accept_encoding() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = CacheLayer::new().layer(service_fn({
+            let calls = calls.clone();
+            move |req: Request| {
+                let calls = calls.clone();
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    let encoding = req
+                        .headers()
+                        .get(header::ACCEPT_ENCODING)
+                        .map(|value| value.to_str().unwrap().to_owned())
+                        .unwrap_or_else(|| "identity".to_owned());
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .header(header::CACHE_CONTROL, "max-age=60")
+                            .header(header::VARY, "accept-encoding")
+                            .body(Body::from(format!("encoded with {encoding}")))
+                            .unwrap(),
+                    )
+                }
+            }
+        }));
+
+        let request = |encoding: Option<&'static str>| {
+            let mut req = request("/");
+            if let Some(encoding) = encoding {
+                req.headers_mut()
+                    .insert(header::ACCEPT_ENCODING, HeaderValue::from_static(encoding));
+            }
+            req
+        };
+
+        for _ in 0..2 {
+            for (encoding, expected) in [
+                (Some("gzip"), "encoded with gzip"),
+                (Some("br"), "encoded with br"),
+                (None, "encoded with identity"),
+            ] {
+                let response = service
+                    .serve(Context::default(), request(encoding))
+                    .await
+                    .unwrap();
+                assert_eq!(body_string(response).await, expected);
+            }
+        }
+        // each representation is fetched once, and served from the cache afterwards
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_vary_star_is_not_stored() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = cached_service(
+            calls.clone(),
+            &[("cache-control", "max-age=60"), ("vary", "*")],
+        );
+
+        service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        service
+            .serve(Context::default(), request("/"))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
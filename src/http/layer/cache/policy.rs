@@ -0,0 +1,312 @@
+//! Caching rules of RFC 9111, as applied by the (shared) [`Cache`] middleware.
+//!
+//! [`Cache`]: super::Cache
+
+use super::store::combined_value;
+use crate::http::{
+    header,
+    headers::{Age, CacheControl, Date, Expires, HeaderMapExt},
+    HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode,
+};
+use std::time::{Duration, SystemTime};
+
+/// The cache directives of a request.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RequestDirectives {
+    /// The response may not be served from nor stored in the cache.
+    pub(super) no_store: bool,
+    /// A stored response has to be revalidated before it can be served.
+    pub(super) no_cache: bool,
+    /// The request carries credentials, see [`is_storable`].
+    pub(super) authorized: bool,
+}
+
+impl RequestDirectives {
+    pub(super) fn new<B>(req: &Request<B>) -> Self {
+        let headers = req.headers();
+        let cache_control = headers.typed_get::<CacheControl>();
+        Self {
+            no_store: cache_control.as_ref().is_some_and(CacheControl::no_store),
+            no_cache: cache_control
+                .as_ref()
+                .is_some_and(|cc| cc.no_cache() || cc.max_age() == Some(Duration::ZERO))
+                || headers
+                    .get(header::PRAGMA)
+                    .is_some_and(|pragma| pragma.as_bytes().eq_ignore_ascii_case(b"no-cache")),
+            authorized: headers.contains_key(header::AUTHORIZATION),
+        }
+    }
+}
+
+/// Returns `true` if the request is not a candidate for caching at all,
+/// in which case it is passed through to the inner service as-is.
+///
+/// This is the case for any request which is not a `GET` request,
+/// as well as for requests which are already conditional or partial,
+/// as the response for those is specific to the client.
+pub(super) fn is_bypassed<B>(req: &Request<B>) -> bool {
+    let headers = req.headers();
+    req.method() != Method::GET
+        || headers.contains_key(header::IF_NONE_MATCH)
+        || headers.contains_key(header::IF_MODIFIED_SINCE)
+        || headers.contains_key(header::IF_MATCH)
+        || headers.contains_key(header::IF_UNMODIFIED_SINCE)
+        || headers.contains_key(header::IF_RANGE)
+        || headers.contains_key(header::RANGE)
+}
+
+/// Returns `true` if a successful response to a request with the given method
+/// invalidates the responses stored for its target (RFC 9111, section 4.4).
+pub(super) fn invalidates(method: &Method) -> bool {
+    !matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Returns `true` if the response may be stored by a shared cache.
+pub(super) fn is_storable(
+    request: &RequestDirectives,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> bool {
+    if request.no_store || status != StatusCode::OK {
+        return false;
+    }
+    let cache_control = headers.typed_get::<CacheControl>();
+    if let Some(cc) = &cache_control {
+        if cc.no_store() || cc.private() {
+            return false;
+        }
+    }
+    // responses to authorized requests are only stored if explicitly allowed
+    if request.authorized
+        && !cache_control
+            .as_ref()
+            .is_some_and(|cc| cc.public() || cc.s_max_age().is_some())
+    {
+        return false;
+    }
+    // a response varying on anything can never be selected for a request
+    if vary_names(headers).is_none() {
+        return false;
+    }
+    // without freshness nor validator a stored response can never be used
+    !freshness_lifetime(headers).is_zero() || has_validator(headers)
+}
+
+/// The request headers listed by the `Vary` header(s) of a response,
+/// or `None` in case it varies on anything (`Vary: *`).
+pub(super) fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(header::VARY) {
+        let value = value.to_str().ok()?;
+        for name in value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            if name == "*" {
+                return None;
+            }
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    Some(names)
+}
+
+/// The values of the request headers selecting the (storable) response,
+/// as listed by its `Vary` header(s).
+pub(super) fn selecting_headers(
+    response: &HeaderMap,
+    request: &HeaderMap,
+) -> Vec<(HeaderName, Option<HeaderValue>)> {
+    vary_names(response)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| {
+            let value = combined_value(request, &name);
+            (name, value)
+        })
+        .collect()
+}
+
+/// Returns `true` if the response has to be revalidated each time it is used.
+pub(super) fn requires_revalidation(headers: &HeaderMap) -> bool {
+    headers
+        .typed_get::<CacheControl>()
+        .is_some_and(|cc| cc.no_cache())
+}
+
+/// Returns `true` if the response can be revalidated using a conditional request.
+pub(super) fn has_validator(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED)
+}
+
+/// Add the conditional headers to revalidate a stored response
+/// with the given headers to the (outgoing) request headers.
+pub(super) fn add_conditional_headers(request: &mut HeaderMap, stored: &HeaderMap) {
+    if let Some(etag) = stored.get(header::ETAG) {
+        request.insert(header::IF_NONE_MATCH, etag.clone());
+    }
+    if let Some(last_modified) = stored.get(header::LAST_MODIFIED) {
+        request.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+    }
+}
+
+/// The freshness lifetime of a response, as defined by a shared cache
+/// (RFC 9111, section 4.2.1). No heuristic freshness is applied.
+pub(super) fn freshness_lifetime(headers: &HeaderMap) -> Duration {
+    if let Some(cc) = headers.typed_get::<CacheControl>() {
+        if let Some(lifetime) = cc.s_max_age().or_else(|| cc.max_age()) {
+            return lifetime;
+        }
+    }
+    match headers.typed_get::<Expires>() {
+        Some(expires) => {
+            let date = headers
+                .typed_get::<Date>()
+                .map(SystemTime::from)
+                .unwrap_or_else(SystemTime::now);
+            SystemTime::from(expires)
+                .duration_since(date)
+                .unwrap_or_default()
+        }
+        None => Duration::ZERO,
+    }
+}
+
+/// The age the response already had when it was received.
+pub(super) fn initial_age(headers: &HeaderMap) -> Duration {
+    headers
+        .typed_get::<Age>()
+        .map(Duration::from)
+        .unwrap_or_default()
+}
+
+/// Update the headers of a stored response with the headers
+/// of the `304 Not Modified` response which revalidated it.
+pub(super) fn update_headers(stored: &mut HeaderMap, not_modified: &HeaderMap) {
+    for name in not_modified.keys() {
+        if name == header::CONTENT_LENGTH || name == header::TRANSFER_ENCODING {
+            continue;
+        }
+        stored.remove(name);
+        for value in not_modified.get_all(name) {
+            stored.append(name.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    header::HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        assert_eq!(freshness_lifetime(&headers(&[])), Duration::ZERO);
+        assert_eq!(
+            freshness_lifetime(&headers(&[("cache-control", "max-age=60")])),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[("cache-control", "max-age=60, s-maxage=120")])),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[
+                ("date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+            ])),
+            Duration::from_secs(600)
+        );
+        // max-age takes precedence over expires
+        assert_eq!(
+            freshness_lifetime(&headers(&[
+                ("cache-control", "max-age=5"),
+                ("date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+            ])),
+            Duration::from_secs(5)
+        );
+        // expired in the past
+        assert_eq!(
+            freshness_lifetime(&headers(&[("expires", "Wed, 21 Oct 2015 07:38:00 GMT")])),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_is_storable() {
+        let request = RequestDirectives::default();
+        let authorized = RequestDirectives {
+            authorized: true,
+            ..Default::default()
+        };
+
+        let fresh = headers(&[("cache-control", "max-age=60")]);
+        assert!(is_storable(&request, StatusCode::OK, &fresh));
+        assert!(!is_storable(&request, StatusCode::NOT_FOUND, &fresh));
+        assert!(!is_storable(&authorized, StatusCode::OK, &fresh));
+        assert!(is_storable(
+            &authorized,
+            StatusCode::OK,
+            &headers(&[("cache-control", "public, max-age=60")])
+        ));
+
+        assert!(is_storable(
+            &request,
+            StatusCode::OK,
+            &headers(&[("etag", "\"v1\"")])
+        ));
+        assert!(!is_storable(&request, StatusCode::OK, &headers(&[])));
+        assert!(!is_storable(
+            &request,
+            StatusCode::OK,
+            &headers(&[("cache-control", "no-store, max-age=60")])
+        ));
+        assert!(!is_storable(
+            &request,
+            StatusCode::OK,
+            &headers(&[("cache-control", "private, max-age=60")])
+        ));
+        assert!(!is_storable(
+            &request,
+            StatusCode::OK,
+            &headers(&[("cache-control", "max-age=60"), ("vary", "*")])
+        ));
+    }
+
+    #[test]
+    fn test_selecting_headers() {
+        let response = headers(&[("vary", "Accept-Encoding, accept-language")]);
+        let request = headers(&[("accept-encoding", "gzip"), ("user-agent", "rama")]);
+        assert_eq!(
+            selecting_headers(&response, &request),
+            vec![
+                (
+                    header::ACCEPT_ENCODING,
+                    Some(HeaderValue::from_static("gzip"))
+                ),
+                (header::ACCEPT_LANGUAGE, None),
+            ]
+        );
+        assert!(selecting_headers(&headers(&[]), &request).is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use crate::http::{utils::LruMap, HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use bytes::Bytes;
+use std::{future::Future, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// A response stored by the [`Cache`] middleware,
+/// together with the metadata required to compute its freshness.
+///
+/// A [`CacheStore`] which does not keep the responses in memory can recreate
+/// a stored response using [`CachedResponse::new`] and its builder methods,
+/// from the values returned by the accessors of the original response.
+///
+/// [`Cache`]: crate::http::layer::cache::Cache
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+    freshness_lifetime: Duration,
+    initial_age: Duration,
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+impl CachedResponse {
+    /// Create a new [`CachedResponse`], stored now, with an age and freshness lifetime of zero,
+    /// and without any `Vary` request headers.
+    pub fn new(status: StatusCode, version: Version, headers: HeaderMap, body: Bytes) -> Self {
+        Self {
+            status,
+            version,
+            headers,
+            body,
+            stored_at: Instant::now(),
+            freshness_lifetime: Duration::ZERO,
+            initial_age: Duration::ZERO,
+            vary: Vec::new(),
+        }
+    }
+
+    /// Set the age the response has at the time it is created.
+    pub fn with_age(mut self, age: Duration) -> Self {
+        self.initial_age = age;
+        self.stored_at = Instant::now();
+        self
+    }
+
+    /// Set the freshness lifetime of the response,
+    /// i.e. the age up to which it can be served without revalidating it.
+    pub fn with_freshness_lifetime(mut self, lifetime: Duration) -> Self {
+        self.freshness_lifetime = lifetime;
+        self
+    }
+
+    /// Set the request headers listed by the `Vary` header of the response,
+    /// with the values they had in the request for which it is stored.
+    pub fn with_vary(mut self, vary: Vec<(HeaderName, Option<HeaderValue>)>) -> Self {
+        self.vary = vary;
+        self
+    }
+
+    /// The status code of the stored response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The http version of the stored response.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The headers of the stored response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub(super) fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// The (buffered) body of the stored response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// The current age of the stored response,
+    /// including the age it already had when it was stored.
+    pub fn age(&self) -> Duration {
+        self.initial_age + self.stored_at.elapsed()
+    }
+
+    /// The freshness lifetime of the stored response.
+    pub fn freshness_lifetime(&self) -> Duration {
+        self.freshness_lifetime
+    }
+
+    /// Returns `true` if the stored response can still be served
+    /// without revalidating it with the origin.
+    pub fn is_fresh(&self) -> bool {
+        self.age() < self.freshness_lifetime
+    }
+
+    /// The request headers listed by the `Vary` header of the stored response,
+    /// with the values they had in the request for which it was stored.
+    pub fn vary(&self) -> &[(HeaderName, Option<HeaderValue>)] {
+        &self.vary
+    }
+
+    /// Returns `true` if the stored response can be used for a request
+    /// with the given headers, as the headers listed by its `Vary` header match.
+    pub fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| combined_value(request_headers, name).as_ref() == value.as_ref())
+    }
+
+    /// Returns `true` if both stored responses are the same representation,
+    /// selected by the same request header values.
+    pub fn is_same_variant(&self, other: &CachedResponse) -> bool {
+        self.vary == other.vary
+    }
+}
+
+/// The combined value of all the values of the given header,
+/// such that headers split over multiple lines compare equal.
+pub(super) fn combined_value(headers: &HeaderMap, name: &HeaderName) -> Option<HeaderValue> {
+    let mut values = headers.get_all(name).iter();
+    let first = values.next()?;
+    let mut combined = first.as_bytes().to_vec();
+    for value in values {
+        combined.extend_from_slice(b", ");
+        combined.extend_from_slice(value.as_bytes());
+    }
+    HeaderValue::from_bytes(&combined).ok()
+}
+
+/// The storage used by the [`Cache`] middleware to store its responses.
+///
+/// Multiple responses can be stored for the same key, one for each variant
+/// of a response with a `Vary` header (see [`CachedResponse::is_same_variant`]).
+///
+/// [`Cache`]: crate::http::layer::cache::Cache
+pub trait CacheStore: Send + Sync + 'static {
+    /// Get all the variants of the response stored for the given key.
+    fn get(&self, key: String) -> impl Future<Output = Vec<CachedResponse>> + Send + '_;
+
+    /// Store the given response for the given key, replacing
+    /// the same variant of the response stored previously for that key.
+    fn put(&self, key: String, response: CachedResponse) -> impl Future<Output = ()> + Send + '_;
+
+    /// Remove all variants of the response stored for the given key.
+    fn remove(&self, key: String) -> impl Future<Output = ()> + Send + '_;
+}
+
+/// The default capacity of an [`InMemoryCacheStore`].
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// An in-memory [`CacheStore`], which evicts the least recently used response
+/// (including all its variants) once its capacity is reached.
+#[derive(Debug)]
+pub struct InMemoryCacheStore {
+    capacity: usize,
+    entries: Mutex<LruMap<String, Vec<CachedResponse>>>,
+}
+
+impl InMemoryCacheStore {
+    /// Create a new [`InMemoryCacheStore`] with the default capacity (1024 responses).
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a new [`InMemoryCacheStore`], which stores responses
+    /// for at most `capacity` keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is `0`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache store capacity must be non-zero");
+        Self {
+            capacity,
+            entries: Mutex::new(LruMap::new()),
+        }
+    }
+
+    /// Returns the number of keys for which responses are currently stored.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no responses are currently stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryCacheStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CacheStore for InMemoryCacheStore {
+    async fn get(&self, key: String) -> Vec<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.get(&key).cloned().unwrap_or_default()
+    }
+
+    async fn put(&self, key: String, response: CachedResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut variants = entries.remove(&key).unwrap_or_default();
+        variants.retain(|variant| !variant.is_same_variant(&response));
+        variants.push(response);
+        entries.insert(key, variants, self.capacity);
+    }
+
+    async fn remove(&self, key: String) {
+        self.entries.lock().unwrap().remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static str) -> CachedResponse {
+        variant(body, None)
+    }
+
+    fn variant(body: &'static str, encoding: Option<&'static str>) -> CachedResponse {
+        CachedResponse::new(
+            StatusCode::OK,
+            Version::HTTP_11,
+            HeaderMap::new(),
+            Bytes::from_static(body.as_bytes()),
+        )
+        .with_freshness_lifetime(Duration::from_secs(60))
+        .with_vary(vec![(
+            crate::http::header::ACCEPT_ENCODING,
+            encoding.map(HeaderValue::from_static),
+        )])
+    }
+
+    fn bodies(variants: Vec<CachedResponse>) -> Vec<Bytes> {
+        variants.into_iter().map(|v| v.body).collect()
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_evicts_least_recently_used() {
+        let store = InMemoryCacheStore::with_capacity(2);
+        store.put("a".to_owned(), response("a")).await;
+        store.put("b".to_owned(), response("b")).await;
+
+        // use a, such that b is the least recently used response
+        assert_eq!(bodies(store.get("a".to_owned()).await), ["a"]);
+        store.put("c".to_owned(), response("c")).await;
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b".to_owned()).await.is_empty());
+        assert_eq!(bodies(store.get("a".to_owned()).await), ["a"]);
+        assert_eq!(bodies(store.get("c".to_owned()).await), ["c"]);
+
+        // replacing a response does not evict another one
+        store.put("c".to_owned(), response("c2")).await;
+        assert_eq!(store.len(), 2);
+        assert_eq!(bodies(store.get("c".to_owned()).await), ["c2"]);
+
+        store.remove("a".to_owned()).await;
+        assert_eq!(store.len(), 1);
+        assert!(store.get("a".to_owned()).await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cached_response_recreate() {
+        let stored = response("a").with_age(Duration::from_secs(10));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(stored.age(), Duration::from_secs(15));
+
+        // e.g. as done by a store which persists its responses elsewhere
+        let recreated = CachedResponse::new(
+            stored.status(),
+            stored.version(),
+            stored.headers().clone(),
+            stored.body().clone(),
+        )
+        .with_age(stored.age())
+        .with_freshness_lifetime(stored.freshness_lifetime())
+        .with_vary(stored.vary().to_vec());
+        assert_eq!(recreated.age(), Duration::from_secs(15));
+        assert!(recreated.is_fresh());
+        assert!(recreated.is_same_variant(&stored));
+
+        tokio::time::advance(Duration::from_secs(45)).await;
+        assert!(!recreated.is_fresh());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_variants() {
+        let store = InMemoryCacheStore::with_capacity(1);
+        store
+            .put("a".to_owned(), variant("gzip", Some("gzip")))
+            .await;
+        store.put("a".to_owned(), variant("identity", None)).await;
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            bodies(store.get("a".to_owned()).await),
+            ["gzip", "identity"]
+        );
+
+        // replacing a variant keeps the other variants
+        store
+            .put("a".to_owned(), variant("gzip2", Some("gzip")))
+            .await;
+        assert_eq!(
+            bodies(store.get("a".to_owned()).await),
+            ["identity", "gzip2"]
+        );
+
+        let mut headers = HeaderMap::new();
+        let variants = store.get("a".to_owned()).await;
+        assert!(variants[0].matches(&headers));
+        assert!(!variants[1].matches(&headers));
+        headers.insert(
+            crate::http::header::ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip"),
+        );
+        assert!(!variants[0].matches(&headers));
+        assert!(variants[1].matches(&headers));
+    }
+}
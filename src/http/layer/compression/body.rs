@@ -21,6 +21,7 @@ use std::{
 use tokio_util::io::StreamReader;
 
 use super::pin_project_cfg::pin_project_cfg;
+use super::streaming::StreamingGzipBody;
 
 pin_project! {
     /// Response body of [`Compression`].
@@ -75,6 +76,10 @@ pin_project_cfg! {
             #[pin]
             inner: GzipBody<B>,
         },
+        StreamingGzip {
+            #[pin]
+            inner: StreamingGzipBody<B>,
+        },
         Deflate {
             #[pin]
             inner: DeflateBody<B>,
@@ -99,6 +104,10 @@ impl<B: Body> BodyInner<B> {
         Self::Gzip { inner }
     }
 
+    pub(crate) fn streaming_gzip(inner: StreamingGzipBody<B>) -> Self {
+        Self::StreamingGzip { inner }
+    }
+
     pub(crate) fn deflate(inner: WrapBody<ZlibEncoder<B>>) -> Self {
         Self::Deflate { inner }
     }
@@ -130,6 +139,7 @@ where
     ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
         match self.project().inner.project() {
             BodyInnerProj::Gzip { inner } => inner.poll_frame(cx),
+            BodyInnerProj::StreamingGzip { inner } => inner.poll_frame(cx),
             BodyInnerProj::Deflate { inner } => inner.poll_frame(cx),
             BodyInnerProj::Brotli { inner } => inner.poll_frame(cx),
             BodyInnerProj::Zstd { inner } => inner.poll_frame(cx),
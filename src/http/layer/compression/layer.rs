@@ -1,5 +1,5 @@
 use super::predicate::DefaultPredicate;
-use super::{Compression, Predicate};
+use super::{Compression, FlushPolicy, Predicate};
 use crate::http::layer::util::compression::{AcceptEncoding, CompressionLevel};
 use crate::service::Layer;
 
@@ -14,6 +14,7 @@ pub struct CompressionLayer<P = DefaultPredicate> {
     accept: AcceptEncoding,
     predicate: P,
     quality: CompressionLevel,
+    flush: Option<FlushPolicy>,
 }
 
 impl<S, P> Layer<S> for CompressionLayer<P>
@@ -28,6 +29,7 @@ where
             accept: self.accept,
             predicate: self.predicate.clone(),
             quality: self.quality,
+            flush: self.flush,
         }
     }
 }
@@ -68,6 +70,15 @@ impl CompressionLayer {
         self
     }
 
+    /// Flush the compressed stream of streaming response bodies as dictated by the given
+    /// [`FlushPolicy`], such that clients receive their data promptly.
+    ///
+    /// See [`Compression::flush`] for more details.
+    pub fn flush(mut self, policy: FlushPolicy) -> Self {
+        self.flush = Some(policy);
+        self
+    }
+
     /// Disables the gzip encoding.
     ///
     /// This method is available even if the `gzip` crate feature is disabled.
@@ -111,6 +122,7 @@ impl CompressionLayer {
             accept: self.accept,
             predicate,
             quality: self.quality,
+            flush: self.flush,
         }
     }
 }
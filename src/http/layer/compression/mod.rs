@@ -74,13 +74,16 @@ mod body;
 mod layer;
 mod pin_project_cfg;
 mod service;
+mod streaming;
 
+pub(crate) use self::service::compress_response;
 #[doc(inline)]
 pub use self::{
     body::CompressionBody,
     layer::CompressionLayer,
     predicate::{DefaultPredicate, Predicate},
     service::Compression,
+    streaming::FlushPolicy,
 };
 pub use crate::http::layer::util::compression::CompressionLevel;
 
@@ -88,7 +91,7 @@ pub use crate::http::layer::util::compression::CompressionLevel;
 mod tests {
     use super::*;
 
-    use crate::http::layer::compression::predicate::SizeAbove;
+    use crate::http::layer::compression::predicate::{NotForContentType, SizeAbove};
 
     use crate::http::dep::http_body_util::BodyExt;
     use crate::http::header::{
@@ -101,6 +104,7 @@ mod tests {
     use std::convert::Infallible;
     use std::io::Read;
     use std::sync::{Arc, RwLock};
+    use std::time::Duration;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio_util::io::StreamReader;
 
@@ -484,4 +488,175 @@ mod tests {
         assert_eq!(headers[CONTENT_ENCODING], "gzip");
         assert_eq!(decompressed, "Hello, World!");
     }
+
+    /// Serve a streaming response, of which the chunks are sent using the returned sender.
+    async fn serve_streaming(
+        flush: FlushPolicy,
+        accept_encoding: &str,
+    ) -> (
+        futures::channel::mpsc::UnboundedSender<Result<&'static str, Infallible>>,
+        Response<CompressionBody<Body>>,
+    ) {
+        serve_streaming_with(flush, Always, "text/plain", accept_encoding).await
+    }
+
+    /// Serve a streaming response of the given content type,
+    /// of which the chunks are sent using the returned sender.
+    async fn serve_streaming_with(
+        flush: FlushPolicy,
+        predicate: impl Predicate + Send + Sync + 'static,
+        content_type: &'static str,
+        accept_encoding: &str,
+    ) -> (
+        futures::channel::mpsc::UnboundedSender<Result<&'static str, Infallible>>,
+        Response<CompressionBody<Body>>,
+    ) {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let body = std::sync::Mutex::new(Some(Body::from_stream(rx)));
+        let svc = Compression::new(service_fn(move |_: Request| {
+            let body = body.lock().unwrap().take().unwrap();
+            async move {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .header(CONTENT_TYPE, content_type)
+                        .body(body)
+                        .unwrap(),
+                )
+            }
+        }))
+        .compress_when(predicate)
+        .flush(flush);
+
+        let req = Request::builder()
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        (tx, svc.serve(Context::default(), req).await.unwrap())
+    }
+
+    /// Decompress the (possibly incomplete) gzip stream received so far.
+    fn gunzip_partial(data: &[u8]) -> String {
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        std::io::Write::write_all(&mut decoder, data).unwrap();
+        std::io::Write::flush(&mut decoder).unwrap();
+        String::from_utf8(decoder.get_ref().clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn gzip_flush_emits_partial_output() {
+        let (tx, res) = serve_streaming(FlushPolicy::new(), "gzip").await;
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        let mut body = res.into_body();
+
+        // the first event is received while the stream is still open
+        tx.unbounded_send(Ok("data: hello\n\n")).unwrap();
+        let mut received = Vec::new();
+        while !gunzip_partial(&received).contains("data: hello") {
+            let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .expect("partial output before the end of the stream")
+                .unwrap()
+                .unwrap();
+            received.extend_from_slice(&frame.into_data().unwrap());
+        }
+        assert_eq!(gunzip_partial(&received), "data: hello\n\n");
+
+        tx.unbounded_send(Ok("data: world\n\n")).unwrap();
+        drop(tx);
+        received.extend_from_slice(&body.collect().await.unwrap().to_bytes());
+
+        let mut decompressed = String::new();
+        GzDecoder::new(&received[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, "data: hello\n\ndata: world\n\n");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gzip_flush_interval() {
+        let (tx, res) = serve_streaming(
+            FlushPolicy::new()
+                .interval(Duration::from_millis(100))
+                .size(64 * 1024),
+            "gzip",
+        )
+        .await;
+        let mut body = res.into_body();
+
+        let start = tokio::time::Instant::now();
+        tx.unbounded_send(Ok("data: hello\n\n")).unwrap();
+        let mut received = Vec::new();
+        while !gunzip_partial(&received).contains("data: hello") {
+            let frame = body.frame().await.unwrap().unwrap();
+            received.extend_from_slice(&frame.into_data().unwrap());
+        }
+        // the data is only flushed once the interval passed, as the size is not reached
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn gzip_flush_prefers_gzip() {
+        let (_tx, res) = serve_streaming(FlushPolicy::new(), "br, zstd, gzip;q=0.5").await;
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+
+        let (_tx, res) = serve_streaming(FlushPolicy::new(), "br, zstd").await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn gzip_flush_only_streaming_bodies() {
+        let svc = Compression::new(service_fn(|_: Request| async {
+            Ok::<_, Infallible>(Response::new(Body::from("Hello, World!")))
+        }))
+        .compress_when(Always)
+        .flush(FlushPolicy::new());
+
+        // bodies of a known size are compressed as usual
+        let req = Request::builder()
+            .header(ACCEPT_ENCODING, "br, gzip;q=0.5")
+            .body(Body::empty())
+            .unwrap();
+        let res = svc.serve(Context::default(), req).await.unwrap();
+        assert_eq!(res.headers()[CONTENT_ENCODING], "br");
+
+        let compressed = res.into_body().collect().await.unwrap().to_bytes();
+        let mut decoder = BrotliDecoder::new(Vec::new());
+        decoder.write_all(&compressed).await.unwrap();
+        decoder.shutdown().await.unwrap();
+        assert_eq!(decoder.into_inner(), b"Hello, World!");
+    }
+
+    #[tokio::test]
+    async fn gzip_flush_sse() {
+        // the default predicate never compresses server-sent events
+        let (_tx, res) = serve_streaming_with(
+            FlushPolicy::new(),
+            DefaultPredicate::new(),
+            "text/event-stream",
+            "gzip",
+        )
+        .await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+
+        let predicate = SizeAbove::default()
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::IMAGES);
+        let (tx, res) =
+            serve_streaming_with(FlushPolicy::new(), predicate, "text/event-stream", "gzip").await;
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        let mut body = res.into_body();
+
+        tx.unbounded_send(Ok("data: hello\n\n")).unwrap();
+        let mut received = Vec::new();
+        while !gunzip_partial(&received).contains("data: hello") {
+            let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .expect("partial output before the end of the stream")
+                .unwrap()
+                .unwrap();
+            received.extend_from_slice(&frame.into_data().unwrap());
+        }
+        drop(tx);
+    }
 }
@@ -1,7 +1,8 @@
 use super::body::BodyInner;
 use super::predicate::{DefaultPredicate, Predicate};
+use super::streaming::StreamingGzipBody;
 use super::CompressionLevel;
-use super::{CompressionBody, CompressionLayer};
+use super::{CompressionBody, CompressionLayer, FlushPolicy};
 use crate::http::dep::http_body::Body;
 use crate::http::layer::util::compression::WrapBody;
 use crate::http::layer::util::{compression::AcceptEncoding, content_encoding::Encoding};
@@ -20,6 +21,7 @@ pub struct Compression<S, P = DefaultPredicate> {
     pub(crate) accept: AcceptEncoding,
     pub(crate) predicate: P,
     pub(crate) quality: CompressionLevel,
+    pub(crate) flush: Option<FlushPolicy>,
 }
 
 impl<S, P> std::fmt::Debug for Compression<S, P>
@@ -31,6 +33,7 @@ where
             .field("inner", &self.inner)
             .field("accept", &self.accept)
             .field("quality", &self.quality)
+            .field("flush", &self.flush)
             .finish()
     }
 }
@@ -43,6 +46,7 @@ impl<S> Compression<S, DefaultPredicate> {
             accept: AcceptEncoding::default(),
             predicate: DefaultPredicate::default(),
             quality: CompressionLevel::default(),
+            flush: None,
         }
     }
 }
@@ -87,6 +91,45 @@ impl<S, P> Compression<S, P> {
         self
     }
 
+    /// Flush the compressed stream of streaming response bodies as dictated by the given
+    /// [`FlushPolicy`], such that clients receive their data promptly.
+    ///
+    /// Only response bodies of which the exact size is unknown upfront are flushed.
+    /// Only the gzip encoding supports flushing, so these bodies are either compressed
+    /// using gzip (if accepted by the client) or not at all. Other responses
+    /// are compressed as usual. See [`FlushPolicy`] for more details.
+    ///
+    /// Note that the [`DefaultPredicate`] never compresses Server-Sent Events (SSE).
+    /// Use a predicate without [`NotForContentType::SSE`] to compress (and flush) these:
+    ///
+    /// ```
+    /// use rama::http::layer::compression::{
+    ///     predicate::{NotForContentType, Predicate, SizeAbove},
+    ///     Compression, FlushPolicy,
+    /// };
+    /// use rama::service::service_fn;
+    ///
+    /// // Placeholder service_fn
+    /// let service = service_fn(|_: ()| async {
+    ///     Ok::<_, std::io::Error>(rama::http::Response::new(()))
+    /// });
+    ///
+    /// // the default predicate, except that SSE responses are compressed as well
+    /// let predicate = SizeAbove::default()
+    ///     .and(NotForContentType::GRPC)
+    ///     .and(NotForContentType::IMAGES);
+    ///
+    /// let service = Compression::new(service)
+    ///     .flush(FlushPolicy::new())
+    ///     .compress_when(predicate);
+    /// ```
+    ///
+    /// [`NotForContentType::SSE`]: super::predicate::NotForContentType::SSE
+    pub fn flush(mut self, policy: FlushPolicy) -> Self {
+        self.flush = Some(policy);
+        self
+    }
+
     /// Disables the gzip encoding.
     ///
     /// This method is available even if the `gzip` crate feature is disabled.
@@ -163,6 +206,7 @@ impl<S, P> Compression<S, P> {
             accept: self.accept,
             predicate,
             quality: self.quality,
+            flush: self.flush,
         }
     }
 }
@@ -181,82 +225,123 @@ where
     type Response = Response<CompressionBody<ResBody>>;
     type Error = S::Error;
 
-    #[allow(unreachable_code, unused_mut, unused_variables, unreachable_patterns)]
     async fn serve(
         &self,
         ctx: Context<State>,
         req: Request<ReqBody>,
     ) -> Result<Self::Response, Self::Error> {
         let encoding = Encoding::from_headers(req.headers(), self.accept);
+        let flush = self.flush.map(|policy| {
+            // only gzip supports flushing
+            let encoding = Encoding::from_headers(
+                req.headers(),
+                AcceptEncoding {
+                    gzip: self.accept.gzip,
+                    deflate: false,
+                    br: false,
+                    zstd: false,
+                },
+            );
+            (policy, encoding)
+        });
 
         let res = self.inner.serve(ctx, req).await?;
 
-        // never recompress responses that are already compressed
-        let should_compress = !res.headers().contains_key(header::CONTENT_ENCODING)
-            // never compress responses that are ranges
-            && !res.headers().contains_key(header::CONTENT_RANGE)
-            && self.predicate.should_compress(&res);
-
-        let (mut parts, body) = res.into_parts();
+        Ok(compress_response(
+            res,
+            encoding,
+            &self.predicate,
+            self.quality,
+            flush,
+        ))
+    }
+}
 
-        if should_compress {
-            parts
-                .headers
-                .append(header::VARY, header::ACCEPT_ENCODING.into());
+/// Compress the body of the given response using the given encoding,
+/// unless it is already compressed or the predicate rejects it.
+///
+/// In case a [`FlushPolicy`] is given, bodies of which the exact size is unknown
+/// are compressed using the encoding given along with it instead, which is
+/// flushed as dictated by the policy.
+#[allow(unreachable_code, unused_mut, unused_variables, unreachable_patterns)]
+pub(crate) fn compress_response<B, P>(
+    res: Response<B>,
+    encoding: Encoding,
+    predicate: &P,
+    quality: CompressionLevel,
+    flush: Option<(FlushPolicy, Encoding)>,
+) -> Response<CompressionBody<B>>
+where
+    B: Body,
+    P: Predicate,
+{
+    // only streaming bodies are flushed, other bodies are compressed as a whole
+    let (encoding, flush) = match flush {
+        Some((policy, streaming_encoding)) if res.body().size_hint().exact().is_none() => {
+            (streaming_encoding, Some(policy))
         }
+        _ => (encoding, None),
+    };
 
-        let body = match (should_compress, encoding) {
-            // if compression is _not_ supported or the client doesn't accept it
-            (false, _) | (_, Encoding::Identity) => {
-                return Ok(Response::from_parts(
-                    parts,
-                    CompressionBody::new(BodyInner::identity(body)),
-                ))
-            }
+    // never recompress responses that are already compressed
+    let should_compress = !res.headers().contains_key(header::CONTENT_ENCODING)
+        // never compress responses that are ranges
+        && !res.headers().contains_key(header::CONTENT_RANGE)
+        && predicate.should_compress(&res);
 
-            (_, Encoding::Gzip) => {
-                CompressionBody::new(BodyInner::gzip(WrapBody::new(body, self.quality)))
-            }
-            (_, Encoding::Deflate) => {
-                CompressionBody::new(BodyInner::deflate(WrapBody::new(body, self.quality)))
-            }
-            (_, Encoding::Brotli) => {
-                CompressionBody::new(BodyInner::brotli(WrapBody::new(body, self.quality)))
-            }
-            (_, Encoding::Zstd) => {
-                CompressionBody::new(BodyInner::zstd(WrapBody::new(body, self.quality)))
-            }
-            #[allow(unreachable_patterns)]
-            (true, _) => {
-                // This should never happen because the `AcceptEncoding` struct which is used to determine
-                // `self.encoding` will only enable the different compression algorithms if the
-                // corresponding crate feature has been enabled. This means
-                // Encoding::[Gzip|Brotli|Deflate] should be impossible at this point without the
-                // features enabled.
-                //
-                // The match arm is still required though because the `fs` feature uses the
-                // Encoding struct independently and requires no compression logic to be enabled.
-                // This means a combination of an individual compression feature and `fs` will fail
-                // to compile without this branch even though it will never be reached.
-                //
-                // To safeguard against refactors that changes this relationship or other bugs the
-                // server will return an uncompressed response instead of panicking since that could
-                // become a ddos attack vector.
-                return Ok(Response::from_parts(
-                    parts,
-                    CompressionBody::new(BodyInner::identity(body)),
-                ));
-            }
-        };
-
-        parts.headers.remove(header::ACCEPT_RANGES);
-        parts.headers.remove(header::CONTENT_LENGTH);
+    let (mut parts, body) = res.into_parts();
 
+    if should_compress {
         parts
             .headers
-            .insert(header::CONTENT_ENCODING, encoding.into_header_value());
-
-        let res = Response::from_parts(parts, body);
-        Ok(res)
+            .append(header::VARY, header::ACCEPT_ENCODING.into());
     }
+
+    let body = match (should_compress, encoding) {
+        // if compression is _not_ supported or the client doesn't accept it
+        (false, _) | (_, Encoding::Identity) => {
+            return Response::from_parts(parts, CompressionBody::new(BodyInner::identity(body)))
+        }
+
+        (_, Encoding::Gzip) => match flush {
+            Some(policy) => CompressionBody::new(BodyInner::streaming_gzip(
+                StreamingGzipBody::new(body, quality, policy),
+            )),
+            None => CompressionBody::new(BodyInner::gzip(WrapBody::new(body, quality))),
+        },
+        (_, Encoding::Deflate) => {
+            CompressionBody::new(BodyInner::deflate(WrapBody::new(body, quality)))
+        }
+        (_, Encoding::Brotli) => {
+            CompressionBody::new(BodyInner::brotli(WrapBody::new(body, quality)))
+        }
+        (_, Encoding::Zstd) => CompressionBody::new(BodyInner::zstd(WrapBody::new(body, quality))),
+        #[allow(unreachable_patterns)]
+        (true, _) => {
+            // This should never happen because the `AcceptEncoding` struct which is used to determine
+            // `self.encoding` will only enable the different compression algorithms if the
+            // corresponding crate feature has been enabled. This means
+            // Encoding::[Gzip|Brotli|Deflate] should be impossible at this point without the
+            // features enabled.
+            //
+            // The match arm is still required though because the `fs` feature uses the
+            // Encoding struct independently and requires no compression logic to be enabled.
+            // This means a combination of an individual compression feature and `fs` will fail
+            // to compile without this branch even though it will never be reached.
+            //
+            // To safeguard against refactors that changes this relationship or other bugs the
+            // server will return an uncompressed response instead of panicking since that could
+            // become a ddos attack vector.
+            return Response::from_parts(parts, CompressionBody::new(BodyInner::identity(body)));
+        }
+    };
+
+    parts.headers.remove(header::ACCEPT_RANGES);
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, encoding.into_header_value());
+
+    Response::from_parts(parts, body)
 }
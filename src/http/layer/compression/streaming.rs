@@ -0,0 +1,210 @@
+use crate::error::BoxError;
+use crate::http::dep::http_body::{Body, Frame};
+use crate::http::layer::util::compression::CompressionLevel;
+use crate::http::HeaderMap;
+
+use async_compression::tokio::write::GzipEncoder;
+use bytes::{Buf, Bytes};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::AsyncWrite;
+use tokio::time::Sleep;
+
+/// Policy to flush the compressed stream of streaming response bodies,
+/// such that clients receive the data written so far promptly, rather than
+/// once the encoder filled its buffers.
+///
+/// This is useful for long-lived streaming responses such as server-sent events,
+/// where the data would otherwise only reach the client once enough of it
+/// was produced to fill the buffers, or once the stream ends.
+///
+/// Each flush ends the current deflate block, which slightly reduces the compression ratio.
+/// By default the stream is flushed after each data frame of the response body.
+/// Configuring an interval or a size makes the stream be flushed instead
+/// once the oldest unflushed data is buffered for that long, or once that amount
+/// of uncompressed data is buffered, whichever comes first.
+///
+/// Only response bodies of which the exact size is unknown upfront are flushed.
+/// Only the gzip encoding supports flushing, so when a [`FlushPolicy`] is configured
+/// for the [`Compression`] middleware, these bodies are either compressed using gzip
+/// (if accepted by the client) or not at all. Note that the [`DefaultPredicate`]
+/// never compresses Server-Sent Events, see [`Compression::flush`] for how to compress them.
+///
+/// [`Compression`]: super::Compression
+/// [`Compression::flush`]: super::Compression::flush
+/// [`DefaultPredicate`]: super::DefaultPredicate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushPolicy {
+    interval: Option<Duration>,
+    size: Option<usize>,
+}
+
+impl FlushPolicy {
+    /// Create a new [`FlushPolicy`], flushing after each data frame of the response body.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flush once the oldest unflushed data is buffered for the given interval.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Flush once the given amount of uncompressed bytes is buffered.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    fn should_flush(&self, unflushed: usize) -> bool {
+        match (self.interval, self.size) {
+            (_, Some(size)) => unflushed >= size,
+            (Some(_), None) => false,
+            (None, None) => unflushed > 0,
+        }
+    }
+}
+
+pin_project! {
+    /// Response body compressed using gzip, flushed as dictated by a [`FlushPolicy`].
+    pub(crate) struct StreamingGzipBody<B> {
+        #[pin]
+        body: B,
+        encoder: GzipEncoder<Vec<u8>>,
+        policy: FlushPolicy,
+        unflushed: usize,
+        timer: Option<Pin<Box<Sleep>>>,
+        trailers: Option<HeaderMap>,
+        read_all_data: bool,
+        finished: bool,
+    }
+}
+
+impl<B> StreamingGzipBody<B> {
+    pub(crate) fn new(body: B, quality: CompressionLevel, policy: FlushPolicy) -> Self {
+        Self {
+            body,
+            encoder: GzipEncoder::with_quality(Vec::new(), quality.into_async_compression()),
+            policy,
+            unflushed: 0,
+            timer: None,
+            trailers: None,
+            read_all_data: false,
+            finished: false,
+        }
+    }
+}
+
+impl<B> Body for StreamingGzipBody<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            if *this.finished {
+                return Poll::Ready(this.trailers.take().map(|t| Ok(Frame::trailers(t))));
+            }
+
+            if *this.read_all_data {
+                ready_io(Pin::new(&mut *this.encoder).poll_shutdown(cx))?;
+                *this.finished = true;
+                if let Some(data) = take_output(this.encoder) {
+                    return Poll::Ready(Some(Ok(Frame::data(data))));
+                }
+                continue;
+            }
+
+            match this.body.as_mut().poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(mut data) => {
+                        while data.has_remaining() {
+                            let n = ready_io(
+                                Pin::new(&mut *this.encoder).poll_write(cx, data.chunk()),
+                            )?;
+                            data.advance(n);
+                            *this.unflushed += n;
+                        }
+                        if let (Some(interval), None) = (this.policy.interval, &this.timer) {
+                            if *this.unflushed > 0 {
+                                *this.timer = Some(Box::pin(tokio::time::sleep(interval)));
+                            }
+                        }
+                        if this.policy.should_flush(*this.unflushed) {
+                            if let Some(data) = flush(this.encoder, this.unflushed, this.timer, cx)?
+                            {
+                                return Poll::Ready(Some(Ok(Frame::data(data))));
+                            }
+                        } else if let Some(data) = take_output(this.encoder) {
+                            // emit the data the encoder already produced without a flush
+                            return Poll::Ready(Some(Ok(Frame::data(data))));
+                        }
+                    }
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            *this.trailers = Some(trailers);
+                        }
+                        *this.read_all_data = true;
+                    }
+                },
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+                Poll::Ready(None) => *this.read_all_data = true,
+                Poll::Pending => {
+                    let expired = match this.timer {
+                        Some(timer) => timer.as_mut().poll(cx).is_ready(),
+                        None => false,
+                    };
+                    if expired {
+                        if let Some(data) = flush(this.encoder, this.unflushed, this.timer, cx)? {
+                            return Poll::Ready(Some(Ok(Frame::data(data))));
+                        }
+                    }
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Flush the encoder, returning the compressed data written so far (if any).
+fn flush(
+    encoder: &mut GzipEncoder<Vec<u8>>,
+    unflushed: &mut usize,
+    timer: &mut Option<Pin<Box<Sleep>>>,
+    cx: &mut Context<'_>,
+) -> io::Result<Option<Bytes>> {
+    ready_io(Pin::new(&mut *encoder).poll_flush(cx))?;
+    *unflushed = 0;
+    *timer = None;
+    Ok(take_output(encoder))
+}
+
+fn take_output(encoder: &mut GzipEncoder<Vec<u8>>) -> Option<Bytes> {
+    let output = std::mem::take(encoder.get_mut());
+    (!output.is_empty()).then(|| Bytes::from(output))
+}
+
+/// The encoder writes into a `Vec`, and is therefore always ready.
+fn ready_io<T>(poll: Poll<io::Result<T>>) -> io::Result<T> {
+    match poll {
+        Poll::Ready(result) => result,
+        Poll::Pending => Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "in-memory gzip encoder is not ready",
+        )),
+    }
+}
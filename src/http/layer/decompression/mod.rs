@@ -99,6 +99,7 @@ mod body;
 mod layer;
 mod service;
 
+pub(crate) use self::service::decompress_response;
 pub use self::{body::DecompressionBody, layer::DecompressionLayer, service::Decompression};
 
 pub use self::request::layer::RequestDecompressionLayer;
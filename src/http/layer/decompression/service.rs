@@ -113,44 +113,55 @@ where
         }
 
         let res = self.inner.serve(ctx, req).await?;
+        Ok(decompress_response(self.accept, res))
+    }
+}
 
-        let (mut parts, body) = res.into_parts();
-
-        let res =
-            if let header::Entry::Occupied(entry) = parts.headers.entry(header::CONTENT_ENCODING) {
-                let body = match entry.get().as_bytes() {
-                    b"gzip" if self.accept.gzip() => DecompressionBody::new(BodyInner::gzip(
-                        WrapBody::new(body, CompressionLevel::default()),
-                    )),
-
-                    b"deflate" if self.accept.deflate() => DecompressionBody::new(
-                        BodyInner::deflate(WrapBody::new(body, CompressionLevel::default())),
-                    ),
-
-                    b"br" if self.accept.br() => DecompressionBody::new(BodyInner::brotli(
-                        WrapBody::new(body, CompressionLevel::default()),
-                    )),
-
-                    b"zstd" if self.accept.zstd() => DecompressionBody::new(BodyInner::zstd(
-                        WrapBody::new(body, CompressionLevel::default()),
-                    )),
-
-                    _ => {
-                        return Ok(Response::from_parts(
-                            parts,
-                            DecompressionBody::new(BodyInner::identity(body)),
-                        ))
-                    }
-                };
-
-                entry.remove();
-                parts.headers.remove(header::CONTENT_LENGTH);
+/// Decompress the body of the given response, in case it is encoded
+/// using one of the accepted encodings.
+pub(crate) fn decompress_response<B>(
+    accept: AcceptEncoding,
+    res: Response<B>,
+) -> Response<DecompressionBody<B>>
+where
+    B: Body,
+{
+    let (mut parts, body) = res.into_parts();
+
+    if let header::Entry::Occupied(entry) = parts.headers.entry(header::CONTENT_ENCODING) {
+        let body = match entry.get().as_bytes() {
+            b"gzip" if accept.gzip() => DecompressionBody::new(BodyInner::gzip(WrapBody::new(
+                body,
+                CompressionLevel::default(),
+            ))),
+
+            b"deflate" if accept.deflate() => DecompressionBody::new(BodyInner::deflate(
+                WrapBody::new(body, CompressionLevel::default()),
+            )),
+
+            b"br" if accept.br() => DecompressionBody::new(BodyInner::brotli(WrapBody::new(
+                body,
+                CompressionLevel::default(),
+            ))),
+
+            b"zstd" if accept.zstd() => DecompressionBody::new(BodyInner::zstd(WrapBody::new(
+                body,
+                CompressionLevel::default(),
+            ))),
+
+            _ => {
+                return Response::from_parts(
+                    parts,
+                    DecompressionBody::new(BodyInner::identity(body)),
+                )
+            }
+        };
 
-                Response::from_parts(parts, body)
-            } else {
-                Response::from_parts(parts, DecompressionBody::new(BodyInner::identity(body)))
-            };
+        entry.remove();
+        parts.headers.remove(header::CONTENT_LENGTH);
 
-        Ok(res)
+        Response::from_parts(parts, body)
+    } else {
+        Response::from_parts(parts, DecompressionBody::new(BodyInner::identity(body)))
     }
 }
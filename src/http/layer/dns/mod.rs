@@ -48,3 +48,11 @@ pub use service::{DnsResolvedSocketAddresses, DnsService};
 
 mod layer;
 pub use layer::DnsLayer;
+
+pub(crate) mod reverse;
+pub use reverse::{ReverseDnsLayer, ReverseDnsName, ReverseDnsResolver, ReverseDnsService};
+
+#[cfg(feature = "secure-dns")]
+mod secure;
+#[cfg(feature = "secure-dns")]
+pub use secure::{DnsUpstream, DohUpstream, DotUpstream, SecureDnsResolver};
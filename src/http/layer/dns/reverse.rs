@@ -0,0 +1,603 @@
+use super::DynamicDnsResolver;
+use crate::{
+    http::utils::LruMap,
+    service::{Context, Layer, Service},
+    stream::SocketInfo,
+};
+use std::{
+    future::Future,
+    io,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// The default time to wait for the verification of the name of a peer.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The default time for which the (verified) name of a peer is cached.
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// The default amount of peers of which the (verified) name is cached.
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// An implementation of [`ReverseDnsResolver`] is used to resolve an IP address
+/// to the names found in its PTR records at runtime.
+///
+/// The [`DynamicDnsResolver`] supertrait is used to resolve those names back to addresses,
+/// as required for the forward-confirmed reverse DNS verification of the [`ReverseDnsService`].
+///
+/// It is implemented by the [`SecureDnsResolver`], as well as by `()`,
+/// which resolves no address to any name.
+///
+/// [`SecureDnsResolver`]: crate::http::layer::dns::SecureDnsResolver
+pub trait ReverseDnsResolver: DynamicDnsResolver {
+    /// Resolve the given address to the names found in its PTR records.
+    fn lookup_ptr(
+        &self,
+        ip: IpAddr,
+    ) -> impl Future<Output = Result<Vec<String>, io::Error>> + Send + '_;
+}
+
+impl ReverseDnsResolver for () {
+    async fn lookup_ptr(&self, _ip: IpAddr) -> Result<Vec<String>, io::Error> {
+        Ok(Vec::new())
+    }
+}
+
+/// State that is added to the [`Context`] by the [`ReverseDnsService`],
+/// containing the verified reverse DNS name of the peer.
+///
+/// The name is lowercased and does not contain a trailing dot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReverseDnsName(String);
+
+impl ReverseDnsName {
+    /// Create a new [`ReverseDnsName`] from the given (verified) name.
+    pub fn new(name: impl AsRef<str>) -> Self {
+        Self(normalize(name.as_ref()))
+    }
+
+    /// The verified reverse DNS name of the peer.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The configuration of a [`ReverseDnsService`], together with its cache,
+/// shared by the services created by the same [`ReverseDnsLayer`].
+#[derive(Debug)]
+struct Config {
+    timeout: Duration,
+    cache_ttl: Duration,
+    cache_capacity: usize,
+    cache: Mutex<NameCache>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            cache: Mutex::new(NameCache::default()),
+        }
+    }
+}
+
+impl Config {
+    fn set_timeout(self: &mut Arc<Self>, timeout: Duration) {
+        self.configure(|config| config.timeout = timeout);
+    }
+
+    fn set_cache_ttl(self: &mut Arc<Self>, ttl: Duration) {
+        self.configure(|config| config.cache_ttl = ttl);
+    }
+
+    fn set_cache_capacity(self: &mut Arc<Self>, capacity: usize) {
+        self.configure(|config| config.cache_capacity = capacity);
+    }
+
+    /// Update the configuration, starting with an empty cache
+    /// which is no longer shared with the services created before.
+    fn configure(self: &mut Arc<Self>, f: impl FnOnce(&mut Self)) {
+        let mut config = Self {
+            timeout: self.timeout,
+            cache_ttl: self.cache_ttl,
+            cache_capacity: self.cache_capacity,
+            cache: Mutex::new(NameCache::default()),
+        };
+        f(&mut config);
+        *self = Arc::new(config);
+    }
+
+    fn cached(&self, ip: IpAddr) -> Option<CachedName> {
+        self.cache.lock().unwrap().get(ip)
+    }
+
+    fn cache(&self, ip: IpAddr, name: Option<ReverseDnsName>) {
+        if self.cache_ttl.is_zero() || self.cache_capacity == 0 {
+            return;
+        }
+        self.cache.lock().unwrap().insert(
+            ip,
+            name,
+            Instant::now() + self.cache_ttl,
+            self.cache_capacity,
+        );
+    }
+}
+
+/// A cache of the (verified) names of peers,
+/// evicting the least recently used peer once full.
+#[derive(Debug, Default)]
+struct NameCache(LruMap<IpAddr, CacheEntry>);
+
+/// The cached outcome of the verification of a peer,
+/// which is `None` in case no name could be verified.
+#[derive(Debug)]
+struct CachedName(Option<ReverseDnsName>);
+
+#[derive(Debug)]
+struct CacheEntry {
+    name: Option<ReverseDnsName>,
+    expires_at: Instant,
+}
+
+impl NameCache {
+    fn get(&mut self, ip: IpAddr) -> Option<CachedName> {
+        let entry = self.0.get(&ip)?;
+        if entry.expires_at <= Instant::now() {
+            self.0.remove(&ip);
+            return None;
+        }
+        Some(CachedName(entry.name.clone()))
+    }
+
+    fn insert(
+        &mut self,
+        ip: IpAddr,
+        name: Option<ReverseDnsName>,
+        expires_at: Instant,
+        capacity: usize,
+    ) {
+        self.0.insert(ip, CacheEntry { name, expires_at }, capacity);
+    }
+}
+
+/// A [`Layer`] which wraps the given service with a [`ReverseDnsService`].
+///
+/// The services created by the same layer share their cache.
+#[derive(Debug, Clone)]
+pub struct ReverseDnsLayer<R> {
+    resolver: R,
+    config: Arc<Config>,
+}
+
+impl<R> ReverseDnsLayer<R> {
+    /// Create a new [`ReverseDnsLayer`] using the given resolver.
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    /// Wait at most the given duration for the verification of the name of a peer,
+    /// instead of the default 2 seconds.
+    ///
+    /// See [`ReverseDnsService::timeout`] for more details.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.set_timeout(timeout);
+        self
+    }
+
+    /// Cache the (verified) name of a peer for the given duration,
+    /// instead of the default 5 minutes. A duration of zero disables caching.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.set_cache_ttl(ttl);
+        self
+    }
+
+    /// Cache the (verified) names of at most the given amount of peers,
+    /// instead of the default 1024 peers. A capacity of `0` disables caching.
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.set_cache_capacity(capacity);
+        self
+    }
+}
+
+impl<S, R: Clone> Layer<S> for ReverseDnsLayer<R> {
+    type Service = ReverseDnsService<S, R>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReverseDnsService {
+            inner,
+            resolver: self.resolver.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// [`Service`] which resolves the reverse DNS name of the peer,
+/// and adds it as a [`ReverseDnsName`] to the [`Context`] once verified.
+///
+/// The peer address is taken from the [`SocketInfo`] found in the [`Context`].
+/// As anyone controlling the reverse DNS zone of an address can make its PTR record
+/// point to any name (e.g. `crawl.googlebot.com`), the name is only added in case
+/// it is forward-confirmed, i.e. in case it resolves back to the address of the peer.
+///
+/// No name is added in case the peer is unknown, has no PTR records, or none of its names
+/// could be verified. The request is served by the inner service in all cases,
+/// use the [`ReverseDnsFilter`] to act on the name.
+///
+/// The verification of a peer is limited in time (see [`ReverseDnsService::timeout`]),
+/// and its outcome is cached per address (see [`ReverseDnsService::cache_ttl`]),
+/// such that the requests of a peer do not trigger new lookups each time.
+///
+/// [`ReverseDnsFilter`]: crate::http::matcher::ReverseDnsFilter
+#[derive(Debug, Clone)]
+pub struct ReverseDnsService<S, R> {
+    inner: S,
+    resolver: R,
+    config: Arc<Config>,
+}
+
+impl<S, R> ReverseDnsService<S, R> {
+    /// Create a new [`ReverseDnsService`] using the given resolver.
+    pub fn new(inner: S, resolver: R) -> Self {
+        Self {
+            inner,
+            resolver,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    /// Wait at most the given duration for the verification of the name of a peer,
+    /// instead of the default 2 seconds.
+    ///
+    /// In case the verification does not complete in time, no name is added,
+    /// and the outcome is not cached, such that it is tried again for the next request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.set_timeout(timeout);
+        self
+    }
+
+    /// Cache the (verified) name of a peer for the given duration,
+    /// instead of the default 5 minutes. A duration of zero disables caching.
+    ///
+    /// Peers of which no name could be verified are cached as well.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.set_cache_ttl(ttl);
+        self
+    }
+
+    /// Cache the (verified) names of at most the given amount of peers,
+    /// instead of the default 1024 peers. A capacity of `0` disables caching.
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.set_cache_capacity(capacity);
+        self
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<S, R> ReverseDnsService<S, R>
+where
+    R: ReverseDnsResolver,
+{
+    /// Resolve the (cached) name of the given address,
+    /// limiting the verification of a name which is not cached to the configured timeout.
+    async fn name(&self, ip: IpAddr) -> Option<ReverseDnsName> {
+        if let Some(CachedName(name)) = self.config.cached(ip) {
+            return name;
+        }
+        match tokio::time::timeout(self.config.timeout, self.verified_name(ip)).await {
+            Ok(Ok(name)) => {
+                self.config.cache(ip, name.clone());
+                name
+            }
+            Ok(Err(err)) => {
+                tracing::trace!(error = %err, %ip, "reverse dns: PTR lookup failed");
+                None
+            }
+            Err(_) => {
+                tracing::trace!(%ip, "reverse dns: verification timed out");
+                None
+            }
+        }
+    }
+
+    /// Resolve the first name of the given address which resolves back to it.
+    ///
+    /// Fails only in case the PTR records of the address could not be resolved.
+    async fn verified_name(&self, ip: IpAddr) -> io::Result<Option<ReverseDnsName>> {
+        let names = self.resolver.lookup_ptr(ip).await?;
+        for name in names {
+            let name = normalize(&name);
+            match self.resolver.lookup_host(name.clone()).await {
+                Ok(mut addresses) => {
+                    if addresses.any(|addr| addr.ip().to_canonical() == ip) {
+                        return Ok(Some(ReverseDnsName(name)));
+                    }
+                    tracing::trace!(%ip, name, "reverse dns: name does not resolve to peer");
+                }
+                Err(err) => {
+                    tracing::trace!(error = %err, %ip, name, "reverse dns: forward lookup failed");
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<State, Request, S, R> Service<State, Request> for ReverseDnsService<S, R>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+    R: ReverseDnsResolver,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let ip = ctx
+            .get::<SocketInfo>()
+            .map(|info| info.peer_addr().ip().to_canonical());
+        if let Some(ip) = ip {
+            if let Some(name) = self.name(ip).await {
+                ctx.insert(name);
+            }
+        }
+        self.inner.serve(ctx, req).await
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::{
+        collections::HashMap,
+        convert::Infallible,
+        net::SocketAddr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A resolver using static PTR and A/AAAA records.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct MockResolver {
+        ptr: Arc<HashMap<IpAddr, Vec<String>>>,
+        hosts: Arc<HashMap<String, Vec<IpAddr>>>,
+        delay: Option<Duration>,
+        ptr_lookups: Arc<AtomicUsize>,
+    }
+
+    impl MockResolver {
+        pub(crate) fn new(ptr: &[(&str, &[&str])], hosts: &[(&str, &[&str])]) -> Self {
+            Self {
+                ptr: Arc::new(
+                    ptr.iter()
+                        .map(|(ip, names)| {
+                            (
+                                ip.parse().unwrap(),
+                                names.iter().map(|name| (*name).to_owned()).collect(),
+                            )
+                        })
+                        .collect(),
+                ),
+                hosts: Arc::new(
+                    hosts
+                        .iter()
+                        .map(|(name, ips)| {
+                            (
+                                (*name).to_owned(),
+                                ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+                            )
+                        })
+                        .collect(),
+                ),
+                delay: None,
+                ptr_lookups: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        /// Delay each PTR lookup with the given duration.
+        pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = Some(delay);
+            self
+        }
+
+        /// The amount of PTR lookups done so far.
+        pub(crate) fn ptr_lookups(&self) -> usize {
+            self.ptr_lookups.load(Ordering::SeqCst)
+        }
+    }
+
+    impl DynamicDnsResolver for MockResolver {
+        type Iterator = std::vec::IntoIter<SocketAddr>;
+
+        async fn lookup_host(&self, host: String) -> Result<Self::Iterator, io::Error> {
+            let ips = self.hosts.get(&host).ok_or(io::ErrorKind::NotFound)?;
+            Ok(ips
+                .iter()
+                .map(|ip| SocketAddr::new(*ip, 0))
+                .collect::<Vec<_>>()
+                .into_iter())
+        }
+    }
+
+    impl ReverseDnsResolver for MockResolver {
+        async fn lookup_ptr(&self, ip: IpAddr) -> Result<Vec<String>, io::Error> {
+            self.ptr_lookups.fetch_add(1, Ordering::SeqCst);
+            if let Some(delay) = self.delay {
+                tokio::time::sleep(delay).await;
+            }
+            Ok(self.ptr.get(&ip).cloned().unwrap_or_default())
+        }
+    }
+
+    async fn resolve(resolver: MockResolver, peer: &str) -> Option<ReverseDnsName> {
+        resolve_with(&ReverseDnsLayer::new(resolver), peer).await
+    }
+
+    async fn resolve_with(
+        layer: &ReverseDnsLayer<MockResolver>,
+        peer: &str,
+    ) -> Option<ReverseDnsName> {
+        let service = layer.layer(service_fn(|ctx: Context<()>, _: ()| async move {
+            Ok::<_, Infallible>(ctx.get::<ReverseDnsName>().cloned())
+        }));
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, peer.parse().unwrap()));
+        service.serve(ctx, ()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reverse_dns_verified() {
+        let resolver = MockResolver::new(
+            &[
+                ("66.249.66.1", &["crawl-66-249-66-1.googlebot.com."]),
+                ("2001:db8::1", &["Host.Example.com"]),
+            ],
+            &[
+                ("crawl-66-249-66-1.googlebot.com", &["66.249.66.1"]),
+                ("host.example.com", &["192.0.2.1", "2001:db8::1"]),
+            ],
+        );
+
+        assert_eq!(
+            resolve(resolver.clone(), "66.249.66.1:1234").await,
+            Some(ReverseDnsName::new("crawl-66-249-66-1.googlebot.com"))
+        );
+        // ipv4-mapped ipv6 peers are resolved as ipv4
+        assert_eq!(
+            resolve(resolver.clone(), "[::ffff:66.249.66.1]:1234").await,
+            Some(ReverseDnsName::new("crawl-66-249-66-1.googlebot.com"))
+        );
+        assert_eq!(
+            resolve(resolver, "[2001:db8::1]:1234")
+                .await
+                .unwrap()
+                .name(),
+            "host.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverse_dns_not_verified() {
+        let resolver = MockResolver::new(
+            &[
+                // spoofed: the name does not resolve back to the peer
+                ("203.0.113.7", &["crawl-203-0-113-7.googlebot.com"]),
+                // the name does not resolve at all
+                ("203.0.113.8", &["unknown.example.com"]),
+                // only the second name is verified
+                ("203.0.113.9", &["fake.googlebot.com", "real.example.com"]),
+            ],
+            &[
+                ("crawl-203-0-113-7.googlebot.com", &["66.249.66.7"]),
+                ("fake.googlebot.com", &["66.249.66.9"]),
+                ("real.example.com", &["203.0.113.9"]),
+            ],
+        );
+
+        assert_eq!(resolve(resolver.clone(), "203.0.113.7:1234").await, None);
+        assert_eq!(resolve(resolver.clone(), "203.0.113.8:1234").await, None);
+        // no PTR records
+        assert_eq!(resolve(resolver.clone(), "203.0.113.10:1234").await, None);
+        assert_eq!(
+            resolve(resolver, "203.0.113.9:1234").await.unwrap().name(),
+            "real.example.com"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reverse_dns_cache() {
+        let resolver = MockResolver::new(
+            &[("192.0.2.1", &["host.example.com"])],
+            &[("host.example.com", &["192.0.2.1"])],
+        );
+        let layer = ReverseDnsLayer::new(resolver.clone()).cache_ttl(Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert_eq!(
+                resolve_with(&layer, "192.0.2.1:1234").await,
+                Some(ReverseDnsName::new("host.example.com"))
+            );
+        }
+        assert_eq!(resolver.ptr_lookups(), 1);
+
+        // peers without a verified name are cached as well
+        assert_eq!(resolve_with(&layer, "192.0.2.2:1234").await, None);
+        assert_eq!(resolve_with(&layer, "192.0.2.2:1234").await, None);
+        assert_eq!(resolver.ptr_lookups(), 2);
+
+        // expired names are resolved again
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert_eq!(
+            resolve_with(&layer, "192.0.2.1:1234").await,
+            Some(ReverseDnsName::new("host.example.com"))
+        );
+        assert_eq!(resolver.ptr_lookups(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_dns_cache_capacity() {
+        let resolver = MockResolver::default();
+        let layer = ReverseDnsLayer::new(resolver.clone()).cache_capacity(2);
+
+        resolve_with(&layer, "192.0.2.1:1234").await;
+        resolve_with(&layer, "192.0.2.2:1234").await;
+        // mark the first peer as recently used
+        resolve_with(&layer, "192.0.2.1:1234").await;
+        assert_eq!(resolver.ptr_lookups(), 2);
+
+        // evicts the second peer
+        resolve_with(&layer, "192.0.2.3:1234").await;
+        resolve_with(&layer, "192.0.2.1:1234").await;
+        assert_eq!(resolver.ptr_lookups(), 3);
+        resolve_with(&layer, "192.0.2.2:1234").await;
+        assert_eq!(resolver.ptr_lookups(), 4);
+
+        // caching disabled
+        let layer = ReverseDnsLayer::new(resolver.clone()).cache_ttl(Duration::ZERO);
+        resolve_with(&layer, "192.0.2.1:1234").await;
+        resolve_with(&layer, "192.0.2.1:1234").await;
+        assert_eq!(resolver.ptr_lookups(), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reverse_dns_timeout() {
+        let resolver = MockResolver::new(
+            &[("192.0.2.1", &["host.example.com"])],
+            &[("host.example.com", &["192.0.2.1"])],
+        )
+        .with_delay(Duration::from_secs(5));
+
+        let layer = ReverseDnsLayer::new(resolver.clone());
+        let start = tokio::time::Instant::now();
+        assert_eq!(resolve_with(&layer, "192.0.2.1:1234").await, None);
+        assert_eq!(start.elapsed(), DEFAULT_TIMEOUT);
+
+        // timeouts are not cached
+        assert_eq!(resolve_with(&layer, "192.0.2.1:1234").await, None);
+        assert_eq!(resolver.ptr_lookups(), 2);
+
+        let layer = ReverseDnsLayer::new(resolver).timeout(Duration::from_secs(10));
+        assert_eq!(
+            resolve_with(&layer, "192.0.2.1:1234").await,
+            Some(ReverseDnsName::new("host.example.com"))
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Minimal DNS wire format (RFC 1035) support,
+//! limited to what is needed to resolve the A and AAAA records of a hostname,
+//! and the PTR records of an address.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const CLASS_IN: u16 = 1;
+
+const RCODE_NO_ERROR: u16 = 0;
+const RCODE_NX_DOMAIN: u16 = 3;
+
+/// The record type to query for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordType {
+    A,
+    Aaaa,
+    Ptr,
+}
+
+impl RecordType {
+    fn code(self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::Aaaa => 28,
+            RecordType::Ptr => 12,
+        }
+    }
+}
+
+/// The addresses found in a DNS response,
+/// together with the TTL for which they can be cached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Answer {
+    pub(crate) addresses: Vec<IpAddr>,
+    pub(crate) ttl: Duration,
+}
+
+/// The name under which the PTR records of the given address are found,
+/// e.g. `1.2.0.192.in-addr.arpa` for `192.0.2.1`.
+pub(crate) fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Encode a recursive query for the given record type of the given hostname.
+pub(crate) fn encode_query(id: u16, host: &str, record_type: RecordType) -> io::Result<Vec<u8>> {
+    let host = host.trim_end_matches('.');
+    if host.is_empty() || host.len() > 253 {
+        return Err(invalid_input(host));
+    }
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + host.len() + 6);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&FLAG_RECURSION_DESIRED.to_be_bytes());
+    buf.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]); // 1 question, no other records
+
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_input(host));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+
+    buf.extend_from_slice(&record_type.code().to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+/// Decode the response to a query created using [`encode_query`].
+///
+/// Only the records of the queried type are returned, all other records
+/// (e.g. CNAME records which lead to them) are skipped. The TTL of the answer
+/// is the smallest TTL of the returned records.
+///
+/// A non-existent domain results in an [`io::ErrorKind::NotFound`] error,
+/// while all other server failures result in an [`io::ErrorKind::Other`] error.
+pub(crate) fn decode_response(id: u16, record_type: RecordType, msg: &[u8]) -> io::Result<Answer> {
+    let mut addresses = Vec::new();
+    let ttl = decode_records(id, record_type, msg, |_, data| {
+        let address = match (record_type, data.len()) {
+            (RecordType::A, 4) => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            (RecordType::Aaaa, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(invalid_data("invalid address record length")),
+        };
+        addresses.push(address);
+        Ok(())
+    })?;
+
+    Ok(Answer {
+        addresses,
+        ttl: Duration::from_secs(ttl.unwrap_or_default() as u64),
+    })
+}
+
+/// Decode the response to a PTR query created using [`encode_query`],
+/// returning the names found in its PTR records.
+///
+/// Errors are returned in the same way as for [`decode_response`].
+pub(crate) fn decode_ptr_response(id: u16, msg: &[u8]) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    decode_records(id, RecordType::Ptr, msg, |pos, _| {
+        names.push(read_name(msg, pos)?);
+        Ok(())
+    })?;
+    Ok(names)
+}
+
+/// Decode the records of the given type found in the given response,
+/// passing the position and data of each record to the given function.
+///
+/// Returns the smallest TTL of these records, if any.
+fn decode_records<F>(
+    id: u16,
+    record_type: RecordType,
+    msg: &[u8],
+    mut f: F,
+) -> io::Result<Option<u32>>
+where
+    F: FnMut(usize, &[u8]) -> io::Result<()>,
+{
+    let mut reader = Reader { msg, pos: 0 };
+
+    if reader.u16()? != id {
+        return Err(invalid_data("response id does not match query id"));
+    }
+    let flags = reader.u16()?;
+    if flags & FLAG_RESPONSE == 0 {
+        return Err(invalid_data("message is not a response"));
+    }
+    match flags & 0x000f {
+        RCODE_NO_ERROR => (),
+        RCODE_NX_DOMAIN => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "dns: non-existent domain",
+            ))
+        }
+        rcode => {
+            return Err(io::Error::other(format!(
+                "dns: server responded with error code {}",
+                rcode
+            )))
+        }
+    }
+    let question_count = reader.u16()?;
+    let answer_count = reader.u16()?;
+    reader.skip(4)?; // authority and additional records are ignored
+
+    for _ in 0..question_count {
+        reader.skip_name()?;
+        reader.skip(4)?;
+    }
+
+    let mut ttl = None;
+    for _ in 0..answer_count {
+        reader.skip_name()?;
+        let rtype = reader.u16()?;
+        let class = reader.u16()?;
+        let record_ttl = reader.u32()?;
+        let len = reader.u16()? as usize;
+        let pos = reader.pos;
+        let data = reader.take(len)?;
+
+        if class != CLASS_IN || rtype != record_type.code() {
+            continue;
+        }
+        f(pos, data)?;
+        ttl = Some(ttl.map_or(record_ttl, |ttl: u32| ttl.min(record_ttl)));
+    }
+    Ok(ttl)
+}
+
+/// Read the (possibly compressed) domain name found at the given position of the message.
+fn read_name(msg: &[u8], mut pos: usize) -> io::Result<String> {
+    // limit the amount of pointers followed, as these could form a loop
+    const MAX_POINTERS: usize = 16;
+
+    let truncated = || invalid_data("message is truncated");
+    let mut labels = Vec::new();
+    let mut pointers = 0;
+    loop {
+        let len = *msg.get(pos).ok_or_else(truncated)?;
+        match len & 0xc0 {
+            0xc0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(invalid_data("too many compression pointers"));
+                }
+                let low = *msg.get(pos + 1).ok_or_else(truncated)?;
+                pos = ((len as usize & 0x3f) << 8) | low as usize;
+            }
+            0x00 if len == 0 => return Ok(labels.join(".")),
+            0x00 => {
+                let label = msg
+                    .get(pos + 1..pos + 1 + len as usize)
+                    .ok_or_else(truncated)?;
+                let label =
+                    std::str::from_utf8(label).map_err(|_| invalid_data("invalid label"))?;
+                labels.push(label);
+                pos += 1 + len as usize;
+            }
+            _ => return Err(invalid_data("invalid label")),
+        }
+    }
+}
+
+struct Reader<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .msg
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid_data("message is truncated"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> io::Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Skip a (possibly compressed) domain name.
+    fn skip_name(&mut self) -> io::Result<()> {
+        loop {
+            let len = self.u8()?;
+            match len & 0xc0 {
+                // a pointer always ends the name
+                0xc0 => return self.skip(1),
+                0x00 if len == 0 => return Ok(()),
+                0x00 => self.skip(len as usize)?,
+                _ => return Err(invalid_data("invalid label")),
+            }
+        }
+    }
+}
+
+fn invalid_input(host: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("dns: invalid hostname: {}", host),
+    )
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("dns: {}", msg))
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    /// Create the response to the given query, answering with the given addresses,
+    /// each with the given TTL (in seconds). The addresses which do not match
+    /// the queried record type are answered as well, to ensure they are skipped.
+    pub(crate) fn response(query: &[u8], addresses: &[IpAddr], ttl: u32) -> Vec<u8> {
+        let mut msg = query.to_vec();
+        msg[2] |= 0x80; // response flag
+        msg[6..8].copy_from_slice(&(addresses.len() as u16 + 1).to_be_bytes());
+
+        // a CNAME record which is to be skipped, pointing to the queried name
+        msg.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1]);
+        msg.extend_from_slice(&ttl.to_be_bytes());
+        msg.extend_from_slice(&[0, 2, 0xc0, 12]);
+
+        for address in addresses {
+            let (rtype, data) = match address {
+                IpAddr::V4(addr) => (RecordType::A, addr.octets().to_vec()),
+                IpAddr::V6(addr) => (RecordType::Aaaa, addr.octets().to_vec()),
+            };
+            msg.extend_from_slice(&[0xc0, 12]);
+            msg.extend_from_slice(&rtype.code().to_be_bytes());
+            msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+            msg.extend_from_slice(&ttl.to_be_bytes());
+            msg.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            msg.extend_from_slice(&data);
+        }
+        msg
+    }
+
+    /// Create the response to the given PTR query, answering with the given names.
+    /// The second (and later) names are compressed, as their first label followed by
+    /// a pointer to the remainder of the first name (i.e. the first name without its first label).
+    pub(crate) fn ptr_response(query: &[u8], names: &[&str], ttl: u32) -> Vec<u8> {
+        let mut msg = query.to_vec();
+        msg[2] |= 0x80; // response flag
+        msg[6..8].copy_from_slice(&(names.len() as u16).to_be_bytes());
+
+        let mut first = None;
+        for name in names {
+            let mut data = Vec::new();
+            match first {
+                None => {
+                    // the remainder of the first name starts after the 12 bytes of
+                    // the header of the record, and the first label
+                    let (label, _) = name.split_once('.').unwrap();
+                    first = Some(msg.len() + 12 + 1 + label.len());
+                    for label in name.split('.') {
+                        data.push(label.len() as u8);
+                        data.extend_from_slice(label.as_bytes());
+                    }
+                    data.push(0);
+                }
+                Some(first) => {
+                    let (label, _) = name.split_once('.').unwrap();
+                    data.push(label.len() as u8);
+                    data.extend_from_slice(label.as_bytes());
+                    data.extend_from_slice(&[0xc0 | (first >> 8) as u8, first as u8]);
+                }
+            }
+            msg.extend_from_slice(&[0xc0, 12]);
+            msg.extend_from_slice(&RecordType::Ptr.code().to_be_bytes());
+            msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+            msg.extend_from_slice(&ttl.to_be_bytes());
+            msg.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            msg.extend_from_slice(&data);
+        }
+        msg
+    }
+
+    /// Create an error response with the given response code to the given query.
+    pub(crate) fn error_response(query: &[u8], rcode: u8) -> Vec<u8> {
+        let mut msg = query.to_vec();
+        msg[2] |= 0x80;
+        msg[3] |= rcode;
+        msg
+    }
+
+    /// Returns the record type and hostname of the given query.
+    pub(crate) fn parse_query(query: &[u8]) -> (RecordType, String) {
+        let mut labels = Vec::new();
+        let mut pos = HEADER_LEN;
+        while query[pos] != 0 {
+            let len = query[pos] as usize;
+            labels.push(std::str::from_utf8(&query[pos + 1..pos + 1 + len]).unwrap());
+            pos += len + 1;
+        }
+        let rtype = match u16::from_be_bytes([query[pos + 1], query[pos + 2]]) {
+            1 => RecordType::A,
+            28 => RecordType::Aaaa,
+            12 => RecordType::Ptr,
+            code => panic!("unexpected record type: {}", code),
+        };
+        (rtype, labels.join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_util::*, *};
+
+    #[test]
+    fn test_encode_query() {
+        let query = encode_query(0x1234, "www.example.com.", RecordType::Aaaa).unwrap();
+        assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(
+            parse_query(&query),
+            (RecordType::Aaaa, "www.example.com".to_owned())
+        );
+
+        assert!(encode_query(0, "", RecordType::A).is_err());
+        assert!(encode_query(0, "www..example.com", RecordType::A).is_err());
+        assert!(encode_query(0, &"a".repeat(64), RecordType::A).is_err());
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let query = encode_query(7, "example.com", RecordType::A).unwrap();
+        let addresses: Vec<IpAddr> = vec![
+            "127.0.0.1".parse().unwrap(),
+            "127.0.0.2".parse().unwrap(),
+            "::1".parse().unwrap(),
+        ];
+        let answer = decode_response(7, RecordType::A, &response(&query, &addresses, 60)).unwrap();
+        assert_eq!(
+            answer,
+            Answer {
+                addresses: addresses[..2].to_vec(),
+                ttl: Duration::from_secs(60),
+            }
+        );
+
+        let answer =
+            decode_response(7, RecordType::Aaaa, &response(&query, &addresses, 30)).unwrap();
+        assert_eq!(answer.addresses, addresses[2..].to_vec());
+        assert_eq!(answer.ttl, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name("192.0.2.1".parse().unwrap()),
+            "1.2.0.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::567:89ab".parse().unwrap()),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn test_decode_ptr_response() {
+        let name = reverse_name("66.249.66.1".parse().unwrap());
+        let query = encode_query(7, &name, RecordType::Ptr).unwrap();
+        assert_eq!(parse_query(&query), (RecordType::Ptr, name));
+
+        let msg = ptr_response(
+            &query,
+            &["crawl-66-249-66-1.googlebot.com", "other.googlebot.com"],
+            60,
+        );
+        assert_eq!(
+            decode_ptr_response(7, &msg).unwrap(),
+            vec!["crawl-66-249-66-1.googlebot.com", "other.googlebot.com"]
+        );
+        assert!(decode_ptr_response(7, &msg[..msg.len() - 1]).is_err());
+
+        // a compression pointer loop, replacing the end of the name
+        // (of 15 bytes) with a pointer to itself
+        let mut msg = ptr_response(&query, &["a.example.com"], 60);
+        let end = msg.len();
+        msg[end - 1] = 0xc0;
+        msg.push((end - 1) as u8);
+        msg[end - 17..end - 15].copy_from_slice(&16u16.to_be_bytes());
+        assert!(decode_ptr_response(7, &msg).is_err());
+    }
+
+    #[test]
+    fn test_decode_response_errors() {
+        let query = encode_query(7, "example.com", RecordType::A).unwrap();
+        let msg = response(&query, &["127.0.0.1".parse().unwrap()], 60);
+
+        // id mismatch, not a response and truncated message
+        assert!(decode_response(8, RecordType::A, &msg).is_err());
+        assert!(decode_response(7, RecordType::A, &query).is_err());
+        assert!(decode_response(7, RecordType::A, &msg[..msg.len() - 1]).is_err());
+
+        let err = decode_response(7, RecordType::A, &error_response(&query, 3)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = decode_response(7, RecordType::A, &error_response(&query, 2)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+}
@@ -0,0 +1,13 @@
+//! Encrypted DNS resolution, using DNS over HTTPS (DoH) or DNS over TLS (DoT).
+//!
+//! See [`SecureDnsResolver`] for more information.
+
+mod message;
+
+mod upstream;
+#[doc(inline)]
+pub use upstream::{DnsUpstream, DohUpstream, DotUpstream};
+
+mod resolver;
+#[doc(inline)]
+pub use resolver::SecureDnsResolver;
@@ -0,0 +1,680 @@
+use super::{
+    message::{
+        decode_ptr_response, decode_response, encode_query, reverse_name, Answer, RecordType,
+    },
+    DnsUpstream,
+};
+use crate::http::{
+    layer::dns::{DynamicDnsResolver, ReverseDnsResolver},
+    utils::LruMap,
+};
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// A [`DynamicDnsResolver`] which resolves hostnames using one or multiple
+/// [`DnsUpstream`] servers, such as a [`DohUpstream`] or [`DotUpstream`].
+///
+/// The upstream servers are tried in order: in case an upstream fails, or does not answer
+/// within the [timeout](SecureDnsResolver::timeout), the next one is tried.
+/// A non-existent domain is however a definitive answer, and is returned as an
+/// [`io::ErrorKind::NotFound`] error without trying the next upstream.
+/// Likewise, a domain without any A or AAAA records resolves to an empty list of addresses.
+///
+/// Resolved addresses are cached for the TTL of their records,
+/// optionally capped using [`SecureDnsResolver::max_ttl`]. Once the cache holds
+/// the addresses of [`SecureDnsResolver::cache_capacity`] hosts, the least recently used host is evicted.
+///
+/// The host to resolve can optionally contain a port (e.g. `example.com:443`),
+/// which is used for the resolved addresses. In case no port is given, port `0` is used.
+///
+/// The resolver also implements [`ReverseDnsResolver`], resolving an address to the names
+/// found in its PTR records, using the upstream servers in the same way. These names are not cached,
+/// as the [`ReverseDnsService`] caches the verified name of each address itself.
+///
+/// [`ReverseDnsService`]: crate::http::layer::dns::ReverseDnsService
+/// [`DohUpstream`]: crate::http::layer::dns::DohUpstream
+/// [`DotUpstream`]: crate::http::layer::dns::DotUpstream
+pub struct SecureDnsResolver<U> {
+    upstreams: Arc<Vec<U>>,
+    cache: Arc<Mutex<DnsCache>>,
+    cache_capacity: usize,
+    max_ttl: Option<Duration>,
+    timeout: Duration,
+}
+
+/// The default amount of hosts of which the resolved addresses are cached.
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// The default time to wait for the answer of an upstream, before trying the next one.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The resolved addresses of the least recently used hosts.
+#[derive(Debug, Default)]
+struct DnsCache(LruMap<String, CacheEntry>);
+
+#[derive(Debug)]
+struct CacheEntry {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+impl DnsCache {
+    fn get(&mut self, host: &str) -> Option<Vec<IpAddr>> {
+        let entry = self.0.get(host)?;
+        if entry.expires_at <= Instant::now() {
+            self.0.remove(host);
+            return None;
+        }
+        Some(entry.addresses.clone())
+    }
+
+    fn insert(
+        &mut self,
+        host: String,
+        addresses: Vec<IpAddr>,
+        expires_at: Instant,
+        capacity: usize,
+    ) {
+        let entry = CacheEntry {
+            addresses,
+            expires_at,
+        };
+        self.0.insert(host, entry, capacity);
+    }
+}
+
+impl<U> SecureDnsResolver<U> {
+    /// Create a new [`SecureDnsResolver`] using the given upstream servers,
+    /// in order of preference.
+    pub fn new(upstreams: impl IntoIterator<Item = U>) -> Self {
+        Self {
+            upstreams: Arc::new(upstreams.into_iter().collect()),
+            cache: Arc::new(Mutex::new(DnsCache::default())),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            max_ttl: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Cache the resolved addresses of at most the given amount of hosts,
+    /// instead of the default 1024 hosts. A capacity of `0` disables caching.
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    /// Wait at most the given duration for the answer of an upstream,
+    /// before trying the next one, instead of the default 5 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Cache resolved addresses for at most the given duration,
+    /// even if the TTL of their records is longer.
+    pub fn max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = Some(max_ttl);
+        self
+    }
+
+    fn cached(&self, host: &str) -> Option<Vec<IpAddr>> {
+        self.cache.lock().unwrap().get(host)
+    }
+
+    fn cache(&self, host: String, addresses: Vec<IpAddr>, ttl: Duration) {
+        let ttl = match self.max_ttl {
+            Some(max_ttl) => ttl.min(max_ttl),
+            None => ttl,
+        };
+        if ttl.is_zero() || self.cache_capacity == 0 {
+            return;
+        }
+        self.cache.lock().unwrap().insert(
+            host,
+            addresses,
+            Instant::now() + ttl,
+            self.cache_capacity,
+        );
+    }
+}
+
+impl<U> SecureDnsResolver<U>
+where
+    U: DnsUpstream,
+{
+    async fn resolve(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        if let Some(addresses) = self.cached(host) {
+            return Ok(addresses);
+        }
+
+        let (addresses, ttl) = self
+            .query_upstreams(host, |upstream| resolve_with(upstream, host))
+            .await?;
+        // an empty answer carries no TTL to cache it for
+        if let Some(ttl) = ttl {
+            self.cache(host.to_owned(), addresses.clone(), ttl);
+        }
+        Ok(addresses)
+    }
+
+    /// Query the upstreams in order using the given function, until one of them answers
+    /// in time, or fails with an [`io::ErrorKind::NotFound`] error.
+    async fn query_upstreams<'a, T, F, Fut>(&'a self, name: &str, f: F) -> io::Result<T>
+    where
+        F: Fn(&'a U) -> Fut,
+        Fut: Future<Output = io::Result<T>>,
+    {
+        let mut last_err = None;
+        for upstream in self.upstreams.iter() {
+            let result = tokio::time::timeout(self.timeout, f(upstream))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "dns: upstream did not answer in time",
+                    ))
+                });
+            match result {
+                Ok(answer) => return Ok(answer),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(err),
+                Err(err) => {
+                    tracing::debug!(error = %err, name, "dns upstream failed, trying next one");
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| io::Error::other("dns: no upstream configured")))
+    }
+}
+
+/// Resolve the A and AAAA records of the given host using the given upstream,
+/// returning all addresses together with the smallest TTL of the found records,
+/// if any records were found.
+async fn resolve_with<U: DnsUpstream>(
+    upstream: &U,
+    host: &str,
+) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+    // DoH recommends an id of 0 to be cache friendly, and each DoT query
+    // uses its own connection, so there is no need to match multiple responses
+    let (v4, v6) = futures::try_join!(
+        query(upstream, host, RecordType::A),
+        query(upstream, host, RecordType::Aaaa),
+    )?;
+
+    let ttl = [&v4, &v6]
+        .into_iter()
+        .filter(|answer| !answer.addresses.is_empty())
+        .map(|answer| answer.ttl)
+        .min();
+    Ok((v4.addresses.into_iter().chain(v6.addresses).collect(), ttl))
+}
+
+async fn query<U: DnsUpstream>(
+    upstream: &U,
+    host: &str,
+    record_type: RecordType,
+) -> io::Result<Answer> {
+    let query = encode_query(0, host, record_type)?;
+    let response = upstream.query(query).await?;
+    decode_response(0, record_type, &response)
+}
+
+/// Split the optional port from the given host.
+fn split_port(host: &str) -> io::Result<(&str, u16)> {
+    if let Some(host) = host.strip_prefix('[') {
+        // bracketed ipv6 address, with an optional port
+        let (addr, rest) = host.split_once(']').ok_or_else(|| invalid_host(host))?;
+        return match rest.strip_prefix(':') {
+            Some(port) => Ok((addr, port.parse().map_err(|_| invalid_host(host))?)),
+            None if rest.is_empty() => Ok((addr, 0)),
+            None => Err(invalid_host(host)),
+        };
+    }
+    match host.rsplit_once(':') {
+        // multiple colons without brackets can only be an ipv6 address
+        Some((name, port)) if !name.contains(':') => {
+            Ok((name, port.parse().map_err(|_| invalid_host(host))?))
+        }
+        _ => Ok((host, 0)),
+    }
+}
+
+fn invalid_host(host: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("dns: invalid host: {}", host),
+    )
+}
+
+impl<U> DynamicDnsResolver for SecureDnsResolver<U>
+where
+    U: DnsUpstream,
+{
+    type Iterator = std::vec::IntoIter<SocketAddr>;
+
+    async fn lookup_host(&self, host: String) -> Result<Self::Iterator, io::Error> {
+        let (name, port) = split_port(&host)?;
+        let addresses = match name.parse::<IpAddr>() {
+            Ok(addr) => vec![addr],
+            Err(_) => self.resolve(&name.to_lowercase()).await?,
+        };
+        Ok(addresses
+            .into_iter()
+            .map(|addr| SocketAddr::new(addr, port))
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+}
+
+impl<U> ReverseDnsResolver for SecureDnsResolver<U>
+where
+    U: DnsUpstream,
+{
+    async fn lookup_ptr(&self, ip: IpAddr) -> Result<Vec<String>, io::Error> {
+        let name = reverse_name(ip.to_canonical());
+        let result = self
+            .query_upstreams(&name, |upstream| async {
+                let query = encode_query(0, &name, RecordType::Ptr)?;
+                let response = upstream.query(query).await?;
+                decode_ptr_response(0, &response)
+            })
+            .await;
+        match result {
+            // an address without a reverse zone has no PTR records
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            result => result,
+        }
+    }
+}
+
+impl<U> Clone for SecureDnsResolver<U> {
+    fn clone(&self) -> Self {
+        Self {
+            upstreams: self.upstreams.clone(),
+            cache: self.cache.clone(),
+            cache_capacity: self.cache_capacity,
+            max_ttl: self.max_ttl,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<U: fmt::Debug> fmt::Debug for SecureDnsResolver<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureDnsResolver")
+            .field("upstreams", &self.upstreams)
+            .field("cache_capacity", &self.cache_capacity)
+            .field("max_ttl", &self.max_ttl)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{
+            dep::http_body_util::BodyExt,
+            layer::dns::{
+                secure::message::test_util, DohUpstream, DotUpstream, ReverseDnsLayer,
+                ReverseDnsName,
+            },
+            Body, IntoResponse, Request, Response, StatusCode,
+        },
+        service::{service_fn, Context, Layer, Service},
+        stream::SocketInfo,
+    };
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A mock DoH endpoint, answering all queries with fixed addresses,
+    /// or with the given status code if it is not 200 (OK).
+    #[derive(Debug, Clone)]
+    struct MockDoh {
+        status: StatusCode,
+        ttl: u32,
+        hang: bool,
+        queries: Arc<AtomicUsize>,
+    }
+
+    impl MockDoh {
+        fn new(ttl: u32) -> Self {
+            Self {
+                status: StatusCode::OK,
+                ttl,
+                hang: false,
+                queries: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn failing() -> Self {
+            Self {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                ..Self::new(0)
+            }
+        }
+
+        fn hanging() -> Self {
+            Self {
+                hang: true,
+                ..Self::new(0)
+            }
+        }
+
+        fn queries(&self) -> usize {
+            self.queries.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Service<(), Request> for MockDoh {
+        type Response = Response;
+        type Error = Infallible;
+
+        async fn serve(&self, _ctx: Context<()>, req: Request) -> Result<Response, Infallible> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(req.uri(), "https://dns.example.com/dns-query");
+            assert_eq!(
+                req.headers()[crate::http::header::CONTENT_TYPE],
+                "application/dns-message"
+            );
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            if self.status != StatusCode::OK {
+                return Ok(self.status.into_response());
+            }
+
+            let query = req.into_body().collect().await.unwrap().to_bytes();
+            let (_, host) = test_util::parse_query(&query);
+            let response = match host.as_str() {
+                "example.com" => test_util::response(&query, &addresses(), self.ttl),
+                "empty.example.com" => test_util::response(&query, &[], self.ttl),
+                "1.0.0.127.in-addr.arpa" => {
+                    test_util::ptr_response(&query, &["example.com"], self.ttl)
+                }
+                _ => test_util::error_response(&query, 3),
+            };
+            Ok(Response::new(Body::from(response)))
+        }
+    }
+
+    fn addresses() -> Vec<IpAddr> {
+        vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()]
+    }
+
+    fn doh(mock: &MockDoh) -> DohUpstream<MockDoh> {
+        DohUpstream::new(
+            mock.clone(),
+            "https://dns.example.com/dns-query".parse().unwrap(),
+        )
+    }
+
+    async fn lookup<R: DynamicDnsResolver>(
+        resolver: &R,
+        host: &str,
+    ) -> io::Result<Vec<SocketAddr>> {
+        resolver
+            .lookup_host(host.to_owned())
+            .await
+            .map(|addresses| addresses.collect())
+    }
+
+    fn socket_addresses(port: u16) -> Vec<SocketAddr> {
+        addresses()
+            .into_iter()
+            .map(|addr| SocketAddr::new(addr, port))
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_secure_dns_resolver_caching() {
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock)]);
+
+        assert_eq!(
+            lookup(&resolver, "example.com:443").await.unwrap(),
+            socket_addresses(443)
+        );
+        assert_eq!(mock.queries(), 2); // A + AAAA
+
+        // served from cache, for the same host with a different port and/or case
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(
+            lookup(&resolver, "Example.com").await.unwrap(),
+            socket_addresses(0)
+        );
+        assert_eq!(mock.queries(), 2);
+
+        // expired
+        tokio::time::advance(Duration::from_secs(31)).await;
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(mock.queries(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_secure_dns_resolver_max_ttl() {
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock)]).max_ttl(Duration::from_secs(10));
+
+        lookup(&resolver, "example.com").await.unwrap();
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(mock.queries(), 2);
+
+        tokio::time::advance(Duration::from_secs(11)).await;
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(mock.queries(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_zero_ttl_not_cached() {
+        let mock = MockDoh::new(0);
+        let resolver = SecureDnsResolver::new([doh(&mock)]);
+
+        lookup(&resolver, "example.com").await.unwrap();
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(mock.queries(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_failover() {
+        let failing = MockDoh::failing();
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&failing), doh(&mock)]);
+
+        assert_eq!(
+            lookup(&resolver, "example.com:80").await.unwrap(),
+            socket_addresses(80)
+        );
+        assert!(failing.queries() > 0);
+        assert_eq!(mock.queries(), 2);
+
+        // a non-existent domain is a definitive answer
+        let err = lookup(&resolver, "unknown.example.com").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let failing_queries = failing.queries();
+        let err = SecureDnsResolver::new([doh(&mock), doh(&failing)])
+            .lookup_host("unknown.example.com".to_owned())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(failing.queries(), failing_queries);
+
+        // all upstreams failing
+        let other_failing = MockDoh::failing();
+        let resolver = SecureDnsResolver::new([doh(&failing), doh(&other_failing)]);
+        assert!(lookup(&resolver, "example.com").await.is_err());
+        assert!(failing.queries() > failing_queries);
+        assert!(other_failing.queries() > 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_secure_dns_resolver_timeout_failover() {
+        let hanging = MockDoh::hanging();
+        let mock = MockDoh::new(60);
+        let resolver =
+            SecureDnsResolver::new([doh(&hanging), doh(&mock)]).timeout(Duration::from_secs(1));
+
+        let start = Instant::now();
+        assert_eq!(
+            lookup(&resolver, "example.com").await.unwrap(),
+            socket_addresses(0)
+        );
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert!(hanging.queries() > 0);
+        assert_eq!(mock.queries(), 2);
+
+        let resolver = SecureDnsResolver::new([doh(&hanging)]).timeout(Duration::from_secs(1));
+        let err = lookup(&resolver, "example.com").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_empty_answer() {
+        let mock = MockDoh::new(60);
+        let other = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock), doh(&other)]);
+
+        // an empty answer is a definitive answer, which is not cached
+        assert!(lookup(&resolver, "empty.example.com")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(lookup(&resolver, "empty.example.com")
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(mock.queries(), 4);
+        assert_eq!(other.queries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_secure_dns_resolver_cache_capacity() {
+        let mut cache = DnsCache::default();
+        let expires_at = Instant::now() + Duration::from_secs(60);
+        let addresses = addresses();
+
+        cache.insert("a.com".to_owned(), addresses.clone(), expires_at, 2);
+        cache.insert("b.com".to_owned(), addresses.clone(), expires_at, 2);
+        // a.com is now more recently used than b.com
+        assert!(cache.get("a.com").is_some());
+        cache.insert("c.com".to_owned(), addresses.clone(), expires_at, 2);
+
+        assert_eq!(cache.0.len(), 2);
+        assert!(cache.get("a.com").is_some());
+        assert!(cache.get("b.com").is_none());
+        assert!(cache.get("c.com").is_some());
+
+        // replacing an entry does not evict another one
+        cache.insert("c.com".to_owned(), addresses, expires_at, 2);
+        assert_eq!(cache.0.len(), 2);
+
+        // caching can be disabled
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock)]).cache_capacity(0);
+        lookup(&resolver, "example.com").await.unwrap();
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(mock.queries(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_ip_literals() {
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock)]);
+
+        assert_eq!(
+            lookup(&resolver, "127.0.0.1:8080").await.unwrap(),
+            vec!["127.0.0.1:8080".parse().unwrap()]
+        );
+        assert_eq!(
+            lookup(&resolver, "[::1]:8080").await.unwrap(),
+            vec!["[::1]:8080".parse().unwrap()]
+        );
+        assert_eq!(
+            lookup(&resolver, "::1").await.unwrap(),
+            vec!["[::1]:0".parse().unwrap()]
+        );
+        assert!(lookup(&resolver, "example.com:http").await.is_err());
+        assert_eq!(mock.queries(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_dot() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let upstream = DotUpstream::new({
+            let connections = connections.clone();
+            move || {
+                connections.fetch_add(1, Ordering::SeqCst);
+                let (client, mut server) = tokio::io::duplex(1024);
+                tokio::spawn(async move {
+                    let len = server.read_u16().await.unwrap();
+                    let mut query = vec![0u8; len as usize];
+                    server.read_exact(&mut query).await.unwrap();
+                    let response = test_util::response(&query, &addresses(), 60);
+                    server.write_u16(response.len() as u16).await.unwrap();
+                    server.write_all(&response).await.unwrap();
+                });
+                async move { Ok(client) }
+            }
+        });
+        let resolver = SecureDnsResolver::new([upstream]);
+
+        assert_eq!(
+            lookup(&resolver, "example.com:853").await.unwrap(),
+            socket_addresses(853)
+        );
+        lookup(&resolver, "example.com").await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_secure_dns_resolver_reverse() {
+        let mock = MockDoh::new(60);
+        let resolver = SecureDnsResolver::new([doh(&mock)]);
+
+        assert_eq!(
+            resolver
+                .lookup_ptr("127.0.0.1".parse().unwrap())
+                .await
+                .unwrap(),
+            vec!["example.com".to_owned()]
+        );
+        // ipv4-mapped ipv6 addresses are resolved as ipv4
+        assert_eq!(
+            resolver
+                .lookup_ptr("::ffff:127.0.0.1".parse().unwrap())
+                .await
+                .unwrap(),
+            vec!["example.com".to_owned()]
+        );
+        // no reverse zone
+        assert!(resolver
+            .lookup_ptr("192.0.2.1".parse().unwrap())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // the name resolves back to the peer
+        let service = ReverseDnsLayer::new(resolver).layer(service_fn(
+            |ctx: Context<()>, _: ()| async move {
+                Ok::<_, Infallible>(ctx.get::<ReverseDnsName>().cloned())
+            },
+        ));
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, "127.0.0.1:1234".parse().unwrap()));
+        assert_eq!(
+            service.serve(ctx, ()).await.unwrap(),
+            Some(ReverseDnsName::new("example.com"))
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    error::BoxError,
+    http::{
+        dep::http_body_util::{BodyExt, LengthLimitError, Limited},
+        header, Body, Method, Request, Response, StatusCode, Uri,
+    },
+    service::{Context, Service},
+};
+use std::{fmt, future::Future, io};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const DNS_MESSAGE_CONTENT_TYPE: &str = "application/dns-message";
+
+/// The maximum size of a DNS message, as its length is encoded using 16 bits on the wire.
+const MAX_DNS_MESSAGE_SIZE: usize = u16::MAX as usize;
+
+/// An upstream DNS server to which (wire format) DNS queries are sent
+/// by the [`SecureDnsResolver`].
+///
+/// [`SecureDnsResolver`]: crate::http::layer::dns::SecureDnsResolver
+pub trait DnsUpstream: Send + Sync + 'static {
+    /// Send the given DNS query to the upstream server,
+    /// returning its (wire format) DNS response.
+    fn query(&self, query: Vec<u8>) -> impl Future<Output = io::Result<Vec<u8>>> + Send + '_;
+}
+
+/// A [`DnsUpstream`] which sends its queries over HTTPS (DoH), as defined in RFC 8484.
+///
+/// The queries are sent as `POST` requests to the configured [`Uri`],
+/// using the given http client [`Service`]. That client is responsible
+/// for establishing the (TLS) connection to the upstream server.
+///
+/// Responses with a body larger than the maximum size of a DNS message (64 KiB)
+/// are rejected, without buffering more than that.
+pub struct DohUpstream<S> {
+    client: S,
+    uri: Uri,
+}
+
+impl<S> DohUpstream<S> {
+    /// Create a new [`DohUpstream`], sending its queries to the given [`Uri`]
+    /// (e.g. `https://dns.example.com/dns-query`) using the given http client.
+    pub fn new(client: S, uri: Uri) -> Self {
+        Self { client, uri }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for DohUpstream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DohUpstream")
+            .field("client", &self.client)
+            .field("uri", &self.uri)
+            .finish()
+    }
+}
+
+impl<S: Clone> Clone for DohUpstream<S> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            uri: self.uri.clone(),
+        }
+    }
+}
+
+impl<S, E> DnsUpstream for DohUpstream<S>
+where
+    S: Service<(), Request, Response = Response, Error = E>,
+    E: Into<BoxError> + Send + Sync + 'static,
+{
+    async fn query(&self, query: Vec<u8>) -> io::Result<Vec<u8>> {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.uri.clone())
+            .header(header::ACCEPT, DNS_MESSAGE_CONTENT_TYPE)
+            .header(header::CONTENT_TYPE, DNS_MESSAGE_CONTENT_TYPE)
+            .body(Body::from(query))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let response = self
+            .client
+            .serve(Context::default(), request)
+            .await
+            .map_err(|err| io::Error::other(err.into()))?;
+        if response.status() != StatusCode::OK {
+            return Err(io::Error::other(format!(
+                "dns: upstream responded with status {}",
+                response.status()
+            )));
+        }
+
+        let body = Limited::new(response.into_body(), MAX_DNS_MESSAGE_SIZE)
+            .collect()
+            .await
+            .map_err(|err| {
+                if err.is::<LengthLimitError>() {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "dns: upstream response too large",
+                    )
+                } else {
+                    io::Error::other(err)
+                }
+            })?;
+        Ok(body.to_bytes().to_vec())
+    }
+}
+
+/// A [`DnsUpstream`] which sends its queries over TLS (DoT), as defined in RFC 7858.
+///
+/// A new connection is established for each query using the given connector,
+/// which is responsible for establishing the TLS connection to the upstream server
+/// (e.g. using a `tokio_rustls::TlsConnector` over a `tokio::net::TcpStream`).
+pub struct DotUpstream<C> {
+    connector: C,
+}
+
+impl<C> DotUpstream<C> {
+    /// Create a new [`DotUpstream`], using the given connector
+    /// to establish a connection for each query.
+    pub fn new(connector: C) -> Self {
+        Self { connector }
+    }
+}
+
+impl<C> fmt::Debug for DotUpstream<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DotUpstream").finish()
+    }
+}
+
+impl<C: Clone> Clone for DotUpstream<C> {
+    fn clone(&self) -> Self {
+        Self {
+            connector: self.connector.clone(),
+        }
+    }
+}
+
+impl<C, Fut, IO> DnsUpstream for DotUpstream<C>
+where
+    C: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = io::Result<IO>> + Send + 'static,
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn query(&self, query: Vec<u8>) -> io::Result<Vec<u8>> {
+        let len = u16::try_from(query.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "dns: query too large"))?;
+
+        let mut stream = (self.connector)().await?;
+
+        // messages are prefixed with their length, as is the case for DNS over TCP
+        let mut msg = Vec::with_capacity(query.len() + 2);
+        msg.extend_from_slice(&len.to_be_bytes());
+        msg.extend_from_slice(&query);
+        stream.write_all(&msg).await?;
+        stream.flush().await?;
+
+        let len = stream.read_u16().await?;
+        let mut response = vec![0u8; len as usize];
+        stream.read_exact(&mut response).await?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    fn doh_upstream(
+        body_size: usize,
+    ) -> DohUpstream<impl Service<(), Request, Response = Response, Error = Infallible>> {
+        let client = service_fn(move |req: Request| async move {
+            assert_eq!(req.headers()[header::ACCEPT], DNS_MESSAGE_CONTENT_TYPE);
+            Ok(Response::new(Body::from(vec![0u8; body_size])))
+        });
+        DohUpstream::new(
+            client,
+            Uri::from_static("https://dns.example.com/dns-query"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_doh_upstream_response() {
+        let response = doh_upstream(MAX_DNS_MESSAGE_SIZE)
+            .query(vec![1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!(response.len(), MAX_DNS_MESSAGE_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_doh_upstream_response_too_large() {
+        let err = doh_upstream(MAX_DNS_MESSAGE_SIZE + 1)
+            .query(vec![1, 2, 3])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
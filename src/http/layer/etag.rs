@@ -0,0 +1,443 @@
+//! Middleware that adds an `ETag` to responses and handles conditional requests.
+//!
+//! For successful responses to `GET` requests without an `ETag` header,
+//! an `ETag` is computed over the response body. Only bodies of which the size is known
+//! to be at most the configured maximum (1 MiB by default) are buffered to do so,
+//! streaming bodies (without a known size) are passed through without an `ETag`.
+//!
+//! The (generated or already present) `ETag` of responses to `GET` and `HEAD` requests
+//! is used to evaluate the preconditions of the request:
+//!
+//! - a request with an `If-Match` header that does not match results in a
+//!   `412 Precondition Failed` response;
+//! - a request with an `If-None-Match` header that matches results in a
+//!   `304 Not Modified` response, without a body.
+//!
+//! Given that the `ETag` is only known once the inner service produced its response,
+//! the preconditions of unsafe methods (e.g. `PUT`) are not evaluated by this middleware,
+//! as the inner service would already have applied the request.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::etag::ETagLayer;
+//! use rama::http::{header, Body, Request, Response, StatusCode};
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::error::BoxError;
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let service = ServiceBuilder::new()
+//!     .layer(ETagLayer::new())
+//!     .service_fn(|_: Request| async move {
+//!         Ok::<_, Infallible>(Response::new(Body::from("hello")))
+//!     });
+//!
+//! let response = service.serve(Context::default(), Request::new(Body::empty())).await?;
+//! let etag = response.headers().get(header::ETAG).unwrap().clone();
+//!
+//! let request = Request::builder()
+//!     .header(header::IF_NONE_MATCH, etag)
+//!     .body(Body::empty())
+//!     .unwrap();
+//! let response = service.serve(Context::default(), request).await?;
+//! assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::BoxError,
+    http::{
+        dep::http_body, dep::http_body_util::BodyExt, header, utils::fnv1a, Body, HeaderMap,
+        HeaderValue, Method, Request, Response, StatusCode,
+    },
+    service::{Context, Layer, Service},
+};
+
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// The response headers which are copied into a `304 Not Modified` response,
+/// as they would have been sent in the `200 OK` response.
+const NOT_MODIFIED_HEADERS: [header::HeaderName; 6] = [
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+    header::VARY,
+];
+
+/// [`Layer`] that applies the [`ETag`] middleware.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct ETagLayer {
+    weak: bool,
+    max_body_size: usize,
+}
+
+impl ETagLayer {
+    /// Create a new [`ETagLayer`], generating strong `ETag`s
+    /// for bodies of at most 1 MiB.
+    pub fn new() -> Self {
+        Self {
+            weak: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Generate weak `ETag`s (e.g. `W/"..."`) instead of strong ones.
+    ///
+    /// Weak `ETag`s are a better fit in case the body can be transformed
+    /// in semantically equivalent ways (e.g. compressed) further down the stack.
+    pub fn weak(mut self, weak: bool) -> Self {
+        self.weak = weak;
+        self
+    }
+
+    /// Set the maximum size of the response bodies which are buffered to generate an `ETag`.
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+}
+
+impl Default for ETagLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for ETagLayer {
+    type Service = ETag<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ETag {
+            inner,
+            weak: self.weak,
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+/// Middleware that adds an `ETag` to responses and handles conditional requests.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct ETag<S> {
+    inner: S,
+    weak: bool,
+    max_body_size: usize,
+}
+
+impl<S> ETag<S> {
+    /// Create a new [`ETag`] middleware, generating strong `ETag`s
+    /// for bodies of at most 1 MiB.
+    pub fn new(inner: S) -> Self {
+        ETagLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+
+    /// Returns a new [`Layer`] that wraps services with an [`ETag`] middleware.
+    pub fn layer() -> ETagLayer {
+        ETagLayer::new()
+    }
+}
+
+impl<State, S, ReqBody, ResBody> Service<State, Request<ReqBody>> for ETag<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    ReqBody: Send + 'static,
+    ResBody: http_body::Body<Data = bytes::Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let method = req.method().clone();
+        if method != Method::GET && method != Method::HEAD {
+            let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+            return Ok(response.map(Body::new));
+        }
+
+        let if_match = req.headers().get(header::IF_MATCH).cloned();
+        let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+
+        let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+        if !response.status().is_success() {
+            return Ok(response.map(Body::new));
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let body = match parts.headers.get(header::ETAG) {
+            Some(_) => Body::new(body),
+            None if method == Method::GET
+                && body
+                    .size_hint()
+                    .upper()
+                    .map(|size| size <= self.max_body_size as u64)
+                    .unwrap_or_default() =>
+            {
+                let body = body.collect().await.map_err(Into::into)?.to_bytes();
+                parts
+                    .headers
+                    .insert(header::ETAG, generate_etag(&body, self.weak));
+                Body::from(body)
+            }
+            None => Body::new(body),
+        };
+
+        let Some(etag) = parts.headers.get(header::ETAG) else {
+            return Ok(Response::from_parts(parts, body));
+        };
+
+        if let Some(if_match) = if_match {
+            if !etag_list_matches(&if_match, etag, true) {
+                tracing::trace!("etag: if-match precondition failed");
+                return Ok(conditional_response(
+                    StatusCode::PRECONDITION_FAILED,
+                    &parts.headers,
+                ));
+            }
+        }
+        if let Some(if_none_match) = if_none_match {
+            if etag_list_matches(&if_none_match, etag, false) {
+                tracing::trace!("etag: if-none-match matched, respond with not modified");
+                return Ok(conditional_response(
+                    StatusCode::NOT_MODIFIED,
+                    &parts.headers,
+                ));
+            }
+        }
+
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+/// Generate an `ETag` for the given body, based on its size and (FNV-1a) hash.
+fn generate_etag(body: &[u8], weak: bool) -> HeaderValue {
+    let hash = fnv1a(body);
+    let prefix = if weak { "W/" } else { "" };
+    HeaderValue::try_from(format!("{prefix}\"{:x}-{hash:016x}\"", body.len()))
+        .expect("generated etag is a valid header value")
+}
+
+/// Returns `true` if any of the `ETag`s of the given `If-Match` or `If-None-Match`
+/// header value matches the given `ETag`, using the strong or weak comparison
+/// as defined in RFC 9110, section 8.8.3.2.
+fn etag_list_matches(list: &HeaderValue, etag: &HeaderValue, strong: bool) -> bool {
+    let (Ok(list), Ok(etag)) = (list.to_str(), etag.to_str()) else {
+        return false;
+    };
+    let list = list.trim();
+    if list == "*" {
+        return true;
+    }
+    let (etag_weak, etag) = split_weak(etag.trim());
+    if strong && etag_weak {
+        return false;
+    }
+    list.split(',').map(str::trim).any(|candidate| {
+        let (candidate_weak, candidate) = split_weak(candidate);
+        !(strong && candidate_weak) && candidate == etag
+    })
+}
+
+fn split_weak(etag: &str) -> (bool, &str) {
+    match etag.strip_prefix("W/") {
+        Some(etag) => (true, etag),
+        None => (false, etag),
+    }
+}
+
+/// Create an empty response with the given status, copying the
+/// relevant headers of the original response.
+fn conditional_response(status: StatusCode, headers: &HeaderMap) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    for name in NOT_MODIFIED_HEADERS {
+        for value in headers.get_all(&name) {
+            response.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    fn service(
+        layer: ETagLayer,
+    ) -> impl Service<(), Request, Response = Response, Error = BoxError> {
+        layer.layer(service_fn(|req: Request| async move {
+            let body = match req.uri().path() {
+                "/stream" => Body::from_stream(futures_util::stream::iter([Ok::<_, Infallible>(
+                    bytes::Bytes::from_static(b"hello"),
+                )])),
+                _ => Body::from("hello"),
+            };
+            Ok::<_, Infallible>(
+                Response::builder()
+                    .header(header::CACHE_CONTROL, "max-age=60")
+                    .body(body)
+                    .unwrap(),
+            )
+        }))
+    }
+
+    fn request(path: &str, headers: &[(header::HeaderName, &str)]) -> Request {
+        let mut builder = Request::builder().uri(path);
+        for (name, value) in headers {
+            builder = builder.header(name, *value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_etag_generated() {
+        let service = service(ETagLayer::new());
+
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with('"'));
+        assert_eq!(body_string(response).await, "hello");
+
+        // the etag is stable for the same body
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+
+        // a non-matching if-none-match results in a normal response
+        let response = service
+            .serve(
+                Context::default(),
+                request("/", &[(header::IF_NONE_MATCH, "\"other\"")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "hello");
+
+        let service = self::service(ETagLayer::new().weak(true));
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        let weak_etag = response.headers().get(header::ETAG).unwrap();
+        assert_eq!(
+            weak_etag.to_str().unwrap(),
+            format!("W/{}", etag.to_str().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_etag_if_none_match_not_modified() {
+        let service = service(ETagLayer::new());
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let if_none_match = format!("\"other\", W/{}", etag.to_str().unwrap());
+        let response = service
+            .serve(
+                Context::default(),
+                request("/", &[(header::IF_NONE_MATCH, &if_none_match)]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "max-age=60"
+        );
+        assert_eq!(body_string(response).await, "");
+
+        let response = service
+            .serve(
+                Context::default(),
+                request("/", &[(header::IF_NONE_MATCH, "*")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_etag_if_match_precondition_failed() {
+        let service = service(ETagLayer::new());
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let response = service
+            .serve(
+                Context::default(),
+                request("/", &[(header::IF_MATCH, "\"other\"")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+        let response = service
+            .serve(
+                Context::default(),
+                request("/", &[(header::IF_MATCH, etag.to_str().unwrap())]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_etag_skips_streaming_and_large_bodies() {
+        let service = service(ETagLayer::new());
+        let response = service
+            .serve(
+                Context::default(),
+                request("/stream", &[(header::IF_NONE_MATCH, "*")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::ETAG));
+        assert_eq!(body_string(response).await, "hello");
+
+        let service = self::service(ETagLayer::new().max_body_size(4));
+        let response = service
+            .serve(Context::default(), request("/", &[]))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::ETAG));
+        assert_eq!(body_string(response).await, "hello");
+    }
+}
@@ -0,0 +1,372 @@
+//! Finalize the headers of responses, once all middlewares had their say.
+//!
+//! Middlewares which append headers to a response can end up producing
+//! multiple field lines for headers which only allow a single value,
+//! such as `Content-Type`, which clients interpret inconsistently (if at all).
+//! The [`FinalizeHeaders`] middleware removes the duplicate values of these
+//! single-valued headers. In case the values conflict, the last value is kept,
+//! being the value appended last, except for `Content-Length`: conflicting lengths
+//! are a framing error, and are therefore removed (and logged), such that the
+//! length is determined by the body itself.
+//!
+//! The single-valued headers are the same as those which the
+//! [`HeaderNormalize`] middleware never coalesces, such that both middlewares
+//! agree with each other, regardless of their order.
+//!
+//! Headers which allow multiple field lines are never modified, and in particular
+//! every `Set-Cookie` header is preserved as a separate field line, given that
+//! cookies cannot be combined into a single comma-separated field line
+//! (their attributes, e.g. `Expires`, can contain commas themselves).
+//!
+//! The [`FinalizeHeadersLayer`] is meant to be the outermost layer
+//! which modifies the response headers, such that it sees the final response.
+//!
+//! [`HeaderNormalize`]: crate::http::layer::header_normalize::HeaderNormalize
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{Body, Request, Response, header};
+//! use rama::http::layer::finalize_headers::FinalizeHeadersLayer;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! async fn handle(req: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::builder()
+//!         .header(header::CONTENT_TYPE, "text/plain")
+//!         .header(header::CONTENT_TYPE, "text/html")
+//!         .header(header::SET_COOKIE, "a=1")
+//!         .header(header::SET_COOKIE, "b=2")
+//!         .body(Body::default())
+//!         .unwrap())
+//! }
+//!
+//! let mut svc = ServiceBuilder::new()
+//!     .layer(FinalizeHeadersLayer::new())
+//!     .service_fn(handle);
+//!
+//! let response = svc.serve(Context::default(), Request::new(Body::default())).await?;
+//!
+//! let content_type: Vec<_> = response.headers().get_all(header::CONTENT_TYPE).iter().collect();
+//! assert_eq!(content_type, ["text/html"]);
+//! let cookies: Vec<_> = response.headers().get_all(header::SET_COOKIE).iter().collect();
+//! assert_eq!(cookies, ["a=1", "b=2"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::layer::util::header_class::HeaderClass;
+use crate::http::{header, HeaderMap, HeaderName, HeaderValue, Request, Response};
+use crate::service::{Context, Layer, Service};
+use std::sync::Arc;
+
+/// Layer that applies [`FinalizeHeaders`] which finalizes the headers of responses.
+///
+/// See the [module docs](crate::http::layer::finalize_headers) for more details.
+#[derive(Clone, Debug)]
+pub struct FinalizeHeadersLayer {
+    single_valued: Arc<Vec<HeaderName>>,
+}
+
+impl FinalizeHeadersLayer {
+    /// Create a new [`FinalizeHeadersLayer`], which deduplicates
+    /// the well-known single-valued headers (e.g. `Content-Type`).
+    pub fn new() -> Self {
+        Self {
+            single_valued: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Treat the given header as single-valued as well, on top of the well-known ones.
+    ///
+    /// Headers which have to be sent as separate field lines, such as `Set-Cookie`,
+    /// are always preserved, and are ignored by this method.
+    pub fn single_valued(mut self, name: HeaderName) -> Self {
+        if HeaderClass::of(&name) != HeaderClass::Multiline && !self.single_valued.contains(&name) {
+            Arc::make_mut(&mut self.single_valued).push(name);
+        }
+        self
+    }
+}
+
+impl Default for FinalizeHeadersLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for FinalizeHeadersLayer {
+    type Service = FinalizeHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FinalizeHeaders {
+            inner,
+            single_valued: self.single_valued.clone(),
+        }
+    }
+}
+
+/// Middleware that finalizes the headers of responses.
+///
+/// See the [module docs](crate::http::layer::finalize_headers) for more details.
+#[derive(Clone, Debug)]
+pub struct FinalizeHeaders<S> {
+    inner: S,
+    single_valued: Arc<Vec<HeaderName>>,
+}
+
+impl<S> FinalizeHeaders<S> {
+    /// Create a new [`FinalizeHeaders`], which deduplicates
+    /// the well-known single-valued headers (e.g. `Content-Type`).
+    pub fn new(inner: S) -> Self {
+        FinalizeHeadersLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+
+    /// Returns a new [`Layer`] that wraps services with a `FinalizeHeaders` middleware.
+    ///
+    /// [`Layer`]: crate::service::Layer
+    pub fn layer() -> FinalizeHeadersLayer {
+        FinalizeHeadersLayer::new()
+    }
+
+    fn is_single_valued(&self, name: &HeaderName) -> bool {
+        HeaderClass::of(name) == HeaderClass::SingleValued || self.single_valued.contains(name)
+    }
+
+    fn finalize(&self, headers: &mut HeaderMap) {
+        let repeated: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| self.is_single_valued(name))
+            .filter(|name| headers.get_all(*name).iter().nth(1).is_some())
+            .cloned()
+            .collect();
+
+        for name in repeated {
+            let mut values: Vec<HeaderValue> = Vec::new();
+            for value in headers.get_all(&name) {
+                if !values.contains(value) {
+                    values.push(value.clone());
+                }
+            }
+
+            let value = match values.pop() {
+                Some(value) if values.is_empty() => value,
+                _ if name == header::CONTENT_LENGTH => {
+                    tracing::warn!("finalize headers: remove conflicting content-length values");
+                    headers.remove(&name);
+                    continue;
+                }
+                Some(value) => {
+                    tracing::debug!(header = %name, "finalize headers: keep last of conflicting values");
+                    value
+                }
+                None => continue,
+            };
+            headers.insert(name, value);
+        }
+    }
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for FinalizeHeaders<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut res = self.inner.serve(ctx, req).await?;
+        self.finalize(res.headers_mut());
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::layer::set_header::SetResponseHeaderLayer;
+    use crate::http::{Body, HeaderValue};
+    use crate::service::{service_fn, ServiceBuilder};
+    use std::convert::Infallible;
+
+    fn values<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+        headers
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_finalize_headers_set_cookie_and_content_type() {
+        let svc = ServiceBuilder::new()
+            .layer(FinalizeHeadersLayer::new())
+            .layer(SetResponseHeaderLayer::appending(
+                header::SET_COOKIE,
+                HeaderValue::from_static("session=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+            ))
+            .layer(SetResponseHeaderLayer::appending(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            ))
+            .layer(SetResponseHeaderLayer::appending(
+                header::SET_COOKIE,
+                HeaderValue::from_static("theme=dark"),
+            ))
+            .service(service_fn(|_: Request| async move {
+                let res = Response::builder()
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .header(header::SET_COOKIE, "lang=en")
+                    .header(header::VARY, "accept")
+                    .header(header::VARY, "accept-encoding")
+                    .body(Body::empty())
+                    .unwrap();
+                Ok::<_, Infallible>(res)
+            }));
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(
+            values(res.headers(), "set-cookie"),
+            [
+                "lang=en",
+                "theme=dark",
+                "session=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT"
+            ]
+        );
+        assert_eq!(values(res.headers(), "content-type"), ["application/json"]);
+        // multi-valued headers are left untouched
+        assert_eq!(values(res.headers(), "vary"), ["accept", "accept-encoding"]);
+    }
+
+    #[tokio::test]
+    async fn test_finalize_headers_duplicated_content_type() {
+        let svc = FinalizeHeaders::new(service_fn(|_: Request| async move {
+            let res = Response::builder()
+                .header(header::CONTENT_TYPE, "text/html")
+                .header(header::CONTENT_TYPE, "text/html")
+                .header(header::SET_COOKIE, "a=1")
+                .header(header::SET_COOKIE, "a=1")
+                .body(Body::empty())
+                .unwrap();
+            Ok::<_, Infallible>(res)
+        }));
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(values(res.headers(), "content-type"), ["text/html"]);
+        // even identical cookies are kept as separate field lines
+        assert_eq!(values(res.headers(), "set-cookie"), ["a=1", "a=1"]);
+    }
+
+    #[tokio::test]
+    async fn test_finalize_headers_content_length() {
+        let svc = FinalizeHeaders::new(service_fn(|req: Request| async move {
+            let mut res = Response::builder();
+            for length in req.headers().get_all("x-length") {
+                res = res.header(header::CONTENT_LENGTH, length);
+            }
+            Ok::<_, Infallible>(res.body(Body::empty()).unwrap())
+        }));
+
+        let serve = |lengths: &'static [&'static str]| {
+            let mut req = Request::builder();
+            for length in lengths {
+                req = req.header("x-length", *length);
+            }
+            svc.serve(Context::default(), req.body(Body::empty()).unwrap())
+        };
+
+        let res = serve(&["0", "0"]).await.unwrap();
+        assert_eq!(values(res.headers(), "content-length"), ["0"]);
+
+        // conflicting lengths are not resolved by picking one of them
+        let res = serve(&["0", "42"]).await.unwrap();
+        assert!(values(res.headers(), "content-length").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finalize_headers_with_header_normalize() {
+        use crate::http::layer::header_normalize::HeaderNormalizeLayer;
+
+        let handler = || {
+            service_fn(|_: Request| async move {
+                let res = Response::builder()
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::SET_COOKIE, "a=1")
+                    .header(header::SET_COOKIE, "b=2")
+                    .header(header::VARY, "accept")
+                    .header(header::VARY, "accept-encoding")
+                    .body(Body::empty())
+                    .unwrap();
+                Ok::<_, Infallible>(res)
+            })
+        };
+
+        let finalize_first = ServiceBuilder::new()
+            .layer(FinalizeHeadersLayer::new())
+            .layer(HeaderNormalizeLayer::new())
+            .service(handler());
+        let normalize_first = ServiceBuilder::new()
+            .layer(HeaderNormalizeLayer::new())
+            .layer(FinalizeHeadersLayer::new())
+            .service(handler());
+
+        let a = finalize_first
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        let b = normalize_first
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(a.headers(), b.headers());
+        assert_eq!(values(a.headers(), "content-type"), ["application/json"]);
+        assert_eq!(values(a.headers(), "set-cookie"), ["a=1", "b=2"]);
+        assert_eq!(values(a.headers(), "vary"), ["accept, accept-encoding"]);
+    }
+
+    #[tokio::test]
+    async fn test_finalize_headers_custom_single_valued() {
+        let svc = FinalizeHeadersLayer::new()
+            .single_valued(HeaderName::from_static("x-single"))
+            .single_valued(header::SET_COOKIE)
+            .layer(service_fn(|_: Request| async move {
+                let res = Response::builder()
+                    .header("x-single", "a")
+                    .header("x-single", "b")
+                    .header("x-multi", "a")
+                    .header("x-multi", "b")
+                    .header(header::SET_COOKIE, "a=1")
+                    .header(header::SET_COOKIE, "b=2")
+                    .body(Body::empty())
+                    .unwrap();
+                Ok::<_, Infallible>(res)
+            }));
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(values(res.headers(), "x-single"), ["b"]);
+        assert_eq!(values(res.headers(), "x-multi"), ["a", "b"]);
+        assert_eq!(values(res.headers(), "set-cookie"), ["a=1", "b=2"]);
+    }
+}
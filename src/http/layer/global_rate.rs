@@ -0,0 +1,259 @@
+//! Middleware that enforces a global (server-wide) request rate,
+//! e.g. to protect a fragile downstream service from being overloaded.
+//!
+//! The rate is enforced using a [`TokenBucketPolicy`] which is shared by all services
+//! created from the same [`GlobalRateLayer`] (and their clones), and thus across all
+//! connections and routes served by them. This is different from a per-peer limit,
+//! as a single client can use up the entire rate.
+//!
+//! The bucket holds up to `burst` tokens (by default as many as the rate), and is refilled
+//! at the configured rate of tokens per second. Each request takes a token, and requests
+//! arriving while the bucket is empty are rejected with a `429 Too Many Requests` response,
+//! with a `Retry-After` header indicating the (rounded up) amount of seconds after which
+//! a token is available again.
+//!
+//! Use the [`TokenBucketPolicy`] with the [`LimitLayer`] instead to limit the rate of
+//! any kind of request, or combined with a [`KeyedPolicy`] to limit the rate per key.
+//!
+//! [`TokenBucketPolicy`]: crate::service::layer::limit::policy::TokenBucketPolicy
+//! [`LimitLayer`]: crate::service::layer::limit::LimitLayer
+//! [`KeyedPolicy`]: crate::service::layer::limit::policy::KeyedPolicy
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{header, Body, Request, Response, StatusCode};
+//! use rama::http::layer::global_rate::GlobalRateLayer;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::default()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let svc = ServiceBuilder::new()
+//!     .layer(GlobalRateLayer::new(1))
+//!     .service_fn(handle);
+//!
+//! let response = svc.serve(Context::default(), Request::new(Body::default())).await?;
+//! assert_eq!(response.status(), StatusCode::OK);
+//!
+//! let response = svc.serve(Context::default(), Request::new(Body::default())).await?;
+//! assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+//! assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{header, HeaderValue, Request, Response, StatusCode};
+use crate::service::layer::limit::policy::{Policy, PolicyOutput, TokenBucketPolicy};
+use crate::service::{Context, Layer, Service};
+
+/// Layer that applies the [`GlobalRate`] middleware.
+///
+/// All services created by this layer share the same token bucket.
+///
+/// See the [module docs](crate::http::layer::global_rate) for more details.
+#[derive(Debug, Clone)]
+pub struct GlobalRateLayer {
+    policy: TokenBucketPolicy,
+}
+
+impl GlobalRateLayer {
+    /// Create a new [`GlobalRateLayer`], allowing the given amount of requests per second,
+    /// with a burst of as many requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is `0`.
+    pub fn new(rate: u32) -> Self {
+        Self::with_policy(TokenBucketPolicy::new(rate))
+    }
+
+    /// Create a new [`GlobalRateLayer`], allowing the given amount of requests per second,
+    /// with the given maximum amount of requests in a single burst,
+    /// i.e. the capacity of the token bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate or burst is `0`.
+    pub fn with_burst(rate: u32, burst: u32) -> Self {
+        Self::with_policy(TokenBucketPolicy::with_burst(rate, burst))
+    }
+
+    /// Create a new [`GlobalRateLayer`] enforcing the rate of the given [`TokenBucketPolicy`],
+    /// sharing its bucket with all other users of (a clone of) the policy.
+    pub fn with_policy(policy: TokenBucketPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for GlobalRateLayer {
+    type Service = GlobalRate<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GlobalRate {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// Middleware that enforces a global (server-wide) request rate.
+///
+/// See the [module docs](crate::http::layer::global_rate) for more details.
+#[derive(Debug, Clone)]
+pub struct GlobalRate<S> {
+    inner: S,
+    policy: TokenBucketPolicy,
+}
+
+impl<S> GlobalRate<S> {
+    /// Create a new [`GlobalRate`] middleware, allowing the given amount of requests per second,
+    /// with a burst of as many requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is `0`.
+    pub fn new(inner: S, rate: u32) -> Self {
+        GlobalRateLayer::new(rate).layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for GlobalRate<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let result = self.policy.check(ctx, req).await;
+        let retry_after = match result.output {
+            PolicyOutput::Ready(()) => return self.inner.serve(result.ctx, result.request).await,
+            PolicyOutput::Abort(err) => err.retry_after(),
+            PolicyOutput::Retry => unreachable!("token bucket policy never retries"),
+        };
+
+        tracing::trace!(?retry_after, "global rate exceeded: reject request");
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        res.headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs.max(1)));
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Body;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    async fn handle(_: Request) -> Result<Response, Infallible> {
+        Ok(Response::new(Body::from("ok")))
+    }
+
+    async fn status<S>(svc: &S) -> (StatusCode, Option<String>)
+    where
+        S: Service<(), Request, Response = Response, Error = Infallible>,
+    {
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        let retry_after = res
+            .headers()
+            .get(header::RETRY_AFTER)
+            .map(|value| value.to_str().unwrap().to_owned());
+        (res.status(), retry_after)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_rate_burst() {
+        let svc = GlobalRateLayer::with_burst(2, 5).layer(service_fn(handle));
+
+        for _ in 0..5 {
+            assert_eq!(status(&svc).await, (StatusCode::OK, None));
+        }
+        assert_eq!(
+            status(&svc).await,
+            (StatusCode::TOO_MANY_REQUESTS, Some("1".to_owned()))
+        );
+
+        // a token is available again after 1 / rate seconds
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(status(&svc).await, (StatusCode::OK, None));
+        assert_eq!(status(&svc).await.0, StatusCode::TOO_MANY_REQUESTS);
+
+        // the bucket refills up to the burst
+        tokio::time::advance(Duration::from_secs(60)).await;
+        for _ in 0..5 {
+            assert_eq!(status(&svc).await, (StatusCode::OK, None));
+        }
+        assert_eq!(status(&svc).await.0, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_rate_steady() {
+        let svc = GlobalRateLayer::with_burst(10, 1).layer(service_fn(handle));
+
+        // a steady rate at the cap is never rejected
+        for _ in 0..50 {
+            assert_eq!(status(&svc).await, (StatusCode::OK, None));
+            tokio::time::advance(Duration::from_millis(100)).await;
+        }
+
+        // twice the cap: half of the requests are rejected
+        let mut accepted = 0;
+        for _ in 0..50 {
+            if status(&svc).await.0 == StatusCode::OK {
+                accepted += 1;
+            }
+            tokio::time::advance(Duration::from_millis(50)).await;
+        }
+        assert_eq!(accepted, 25);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_rate_retry_after() {
+        let svc = GlobalRateLayer::new(1).layer(service_fn(handle));
+        assert_eq!(status(&svc).await.0, StatusCode::OK);
+
+        // rounded up to the next second
+        tokio::time::advance(Duration::from_millis(200)).await;
+        assert_eq!(
+            status(&svc).await,
+            (StatusCode::TOO_MANY_REQUESTS, Some("1".to_owned()))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_rate_shared() {
+        let layer = GlobalRateLayer::new(3);
+        let a = layer.layer(service_fn(handle));
+        let b = layer.layer(service_fn(handle));
+        let c = a.clone();
+
+        assert_eq!(status(&a).await.0, StatusCode::OK);
+        assert_eq!(status(&b).await.0, StatusCode::OK);
+        assert_eq!(status(&c).await.0, StatusCode::OK);
+        assert_eq!(status(&a).await.0, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status(&b).await.0, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,391 @@
+//! Normalize the headers of requests and/or responses.
+//!
+//! Some upstreams (and clients) are picky about the formatting of headers,
+//! for example not accepting a header being sent more than once.
+//! The [`HeaderNormalize`] middleware coalesces the repeated field lines
+//! of a header into a single comma-separated field line, removing duplicate values.
+//! The field lines of the `Cookie` header are joined using `"; "` instead.
+//!
+//! Headers which only allow a single value (e.g. `Content-Type` or `Content-Length`)
+//! are never coalesced, as that would make their value invalid: only their duplicate
+//! values are removed. Headers which cannot be combined into a single field line,
+//! such as `Set-Cookie`, are never modified.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{Body, Request, Response, header::ACCEPT_ENCODING};
+//! use rama::http::layer::header_normalize::HeaderNormalizeLayer;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! async fn handle(req: Request) -> Result<Response, Infallible> {
+//!     let values: Vec<_> = req.headers().get_all(ACCEPT_ENCODING).iter().collect();
+//!     assert_eq!(values, ["gzip, br"]);
+//!     # Ok(Response::new(Body::default()))
+//! }
+//!
+//! let mut svc = ServiceBuilder::new()
+//!     .layer(HeaderNormalizeLayer::new())
+//!     .service_fn(handle);
+//!
+//! let request = Request::builder()
+//!     .header(ACCEPT_ENCODING, "gzip")
+//!     .header(ACCEPT_ENCODING, "br")
+//!     .header(ACCEPT_ENCODING, "gzip")
+//!     .body(Body::default())?;
+//!
+//! svc.serve(Context::default(), request).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::layer::util::header_class::HeaderClass;
+use crate::http::{HeaderMap, HeaderName, HeaderValue, Request, Response};
+use crate::service::{Context, Layer, Service};
+use std::sync::Arc;
+
+/// Layer that applies [`HeaderNormalize`] which normalizes the headers
+/// of requests and/or responses.
+///
+/// See the [module docs](crate::http::layer::header_normalize) for more details.
+#[derive(Clone, Debug)]
+pub struct HeaderNormalizeLayer {
+    config: Arc<NormalizeConfig>,
+}
+
+#[derive(Debug, Clone)]
+struct NormalizeConfig {
+    requests: bool,
+    responses: bool,
+    coalesce: bool,
+    dedup: bool,
+    excluded: Vec<HeaderName>,
+}
+
+impl HeaderNormalizeLayer {
+    /// Create a new [`HeaderNormalizeLayer`], which normalizes the headers
+    /// of both requests and responses, coalescing and deduplicating repeated headers.
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(NormalizeConfig {
+                requests: true,
+                responses: true,
+                coalesce: true,
+                dedup: true,
+                excluded: Vec::new(),
+            }),
+        }
+    }
+
+    fn config_mut(&mut self) -> &mut NormalizeConfig {
+        Arc::make_mut(&mut self.config)
+    }
+
+    /// Set whether or not the headers of requests are normalized.
+    ///
+    /// Enabled by default.
+    pub fn normalize_requests(mut self, enabled: bool) -> Self {
+        self.config_mut().requests = enabled;
+        self
+    }
+
+    /// Set whether or not the headers of responses are normalized.
+    ///
+    /// Enabled by default.
+    pub fn normalize_responses(mut self, enabled: bool) -> Self {
+        self.config_mut().responses = enabled;
+        self
+    }
+
+    /// Set whether or not repeated headers are coalesced into a single
+    /// comma-separated field line.
+    ///
+    /// Enabled by default.
+    pub fn coalesce(mut self, enabled: bool) -> Self {
+        self.config_mut().coalesce = enabled;
+        self
+    }
+
+    /// Set whether or not duplicate values of repeated headers are removed.
+    ///
+    /// Enabled by default.
+    pub fn dedup(mut self, enabled: bool) -> Self {
+        self.config_mut().dedup = enabled;
+        self
+    }
+
+    /// Never modify the given header, on top of the headers
+    /// which cannot be combined into a single field line (e.g. `Set-Cookie`).
+    pub fn exclude(mut self, name: HeaderName) -> Self {
+        self.config_mut().excluded.push(name);
+        self
+    }
+}
+
+impl Default for HeaderNormalizeLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for HeaderNormalizeLayer {
+    type Service = HeaderNormalize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HeaderNormalize {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that normalizes the headers of requests and/or responses.
+///
+/// See the [module docs](crate::http::layer::header_normalize) for more details.
+#[derive(Clone, Debug)]
+pub struct HeaderNormalize<S> {
+    inner: S,
+    config: Arc<NormalizeConfig>,
+}
+
+impl<S> HeaderNormalize<S> {
+    /// Create a new [`HeaderNormalize`], which normalizes the headers
+    /// of both requests and responses, coalescing and deduplicating repeated headers.
+    pub fn new(inner: S) -> Self {
+        HeaderNormalizeLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+
+    /// Returns a new [`Layer`] that wraps services with a `HeaderNormalize` middleware.
+    ///
+    /// [`Layer`]: crate::service::Layer
+    pub fn layer() -> HeaderNormalizeLayer {
+        HeaderNormalizeLayer::new()
+    }
+}
+
+impl NormalizeConfig {
+    fn normalize(&self, headers: &mut HeaderMap) {
+        if !self.coalesce && !self.dedup {
+            return;
+        }
+
+        let repeated: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| !self.excluded.contains(name))
+            .filter(|name| headers.get_all(*name).iter().nth(1).is_some())
+            .cloned()
+            .collect();
+
+        for name in repeated {
+            let separator = match HeaderClass::of(&name) {
+                HeaderClass::Multiline => continue,
+                // conflicting values are left as-is, only duplicates are removed
+                HeaderClass::SingleValued if !self.dedup => continue,
+                HeaderClass::SingleValued => None,
+                HeaderClass::Cookie => Some("; "),
+                HeaderClass::List => Some(", "),
+            };
+
+            let mut values: Vec<HeaderValue> = headers.get_all(&name).iter().cloned().collect();
+            if self.dedup {
+                let mut unique: Vec<HeaderValue> = Vec::with_capacity(values.len());
+                for value in values {
+                    if !unique.contains(&value) {
+                        unique.push(value);
+                    }
+                }
+                values = unique;
+            }
+
+            headers.remove(&name);
+            if let (true, Some(separator)) = (self.coalesce, separator) {
+                headers.insert(name, coalesce_values(&values, separator));
+            } else {
+                for value in values {
+                    headers.append(name.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+/// Combine the given values into a single value, joined by the given separator,
+/// which is marked as sensitive if any of the values is.
+fn coalesce_values(values: &[HeaderValue], separator: &str) -> HeaderValue {
+    let mut combined = Vec::new();
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            combined.extend_from_slice(separator.as_bytes());
+        }
+        combined.extend_from_slice(value.as_bytes());
+    }
+    let mut value =
+        HeaderValue::from_bytes(&combined).expect("joined header values to be a valid value");
+    value.set_sensitive(values.iter().any(HeaderValue::is_sensitive));
+    value
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for HeaderNormalize<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        mut req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.config.requests {
+            self.config.normalize(req.headers_mut());
+        }
+
+        let mut res = self.inner.serve(ctx, req).await?;
+
+        if self.config.responses {
+            self.config.normalize(res.headers_mut());
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, Body};
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    fn values<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+        headers
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_header_normalize_duplicates() {
+        let svc = HeaderNormalizeLayer::new().layer(service_fn(|req: Request| async move {
+            assert_eq!(values(req.headers(), "x-custom"), ["a, b"]);
+            assert_eq!(values(req.headers(), "accept"), ["text/html"]);
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+
+        let req = Request::builder()
+            .header("x-custom", "a")
+            .header("x-custom", "b")
+            .header("x-custom", "a")
+            .header("accept", "text/html")
+            .header("accept", "text/html")
+            .body(Body::empty())
+            .unwrap();
+        svc.serve(Context::default(), req).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_header_normalize_cookie_and_single_valued() {
+        let svc = HeaderNormalizeLayer::new().layer(service_fn(|req: Request| async move {
+            assert_eq!(values(req.headers(), "cookie"), ["a=1; b=2"]);
+            assert_eq!(values(req.headers(), "host"), ["example.com"]);
+            let res = Response::builder()
+                .header(header::CONTENT_TYPE, "text/plain")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::CONTENT_LENGTH, "4")
+                .header(header::CONTENT_LENGTH, "4")
+                .header(header::LOCATION, "/a")
+                .header(header::LOCATION, "/b")
+                .body(Body::empty())
+                .unwrap();
+            Ok::<_, Infallible>(res)
+        }));
+
+        let req = Request::builder()
+            .header(header::COOKIE, "a=1")
+            .header(header::COOKIE, "b=2")
+            .header(header::HOST, "example.com")
+            .header(header::HOST, "example.com")
+            .body(Body::empty())
+            .unwrap();
+        let res = svc.serve(Context::default(), req).await.unwrap();
+        // single-valued headers are never coalesced, only deduplicated
+        assert_eq!(
+            values(res.headers(), "content-type"),
+            ["text/plain", "application/json"]
+        );
+        assert_eq!(values(res.headers(), "content-length"), ["4"]);
+        assert_eq!(values(res.headers(), "location"), ["/a", "/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_header_normalize_set_cookie_multiplicity() {
+        let svc = HeaderNormalizeLayer::new().layer(service_fn(|_: Request| async move {
+            let res = Response::builder()
+                .header(header::SET_COOKIE, "a=1; Path=/")
+                .header(
+                    header::SET_COOKIE,
+                    "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                )
+                .header(header::SET_COOKIE, "a=1; Path=/")
+                .header(header::VARY, "accept")
+                .header(header::VARY, "accept-encoding")
+                .body(Body::empty())
+                .unwrap();
+            Ok::<_, Infallible>(res)
+        }));
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(
+            values(res.headers(), "set-cookie"),
+            [
+                "a=1; Path=/",
+                "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                "a=1; Path=/"
+            ]
+        );
+        assert_eq!(values(res.headers(), "vary"), ["accept, accept-encoding"]);
+    }
+
+    #[tokio::test]
+    async fn test_header_normalize_config() {
+        let svc = HeaderNormalizeLayer::new()
+            .coalesce(false)
+            .exclude(HeaderName::from_static("x-keep"))
+            .normalize_responses(false)
+            .layer(service_fn(|req: Request| async move {
+                assert_eq!(values(req.headers(), "x-custom"), ["a", "b"]);
+                assert_eq!(values(req.headers(), "x-keep"), ["a", "a"]);
+                let res = Response::builder()
+                    .header("x-custom", "a")
+                    .header("x-custom", "a")
+                    .body(Body::empty())
+                    .unwrap();
+                Ok::<_, Infallible>(res)
+            }));
+
+        let req = Request::builder()
+            .header("x-custom", "a")
+            .header("x-custom", "b")
+            .header("x-custom", "a")
+            .header("x-keep", "a")
+            .header("x-keep", "a")
+            .body(Body::empty())
+            .unwrap();
+        let res = svc.serve(Context::default(), req).await.unwrap();
+        assert_eq!(values(res.headers(), "x-custom"), ["a", "a"]);
+    }
+}
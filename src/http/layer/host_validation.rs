@@ -0,0 +1,437 @@
+//! Middleware that rejects requests whose `Host` isn't part of an allow-list,
+//! protecting inner services against host header attacks.
+//!
+//! The host is read from the authority of the request URI (e.g. an absolute-form request target,
+//! or the `:authority` pseudo header of http/2 requests), which takes precedence over the `Host`
+//! header as defined by [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-3.2.2),
+//! falling back to the `Host` header. It is matched case-insensitively
+//! against the allowed hosts, which can be an exact host (`example.com`),
+//! a wildcard matching any subdomain (`*.example.com`), or a wildcard matching any host (`*`).
+//!
+//! The hosts of the `Forwarded` and `X-Forwarded-Host` headers are validated as well,
+//! given that they are trusted by the [`Host`] extractor: a request is rejected in case
+//! any of the forwarded hosts isn't allowed.
+//!
+//! [`Host`]: crate::http::service::web::extract::Host
+//!
+//! Ports are ignored by default. When [`HostValidationLayer::ignore_port`] is disabled,
+//! an allowed host with a port (`example.com:8080`) only matches that port,
+//! and one without a port only matches hosts without a port.
+//!
+//! Rejected requests get a `400 Bad Request` response. This layer is meant to be applied
+//! before any routing, such that no inner service sees requests for unexpected hosts.
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{header, Body, Request, Response, StatusCode};
+//! use rama::http::layer::host_validation::HostValidationLayer;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::default()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let svc = ServiceBuilder::new()
+//!     .layer(HostValidationLayer::new(["example.com", "*.example.com"]))
+//!     .service_fn(handle);
+//!
+//! let req = Request::builder()
+//!     .header(header::HOST, "www.EXAMPLE.com:8080")
+//!     .body(Body::default())?;
+//! let response = svc.serve(Context::default(), req).await?;
+//! assert_eq!(response.status(), StatusCode::OK);
+//!
+//! let req = Request::builder()
+//!     .header(header::HOST, "evil.com")
+//!     .body(Body::default())?;
+//! let response = svc.serve(Context::default(), req).await?;
+//! assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::dep::http::uri::Authority;
+use crate::http::{header, HeaderMap, Request, Response, StatusCode};
+use crate::service::{Context, Layer, Service};
+use std::sync::Arc;
+
+/// Layer that applies the [`HostValidation`] middleware.
+///
+/// See the [module docs](crate::http::layer::host_validation) for more details.
+#[derive(Debug, Clone)]
+pub struct HostValidationLayer {
+    config: Arc<HostValidationConfig>,
+}
+
+#[derive(Debug, Clone)]
+struct HostValidationConfig {
+    allowed: Vec<HostPattern>,
+    ignore_port: bool,
+    allow_missing_host: bool,
+}
+
+#[derive(Debug, Clone)]
+enum HostPattern {
+    Any,
+    Exact(String, Option<u16>),
+    Subdomain(String, Option<u16>),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Self {
+        let pattern = pattern.trim().to_lowercase();
+        if pattern == "*" {
+            return Self::Any;
+        }
+        let (host, port) = match pattern.parse::<Authority>() {
+            Ok(authority) => (authority.host().to_owned(), authority.port_u16()),
+            Err(_) => (pattern, None),
+        };
+        match host.strip_prefix("*.") {
+            Some(domain) => Self::Subdomain(domain.to_owned(), port),
+            None => Self::Exact(host, port),
+        }
+    }
+
+    fn matches(&self, host: &str, port: Option<u16>, ignore_port: bool) -> bool {
+        let (matches_host, expected_port) = match self {
+            Self::Any => return true,
+            Self::Exact(expected, expected_port) => {
+                (expected.eq_ignore_ascii_case(host), *expected_port)
+            }
+            Self::Subdomain(domain, expected_port) => (
+                host.len() > domain.len() + 1
+                    && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+                    && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain),
+                *expected_port,
+            ),
+        };
+        matches_host && (ignore_port || expected_port == port)
+    }
+}
+
+impl HostValidationLayer {
+    /// Create a new [`HostValidationLayer`], only allowing requests
+    /// for one of the given hosts.
+    pub fn new<I, H>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        H: AsRef<str>,
+    {
+        Self {
+            config: Arc::new(HostValidationConfig {
+                allowed: allowed
+                    .into_iter()
+                    .map(|host| HostPattern::parse(host.as_ref()))
+                    .collect(),
+                ignore_port: true,
+                allow_missing_host: false,
+            }),
+        }
+    }
+
+    /// Ignore the port of the host when matching it against the allowed hosts.
+    ///
+    /// Enabled by default.
+    pub fn ignore_port(mut self, ignore: bool) -> Self {
+        Arc::make_mut(&mut self.config).ignore_port = ignore;
+        self
+    }
+
+    /// Allow requests without any host, such as `HTTP/1.0` requests without a `Host` header.
+    ///
+    /// Disabled by default.
+    pub fn allow_missing_host(mut self, allow: bool) -> Self {
+        Arc::make_mut(&mut self.config).allow_missing_host = allow;
+        self
+    }
+}
+
+impl<S> Layer<S> for HostValidationLayer {
+    type Service = HostValidation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HostValidation {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that rejects requests whose `Host` isn't part of an allow-list.
+///
+/// See the [module docs](crate::http::layer::host_validation) for more details.
+#[derive(Debug, Clone)]
+pub struct HostValidation<S> {
+    inner: S,
+    config: Arc<HostValidationConfig>,
+}
+
+impl<S> HostValidation<S> {
+    /// Create a new [`HostValidation`] middleware, only allowing requests
+    /// for one of the given hosts.
+    pub fn new<I, H>(inner: S, allowed: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        H: AsRef<str>,
+    {
+        HostValidationLayer::new(allowed).layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl HostValidationConfig {
+    fn is_allowed<B>(&self, req: &Request<B>) -> bool {
+        // the uri authority takes precedence, as it is the one used for routing
+        let allowed = match req.uri().authority() {
+            Some(authority) => self.is_allowed_authority(authority),
+            None => match req.headers().get(header::HOST) {
+                Some(value) => value
+                    .to_str()
+                    .ok()
+                    .and_then(|v| v.parse::<Authority>().ok())
+                    .is_some_and(|authority| self.is_allowed_authority(&authority)),
+                None => self.allow_missing_host,
+            },
+        };
+        // forwarded hosts are trusted by the `Host` extractor
+        allowed
+            && forwarded_hosts(req.headers()).into_iter().all(|host| {
+                host.and_then(|host| host.parse::<Authority>().ok())
+                    .is_some_and(|authority| self.is_allowed_authority(&authority))
+            })
+    }
+
+    fn is_allowed_authority(&self, authority: &Authority) -> bool {
+        let host = authority.host();
+        let port = authority.port_u16();
+        self.allowed
+            .iter()
+            .any(|pattern| pattern.matches(host, port, self.ignore_port))
+    }
+}
+
+/// The name of the `X-Forwarded-Host` header.
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// The hosts of the `Forwarded` and `X-Forwarded-Host` headers,
+/// or `None` for header values which are not valid UTF-8.
+fn forwarded_hosts(headers: &HeaderMap) -> Vec<Option<&str>> {
+    let mut hosts = Vec::new();
+    for value in headers.get_all(header::FORWARDED) {
+        let Ok(value) = value.to_str() else {
+            hosts.push(None);
+            continue;
+        };
+        for pair in value.split([',', ';']) {
+            if let Some((key, host)) = pair.split_once('=') {
+                if key.trim().eq_ignore_ascii_case("host") {
+                    hosts.push(Some(host.trim().trim_matches('"')));
+                }
+            }
+        }
+    }
+    for value in headers.get_all(X_FORWARDED_HOST) {
+        match value.to_str() {
+            Ok(value) => hosts.extend(value.split(',').map(|host| Some(host.trim()))),
+            Err(_) => hosts.push(None),
+        }
+    }
+    hosts
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for HostValidation<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.config.is_allowed(&req) {
+            return self.inner.serve(ctx, req).await;
+        }
+
+        tracing::debug!(host = ?req.headers().get(header::HOST), "request host not allowed");
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = StatusCode::BAD_REQUEST;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Body, Version};
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    async fn serve(layer: &HostValidationLayer, req: Request) -> StatusCode {
+        let svc = layer.layer(service_fn(|_: Request| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        svc.serve(Context::default(), req).await.unwrap().status()
+    }
+
+    fn request(host: &str) -> Request {
+        Request::builder()
+            .header(header::HOST, host)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_host_validation_allowed() {
+        let layer = HostValidationLayer::new(["example.com", "*.Example.org"]);
+        for host in [
+            "example.com",
+            "EXAMPLE.com",
+            "example.com:8080",
+            "www.example.org",
+            "a.b.example.org:443",
+        ] {
+            assert_eq!(serve(&layer, request(host)).await, StatusCode::OK, "{host}");
+        }
+
+        // the uri authority is used in absence of a host header
+        let req = Request::builder()
+            .uri("https://example.com/foo")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(serve(&layer, req).await, StatusCode::OK);
+
+        let layer = HostValidationLayer::new(["*"]);
+        assert_eq!(serve(&layer, request("anything.net")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_host_validation_disallowed() {
+        let layer = HostValidationLayer::new(["example.com", "*.example.org"]);
+        for host in [
+            "evil.com",
+            "www.example.com",
+            "example.com.evil.com",
+            "example.org",
+            "badexample.org",
+            "not a host",
+        ] {
+            assert_eq!(
+                serve(&layer, request(host)).await,
+                StatusCode::BAD_REQUEST,
+                "{host}"
+            );
+        }
+
+        let layer =
+            HostValidationLayer::new(["example.com:8080", "example.org"]).ignore_port(false);
+        assert_eq!(
+            serve(&layer, request("example.com:8080")).await,
+            StatusCode::OK
+        );
+        assert_eq!(serve(&layer, request("example.org")).await, StatusCode::OK);
+        for host in ["example.com", "example.com:9090", "example.org:8080"] {
+            assert_eq!(
+                serve(&layer, request(host)).await,
+                StatusCode::BAD_REQUEST,
+                "{host}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_validation_uri_authority_precedence() {
+        let layer = HostValidationLayer::new(["example.com"]);
+
+        let req = |uri: &str, host: &str| {
+            Request::builder()
+                .uri(uri)
+                .header(header::HOST, host)
+                .body(Body::empty())
+                .unwrap()
+        };
+        assert_eq!(
+            serve(&layer, req("http://evil.com/", "example.com")).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            serve(&layer, req("http://example.com/", "evil.com")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_host_validation_forwarded_hosts() {
+        let layer = HostValidationLayer::new(["example.com", "*.example.org"]);
+
+        let req = |name: &str, value: &str| {
+            Request::builder()
+                .header(header::HOST, "example.com")
+                .header(name, value)
+                .body(Body::empty())
+                .unwrap()
+        };
+        for (name, value) in [
+            (
+                "forwarded",
+                "for=192.0.2.60;host=www.example.org;proto=https",
+            ),
+            ("forwarded", "host=\"example.com:443\", for=192.0.2.43"),
+            ("forwarded", "for=192.0.2.43"),
+            ("x-forwarded-host", "example.com"),
+            ("x-forwarded-host", "www.example.org, example.com"),
+        ] {
+            assert_eq!(
+                serve(&layer, req(name, value)).await,
+                StatusCode::OK,
+                "{value}"
+            );
+        }
+        for (name, value) in [
+            ("forwarded", "for=192.0.2.60;host=evil.com"),
+            ("forwarded", "host=example.com, host=evil.com"),
+            ("x-forwarded-host", "evil.com"),
+            ("x-forwarded-host", "example.com, evil.com"),
+        ] {
+            assert_eq!(
+                serve(&layer, req(name, value)).await,
+                StatusCode::BAD_REQUEST,
+                "{value}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_validation_missing_host() {
+        let req = || {
+            Request::builder()
+                .version(Version::HTTP_10)
+                .uri("/")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let layer = HostValidationLayer::new(["example.com"]);
+        assert_eq!(serve(&layer, req()).await, StatusCode::BAD_REQUEST);
+
+        let layer = layer.allow_missing_host(true);
+        assert_eq!(serve(&layer, req()).await, StatusCode::OK);
+        // a present host is still validated
+        assert_eq!(
+            serve(&layer, request("evil.com")).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//! Middleware that makes retries of non-idempotent requests (e.g. `POST`) safe,
+//! by replaying the response of the first request for the same idempotency key.
+//!
+//! Requests with an `Idempotency-Key` header are only processed once per key:
+//! the response of the inner service to the first request is stored in an [`IdempotencyStore`],
+//! and replayed for all later requests with the same key, which get an additional
+//! `Idempotent-Replayed: true` header. Duplicate requests which arrive while the first request
+//! is still being processed wait for it to complete, such that the inner service
+//! is never called concurrently for the same key.
+//!
+//! Keys are scoped per client, such that clients cannot replay each other's responses.
+//! A client is identified by its `Authorization` header (see [`IdempotencyLayer::client_header`]),
+//! or by its peer IP address in case the request has no such header.
+//!
+//! A key is bound to the method and uri of its first request. Reusing a key for
+//! another request results in a `422 Unprocessable Entity` response.
+//! Server errors (`5xx`) are not stored, allowing the request to be retried,
+//! and neither are failed requests, nor responses with a body larger than
+//! [`IdempotencyLayer::max_body_size`]. Requests without a key, or with a method which is
+//! not configured (`POST` and `PATCH` by default), are passed through as-is.
+//!
+//! The responses are stored in an [`InMemoryIdempotencyStore`] by default.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::idempotency::IdempotencyLayer;
+//! use rama::http::{Body, Method, Request, Response, StatusCode};
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::error::BoxError;
+//! use std::convert::Infallible;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! static ORDERS: AtomicUsize = AtomicUsize::new(0);
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(IdempotencyLayer::new())
+//!     .service_fn(|_: Request| async move {
+//!         let order = ORDERS.fetch_add(1, Ordering::SeqCst);
+//!         Ok::<_, Infallible>(Response::new(Body::from(format!("order #{order}"))))
+//!     });
+//!
+//! let request = || {
+//!     Request::builder()
+//!         .method(Method::POST)
+//!         .uri("/orders")
+//!         .header("idempotency-key", "8e03978e-40d5-43e8-bc93-6894a57f9324")
+//!         .body(Body::empty())
+//!         .unwrap()
+//! };
+//! service.serve(Context::default(), request()).await?;
+//! // replayed, without placing a second order
+//! let response = service.serve(Context::default(), request()).await?;
+//! assert_eq!(response.headers()["idempotent-replayed"], "true");
+//! assert_eq!(ORDERS.load(Ordering::SeqCst), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::BoxError,
+    http::{
+        header,
+        layer::util::body::{buffer_body, BufferedBody},
+        Body, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    },
+    service::{
+        layer::single_flight::{Flight, Flights},
+        Context, Layer, Service,
+    },
+    stream::SocketInfo,
+};
+use std::sync::Arc;
+
+mod store;
+#[doc(inline)]
+pub use store::{IdempotencyStore, IdempotentResponse, InMemoryIdempotencyStore};
+
+/// The default name of the header containing the idempotency key.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// The name of the header added to replayed responses.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// [`Layer`] that applies the [`Idempotency`] middleware.
+///
+/// All services created by this layer share the same store,
+/// as well as the requests which are currently being processed.
+#[derive(Debug)]
+pub struct IdempotencyLayer<St = InMemoryIdempotencyStore> {
+    store: Arc<St>,
+    flights: Arc<Flights<String, IdempotentResponse>>,
+    config: Arc<IdempotencyConfig>,
+}
+
+/// The default maximum size of the body of a stored response: 1 MiB.
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+struct IdempotencyConfig {
+    header: HeaderName,
+    client_header: Option<HeaderName>,
+    methods: Vec<Method>,
+    max_body_size: usize,
+}
+
+impl IdempotencyLayer {
+    /// Create a new [`IdempotencyLayer`], storing its responses in an [`InMemoryIdempotencyStore`].
+    pub fn new() -> Self {
+        Self::with_store(InMemoryIdempotencyStore::new())
+    }
+}
+
+impl Default for IdempotencyLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<St> IdempotencyLayer<St> {
+    /// Create a new [`IdempotencyLayer`], storing its responses in the given [`IdempotencyStore`].
+    pub fn with_store(store: St) -> Self {
+        Self {
+            store: Arc::new(store),
+            flights: Arc::new(Flights::default()),
+            config: Arc::new(IdempotencyConfig {
+                header: IDEMPOTENCY_KEY,
+                client_header: Some(header::AUTHORIZATION),
+                methods: vec![Method::POST, Method::PATCH],
+                max_body_size: DEFAULT_MAX_BODY_SIZE,
+            }),
+        }
+    }
+
+    /// Set the name of the header containing the idempotency key.
+    ///
+    /// `Idempotency-Key` by default.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        Arc::make_mut(&mut self.config).header = header;
+        self
+    }
+
+    /// Set the name of the header identifying the client, by which the keys are scoped,
+    /// or `None` to identify clients only by their peer IP address.
+    ///
+    /// `Authorization` by default. Requests without this header
+    /// are identified by their peer IP address instead.
+    pub fn client_header(mut self, client_header: Option<HeaderName>) -> Self {
+        Arc::make_mut(&mut self.config).client_header = client_header;
+        self
+    }
+
+    /// Only store responses with a body of at most the given amount of bytes,
+    /// instead of the default 1 MiB.
+    ///
+    /// Larger responses are streamed to the client without being stored,
+    /// such that they are never buffered entirely.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        Arc::make_mut(&mut self.config).max_body_size = max_body_size;
+        self
+    }
+
+    /// Set the methods of the requests for which the idempotency key is respected.
+    ///
+    /// `POST` and `PATCH` by default.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Arc::make_mut(&mut self.config).methods = methods.into_iter().collect();
+        self
+    }
+}
+
+impl<St> Clone for IdempotencyLayer<St> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            flights: self.flights.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S, St> Layer<S> for IdempotencyLayer<St> {
+    type Service = Idempotency<S, St>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Idempotency {
+            inner,
+            store: self.store.clone(),
+            flights: self.flights.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that replays the stored response for requests with a known idempotency key.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug)]
+pub struct Idempotency<S, St = InMemoryIdempotencyStore> {
+    inner: S,
+    store: Arc<St>,
+    flights: Arc<Flights<String, IdempotentResponse>>,
+    config: Arc<IdempotencyConfig>,
+}
+
+impl<S, St> Idempotency<S, St> {
+    define_inner_service_accessors!();
+}
+
+impl<S> Idempotency<S> {
+    /// Returns a new [`Layer`] that wraps services with an [`Idempotency`] middleware.
+    pub fn layer() -> IdempotencyLayer {
+        IdempotencyLayer::new()
+    }
+}
+
+impl<S: Clone, St> Clone for Idempotency<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            store: self.store.clone(),
+            flights: self.flights.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<State, S, St, ReqBody, ResBody> Service<State, Request<ReqBody>> for Idempotency<S, St>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    St: IdempotencyStore,
+    ReqBody: Send + 'static,
+    ResBody: http_body::Body<Data = bytes::Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let key = match req
+            .headers()
+            .get(&self.config.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|key| !key.is_empty())
+        {
+            Some(key) if self.config.methods.contains(req.method()) => {
+                self.config.scoped_key(&ctx, &req, key)
+            }
+            _ => {
+                let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+                return Ok(response.map(Body::new));
+            }
+        };
+
+        loop {
+            // concurrent requests with the same key wait for the first one to complete
+            let leader = match self.flights.join(key.clone()) {
+                Flight::Leader(leader) => leader,
+                Flight::Follower(follower) => match follower.outcome().await {
+                    Some(stored) => return Ok(replay(&key, &req, &stored)),
+                    None => {
+                        // the response was not stored: process the request itself
+                        tracing::trace!(key, "idempotency: first request not stored, retry");
+                        continue;
+                    }
+                },
+            };
+
+            if let Some(stored) = self.store.get(key.clone()).await {
+                let response = replay(&key, &req, &stored);
+                leader.complete(Some(stored));
+                return Ok(response);
+            }
+
+            let method = req.method().clone();
+            let uri = req.uri().clone();
+            let response = self.inner.serve(ctx, req).await.map_err(Into::into)?;
+            let (parts, body) = response.into_parts();
+            if parts.status.is_server_error() {
+                return Ok(Response::from_parts(parts, Body::new(body)));
+            }
+
+            let body = match buffer_body(body, self.config.max_body_size).await? {
+                BufferedBody::Complete(body) => body,
+                BufferedBody::TooLarge(body) => {
+                    tracing::debug!(key, "idempotency: response body too large to store");
+                    return Ok(Response::from_parts(parts, body));
+                }
+            };
+            let stored = IdempotentResponse::new(
+                method,
+                uri,
+                parts.status,
+                parts.version,
+                parts.headers.clone(),
+                body.clone(),
+            );
+            tracing::trace!(key, "idempotency: store response");
+            self.store.put(key, stored.clone()).await;
+            leader.complete(Some(stored));
+
+            return Ok(Response::from_parts(parts, Body::from(body)));
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    /// Scope the given idempotency key to the client of the given request.
+    fn scoped_key<State, B>(&self, ctx: &Context<State>, req: &Request<B>, key: &str) -> String {
+        let client = match self
+            .client_header
+            .as_ref()
+            .and_then(|name| req.headers().get(name))
+        {
+            Some(value) => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            None => ctx
+                .get::<SocketInfo>()
+                .map(|info| info.peer_addr().ip().to_string())
+                .unwrap_or_default(),
+        };
+        // length prefixed, such that the client and key cannot be confused
+        format!("{}:{client}:{key}", client.len())
+    }
+}
+
+/// Replay the given stored response, unless it was stored for another request.
+fn replay<B>(key: &str, req: &Request<B>, stored: &IdempotentResponse) -> Response {
+    if stored.method() != req.method() || stored.uri() != req.uri() {
+        tracing::debug!(key, "idempotency: key reused for another request");
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
+        return response;
+    }
+    tracing::trace!(key, "idempotency: replay stored response");
+    replayed_response(stored)
+}
+
+/// Create a replayed response for the given stored response.
+fn replayed_response(stored: &IdempotentResponse) -> Response {
+    let mut response = Response::new(Body::from(stored.body().clone()));
+    *response.status_mut() = stored.status();
+    *response.version_mut() = stored.version();
+    *response.headers_mut() = stored.headers().clone();
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::service::service_fn;
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    fn request(method: Method, path: &str, key: Option<&str>) -> Request {
+        let mut builder = Request::builder().method(method).uri(path);
+        if let Some(key) = key {
+            builder = builder.header(IDEMPOTENCY_KEY, key);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    /// Create an idempotent service, which counts the calls to its inner service,
+    /// responding with the number of the call after the given delay.
+    fn idempotent_service(
+        layer: IdempotencyLayer,
+        calls: Arc<AtomicUsize>,
+        delay: Duration,
+        status: StatusCode,
+    ) -> impl Service<(), Request, Response = Response, Error = BoxError> {
+        layer.layer(service_fn(move |_: Request| {
+            let calls = calls.clone();
+            async move {
+                let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::time::sleep(delay).await;
+                let mut response = Response::new(Body::from(format!("call {call}")));
+                *response.status_mut() = status;
+                Ok::<_, Infallible>(response)
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_replays_duplicate() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = idempotent_service(
+            IdempotencyLayer::new(),
+            calls.clone(),
+            Duration::ZERO,
+            StatusCode::CREATED,
+        );
+
+        let response = service
+            .serve(
+                Context::default(),
+                request(Method::POST, "/orders", Some("a")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert!(response.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(body_string(response).await, "call 1");
+
+        let response = service
+            .serve(
+                Context::default(),
+                request(Method::POST, "/orders", Some("a")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(body_string(response).await, "call 1");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // another key is processed
+        let response = service
+            .serve(
+                Context::default(),
+                request(Method::POST, "/orders", Some("b")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "call 2");
+
+        // the key cannot be reused for another request
+        let response = service
+            .serve(
+                Context::default(),
+                request(Method::POST, "/other", Some("a")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_passthrough() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = idempotent_service(
+            IdempotencyLayer::new(),
+            calls.clone(),
+            Duration::ZERO,
+            StatusCode::OK,
+        );
+
+        for req in [
+            || request(Method::POST, "/orders", None),
+            || request(Method::PUT, "/orders", Some("a")),
+        ] {
+            service.serve(Context::default(), req()).await.unwrap();
+            let response = service.serve(Context::default(), req()).await.unwrap();
+            assert!(response.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // server errors are not stored
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = idempotent_service(
+            IdempotencyLayer::new(),
+            calls.clone(),
+            Duration::ZERO,
+            StatusCode::SERVICE_UNAVAILABLE,
+        );
+        for _ in 0..2 {
+            let response = service
+                .serve(
+                    Context::default(),
+                    request(Method::POST, "/orders", Some("a")),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_scoped_per_client() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = idempotent_service(
+            IdempotencyLayer::new(),
+            calls.clone(),
+            Duration::ZERO,
+            StatusCode::CREATED,
+        );
+
+        let serve = |auth: Option<&'static str>, peer: &'static str| {
+            let mut req = request(Method::POST, "/orders", Some("a"));
+            if let Some(auth) = auth {
+                req.headers_mut()
+                    .insert(header::AUTHORIZATION, HeaderValue::from_static(auth));
+            }
+            let mut ctx = Context::default();
+            ctx.insert(SocketInfo::new(None, peer.parse().unwrap()));
+            let service = &service;
+            async move { body_string(service.serve(ctx, req).await.unwrap()).await }
+        };
+
+        assert_eq!(serve(Some("Bearer alice"), "10.0.0.1:1000").await, "call 1");
+        // same client, from another address
+        assert_eq!(serve(Some("Bearer alice"), "10.0.0.2:1000").await, "call 1");
+        // other clients using the same key
+        assert_eq!(serve(Some("Bearer bob"), "10.0.0.1:1000").await, "call 2");
+        assert_eq!(serve(None, "10.0.0.1:1000").await, "call 3");
+        assert_eq!(serve(None, "10.0.0.1:2000").await, "call 3");
+        assert_eq!(serve(None, "10.0.0.2:1000").await, "call 4");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_max_body_size() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = idempotent_service(
+            IdempotencyLayer::new().max_body_size(4),
+            calls.clone(),
+            Duration::ZERO,
+            StatusCode::CREATED,
+        );
+
+        for call in 1..=2 {
+            let response = service
+                .serve(
+                    Context::default(),
+                    request(Method::POST, "/orders", Some("a")),
+                )
+                .await
+                .unwrap();
+            assert!(response.headers().get(IDEMPOTENT_REPLAYED).is_none());
+            assert_eq!(body_string(response).await, format!("call {call}"));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idempotency_concurrent_duplicates() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let layer = IdempotencyLayer::new();
+        let service = idempotent_service(
+            layer.clone(),
+            calls.clone(),
+            Duration::from_secs(1),
+            StatusCode::CREATED,
+        );
+
+        let serve = || {
+            service.serve(
+                Context::default(),
+                request(Method::POST, "/orders", Some("a")),
+            )
+        };
+        let (a, b, c) = tokio::join!(serve(), serve(), serve());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let mut replayed = 0;
+        for response in [a.unwrap(), b.unwrap(), c.unwrap()] {
+            assert_eq!(response.status(), StatusCode::CREATED);
+            if response.headers().contains_key(IDEMPOTENT_REPLAYED) {
+                replayed += 1;
+            }
+            assert_eq!(body_string(response).await, "call 1");
+        }
+        assert_eq!(replayed, 2);
+
+        // the key is no longer marked as being processed
+        assert!(layer.flights.is_empty());
+    }
+}
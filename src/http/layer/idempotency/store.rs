@@ -0,0 +1,223 @@
+use crate::http::{utils::LruMap, HeaderMap, Method, StatusCode, Uri, Version};
+use bytes::Bytes;
+use std::{future::Future, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// A response stored by the [`Idempotency`] middleware for an idempotency key,
+/// together with the request it was the response to.
+///
+/// An [`IdempotencyStore`] which does not keep the responses in memory can recreate
+/// a stored response using [`IdempotentResponse::new`],
+/// from the values returned by the accessors of the original response.
+///
+/// [`Idempotency`]: crate::http::layer::idempotency::Idempotency
+#[derive(Debug, Clone)]
+pub struct IdempotentResponse {
+    method: Method,
+    uri: Uri,
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl IdempotentResponse {
+    /// Create a new [`IdempotentResponse`], stored for a request with the given method and uri.
+    pub fn new(
+        method: Method,
+        uri: Uri,
+        status: StatusCode,
+        version: Version,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Self {
+        Self {
+            method,
+            uri,
+            status,
+            version,
+            headers,
+            body,
+        }
+    }
+
+    /// The method of the request for which the response was stored.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The uri of the request for which the response was stored.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The status code of the stored response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The http version of the stored response.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The headers of the stored response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The (buffered) body of the stored response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+}
+
+/// The storage used by the [`Idempotency`] middleware to store
+/// the response of the first request for each idempotency key.
+///
+/// [`Idempotency`]: crate::http::layer::idempotency::Idempotency
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Get the response stored for the given (client scoped) idempotency key.
+    fn get(&self, key: String) -> impl Future<Output = Option<IdempotentResponse>> + Send + '_;
+
+    /// Store the given response for the given (client scoped) idempotency key.
+    fn put(
+        &self,
+        key: String,
+        response: IdempotentResponse,
+    ) -> impl Future<Output = ()> + Send + '_;
+}
+
+/// The default duration for which an [`InMemoryIdempotencyStore`] keeps a response.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The default maximum amount of responses kept by an [`InMemoryIdempotencyStore`].
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// An in-memory [`IdempotencyStore`], which keeps each response
+/// for a limited duration (24 hours by default).
+///
+/// At most 10 000 responses are kept by default, evicting the least recently used
+/// response once full. Expired responses are evicted when they are looked up,
+/// or once they are the least recently used response.
+#[derive(Debug)]
+pub struct InMemoryIdempotencyStore {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<LruMap<String, (IdempotentResponse, Instant)>>,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Create a new [`InMemoryIdempotencyStore`], keeping responses for 24 hours.
+    pub fn new() -> Self {
+        Self::with_ttl(DEFAULT_TTL)
+    }
+
+    /// Create a new [`InMemoryIdempotencyStore`], keeping responses for the given duration.
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            entries: Mutex::new(LruMap::new()),
+        }
+    }
+
+    /// Keep at most the given amount of responses, instead of the default 10 000.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is `0`.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        assert!(
+            max_entries > 0,
+            "idempotency store max entries must be non-zero"
+        );
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Returns the number of keys for which a response is currently stored,
+    /// including expired responses which are not yet evicted.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no responses are currently stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn get(&self, key: String) -> Option<IdempotentResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some((_, stored_at)) if stored_at.elapsed() >= self.ttl => {
+                entries.remove(&key);
+                None
+            }
+            Some((response, _)) => Some(response.clone()),
+            None => None,
+        }
+    }
+
+    async fn put(&self, key: String, response: IdempotentResponse) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (response, Instant::now()), self.max_entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static str) -> IdempotentResponse {
+        IdempotentResponse::new(
+            Method::POST,
+            Uri::from_static("/orders"),
+            StatusCode::CREATED,
+            Version::HTTP_11,
+            HeaderMap::new(),
+            Bytes::from_static(body.as_bytes()),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_in_memory_store_expires_responses() {
+        let store = InMemoryIdempotencyStore::with_ttl(Duration::from_secs(60));
+        store.put("a".to_owned(), response("a")).await;
+        tokio::time::advance(Duration::from_secs(30)).await;
+        store.put("b".to_owned(), response("b")).await;
+
+        assert_eq!(store.get("a".to_owned()).await.unwrap().body(), "a");
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(store.get("a".to_owned()).await.is_none());
+        assert_eq!(store.get("b".to_owned()).await.unwrap().body(), "b");
+
+        // expired responses are evicted when looked up
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_max_entries() {
+        let store = InMemoryIdempotencyStore::new().max_entries(2);
+        store.put("a".to_owned(), response("a")).await;
+        store.put("b".to_owned(), response("b")).await;
+
+        // use a, such that b is the least recently used response
+        assert!(store.get("a".to_owned()).await.is_some());
+        store.put("c".to_owned(), response("c")).await;
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b".to_owned()).await.is_none());
+        assert_eq!(store.get("a".to_owned()).await.unwrap().body(), "a");
+        assert_eq!(store.get("c".to_owned()).await.unwrap().body(), "c");
+    }
+}
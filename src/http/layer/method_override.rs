@@ -0,0 +1,425 @@
+//! Middleware that overrides the method of a request,
+//! for clients which can only send `GET` and `POST` requests (e.g. html forms).
+//!
+//! The intended method is read from the `X-HTTP-Method-Override` header,
+//! or from the `_method` field of an `application/x-www-form-urlencoded` body,
+//! such that routing and other inner services see the intended method.
+//!
+//! For safety, only requests with one of the configured source methods (`POST` by default)
+//! are overridden, and only to one of the configured target methods
+//! (`PUT`, `PATCH` and `DELETE` by default). Other requests are left untouched.
+//! The original method is added to the request extensions as an [`OriginalMethod`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::http::layer::method_override::MethodOverrideLayer;
+//! use rama::http::{Body, Method, Request, Response};
+//! use rama::service::{Context, ServiceBuilder, Service};
+//! use rama::error::Error;
+//!
+//! async fn handle(req: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::from(req.method().to_string())))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let service = ServiceBuilder::new()
+//!     .layer(MethodOverrideLayer::new())
+//!     .service_fn(handle);
+//!
+//! let request = Request::builder()
+//!     .method(Method::POST)
+//!     .header("x-http-method-override", "DELETE")
+//!     .body(Body::empty())?;
+//! let response = service.serve(Context::default(), request).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::BoxError;
+use crate::http::dep::http_body;
+use crate::http::dep::http_body_util::{BodyExt, Limited};
+use crate::http::{header, Body, HeaderName, Method, Request};
+use crate::service::{Context, Layer, Service};
+use bytes::Bytes;
+use std::sync::Arc;
+
+/// The default name of the header used to override the method.
+pub const X_HTTP_METHOD_OVERRIDE: HeaderName = HeaderName::from_static("x-http-method-override");
+
+/// The name of the form field used to override the method.
+const METHOD_FORM_FIELD: &str = "_method";
+
+/// The default maximum size of a form body inspected for the `_method` field.
+const DEFAULT_MAX_FORM_SIZE: u64 = 16 * 1024;
+
+/// The method of a request prior to it being overridden by the [`MethodOverride`] middleware,
+/// added to the request extensions of overridden requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalMethod(pub Method);
+
+/// Layer that applies the [`MethodOverride`] middleware.
+///
+/// See the [module docs](crate::http::layer::method_override) for more details.
+#[derive(Debug, Clone)]
+pub struct MethodOverrideLayer {
+    config: Arc<MethodOverrideConfig>,
+}
+
+#[derive(Debug, Clone)]
+struct MethodOverrideConfig {
+    header: HeaderName,
+    form_field: bool,
+    max_form_size: u64,
+    source_methods: Vec<Method>,
+    target_methods: Vec<Method>,
+}
+
+impl MethodOverrideLayer {
+    /// Create a new [`MethodOverrideLayer`], overriding `POST` requests
+    /// to `PUT`, `PATCH` or `DELETE` requests.
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(MethodOverrideConfig {
+                header: X_HTTP_METHOD_OVERRIDE,
+                form_field: true,
+                max_form_size: DEFAULT_MAX_FORM_SIZE,
+                source_methods: vec![Method::POST],
+                target_methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+            }),
+        }
+    }
+
+    /// Set the name of the header used to override the method.
+    ///
+    /// `X-HTTP-Method-Override` by default.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        Arc::make_mut(&mut self.config).header = header;
+        self
+    }
+
+    /// Enable or disable reading the method from the `_method` field
+    /// of `application/x-www-form-urlencoded` bodies.
+    ///
+    /// The header takes precedence over the form field. Enabled by default.
+    pub fn form_field(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.config).form_field = enabled;
+        self
+    }
+
+    /// Set the maximum size of a form body inspected for the `_method` field.
+    ///
+    /// Larger bodies, as well as bodies without a `Content-Length`, are not inspected.
+    /// A body exceeding this size while announcing a smaller `Content-Length` is not buffered
+    /// any further, and fails once read by the inner service. 16 KiB by default.
+    pub fn max_form_size(mut self, size: u64) -> Self {
+        Arc::make_mut(&mut self.config).max_form_size = size;
+        self
+    }
+
+    /// Set the methods of the requests that can be overridden.
+    ///
+    /// Only `POST` by default.
+    pub fn source_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Arc::make_mut(&mut self.config).source_methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Set the methods a request can be overridden to.
+    ///
+    /// `PUT`, `PATCH` and `DELETE` by default.
+    pub fn target_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Arc::make_mut(&mut self.config).target_methods = methods.into_iter().collect();
+        self
+    }
+}
+
+impl Default for MethodOverrideLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for MethodOverrideLayer {
+    type Service = MethodOverride<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodOverride {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that overrides the method of a request.
+///
+/// See the [module docs](crate::http::layer::method_override) for more details.
+#[derive(Debug, Clone)]
+pub struct MethodOverride<S> {
+    inner: S,
+    config: Arc<MethodOverrideConfig>,
+}
+
+impl<S> MethodOverride<S> {
+    /// Create a new [`MethodOverride`] middleware, overriding `POST` requests
+    /// to `PUT`, `PATCH` or `DELETE` requests.
+    pub fn new(inner: S) -> Self {
+        MethodOverrideLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl MethodOverrideConfig {
+    fn target_method(&self, value: &[u8]) -> Option<Method> {
+        let value = std::str::from_utf8(value).ok()?.trim();
+        let method = Method::from_bytes(value.as_bytes()).ok()?;
+        self.target_methods
+            .iter()
+            .find(|target| target.as_str().eq_ignore_ascii_case(method.as_str()))
+            .cloned()
+    }
+
+    fn is_form<B>(&self, req: &Request<B>) -> bool {
+        let is_form = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .map(|mime| mime.essence_str() == mime::APPLICATION_WWW_FORM_URLENCODED.as_ref())
+            .unwrap_or_default();
+        let within_size = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+            .map(|length| length <= self.max_form_size)
+            .unwrap_or_default();
+        is_form && within_size
+    }
+}
+
+impl<State, S, ReqBody> Service<State, Request<ReqBody>> for MethodOverride<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request>,
+    ReqBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    ReqBody::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.config.source_methods.contains(req.method()) {
+            return self.inner.serve(ctx, req.map(Body::new)).await;
+        }
+
+        let mut target = req
+            .headers()
+            .get(&self.config.header)
+            .and_then(|value| self.config.target_method(value.as_bytes()));
+
+        let mut req = if target.is_none() && self.config.form_field && self.config.is_form(&req) {
+            let (parts, body) = req.into_parts();
+            let body =
+                // the limit also applies to bodies larger than their announced length
+                match Limited::new(body, self.config.max_form_size as usize).collect().await {
+                    Ok(collected) => {
+                        let bytes = collected.to_bytes();
+                        target = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+                            .ok()
+                            .and_then(|fields| {
+                                fields
+                                    .into_iter()
+                                    .find(|(name, _)| name == METHOD_FORM_FIELD)
+                            })
+                            .and_then(|(_, value)| self.config.target_method(value.as_bytes()));
+                        Body::from(bytes)
+                    }
+                    // the error (e.g. the body exceeding the limit) is returned
+                    // to the inner service once it reads the body
+                    Err(err) => Body::from_stream(futures_util::stream::once(async move {
+                        Err::<Bytes, _>(err)
+                    })),
+                };
+            Request::from_parts(parts, body)
+        } else {
+            req.map(Body::new)
+        };
+
+        if let Some(target) = target {
+            tracing::trace!(original = %req.method(), method = %target, "method overridden");
+            let original = std::mem::replace(req.method_mut(), target);
+            req.extensions_mut().insert(OriginalMethod(original));
+        }
+
+        self.inner.serve(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Response;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    async fn serve(layer: MethodOverrideLayer, req: Request) -> (Method, Option<Method>, String) {
+        let service = layer.layer(service_fn(|req: Request| async move {
+            let original = req
+                .extensions()
+                .get::<OriginalMethod>()
+                .map(|original| original.0.clone());
+            let method = req.method().clone();
+            let body = req.into_body().collect().await.unwrap().to_bytes();
+            Ok::<_, Infallible>(Response::new((
+                method,
+                original,
+                String::from_utf8(body.to_vec()).unwrap(),
+            )))
+        }));
+        service
+            .serve(Context::default(), req)
+            .await
+            .unwrap()
+            .into_body()
+    }
+
+    fn request(method: Method, header: Option<&str>) -> Request {
+        let mut builder = Request::builder().method(method);
+        if let Some(value) = header {
+            builder = builder.header(X_HTTP_METHOD_OVERRIDE, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn form_request(method: Method, form: &'static str) -> Request {
+        Request::builder()
+            .method(method)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::CONTENT_LENGTH, form.len())
+            .body(Body::from(form))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_method_override_header() {
+        let (method, original, _) = serve(
+            MethodOverrideLayer::new(),
+            request(Method::POST, Some("DELETE")),
+        )
+        .await;
+        assert_eq!(method, Method::DELETE);
+        assert_eq!(original, Some(Method::POST));
+
+        // header values are case insensitive
+        let (method, _, _) = serve(
+            MethodOverrideLayer::new(),
+            request(Method::POST, Some("patch")),
+        )
+        .await;
+        assert_eq!(method, Method::PATCH);
+    }
+
+    #[tokio::test]
+    async fn test_method_override_absent_or_not_allowed() {
+        // no override header
+        let (method, original, _) =
+            serve(MethodOverrideLayer::new(), request(Method::POST, None)).await;
+        assert_eq!(method, Method::POST);
+        assert_eq!(original, None);
+
+        // not a source method
+        let (method, original, _) = serve(
+            MethodOverrideLayer::new(),
+            request(Method::GET, Some("DELETE")),
+        )
+        .await;
+        assert_eq!(method, Method::GET);
+        assert_eq!(original, None);
+
+        // not a target method
+        for value in ["CONNECT", "GET", "not a method"] {
+            let (method, _, _) = serve(
+                MethodOverrideLayer::new(),
+                request(Method::POST, Some(value)),
+            )
+            .await;
+            assert_eq!(method, Method::POST);
+        }
+
+        // custom source methods
+        let (method, _, _) = serve(
+            MethodOverrideLayer::new().source_methods([Method::GET]),
+            request(Method::GET, Some("DELETE")),
+        )
+        .await;
+        assert_eq!(method, Method::DELETE);
+        let (method, _, _) = serve(
+            MethodOverrideLayer::new().source_methods([Method::GET]),
+            request(Method::POST, Some("DELETE")),
+        )
+        .await;
+        assert_eq!(method, Method::POST);
+    }
+
+    #[tokio::test]
+    async fn test_method_override_form_field() {
+        let form = "name=foo&_method=DELETE";
+        let (method, original, body) =
+            serve(MethodOverrideLayer::new(), form_request(Method::POST, form)).await;
+        assert_eq!(method, Method::DELETE);
+        assert_eq!(original, Some(Method::POST));
+        // the inner service still receives the full body
+        assert_eq!(body, form);
+
+        let (method, _, body) = serve(
+            MethodOverrideLayer::new().form_field(false),
+            form_request(Method::POST, form),
+        )
+        .await;
+        assert_eq!(method, Method::POST);
+        assert_eq!(body, form);
+
+        let (method, _, _) = serve(
+            MethodOverrideLayer::new().max_form_size(8),
+            form_request(Method::POST, form),
+        )
+        .await;
+        assert_eq!(method, Method::POST);
+
+        let (method, _, body) = serve(
+            MethodOverrideLayer::new(),
+            form_request(Method::POST, "name=foo"),
+        )
+        .await;
+        assert_eq!(method, Method::POST);
+        assert_eq!(body, "name=foo");
+    }
+
+    #[tokio::test]
+    async fn test_form_override_body_larger_than_content_length() {
+        let service = MethodOverrideLayer::new()
+            .max_form_size(8)
+            .layer(service_fn(|req: Request| async move {
+                let method = req.method().clone();
+                let body = req.into_body().collect().await;
+                Ok::<_, Infallible>((method, body.is_err()))
+            }));
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::CONTENT_LENGTH, "8")
+            .body(Body::from("name=foo&_method=DELETE"))
+            .unwrap();
+        let (method, body_err) = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(method, Method::POST);
+        assert!(body_err);
+    }
+}
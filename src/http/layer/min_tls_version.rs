@@ -0,0 +1,242 @@
+//! Middleware that rejects requests served over a connection
+//! which negotiated a TLS version below a configured minimum.
+//!
+//! The negotiated version is read from the [`TlsConnectionInfo`] found in the [`Context`],
+//! as added by the [`TlsAcceptorService`]. This allows to enforce a minimum version
+//! at the http layer, even when the TLS configuration itself is more permissive.
+//!
+//! Rejected requests get a `403 Forbidden` response by default,
+//! optionally also closing the connection. Requests without TLS connection info
+//! (e.g. plain text connections) are rejected as well,
+//! unless [`MinTlsVersionLayer::allow_missing_info`] is enabled.
+//!
+//! [`TlsConnectionInfo`]: crate::tls::rustls::server::TlsConnectionInfo
+//! [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{Body, Request, Response, StatusCode};
+//! use rama::http::layer::min_tls_version::MinTlsVersionLayer;
+//! use rama::tls::rustls::dep::rustls::ProtocolVersion;
+//! use rama::tls::rustls::server::TlsConnectionInfo;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::default()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let svc = ServiceBuilder::new()
+//!     .layer(MinTlsVersionLayer::new(ProtocolVersion::TLSv1_2))
+//!     .service_fn(handle);
+//!
+//! let mut ctx = Context::default();
+//! ctx.insert(TlsConnectionInfo::new().with_protocol_version(ProtocolVersion::TLSv1_1));
+//!
+//! let response = svc.serve(ctx, Request::new(Body::default())).await?;
+//! assert_eq!(response.status(), StatusCode::FORBIDDEN);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{header, HeaderValue, Request, Response, StatusCode};
+use crate::service::{Context, Layer, Service};
+use crate::tls::rustls::{dep::rustls::ProtocolVersion, server::TlsConnectionInfo};
+
+/// Layer that applies the [`MinTlsVersion`] middleware.
+///
+/// See the [module docs](crate::http::layer::min_tls_version) for more details.
+#[derive(Debug, Clone)]
+pub struct MinTlsVersionLayer {
+    config: MinTlsVersionConfig,
+}
+
+#[derive(Debug, Clone)]
+struct MinTlsVersionConfig {
+    min_version: ProtocolVersion,
+    allow_missing_info: bool,
+    status: StatusCode,
+    close_connection: bool,
+}
+
+impl MinTlsVersionLayer {
+    /// Create a new [`MinTlsVersionLayer`], rejecting requests served over a connection
+    /// which negotiated a TLS version below the given version.
+    pub fn new(min_version: ProtocolVersion) -> Self {
+        Self {
+            config: MinTlsVersionConfig {
+                min_version,
+                allow_missing_info: false,
+                status: StatusCode::FORBIDDEN,
+                close_connection: false,
+            },
+        }
+    }
+
+    /// Allow requests for which no TLS connection info could be found,
+    /// such as requests served over a plain text connection.
+    ///
+    /// Disabled by default.
+    pub fn allow_missing_info(mut self, allow: bool) -> Self {
+        self.config.allow_missing_info = allow;
+        self
+    }
+
+    /// Set the status code of the response for rejected requests.
+    ///
+    /// `403 Forbidden` by default.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.config.status = status;
+        self
+    }
+
+    /// Close the connection after responding to a rejected request.
+    ///
+    /// Disabled by default.
+    pub fn close_connection(mut self, close: bool) -> Self {
+        self.config.close_connection = close;
+        self
+    }
+}
+
+impl<S> Layer<S> for MinTlsVersionLayer {
+    type Service = MinTlsVersion<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MinTlsVersion {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that rejects requests served over a connection
+/// which negotiated a TLS version below a configured minimum.
+///
+/// See the [module docs](crate::http::layer::min_tls_version) for more details.
+#[derive(Debug, Clone)]
+pub struct MinTlsVersion<S> {
+    inner: S,
+    config: MinTlsVersionConfig,
+}
+
+impl<S> MinTlsVersion<S> {
+    /// Create a new [`MinTlsVersion`] middleware, rejecting requests served over a connection
+    /// which negotiated a TLS version below the given version.
+    pub fn new(inner: S, min_version: ProtocolVersion) -> Self {
+        MinTlsVersionLayer::new(min_version).layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl MinTlsVersionConfig {
+    fn is_allowed(&self, info: Option<&TlsConnectionInfo>) -> bool {
+        match info.and_then(TlsConnectionInfo::protocol_version) {
+            Some(version) => version.get_u16() >= self.min_version.get_u16(),
+            None => self.allow_missing_info,
+        }
+    }
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for MinTlsVersion<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.config.is_allowed(ctx.get::<TlsConnectionInfo>()) {
+            return self.inner.serve(ctx, req).await;
+        }
+
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = self.config.status;
+        if self.config.close_connection {
+            res.headers_mut()
+                .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Body;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    fn context(version: Option<ProtocolVersion>) -> Context<()> {
+        let mut ctx = Context::default();
+        if let Some(version) = version {
+            ctx.insert(TlsConnectionInfo::new().with_protocol_version(version));
+        }
+        ctx
+    }
+
+    async fn serve(layer: MinTlsVersionLayer, ctx: Context<()>) -> Response {
+        let svc = layer.layer(service_fn(|_: Request| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        svc.serve(ctx, Request::new(Body::empty())).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_min_tls_version() {
+        for (version, expected) in [
+            (ProtocolVersion::SSLv3, StatusCode::FORBIDDEN),
+            (ProtocolVersion::TLSv1_0, StatusCode::FORBIDDEN),
+            (ProtocolVersion::TLSv1_1, StatusCode::FORBIDDEN),
+            (ProtocolVersion::TLSv1_2, StatusCode::OK),
+            (ProtocolVersion::TLSv1_3, StatusCode::OK),
+        ] {
+            let layer = MinTlsVersionLayer::new(ProtocolVersion::TLSv1_2);
+            let res = serve(layer, context(Some(version))).await;
+            assert_eq!(res.status(), expected, "{:?}", version);
+            assert!(res.headers().get(header::CONNECTION).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_tls_version_missing_info() {
+        let layer = MinTlsVersionLayer::new(ProtocolVersion::TLSv1_2);
+        let res = serve(layer.clone(), context(None)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // connection info without a negotiated version
+        let mut ctx = Context::default();
+        ctx.insert(TlsConnectionInfo::new());
+        let res = serve(layer.clone(), ctx).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = serve(layer.allow_missing_info(true), context(None)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_min_tls_version_rejection_config() {
+        let layer = MinTlsVersionLayer::new(ProtocolVersion::TLSv1_3)
+            .status(StatusCode::UPGRADE_REQUIRED)
+            .close_connection(true);
+
+        let res = serve(layer.clone(), context(Some(ProtocolVersion::TLSv1_2))).await;
+        assert_eq!(res.status(), StatusCode::UPGRADE_REQUIRED);
+        assert_eq!(res.headers()[header::CONNECTION], "close");
+
+        let res = serve(layer, context(Some(ProtocolVersion::TLSv1_3))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
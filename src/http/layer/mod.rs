@@ -18,23 +18,41 @@
 //! [`Service`]: crate::service::Service
 
 pub mod auth;
+pub mod body_limit;
+pub mod body_prefix;
+pub mod cache;
 pub mod catch_panic;
 pub mod classify;
 pub mod cors;
 pub mod dns;
+pub mod etag;
+pub mod finalize_headers;
+pub mod global_rate;
 pub mod header_config;
+pub mod header_normalize;
+pub mod host_validation;
+pub mod idempotency;
 pub mod map_request_body;
 pub mod map_response_body;
+pub mod method_override;
+pub mod min_tls_version;
+pub mod no_pipeline;
 pub mod normalize_path;
 pub mod propagate_headers;
 pub mod proxy_auth;
 pub mod request_id;
 pub mod sensitive_headers;
+pub mod server_header;
 pub mod set_header;
 pub mod set_status;
+pub mod shadow;
+pub mod shutdown_reject;
+pub mod status_rewrite;
 pub mod timeout;
 pub mod trace;
+pub mod trace_context;
 pub mod upgrade;
+pub mod uri_length_limit;
 pub mod validate_request;
 
 pub(crate) mod util;
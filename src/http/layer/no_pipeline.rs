@@ -0,0 +1,420 @@
+//! Middleware that processes at most one request at a time per connection,
+//! for handlers which are not safe to be used with pipelined or concurrent requests.
+//!
+//! The next request of a connection is only passed to the inner service once
+//! the response of the current request is fully sent, i.e. once its body ended
+//! (or got dropped). Use [`NoPipelineLayer::until_body_sent`] to let the next request
+//! through as soon as the current response head is ready instead.
+//!
+//! The http/1.1 server of hyper already serializes pipelined requests: it only reads
+//! the next request of a connection once the response of the current one is written.
+//! This middleware therefore only changes the behaviour for the concurrent streams
+//! of an HTTP/2 connection, which are otherwise served concurrently.
+//!
+//! Connections are identified by the peer address of the [`SocketInfo`] found in the [`Context`],
+//! requests without [`SocketInfo`] are served without any ordering.
+//!
+//! [`SocketInfo`]: crate::stream::SocketInfo
+//! [`Context`]: crate::service::Context
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rama::http::layer::no_pipeline::NoPipelineLayer;
+//! use rama::http::{server::HttpServer, Body, Request, Response};
+//! use rama::service::ServiceBuilder;
+//! use rama::tcp::server::TcpListener;
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = HttpServer::http1().service(
+//!     ServiceBuilder::new()
+//!         .layer(NoPipelineLayer::new())
+//!         .service_fn(|_: Request| async {
+//!             Ok::<_, Infallible>(Response::new(Body::from("hello")))
+//!         }),
+//! );
+//!
+//! TcpListener::bind("127.0.0.1:8080")
+//!     .await
+//!     .unwrap()
+//!     .serve(service)
+//!     .await;
+//! # }
+//! ```
+
+use crate::error::{BoxError, Error};
+use crate::http::dep::http_body::{self, Frame};
+use crate::http::{Body, Request, Response};
+use crate::service::{Context, Layer, Service};
+use crate::stream::SocketInfo;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Layer that applies the [`NoPipeline`] middleware.
+///
+/// See the [module docs](crate::http::layer::no_pipeline) for more details.
+#[derive(Debug, Clone)]
+pub struct NoPipelineLayer {
+    until_body_sent: bool,
+}
+
+impl NoPipelineLayer {
+    /// Create a new [`NoPipelineLayer`], serving the next request of a connection
+    /// only once the response of the current request is fully sent.
+    pub fn new() -> Self {
+        Self {
+            until_body_sent: true,
+        }
+    }
+
+    /// Set whether the next request of a connection waits until the response body
+    /// of the current request is fully sent (the default), or only until its response head is ready.
+    pub fn until_body_sent(mut self, until_body_sent: bool) -> Self {
+        self.until_body_sent = until_body_sent;
+        self
+    }
+}
+
+impl Default for NoPipelineLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for NoPipelineLayer {
+    type Service = NoPipeline<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NoPipeline {
+            inner,
+            until_body_sent: self.until_body_sent,
+            connections: Default::default(),
+        }
+    }
+}
+
+/// Middleware that processes at most one request at a time per connection.
+///
+/// See the [module docs](crate::http::layer::no_pipeline) for more details.
+#[derive(Debug, Clone)]
+pub struct NoPipeline<S> {
+    inner: S,
+    until_body_sent: bool,
+    connections: Arc<Mutex<HashMap<SocketAddr, Arc<Semaphore>>>>,
+}
+
+impl<S> NoPipeline<S> {
+    /// Create a new [`NoPipeline`] middleware, serving the next request of a connection
+    /// only once the response of the current request is fully sent.
+    pub fn new(inner: S) -> Self {
+        NoPipelineLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+
+    /// Wait for the turn of a request of the connection with the given peer address.
+    async fn acquire(&self, peer: SocketAddr) -> ConnectionPermit {
+        let semaphore = self
+            .connections
+            .lock()
+            .unwrap()
+            .entry(peer)
+            .or_insert_with(|| Arc::new(Semaphore::new(1)))
+            .clone();
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("connection semaphore is never closed");
+        ConnectionPermit {
+            peer,
+            permit: Some(permit),
+            connections: self.connections.clone(),
+        }
+    }
+}
+
+impl<State, S, ReqBody, ResBody> Service<State, Request<ReqBody>> for NoPipeline<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: Send + 'static,
+    ResBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let Some(peer) = ctx.get::<SocketInfo>().map(|info| *info.peer_addr()) else {
+            tracing::trace!("no pipeline: no socket info found, serve request without ordering");
+            return Ok(self.inner.serve(ctx, req).await?.map(Body::new));
+        };
+
+        let permit = self.acquire(peer).await;
+        let response = self.inner.serve(ctx, req).await?;
+        if !self.until_body_sent {
+            return Ok(response.map(Body::new));
+        }
+        Ok(response.map(|body| {
+            Body::new(PermitBody {
+                inner: Body::new(body),
+                permit: Some(permit),
+            })
+        }))
+    }
+}
+
+/// The turn of a request to be served on its connection,
+/// cleaning up the state of the connection once no other request is waiting.
+struct ConnectionPermit {
+    peer: SocketAddr,
+    permit: Option<OwnedSemaphorePermit>,
+    connections: Arc<Mutex<HashMap<SocketAddr, Arc<Semaphore>>>>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(semaphore) = connections.get(&self.peer) {
+            // only the map itself still refers to the semaphore
+            if Arc::strong_count(semaphore) == 1 {
+                connections.remove(&self.peer);
+            }
+        }
+    }
+}
+
+/// A response body releasing the turn of the connection once it ended or got dropped.
+struct PermitBody {
+    inner: Body,
+    permit: Option<ConnectionPermit>,
+}
+
+impl http_body::Body for PermitBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let result = std::task::ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        if !matches!(result, Some(Ok(_))) {
+            self.permit = None;
+        }
+        Poll::Ready(result)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::http::server::HttpServer;
+    use crate::rt::Executor;
+    use crate::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn context(peer: &str) -> Context<()> {
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, peer.parse().unwrap()));
+        ctx
+    }
+
+    /// A service recording the start and end of each request,
+    /// of which the response body is only sent after a delay.
+    fn recording_service(
+        log: Arc<Mutex<Vec<String>>>,
+    ) -> impl Service<(), Request, Response = Response, Error = Infallible> {
+        service_fn(move |req: Request| {
+            let log = log.clone();
+            async move {
+                let path = req.uri().path().to_owned();
+                log.lock().unwrap().push(format!("start {path}"));
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let stream = futures::stream::once(async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    log.lock().unwrap().push(format!("end {path}"));
+                    Ok::<_, Infallible>(path)
+                });
+                Ok(Response::new(Body::from_stream(stream)))
+            }
+        })
+    }
+
+    async fn serve_concurrently(
+        service: &NoPipeline<impl Service<(), Request, Response = Response, Error = Infallible>>,
+        [a, b]: [&'static str; 2],
+    ) {
+        let serve = |path: &'static str, peer: &'static str| async move {
+            let req = Request::builder().uri(path).body(Body::empty()).unwrap();
+            let res = service.serve(context(peer), req).await.unwrap();
+            res.into_body().collect().await.unwrap();
+        };
+        tokio::join!(serve("/a", a), async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            serve("/b", b).await
+        });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_pipeline_same_connection() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let service = NoPipeline::new(recording_service(log.clone()));
+
+        serve_concurrently(&service, ["127.0.0.1:1000", "127.0.0.1:1000"]).await;
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["start /a", "end /a", "start /b", "end /b"]
+        );
+        // the state of the connection is cleaned up
+        assert!(service.connections.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_pipeline_until_response_head() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let service = NoPipelineLayer::new()
+            .until_body_sent(false)
+            .layer(recording_service(log.clone()));
+
+        serve_concurrently(&service, ["127.0.0.1:1000", "127.0.0.1:1000"]).await;
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["start /a", "start /b", "end /a", "end /b"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_pipeline_other_connections() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let service = NoPipeline::new(recording_service(log.clone()));
+
+        serve_concurrently(&service, ["127.0.0.1:1000", "127.0.0.1:2000"]).await;
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["start /a", "start /b", "end /a", "end /b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_pipeline_http1_pipelined_requests() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let service = HttpServer::http1().service(NoPipeline::new(recording_service(log.clone())));
+
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut ctx = Context::default();
+            ctx.insert(SocketInfo::new(None, "127.0.0.1:1000".parse().unwrap()));
+            let _ = service.serve(ctx, server).await;
+        });
+
+        // both requests are written at once, before any response is received
+        let (mut read, mut write) = tokio::io::split(client);
+        write
+            .write_all(
+                b"GET /a HTTP/1.1\r\nhost: example.com\r\n\r\n\
+                  GET /b HTTP/1.1\r\nhost: example.com\r\nconnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        read.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(response.find("/a").unwrap() < response.find("/b").unwrap());
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["start /a", "end /a", "start /b", "end /b"]
+        );
+    }
+
+    /// Serve the requests `/a` and `/b` as concurrent streams of a single http/2 connection.
+    async fn serve_h2_streams(
+        service: impl Service<(), Request, Response = Response, Error = Infallible>,
+    ) {
+        let service = HttpServer::h2(Executor::default()).service(service);
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let mut ctx = Context::default();
+            ctx.insert(SocketInfo::new(None, "127.0.0.1:1000".parse().unwrap()));
+            let _ = service.serve(ctx, server).await;
+        });
+
+        let (sender, conn) =
+            hyper::client::conn::http2::handshake(Executor::default(), TokioIo::new(client))
+                .await
+                .unwrap();
+        let conn = tokio::spawn(conn);
+
+        let get = |path: &'static str| {
+            let mut sender = sender.clone();
+            async move {
+                let req = Request::builder()
+                    .uri(format!("http://example.com{path}"))
+                    .body(Body::empty())
+                    .unwrap();
+                let res = sender.send_request(req).await.unwrap();
+                let body = res.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(body, path);
+            }
+        };
+        tokio::join!(get("/a"), get("/b"));
+
+        drop(sender);
+        conn.await.unwrap().unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_pipeline_h2_concurrent_streams() {
+        // without the middleware, the streams of a connection are served concurrently
+        let log = Arc::new(Mutex::new(Vec::new()));
+        serve_h2_streams(recording_service(log.clone())).await;
+        let log = log.lock().unwrap().clone();
+        assert!(
+            log[..2].iter().all(|entry| entry.starts_with("start")),
+            "{log:?}"
+        );
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        serve_h2_streams(NoPipeline::new(recording_service(log.clone()))).await;
+        let log = log.lock().unwrap().clone();
+        let first = log[0].strip_prefix("start ").unwrap();
+        let second = if first == "/a" { "/b" } else { "/a" };
+        assert_eq!(
+            log,
+            [
+                format!("start {first}"),
+                format!("end {first}"),
+                format!("start {second}"),
+                format!("end {second}"),
+            ]
+        );
+    }
+}
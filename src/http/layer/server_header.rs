@@ -0,0 +1,208 @@
+//! Middleware to control the `Server` response header,
+//! e.g. to limit the fingerprinting of a server.
+//!
+//! The [`HttpServer`] itself does not add a `Server` header to responses,
+//! so any `Server` header is added by the services it serves (e.g. a proxied upstream).
+//! The [`ServerHeaderLayer`] can set it to a custom value, remove it entirely,
+//! or keep it as returned by the inner service.
+//!
+//! [`HttpServer`]: crate::http::server::HttpServer
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::http::layer::server_header::ServerHeaderLayer;
+//! use rama::http::{header::SERVER, Body, HeaderValue, Request, Response};
+//! use rama::service::{Context, ServiceBuilder, Service};
+//! use rama::error::Error;
+//!
+//! async fn handle(req: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::builder()
+//!         .header(SERVER, "nginx/1.25.4")
+//!         .body(Body::empty())
+//!         .unwrap())
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let service = ServiceBuilder::new()
+//!     .layer(ServerHeaderLayer::set(HeaderValue::from_static("rama")))
+//!     .service_fn(handle);
+//!
+//! let response = service.serve(Context::default(), Request::new(Body::empty())).await?;
+//! assert_eq!(response.headers()[SERVER], "rama");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{header::SERVER, HeaderValue, Request, Response};
+use crate::service::{Context, Layer, Service};
+
+/// How the `Server` response header is handled by the [`ServerHeader`] middleware.
+#[derive(Debug, Clone, Default)]
+pub enum ServerHeaderMode {
+    /// Set the header to the given value, overwriting any existing value.
+    Set(HeaderValue),
+    /// Remove the header entirely.
+    Remove,
+    /// Keep the header as returned by the inner service.
+    #[default]
+    Keep,
+}
+
+/// Layer that applies the [`ServerHeader`] middleware.
+///
+/// See the [module docs](crate::http::layer::server_header) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct ServerHeaderLayer {
+    mode: ServerHeaderMode,
+}
+
+impl ServerHeaderLayer {
+    /// Create a new [`ServerHeaderLayer`] handling the `Server` header using the given mode.
+    pub fn new(mode: ServerHeaderMode) -> Self {
+        Self { mode }
+    }
+
+    /// Create a new [`ServerHeaderLayer`] setting the `Server` header to the given value,
+    /// overwriting any existing value.
+    pub fn set(value: HeaderValue) -> Self {
+        Self::new(ServerHeaderMode::Set(value))
+    }
+
+    /// Create a new [`ServerHeaderLayer`] removing the `Server` header entirely.
+    pub fn remove() -> Self {
+        Self::new(ServerHeaderMode::Remove)
+    }
+
+    /// Create a new [`ServerHeaderLayer`] keeping the `Server` header
+    /// as returned by the inner service.
+    pub fn keep() -> Self {
+        Self::new(ServerHeaderMode::Keep)
+    }
+}
+
+impl<S> Layer<S> for ServerHeaderLayer {
+    type Service = ServerHeader<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ServerHeader {
+            inner,
+            mode: self.mode.clone(),
+        }
+    }
+}
+
+/// Middleware to set, remove or keep the `Server` response header.
+///
+/// See the [module docs](crate::http::layer::server_header) for more details.
+#[derive(Debug, Clone)]
+pub struct ServerHeader<S> {
+    inner: S,
+    mode: ServerHeaderMode,
+}
+
+impl<S> ServerHeader<S> {
+    /// Create a new [`ServerHeader`] middleware handling the `Server` header using the given mode.
+    pub fn new(inner: S, mode: ServerHeaderMode) -> Self {
+        Self { inner, mode }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, S, ReqBody, ResBody> Service<State, Request<ReqBody>> for ServerHeader<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut response = self.inner.serve(ctx, req).await?;
+        match &self.mode {
+            ServerHeaderMode::Set(value) => {
+                response.headers_mut().insert(SERVER, value.clone());
+            }
+            ServerHeaderMode::Remove => {
+                response.headers_mut().remove(SERVER);
+            }
+            ServerHeaderMode::Keep => (),
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Body;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    async fn serve(layer: ServerHeaderLayer, upstream: &[&'static str]) -> Response {
+        let upstream = upstream.to_vec();
+        let service = layer.layer(service_fn(move |_req: Request| {
+            let upstream = upstream.clone();
+            async move {
+                let mut response = Response::new(Body::empty());
+                for value in upstream {
+                    response
+                        .headers_mut()
+                        .append(SERVER, HeaderValue::from_static(value));
+                }
+                Ok::<_, Infallible>(response)
+            }
+        }));
+        service
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap()
+    }
+
+    fn server_values(response: &Response) -> Vec<&str> {
+        response
+            .headers()
+            .get_all(SERVER)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_server_header_set() {
+        let layer = ServerHeaderLayer::set(HeaderValue::from_static("rama"));
+
+        let response = serve(layer.clone(), &[]).await;
+        assert_eq!(server_values(&response), ["rama"]);
+
+        let response = serve(layer, &["nginx/1.25.4", "apache"]).await;
+        assert_eq!(server_values(&response), ["rama"]);
+    }
+
+    #[tokio::test]
+    async fn test_server_header_remove() {
+        let response = serve(ServerHeaderLayer::remove(), &["nginx/1.25.4", "apache"]).await;
+        assert!(server_values(&response).is_empty());
+
+        let response = serve(ServerHeaderLayer::remove(), &[]).await;
+        assert!(server_values(&response).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_server_header_keep() {
+        let response = serve(ServerHeaderLayer::keep(), &["nginx/1.25.4"]).await;
+        assert_eq!(server_values(&response), ["nginx/1.25.4"]);
+
+        let response = serve(ServerHeaderLayer::default(), &[]).await;
+        assert!(server_values(&response).is_empty());
+    }
+}
@@ -323,3 +323,117 @@ where
         self.inner.serve(ctx, req).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::http::{header, Body, HeaderValue};
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    /// Respond with the headers of the request, as received by the inner (client) service.
+    async fn echo_headers(req: Request) -> Result<Response, Infallible> {
+        let mut res = Response::new(Body::empty());
+        *res.headers_mut() = req.headers().clone();
+        Ok(res)
+    }
+
+    fn user_agent_request() -> Request {
+        Request::builder()
+            .header(header::USER_AGENT, "curl/8.4.0")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_override_mode() {
+        let svc = SetRequestHeader::overriding(
+            service_fn(echo_headers),
+            header::USER_AGENT,
+            HeaderValue::from_static("rama"),
+        );
+
+        let res = svc
+            .serve(Context::default(), user_agent_request())
+            .await
+            .unwrap();
+
+        let mut values = res.headers().get_all(header::USER_AGENT).iter();
+        assert_eq!(values.next().unwrap(), "rama");
+        assert_eq!(values.next(), None);
+    }
+
+    #[tokio::test]
+    async fn test_append_mode() {
+        let svc = SetRequestHeader::appending(
+            service_fn(echo_headers),
+            header::USER_AGENT,
+            HeaderValue::from_static("rama"),
+        );
+
+        let res = svc
+            .serve(Context::default(), user_agent_request())
+            .await
+            .unwrap();
+
+        let mut values = res.headers().get_all(header::USER_AGENT).iter();
+        assert_eq!(values.next().unwrap(), "curl/8.4.0");
+        assert_eq!(values.next().unwrap(), "rama");
+        assert_eq!(values.next(), None);
+    }
+
+    #[tokio::test]
+    async fn test_skip_if_present_mode() {
+        let svc = SetRequestHeader::if_not_present(
+            service_fn(echo_headers),
+            header::USER_AGENT,
+            HeaderValue::from_static("rama"),
+        );
+
+        let res = svc
+            .serve(Context::default(), user_agent_request())
+            .await
+            .unwrap();
+
+        let mut values = res.headers().get_all(header::USER_AGENT).iter();
+        assert_eq!(values.next().unwrap(), "curl/8.4.0");
+        assert_eq!(values.next(), None);
+    }
+
+    #[tokio::test]
+    async fn test_skip_if_present_mode_when_not_present() {
+        let svc = SetRequestHeader::if_not_present(
+            service_fn(echo_headers),
+            header::USER_AGENT,
+            HeaderValue::from_static("rama"),
+        );
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+
+        let mut values = res.headers().get_all(header::USER_AGENT).iter();
+        assert_eq!(values.next().unwrap(), "rama");
+        assert_eq!(values.next(), None);
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_value() {
+        let svc = SetRequestHeaderLayer::overriding_fn(header::AUTHORIZATION, || async {
+            Some(HeaderValue::from_static("Bearer fresh-token"))
+        })
+        .layer(service_fn(echo_headers));
+
+        let req = Request::builder()
+            .header(header::AUTHORIZATION, "Bearer stale-token")
+            .body(Body::empty())
+            .unwrap();
+        let res = svc.serve(Context::default(), req).await.unwrap();
+
+        let mut values = res.headers().get_all(header::AUTHORIZATION).iter();
+        assert_eq!(values.next().unwrap(), "Bearer fresh-token");
+        assert_eq!(values.next(), None);
+    }
+}
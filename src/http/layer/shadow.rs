@@ -0,0 +1,610 @@
+//! Middleware that mirrors requests to a shadow service,
+//! e.g. a new version of an upstream which is being rolled out.
+//!
+//! Each request is served as usual by the inner service, whose response is returned
+//! to the client. In addition a copy of the request is sent, in the background,
+//! to the shadow service. The response of the shadow service is discarded,
+//! such that it never affects the client: not even when the shadow service
+//! is slow, fails or panics.
+//!
+//! To duplicate the request body it is buffered in memory, up to the configured
+//! [`ShadowLayer::max_body_size`]. Requests with a larger body (or a body which fails
+//! to be read) are not mirrored, and are served by the inner service with their full body.
+//!
+//! The amount of shadow requests in flight is capped by [`ShadowLayer::max_concurrency`],
+//! such that a slow shadow service cannot pile up background tasks (and buffered bodies).
+//! While this limit is reached requests are not mirrored, and only served by the inner service.
+//!
+//! Using [`ShadowLayer::on_compare`] a callback can be registered which is called
+//! with a [`ShadowComparison`] of the response (head) of both services,
+//! once both have responded, e.g. to log or count any differences.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::shadow::ShadowLayer;
+//! use rama::http::{Body, Request, Response, StatusCode};
+//! use rama::service::{service_fn, Context, Service, ServiceBuilder};
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let shadow = service_fn(|_: Request| async {
+//!     Ok::<_, Infallible>(
+//!         Response::builder()
+//!             .status(StatusCode::INTERNAL_SERVER_ERROR)
+//!             .body(Body::empty())
+//!             .unwrap(),
+//!     )
+//! });
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(ShadowLayer::new(shadow).on_compare(|comparison| {
+//!         if !comparison.is_status_match() {
+//!             tracing::warn!(?comparison, "shadow response differs");
+//!         }
+//!     }))
+//!     .service_fn(|_: Request| async { Ok::<_, Infallible>(Response::new(Body::from("hello"))) });
+//!
+//! // the client only ever sees the response of the inner service
+//! let res = service
+//!     .serve(Context::default(), Request::new(Body::from("ping")))
+//!     .await
+//!     .unwrap();
+//! assert_eq!(res.status(), StatusCode::OK);
+//! # }
+//! ```
+
+use super::body_prefix::PrefixedBody;
+use crate::error::BoxError;
+use crate::http::dep::http_body::{self, Frame};
+use crate::http::dep::http_body_util::BodyExt;
+use crate::http::{Body, HeaderMap, Request, Response, StatusCode};
+use crate::service::{Context, Layer, Service};
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The default maximum size of a request body which is mirrored, 64 KiB.
+const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// The default maximum amount of shadow requests in flight.
+const DEFAULT_MAX_CONCURRENCY: usize = 64;
+
+type CompareFn = Arc<dyn Fn(ShadowComparison) + Send + Sync + 'static>;
+
+/// The comparison of the responses of the inner and shadow service
+/// for a mirrored request, passed to the callback registered using
+/// [`ShadowLayer::on_compare`].
+#[derive(Debug)]
+pub struct ShadowComparison {
+    primary: Option<ResponseHead>,
+    shadow: Result<ResponseHead, BoxError>,
+}
+
+#[derive(Debug)]
+struct ResponseHead {
+    status: StatusCode,
+    headers: HeaderMap,
+}
+
+impl ResponseHead {
+    fn new<B>(res: &Response<B>) -> Self {
+        Self {
+            status: res.status(),
+            headers: res.headers().clone(),
+        }
+    }
+}
+
+impl ShadowComparison {
+    /// The status of the response of the inner service,
+    /// `None` in case the inner service failed.
+    pub fn primary_status(&self) -> Option<StatusCode> {
+        self.primary.as_ref().map(|head| head.status)
+    }
+
+    /// The headers of the response of the inner service,
+    /// `None` in case the inner service failed.
+    pub fn primary_headers(&self) -> Option<&HeaderMap> {
+        self.primary.as_ref().map(|head| &head.headers)
+    }
+
+    /// The status of the response of the shadow service,
+    /// `None` in case the shadow service failed.
+    pub fn shadow_status(&self) -> Option<StatusCode> {
+        self.shadow.as_ref().ok().map(|head| head.status)
+    }
+
+    /// The headers of the response of the shadow service,
+    /// `None` in case the shadow service failed.
+    pub fn shadow_headers(&self) -> Option<&HeaderMap> {
+        self.shadow.as_ref().ok().map(|head| &head.headers)
+    }
+
+    /// The error of the shadow service, if it failed.
+    pub fn shadow_error(&self) -> Option<&BoxError> {
+        self.shadow.as_ref().err()
+    }
+
+    /// Returns `true` if both services responded with the same status.
+    pub fn is_status_match(&self) -> bool {
+        match (self.primary_status(), self.shadow_status()) {
+            (Some(primary), Some(shadow)) => primary == shadow,
+            _ => false,
+        }
+    }
+}
+
+/// Layer that applies the [`Shadow`] middleware.
+///
+/// See the [module docs](crate::http::layer::shadow) for more details.
+pub struct ShadowLayer<T> {
+    shadow: Arc<T>,
+    max_body_size: usize,
+    semaphore: Arc<Semaphore>,
+    on_compare: Option<CompareFn>,
+}
+
+impl<T> ShadowLayer<T> {
+    /// Create a new [`ShadowLayer`], mirroring requests to the given shadow service.
+    pub fn new(shadow: T) -> Self {
+        Self {
+            shadow: Arc::new(shadow),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            semaphore: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY)),
+            on_compare: None,
+        }
+    }
+
+    /// Set the maximum size of a request body which is mirrored,
+    /// requests with a larger body are only served by the inner service.
+    ///
+    /// Defaults to 64 KiB.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Set the maximum amount of shadow requests in flight, shared by all services
+    /// created by this layer. Requests arriving while this limit is reached
+    /// are not mirrored, and are only served by the inner service.
+    ///
+    /// Defaults to 64.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.semaphore = Arc::new(Semaphore::new(max_concurrency));
+        self
+    }
+
+    /// Register a callback which is called with the [`ShadowComparison`]
+    /// of each mirrored request, once both services have responded.
+    pub fn on_compare<F>(mut self, on_compare: F) -> Self
+    where
+        F: Fn(ShadowComparison) + Send + Sync + 'static,
+    {
+        self.on_compare = Some(Arc::new(on_compare));
+        self
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ShadowLayer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShadowLayer")
+            .field("shadow", &self.shadow)
+            .field("max_body_size", &self.max_body_size)
+            .field("available_permits", &self.semaphore.available_permits())
+            .field("on_compare", &self.on_compare.is_some())
+            .finish()
+    }
+}
+
+impl<T> Clone for ShadowLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shadow: self.shadow.clone(),
+            max_body_size: self.max_body_size,
+            semaphore: self.semaphore.clone(),
+            on_compare: self.on_compare.clone(),
+        }
+    }
+}
+
+impl<S, T> Layer<S> for ShadowLayer<T> {
+    type Service = Shadow<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Shadow {
+            inner,
+            shadow: self.shadow.clone(),
+            max_body_size: self.max_body_size,
+            semaphore: self.semaphore.clone(),
+            on_compare: self.on_compare.clone(),
+        }
+    }
+}
+
+/// Middleware that mirrors requests to a shadow service.
+///
+/// See the [module docs](crate::http::layer::shadow) for more details.
+pub struct Shadow<S, T> {
+    inner: S,
+    shadow: Arc<T>,
+    max_body_size: usize,
+    semaphore: Arc<Semaphore>,
+    on_compare: Option<CompareFn>,
+}
+
+impl<S, T> Shadow<S, T> {
+    /// Create a new [`Shadow`] middleware, mirroring requests to the given shadow service.
+    pub fn new(inner: S, shadow: T) -> Self {
+        ShadowLayer::new(shadow).layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<S: fmt::Debug, T: fmt::Debug> fmt::Debug for Shadow<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shadow")
+            .field("inner", &self.inner)
+            .field("shadow", &self.shadow)
+            .field("max_body_size", &self.max_body_size)
+            .field("available_permits", &self.semaphore.available_permits())
+            .field("on_compare", &self.on_compare.is_some())
+            .finish()
+    }
+}
+
+impl<S: Clone, T> Clone for Shadow<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            shadow: self.shadow.clone(),
+            max_body_size: self.max_body_size,
+            semaphore: self.semaphore.clone(),
+            on_compare: self.on_compare.clone(),
+        }
+    }
+}
+
+impl<S, T, State, ReqBody, ResBody, ShadowBody> Service<State, Request<ReqBody>> for Shadow<S, T>
+where
+    S: Service<State, Request, Response = Response<ResBody>>,
+    T: Service<State, Request, Response = Response<ShadowBody>>,
+    T::Error: Into<BoxError>,
+    State: Send + Sync + 'static,
+    ReqBody: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    ReqBody::Error: Into<BoxError>,
+    ResBody: Send + 'static,
+    ShadowBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        // the permit is held by the background task which serves the shadow request
+        let Ok(permit) = self.semaphore.clone().try_acquire_owned() else {
+            tracing::trace!("shadow: request not mirrored: too many shadow requests in flight");
+            return self.inner.serve(ctx, req.map(Body::new)).await;
+        };
+
+        let (parts, body) = req.into_parts();
+        let mut body = Body::new(body);
+
+        // buffer the body (up to the max size), such that it can be sent to both services,
+        // the data itself is shared (not copied) between the buffered frames and chunks
+        let mut buffered = VecDeque::new();
+        let mut chunks = Vec::new();
+        let mut size = 0;
+        let mut trailers = None;
+        let complete = loop {
+            match body.frame().await {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => {
+                        size += data.len();
+                        buffered.push_back(Ok(Frame::data(data.clone())));
+                        if size > self.max_body_size {
+                            break false;
+                        }
+                        chunks.push(data);
+                    }
+                    Err(frame) => {
+                        // only trailers can follow the data frames
+                        trailers = frame.trailers_ref().cloned();
+                        buffered.push_back(Ok(frame));
+                        break true;
+                    }
+                },
+                Some(Err(err)) => {
+                    buffered.push_back(Err(err));
+                    break false;
+                }
+                None => break true,
+            }
+        };
+
+        if !complete {
+            tracing::trace!(
+                max_body_size = self.max_body_size,
+                "shadow: request body not mirrored: too large or failed to read"
+            );
+            let body = Body::new(PrefixedBody::new(buffered, Some(body)));
+            return self
+                .inner
+                .serve(ctx, Request::from_parts(parts, body))
+                .await;
+        }
+
+        let mut shadow_body = Body::from_stream(futures::stream::iter(
+            chunks.into_iter().map(Ok::<_, BoxError>),
+        ));
+        if let Some(trailers) = trailers {
+            shadow_body = shadow_body.with_trailers(trailers);
+        }
+        let mut shadow_req = Request::new(shadow_body);
+        *shadow_req.method_mut() = parts.method.clone();
+        *shadow_req.uri_mut() = parts.uri.clone();
+        *shadow_req.version_mut() = parts.version;
+        *shadow_req.headers_mut() = parts.headers.clone();
+
+        let (primary_tx, compare) = match self.on_compare.clone() {
+            Some(on_compare) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                (Some(tx), Some((on_compare, rx)))
+            }
+            None => (None, None),
+        };
+
+        let shadow = self.shadow.clone();
+        let shadow_ctx = ctx.clone();
+        ctx.spawn(async move {
+            let _permit = permit;
+            let result = shadow
+                .serve(shadow_ctx, shadow_req)
+                .await
+                .map(|res| ResponseHead::new(&res))
+                .map_err(Into::into);
+            match &result {
+                Ok(head) => tracing::trace!(status = %head.status, "shadow: response received"),
+                Err(err) => tracing::debug!(error = %err, "shadow: service error"),
+            }
+            if let Some((on_compare, primary_rx)) = compare {
+                // the sender is dropped without a value if the inner service is cancelled
+                if let Ok(primary) = primary_rx.await {
+                    on_compare(ShadowComparison {
+                        primary,
+                        shadow: result,
+                    });
+                }
+            }
+        });
+
+        let body = Body::new(PrefixedBody::new(buffered, None));
+        let result = self
+            .inner
+            .serve(ctx, Request::from_parts(parts, body))
+            .await;
+        if let Some(primary_tx) = primary_tx {
+            let _ = primary_tx.send(result.as_ref().ok().map(ResponseHead::new));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+    use tokio::sync::mpsc;
+
+    fn echo() -> impl Service<(), Request, Response = Response, Error = Infallible> {
+        service_fn(|req: Request| async move {
+            let body = req.into_body().collect().await.unwrap().to_bytes();
+            Ok(Response::new(Body::from(body)))
+        })
+    }
+
+    async fn read_body(res: Response) -> String {
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    fn request(body: Body) -> Request {
+        Request::builder()
+            .method("POST")
+            .uri("/api/echo")
+            .header("x-test", "shadow")
+            .body(body)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_shadow_receives_request() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shadow = service_fn(move |req: Request| {
+            let tx = tx.clone();
+            async move {
+                let (parts, body) = req.into_parts();
+                let body = body.collect().await.unwrap().to_bytes();
+                tx.send((parts, body)).unwrap();
+                Ok::<_, Infallible>(Response::new(Body::from("shadow")))
+            }
+        });
+        let service = ShadowLayer::new(shadow).layer(echo());
+
+        let chunks = ["hello", ", ", "world"].map(Ok::<_, Infallible>);
+        let res = service
+            .serve(
+                Context::default(),
+                request(Body::from_stream(futures::stream::iter(chunks))),
+            )
+            .await
+            .unwrap();
+        assert_eq!(read_body(res).await, "hello, world");
+
+        let (parts, body) = rx.recv().await.unwrap();
+        assert_eq!(parts.method, "POST");
+        assert_eq!(parts.uri, "/api/echo");
+        assert_eq!(parts.headers["x-test"], "shadow");
+        assert_eq!(body, "hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_shadow_error_does_not_affect_client() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shadow =
+            service_fn(|_: Request| async { Err::<Response, _>(BoxError::from("shadow failure")) });
+        let service = ShadowLayer::new(shadow)
+            .on_compare(move |comparison| tx.send(comparison).unwrap())
+            .layer(echo());
+
+        let res = service
+            .serve(Context::default(), request(Body::from("ping")))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "ping");
+
+        let comparison = rx.recv().await.unwrap();
+        assert_eq!(comparison.primary_status(), Some(StatusCode::OK));
+        assert_eq!(comparison.shadow_status(), None);
+        assert_eq!(
+            comparison.shadow_error().unwrap().to_string(),
+            "shadow failure"
+        );
+        assert!(!comparison.is_status_match());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_panic_does_not_affect_client() {
+        let shadow = service_fn(|_: Request| async {
+            if true {
+                panic!("shadow panic");
+            }
+            Ok::<Response, Infallible>(Response::new(Body::empty()))
+        });
+        let service = ShadowLayer::new(shadow).layer(echo());
+
+        for _ in 0..2 {
+            let res = service
+                .serve(Context::default(), request(Body::from("ping")))
+                .await
+                .unwrap();
+            assert_eq!(read_body(res).await, "ping");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shadow_compare() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shadow = service_fn(|_: Request| async {
+            Ok::<_, Infallible>(
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .header("x-version", "next")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        });
+        let service = ShadowLayer::new(shadow)
+            .on_compare(move |comparison| tx.send(comparison).unwrap())
+            .layer(echo());
+
+        service
+            .serve(Context::default(), request(Body::empty()))
+            .await
+            .unwrap();
+
+        let comparison = rx.recv().await.unwrap();
+        assert_eq!(comparison.primary_status(), Some(StatusCode::OK));
+        assert_eq!(comparison.shadow_status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(comparison.shadow_headers().unwrap()["x-version"], "next");
+        assert!(comparison.shadow_error().is_none());
+        assert!(!comparison.is_status_match());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_body_too_large() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shadow = service_fn(move |_: Request| {
+            let tx = tx.clone();
+            async move {
+                tx.send(()).unwrap();
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }
+        });
+        let service = ShadowLayer::new(shadow).max_body_size(8).layer(echo());
+
+        let chunks = ["hello", ", ", "world"].map(Ok::<_, Infallible>);
+        let res = service
+            .serve(
+                Context::default(),
+                request(Body::from_stream(futures::stream::iter(chunks))),
+            )
+            .await
+            .unwrap();
+        // the inner service still receives the full body
+        assert_eq!(read_body(res).await, "hello, world");
+
+        // a body within the limit is mirrored
+        let res = service
+            .serve(Context::default(), request(Body::from("hello")))
+            .await
+            .unwrap();
+        assert_eq!(read_body(res).await, "hello");
+
+        rx.recv().await.unwrap();
+        // the shadow service is called only once, and the sender is dropped with it
+        drop(service);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shadow_max_concurrency() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let shadow = service_fn({
+            let release = release.clone();
+            move |req: Request| {
+                let tx = tx.clone();
+                let release = release.clone();
+                async move {
+                    tx.send(read_body(Response::new(req.into_body())).await)
+                        .unwrap();
+                    release.notified().await;
+                    Ok::<_, Infallible>(Response::new(Body::empty()))
+                }
+            }
+        });
+        let service = ShadowLayer::new(shadow).max_concurrency(1).layer(echo());
+
+        for body in ["first", "second"] {
+            let res = service
+                .serve(Context::default(), request(Body::from(body)))
+                .await
+                .unwrap();
+            assert_eq!(read_body(res).await, body);
+        }
+        // the second request is not mirrored while the first one is in flight
+        assert_eq!(rx.recv().await.unwrap(), "first");
+        tokio::task::yield_now().await;
+        assert!(rx.try_recv().is_err());
+
+        // once the first shadow request is done, requests are mirrored again
+        release.notify_one();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let res = service
+            .serve(Context::default(), request(Body::from("third")))
+            .await
+            .unwrap();
+        assert_eq!(read_body(res).await, "third");
+        assert_eq!(rx.recv().await.unwrap(), "third");
+    }
+}
@@ -0,0 +1,279 @@
+//! Middleware that refuses new requests once a graceful shutdown is triggered,
+//! instead of processing them while the server drains.
+//!
+//! Rejected requests get a `503 Service Unavailable` response with a
+//! `Connection: close` header by default, such that clients retry the request
+//! on a new connection (e.g. to another instance), and kept-alive connections
+//! are closed instead of being kept open by new requests.
+//!
+//! By default the global graceful shutdown is observed, using the [`ShutdownGuard`]
+//! found in the [`Context`] (see [`Context::shutdown_signalled`]). Requests served
+//! without a guard are never rejected. Use [`ShutdownRejectLayer::with_token`]
+//! to observe a [`ServiceShutdown`] token instead, e.g. to drain a single mounted service.
+//!
+//! [`ShutdownGuard`]: crate::graceful::ShutdownGuard
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::graceful::ServiceShutdown;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{header, Body, Request, Response, StatusCode};
+//! use rama::http::layer::shutdown_reject::ShutdownRejectLayer;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::default()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let shutdown = ServiceShutdown::new();
+//! let svc = ServiceBuilder::new()
+//!     .layer(ShutdownRejectLayer::with_token(shutdown.clone()))
+//!     .service_fn(handle);
+//!
+//! shutdown.signal();
+//! let response = svc.serve(Context::default(), Request::new(Body::default())).await?;
+//! assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+//! assert_eq!(response.headers()[header::CONNECTION], "close");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::graceful::ServiceShutdown;
+use crate::http::{header, HeaderValue, Request, Response, StatusCode};
+use crate::service::{Context, Layer, Service};
+use futures_util::FutureExt;
+
+/// Layer that applies the [`ShutdownReject`] middleware.
+///
+/// See the [module docs](crate::http::layer::shutdown_reject) for more details.
+#[derive(Debug, Clone)]
+pub struct ShutdownRejectLayer {
+    config: ShutdownRejectConfig,
+}
+
+#[derive(Debug, Clone)]
+struct ShutdownRejectConfig {
+    token: Option<ServiceShutdown>,
+    status: StatusCode,
+    close_connection: bool,
+}
+
+impl ShutdownRejectLayer {
+    /// Create a new [`ShutdownRejectLayer`], rejecting requests once
+    /// the graceful shutdown of the [`Context`] is triggered.
+    pub fn new() -> Self {
+        Self {
+            config: ShutdownRejectConfig {
+                token: None,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                close_connection: true,
+            },
+        }
+    }
+
+    /// Create a new [`ShutdownRejectLayer`], rejecting requests once
+    /// the given [`ServiceShutdown`] token is signalled.
+    pub fn with_token(token: ServiceShutdown) -> Self {
+        let mut layer = Self::new();
+        layer.config.token = Some(token);
+        layer
+    }
+
+    /// Set the status code of the response for rejected requests.
+    ///
+    /// `503 Service Unavailable` by default.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.config.status = status;
+        self
+    }
+
+    /// Close the connection after responding to a rejected request.
+    ///
+    /// Enabled by default.
+    pub fn close_connection(mut self, close: bool) -> Self {
+        self.config.close_connection = close;
+        self
+    }
+}
+
+impl Default for ShutdownRejectLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for ShutdownRejectLayer {
+    type Service = ShutdownReject<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ShutdownReject {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that refuses new requests once a graceful shutdown is triggered.
+///
+/// See the [module docs](crate::http::layer::shutdown_reject) for more details.
+#[derive(Debug, Clone)]
+pub struct ShutdownReject<S> {
+    inner: S,
+    config: ShutdownRejectConfig,
+}
+
+impl<S> ShutdownReject<S> {
+    /// Create a new [`ShutdownReject`] middleware, rejecting requests once
+    /// the graceful shutdown of the [`Context`] is triggered.
+    pub fn new(inner: S) -> Self {
+        ShutdownRejectLayer::new().layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl ShutdownRejectConfig {
+    fn is_shutting_down<State>(&self, ctx: &Context<State>) -> bool {
+        match &self.token {
+            Some(token) => token.is_signalled(),
+            None => ctx.shutdown_signalled().now_or_never().is_some(),
+        }
+    }
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for ShutdownReject<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.config.is_shutting_down(&ctx) {
+            return self.inner.serve(ctx, req).await;
+        }
+
+        tracing::trace!("shutting down: reject request");
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = self.config.status;
+        if self.config.close_connection {
+            res.headers_mut()
+                .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use crate::http::server::HttpServer;
+    use crate::http::Body;
+    use crate::rt::Executor;
+    use crate::service::service_fn;
+    use crate::test_helpers::net::read_http_head;
+    use std::{convert::Infallible, sync::Arc};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn handle(_: Request) -> Result<Response, Infallible> {
+        Ok(Response::new(Body::from("ok")))
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_reject_graceful_context() {
+        let svc = ShutdownRejectLayer::new().layer(service_fn(handle));
+
+        // without a shutdown guard requests are never rejected
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = rx.await;
+        });
+        let ctx = Context::new(Arc::new(()), Executor::graceful(shutdown.guard()));
+
+        let res = svc
+            .serve(ctx.clone(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        tx.send(()).unwrap();
+        ctx.shutdown_signalled().await;
+        let res = svc.serve(ctx, Request::new(Body::empty())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()[header::CONNECTION], "close");
+        shutdown.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_reject_existing_connection() {
+        let token = ServiceShutdown::new();
+        let svc = ShutdownRejectLayer::with_token(token.clone()).layer(service_fn(handle));
+
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        let server = async move {
+            HttpServer::http1()
+                .serve(Context::default(), server_io, svc)
+                .await
+                .unwrap();
+        };
+
+        let client = async move {
+            let request = b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n";
+
+            client_io.write_all(request).await.unwrap();
+            let head = read_http_head(&mut client_io).await;
+            let head = String::from_utf8(head).unwrap();
+            assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{head}");
+            let mut body = [0u8; 2];
+            client_io.read_exact(&mut body).await.unwrap();
+
+            // the connection is kept alive, but the service is shutting down
+            token.signal();
+            client_io.write_all(request).await.unwrap();
+            let mut response = String::new();
+            client_io.read_to_string(&mut response).await.unwrap();
+            assert!(
+                response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+                "{response}"
+            );
+            assert!(response.contains("connection: close\r\n"), "{response}");
+        };
+
+        tokio::join!(server, client);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_reject_config() {
+        let token = ServiceShutdown::new();
+        token.signal();
+        let svc = ShutdownRejectLayer::with_token(token)
+            .status(StatusCode::GONE)
+            .close_connection(false)
+            .layer(service_fn(handle));
+
+        let res = svc
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::GONE);
+        assert!(res.headers().get(header::CONNECTION).is_none());
+    }
+}
@@ -0,0 +1,394 @@
+//! Middleware to rewrite the status code of responses per a configured table,
+//! e.g. as a compatibility shim translating the status codes of an upstream service.
+//!
+//! Only responses with a status code found in the table are rewritten,
+//! all other responses are returned as-is. The body of a rewritten response is kept,
+//! unless a replacement body is configured using [`StatusRewriteLayer::rewrite_with_body`],
+//! in which case the `Content-Length` and `Content-Encoding` headers of the original body are removed.
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::http::layer::status_rewrite::StatusRewriteLayer;
+//! use rama::http::{Body, Request, Response, StatusCode};
+//! use rama::service::{Context, ServiceBuilder, Service};
+//! use rama::error::Error;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     let mut response = Response::new(Body::from("I'm a teapot"));
+//!     *response.status_mut() = StatusCode::IM_A_TEAPOT;
+//!     Ok(response)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let service = ServiceBuilder::new()
+//!     .layer(StatusRewriteLayer::new().rewrite(StatusCode::IM_A_TEAPOT, StatusCode::BAD_REQUEST))
+//!     .service_fn(handle);
+//!
+//! let response = service.serve(Context::default(), Request::new(Body::empty())).await?;
+//! assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{header, Request, Response, StatusCode};
+use crate::service::{Context, Layer, Service};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A single entry of the rewrite table, with a replacement body of type `B`,
+/// which is `()` for tables without any replacement bodies.
+#[derive(Debug, Clone)]
+struct Rewrite<B> {
+    status: StatusCode,
+    body: Option<B>,
+}
+
+type Table<B> = HashMap<StatusCode, Rewrite<B>>;
+
+fn insert_status<B>(table: &mut Table<B>, from: StatusCode, to: StatusCode) {
+    table.insert(
+        from,
+        Rewrite {
+            status: to,
+            body: None,
+        },
+    );
+}
+
+fn insert_body(table: &mut Table<Bytes>, from: StatusCode, to: StatusCode, body: Bytes) {
+    table.insert(
+        from,
+        Rewrite {
+            status: to,
+            body: Some(body),
+        },
+    );
+}
+
+/// Convert a table without replacement bodies into one which can hold them.
+fn with_bodies(table: Table<()>) -> Table<Bytes> {
+    table
+        .into_iter()
+        .map(|(from, rewrite)| {
+            (
+                from,
+                Rewrite {
+                    status: rewrite.status,
+                    body: None,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Layer that applies the [`StatusRewrite`] middleware.
+///
+/// The type parameter `B` is `()` until a replacement body is configured using
+/// [`StatusRewriteLayer::rewrite_with_body`], after which it is [`Bytes`],
+/// such that only then the response body is required to implement `From<Bytes>`.
+///
+/// See the [module docs](crate::http::layer::status_rewrite) for more details.
+#[derive(Debug, Clone)]
+pub struct StatusRewriteLayer<B = ()> {
+    table: Table<B>,
+}
+
+impl Default for StatusRewriteLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusRewriteLayer {
+    /// Create a new [`StatusRewriteLayer`] with an empty table,
+    /// which does not rewrite any response.
+    pub fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+        }
+    }
+
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// replacing their body with the given body.
+    pub fn rewrite_with_body(
+        self,
+        from: StatusCode,
+        to: StatusCode,
+        body: impl Into<Bytes>,
+    ) -> StatusRewriteLayer<Bytes> {
+        StatusRewriteLayer {
+            table: with_bodies(self.table),
+        }
+        .rewrite_with_body(from, to, body)
+    }
+}
+
+impl StatusRewriteLayer<Bytes> {
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// replacing their body with the given body.
+    pub fn rewrite_with_body(
+        mut self,
+        from: StatusCode,
+        to: StatusCode,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        insert_body(&mut self.table, from, to, body.into());
+        self
+    }
+}
+
+impl<B> StatusRewriteLayer<B> {
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// keeping their body.
+    pub fn rewrite(mut self, from: StatusCode, to: StatusCode) -> Self {
+        insert_status(&mut self.table, from, to);
+        self
+    }
+}
+
+impl<S, B: Clone> Layer<S> for StatusRewriteLayer<B> {
+    type Service = StatusRewrite<S, B>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StatusRewrite {
+            inner,
+            table: Arc::new(self.table.clone()),
+        }
+    }
+}
+
+/// Middleware to rewrite the status code of responses per a configured table.
+///
+/// See [`StatusRewriteLayer`] for the meaning of the type parameter `B`,
+/// and the [module docs](crate::http::layer::status_rewrite) for more details.
+#[derive(Debug, Clone)]
+pub struct StatusRewrite<S, B = ()> {
+    inner: S,
+    table: Arc<Table<B>>,
+}
+
+impl<S> StatusRewrite<S> {
+    /// Create a new [`StatusRewrite`] middleware with an empty table,
+    /// which does not rewrite any response.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            table: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// replacing their body with the given body.
+    pub fn rewrite_with_body(
+        self,
+        from: StatusCode,
+        to: StatusCode,
+        body: impl Into<Bytes>,
+    ) -> StatusRewrite<S, Bytes> {
+        let table = Arc::try_unwrap(self.table).unwrap_or_else(|table| (*table).clone());
+        StatusRewrite {
+            inner: self.inner,
+            table: Arc::new(with_bodies(table)),
+        }
+        .rewrite_with_body(from, to, body)
+    }
+}
+
+impl<S> StatusRewrite<S, Bytes> {
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// replacing their body with the given body.
+    pub fn rewrite_with_body(
+        mut self,
+        from: StatusCode,
+        to: StatusCode,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        insert_body(Arc::make_mut(&mut self.table), from, to, body.into());
+        self
+    }
+}
+
+impl<S, B: Clone> StatusRewrite<S, B> {
+    /// Rewrite the responses with the `from` status code to the `to` status code,
+    /// keeping their body.
+    pub fn rewrite(mut self, from: StatusCode, to: StatusCode) -> Self {
+        insert_status(Arc::make_mut(&mut self.table), from, to);
+        self
+    }
+}
+
+impl<S, B> StatusRewrite<S, B> {
+    define_inner_service_accessors!();
+
+    /// Rewrite the status of the given response, returning the replacement body (if any).
+    fn rewrite_status<ResBody>(&self, response: &mut Response<ResBody>) -> Option<&B> {
+        let rewrite = self.table.get(&response.status())?;
+        tracing::trace!(from = %response.status(), to = %rewrite.status, "rewrite response status");
+        *response.status_mut() = rewrite.status;
+        rewrite.body.as_ref()
+    }
+}
+
+impl<State, S, ReqBody, ResBody> Service<State, Request<ReqBody>> for StatusRewrite<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut response = self.inner.serve(ctx, req).await?;
+        self.rewrite_status(&mut response);
+        Ok(response)
+    }
+}
+
+impl<State, S, ReqBody, ResBody> Service<State, Request<ReqBody>> for StatusRewrite<S, Bytes>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: Send + 'static,
+    ResBody: From<Bytes> + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut response = self.inner.serve(ctx, req).await?;
+        if let Some(body) = self.rewrite_status(&mut response).cloned() {
+            response.headers_mut().remove(header::CONTENT_LENGTH);
+            response.headers_mut().remove(header::CONTENT_ENCODING);
+            *response.body_mut() = ResBody::from(body);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{dep::http_body_util::BodyExt, Body};
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    async fn serve<S>(service: &S, status: StatusCode) -> (StatusCode, String)
+    where
+        S: Service<(), Request, Response = Response, Error = Infallible>,
+    {
+        let req = Request::builder()
+            .uri(format!("/{}", status.as_u16()))
+            .body(Body::empty())
+            .unwrap();
+        let res = service.serve(Context::default(), req).await.unwrap();
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// Respond with the status code found in the path, and the original status as body.
+    async fn handle(req: Request) -> Result<Response, Infallible> {
+        let status = req.uri().path()[1..].parse::<u16>().unwrap();
+        let mut res = Response::new(Body::from(status.to_string()));
+        *res.status_mut() = StatusCode::from_u16(status).unwrap();
+        Ok(res)
+    }
+
+    #[tokio::test]
+    async fn test_status_rewrite() {
+        let service = StatusRewriteLayer::new()
+            .rewrite(StatusCode::IM_A_TEAPOT, StatusCode::BAD_REQUEST)
+            .layer(service_fn(handle));
+
+        assert_eq!(
+            serve(&service, StatusCode::IM_A_TEAPOT).await,
+            (StatusCode::BAD_REQUEST, "418".to_owned())
+        );
+        // other status codes are left untouched
+        assert_eq!(
+            serve(&service, StatusCode::OK).await,
+            (StatusCode::OK, "200".to_owned())
+        );
+        assert_eq!(
+            serve(&service, StatusCode::BAD_REQUEST).await,
+            (StatusCode::BAD_REQUEST, "400".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_rewrite_with_body() {
+        let service = StatusRewriteLayer::new()
+            .rewrite_with_body(
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::TOO_MANY_REQUESTS,
+                "slow down",
+            )
+            .layer(service_fn(handle));
+
+        assert_eq!(
+            serve(&service, StatusCode::SERVICE_UNAVAILABLE).await,
+            (StatusCode::TOO_MANY_REQUESTS, "slow down".to_owned())
+        );
+        assert_eq!(
+            serve(&service, StatusCode::NOT_FOUND).await,
+            (StatusCode::NOT_FOUND, "404".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_rewrite_mixed_table() {
+        let service = StatusRewriteLayer::new()
+            .rewrite(StatusCode::IM_A_TEAPOT, StatusCode::BAD_REQUEST)
+            .rewrite_with_body(
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::TOO_MANY_REQUESTS,
+                "slow down",
+            )
+            .rewrite(StatusCode::NOT_FOUND, StatusCode::GONE)
+            .layer(service_fn(handle));
+
+        assert_eq!(
+            serve(&service, StatusCode::IM_A_TEAPOT).await,
+            (StatusCode::BAD_REQUEST, "418".to_owned())
+        );
+        assert_eq!(
+            serve(&service, StatusCode::SERVICE_UNAVAILABLE).await,
+            (StatusCode::TOO_MANY_REQUESTS, "slow down".to_owned())
+        );
+        assert_eq!(
+            serve(&service, StatusCode::NOT_FOUND).await,
+            (StatusCode::GONE, "404".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_rewrite_any_body() {
+        // the body only has to implement `From<Bytes>` when it can be replaced
+        let service = StatusRewrite::new(service_fn(|_: Request<()>| async {
+            let mut res = Response::new(());
+            *res.status_mut() = StatusCode::IM_A_TEAPOT;
+            Ok::<_, Infallible>(res)
+        }))
+        .rewrite(StatusCode::IM_A_TEAPOT, StatusCode::BAD_REQUEST);
+
+        let res = service
+            .serve(Context::default(), Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
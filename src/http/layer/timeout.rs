@@ -13,6 +13,12 @@
 //! response. That means if your service's error type is [`Infallible`] it will still be
 //! [`Infallible`] after applying this middleware.
 //!
+//! In case a [`Deadline`] is present in the [`Context`], the timeout
+//! is shrunk to the time remaining until that deadline.
+//!
+//! [`Deadline`]: crate::service::layer::budget::Deadline
+//! [`Context`]: crate::service::Context
+//!
 //! # Example
 //!
 //! ```
@@ -43,7 +49,7 @@
 use std::time::Duration;
 
 use crate::http::{Request, Response, StatusCode};
-use crate::service::{Context, Layer, Service};
+use crate::service::{layer::budget::Deadline, Context, Layer, Service};
 
 /// Layer that applies the [`Timeout`] middleware which apply a timeout to requests.
 ///
@@ -111,9 +117,10 @@ where
         ctx: Context<State>,
         req: Request<ReqBody>,
     ) -> Result<Self::Response, Self::Error> {
+        let timeout = Deadline::clamp_timeout(&ctx, self.timeout);
         tokio::select! {
             res = self.inner.serve(ctx, req) => res,
-            _ = tokio::time::sleep(self.timeout) => {
+            _ = tokio::time::sleep(timeout) => {
                 let mut res = Response::new(ResBody::default());
                 *res.status_mut() = StatusCode::REQUEST_TIMEOUT;
                 Ok(res)
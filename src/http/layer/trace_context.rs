@@ -0,0 +1,492 @@
+//! Extract and propagate the W3C [Trace Context] of requests, for distributed tracing.
+//!
+//! The [`TraceContextLayer`] extracts the `traceparent` (and `tracestate`) headers
+//! of incoming requests, continuing the trace of the caller, or starts a new trace
+//! in case no (valid) trace context is found. The resulting [`TraceContext`] is inserted
+//! into the [`Context`] and the inner service is served within a [`tracing`] span
+//! which records the trace and span ids.
+//!
+//! The [`PropagateTraceContextLayer`] is to be used for outbound (client) requests,
+//! and injects the [`TraceContext`] found in the [`Context`] into the request headers,
+//! such that the next service continues the same trace.
+//!
+//! [Trace Context]: https://www.w3.org/TR/trace-context/
+//! [`Context`]: crate::service::Context
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::layer::trace_context::{TraceContext, TraceContextLayer, PropagateTraceContextLayer};
+//! use rama::http::{Body, Request, Response};
+//! use rama::service::{Context, Service, ServiceBuilder, service_fn};
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Infallible> {
+//! // an (outbound) client, which propagates the trace context
+//! let client = ServiceBuilder::new()
+//!     .layer(PropagateTraceContextLayer::new())
+//!     .service_fn(|req: Request| async move {
+//!         let traceparent = req.headers()["traceparent"].to_str().unwrap().to_owned();
+//!         Ok::<_, Infallible>(Response::new(Body::from(traceparent)))
+//!     });
+//!
+//! let server = ServiceBuilder::new()
+//!     .layer(TraceContextLayer::new())
+//!     .service_fn(move |ctx: Context<()>, _req: Request| {
+//!         let client = client.clone();
+//!         async move {
+//!             let trace_id = ctx.get::<TraceContext>().unwrap().trace_id();
+//!             assert_eq!(trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+//!             client.serve(ctx, Request::new(Body::empty())).await
+//!         }
+//!     });
+//!
+//! let request = Request::builder()
+//!     .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+//!     .body(Body::empty())
+//!     .unwrap();
+//! server.serve(Context::default(), request).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Request,
+};
+use crate::service::{Context, Layer, Service};
+use std::fmt;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// The name of the header containing the trace id, parent id and trace flags.
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+/// The name of the header containing vendor-specific trace information.
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// The W3C trace context of a request.
+///
+/// Found in the [`Context`] of requests served by the [`TraceContextService`].
+///
+/// [`Context`]: crate::service::Context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    flags: u8,
+    trace_state: Option<HeaderValue>,
+}
+
+impl TraceContext {
+    /// Create a new (sampled) [`TraceContext`], starting a new trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: Uuid::new_v4().as_u128(),
+            span_id: new_span_id(),
+            parent_span_id: None,
+            flags: FLAG_SAMPLED,
+            trace_state: None,
+        }
+    }
+
+    /// Extract the [`TraceContext`] from the `traceparent` and `tracestate` headers,
+    /// returning `None` in case no valid `traceparent` header is found.
+    ///
+    /// The span id of the returned context is the one of the caller.
+    /// Use [`TraceContext::child`] to create the context for a new span within this trace.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let traceparent = headers.get(TRACEPARENT)?.to_str().ok()?;
+        let mut trace_context = Self::parse_traceparent(traceparent)?;
+        trace_context.trace_state = headers.get(TRACESTATE).cloned();
+        Some(trace_context)
+    }
+
+    fn parse_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parse_hex(parts.next()?, 2)? as u8;
+        let trace_id = parse_hex(parts.next()?, 32)?;
+        let span_id = parse_hex(parts.next()?, 16)? as u64;
+        let flags = parse_hex(parts.next()?, 2)? as u8;
+
+        // future versions can append fields, which are to be ignored
+        if version == 0xff || (version == 0 && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_span_id: None,
+            flags,
+            trace_state: None,
+        })
+    }
+
+    /// Create the context for a new span within the same trace,
+    /// which has the span of this context as its parent.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: new_span_id(),
+            parent_span_id: Some(self.span_id),
+            ..self.clone()
+        }
+    }
+
+    /// The id of the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The id of the span.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// The id of the parent span, if known.
+    pub fn parent_span_id(&self) -> Option<u64> {
+        self.parent_span_id
+    }
+
+    /// Returns `true` if the caller (possibly) recorded the trace.
+    pub fn sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// The vendor-specific trace information, if any.
+    pub fn trace_state(&self) -> Option<&HeaderValue> {
+        self.trace_state.as_ref()
+    }
+
+    /// Inject this context into the given headers,
+    /// overwriting any existing `traceparent` and `tracestate` headers.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            TRACEPARENT,
+            HeaderValue::try_from(self.to_string()).expect("valid traceparent header value"),
+        );
+        match &self.trace_state {
+            Some(trace_state) => {
+                headers.insert(TRACESTATE, trace_state.clone());
+            }
+            None => {
+                headers.remove(TRACESTATE);
+            }
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    /// Formats the context as a `traceparent` header value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+fn new_span_id() -> u64 {
+    (Uuid::new_v4().as_u128() as u64).max(1)
+}
+
+/// Parse a lowercase hex value of exactly the given length.
+fn parse_hex(value: &str, len: usize) -> Option<u128> {
+    if value.len() != len
+        || !value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return None;
+    }
+    u128::from_str_radix(value, 16).ok()
+}
+
+/// Extract the [`TraceContext`] of incoming requests, and serve them within a span.
+///
+/// This layer applies the [`TraceContextService`] middleware.
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct TraceContextLayer;
+
+impl TraceContextLayer {
+    /// Create a new [`TraceContextLayer`].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContextService::new(inner)
+    }
+}
+
+/// Extract the [`TraceContext`] of incoming requests, and serve them within a span.
+///
+/// Requests without a (valid) `traceparent` header start a new trace.
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone)]
+pub struct TraceContextService<S> {
+    inner: S,
+}
+
+impl<S> TraceContextService<S> {
+    /// Create a new [`TraceContextService`].
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, S, Body> Service<State, Request<Body>> for TraceContextService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<Body>>,
+    Body: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request<Body>,
+    ) -> Result<Self::Response, Self::Error> {
+        let trace_context = match TraceContext::from_headers(req.headers()) {
+            Some(remote) => remote.child(),
+            None => TraceContext::new_root(),
+        };
+
+        let span = tracing::info_span!(
+            "trace_context",
+            trace_id = %format_args!("{:032x}", trace_context.trace_id),
+            span_id = %format_args!("{:016x}", trace_context.span_id),
+            parent_span_id = trace_context
+                .parent_span_id
+                .map(|id| tracing::field::display(format!("{:016x}", id))),
+        );
+        ctx.insert(trace_context);
+
+        self.inner.serve(ctx, req).instrument(span).await
+    }
+}
+
+/// Propagate the [`TraceContext`] to outbound requests.
+///
+/// This layer applies the [`PropagateTraceContext`] middleware.
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct PropagateTraceContextLayer;
+
+impl PropagateTraceContextLayer {
+    /// Create a new [`PropagateTraceContextLayer`].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for PropagateTraceContextLayer {
+    type Service = PropagateTraceContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PropagateTraceContext::new(inner)
+    }
+}
+
+/// Propagate the [`TraceContext`] to outbound requests.
+///
+/// In case a [`TraceContext`] is found in the [`Context`], a child span is created
+/// and injected into the `traceparent` and `tracestate` headers of the request.
+/// Requests are left untouched in case no [`TraceContext`] is found.
+///
+/// See the [module docs](self) for more details.
+#[derive(Debug, Clone)]
+pub struct PropagateTraceContext<S> {
+    inner: S,
+}
+
+impl<S> PropagateTraceContext<S> {
+    /// Create a new [`PropagateTraceContext`].
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, S, Body> Service<State, Request<Body>> for PropagateTraceContext<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request<Body>>,
+    Body: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        mut req: Request<Body>,
+    ) -> Result<Self::Response, Self::Error> {
+        if let Some(trace_context) = ctx.get::<TraceContext>() {
+            trace_context.child().inject(req.headers_mut());
+        }
+        self.inner.serve(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Body, Response};
+    use crate::service::{service_fn, ServiceBuilder};
+    use std::convert::Infallible;
+
+    const TRACE_ID: u128 = 0x4bf92f3577b34da6a3ce929d0e0e4736;
+    const SPAN_ID: u64 = 0x00f067aa0ba902b7;
+    const TRACEPARENT_VALUE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(traceparent: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, traceparent.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_trace_context_from_headers() {
+        let mut map = headers(TRACEPARENT_VALUE);
+        map.insert(TRACESTATE, "congo=t61rcWkgMzE".parse().unwrap());
+
+        let trace_context = TraceContext::from_headers(&map).unwrap();
+        assert_eq!(trace_context.trace_id(), TRACE_ID);
+        assert_eq!(trace_context.span_id(), SPAN_ID);
+        assert!(trace_context.sampled());
+        assert_eq!(trace_context.trace_state().unwrap(), "congo=t61rcWkgMzE");
+        assert_eq!(trace_context.to_string(), TRACEPARENT_VALUE);
+
+        // future versions can have additional fields
+        let trace_context = TraceContext::from_headers(&headers(
+            "cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-what-the-future-holds",
+        ))
+        .unwrap();
+        assert_eq!(trace_context.trace_id(), TRACE_ID);
+        assert!(!trace_context.sampled());
+    }
+
+    #[test]
+    fn test_trace_context_from_invalid_headers() {
+        assert!(TraceContext::from_headers(&HeaderMap::new()).is_none());
+        for traceparent in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            assert!(
+                TraceContext::from_headers(&headers(traceparent)).is_none(),
+                "{}",
+                traceparent
+            );
+        }
+    }
+
+    #[test]
+    fn test_trace_context_child() {
+        let root = TraceContext::new_root();
+        let child = root.child();
+        assert_eq!(child.trace_id(), root.trace_id());
+        assert_ne!(child.span_id(), root.span_id());
+        assert_eq!(child.parent_span_id(), Some(root.span_id()));
+    }
+
+    #[tokio::test]
+    async fn test_trace_context_propagation() {
+        // the outbound client echoes the trace context headers it received
+        let client = ServiceBuilder::new()
+            .layer(PropagateTraceContextLayer::new())
+            .service(service_fn(|req: Request| async move {
+                let mut res = Response::new(Body::empty());
+                res.headers_mut().extend(
+                    req.headers()
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone())),
+                );
+                Ok::<_, Infallible>(res)
+            }));
+
+        let server = ServiceBuilder::new()
+            .layer(TraceContextLayer::new())
+            .service(service_fn(move |ctx: Context<()>, _req: Request| {
+                let client = client.clone();
+                async move {
+                    let trace_context = ctx.get::<TraceContext>().unwrap().clone();
+                    let res = client.serve(ctx, Request::new(Body::empty())).await?;
+                    Ok::<_, Infallible>((trace_context, res))
+                }
+            }));
+
+        let request = Request::builder()
+            .header(TRACEPARENT, TRACEPARENT_VALUE)
+            .header(TRACESTATE, "congo=t61rcWkgMzE")
+            .body(Body::empty())
+            .unwrap();
+        let (trace_context, res) = server.serve(Context::default(), request).await.unwrap();
+
+        // the incoming trace is continued
+        assert_eq!(trace_context.trace_id(), TRACE_ID);
+        assert_eq!(trace_context.parent_span_id(), Some(SPAN_ID));
+        assert_ne!(trace_context.span_id(), SPAN_ID);
+
+        // and propagated to the outbound request, as a child of the server span
+        let propagated = TraceContext::from_headers(res.headers()).unwrap();
+        assert_eq!(propagated.trace_id(), TRACE_ID);
+        assert_ne!(propagated.span_id(), trace_context.span_id());
+        assert_eq!(res.headers()[TRACESTATE], "congo=t61rcWkgMzE");
+    }
+
+    #[tokio::test]
+    async fn test_trace_context_new_root() {
+        let server = TraceContextLayer::new().layer(service_fn(
+            |ctx: Context<()>, _req: Request| async move {
+                Ok::<_, Infallible>(ctx.get::<TraceContext>().unwrap().clone())
+            },
+        ));
+
+        let request = Request::builder()
+            .header(TRACEPARENT, "invalid")
+            .body(Body::empty())
+            .unwrap();
+        let trace_context = server.serve(Context::default(), request).await.unwrap();
+        assert_ne!(trace_context.trace_id(), 0);
+        assert_eq!(trace_context.parent_span_id(), None);
+        assert!(trace_context.sampled());
+
+        // no trace context to propagate: request is untouched
+        let client =
+            PropagateTraceContextLayer::new().layer(service_fn(|req: Request| async move {
+                Ok::<_, Infallible>(req.headers().contains_key(TRACEPARENT))
+            }));
+        assert!(!client
+            .serve(Context::default(), Request::new(Body::empty()))
+            .await
+            .unwrap());
+    }
+}
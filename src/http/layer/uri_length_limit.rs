@@ -0,0 +1,196 @@
+//! Middleware that rejects requests with an overly long target URI,
+//! protecting inner services (e.g. routers and query parsers) against them.
+//!
+//! The length of the target is the length of the path and query of the request URI,
+//! or the length of its authority for requests without a path (e.g. `CONNECT` requests).
+//! The scheme and authority of absolute URIs are not taken into account, such that
+//! http/1.1 and http/2 requests for the same resource have the same length.
+//!
+//! Rejected requests get a `414 URI Too Long` response. This layer is meant to be applied
+//! before any routing, such that no inner service sees requests with overly long URIs.
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use rama::error::Error;
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::http::{Body, Request, Response, StatusCode};
+//! use rama::http::layer::uri_length_limit::UriLengthLimitLayer;
+//!
+//! async fn handle(_: Request) -> Result<Response, Infallible> {
+//!     Ok(Response::new(Body::default()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let svc = ServiceBuilder::new()
+//!     .layer(UriLengthLimitLayer::new(16))
+//!     .service_fn(handle);
+//!
+//! let req = Request::builder().uri("/search?q=rama").body(Body::default())?;
+//! let response = svc.serve(Context::default(), req).await?;
+//! assert_eq!(response.status(), StatusCode::OK);
+//!
+//! let req = Request::builder().uri("/search?q=rama+http").body(Body::default())?;
+//! let response = svc.serve(Context::default(), req).await?;
+//! assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::{Request, Response, StatusCode, Uri};
+use crate::service::{Context, Layer, Service};
+
+/// Layer that applies the [`UriLengthLimit`] middleware.
+///
+/// See the [module docs](crate::http::layer::uri_length_limit) for more details.
+#[derive(Debug, Clone)]
+pub struct UriLengthLimitLayer {
+    max_length: usize,
+}
+
+impl UriLengthLimitLayer {
+    /// Create a new [`UriLengthLimitLayer`], rejecting requests
+    /// of which the target is longer than the given number of bytes.
+    pub fn new(max_length: usize) -> Self {
+        Self { max_length }
+    }
+}
+
+impl<S> Layer<S> for UriLengthLimitLayer {
+    type Service = UriLengthLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UriLengthLimit {
+            inner,
+            max_length: self.max_length,
+        }
+    }
+}
+
+/// Middleware that rejects requests with an overly long target URI.
+///
+/// See the [module docs](crate::http::layer::uri_length_limit) for more details.
+#[derive(Debug, Clone)]
+pub struct UriLengthLimit<S> {
+    inner: S,
+    max_length: usize,
+}
+
+impl<S> UriLengthLimit<S> {
+    /// Create a new [`UriLengthLimit`] middleware, rejecting requests
+    /// of which the target is longer than the given number of bytes.
+    pub fn new(inner: S, max_length: usize) -> Self {
+        UriLengthLimitLayer::new(max_length).layer(inner)
+    }
+
+    define_inner_service_accessors!();
+}
+
+/// The length of the request target of the given URI.
+fn target_length(uri: &Uri) -> usize {
+    match uri.path_and_query() {
+        Some(path_and_query) => path_and_query.as_str().len(),
+        None => uri
+            .authority()
+            .map(|authority| authority.as_str().len())
+            .unwrap_or_default(),
+    }
+}
+
+impl<ReqBody, ResBody, S, State> Service<State, Request<ReqBody>> for UriLengthLimit<S>
+where
+    S: Service<State, Request<ReqBody>, Response = Response<ResBody>>,
+    State: Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<ReqBody>,
+    ) -> Result<Self::Response, Self::Error> {
+        let length = target_length(req.uri());
+        if length <= self.max_length {
+            return self.inner.serve(ctx, req).await;
+        }
+
+        tracing::debug!(
+            length,
+            max_length = self.max_length,
+            "request uri exceeds the length limit"
+        );
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = StatusCode::URI_TOO_LONG;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Body, Method};
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+
+    async fn serve(layer: &UriLengthLimitLayer, req: Request) -> StatusCode {
+        let svc = layer.layer(service_fn(|_: Request| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        svc.serve(Context::default(), req).await.unwrap().status()
+    }
+
+    fn request(uri: &str) -> Request {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_uri_length_limit_under_limit() {
+        let layer = UriLengthLimitLayer::new(32);
+        for uri in [
+            "/",
+            "/users/42?expand=true",
+            "/exactly/32/bytes/long/path?q=12",
+            // only the path and query are taken into account
+            "https://a.very.long.host.name.example.com/users/42",
+        ] {
+            assert_eq!(serve(&layer, request(uri)).await, StatusCode::OK, "{uri}");
+        }
+
+        let req = Request::builder()
+            .method(Method::CONNECT)
+            .uri("example.com:443")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(serve(&layer, req).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_uri_length_limit_over_limit() {
+        let layer = UriLengthLimitLayer::new(32);
+        let long_query = format!("/search?q={}", "a".repeat(16 * 1024));
+        for uri in [
+            "/exactly/33/bytes/long/path?q=123",
+            "/a/very/long/path/to/some/nested/resource",
+            "https://example.com/a/very/long/path/to/some/nested/resource",
+            long_query.as_str(),
+        ] {
+            assert_eq!(
+                serve(&layer, request(uri)).await,
+                StatusCode::URI_TOO_LONG,
+                "{uri}"
+            );
+        }
+
+        let req = Request::builder()
+            .method(Method::CONNECT)
+            .uri("a.very.long.host.name.example.com:443")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(serve(&layer, req).await, StatusCode::URI_TOO_LONG);
+    }
+}
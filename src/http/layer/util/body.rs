@@ -0,0 +1,76 @@
+use crate::error::BoxError;
+use crate::http::dep::http_body_util::BodyExt;
+use crate::http::Body;
+use bytes::{Bytes, BytesMut};
+use futures_util::{stream, StreamExt};
+
+/// A response body buffered up to a size limit, see [`buffer_body`].
+pub(crate) enum BufferedBody {
+    /// The entire body, which did not exceed the limit.
+    Complete(Bytes),
+    /// The body exceeded the limit, and is to be streamed instead,
+    /// including the data which got buffered before the limit was reached.
+    TooLarge(Body),
+}
+
+/// Buffer the data of the given body, as long as it does not exceed `limit` bytes.
+///
+/// Trailers are discarded.
+pub(crate) async fn buffer_body<B>(body: B, limit: usize) -> Result<BufferedBody, BoxError>
+where
+    B: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<BoxError>,
+{
+    if body.size_hint().lower() > limit as u64 {
+        return Ok(BufferedBody::TooLarge(Body::new(body)));
+    }
+
+    let mut body = Body::new(body);
+    let mut buffered = BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let Ok(data) = frame?.into_data() else {
+            continue;
+        };
+        buffered.extend_from_slice(&data);
+        if buffered.len() > limit {
+            let buffered = stream::once(async move { Ok(buffered.freeze()) });
+            return Ok(BufferedBody::TooLarge(Body::from_stream(
+                buffered.chain(body.into_data_stream()),
+            )));
+        }
+    }
+    Ok(BufferedBody::Complete(buffered.freeze()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(body: Body) -> Bytes {
+        body.collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_buffer_body_complete() {
+        match buffer_body(Body::from("hello"), 5).await.unwrap() {
+            BufferedBody::Complete(data) => assert_eq!(data, "hello"),
+            BufferedBody::TooLarge(_) => panic!("body is within the limit"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buffer_body_too_large() {
+        // known to be too large upfront
+        match buffer_body(Body::from("hello"), 4).await.unwrap() {
+            BufferedBody::Complete(_) => panic!("body exceeds the limit"),
+            BufferedBody::TooLarge(body) => assert_eq!(collect(body).await, "hello"),
+        }
+
+        // only found to be too large while buffering
+        let chunks = stream::iter(["he", "ll", "o ", "world"].map(Ok::<_, BoxError>));
+        match buffer_body(Body::from_stream(chunks), 5).await.unwrap() {
+            BufferedBody::Complete(_) => panic!("body exceeds the limit"),
+            BufferedBody::TooLarge(body) => assert_eq!(collect(body).await, "hello world"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::http::{header, HeaderName};
+
+/// How the repeated field lines of a header can be combined,
+/// shared by the middlewares which normalize or finalize headers,
+/// such that they agree with each other regardless of their order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeaderClass {
+    /// The header is a comma-separated list,
+    /// of which the field lines can be joined using `", "`.
+    List,
+    /// The `Cookie` header, of which the field lines can be joined using `"; "`.
+    Cookie,
+    /// The header only allows a single value, such that repeated field lines
+    /// are either duplicates or conflicting (e.g. `Content-Type`).
+    SingleValued,
+    /// The header has to be sent as separate field lines, given that its values
+    /// cannot be combined into a single field line (e.g. `Set-Cookie`).
+    Multiline,
+}
+
+/// Headers of which the values cannot be combined into a single field line.
+static MULTILINE_HEADERS: [HeaderName; 3] = [
+    header::SET_COOKIE,
+    header::WWW_AUTHENTICATE,
+    header::PROXY_AUTHENTICATE,
+];
+
+/// Headers which only allow a single value.
+static SINGLE_VALUED_HEADERS: [HeaderName; 23] = [
+    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+    header::ACCESS_CONTROL_MAX_AGE,
+    header::AGE,
+    header::AUTHORIZATION,
+    header::CONTENT_LENGTH,
+    header::CONTENT_LOCATION,
+    header::CONTENT_RANGE,
+    header::CONTENT_TYPE,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+    header::HOST,
+    header::IF_MODIFIED_SINCE,
+    header::IF_RANGE,
+    header::IF_UNMODIFIED_SINCE,
+    header::LAST_MODIFIED,
+    header::LOCATION,
+    header::MAX_FORWARDS,
+    header::PROXY_AUTHORIZATION,
+    header::REFERER,
+    header::RETRY_AFTER,
+    header::SERVER,
+];
+
+impl HeaderClass {
+    /// Get the class of the header with the given name,
+    /// defaulting to [`HeaderClass::List`] for unknown headers.
+    pub(crate) fn of(name: &HeaderName) -> Self {
+        if MULTILINE_HEADERS.contains(name) {
+            HeaderClass::Multiline
+        } else if SINGLE_VALUED_HEADERS.contains(name) {
+            HeaderClass::SingleValued
+        } else if name == header::COOKIE {
+            HeaderClass::Cookie
+        } else {
+            HeaderClass::List
+        }
+    }
+}
@@ -3,4 +3,8 @@
 #[cfg(feature = "compression")]
 pub(crate) mod compression;
 
+pub(crate) mod body;
+
 pub(crate) mod content_encoding;
+
+pub(crate) mod header_class;
@@ -0,0 +1,69 @@
+use super::ValidateRequest;
+use crate::{
+    http::dep::http_body::Body,
+    http::{matcher::HeaderBudgetFilter, Request, Response, StatusCode},
+    service::Context,
+};
+use std::{fmt, marker::PhantomData};
+
+/// Type that validates the total size and count of the request headers,
+/// using a [`HeaderBudgetFilter`].
+///
+/// Requests exceeding the budget are rejected with a
+/// `431 Request Header Fields Too Large` response.
+pub struct HeaderBudget<ResBody = crate::http::Body> {
+    filter: HeaderBudgetFilter,
+    _ty: PhantomData<fn() -> ResBody>,
+}
+
+impl<ResBody> HeaderBudget<ResBody> {
+    /// Create a new `HeaderBudget`.
+    pub(super) fn new(filter: HeaderBudgetFilter) -> Self
+    where
+        ResBody: Body + Default,
+    {
+        Self {
+            filter,
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<ResBody> Clone for HeaderBudget<ResBody> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<ResBody> fmt::Debug for HeaderBudget<ResBody> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderBudget")
+            .field("filter", &self.filter)
+            .finish()
+    }
+}
+
+impl<S, B, ResBody> ValidateRequest<S, B> for HeaderBudget<ResBody>
+where
+    S: Send + Sync + 'static,
+    B: Send + Sync + 'static,
+    ResBody: Body + Default + Send + 'static,
+{
+    type ResponseBody = ResBody;
+
+    async fn validate(
+        &self,
+        ctx: Context<S>,
+        req: Request<B>,
+    ) -> Result<(Context<S>, Request<B>), Response<Self::ResponseBody>> {
+        if self.filter.is_within_budget(req.headers()) {
+            return Ok((ctx, req));
+        }
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE;
+        Err(res)
+    }
+}
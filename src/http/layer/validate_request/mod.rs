@@ -135,11 +135,19 @@
 //! ```
 
 mod accept_header;
+mod header_budget;
+mod require_content_type;
+mod require_headers;
 mod validate;
 mod validate_fn;
 mod validate_request_header;
 
 pub use accept_header::AcceptHeader;
+pub use header_budget::HeaderBudget;
+pub use require_content_type::RequireContentType;
+pub use require_headers::RequireHeaders;
 pub use validate::ValidateRequest;
 pub use validate_fn::{BoxValidateRequestFn, ValidateRequestFn};
-pub use validate_request_header::{ValidateRequestHeader, ValidateRequestHeaderLayer};
+pub use validate_request_header::{
+    RequireContentTypeLayer, RequireHeadersLayer, ValidateRequestHeader, ValidateRequestHeaderLayer,
+};
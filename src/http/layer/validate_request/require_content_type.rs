@@ -0,0 +1,124 @@
+use super::ValidateRequest;
+use crate::{
+    http::dep::{
+        http_body::Body,
+        mime::{self, Mime},
+    },
+    http::{header, Method, Request, Response, StatusCode},
+    service::Context,
+};
+use std::{fmt, marker::PhantomData, sync::Arc};
+
+/// Type that validates the `Content-Type` of mutating requests (`POST`, `PUT` and `PATCH`)
+/// which have a body, to be one of a set of allowed media types.
+///
+/// Requests with a missing or other `Content-Type` are rejected with a
+/// `415 Unsupported Media Type` response. Requests using any other method,
+/// as well as requests without a body, are not validated.
+///
+/// Media types are compared without their parameters (e.g. `charset`),
+/// an allowed type of the form `type/*` allows any subtype of that type.
+pub struct RequireContentType<ResBody = crate::http::Body> {
+    allowed: Arc<Vec<Mime>>,
+    _ty: PhantomData<fn() -> ResBody>,
+}
+
+impl<ResBody> RequireContentType<ResBody> {
+    /// Create a new `RequireContentType`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the given types is not in the form `type/subtype`,
+    /// such as `application/json`.
+    pub(super) fn new<I, T>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+        ResBody: Body + Default,
+    {
+        let allowed = allowed
+            .into_iter()
+            .map(|value| {
+                value
+                    .as_ref()
+                    .parse::<Mime>()
+                    .expect("value is not a valid media type")
+            })
+            .collect();
+        Self {
+            allowed: Arc::new(allowed),
+            _ty: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the given `Content-Type` value is one of the allowed types.
+    pub fn is_allowed(&self, content_type: &Mime) -> bool {
+        self.allowed.iter().any(|allowed| {
+            allowed.type_() == content_type.type_()
+                && (allowed.subtype() == mime::STAR || allowed.subtype() == content_type.subtype())
+        })
+    }
+}
+
+impl<ResBody> Clone for RequireContentType<ResBody> {
+    fn clone(&self) -> Self {
+        Self {
+            allowed: self.allowed.clone(),
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<ResBody> fmt::Debug for RequireContentType<ResBody> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequireContentType")
+            .field("allowed", &self.allowed)
+            .finish()
+    }
+}
+
+fn is_mutating(method: &Method) -> bool {
+    method == Method::POST || method == Method::PUT || method == Method::PATCH
+}
+
+fn has_body<B: Body>(req: &Request<B>) -> bool {
+    let declared_empty = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim() == "0")
+        .unwrap_or_default();
+    !(declared_empty || req.body().is_end_stream() || req.body().size_hint().exact() == Some(0))
+}
+
+impl<S, B, ResBody> ValidateRequest<S, B> for RequireContentType<ResBody>
+where
+    S: Send + Sync + 'static,
+    B: Body + Send + Sync + 'static,
+    ResBody: Body + Default + Send + 'static,
+{
+    type ResponseBody = ResBody;
+
+    async fn validate(
+        &self,
+        ctx: Context<S>,
+        req: Request<B>,
+    ) -> Result<(Context<S>, Request<B>), Response<Self::ResponseBody>> {
+        if !is_mutating(req.method()) || !has_body(&req) {
+            return Ok((ctx, req));
+        }
+        let allowed = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Mime>().ok())
+            .map(|content_type| self.is_allowed(&content_type))
+            .unwrap_or_default();
+        if allowed {
+            return Ok((ctx, req));
+        }
+        let mut res = Response::new(ResBody::default());
+        *res.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+        Err(res)
+    }
+}
@@ -0,0 +1,90 @@
+use super::ValidateRequest;
+use crate::{
+    http::dep::http_body::Body,
+    http::{header, HeaderName, HeaderValue, Request, Response, StatusCode},
+    service::Context,
+};
+use std::{fmt, marker::PhantomData, sync::Arc};
+
+/// Type that validates the presence of a set of required request headers.
+///
+/// Requests missing any of the required headers are rejected with a
+/// `400 Bad Request` response, with a plain text body listing the missing headers.
+pub struct RequireHeaders<ResBody = crate::http::Body> {
+    headers: Arc<Vec<HeaderName>>,
+    _ty: PhantomData<fn() -> ResBody>,
+}
+
+impl<ResBody> RequireHeaders<ResBody> {
+    /// Create a new `RequireHeaders`.
+    pub(super) fn new<I>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+        ResBody: Body + From<String>,
+    {
+        let mut required = Vec::new();
+        for name in headers {
+            if !required.contains(&name) {
+                required.push(name);
+            }
+        }
+        Self {
+            headers: Arc::new(required),
+            _ty: PhantomData,
+        }
+    }
+
+    /// The headers missing from the given request, in the configured order.
+    pub fn missing<'a, B>(&'a self, req: &'a Request<B>) -> impl Iterator<Item = &'a HeaderName> {
+        self.headers
+            .iter()
+            .filter(|name| !req.headers().contains_key(*name))
+    }
+}
+
+impl<ResBody> Clone for RequireHeaders<ResBody> {
+    fn clone(&self) -> Self {
+        Self {
+            headers: self.headers.clone(),
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<ResBody> fmt::Debug for RequireHeaders<ResBody> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequireHeaders")
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+impl<S, B, ResBody> ValidateRequest<S, B> for RequireHeaders<ResBody>
+where
+    S: Send + Sync + 'static,
+    B: Send + Sync + 'static,
+    ResBody: Body + From<String> + Send + 'static,
+{
+    type ResponseBody = ResBody;
+
+    async fn validate(
+        &self,
+        ctx: Context<S>,
+        req: Request<B>,
+    ) -> Result<(Context<S>, Request<B>), Response<Self::ResponseBody>> {
+        let missing: Vec<_> = self.missing(&req).map(HeaderName::as_str).collect();
+        if missing.is_empty() {
+            return Ok((ctx, req));
+        }
+        let mut res = Response::new(ResBody::from(format!(
+            "missing required headers: {}",
+            missing.join(", ")
+        )));
+        *res.status_mut() = StatusCode::BAD_REQUEST;
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        Err(res)
+    }
+}
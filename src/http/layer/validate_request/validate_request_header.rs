@@ -1,8 +1,11 @@
-use super::{AcceptHeader, BoxValidateRequestFn, ValidateRequest};
+use super::{
+    AcceptHeader, BoxValidateRequestFn, HeaderBudget, RequireContentType, RequireHeaders,
+    ValidateRequest,
+};
 use crate::service::{Layer, Service};
 use crate::{
     http::dep::http_body::Body,
-    http::{Request, Response},
+    http::{matcher::HeaderBudgetFilter, HeaderName, Request, Response},
     service::Context,
 };
 
@@ -53,6 +56,94 @@ impl<ResBody> ValidateRequestHeaderLayer<AcceptHeader<ResBody>> {
     }
 }
 
+impl<ResBody> ValidateRequestHeaderLayer<HeaderBudget<ResBody>> {
+    /// Validate requests have their headers within the budget of the given [`HeaderBudgetFilter`].
+    ///
+    /// Requests exceeding the budget get a `431 Request Header Fields Too Large` response.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rama::http::layer::validate_request::{HeaderBudget, ValidateRequestHeaderLayer};
+    /// use rama::http::matcher::HeaderBudgetFilter;
+    ///
+    /// let layer = ValidateRequestHeaderLayer::<HeaderBudget>::header_budget(
+    ///     HeaderBudgetFilter::new().max_size(8 * 1024).max_count(100),
+    /// );
+    /// ```
+    pub fn header_budget(filter: HeaderBudgetFilter) -> Self
+    where
+        ResBody: Body + Default,
+    {
+        Self::custom(HeaderBudget::new(filter))
+    }
+}
+
+/// Layer that rejects requests missing any of a set of required headers,
+/// see [`ValidateRequestHeaderLayer::require_headers`].
+pub type RequireHeadersLayer<ResBody = crate::http::Body> =
+    ValidateRequestHeaderLayer<RequireHeaders<ResBody>>;
+
+impl<ResBody> ValidateRequestHeaderLayer<RequireHeaders<ResBody>> {
+    /// Validate requests have all of the given headers.
+    ///
+    /// Requests missing any of them get a `400 Bad Request` response,
+    /// with a body listing the missing headers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rama::http::layer::validate_request::RequireHeadersLayer;
+    /// use rama::http::HeaderName;
+    ///
+    /// let layer: RequireHeadersLayer = RequireHeadersLayer::require_headers([
+    ///     HeaderName::from_static("x-api-version"),
+    /// ]);
+    /// ```
+    pub fn require_headers<I>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+        ResBody: Body + From<String>,
+    {
+        Self::custom(RequireHeaders::new(headers))
+    }
+}
+
+/// Layer that rejects mutating requests with a body of an unsupported `Content-Type`,
+/// see [`ValidateRequestHeaderLayer::require_content_type`].
+pub type RequireContentTypeLayer<ResBody = crate::http::Body> =
+    ValidateRequestHeaderLayer<RequireContentType<ResBody>>;
+
+impl<ResBody> ValidateRequestHeaderLayer<RequireContentType<ResBody>> {
+    /// Validate `POST`, `PUT` and `PATCH` requests with a body have a `Content-Type`
+    /// which is one of the given media types.
+    ///
+    /// Requests with a missing or other `Content-Type` get a `415 Unsupported Media Type`
+    /// response. Requests using another method, or without a body, are not validated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the given types is not in the form `type/subtype`,
+    /// such as `application/json`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rama::http::layer::validate_request::RequireContentTypeLayer;
+    ///
+    /// let layer: RequireContentTypeLayer =
+    ///     RequireContentTypeLayer::require_content_type(["application/json"]);
+    /// ```
+    pub fn require_content_type<I, T>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+        ResBody: Body + Default,
+    {
+        Self::custom(RequireContentType::new(allowed))
+    }
+}
+
 impl<T> ValidateRequestHeaderLayer<T> {
     /// Validate requests using a custom validator.
     pub fn custom(validate: T) -> Self {
@@ -127,6 +218,48 @@ impl<S, ResBody> ValidateRequestHeader<S, AcceptHeader<ResBody>> {
     }
 }
 
+impl<S, ResBody> ValidateRequestHeader<S, HeaderBudget<ResBody>> {
+    /// Validate requests have their headers within the budget of the given [`HeaderBudgetFilter`].
+    ///
+    /// Requests exceeding the budget get a `431 Request Header Fields Too Large` response.
+    pub fn header_budget(inner: S, filter: HeaderBudgetFilter) -> Self
+    where
+        ResBody: Body + Default,
+    {
+        Self::custom(inner, HeaderBudget::new(filter))
+    }
+}
+
+impl<S, ResBody> ValidateRequestHeader<S, RequireHeaders<ResBody>> {
+    /// Validate requests have all of the given headers.
+    ///
+    /// Requests missing any of them get a `400 Bad Request` response,
+    /// with a body listing the missing headers.
+    pub fn require_headers<I>(inner: S, headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+        ResBody: Body + From<String>,
+    {
+        Self::custom(inner, RequireHeaders::new(headers))
+    }
+}
+
+impl<S, ResBody> ValidateRequestHeader<S, RequireContentType<ResBody>> {
+    /// Validate `POST`, `PUT` and `PATCH` requests with a body have a `Content-Type`
+    /// which is one of the given media types.
+    ///
+    /// Requests with a missing or other `Content-Type` get a `415 Unsupported Media Type`
+    /// response. Requests using another method, or without a body, are not validated.
+    pub fn require_content_type<I, T>(inner: S, allowed: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+        ResBody: Body + Default,
+    {
+        Self::custom(inner, RequireContentType::new(allowed))
+    }
+}
+
 impl<S, T> ValidateRequestHeader<S, T> {
     /// Validate requests using a custom validator.
     pub fn custom(inner: S, validate: T) -> Self {
@@ -172,7 +305,7 @@ mod tests {
     #[allow(unused_imports)]
     use super::*;
 
-    use crate::http::{header, Body, StatusCode};
+    use crate::http::{dep::http_body_util::BodyExt, header, Body, Method, StatusCode};
     use crate::{error::BoxError, service::ServiceBuilder};
 
     #[tokio::test]
@@ -337,6 +470,197 @@ mod tests {
         assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
     }
 
+    #[tokio::test]
+    async fn header_budget_under_limit() {
+        let service = ServiceBuilder::new()
+            .layer(ValidateRequestHeaderLayer::header_budget(
+                HeaderBudgetFilter::new().max_size(64).max_count(2),
+            ))
+            .service_fn(echo);
+
+        let request = Request::get("/")
+            .header(header::ACCEPT, "application/json")
+            .header(header::USER_AGENT, "rama")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service.serve(Context::default(), request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn header_budget_over_size_limit() {
+        let service = ServiceBuilder::new()
+            .layer(ValidateRequestHeaderLayer::header_budget(
+                HeaderBudgetFilter::new().max_size(64),
+            ))
+            .service_fn(echo);
+
+        let request = Request::get("/")
+            .header(header::USER_AGENT, "a".repeat(64))
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service.serve(Context::default(), request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn header_budget_over_count_limit() {
+        let service = ServiceBuilder::new()
+            .layer(ValidateRequestHeaderLayer::header_budget(
+                HeaderBudgetFilter::new().max_count(2),
+            ))
+            .service_fn(echo);
+
+        let request = Request::get("/")
+            .header("x-a", "1")
+            .header("x-b", "2")
+            .header("x-c", "3")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service.serve(Context::default(), request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn require_headers_all_present() {
+        let service = ServiceBuilder::new()
+            .layer(RequireHeadersLayer::require_headers([
+                HeaderName::from_static("x-api-version"),
+                header::USER_AGENT,
+            ]))
+            .service_fn(echo);
+
+        let request = Request::get("/")
+            .header("x-api-version", "2")
+            .header(header::USER_AGENT, "rama")
+            .body(Body::from("hello"))
+            .unwrap();
+
+        let res = service.serve(Context::default(), request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn require_headers_partially_missing() {
+        let service = ServiceBuilder::new()
+            .layer(RequireHeadersLayer::require_headers([
+                HeaderName::from_static("x-api-version"),
+                header::USER_AGENT,
+                HeaderName::from_static("x-request-id"),
+            ]))
+            .service_fn(|_: Request| async {
+                Err::<Response, BoxError>("inner service should not be called".into())
+            });
+
+        let request = Request::get("/")
+            .header(header::USER_AGENT, "rama")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service.serve(Context::default(), request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            &body[..],
+            b"missing required headers: x-api-version, x-request-id"
+        );
+    }
+
+    fn require_json() -> impl Service<(), Request, Response = Response, Error = BoxError> {
+        ServiceBuilder::new()
+            .layer(RequireContentTypeLayer::require_content_type([
+                "application/json",
+                "text/*",
+            ]))
+            .service_fn(echo)
+    }
+
+    #[tokio::test]
+    async fn require_content_type_allowed() {
+        let service = require_json();
+
+        for (method, content_type) in [
+            (Method::POST, "application/json"),
+            (Method::PUT, "application/json; charset=utf-8"),
+            (Method::PATCH, "Application/JSON"),
+            (Method::POST, "text/plain"),
+        ] {
+            let request = Request::builder()
+                .method(method)
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from("{}"))
+                .unwrap();
+
+            let res = service.serve(Context::default(), request).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK, "{content_type}");
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], b"{}");
+        }
+    }
+
+    #[tokio::test]
+    async fn require_content_type_wrong_or_missing() {
+        let service = require_json();
+
+        for content_type in [Some("application/xml"), Some("not a mime"), None] {
+            let mut request = Request::post("/").body(Body::from("{}")).unwrap();
+            if let Some(content_type) = content_type {
+                request
+                    .headers_mut()
+                    .insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            }
+
+            let res = service.serve(Context::default(), request).await.unwrap();
+
+            assert_eq!(
+                res.status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "{content_type:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn require_content_type_bypassed() {
+        let service = require_json();
+
+        // safe methods are not validated, even with a body
+        for method in [Method::GET, Method::HEAD, Method::DELETE, Method::OPTIONS] {
+            let request = Request::builder()
+                .method(method.clone())
+                .header(header::CONTENT_TYPE, "application/xml")
+                .body(Body::from("<xml/>"))
+                .unwrap();
+
+            let res = service.serve(Context::default(), request).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK, "{method}");
+        }
+
+        // nor are mutating requests without a body
+        let request = Request::post("/").body(Body::empty()).unwrap();
+        let res = service.serve(Context::default(), request).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let request = Request::put("/")
+            .header(header::CONTENT_LENGTH, "0")
+            .body(Body::empty())
+            .unwrap();
+        let res = service.serve(Context::default(), request).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
     async fn echo<B>(req: Request<B>) -> Result<Response<B>, BoxError> {
         Ok(Response::new(req.into_body()))
     }
@@ -0,0 +1,119 @@
+use crate::{
+    http::{Method, Request, Version},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone, Default)]
+/// Filter matching [`Request`]s with an absolute-form request target
+/// (e.g. `GET http://example.com/path HTTP/1.1`),
+/// as sent by clients to a forward proxy.
+///
+/// Requests with an origin-form target (e.g. `GET /path HTTP/1.1`),
+/// as sent to an origin server, do not match, and neither do `CONNECT` requests,
+/// which use the authority-form (e.g. `CONNECT example.com:443 HTTP/1.1`).
+/// This allows a single listener to route proxy requests
+/// separately from requests meant for the server itself.
+///
+/// The request target form only exists for HTTP/1.x requests.
+/// HTTP/2 (and later) requests always carry a scheme and authority
+/// as pseudo headers, and thus never match.
+///
+/// [`Request`]: crate::http::Request
+pub struct AbsoluteUriFilter {
+    _priv: (),
+}
+
+impl AbsoluteUriFilter {
+    /// Create a new filter matching requests with an absolute-form request target.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for AbsoluteUriFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        req.version() <= Version::HTTP_11
+            && req.method() != Method::CONNECT
+            && req.uri().scheme().is_some()
+            && req.uri().authority().is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(method: Method, version: Version, target: &str) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .version(version)
+            .uri(target)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_absolute_uri_filter_absolute_form() {
+        let ctx = Context::default();
+        let filter = AbsoluteUriFilter::new();
+
+        for (method, target) in [
+            (Method::GET, "http://example.com/path?q=1"),
+            (Method::POST, "https://example.com:8443/"),
+            (Method::GET, "http://127.0.0.1"),
+        ] {
+            assert!(
+                filter.matches(None, &ctx, &request(method, Version::HTTP_11, target)),
+                "{target}"
+            );
+        }
+        assert!(filter.matches(
+            None,
+            &ctx,
+            &request(Method::GET, Version::HTTP_10, "http://example.com/")
+        ));
+    }
+
+    #[test]
+    fn test_absolute_uri_filter_origin_form() {
+        let ctx = Context::default();
+        let filter = AbsoluteUriFilter::new();
+
+        for target in ["/", "/path?q=1", "*"] {
+            assert!(
+                !filter.matches(None, &ctx, &request(Method::GET, Version::HTTP_11, target)),
+                "{target}"
+            );
+        }
+
+        // http/2 requests always carry the scheme and authority
+        assert!(!filter.matches(
+            None,
+            &ctx,
+            &request(Method::GET, Version::HTTP_2, "https://example.com/path")
+        ));
+    }
+
+    #[test]
+    fn test_absolute_uri_filter_authority_form() {
+        let ctx = Context::default();
+        let filter = AbsoluteUriFilter::new();
+
+        assert!(!filter.matches(
+            None,
+            &ctx,
+            &request(Method::CONNECT, Version::HTTP_11, "example.com:443")
+        ));
+        // even when written with a scheme
+        assert!(!filter.matches(
+            None,
+            &ctx,
+            &request(Method::CONNECT, Version::HTTP_11, "http://example.com:443")
+        ));
+    }
+}
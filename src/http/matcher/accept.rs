@@ -0,0 +1,262 @@
+use crate::{
+    http::{header, HeaderMap, HeaderName, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone)]
+/// Filter based on content negotiation, matching only if the configured media type
+/// (or language) is acceptable according to the `Accept` (or `Accept-Language`)
+/// header of the [`Request`].
+///
+/// The q-values of the header are respected: the most specific range matching
+/// the configured value determines its quality, and a quality of `0` means
+/// that the value is not acceptable. Wildcards such as `text/*`, `*/*`
+/// and (for languages) `*` are supported, as are language prefixes
+/// (e.g. `en` matches `en-US`).
+///
+/// Requests without the relevant header do not match,
+/// unless the filter is made [`optional`].
+///
+/// [`Request`]: crate::http::Request
+/// [`optional`]: AcceptFilter::optional
+pub struct AcceptFilter {
+    kind: AcceptKind,
+    value: String,
+    optional: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AcceptKind {
+    Media,
+    Language,
+}
+
+impl AcceptFilter {
+    /// Create a new filter matching if the given media type (e.g. `text/html`)
+    /// is acceptable according to the `Accept` header.
+    pub fn media(media_type: impl Into<String>) -> Self {
+        Self {
+            kind: AcceptKind::Media,
+            value: media_type.into().to_ascii_lowercase(),
+            optional: false,
+        }
+    }
+
+    /// Create a new filter matching if the given language tag (e.g. `en-US`)
+    /// is acceptable according to the `Accept-Language` header.
+    pub fn language(language: impl Into<String>) -> Self {
+        Self {
+            kind: AcceptKind::Language,
+            value: language.into().to_ascii_lowercase(),
+            optional: false,
+        }
+    }
+
+    /// Make the filter also match requests without the relevant header.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    fn header_name(&self) -> HeaderName {
+        match self.kind {
+            AcceptKind::Media => header::ACCEPT,
+            AcceptKind::Language => header::ACCEPT_LANGUAGE,
+        }
+    }
+
+    /// Returns the quality (q-value) of the configured value according to the given headers,
+    /// or `None` in case no range of the relevant header matches it
+    /// (or the header is missing).
+    pub fn quality(&self, headers: &HeaderMap) -> Option<f32> {
+        let mut best: Option<(usize, f32)> = None;
+        for value in headers.get_all(self.header_name()) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            for (range, q) in value.split(',').filter_map(parse_range) {
+                let specificity = match self.kind {
+                    AcceptKind::Media => media_specificity(&range, &self.value),
+                    AcceptKind::Language => language_specificity(&range, &self.value),
+                };
+                if let Some(specificity) = specificity {
+                    if best.map_or(true, |(best, _)| specificity > best) {
+                        best = Some((specificity, q));
+                    }
+                }
+            }
+        }
+        best.map(|(_, q)| q)
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for AcceptFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        if !req.headers().contains_key(self.header_name()) {
+            return self.optional;
+        }
+        self.quality(req.headers()).is_some_and(|q| q > 0.0)
+    }
+}
+
+/// Parse a single (lowercased) range of an `Accept(-Language)` header and its q-value,
+/// ignoring ranges with an invalid q-value.
+fn parse_range(item: &str) -> Option<(String, f32)> {
+    let mut params = item.split(';');
+    let range = params.next()?.trim().to_ascii_lowercase();
+    if range.is_empty() {
+        return None;
+    }
+    let mut q = 1.0;
+    for param in params {
+        if let Some((name, value)) = param.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("q") {
+                q = value.trim().parse::<f32>().ok()?;
+                if !(0.0..=1.0).contains(&q) {
+                    return None;
+                }
+            }
+        }
+    }
+    Some((range, q))
+}
+
+/// The specificity of the media range if it matches the given media type.
+fn media_specificity(range: &str, media_type: &str) -> Option<usize> {
+    if range == media_type {
+        return Some(3);
+    }
+    if range == "*/*" {
+        return Some(1);
+    }
+    let (range_type, range_subtype) = range.split_once('/')?;
+    let (media_type, _) = media_type.split_once('/')?;
+    (range_subtype == "*" && range_type == media_type).then_some(2)
+}
+
+/// The specificity of the language range if it matches the given language tag,
+/// using basic filtering as defined in RFC 4647.
+fn language_specificity(range: &str, language: &str) -> Option<usize> {
+    if range == "*" {
+        return Some(0);
+    }
+    let matches = language == range
+        || (language.starts_with(range) && language.as_bytes().get(range.len()) == Some(&b'-'));
+    matches.then_some(range.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(name: HeaderName, value: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder();
+        if let Some(value) = value {
+            builder = builder.header(name, value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_accept_filter_media_q_values() {
+        let ctx = Context::default();
+        let html = AcceptFilter::media("text/html");
+        let json = AcceptFilter::media("application/json");
+
+        let req = request(
+            header::ACCEPT,
+            Some("text/html;q=0.9, application/json;q=0.5, image/png"),
+        );
+        assert!(html.matches(None, &ctx, &req));
+        assert!(json.matches(None, &ctx, &req));
+        assert_eq!(html.quality(req.headers()), Some(0.9));
+        assert_eq!(json.quality(req.headers()), Some(0.5));
+
+        // case-insensitive
+        let req = request(header::ACCEPT, Some("Text/HTML"));
+        assert!(html.matches(None, &ctx, &req));
+        assert!(!json.matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_accept_filter_media_wildcards() {
+        let ctx = Context::default();
+        let html = AcceptFilter::media("text/html");
+        let json = AcceptFilter::media("application/json");
+
+        let req = request(header::ACCEPT, Some("text/*"));
+        assert!(html.matches(None, &ctx, &req));
+        assert!(!json.matches(None, &ctx, &req));
+
+        let req = request(header::ACCEPT, Some("*/*;q=0.1"));
+        assert!(html.matches(None, &ctx, &req));
+        assert!(json.matches(None, &ctx, &req));
+        assert_eq!(json.quality(req.headers()), Some(0.1));
+
+        // the most specific range wins, regardless of order
+        let req = request(
+            header::ACCEPT,
+            Some("text/html;q=0.2, text/*;q=0.8, */*;q=0"),
+        );
+        assert_eq!(html.quality(req.headers()), Some(0.2));
+        assert_eq!(
+            AcceptFilter::media("text/plain").quality(req.headers()),
+            Some(0.8)
+        );
+        assert!(!json.matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_accept_filter_media_unacceptable() {
+        let ctx = Context::default();
+        let html = AcceptFilter::media("text/html");
+
+        // explicitly not acceptable
+        let req = request(header::ACCEPT, Some("application/json, text/html;q=0"));
+        assert!(!html.matches(None, &ctx, &req));
+
+        // not listed
+        let req = request(header::ACCEPT, Some("application/json"));
+        assert!(!html.matches(None, &ctx, &req));
+
+        // invalid q-values are ignored
+        let req = request(header::ACCEPT, Some("text/html;q=2, text/html;q=abc"));
+        assert!(!html.matches(None, &ctx, &req));
+
+        // missing header
+        let req = request(header::ACCEPT, None);
+        assert!(!html.matches(None, &ctx, &req));
+        assert!(html.clone().optional().matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_accept_filter_language() {
+        let ctx = Context::default();
+        let en_us = AcceptFilter::language("en-US");
+        let fr = AcceptFilter::language("fr");
+
+        let req = request(header::ACCEPT_LANGUAGE, Some("en;q=0.8, nl"));
+        assert!(en_us.matches(None, &ctx, &req));
+        assert_eq!(en_us.quality(req.headers()), Some(0.8));
+        assert!(!fr.matches(None, &ctx, &req));
+
+        let req = request(header::ACCEPT_LANGUAGE, Some("en-us, en;q=0, *;q=0.5"));
+        assert_eq!(en_us.quality(req.headers()), Some(1.0));
+        assert_eq!(fr.quality(req.headers()), Some(0.5));
+        assert!(!AcceptFilter::language("en-GB").matches(None, &ctx, &req));
+
+        // a prefix only matches on a subtag boundary
+        let req = request(header::ACCEPT_LANGUAGE, Some("e"));
+        assert!(!en_us.matches(None, &ctx, &req));
+
+        let req = request(header::ACCEPT_LANGUAGE, None);
+        assert!(!en_us.matches(None, &ctx, &req));
+        assert!(en_us.optional().matches(None, &ctx, &req));
+    }
+}
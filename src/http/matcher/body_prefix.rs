@@ -0,0 +1,71 @@
+use crate::{
+    http::{layer::body_prefix::BodyPrefix, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+use std::fmt;
+
+/// Filter based on the buffered prefix of the request body,
+/// matching only if the given predicate returns `true` for the buffered bytes.
+///
+/// The filter relies on the [`BodyPrefix`] found in the [`Context`],
+/// as added by the [`BodyPrefixLayer`], which buffers a bounded prefix of the body.
+/// Keep in mind that the prefix might not be the full body,
+/// see [`BodyPrefix::is_complete`].
+///
+/// [`BodyPrefixLayer`]: crate::http::layer::body_prefix::BodyPrefixLayer
+#[derive(Clone)]
+pub struct BodyPrefixFilter<F> {
+    predicate: F,
+    optional: bool,
+}
+
+impl<F> BodyPrefixFilter<F> {
+    /// Create a new filter matching only if a body prefix is found in the [`Context`]
+    /// and the predicate returns `true` for it.
+    ///
+    /// This filter will not match in case no body prefix could be found,
+    /// if you want to match in case it could not be found,
+    /// use the [`BodyPrefixFilter::optional`] constructor.
+    pub fn new(predicate: F) -> Self {
+        Self {
+            predicate,
+            optional: false,
+        }
+    }
+
+    /// Create a new filter matching only if the predicate returns `true` for the body prefix,
+    /// or no body prefix could be found in the [`Context`].
+    ///
+    /// Use the [`BodyPrefixFilter::new`] constructor if you do not want
+    /// to match in case no body prefix could be found.
+    pub fn optional(predicate: F) -> Self {
+        Self {
+            predicate,
+            optional: true,
+        }
+    }
+}
+
+impl<F> fmt::Debug for BodyPrefixFilter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyPrefixFilter")
+            .field("optional", &self.optional)
+            .finish()
+    }
+}
+
+impl<State, Body, F> Matcher<State, Request<Body>> for BodyPrefixFilter<F>
+where
+    F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+{
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<BodyPrefix>()
+            .map(|prefix| (self.predicate)(prefix.bytes()))
+            .unwrap_or(self.optional)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    http::{layer::auth::Claims, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+use serde_json::Value;
+
+/// Filter based on a claim of the authenticated request,
+/// e.g. to gate admin endpoints to requests with a `role` claim equal to `admin`.
+///
+/// The filter relies on the [`Claims`] found in the [`Context`],
+/// as inserted by the authorization middleware.
+/// A claim matches if it is equal to the expected value, or in case it is an array
+/// (e.g. a list of roles), if it contains the expected value.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{layer::auth::Claims, matcher::ClaimFilter, Request};
+/// use rama::service::{Context, Matcher};
+///
+/// let filter = ClaimFilter::new("role", "admin");
+/// let request = Request::builder().body(()).unwrap();
+///
+/// let mut ctx = Context::<()>::default();
+/// ctx.insert(Claims::from_iter([("sub", "alice"), ("role", "admin")]));
+/// assert!(filter.matches(None, &ctx, &request));
+///
+/// ctx.insert(Claims::from_iter([("sub", "bob"), ("role", "user")]));
+/// assert!(!filter.matches(None, &ctx, &request));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClaimFilter {
+    name: String,
+    value: Value,
+    optional: bool,
+}
+
+impl ClaimFilter {
+    /// Create a new filter matching only if the claim with the given name
+    /// is found in the [`Context`] and matches the given value.
+    ///
+    /// This filter will not match in case no claims or no such claim could be found,
+    /// if you want to match in case it could not be found,
+    /// use the [`ClaimFilter::optional`] constructor.
+    pub fn new(name: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            optional: false,
+        }
+    }
+
+    /// Create a new filter matching only if the claim with the given name matches the given value,
+    /// or no such claim could be found in the [`Context`].
+    ///
+    /// Use the [`ClaimFilter::new`] constructor if you do not want
+    /// to match in case no such claim could be found.
+    pub fn optional(name: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            optional: true,
+        }
+    }
+
+    fn matches_claim(&self, claim: &Value) -> bool {
+        match claim {
+            Value::Array(values) if !self.value.is_array() => values.contains(&self.value),
+            claim => claim == &self.value,
+        }
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for ClaimFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<Claims>()
+            .and_then(|claims| claims.get(&self.name))
+            .map(|claim| self.matches_claim(claim))
+            .unwrap_or(self.optional)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::matcher::HttpMatcher;
+    use crate::http::service::web::match_service;
+    use crate::http::{Body, Method, StatusCode};
+    use crate::service::Service;
+    use serde_json::json;
+
+    fn context(claims: Option<Value>) -> Context<()> {
+        let mut ctx = Context::default();
+        if let Some(Value::Object(claims)) = claims {
+            ctx.insert(Claims::new(claims));
+        }
+        ctx
+    }
+
+    #[test]
+    fn test_claim_filter_role() {
+        let request = Request::builder().body(()).unwrap();
+        let filter = ClaimFilter::new("role", "admin");
+
+        let ctx = context(Some(json!({ "sub": "alice", "role": "admin" })));
+        assert!(filter.matches(None, &ctx, &request));
+
+        let ctx = context(Some(json!({ "sub": "bob", "role": "user" })));
+        assert!(!filter.matches(None, &ctx, &request));
+
+        // array claims contain the value
+        let ctx = context(Some(json!({ "role": ["user", "admin"] })));
+        assert!(filter.matches(None, &ctx, &request));
+        let ctx = context(Some(json!({ "role": ["user"] })));
+        assert!(!filter.matches(None, &ctx, &request));
+
+        // values are compared by type
+        let filter = ClaimFilter::new("level", 3);
+        assert!(filter.matches(None, &context(Some(json!({ "level": 3 }))), &request));
+        assert!(!filter.matches(None, &context(Some(json!({ "level": "3" }))), &request));
+    }
+
+    #[test]
+    fn test_claim_filter_missing() {
+        let request = Request::builder().body(()).unwrap();
+
+        let ctx = context(Some(json!({ "sub": "alice" })));
+        assert!(!ClaimFilter::new("role", "admin").matches(None, &ctx, &request));
+        assert!(ClaimFilter::optional("role", "admin").matches(None, &ctx, &request));
+
+        let ctx = context(None);
+        assert!(!ClaimFilter::new("role", "admin").matches(None, &ctx, &request));
+        assert!(ClaimFilter::optional("role", "admin").matches(None, &ctx, &request));
+
+        // a present claim with another value never matches
+        let ctx = context(Some(json!({ "role": "user" })));
+        assert!(!ClaimFilter::optional("role", "admin").matches(None, &ctx, &request));
+    }
+
+    #[tokio::test]
+    async fn test_claim_filter_routing() {
+        let service = match_service! {
+            HttpMatcher::path("/admin/*").and_claim(ClaimFilter::new("role", "admin")) => StatusCode::OK,
+            HttpMatcher::path("/admin/*") => StatusCode::FORBIDDEN,
+            _ => StatusCode::NOT_FOUND,
+        };
+
+        let status = |claims: Value| {
+            let service = &service;
+            async move {
+                let request = Request::builder()
+                    .method(Method::GET)
+                    .uri("/admin/users")
+                    .body(Body::empty())
+                    .unwrap();
+                service
+                    .serve(context(Some(claims)), request)
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(status(json!({ "role": "admin" })).await, StatusCode::OK);
+        assert_eq!(
+            status(json!({ "role": "user" })).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
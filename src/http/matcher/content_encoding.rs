@@ -0,0 +1,130 @@
+use crate::{
+    http::{header, HeaderMap, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone)]
+/// Filter based on the `Content-Encoding` of the [`Request`],
+/// e.g. to route compressed uploads to a handler capable of decompressing them.
+///
+/// Encodings are compared case-insensitively, and can be listed
+/// comma-separated and/or spread over multiple `Content-Encoding` headers,
+/// in which case the filter matches if any of them matches.
+/// The `identity` encoding (the default) is never considered to be compressed.
+///
+/// [`Request`]: crate::http::Request
+pub struct ContentEncodingFilter {
+    encoding: Option<String>,
+}
+
+impl ContentEncodingFilter {
+    /// Create a new filter matching requests with the given `Content-Encoding` (e.g. `gzip`).
+    pub fn new(encoding: impl Into<String>) -> Self {
+        Self {
+            encoding: Some(encoding.into()),
+        }
+    }
+
+    /// Create a new filter matching requests with any non-identity `Content-Encoding`.
+    pub fn compressed() -> Self {
+        Self { encoding: None }
+    }
+
+    /// Returns `true` if the `Content-Encoding` declared in the given headers matches.
+    pub fn is_match(&self, headers: &HeaderMap) -> bool {
+        let mut encodings = headers
+            .get_all(header::CONTENT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|encoding| !encoding.is_empty());
+        match &self.encoding {
+            Some(expected) => encodings.any(|encoding| encoding.eq_ignore_ascii_case(expected)),
+            None => encodings.any(|encoding| !encoding.eq_ignore_ascii_case("identity")),
+        }
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for ContentEncodingFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        self.is_match(req.headers())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(content_encoding: &[&str]) -> Request<()> {
+        let mut builder = Request::builder();
+        for value in content_encoding {
+            builder = builder.header(header::CONTENT_ENCODING, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_content_encoding_filter_gzip() {
+        let ctx = Context::default();
+
+        let filter = ContentEncodingFilter::new("gzip");
+        assert!(filter.matches(None, &ctx, &request(&["gzip"])));
+        assert!(filter.matches(None, &ctx, &request(&["GZip"])));
+        assert!(!filter.matches(None, &ctx, &request(&["br"])));
+        assert!(!filter.matches(None, &ctx, &request(&["x-gzip-like"])));
+
+        assert!(ContentEncodingFilter::compressed().matches(None, &ctx, &request(&["gzip"])));
+    }
+
+    #[test]
+    fn test_content_encoding_filter_identity_or_absent() {
+        let ctx = Context::default();
+
+        let filter = ContentEncodingFilter::compressed();
+        assert!(!filter.matches(None, &ctx, &request(&["identity"])));
+        assert!(!filter.matches(None, &ctx, &request(&["Identity"])));
+        assert!(!filter.matches(None, &ctx, &request(&[])));
+        assert!(!filter.matches(None, &ctx, &request(&[""])));
+
+        let filter = ContentEncodingFilter::new("identity");
+        assert!(filter.matches(None, &ctx, &request(&["identity"])));
+        assert!(!filter.matches(None, &ctx, &request(&[])));
+        assert!(!ContentEncodingFilter::new("gzip").matches(None, &ctx, &request(&[])));
+    }
+
+    #[test]
+    fn test_content_encoding_filter_multiple_encodings() {
+        let ctx = Context::default();
+
+        for encodings in [
+            &["deflate, gzip"][..],
+            &["deflate", "gzip"],
+            &[" br ,gzip,"],
+        ] {
+            assert!(
+                ContentEncodingFilter::new("gzip").matches(None, &ctx, &request(encodings)),
+                "{encodings:?}"
+            );
+            assert!(
+                ContentEncodingFilter::compressed().matches(None, &ctx, &request(encodings)),
+                "{encodings:?}"
+            );
+        }
+        assert!(!ContentEncodingFilter::new("zstd").matches(
+            None,
+            &ctx,
+            &request(&["deflate, gzip", "br"])
+        ));
+        assert!(ContentEncodingFilter::compressed().matches(
+            None,
+            &ctx,
+            &request(&["identity", "br"])
+        ));
+    }
+}
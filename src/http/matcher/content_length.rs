@@ -0,0 +1,148 @@
+use crate::{
+    http::{header, HeaderMap, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone)]
+/// Filter based on the declared `Content-Length` of the [`Request`],
+/// matching only if it is within the configured (inclusive) range.
+///
+/// Requests without a `Content-Length` header (e.g. chunked requests)
+/// do not match, unless the filter is made [`optional`].
+/// Requests with an invalid `Content-Length` header never match.
+///
+/// [`Request`]: crate::http::Request
+/// [`optional`]: ContentLengthFilter::optional
+pub struct ContentLengthFilter {
+    range: RangeInclusive<u64>,
+    optional: bool,
+}
+
+impl ContentLengthFilter {
+    /// Create a new filter matching a `Content-Length` of at least `min` bytes.
+    pub fn min(min: u64) -> Self {
+        Self::range(min, u64::MAX)
+    }
+
+    /// Create a new filter matching a `Content-Length` of at most `max` bytes.
+    pub fn max(max: u64) -> Self {
+        Self::range(0, max)
+    }
+
+    /// Create a new filter matching a `Content-Length` of at least `min`
+    /// and at most `max` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn range(min: u64, max: u64) -> Self {
+        assert!(
+            min <= max,
+            "content length filter: min ({min}) must not be greater than max ({max})"
+        );
+        Self {
+            range: min..=max,
+            optional: false,
+        }
+    }
+
+    /// Make the filter also match requests without a `Content-Length` header.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Returns `true` if the `Content-Length` declared in the given headers
+    /// is within the configured range.
+    pub fn is_within_range(&self, headers: &HeaderMap) -> bool {
+        let mut values = headers.get_all(header::CONTENT_LENGTH).iter();
+        let first = match values.next() {
+            Some(value) => value,
+            None => return self.optional,
+        };
+        // repeated values are only valid if they are all the same
+        if values.any(|value| value != first) {
+            return false;
+        }
+        first
+            .to_str()
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(|length| self.range.contains(&length))
+            .unwrap_or_default()
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for ContentLengthFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        self.is_within_range(req.headers())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(content_length: &[&str]) -> Request<()> {
+        let mut builder = Request::builder();
+        for value in content_length {
+            builder = builder.header(header::CONTENT_LENGTH, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_content_length_filter_in_range() {
+        let ctx = Context::default();
+
+        let filter = ContentLengthFilter::range(10, 20);
+        assert!(filter.matches(None, &ctx, &request(&["10"])));
+        assert!(filter.matches(None, &ctx, &request(&["15"])));
+        assert!(filter.matches(None, &ctx, &request(&["20"])));
+        assert!(filter.matches(None, &ctx, &request(&["15", "15"])));
+
+        assert!(ContentLengthFilter::min(1024).matches(None, &ctx, &request(&["1048576"])));
+        assert!(ContentLengthFilter::max(1024).matches(None, &ctx, &request(&["0"])));
+    }
+
+    #[test]
+    fn test_content_length_filter_out_of_range() {
+        let ctx = Context::default();
+
+        let filter = ContentLengthFilter::range(10, 20);
+        assert!(!filter.matches(None, &ctx, &request(&["9"])));
+        assert!(!filter.matches(None, &ctx, &request(&["21"])));
+
+        assert!(!ContentLengthFilter::min(1024).matches(None, &ctx, &request(&["1023"])));
+        assert!(!ContentLengthFilter::max(1024).matches(None, &ctx, &request(&["1025"])));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_content_length_filter_empty_range() {
+        ContentLengthFilter::range(20, 10);
+    }
+
+    #[test]
+    fn test_content_length_filter_missing_or_invalid() {
+        let ctx = Context::default();
+
+        let filter = ContentLengthFilter::max(1024);
+        assert!(!filter.matches(None, &ctx, &request(&[])));
+        assert!(!filter.matches(None, &ctx, &request(&["abc"])));
+        assert!(!filter.matches(None, &ctx, &request(&["-1"])));
+        assert!(!filter.matches(None, &ctx, &request(&["10", "20"])));
+
+        let filter = filter.optional();
+        assert!(filter.matches(None, &ctx, &request(&[])));
+        assert!(filter.matches(None, &ctx, &request(&["10"])));
+        assert!(!filter.matches(None, &ctx, &request(&["2048"])));
+        assert!(!filter.matches(None, &ctx, &request(&["abc"])));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    http::{
+        headers::{Date, HeaderMapExt},
+        Request,
+    },
+    service::{context::Extensions, Context, Matcher},
+};
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// Filter based on the age of a request, as indicated by its `Date` header,
+/// e.g. to reject replayed requests of signed-request schemes which sign the `Date` header.
+///
+/// The filter matches in case the `Date` of the request lies within the allowed skew
+/// of the server clock, in the past as well as in the future (to allow for clocks
+/// running ahead). Requests with a missing or invalid `Date` header do not match,
+/// unless the filter is created using [`DateFreshnessFilter::optional`].
+///
+/// The server clock defaults to the system clock, and can be replaced using
+/// [`DateFreshnessFilter::with_clock`], e.g. for testing purposes.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{header, matcher::DateFreshnessFilter, Request};
+/// use rama::service::{Context, Matcher};
+/// use std::time::{Duration, SystemTime};
+///
+/// let filter = DateFreshnessFilter::new(Duration::from_secs(300));
+/// let ctx = Context::<()>::default();
+///
+/// let now = httpdate::fmt_http_date(SystemTime::now());
+/// let request = Request::builder().header(header::DATE, now).body(()).unwrap();
+/// assert!(filter.matches(None, &ctx, &request));
+///
+/// let request = Request::builder()
+///     .header(header::DATE, "Sun, 06 Nov 1994 08:49:37 GMT")
+///     .body(())
+///     .unwrap();
+/// assert!(!filter.matches(None, &ctx, &request));
+/// ```
+#[derive(Clone)]
+pub struct DateFreshnessFilter {
+    max_skew: Duration,
+    optional: bool,
+    clock: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+}
+
+impl DateFreshnessFilter {
+    /// Create a new filter matching only requests of which the `Date` header
+    /// differs at most the given skew from the server clock.
+    ///
+    /// This filter will not match in case the `Date` header is missing or invalid,
+    /// if you want to match in that case, use the [`DateFreshnessFilter::optional`] constructor.
+    pub fn new(max_skew: Duration) -> Self {
+        Self {
+            max_skew,
+            optional: false,
+            clock: Arc::new(SystemTime::now),
+        }
+    }
+
+    /// Create a new filter matching only requests of which the `Date` header
+    /// differs at most the given skew from the server clock, or is missing or invalid.
+    ///
+    /// Use the [`DateFreshnessFilter::new`] constructor if you do not want to match
+    /// in case the `Date` header is missing or invalid.
+    pub fn optional(max_skew: Duration) -> Self {
+        Self {
+            optional: true,
+            ..Self::new(max_skew)
+        }
+    }
+
+    /// Replace the server clock, which defaults to the system clock.
+    pub fn with_clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    fn is_fresh(&self, date: SystemTime) -> bool {
+        let now = (self.clock)();
+        let skew = match now.duration_since(date) {
+            Ok(age) => age,
+            Err(err) => err.duration(),
+        };
+        skew <= self.max_skew
+    }
+}
+
+impl fmt::Debug for DateFreshnessFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DateFreshnessFilter")
+            .field("max_skew", &self.max_skew)
+            .field("optional", &self.optional)
+            .finish()
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for DateFreshnessFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        req.headers()
+            .typed_get::<Date>()
+            .map(|date| self.is_fresh(date.into()))
+            .unwrap_or(self.optional)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::header;
+
+    /// A fixed clock, at 2024-03-01T12:00:00Z.
+    fn clock() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_294_400)
+    }
+
+    fn request(date: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder();
+        if let Some(date) = date {
+            builder = builder.header(header::DATE, date);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn matches(filter: &DateFreshnessFilter, date: Option<&str>) -> bool {
+        filter.matches(None, &Context::<()>::default(), &request(date))
+    }
+
+    #[test]
+    fn test_date_freshness_filter_fresh() {
+        let filter = DateFreshnessFilter::new(Duration::from_secs(300)).with_clock(clock);
+
+        assert!(matches(&filter, Some("Fri, 01 Mar 2024 12:00:00 GMT")));
+        assert!(matches(&filter, Some("Fri, 01 Mar 2024 11:55:00 GMT")));
+        assert!(matches(&filter, Some("Fri, 01 Mar 2024 11:59:59 GMT")));
+    }
+
+    #[test]
+    fn test_date_freshness_filter_too_old() {
+        let filter = DateFreshnessFilter::new(Duration::from_secs(300)).with_clock(clock);
+
+        assert!(!matches(&filter, Some("Fri, 01 Mar 2024 11:54:59 GMT")));
+        assert!(!matches(&filter, Some("Thu, 29 Feb 2024 12:00:00 GMT")));
+    }
+
+    #[test]
+    fn test_date_freshness_filter_future_skew() {
+        let filter = DateFreshnessFilter::new(Duration::from_secs(300)).with_clock(clock);
+
+        assert!(matches(&filter, Some("Fri, 01 Mar 2024 12:05:00 GMT")));
+        assert!(!matches(&filter, Some("Fri, 01 Mar 2024 12:05:01 GMT")));
+        assert!(!matches(&filter, Some("Sat, 02 Mar 2024 12:00:00 GMT")));
+    }
+
+    #[test]
+    fn test_date_freshness_filter_missing_date() {
+        let filter = DateFreshnessFilter::new(Duration::from_secs(300)).with_clock(clock);
+        assert!(!matches(&filter, None));
+        assert!(!matches(&filter, Some("yesterday")));
+
+        let filter = DateFreshnessFilter::optional(Duration::from_secs(300)).with_clock(clock);
+        assert!(matches(&filter, None));
+        assert!(matches(&filter, Some("yesterday")));
+        // a present date is still checked
+        assert!(!matches(&filter, Some("Thu, 29 Feb 2024 12:00:00 GMT")));
+    }
+}
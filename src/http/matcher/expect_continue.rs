@@ -0,0 +1,94 @@
+use crate::{
+    http::{header, HeaderMap, Request, Version},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone, Default)]
+/// Filter matching [`Request`]s which carry an `Expect: 100-continue` header,
+/// meaning the client waits for an interim `100 Continue` response
+/// before sending the request body.
+///
+/// The [`HttpServer`] emits the interim `100 Continue` response
+/// as soon as the body of such a request is first read.
+/// This allows routing these requests to a handler which first inspects the
+/// request head (e.g. its `Content-Length` or authorization),
+/// and either reads the body or rejects the request (e.g. with a
+/// `417 Expectation Failed` or `413 Payload Too Large` response)
+/// without the client ever sending the body.
+///
+/// As required by [RFC 9110], the expectation is ignored for HTTP/1.0 requests,
+/// which never match.
+///
+/// [`Request`]: crate::http::Request
+/// [`HttpServer`]: crate::http::server::HttpServer
+/// [RFC 9110]: https://www.rfc-editor.org/rfc/rfc9110#section-10.1.1
+pub struct ExpectContinueFilter {
+    _priv: (),
+}
+
+impl ExpectContinueFilter {
+    /// Create a new filter matching requests carrying an `Expect: 100-continue` header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the given headers contain an `Expect: 100-continue` header.
+    pub fn is_expect_continue(headers: &HeaderMap) -> bool {
+        headers
+            .get_all(header::EXPECT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case("100-continue"))
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for ExpectContinueFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        req.version() > Version::HTTP_10 && Self::is_expect_continue(req.headers())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(version: Version, expect: &[&str]) -> Request<()> {
+        let mut builder = Request::builder().version(version);
+        for value in expect {
+            builder = builder.header(header::EXPECT, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_expect_continue_filter() {
+        let ctx = Context::default();
+        let filter = ExpectContinueFilter::new();
+
+        assert!(filter.matches(None, &ctx, &request(Version::HTTP_11, &["100-continue"])));
+        assert!(filter.matches(None, &ctx, &request(Version::HTTP_11, &["100-Continue"])));
+        assert!(filter.matches(None, &ctx, &request(Version::HTTP_2, &[" 100-continue "])));
+        assert!(filter.matches(
+            None,
+            &ctx,
+            &request(Version::HTTP_11, &["foo", "bar, 100-continue"])
+        ));
+    }
+
+    #[test]
+    fn test_expect_continue_filter_no_match() {
+        let ctx = Context::default();
+        let filter = ExpectContinueFilter::new();
+
+        assert!(!filter.matches(None, &ctx, &request(Version::HTTP_11, &[])));
+        assert!(!filter.matches(None, &ctx, &request(Version::HTTP_11, &["foo"])));
+        assert!(!filter.matches(None, &ctx, &request(Version::HTTP_11, &["100-continued"])));
+        assert!(!filter.matches(None, &ctx, &request(Version::HTTP_10, &["100-continue"])));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    http::{HeaderMap, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone, Default)]
+/// Filter based on the total size and/or count of the [`Request`]'s headers,
+/// matching only if the headers are within the configured budget.
+///
+/// The size of a header is computed as the length of its name plus
+/// the length of its value, the total size being the sum of the size
+/// of all headers. Each value of a repeated header counts as a separate header.
+///
+/// A filter without any limits configured matches all requests.
+///
+/// [`Request`]: crate::http::Request
+pub struct HeaderBudgetFilter {
+    max_size: Option<usize>,
+    max_count: Option<usize>,
+}
+
+impl HeaderBudgetFilter {
+    /// Create a new header budget filter, without any limits configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the total size (in bytes) of all headers.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Limit the number of headers.
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Returns `true` if the given headers are within the configured budget.
+    pub fn is_within_budget(&self, headers: &HeaderMap) -> bool {
+        if let Some(max_count) = self.max_count {
+            if headers.len() > max_count {
+                return false;
+            }
+        }
+        if let Some(max_size) = self.max_size {
+            let mut size = 0;
+            for (name, value) in headers.iter() {
+                size += name.as_str().len() + value.len();
+                if size > max_size {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for HeaderBudgetFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        self.is_within_budget(req.headers())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_header_budget_filter_no_limits() {
+        let filter = HeaderBudgetFilter::new();
+        let ctx = Context::default();
+
+        assert!(filter.matches(None, &ctx, &request(&[])));
+        assert!(filter.matches(None, &ctx, &request(&[("x-foo", &"a".repeat(1024))])));
+    }
+
+    #[test]
+    fn test_header_budget_filter_max_size() {
+        // "x-foo" + "bar" = 8 bytes
+        let filter = HeaderBudgetFilter::new().max_size(16);
+        let ctx = Context::default();
+
+        assert!(filter.matches(None, &ctx, &request(&[])));
+        assert!(filter.matches(None, &ctx, &request(&[("x-foo", "bar")])));
+        assert!(filter.matches(None, &ctx, &request(&[("x-foo", "bar"), ("x-baz", "qux")])));
+        assert!(!filter.matches(None, &ctx, &request(&[("x-foo", "bar"), ("x-baz", "quxx")])));
+        assert!(!filter.matches(None, &ctx, &request(&[("x-foo", &"a".repeat(12))])));
+    }
+
+    #[test]
+    fn test_header_budget_filter_max_count() {
+        let filter = HeaderBudgetFilter::new().max_count(2);
+        let ctx = Context::default();
+
+        assert!(filter.matches(None, &ctx, &request(&[])));
+        assert!(filter.matches(None, &ctx, &request(&[("x-foo", "bar"), ("x-baz", "qux")])));
+        assert!(!filter.matches(
+            None,
+            &ctx,
+            &request(&[("x-foo", "bar"), ("x-baz", "qux"), ("x-qux", "baz")])
+        ));
+        // repeated headers count as separate headers
+        assert!(!filter.matches(
+            None,
+            &ctx,
+            &request(&[("x-foo", "a"), ("x-foo", "b"), ("x-foo", "c")])
+        ));
+    }
+}
@@ -22,14 +22,74 @@ mod version;
 pub use version::VersionFilter;
 
 mod path;
-pub use path::{PathFilter, UriParams, UriParamsDeserializeError};
+pub use path::{PathFilter, PathRouter, UriParams, UriParamsDeserializeError};
 
 mod header;
 #[doc(inline)]
 pub use header::HeaderFilter;
 
+mod header_budget;
+#[doc(inline)]
+pub use header_budget::HeaderBudgetFilter;
+
+mod content_length;
+#[doc(inline)]
+pub use content_length::ContentLengthFilter;
+
+mod content_encoding;
+#[doc(inline)]
+pub use content_encoding::ContentEncodingFilter;
+
+mod accept;
+#[doc(inline)]
+pub use accept::AcceptFilter;
+
+mod expect_continue;
+#[doc(inline)]
+pub use expect_continue::ExpectContinueFilter;
+
+mod body_prefix;
+#[doc(inline)]
+pub use body_prefix::BodyPrefixFilter;
+
+mod absolute_uri;
+#[doc(inline)]
+pub use absolute_uri::AbsoluteUriFilter;
+
+mod nonce;
+#[doc(inline)]
+pub use nonce::NonceFilter;
+
+mod request_index;
+#[doc(inline)]
+pub use request_index::RequestIndexFilter;
+
+mod weighted_split;
+#[doc(inline)]
+pub use weighted_split::WeightedSplitFilter;
+
+mod origin;
+#[doc(inline)]
+pub use origin::OriginFilter;
+
+mod reverse_dns;
+#[doc(inline)]
+pub use reverse_dns::ReverseDnsFilter;
+
+mod claim;
+#[doc(inline)]
+pub use claim::ClaimFilter;
+
+mod range;
+#[doc(inline)]
+pub use range::RangeRequestFilter;
+
+mod date_freshness;
+#[doc(inline)]
+pub use date_freshness::DateFreshnessFilter;
+
 use crate::{
-    http::Request,
+    http::{Method, Request},
     service::{context::Extensions, matcher::IteratorMatcherExt, Context},
     stream::matcher::SocketMatcher,
 };
@@ -64,6 +124,10 @@ pub enum HttpFilterKind {
     ///
     /// [`SocketAddr`]: std::net::SocketAddr
     Socket(SocketMatcher),
+    /// [`OriginFilter`], a filter matching the `Origin` header against an allow-list.
+    Origin(OriginFilter),
+    /// [`ClaimFilter`], a filter matching a claim of the authenticated request.
+    Claim(ClaimFilter),
 }
 
 impl HttpMatcher {
@@ -797,6 +861,152 @@ impl HttpMatcher {
         self
     }
 
+    /// Create a new filter that matches the `Origin` header against an allow-list.
+    ///
+    /// See [`OriginFilter`] for more information.
+    pub fn origin(origin: OriginFilter) -> Self {
+        Self {
+            kind: HttpFilterKind::Origin(origin),
+            negate: false,
+        }
+    }
+
+    /// Add an [`OriginFilter`] to filter on top of the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`OriginFilter`] for more information.
+    pub fn and_origin(mut self, origin: OriginFilter) -> Self {
+        let filter = HttpFilterKind::Origin(origin);
+        match &mut self.kind {
+            HttpFilterKind::All(v) => {
+                v.push(filter);
+            }
+            _ => {
+                self.kind = HttpFilterKind::All(vec![self.kind, filter]);
+            }
+        }
+        self
+    }
+
+    /// Create an [`OriginFilter`] to match as an alternative to the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`OriginFilter`] for more information.
+    pub fn or_origin(mut self, origin: OriginFilter) -> Self {
+        let filter = HttpFilterKind::Origin(origin);
+        match &mut self.kind {
+            HttpFilterKind::Any(v) => {
+                v.push(filter);
+            }
+            _ => {
+                self.kind = HttpFilterKind::Any(vec![self.kind, filter]);
+            }
+        }
+        self
+    }
+
+    /// Create a new filter that matches a claim of the authenticated request.
+    ///
+    /// See [`ClaimFilter`] for more information.
+    pub fn claim(claim: ClaimFilter) -> Self {
+        Self {
+            kind: HttpFilterKind::Claim(claim),
+            negate: false,
+        }
+    }
+
+    /// Add a [`ClaimFilter`] to filter on top of the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`ClaimFilter`] for more information.
+    pub fn and_claim(mut self, claim: ClaimFilter) -> Self {
+        let filter = HttpFilterKind::Claim(claim);
+        match &mut self.kind {
+            HttpFilterKind::All(v) => {
+                v.push(filter);
+            }
+            _ => {
+                self.kind = HttpFilterKind::All(vec![self.kind, filter]);
+            }
+        }
+        self
+    }
+
+    /// Create a [`ClaimFilter`] to match as an alternative to the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`ClaimFilter`] for more information.
+    pub fn or_claim(mut self, claim: ClaimFilter) -> Self {
+        let filter = HttpFilterKind::Claim(claim);
+        match &mut self.kind {
+            HttpFilterKind::Any(v) => {
+                v.push(filter);
+            }
+            _ => {
+                self.kind = HttpFilterKind::Any(vec![self.kind, filter]);
+            }
+        }
+        self
+    }
+
+    /// Create a filter on the peer of the connection,
+    /// using a socket-level filter such as a [`LoopbackFilter`] or [`SocketMatcher`].
+    ///
+    /// This is equivalent to [`HttpMatcher::socket`],
+    /// without having to wrap the filter in a [`SocketMatcher`] first.
+    ///
+    /// [`LoopbackFilter`]: crate::stream::matcher::LoopbackFilter
+    pub fn from_peer(peer: impl Into<SocketMatcher>) -> Self {
+        Self::socket(peer.into())
+    }
+
+    /// Add a filter on the peer of the connection on top of the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`HttpMatcher::from_peer`] for more information.
+    pub fn and_peer(self, peer: impl Into<SocketMatcher>) -> Self {
+        self.and_socket(peer.into())
+    }
+
+    /// Create a filter on the peer of the connection to match as an alternative
+    /// to the existing set of [`HttpMatcher`] filters.
+    ///
+    /// See [`HttpMatcher::from_peer`] for more information.
+    pub fn or_peer(self, peer: impl Into<SocketMatcher>) -> Self {
+        self.or_socket(peer.into())
+    }
+
+    /// Create a filter that matches requests with the given [`Method`] and path.
+    ///
+    /// This is a shorthand for combining [`HttpMatcher::method`] and [`HttpMatcher::and_path`],
+    /// see [`MethodFilter`] and [`PathFilter`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method has no matching [`MethodFilter`] (e.g. an extension method).
+    pub fn route(method: Method, path: impl AsRef<str>) -> Self {
+        Self::method(route_method_filter(&method)).and_path(path)
+    }
+
+    /// Create a filter that can also match requests with the given [`Method`] and path
+    /// as an alternative to the existing [`HttpMatcher`] filters.
+    ///
+    /// See [`HttpMatcher::route`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method has no matching [`MethodFilter`] (e.g. an extension method).
+    pub fn or_route(mut self, method: Method, path: impl AsRef<str>) -> Self {
+        let filter = HttpFilterKind::All(vec![
+            HttpFilterKind::Method(route_method_filter(&method)),
+            HttpFilterKind::Path(PathFilter::new(path)),
+        ]);
+        match &mut self.kind {
+            HttpFilterKind::Any(v) => {
+                v.push(filter);
+            }
+            _ => {
+                self.kind = HttpFilterKind::Any(vec![self.kind, filter]);
+            }
+        };
+        self
+    }
+
     /// Create a [`PathFilter`] filter to match for a GET request.
     pub fn get(path: impl AsRef<str>) -> Self {
         Self::method_get().and_path(path)
@@ -846,6 +1056,13 @@ impl HttpMatcher {
     }
 }
 
+fn route_method_filter(method: &Method) -> MethodFilter {
+    match MethodFilter::try_from(method) {
+        Ok(filter) => filter,
+        Err(err) => panic!("invalid route method: {err}"),
+    }
+}
+
 impl<State, Body> crate::service::Matcher<State, Request<Body>> for HttpMatcher {
     fn matches(
         &self,
@@ -878,7 +1095,152 @@ impl<State, Body> crate::service::Matcher<State, Request<Body>> for HttpFilterKi
             HttpFilterKind::Uri(uri) => uri.matches(ext, ctx, req),
             HttpFilterKind::Header(header) => header.matches(ext, ctx, req),
             HttpFilterKind::Socket(socket) => socket.matches(ext, ctx, req),
+            HttpFilterKind::Origin(origin) => origin.matches(ext, ctx, req),
+            HttpFilterKind::Claim(claim) => claim.matches(ext, ctx, req),
             HttpFilterKind::Any(all) => all.iter().matches_or(ext, ctx, req),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        service::Matcher,
+        stream::{
+            matcher::{LoopbackFilter, PortFilter},
+            SocketInfo,
+        },
+    };
+
+    fn request(path: &str) -> Request<()> {
+        Request::builder().uri(path).body(()).unwrap()
+    }
+
+    fn context(peer: std::net::SocketAddr) -> Context<()> {
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, peer));
+        ctx
+    }
+
+    #[test]
+    fn test_http_matcher_peer_and_path() {
+        let matcher = HttpMatcher::from_peer(LoopbackFilter::new()).and_path("/admin/*");
+
+        let loopback = context(([127, 0, 0, 1], 8080).into());
+        let remote = context(([192, 168, 0, 1], 8080).into());
+
+        assert!(matcher.matches(None, &loopback, &request("/admin/users")));
+        assert!(!matcher.matches(None, &loopback, &request("/api/users")));
+        assert!(!matcher.matches(None, &remote, &request("/admin/users")));
+        assert!(!matcher.matches(None, &remote, &request("/api/users")));
+
+        // no socket info available
+        assert!(!matcher.matches(None, &Context::default(), &request("/admin/users")));
+    }
+
+    #[test]
+    fn test_http_matcher_path_and_peer_forwards_extensions() {
+        let matcher = HttpMatcher::path("/users/:id").and_peer(PortFilter::new(8080));
+
+        let mut ext = Extensions::new();
+        let ctx = context(([192, 168, 0, 1], 8080).into());
+        assert!(matcher.matches(Some(&mut ext), &ctx, &request("/users/42")));
+        assert_eq!(ext.get::<UriParams>().unwrap().get("id"), Some("42"));
+
+        let ctx = context(([192, 168, 0, 1], 9090).into());
+        assert!(!matcher.matches(None, &ctx, &request("/users/42")));
+    }
+
+    #[test]
+    fn test_http_matcher_or_peer() {
+        let matcher = HttpMatcher::path("/public/*").or_peer(LoopbackFilter::new());
+
+        let loopback = context(([127, 0, 0, 1], 8080).into());
+        let remote = context(([192, 168, 0, 1], 8080).into());
+
+        assert!(matcher.matches(None, &remote, &request("/public/index.html")));
+        assert!(matcher.matches(None, &loopback, &request("/private")));
+        assert!(!matcher.matches(None, &remote, &request("/private")));
+    }
+
+    #[test]
+    fn test_http_matcher_path_and_origin() {
+        let matcher =
+            HttpMatcher::get("/ws").and_origin(OriginFilter::new().allow("https://*.example.com"));
+
+        let request = |origin: &str| {
+            Request::builder()
+                .uri("/ws")
+                .header(http::header::ORIGIN, origin)
+                .body(())
+                .unwrap()
+        };
+        let ctx = Context::default();
+
+        assert!(matcher.matches(None, &ctx, &request("https://app.example.com")));
+        assert!(!matcher.matches(None, &ctx, &request("https://example.org")));
+        assert!(!matcher.matches(None, &ctx, &super::test::request("/ws")));
+    }
+
+    fn method_request(method: Method, path: &str) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_http_matcher_route() {
+        let matcher = HttpMatcher::route(Method::POST, "/submit");
+        let ctx = Context::default();
+
+        assert!(matcher.matches(None, &ctx, &method_request(Method::POST, "/submit")));
+        // method mismatch
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::GET, "/submit")));
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::PUT, "/submit")));
+        // path mismatch
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::POST, "/")));
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::POST, "/submit/more")));
+        // both mismatch
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::GET, "/other")));
+    }
+
+    #[test]
+    fn test_http_matcher_route_composes() {
+        let ctx = Context::default();
+
+        let matcher = HttpMatcher::route(Method::GET, "/users/:id")
+            .or_route(Method::DELETE, "/admin/users/:id");
+        let mut ext = Extensions::new();
+        assert!(matcher.matches(
+            Some(&mut ext),
+            &ctx,
+            &method_request(Method::GET, "/users/42")
+        ));
+        assert_eq!(ext.get::<UriParams>().unwrap().get("id"), Some("42"));
+        assert!(matcher.matches(
+            None,
+            &ctx,
+            &method_request(Method::DELETE, "/admin/users/42")
+        ));
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::DELETE, "/users/42")));
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::GET, "/admin/users/42")));
+
+        let matcher = HttpMatcher::route(Method::POST, "/submit").negate();
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::POST, "/submit")));
+        assert!(matcher.matches(None, &ctx, &method_request(Method::GET, "/submit")));
+
+        let matcher = HttpMatcher::route(Method::POST, "/submit").and_peer(LoopbackFilter::new());
+        let loopback = context(([127, 0, 0, 1], 8080).into());
+        assert!(matcher.matches(None, &loopback, &method_request(Method::POST, "/submit")));
+        assert!(!matcher.matches(None, &ctx, &method_request(Method::POST, "/submit")));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_http_matcher_route_extension_method() {
+        HttpMatcher::route(Method::from_bytes(b"PURGE").unwrap(), "/");
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    http::{utils::LruMap, HeaderName, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+const DEFAULT_HEADER: HeaderName = HeaderName::from_static("x-nonce");
+const DEFAULT_WINDOW: Duration = Duration::from_secs(300);
+const DEFAULT_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone)]
+/// Filter matching requests which are replayed, i.e. of which the nonce
+/// was already seen within the configured window (5 minutes by default).
+///
+/// The nonce of a request is by default the value of its `X-Nonce` header,
+/// requests without a nonce never match. Use [`NonceFilter::request_hash`]
+/// to use a hash of the method, uri and headers of the request as its nonce instead,
+/// which flags identical requests as replays, including legitimate ones.
+///
+/// This is a rudimentary guard against naive replays, the seen nonces are kept
+/// in a bounded set (of 10 000 nonces by default), evicting the least recently seen
+/// nonce once it is full. Replays of an evicted nonce are no longer detected,
+/// and given that only a hash of each nonce is stored, distinct nonces with
+/// colliding hashes can be flagged as replays (although that is very unlikely).
+///
+/// The nonce of a request is recorded as a side effect of matching, meaning that
+/// the filter matches a request in case it is evaluated more than once for it.
+/// All clones of a filter share the same set of seen nonces.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{matcher::NonceFilter, Request, StatusCode};
+/// use rama::http::service::web::match_service;
+/// use rama::service::{Context, Service};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let service = match_service! {
+///     NonceFilter::new() => StatusCode::CONFLICT,
+///     _ => StatusCode::OK,
+/// };
+///
+/// let request = || Request::builder().header("x-nonce", "abc").body(Default::default()).unwrap();
+/// let response = service.serve(Context::default(), request()).await.unwrap();
+/// assert_eq!(response.status(), StatusCode::OK);
+/// let response = service.serve(Context::default(), request()).await.unwrap();
+/// assert_eq!(response.status(), StatusCode::CONFLICT);
+/// # }
+/// ```
+pub struct NonceFilter {
+    source: NonceSource,
+    window: Duration,
+    capacity: usize,
+    hasher: RandomState,
+    seen: Arc<Mutex<SeenNonces>>,
+}
+
+#[derive(Debug, Clone)]
+enum NonceSource {
+    Header(HeaderName),
+    RequestHash,
+}
+
+/// The (hashed) nonces seen most recently, with the instant they were last seen.
+#[derive(Debug, Default)]
+struct SeenNonces(LruMap<u64, Instant>);
+
+impl SeenNonces {
+    /// Record the given nonce, returning `true` if it was already seen within the window.
+    fn record(&mut self, nonce: u64, window: Duration, capacity: usize) -> bool {
+        let now = Instant::now();
+        self.0
+            .insert(nonce, now, capacity)
+            .is_some_and(|seen_at| now.duration_since(seen_at) < window)
+    }
+}
+
+impl NonceFilter {
+    /// Create a new filter using the value of the `X-Nonce` header as nonce.
+    pub fn new() -> Self {
+        Self::with_header(DEFAULT_HEADER)
+    }
+
+    /// Create a new filter using the value of the given header as nonce.
+    pub fn with_header(name: HeaderName) -> Self {
+        Self::with_source(NonceSource::Header(name))
+    }
+
+    /// Create a new filter using a hash of the method, uri and headers
+    /// of the request as nonce.
+    pub fn request_hash() -> Self {
+        Self::with_source(NonceSource::RequestHash)
+    }
+
+    fn with_source(source: NonceSource) -> Self {
+        Self {
+            source,
+            window: DEFAULT_WINDOW,
+            capacity: DEFAULT_CAPACITY,
+            hasher: RandomState::new(),
+            seen: Arc::new(Mutex::new(SeenNonces::default())),
+        }
+    }
+
+    /// Set the window within which a nonce seen before flags a request as replayed.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the maximum number of nonces remembered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is `0`.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "nonce filter capacity must be non-zero");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Compute the (hashed) nonce of the given request, if any.
+    fn nonce<Body>(&self, req: &Request<Body>) -> Option<u64> {
+        let mut hasher = self.hasher.build_hasher();
+        match &self.source {
+            NonceSource::Header(name) => req.headers().get(name)?.as_bytes().hash(&mut hasher),
+            NonceSource::RequestHash => {
+                req.method().hash(&mut hasher);
+                req.uri().hash(&mut hasher);
+                for (name, value) in req.headers() {
+                    name.hash(&mut hasher);
+                    value.hash(&mut hasher);
+                }
+            }
+        }
+        Some(hasher.finish())
+    }
+}
+
+impl Default for NonceFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for NonceFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        match self.nonce(req) {
+            Some(nonce) => self
+                .seen
+                .lock()
+                .unwrap()
+                .record(nonce, self.window, self.capacity),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(nonce: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().uri("/transfer");
+        if let Some(nonce) = nonce {
+            builder = builder.header("x-nonce", nonce);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_nonce_filter_replay() {
+        let filter = NonceFilter::new();
+        let ctx = Context::default();
+
+        // first seen: no match
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+        assert!(!filter.matches(None, &ctx, &request(Some("b"))));
+
+        // immediate replay: match, also for clones of the filter
+        assert!(filter.matches(None, &ctx, &request(Some("a"))));
+        assert!(filter.clone().matches(None, &ctx, &request(Some("b"))));
+
+        // no nonce: never a match
+        assert!(!filter.matches(None, &ctx, &request(None)));
+        assert!(!filter.matches(None, &ctx, &request(None)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_nonce_filter_window() {
+        let filter = NonceFilter::new().window(Duration::from_secs(10));
+        let ctx = Context::default();
+
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(filter.matches(None, &ctx, &request(Some("a"))));
+
+        // seen again after the window expired: no match
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+    }
+
+    #[test]
+    fn test_nonce_filter_capacity() {
+        let filter = NonceFilter::new().capacity(2);
+        let ctx = Context::default();
+
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+        assert!(!filter.matches(None, &ctx, &request(Some("b"))));
+        assert!(!filter.matches(None, &ctx, &request(Some("c"))));
+
+        // "a" got evicted, its replay is no longer detected
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+        assert!(filter.matches(None, &ctx, &request(Some("c"))));
+    }
+
+    #[test]
+    fn test_nonce_filter_request_hash() {
+        let filter = NonceFilter::request_hash();
+        let ctx = Context::default();
+
+        assert!(!filter.matches(None, &ctx, &request(None)));
+        assert!(filter.matches(None, &ctx, &request(None)));
+        assert!(!filter.matches(None, &ctx, &request(Some("a"))));
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    http::{header, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone)]
+/// Filter based on the `Origin` header of the [`Request`], matching it against an allow-list,
+/// e.g. to gate WebSocket upgrades or CORS requests to trusted origins.
+///
+/// Origins are allowed as `scheme://host[:port]`, where the host can start with a `*.`
+/// wildcard to allow all its subdomains (but not the domain itself).
+/// Origins are compared case-insensitively, and the default port of the scheme
+/// (`80` for `http` and `443` for `https`) can be omitted.
+///
+/// The opaque `null` origin (e.g. sent by sandboxed documents) and requests without an `Origin`
+/// header (e.g. sent by non-browser clients) never match, unless allowed using
+/// [`OriginFilter::allow_null`] and [`OriginFilter::allow_missing`] respectively.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{matcher::OriginFilter, Request};
+/// use rama::service::{Context, Matcher};
+///
+/// let filter = OriginFilter::new()
+///     .allow("https://example.com")
+///     .allow("https://*.example.com");
+///
+/// let request = Request::builder().header("origin", "https://app.example.com").body(()).unwrap();
+/// assert!(filter.matches(None, &Context::<()>::default(), &request));
+///
+/// let request = Request::builder().header("origin", "https://example.org").body(()).unwrap();
+/// assert!(!filter.matches(None, &Context::<()>::default(), &request));
+/// ```
+///
+/// [`Request`]: crate::http::Request
+pub struct OriginFilter {
+    allowed: Vec<AllowedOrigin>,
+    allow_null: bool,
+    allow_missing: bool,
+}
+
+#[derive(Debug, Clone)]
+struct AllowedOrigin {
+    scheme: String,
+    host: String,
+    wildcard: bool,
+    port: Option<u16>,
+}
+
+/// An origin split into its (lowercased) scheme, host and (non-default) port.
+#[derive(Debug)]
+struct Origin {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+}
+
+impl Origin {
+    fn parse(s: &str) -> Option<Self> {
+        let (scheme, authority) = s.split_once("://")?;
+        if scheme.is_empty() || authority.is_empty() || authority.contains(['/', '?', '#', '@']) {
+            return None;
+        }
+        let scheme = scheme.to_ascii_lowercase();
+        // the host can be an IPv6 address, e.g. `[::1]:8080`
+        let port_separator = match authority.rfind(']') {
+            Some(end) => authority[end..].find(':').map(|i| end + i),
+            None => authority.rfind(':'),
+        };
+        let (host, port) = match port_separator {
+            Some(i) => (
+                &authority[..i],
+                Some(authority[i + 1..].parse::<u16>().ok()?),
+            ),
+            None => (authority, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let default_port = match scheme.as_str() {
+            "http" | "ws" => Some(80),
+            "https" | "wss" => Some(443),
+            _ => None,
+        };
+        Some(Self {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port: port.filter(|port| Some(*port) != default_port),
+        })
+    }
+}
+
+impl OriginFilter {
+    /// Create a new filter with an empty allow-list, matching no origins.
+    pub fn new() -> Self {
+        Self {
+            allowed: Vec::new(),
+            allow_null: false,
+            allow_missing: false,
+        }
+    }
+
+    /// Add the given origin (e.g. `https://example.com` or `https://*.example.com`)
+    /// to the allow-list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the origin is not of the form `scheme://host[:port]`.
+    pub fn allow(mut self, origin: impl AsRef<str>) -> Self {
+        let origin = origin.as_ref();
+        let Origin { scheme, host, port } = match Origin::parse(origin) {
+            Some(parsed) => parsed,
+            None => panic!("invalid origin: {origin}"),
+        };
+        let (host, wildcard) = match host.strip_prefix("*.") {
+            Some(domain) => (domain.to_owned(), true),
+            None => (host, false),
+        };
+        self.allowed.push(AllowedOrigin {
+            scheme,
+            host,
+            wildcard,
+            port,
+        });
+        self
+    }
+
+    /// Match (or not) the opaque `null` origin.
+    ///
+    /// Default is `false`.
+    pub fn allow_null(mut self, allow: bool) -> Self {
+        self.allow_null = allow;
+        self
+    }
+
+    /// Match (or not) requests without an `Origin` header.
+    ///
+    /// Default is `false`.
+    pub fn allow_missing(mut self, allow: bool) -> Self {
+        self.allow_missing = allow;
+        self
+    }
+
+    fn matches_origin(&self, origin: &str) -> bool {
+        if origin.eq_ignore_ascii_case("null") {
+            return self.allow_null;
+        }
+        let origin = match Origin::parse(origin) {
+            Some(origin) => origin,
+            None => return false,
+        };
+        self.allowed.iter().any(|allowed| {
+            allowed.scheme == origin.scheme
+                && allowed.port == origin.port
+                && if allowed.wildcard {
+                    origin
+                        .host
+                        .strip_suffix(allowed.host.as_str())
+                        .map(|sub| sub.len() > 1 && sub.ends_with('.'))
+                        .unwrap_or_default()
+                } else {
+                    allowed.host == origin.host
+                }
+        })
+    }
+}
+
+impl Default for OriginFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for OriginFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        let mut values = req.headers().get_all(header::ORIGIN).iter();
+        match (values.next(), values.next()) {
+            (None, _) => self.allow_missing,
+            (Some(value), None) => value
+                .to_str()
+                .map(|origin| self.matches_origin(origin.trim()))
+                .unwrap_or_default(),
+            // multiple origins are not allowed
+            (Some(_), Some(_)) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(origins: &[&str]) -> Request<()> {
+        let mut builder = Request::builder();
+        for origin in origins {
+            builder = builder.header(header::ORIGIN, *origin);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn filter() -> OriginFilter {
+        OriginFilter::new()
+            .allow("https://example.com")
+            .allow("http://localhost:8080")
+            .allow("https://*.example.org")
+    }
+
+    #[test]
+    fn test_origin_filter_exact() {
+        let ctx = Context::default();
+        let filter = filter();
+
+        assert!(filter.matches(None, &ctx, &request(&["https://example.com"])));
+        assert!(filter.matches(None, &ctx, &request(&["HTTPS://Example.COM"])));
+        assert!(filter.matches(None, &ctx, &request(&["https://example.com:443"])));
+        assert!(filter.matches(None, &ctx, &request(&["http://localhost:8080"])));
+
+        assert!(!filter.matches(None, &ctx, &request(&["http://example.com"])));
+        assert!(!filter.matches(None, &ctx, &request(&["https://example.com:8443"])));
+        assert!(!filter.matches(None, &ctx, &request(&["https://www.example.com"])));
+        assert!(!filter.matches(None, &ctx, &request(&["https://example.com.evil.com"])));
+        assert!(!filter.matches(None, &ctx, &request(&["http://localhost"])));
+        assert!(!filter.matches(None, &ctx, &request(&["not an origin"])));
+
+        let filter = OriginFilter::new().allow("http://[::1]:8080");
+        assert!(filter.matches(None, &ctx, &request(&["http://[::1]:8080"])));
+        assert!(!filter.matches(None, &ctx, &request(&["http://[::1]"])));
+    }
+
+    #[test]
+    fn test_origin_filter_wildcard_subdomain() {
+        let ctx = Context::default();
+        let filter = filter();
+
+        assert!(filter.matches(None, &ctx, &request(&["https://app.example.org"])));
+        assert!(filter.matches(None, &ctx, &request(&["https://a.b.example.org"])));
+
+        assert!(!filter.matches(None, &ctx, &request(&["https://example.org"])));
+        assert!(!filter.matches(None, &ctx, &request(&["https://evilexample.org"])));
+        assert!(!filter.matches(None, &ctx, &request(&["http://app.example.org"])));
+        assert!(!filter.matches(None, &ctx, &request(&["https://app.example.org:8443"])));
+    }
+
+    #[test]
+    fn test_origin_filter_null() {
+        let ctx = Context::default();
+
+        assert!(!filter().matches(None, &ctx, &request(&["null"])));
+        assert!(filter()
+            .allow_null(true)
+            .matches(None, &ctx, &request(&["null"])));
+    }
+
+    #[test]
+    fn test_origin_filter_missing() {
+        let ctx = Context::default();
+
+        assert!(!filter().matches(None, &ctx, &request(&[])));
+        assert!(filter()
+            .allow_missing(true)
+            .matches(None, &ctx, &request(&[])));
+
+        // multiple origins never match
+        assert!(!filter().allow_missing(true).matches(
+            None,
+            &ctx,
+            &request(&["https://example.com", "https://example.com"])
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_origin_filter_invalid_origin() {
+        OriginFilter::new().allow("example.com");
+    }
+}
@@ -6,6 +6,10 @@ use std::collections::HashMap;
 
 mod de;
 
+mod router;
+#[doc(inline)]
+pub use router::PathRouter;
+
 #[derive(Debug, Clone, Default)]
 /// parameters that are inserted in the [`Context`],
 /// in case the [`PathFilter`] found a match for the given [`Request`].
@@ -0,0 +1,451 @@
+use super::UriParams;
+use std::{borrow::Cow, collections::HashMap};
+
+#[derive(Debug, Clone)]
+/// A router resolving a URI path to the value of the best matching route,
+/// using a tree of path segments such that only the routes sharing
+/// a prefix with the path are considered.
+///
+/// Routes use the same syntax and matching rules as the [`PathFilter`]:
+///
+/// - literal segments match case-insensitively;
+/// - `:name` segments match any non-empty segment, captured as param `name`;
+/// - a `*` as the last segment matches one or more trailing segments, captured as glob.
+///
+/// When multiple routes match a path, literal segments take priority over params,
+/// which take priority over a glob, regardless of the order in which the routes
+/// were inserted. A path such as `/users/me` therefore resolves to the route `/users/me`
+/// rather than `/users/:id`, while `/users/me/posts` still resolves to `/users/:id/posts`.
+///
+/// The [`UriParams`] returned for a match can be inserted in the [`Context`],
+/// such that the value (e.g. a service) can make use of them, as is done for
+/// the endpoints of a [`WebService`].
+///
+/// # Example
+///
+/// ```
+/// use rama::http::matcher::PathRouter;
+///
+/// let mut router = PathRouter::new();
+/// router.insert("/users/:id", "user");
+/// router.insert("/users/me", "me");
+/// router.insert("/assets/*", "assets");
+///
+/// let (value, params) = router.at("/users/42").unwrap();
+/// assert_eq!(*value, "user");
+/// assert_eq!(params.get("id"), Some("42"));
+///
+/// let (value, _) = router.at("/users/me").unwrap();
+/// assert_eq!(*value, "me");
+///
+/// let (value, params) = router.at("/assets/css/reset.css").unwrap();
+/// assert_eq!(*value, "assets");
+/// assert_eq!(params.glob(), Some("/css/reset.css"));
+///
+/// assert!(router.at("/posts").is_none());
+/// ```
+///
+/// [`PathFilter`]: super::PathFilter
+/// [`Context`]: crate::service::Context
+/// [`WebService`]: crate::http::service::web::WebService
+pub struct PathRouter<T> {
+    root: Node<T>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    literals: HashMap<String, Node<T>>,
+    param: Option<Box<Node<T>>>,
+    glob: Option<Route<T>>,
+    route: Option<Route<T>>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self {
+            literals: HashMap::new(),
+            param: None,
+            glob: None,
+            route: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Route<T> {
+    value: T,
+    params: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Segment<'a> {
+    Literal(Cow<'a, str>),
+    Param(&'a str),
+    Glob,
+}
+
+/// Split the given route into segments, following the rules of the [`PathFilter`].
+///
+/// [`PathFilter`]: super::PathFilter
+fn route_segments(path: &str) -> Vec<Segment<'_>> {
+    let path = path.trim().trim_matches('/');
+
+    if !path.contains([':', '*']) {
+        return path
+            .split('/')
+            .map(|s| Segment::Literal(lowercase(s)))
+            .collect();
+    }
+
+    let parts: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let last = parts.len() - 1;
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, s)| {
+            if let Some(name) = s.strip_prefix(':') {
+                Segment::Param(name.trim_start_matches(':'))
+            } else if s == "*" && index == last {
+                Segment::Glob
+            } else {
+                Segment::Literal(lowercase(s))
+            }
+        })
+        .collect()
+}
+
+fn lowercase(s: &str) -> Cow<'_, str> {
+    if s.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(s.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+impl<T> PathRouter<T> {
+    /// Create a new, empty, [`PathRouter`].
+    pub fn new() -> Self {
+        Self {
+            root: Node::default(),
+            len: 0,
+        }
+    }
+
+    /// Insert a route for the given path, returning the value
+    /// of the route previously inserted for the same path, if any.
+    ///
+    /// Routes only differing in the names of their params are considered the same.
+    pub fn insert(&mut self, path: impl AsRef<str>, value: T) -> Option<T> {
+        let mut node = &mut self.root;
+        let mut params = Vec::new();
+        let mut glob = false;
+        for segment in route_segments(path.as_ref()) {
+            match segment {
+                Segment::Literal(literal) => {
+                    node = node.literals.entry(literal.into_owned()).or_default();
+                }
+                Segment::Param(name) => {
+                    params.push(name.to_lowercase());
+                    node = node.param.get_or_insert_with(Default::default);
+                }
+                Segment::Glob => glob = true,
+            }
+        }
+
+        let slot = if glob {
+            &mut node.glob
+        } else {
+            &mut node.route
+        };
+        let previous = slot.replace(Route { value, params });
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous.map(|route| route.value)
+    }
+
+    /// Resolve the given path to the value of the best matching route,
+    /// together with the params and glob captured for it.
+    pub fn at(&self, path: &str) -> Option<(&T, UriParams)> {
+        self.at_matching(path, |_| true)
+    }
+
+    /// Resolve the given path to the value of the best matching route accepted by the
+    /// given predicate, together with the params and glob captured for it.
+    ///
+    /// Routes which are not accepted are skipped, such that a less specific route can match instead.
+    pub(crate) fn at_matching<F>(&self, path: &str, accept: F) -> Option<(&T, UriParams)>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let path = path.trim().trim_matches('/');
+        let segments: Vec<_> = path.split('/').collect();
+
+        let mut captured = Vec::new();
+        let (route, glob_start) = self.root.find(&segments, 0, &accept, &mut captured)?;
+
+        let mut params = UriParams::default();
+        for (name, segment) in route.params.iter().zip(captured) {
+            let segment = percent_encoding::percent_decode(segment.as_bytes())
+                .decode_utf8()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|_| segment.to_owned());
+            params.insert(name.clone(), segment);
+        }
+        if let Some(start) = glob_start {
+            for segment in &segments[start..] {
+                params.append_glob(segment);
+            }
+        }
+
+        Some((&route.value, params))
+    }
+
+    /// Returns the number of routes in the router.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` in case the router contains no routes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T> Default for PathRouter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Node<T> {
+    /// Find the best matching route for the segments starting at the given index,
+    /// pushing the segments captured by params along the way.
+    ///
+    /// Returns the route and, in case it is a glob route, the index of the first globbed segment.
+    fn find<'a, 'p, F>(
+        &'a self,
+        segments: &[&'p str],
+        index: usize,
+        accept: &F,
+        captured: &mut Vec<&'p str>,
+    ) -> Option<(&'a Route<T>, Option<usize>)>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let Some(segment) = segments.get(index) else {
+            return self
+                .route
+                .as_ref()
+                .filter(|route| accept(&route.value))
+                .map(|route| (route, None));
+        };
+
+        if let Some(child) = self.literals.get(lowercase(segment).as_ref()) {
+            if let Some(found) = child.find(segments, index + 1, accept, captured) {
+                return Some(found);
+            }
+        }
+
+        if !segment.is_empty() {
+            if let Some(child) = &self.param {
+                captured.push(segment);
+                if let Some(found) = child.find(segments, index + 1, accept, captured) {
+                    return Some(found);
+                }
+                captured.pop();
+            }
+        }
+
+        self.glob
+            .as_ref()
+            .filter(|route| accept(&route.value))
+            .map(|route| (route, Some(index)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::matcher::PathFilter;
+
+    fn lookup<'a>(router: &'a PathRouter<&'static str>, path: &str) -> Option<&'a str> {
+        router.at(path).map(|(value, _)| *value)
+    }
+
+    #[test]
+    fn test_path_router_overlapping_prefixes() {
+        let mut router = PathRouter::new();
+        router.insert("/", "root");
+        router.insert("/api", "api");
+        router.insert("/api/users", "users");
+        router.insert("/api/users/:id", "user");
+        router.insert("/apix", "apix");
+
+        assert_eq!(lookup(&router, "/"), Some("root"));
+        assert_eq!(lookup(&router, ""), Some("root"));
+        assert_eq!(lookup(&router, "/api"), Some("api"));
+        assert_eq!(lookup(&router, "/api/"), Some("api"));
+        assert_eq!(lookup(&router, "/API/Users"), Some("users"));
+        assert_eq!(lookup(&router, "/api/users/42"), Some("user"));
+        assert_eq!(lookup(&router, "/apix"), Some("apix"));
+        assert_eq!(lookup(&router, "/ap"), None);
+        assert_eq!(lookup(&router, "/api/user"), None);
+        assert_eq!(lookup(&router, "/api/users/42/posts"), None);
+        assert_eq!(router.len(), 5);
+
+        // inserting the same route (modulo param names) replaces it
+        assert_eq!(router.insert("/api/users/:name", "named"), Some("user"));
+        assert_eq!(router.len(), 5);
+        let (value, params) = router.at("/api/users/glen%20dc").unwrap();
+        assert_eq!(*value, "named");
+        assert_eq!(params.get("name"), Some("glen dc"));
+        assert_eq!(params.get("id"), None);
+    }
+
+    #[test]
+    fn test_path_router_param_vs_static_priority() {
+        for reversed in [false, true] {
+            let mut routes = vec![
+                ("/users/:id", "user"),
+                ("/users/me", "me"),
+                ("/users/:id/posts", "user-posts"),
+                ("/users/me/settings", "me-settings"),
+            ];
+            if reversed {
+                routes.reverse();
+            }
+            let mut router = PathRouter::new();
+            for (path, value) in routes {
+                router.insert(path, value);
+            }
+
+            assert_eq!(lookup(&router, "/users/me"), Some("me"));
+            assert_eq!(lookup(&router, "/users/me/settings"), Some("me-settings"));
+            assert_eq!(lookup(&router, "/users/42"), Some("user"));
+
+            // backtracks from the literal to the param
+            let (value, params) = router.at("/users/me/posts").unwrap();
+            assert_eq!(*value, "user-posts");
+            assert_eq!(params.get("id"), Some("me"));
+
+            assert_eq!(lookup(&router, "/users/42/settings"), None);
+            assert_eq!(lookup(&router, "/users//posts"), None);
+        }
+    }
+
+    #[test]
+    fn test_path_router_wildcard_tail() {
+        let mut router = PathRouter::new();
+        router.insert("/assets/*", "assets");
+        router.insert("/assets/favicon.ico", "favicon");
+        router.insert("/assets/:locale/css/*", "css");
+        router.insert("/*", "fallback");
+
+        let (value, params) = router.at("/assets/js/app.js").unwrap();
+        assert_eq!(*value, "assets");
+        assert_eq!(params.glob(), Some("/js/app.js"));
+
+        assert_eq!(lookup(&router, "/assets/favicon.ico"), Some("favicon"));
+
+        let (value, params) = router.at("/assets/eu/css/reset.css").unwrap();
+        assert_eq!(*value, "css");
+        assert_eq!(params.get("locale"), Some("eu"));
+        assert_eq!(params.glob(), Some("/reset.css"));
+
+        // the glob requires at least one segment
+        let (value, params) = router.at("/assets/eu/css").unwrap();
+        assert_eq!(*value, "assets");
+        assert_eq!(params.get("locale"), None);
+        assert_eq!(params.glob(), Some("/eu/css"));
+        let (value, params) = router.at("/assets").unwrap();
+        assert_eq!(*value, "fallback");
+        assert_eq!(params.glob(), Some("/assets"));
+
+        let (value, params) = router.at("/").unwrap();
+        assert_eq!(*value, "fallback");
+        assert_eq!(params.glob(), Some("/"));
+
+        // a `*` that is not the last segment is a literal
+        let mut router = PathRouter::new();
+        router.insert("/foo/*/bar", "literal");
+        assert_eq!(lookup(&router, "/foo/*/bar"), Some("literal"));
+        assert_eq!(lookup(&router, "/foo/baz/bar"), None);
+    }
+
+    #[test]
+    fn test_path_router_at_matching() {
+        let mut router = PathRouter::new();
+        router.insert("/users/:id", "user");
+        router.insert("/users/me", "me");
+        router.insert("/users/*", "users");
+
+        let (value, params) = router.at_matching("/users/me", |v| *v != "me").unwrap();
+        assert_eq!(*value, "user");
+        assert_eq!(params.get("id"), Some("me"));
+
+        let (value, params) = router.at_matching("/users/me", |v| *v == "users").unwrap();
+        assert_eq!(*value, "users");
+        assert_eq!(params.get("id"), None);
+        assert_eq!(params.glob(), Some("/me"));
+
+        assert!(router.at_matching("/users/me", |_| false).is_none());
+    }
+
+    #[test]
+    fn test_path_router_many_routes() {
+        const SERVICES: usize = 1_000;
+
+        let mut router = PathRouter::new();
+        let mut filters = Vec::new();
+        for i in 0..SERVICES {
+            for (index, path) in [
+                format!("/service{i}/items"),
+                format!("/service{i}/items/:id"),
+                format!("/service{i}/items/:id/tags/:tag"),
+                format!("/service{i}/files/*"),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                assert!(router.insert(&path, (i, index)).is_none());
+                filters.push(((i, index), PathFilter::new(&path)));
+            }
+        }
+        assert_eq!(router.len(), SERVICES * 4);
+
+        for i in (0..SERVICES).step_by(7) {
+            for (path, expected) in [
+                (format!("/service{i}/items"), Some((i, 0))),
+                (format!("/service{i}/items/{i}"), Some((i, 1))),
+                (format!("/service{i}/items/{i}/tags/rust"), Some((i, 2))),
+                (format!("/service{i}/files/a/b/c.txt"), Some((i, 3))),
+                (format!("/service{i}/items/{i}/tags"), None),
+                (format!("/service{i}/files"), None),
+                (format!("/service{i}x/items"), None),
+            ] {
+                let result = router.at(&path);
+                assert_eq!(
+                    result.as_ref().map(|(value, _)| **value),
+                    expected,
+                    "{path}"
+                );
+
+                // the router agrees with the (linear) path filters
+                let filtered = filters
+                    .iter()
+                    .find_map(|(value, filter)| filter.matches_path(&path).map(|p| (*value, p)));
+                match (result, filtered) {
+                    (None, None) => (),
+                    (Some((value, params)), Some((filtered_value, filtered_params))) => {
+                        assert_eq!(*value, filtered_value, "{path}");
+                        assert_eq!(params.params, filtered_params.params, "{path}");
+                        assert_eq!(params.glob, filtered_params.glob, "{path}");
+                    }
+                    (result, filtered) => panic!("{path}: {:?} != {:?}", result, filtered),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    http::{header, HeaderMap, Method, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+#[derive(Debug, Clone, Default)]
+/// Filter matching [`Request`]s which carry a valid `Range` header,
+/// requesting one or more byte ranges of the targeted resource
+/// (e.g. `Range: bytes=0-499` or `Range: bytes=0-99, 200-, -500`).
+///
+/// This allows routing these requests to a handler capable of serving partial content.
+/// Requests with a malformed `Range` header (including unknown range units)
+/// or with multiple `Range` headers do not match, and neither do requests
+/// with a method other than `GET`, for which [RFC 9110] requires the `Range` header
+/// to be ignored. Use [`RangeRequestFilter::is_malformed_range`] to reject
+/// malformed ranges (e.g. with a `400 Bad Request` response) instead.
+///
+/// Whether the ranges are satisfiable depends on the length of the resource,
+/// and is therefore left to the handler.
+///
+/// [`Request`]: crate::http::Request
+/// [RFC 9110]: https://www.rfc-editor.org/rfc/rfc9110#section-14.2
+pub struct RangeRequestFilter {
+    _priv: (),
+}
+
+impl RangeRequestFilter {
+    /// Create a new filter matching requests carrying a valid `Range` header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the given headers contain a single, valid `Range` header.
+    pub fn is_range_request(headers: &HeaderMap) -> bool {
+        matches!(range_header(headers), Some(Ok(())))
+    }
+
+    /// Returns `true` if the given headers contain a `Range` header which is malformed,
+    /// or in case they contain multiple `Range` headers.
+    pub fn is_malformed_range(headers: &HeaderMap) -> bool {
+        matches!(range_header(headers), Some(Err(())))
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for RangeRequestFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        req.method() == Method::GET && Self::is_range_request(req.headers())
+    }
+}
+
+/// Validate the `Range` header of the given headers, if any.
+fn range_header(headers: &HeaderMap) -> Option<Result<(), ()>> {
+    let mut values = headers.get_all(header::RANGE).iter();
+    let value = values.next()?;
+    if values.next().is_some() {
+        return Some(Err(()));
+    }
+    Some(value.to_str().map_err(drop).and_then(parse_byte_ranges))
+}
+
+/// Validate the `bytes=<range-set>` syntax of a `Range` header value,
+/// as defined by [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-14.1.2).
+fn parse_byte_ranges(value: &str) -> Result<(), ()> {
+    let (unit, ranges) = value.split_once('=').ok_or(())?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return Err(());
+    }
+
+    let mut count = 0;
+    for range in ranges.split(',').map(str::trim) {
+        // empty list elements are allowed, but do not count as a range
+        if range.is_empty() {
+            continue;
+        }
+        let (first, last) = range.split_once('-').ok_or(())?;
+        match (parse_position(first), parse_position(last)) {
+            // int-range
+            (Some(first), Some(last)) if first <= last => (),
+            (Some(_), None) if last.is_empty() => (),
+            // suffix-range
+            (None, Some(_)) if first.is_empty() => (),
+            _ => return Err(()),
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return Err(());
+    }
+    Ok(())
+}
+
+fn parse_position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(method: Method, range: &[&str]) -> Request<()> {
+        let mut builder = Request::builder().method(method);
+        for value in range {
+            builder = builder.header(header::RANGE, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn matches(range: &[&str]) -> bool {
+        RangeRequestFilter::new().matches(
+            None,
+            &Context::<()>::default(),
+            &request(Method::GET, range),
+        )
+    }
+
+    #[test]
+    fn test_range_request_filter_single() {
+        assert!(matches(&["bytes=0-499"]));
+        assert!(matches(&["bytes=500-"]));
+        assert!(matches(&["bytes=-500"]));
+        assert!(matches(&["bytes=42-42"]));
+        assert!(matches(&["Bytes=0-499"]));
+    }
+
+    #[test]
+    fn test_range_request_filter_multi() {
+        assert!(matches(&["bytes=0-99,200-299"]));
+        assert!(matches(&["bytes=0-99, 200-, -500"]));
+        assert!(matches(&["bytes=0-99,,200-299,"]));
+    }
+
+    #[test]
+    fn test_range_request_filter_malformed() {
+        for range in [
+            "bytes=",
+            "bytes=,",
+            "bytes=499-0",
+            "bytes=-",
+            "bytes=a-b",
+            "bytes=0-99;200-299",
+            "bytes=0-99,foo",
+            "bytes=+1-2",
+            "bytes=0--1",
+            "bytes 0-499",
+            "bytes = 0-499",
+            "items=0-9",
+            "0-499",
+        ] {
+            assert!(!matches(&[range]), "{range}");
+            assert!(RangeRequestFilter::is_malformed_range(
+                request(Method::GET, &[range]).headers()
+            ));
+        }
+
+        // multiple range headers are ambiguous
+        assert!(!matches(&["bytes=0-99", "bytes=200-299"]));
+        assert!(RangeRequestFilter::is_malformed_range(
+            request(Method::GET, &["bytes=0-99", "bytes=200-299"]).headers()
+        ));
+    }
+
+    #[test]
+    fn test_range_request_filter_no_range() {
+        assert!(!matches(&[]));
+        assert!(!RangeRequestFilter::is_malformed_range(
+            request(Method::GET, &[]).headers()
+        ));
+    }
+
+    #[test]
+    fn test_range_request_filter_method() {
+        let ctx = Context::default();
+        let filter = RangeRequestFilter::new();
+
+        assert!(!filter.matches(None, &ctx, &request(Method::HEAD, &["bytes=0-499"])));
+        assert!(!filter.matches(None, &ctx, &request(Method::POST, &["bytes=0-499"])));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    http::{server::RequestIndex, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone)]
+/// Filter based on the ordinal of the [`Request`] within its (keep-alive) connection,
+/// matching only if it is within the configured (inclusive) range.
+///
+/// The ordinal is read from the [`RequestIndex`] found in the [`Context`],
+/// as inserted by the [`HttpServer`] for each request it serves.
+/// Requests without a [`RequestIndex`] never match.
+///
+/// This can for example be used to only apply a one-time handshake
+/// to the first request of each connection, or to test keep-alive behaviour.
+///
+/// [`Request`]: crate::http::Request
+/// [`HttpServer`]: crate::http::server::HttpServer
+pub struct RequestIndexFilter {
+    range: RangeInclusive<usize>,
+}
+
+impl RequestIndexFilter {
+    /// Create a new filter matching only the request with the given (zero-based) index.
+    pub fn new(index: usize) -> Self {
+        Self::range(index, index)
+    }
+
+    /// Create a new filter matching only the first request of each connection.
+    pub fn first() -> Self {
+        Self::new(0)
+    }
+
+    /// Create a new filter matching the requests with an index
+    /// of at least `min` and at most `max`.
+    pub fn range(min: usize, max: usize) -> Self {
+        Self { range: min..=max }
+    }
+
+    /// Create a new filter matching the requests with an index of at least `min`,
+    /// e.g. all but the first request for a `min` of `1`.
+    pub fn at_least(min: usize) -> Self {
+        Self::range(min, usize::MAX)
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for RequestIndexFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<RequestIndex>()
+            .map(|index| self.range.contains(&index.index()))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        http::{server::HttpServer, Body, Response},
+        service::service_fn,
+    };
+    use std::convert::Infallible;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn context(index: Option<usize>) -> Context<()> {
+        let mut ctx = Context::default();
+        if let Some(index) = index {
+            ctx.insert(RequestIndex::new(index));
+        }
+        ctx
+    }
+
+    #[test]
+    fn test_request_index_filter() {
+        let req = Request::builder().body(()).unwrap();
+
+        let filter = RequestIndexFilter::first();
+        assert!(filter.matches(None, &context(Some(0)), &req));
+        assert!(!filter.matches(None, &context(Some(1)), &req));
+        assert!(!filter.matches(None, &context(None), &req));
+
+        let filter = RequestIndexFilter::range(2, 3);
+        assert!(!filter.matches(None, &context(Some(1)), &req));
+        assert!(filter.matches(None, &context(Some(2)), &req));
+        assert!(filter.matches(None, &context(Some(3)), &req));
+        assert!(!filter.matches(None, &context(Some(4)), &req));
+
+        let filter = RequestIndexFilter::at_least(1);
+        assert!(!filter.matches(None, &context(Some(0)), &req));
+        assert!(filter.matches(None, &context(Some(1)), &req));
+        assert!(filter.matches(None, &context(Some(usize::MAX)), &req));
+    }
+
+    #[tokio::test]
+    async fn test_request_index_filter_keep_alive_connection() {
+        let filter = RequestIndexFilter::new(1);
+        let service = service_fn(move |ctx: Context<()>, req: Request| {
+            let matched = filter.matches(None, &ctx, &req);
+            async move {
+                let body = if matched { "match" } else { "skip!" };
+                Ok::<_, Infallible>(Response::new(Body::from(body)))
+            }
+        });
+
+        let (mut client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            HttpServer::http1()
+                .serve(Context::default(), server_io, service)
+                .await
+        });
+
+        client_io
+            .write_all(
+                b"GET /a HTTP/1.1\r\nhost: example.com\r\n\r\n\
+                  GET /b HTTP/1.1\r\nhost: example.com\r\n\r\n\
+                  GET /c HTTP/1.1\r\nhost: example.com\r\n\r\n\
+                  GET /d HTTP/1.1\r\nhost: example.com\r\nconnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let mut responses = String::new();
+        client_io.read_to_string(&mut responses).await.unwrap();
+        server.await.unwrap().unwrap();
+
+        let bodies: Vec<_> = responses
+            .split("HTTP/1.1 200 OK")
+            .skip(1)
+            .map(|response| &response[response.len() - 5..])
+            .collect();
+        assert_eq!(bodies, ["skip!", "match", "skip!", "skip!"]);
+    }
+}
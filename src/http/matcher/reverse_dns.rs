@@ -0,0 +1,146 @@
+use crate::{
+    http::{layer::dns::ReverseDnsName, Request},
+    service::{context::Extensions, Context, Matcher},
+};
+
+/// Filter based on the verified reverse DNS (PTR) name of the peer,
+/// e.g. to recognise crawlers such as `*.googlebot.com`.
+///
+/// The filter relies on the [`ReverseDnsName`] found in the [`Context`],
+/// as added by the [`ReverseDnsLayer`] once the name is forward-confirmed,
+/// such that it cannot be spoofed by the owner of the reverse DNS zone of the peer.
+/// The filter does not match in case no name could be found.
+///
+/// The pattern is either an exact name (e.g. `crawl.example.com`), or starts with a `*.`
+/// wildcard to match all subdomains of a domain (but not the domain itself).
+/// Names are compared case-insensitively.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{layer::dns::ReverseDnsName, matcher::ReverseDnsFilter, Request};
+/// use rama::service::{Context, Matcher};
+///
+/// let filter = ReverseDnsFilter::new("*.googlebot.com");
+/// let request = Request::builder().body(()).unwrap();
+///
+/// let mut ctx = Context::<()>::default();
+/// assert!(!filter.matches(None, &ctx, &request));
+///
+/// ctx.insert(ReverseDnsName::new("crawl-66-249-66-1.googlebot.com"));
+/// assert!(filter.matches(None, &ctx, &request));
+/// ```
+///
+/// [`ReverseDnsLayer`]: crate::http::layer::dns::ReverseDnsLayer
+#[derive(Debug, Clone)]
+pub struct ReverseDnsFilter {
+    name: String,
+    wildcard: bool,
+}
+
+impl ReverseDnsFilter {
+    /// Create a new filter matching the verified reverse DNS name of the peer
+    /// against the given pattern (e.g. `crawl.example.com` or `*.googlebot.com`).
+    pub fn new(pattern: impl AsRef<str>) -> Self {
+        let pattern = pattern.as_ref().trim_end_matches('.').to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => Self {
+                name: domain.to_owned(),
+                wildcard: true,
+            },
+            None => Self {
+                name: pattern,
+                wildcard: false,
+            },
+        }
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        if self.wildcard {
+            name.strip_suffix(self.name.as_str())
+                .map(|sub| sub.len() > 1 && sub.ends_with('.'))
+                .unwrap_or_default()
+        } else {
+            name == self.name
+        }
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for ReverseDnsFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<ReverseDnsName>()
+            .map(|name| self.matches_name(name.name()))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::layer::dns::{reverse::tests::MockResolver, ReverseDnsLayer};
+    use crate::http::service::web::match_service;
+    use crate::http::{Body, StatusCode};
+    use crate::service::{Layer, Service};
+    use crate::stream::SocketInfo;
+
+    #[test]
+    fn test_reverse_dns_filter_pattern() {
+        let request = Request::builder().body(()).unwrap();
+        let matches = |pattern: &str, name: Option<&str>| {
+            let mut ctx = Context::<()>::default();
+            if let Some(name) = name {
+                ctx.insert(ReverseDnsName::new(name));
+            }
+            ReverseDnsFilter::new(pattern).matches(None, &ctx, &request)
+        };
+
+        assert!(matches("*.googlebot.com", Some("crawl.googlebot.com")));
+        assert!(matches("*.googlebot.com", Some("a.b.GoogleBot.com.")));
+        assert!(matches("*.GOOGLEBOT.com.", Some("crawl.googlebot.com")));
+        assert!(!matches("*.googlebot.com", Some("googlebot.com")));
+        assert!(!matches("*.googlebot.com", Some("evilgooglebot.com")));
+        assert!(!matches("*.googlebot.com", Some("googlebot.com.evil.com")));
+        assert!(!matches("*.googlebot.com", None));
+
+        assert!(matches("crawl.example.com", Some("Crawl.Example.com")));
+        assert!(!matches("crawl.example.com", Some("a.crawl.example.com")));
+    }
+
+    #[tokio::test]
+    async fn test_reverse_dns_filter_fcrdns() {
+        let resolver = MockResolver::new(
+            &[
+                ("66.249.66.1", &["crawl-66-249-66-1.googlebot.com"]),
+                ("203.0.113.7", &["crawl-203-0-113-7.googlebot.com"]),
+            ],
+            &[
+                ("crawl-66-249-66-1.googlebot.com", &["66.249.66.1"]),
+                ("crawl-203-0-113-7.googlebot.com", &["66.249.66.7"]),
+            ],
+        );
+        let service = ReverseDnsLayer::new(resolver).layer(match_service! {
+            ReverseDnsFilter::new("*.googlebot.com") => StatusCode::ACCEPTED,
+            _ => StatusCode::OK,
+        });
+
+        let status = |peer: &'static str| {
+            let service = &service;
+            async move {
+                let mut ctx = Context::default();
+                ctx.insert(SocketInfo::new(None, peer.parse().unwrap()));
+                let request = Request::builder().body(Body::empty()).unwrap();
+                service.serve(ctx, request).await.unwrap().status()
+            }
+        };
+
+        // verification passes
+        assert_eq!(status("66.249.66.1:1234").await, StatusCode::ACCEPTED);
+        // verification fails: the PTR record is spoofed
+        assert_eq!(status("203.0.113.7:1234").await, StatusCode::OK);
+    }
+}
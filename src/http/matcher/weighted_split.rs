@@ -0,0 +1,349 @@
+use crate::{
+    http::{header, HeaderName, Request},
+    service::{
+        context::Extensions,
+        util::rng::{HasherRng, Rng, StableState},
+        Context, Matcher,
+    },
+    stream::SocketInfo,
+};
+use std::{
+    hash::BuildHasher,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+/// The number of buckets the requests are split into,
+/// such that percentages can be configured with a precision of `0.01`.
+const BUCKETS: u64 = 10_000;
+
+#[derive(Debug, Clone)]
+/// Filter matching a configurable percentage of requests,
+/// e.g. to route them to the B branch of an A/B rollout.
+///
+/// Requests are split either randomly (see [`WeightedSplitFilter::random`]), or
+/// deterministically by a key of the request, such as a header, cookie or peer IP,
+/// in which case all requests with the same key consistently match (or don't).
+/// Requests without a key never match in the deterministic modes.
+///
+/// Deterministic splits do not depend on the process or the filter instance,
+/// such that a client lands on the same branch across restarts and replicas.
+/// Increasing the percentage only adds keys to the matching branch,
+/// which makes it suitable for gradual rollouts. Use [`WeightedSplitFilter::salt`]
+/// to give each experiment its own salt, such that experiments split by the same key
+/// are independent, instead of matching the same keys.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{matcher::WeightedSplitFilter, Request, StatusCode};
+/// use rama::http::service::web::match_service;
+/// use rama::service::{Context, Service};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let service = match_service! {
+///     WeightedSplitFilter::by_cookie(100.0, "session") => StatusCode::ACCEPTED,
+///     _ => StatusCode::OK,
+/// };
+///
+/// let request = Request::builder().header("cookie", "session=abc").body(Default::default()).unwrap();
+/// let response = service.serve(Context::default(), request).await.unwrap();
+/// assert_eq!(response.status(), StatusCode::ACCEPTED);
+///
+/// let request = Request::builder().body(Default::default()).unwrap();
+/// let response = service.serve(Context::default(), request).await.unwrap();
+/// assert_eq!(response.status(), StatusCode::OK);
+/// # }
+/// ```
+pub struct WeightedSplitFilter {
+    threshold: u64,
+    kind: SplitKind,
+    salt: StableState,
+}
+
+#[derive(Debug, Clone)]
+enum SplitKind {
+    Random(Arc<Mutex<HasherRng>>),
+    Header(HeaderName),
+    Cookie(String),
+    PeerIp,
+}
+
+impl WeightedSplitFilter {
+    /// Create a new filter randomly matching the given percentage of requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the percentage is not within `0.0..=100.0`.
+    pub fn random(percentage: f64) -> Self {
+        Self::with_kind(
+            percentage,
+            SplitKind::Random(Arc::new(Mutex::new(HasherRng::new()))),
+        )
+    }
+
+    /// Create a new filter matching the given percentage of requests,
+    /// keyed by the value of the given header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the percentage is not within `0.0..=100.0`.
+    pub fn by_header(percentage: f64, name: HeaderName) -> Self {
+        Self::with_kind(percentage, SplitKind::Header(name))
+    }
+
+    /// Create a new filter matching the given percentage of requests,
+    /// keyed by the value of the cookie with the given name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the percentage is not within `0.0..=100.0`.
+    pub fn by_cookie(percentage: f64, name: impl Into<String>) -> Self {
+        Self::with_kind(percentage, SplitKind::Cookie(name.into()))
+    }
+
+    /// Create a new filter matching the given percentage of requests,
+    /// keyed by the IP address of the peer, as found in the [`SocketInfo`] of the [`Context`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the percentage is not within `0.0..=100.0`.
+    ///
+    /// [`SocketInfo`]: crate::stream::SocketInfo
+    pub fn by_peer_ip(percentage: f64) -> Self {
+        Self::with_kind(percentage, SplitKind::PeerIp)
+    }
+
+    fn with_kind(percentage: f64, kind: SplitKind) -> Self {
+        assert!(
+            (0.0..=100.0).contains(&percentage),
+            "weighted split: percentage must be within 0.0..=100.0"
+        );
+        Self {
+            threshold: (percentage * (BUCKETS / 100) as f64).round() as u64,
+            kind,
+            salt: StableState::default(),
+        }
+    }
+
+    /// Salt the keys of the requests with the given salt (e.g. the name of the experiment),
+    /// such that the requests with the same key can land on a different branch
+    /// than for filters with another salt.
+    ///
+    /// Has no effect for filters splitting the requests randomly.
+    pub fn salt(mut self, salt: impl AsRef<[u8]>) -> Self {
+        self.salt = StableState::new(StableState::default().hash_one(salt.as_ref()));
+        self
+    }
+
+    /// Compute the bucket of the given request, if it has a key.
+    fn bucket<State, Body>(&self, ctx: &Context<State>, req: &Request<Body>) -> Option<u64> {
+        let hash = match &self.kind {
+            SplitKind::Random(rng) => rng.lock().unwrap().next_u64(),
+            SplitKind::Header(name) => self.salt.hash_one(req.headers().get(name)?.as_bytes()),
+            SplitKind::Cookie(name) => self.salt.hash_one(cookie(req, name)?),
+            SplitKind::PeerIp => match ctx.get::<SocketInfo>()?.peer_addr().ip() {
+                IpAddr::V4(ip) => self.salt.hash_one(ip.octets()),
+                IpAddr::V6(ip) => self.salt.hash_one(ip.octets()),
+            },
+        };
+        Some(hash % BUCKETS)
+    }
+}
+
+impl<State, Body> Matcher<State, Request<Body>> for WeightedSplitFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        req: &Request<Body>,
+    ) -> bool {
+        self.bucket(ctx, req)
+            .map(|bucket| bucket < self.threshold)
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the value of the first cookie with the given name.
+fn cookie<'a, Body>(req: &'a Request<Body>, name: &str) -> Option<&'a [u8]> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (key.trim() == name).then(|| value.trim().as_bytes())
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REQUESTS: usize = 10_000;
+
+    fn request(header: Option<(&str, &str)>) -> Request<()> {
+        let mut builder = Request::builder();
+        if let Some((name, value)) = header {
+            builder = builder.header(name, value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn context(peer: Option<&str>) -> Context<()> {
+        let mut ctx = Context::default();
+        if let Some(peer) = peer {
+            ctx.insert(SocketInfo::new(None, peer.parse().unwrap()));
+        }
+        ctx
+    }
+
+    fn assert_ratio(matched: usize, percentage: f64) {
+        let ratio = matched as f64 / REQUESTS as f64 * 100.0;
+        assert!(
+            (ratio - percentage).abs() < 2.0,
+            "matched {ratio}% instead of {percentage}%"
+        );
+    }
+
+    #[test]
+    fn test_weighted_split_filter_random_ratio() {
+        let ctx = context(None);
+        for percentage in [0.0, 10.0, 25.0, 50.0, 90.0, 100.0] {
+            let filter = WeightedSplitFilter::random(percentage);
+            let matched = (0..REQUESTS)
+                .filter(|_| filter.matches(None, &ctx, &request(None)))
+                .count();
+            assert_ratio(matched, percentage);
+        }
+    }
+
+    #[test]
+    fn test_weighted_split_filter_header_ratio_and_stability() {
+        let ctx = context(None);
+        let filter = WeightedSplitFilter::by_header(20.0, HeaderName::from_static("x-client-id"));
+        let other = filter.clone();
+
+        let mut matched = 0;
+        for i in 0..REQUESTS {
+            let id = format!("client-{i}");
+            let req = request(Some(("x-client-id", &id)));
+            let result = filter.matches(None, &ctx, &req);
+            if result {
+                matched += 1;
+            }
+            // stable for the same key, across evaluations and instances
+            assert_eq!(filter.matches(None, &ctx, &req), result);
+            assert_eq!(other.matches(None, &ctx, &req), result);
+            assert_eq!(
+                WeightedSplitFilter::by_header(20.0, HeaderName::from_static("x-client-id"))
+                    .matches(None, &ctx, &req),
+                result
+            );
+        }
+        assert_ratio(matched, 20.0);
+
+        // no key: no match
+        assert!(!filter.matches(None, &ctx, &request(None)));
+    }
+
+    #[test]
+    fn test_weighted_split_filter_rollout_is_monotonic() {
+        let ctx = context(None);
+        let small = WeightedSplitFilter::by_header(10.0, HeaderName::from_static("x-client-id"));
+        let large = WeightedSplitFilter::by_header(30.0, HeaderName::from_static("x-client-id"));
+        for i in 0..REQUESTS {
+            let id = format!("client-{i}");
+            let req = request(Some(("x-client-id", &id)));
+            if small.matches(None, &ctx, &req) {
+                assert!(large.matches(None, &ctx, &req));
+            }
+        }
+    }
+
+    #[test]
+    fn test_weighted_split_filter_cookie() {
+        let ctx = context(None);
+        let filter = WeightedSplitFilter::by_cookie(50.0, "session");
+
+        let mut matched = 0;
+        for i in 0..REQUESTS {
+            let cookie = format!("theme=dark; session={i}; lang=en");
+            let result = filter.matches(None, &ctx, &request(Some(("cookie", &cookie))));
+            if result {
+                matched += 1;
+            }
+            let cookie = format!("session={i}");
+            assert_eq!(
+                filter.matches(None, &ctx, &request(Some(("cookie", &cookie)))),
+                result
+            );
+        }
+        assert_ratio(matched, 50.0);
+
+        assert!(!filter.matches(None, &ctx, &request(Some(("cookie", "sessions=1")))));
+        assert!(!filter.matches(None, &ctx, &request(None)));
+        assert!(WeightedSplitFilter::by_cookie(100.0, "session").matches(
+            None,
+            &ctx,
+            &request(Some(("cookie", "session=1")))
+        ));
+    }
+
+    #[test]
+    fn test_weighted_split_filter_peer_ip() {
+        let filter = WeightedSplitFilter::by_peer_ip(30.0);
+
+        let mut matched = 0;
+        for i in 0..REQUESTS {
+            let ip = format!("10.0.{}.{}", i / 256, i % 256);
+            let result =
+                filter.matches(None, &context(Some(&format!("{ip}:1234"))), &request(None));
+            if result {
+                matched += 1;
+            }
+            // the port of the peer does not matter
+            assert_eq!(
+                filter.matches(None, &context(Some(&format!("{ip}:4321"))), &request(None)),
+                result
+            );
+        }
+        assert_ratio(matched, 30.0);
+
+        assert!(!filter.matches(None, &context(None), &request(None)));
+    }
+
+    #[test]
+    fn test_weighted_split_filter_salt() {
+        let ctx = context(None);
+        let header = HeaderName::from_static("x-client-id");
+        let filter = WeightedSplitFilter::by_header(50.0, header.clone()).salt("experiment-a");
+        let same = WeightedSplitFilter::by_header(50.0, header.clone()).salt("experiment-a");
+        let other = WeightedSplitFilter::by_header(50.0, header).salt("experiment-b");
+
+        let (mut matched, mut both) = (0, 0);
+        for i in 0..REQUESTS {
+            let id = format!("client-{i}");
+            let req = request(Some(("x-client-id", &id)));
+            let result = filter.matches(None, &ctx, &req);
+            assert_eq!(same.matches(None, &ctx, &req), result);
+            if result {
+                matched += 1;
+                if other.matches(None, &ctx, &req) {
+                    both += 1;
+                }
+            }
+        }
+        assert_ratio(matched, 50.0);
+        // independent experiments: half of the matching keys also match the other one
+        assert_ratio(both, 25.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_weighted_split_filter_invalid_percentage() {
+        WeightedSplitFilter::random(100.1);
+    }
+}
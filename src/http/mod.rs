@@ -1,7 +1,7 @@
 //! Rama http modules.
 
 pub(crate) mod body;
-pub use body::{Body, BodyDataStream};
+pub use body::{multipart, Body, BodyDataStream};
 
 pub mod utils;
 
@@ -23,6 +23,9 @@ pub use json::Json;
 mod redirect;
 pub use redirect::Redirect;
 
+mod trailers;
+pub use trailers::WithTrailers;
+
 /// Type alias for [`http::Response`] whose body type defaults to [`Body`], the most common body
 /// type used with rama.
 pub type Response<T = Body> = http::Response<T>;
@@ -0,0 +1,195 @@
+use super::IntoResponse;
+use crate::http::{header, HeaderMap, HeaderName, HeaderValue, Response};
+use std::future::{Future, Ready};
+
+/// Attach trailers to the body of a response,
+/// sent once all data of the body has been sent.
+///
+/// The names of the trailers are declared in the `Trailer` header of the response,
+/// and its `Content-Length` header is removed, such that an HTTP/1.1 server
+/// sends the body chunked with the trailers appended. Note that HTTP/1.1 clients
+/// only receive trailers in case they announced support for them using a
+/// `TE: trailers` request header. HTTP/2 clients always receive them.
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{response::{IntoResponse, WithTrailers}, HeaderMap, HeaderName, HeaderValue};
+///
+/// async fn handler() -> impl IntoResponse {
+///     WithTrailers::future(
+///         "hello",
+///         [HeaderName::from_static("grpc-status")],
+///         async {
+///             let mut trailers = HeaderMap::new();
+///             trailers.insert("grpc-status", HeaderValue::from_static("0"));
+///             Some(trailers)
+///         },
+///     )
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithTrailers<R, F = Ready<Option<HeaderMap>>> {
+    response: R,
+    names: Vec<HeaderName>,
+    trailers: F,
+}
+
+impl<R> WithTrailers<R> {
+    /// Attach the given trailers to the body of the given response,
+    /// declaring all their names in the `Trailer` header of the response.
+    pub fn new(response: R, trailers: HeaderMap) -> Self {
+        let names = trailers.keys().cloned().collect();
+        Self {
+            response,
+            names,
+            trailers: std::future::ready(Some(trailers)),
+        }
+    }
+}
+
+impl<R, F> WithTrailers<R, F> {
+    /// Attach the trailers produced by the given future to the body of the given response,
+    /// declaring the given names in the `Trailer` header of the response.
+    ///
+    /// The future is only polled once all data of the body has been sent,
+    /// see [`Body::with_trailers_future`] for more information.
+    ///
+    /// [`Body::with_trailers_future`]: crate::http::Body::with_trailers_future
+    pub fn future(response: R, names: impl IntoIterator<Item = HeaderName>, trailers: F) -> Self {
+        Self {
+            response,
+            names: names.into_iter().collect(),
+            trailers,
+        }
+    }
+}
+
+impl<R, F> IntoResponse for WithTrailers<R, F>
+where
+    R: IntoResponse,
+    F: Future<Output = Option<HeaderMap>> + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let mut res = self.response.into_response();
+        res.headers_mut().remove(header::CONTENT_LENGTH);
+        if !self.names.is_empty() {
+            let names = self
+                .names
+                .iter()
+                .map(HeaderName::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if let Ok(value) = HeaderValue::from_str(&names) {
+                res.headers_mut().insert(header::TRAILER, value);
+            }
+        }
+        let trailers = self.trailers;
+        res.map(|body| body.with_trailers_future(trailers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::client::HttpClient;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::http::server::HttpServer;
+    use crate::http::{Body, Request, Version};
+    use crate::rt::Executor;
+    use crate::service::{service_fn, Context, Service};
+    use crate::test_helpers::net::read_http_head;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    async fn handler(_req: Request) -> Result<Response, Infallible> {
+        let body = Body::from_stream(futures_util::stream::iter([
+            Ok::<_, Infallible>("hello, "),
+            Ok("world"),
+        ]));
+        Ok(
+            WithTrailers::future(body, [HeaderName::from_static("grpc-status")], async {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                Some(trailers)
+            })
+            .into_response(),
+        )
+    }
+
+    async fn spawn_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    HttpServer::auto(Executor::new())
+                        .serve(Context::default(), stream, service_fn(handler))
+                        .await
+                        .unwrap();
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_with_trailers_headers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        trailers.insert("grpc-message", HeaderValue::from_static("ok"));
+        let res = WithTrailers::new("hello", trailers).into_response();
+
+        assert!(res.headers().get(header::CONTENT_LENGTH).is_none());
+        assert_eq!(
+            res.headers().get(header::TRAILER).unwrap(),
+            "grpc-status, grpc-message"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trailers_http2() {
+        let addr = spawn_server().await;
+
+        let request = Request::builder()
+            .version(Version::HTTP_2)
+            .uri(format!("http://{}/", addr))
+            .body(Body::empty())
+            .unwrap();
+        let response = HttpClient::new()
+            .serve(Context::default(), request)
+            .await
+            .unwrap();
+        assert_eq!(response.version(), Version::HTTP_2);
+
+        let collected = response.into_body().collect().await.unwrap();
+        let trailers = collected.trailers().cloned().unwrap();
+        assert_eq!(&collected.to_bytes()[..], b"hello, world");
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+    }
+
+    #[tokio::test]
+    async fn test_trailers_http1_wire_format() {
+        let addr = spawn_server().await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nhost: example.com\r\nte: trailers\r\nconnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let head = String::from_utf8(read_http_head(&mut stream).await).unwrap();
+        assert!(head.contains("transfer-encoding: chunked\r\n"), "{head}");
+        assert!(head.contains("trailer: grpc-status\r\n"), "{head}");
+
+        // the trailers are sent after the (last) body chunk
+        let mut body = String::new();
+        stream.read_to_string(&mut body).await.unwrap();
+        assert!(body.ends_with("0\r\ngrpc-status: 0\r\n\r\n"), "{body}");
+        assert!(body.contains("world"));
+    }
+}
@@ -1,3 +1,4 @@
+use super::limits::{shutdown_signal, ConnectionLimiter, ConnectionLimits, LimitedService};
 use super::HttpServeResult;
 use crate::http::{IntoResponse, Request};
 use crate::rt::Executor;
@@ -22,6 +23,7 @@ pub trait HyperConnServer: Send + Sync + private::Sealed + 'static {
         ctx: Context<State>,
         io: IO,
         service: S,
+        limits: ConnectionLimits,
     ) -> impl std::future::Future<Output = HttpServeResult> + Send + '_
     where
         IO: Stream,
@@ -37,6 +39,7 @@ impl HyperConnServer for Http1Builder {
         ctx: Context<State>,
         io: IO,
         service: S,
+        limits: ConnectionLimits,
     ) -> HttpServeResult
     where
         IO: Stream,
@@ -46,17 +49,17 @@ impl HyperConnServer for Http1Builder {
     {
         let stream = TokioIo::new(Box::pin(io));
         let guard = ctx.guard().cloned();
-        let service = HyperService::new(ctx, service);
+        let limiter = ConnectionLimiter::new(limits);
+        let service = HyperService::new(ctx, LimitedService::new(service, limiter.clone()));
 
         let mut conn = pin!(self.serve_connection(stream, service).with_upgrades());
 
-        if let Some(guard) = guard {
-            let mut cancelled_fut = pin!(guard.cancelled().fuse());
+        if guard.is_some() || limiter.is_some() {
+            let mut shutdown_fut = pin!(shutdown_signal(guard, limiter).fuse());
 
             loop {
                 select! {
-                    _ = cancelled_fut.as_mut() => {
-                        tracing::trace!("signal received: initiate graceful shutdown");
+                    _ = shutdown_fut.as_mut() => {
                         conn.as_mut().graceful_shutdown();
                     }
                     result = conn.as_mut() => {
@@ -78,6 +81,7 @@ impl HyperConnServer for Http2Builder<Executor> {
         ctx: Context<State>,
         io: IO,
         service: S,
+        limits: ConnectionLimits,
     ) -> HttpServeResult
     where
         IO: Stream,
@@ -87,17 +91,17 @@ impl HyperConnServer for Http2Builder<Executor> {
     {
         let stream = TokioIo::new(Box::pin(io));
         let guard = ctx.guard().cloned();
-        let service = HyperService::new(ctx, service);
+        let limiter = ConnectionLimiter::new(limits);
+        let service = HyperService::new(ctx, LimitedService::new(service, limiter.clone()));
 
         let mut conn = pin!(self.serve_connection(stream, service));
 
-        if let Some(guard) = guard {
-            let mut cancelled_fut = pin!(guard.cancelled().fuse());
+        if guard.is_some() || limiter.is_some() {
+            let mut shutdown_fut = pin!(shutdown_signal(guard, limiter).fuse());
 
             loop {
                 select! {
-                    _ = cancelled_fut.as_mut() => {
-                        tracing::trace!("signal received: initiate graceful shutdown");
+                    _ = shutdown_fut.as_mut() => {
                         conn.as_mut().graceful_shutdown();
                     }
                     result = conn.as_mut() => {
@@ -119,6 +123,7 @@ impl HyperConnServer for AutoBuilder<Executor> {
         ctx: Context<State>,
         io: IO,
         service: S,
+        limits: ConnectionLimits,
     ) -> HttpServeResult
     where
         IO: Stream,
@@ -128,17 +133,18 @@ impl HyperConnServer for AutoBuilder<Executor> {
     {
         let stream = TokioIo::new(Box::pin(io));
         let guard = ctx.guard().cloned();
-        let service = HyperService::new(ctx, service);
+        let limiter = ConnectionLimiter::new(limits);
+        let service = HyperService::new(ctx, LimitedService::new(service, limiter.clone()));
 
         let mut conn = pin!(self.serve_connection_with_upgrades(stream, service));
 
-        if let Some(guard) = guard {
-            let mut cancelled_fut = pin!(guard.cancelled().fuse());
+        if guard.is_some() || limiter.is_some() {
+            let mut shutdown_fut = pin!(shutdown_signal(guard, limiter).fuse());
 
             loop {
                 select! {
-                    _ = cancelled_fut.as_mut() => {
-                        tracing::trace!("signal received: nop: graceful shutdown not supported for auto builder");
+                    _ = shutdown_fut.as_mut() => {
+                        // nop: graceful shutdown not supported for auto builder
                         conn.as_mut().graceful_shutdown();
                     }
                     result = conn.as_mut() => {
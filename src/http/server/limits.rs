@@ -0,0 +1,166 @@
+use super::RequestIndex;
+use crate::graceful::ShutdownEscalation;
+use crate::http::{header, HeaderValue, IntoResponse, Request, Response, Version};
+use crate::service::{Context, Service};
+use futures::FutureExt;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tokio_graceful::ShutdownGuard;
+
+/// The limits applied to each connection served by the [`HttpServer`],
+/// after which the connection is gracefully closed.
+///
+/// [`HttpServer`]: super::HttpServer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionLimits {
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) max_requests: Option<usize>,
+}
+
+/// The state of a single connection, tracking whether its limits are reached.
+#[derive(Debug)]
+pub(crate) struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    started_at: Instant,
+    requests: AtomicUsize,
+    closing: Notify,
+}
+
+impl ConnectionLimiter {
+    /// Create a new [`ConnectionLimiter`] for a new connection,
+    /// or `None` in case no limits are configured.
+    pub(crate) fn new(limits: ConnectionLimits) -> Option<Arc<Self>> {
+        if limits.max_age.is_none() && limits.max_requests.is_none() {
+            return None;
+        }
+        Some(Arc::new(Self {
+            limits,
+            started_at: Instant::now(),
+            requests: AtomicUsize::new(0),
+            closing: Notify::new(),
+        }))
+    }
+
+    /// Register a new request, returning `true` in case it is the last request
+    /// that is to be served on the connection.
+    fn register_request(&self) -> bool {
+        let requests = self.requests.fetch_add(1, Ordering::AcqRel) + 1;
+        let last = self
+            .limits
+            .max_requests
+            .map(|max| requests >= max)
+            .unwrap_or_default()
+            || self
+                .limits
+                .max_age
+                .map(|max_age| self.started_at.elapsed() >= max_age)
+                .unwrap_or_default();
+        if last {
+            self.closing.notify_one();
+        }
+        last
+    }
+
+    /// Resolves once the connection has reached one of its limits.
+    async fn closed(&self) {
+        match self.limits.max_age {
+            Some(max_age) => {
+                tokio::select! {
+                    _ = self.closing.notified() => (),
+                    _ = tokio::time::sleep_until(self.started_at + max_age) => (),
+                }
+            }
+            None => self.closing.notified().await,
+        }
+    }
+}
+
+/// Resolves once the connection is to be gracefully shut down,
+/// either because the (optional) guard got cancelled or because the limits
+/// of the (optional) limiter are reached.
+pub(crate) async fn shutdown_signal(
+    guard: Option<ShutdownGuard>,
+    limiter: Option<Arc<ConnectionLimiter>>,
+) {
+    let cancelled = async {
+        match guard {
+            Some(guard) => guard.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+    let limited = async {
+        match limiter {
+            Some(limiter) => limiter.closed().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        _ = cancelled.fuse() => tracing::trace!("signal received: initiate graceful shutdown"),
+        _ = limited.fuse() => tracing::trace!("connection limit reached: initiate graceful shutdown"),
+    }
+}
+
+/// A [`Service`] registering each request served on a connection with its [`ConnectionLimiter`],
+/// adding a `Connection: close` header to the last http/1 response, which is also the case
+/// for responses sent once the soft deadline of a [`ShutdownEscalation`] is reached.
+///
+/// The [`RequestIndex`] of each request is inserted into its [`Context`].
+pub(crate) struct LimitedService<S> {
+    inner: S,
+    limiter: Option<Arc<ConnectionLimiter>>,
+    requests: AtomicUsize,
+}
+
+impl<S> LimitedService<S> {
+    pub(crate) fn new(inner: S, limiter: Option<Arc<ConnectionLimiter>>) -> Self {
+        Self {
+            inner,
+            limiter,
+            requests: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<State, S, R> Service<State, Request> for LimitedService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request, Response = R, Error = Infallible>,
+    R: IntoResponse + Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let index = self.requests.fetch_add(1, Ordering::AcqRel);
+        ctx.insert(RequestIndex::new(index));
+        let escalation = ctx.get::<ShutdownEscalation>().cloned();
+
+        let last = self
+            .limiter
+            .as_ref()
+            .map(|limiter| limiter.register_request())
+            .unwrap_or_default();
+        let version = req.version();
+
+        let mut response = self.inner.serve(ctx, req).await?.into_response();
+        // close the connection once the soft deadline of the graceful shutdown is reached
+        let last = last
+            || escalation
+                .map(|escalation| escalation.is_soft_deadline_reached())
+                .unwrap_or_default();
+        if last && version <= Version::HTTP_11 {
+            response
+                .headers_mut()
+                .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        }
+        Ok(response)
+    }
+}
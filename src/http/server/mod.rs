@@ -7,4 +7,10 @@ pub type HttpServeResult = Result<(), crate::error::Error>;
 pub mod service;
 pub use service::HttpServer;
 
+mod request_index;
+#[doc(inline)]
+pub use request_index::RequestIndex;
+
 mod hyper_conn;
+mod limits;
+mod upgrade;
@@ -0,0 +1,32 @@
+/// The (zero-based) ordinal of a request within the connection it was received on,
+/// inserted into the [`Context`] of each request served by the [`HttpServer`].
+///
+/// For http/1 connections this is the position of the request in the keep-alive
+/// sequence of the connection, the first request having index `0`.
+/// For http/2 connections this is the order in which the (concurrent)
+/// requests were received on the connection.
+///
+/// Use the [`RequestIndexFilter`] to match on it.
+///
+/// [`Context`]: crate::service::Context
+/// [`HttpServer`]: super::HttpServer
+/// [`RequestIndexFilter`]: crate::http::matcher::RequestIndexFilter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestIndex(usize);
+
+impl RequestIndex {
+    /// Create a new [`RequestIndex`] for the given (zero-based) ordinal.
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    /// Get the (zero-based) ordinal of the request within its connection.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    /// Returns `true` in case this is the first request of its connection.
+    pub fn is_first(&self) -> bool {
+        self.0 == 0
+    }
+}
@@ -1,7 +1,10 @@
 //! Rama HTTP server module.
 
 use super::hyper_conn::HyperConnServer;
+use super::limits::ConnectionLimits;
+use super::upgrade::OnUpgradeService;
 use super::HttpServeResult;
+use crate::http::layer::upgrade::Upgraded;
 use crate::http::{IntoResponse, Request};
 use crate::rt::Executor;
 use crate::service::{Context, Service};
@@ -19,23 +22,38 @@ use std::time::Duration;
 use tokio::net::ToSocketAddrs;
 use tokio_graceful::ShutdownGuard;
 
+pub use super::upgrade::NoUpgrade;
+
 /// A builder for configuring and listening over HTTP using a [`Service`].
 ///
 /// Supported Protocols: HTTP/1, H2, Auto (HTTP/1 + H2)
 ///
+/// For HTTP/1.1 requests carrying an `Expect: 100-continue` header,
+/// the interim `100 Continue` response is emitted as soon as the [`Service`]
+/// starts reading the request body. A [`Service`] that responds without reading the body
+/// therefore rejects the request without the client sending it,
+/// see [`ExpectContinueFilter`] to match such requests,
+/// and the [`ContinueBody`] extractor to explicitly accept or decline the body.
+///
 /// [`Service`]: crate::service::Service
+/// [`ExpectContinueFilter`]: crate::http::matcher::ExpectContinueFilter
+/// [`ContinueBody`]: crate::http::service::web::extract::ContinueBody
 #[derive(Debug)]
-pub struct HttpServer<B> {
+pub struct HttpServer<B, H = NoUpgrade> {
     builder: B,
+    limits: ConnectionLimits,
+    on_upgrade: Option<Arc<H>>,
 }
 
-impl<B> Clone for HttpServer<B>
+impl<B, H> Clone for HttpServer<B, H>
 where
     B: Clone,
 {
     fn clone(&self) -> Self {
         Self {
             builder: self.builder.clone(),
+            limits: self.limits,
+            on_upgrade: self.on_upgrade.clone(),
         }
     }
 }
@@ -45,11 +63,13 @@ impl HttpServer<Http1ConnBuilder> {
     pub fn http1() -> Self {
         Self {
             builder: Http1ConnBuilder::new(),
+            limits: ConnectionLimits::default(),
+            on_upgrade: None,
         }
     }
 }
 
-impl HttpServer<Http1ConnBuilder> {
+impl<H> HttpServer<Http1ConnBuilder, H> {
     /// Http1 configuration.
     pub fn http1_mut(&mut self) -> Http1Config<'_> {
         Http1Config {
@@ -178,11 +198,13 @@ impl HttpServer<H2ConnBuilder<Executor>> {
     pub fn h2(exec: Executor) -> Self {
         Self {
             builder: H2ConnBuilder::new(exec),
+            limits: ConnectionLimits::default(),
+            on_upgrade: None,
         }
     }
 }
 
-impl<E> HttpServer<H2ConnBuilder<E>> {
+impl<E, H> HttpServer<H2ConnBuilder<E>, H> {
     /// H2 configuration.
     pub fn h2_mut(&mut self) -> H2Config<'_, E> {
         H2Config {
@@ -198,6 +220,23 @@ pub struct H2Config<'a, E> {
 }
 
 impl<'a, E> H2Config<'a, E> {
+    /// Sets the maximum number of streams reset by the client before being
+    /// accepted by the server, after which the connection is closed
+    /// with a `GOAWAY` frame (`ENHANCE_YOUR_CALM`).
+    ///
+    /// This protects against floods of streams which are opened and
+    /// immediately reset again by the client, such as the
+    /// [Rapid Reset][cve] attack.
+    ///
+    /// Passing `None` will do nothing. If not set, the default of the `h2` crate
+    /// is used, which is currently 20.
+    ///
+    /// [cve]: https://nvd.nist.gov/vuln/detail/CVE-2023-44487
+    pub fn max_pending_accept_reset_streams(&mut self, max: impl Into<Option<usize>>) -> &mut Self {
+        self.inner.max_pending_accept_reset_streams(max);
+        self
+    }
+
     /// Sets the [`SETTINGS_INITIAL_WINDOW_SIZE`][spec] option for HTTP2
     /// stream-level flow control.
     ///
@@ -244,7 +283,7 @@ impl<'a, E> H2Config<'a, E> {
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
-    /// Default is no limit (`std::u32::MAX`). Passing `None` will do nothing.
+    /// Default is 200 streams. Passing `None` will do nothing.
     ///
     /// [spec]: https://http2.github.io/http2-spec/#SETTINGS_MAX_CONCURRENT_STREAMS
     pub fn max_concurrent_streams(&mut self, max: impl Into<Option<u32>>) -> &mut Self {
@@ -323,11 +362,13 @@ impl HttpServer<AutoConnBuilder<Executor>> {
     pub fn auto(exec: Executor) -> Self {
         Self {
             builder: AutoConnBuilder::new(exec),
+            limits: ConnectionLimits::default(),
+            on_upgrade: None,
         }
     }
 }
 
-impl<E> HttpServer<AutoConnBuilder<E>> {
+impl<E, H> HttpServer<AutoConnBuilder<E>, H> {
     /// Http1 configuration.
     pub fn http1_mut(&mut self) -> AutoHttp1Config<'_, E> {
         AutoHttp1Config {
@@ -521,7 +562,7 @@ impl<'a, E> AutoH2Config<'a, E> {
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
-    /// Default is no limit (`std::u32::MAX`). Passing `None` will do nothing.
+    /// Default is 200 streams. Passing `None` will do nothing.
     ///
     /// [spec]: https://http2.github.io/http2-spec/#SETTINGS_MAX_CONCURRENT_STREAMS
     pub fn max_concurrent_streams(&mut self, max: impl Into<Option<u32>>) -> &mut Self {
@@ -595,18 +636,75 @@ impl<'a, E> AutoH2Config<'a, E> {
     }
 }
 
-impl<B> HttpServer<B>
+impl<B, H> HttpServer<B, H> {
+    /// Gracefully close each connection once it has been open for the given duration.
+    ///
+    /// For http/1 connections the `Connection: close` header is added to the response
+    /// of the first request received after the age has been reached, while idle
+    /// connections are closed as soon as the age is reached.
+    /// Http/2 connections are closed by sending a `GOAWAY` frame.
+    ///
+    /// Forcing clients to reconnect periodically helps to rebalance connections
+    /// across multiple servers (e.g. behind a load balancer). By default connections
+    /// can stay open for as long as the client keeps them alive.
+    pub fn max_connection_age(&mut self, age: Duration) -> &mut Self {
+        self.limits.max_age = Some(age);
+        self
+    }
+
+    /// Gracefully close each connection once it has received the given number of requests.
+    ///
+    /// For http/1 connections the `Connection: close` header is added to the response
+    /// of the last request. Http/2 connections are closed by sending a `GOAWAY` frame,
+    /// which does not affect the requests (streams) which are already in flight.
+    ///
+    /// By default there is no limit on the number of requests per connection.
+    pub fn max_requests_per_connection(&mut self, max: usize) -> &mut Self {
+        self.limits.max_requests = Some(max);
+        self
+    }
+
+    /// Hand over the http/1.1 connections upgraded to another protocol to the given
+    /// handler [`Service`], replacing the handler set previously (if any).
+    ///
+    /// A connection is upgraded when the [`Service`] responds to a request carrying
+    /// an `Upgrade` header with `101 Switching Protocols`, after which no more HTTP
+    /// is processed on it. Once the response is written, the handler is spawned with the
+    /// [`Context`] of the request, as it was before the request was served by the [`Service`],
+    /// and the [`Upgraded`] connection, which also yields the bytes the client sent right
+    /// after its request, in case these were already buffered by the server.
+    /// The head ([`Parts`]) of the request is available as an extension of the [`Context`].
+    ///
+    /// The handler takes over the upgrades of all requests, such that it cannot be combined
+    /// with an [`UpgradeLayer`] for the same connections. As it has to serve the same state
+    /// as the [`Service`] of the server, a handler for another state does not compile.
+    ///
+    /// [`Service`]: crate::service::Service
+    /// [`Parts`]: crate::http::dep::http::request::Parts
+    /// [`UpgradeLayer`]: crate::http::layer::upgrade::UpgradeLayer
+    pub fn on_upgrade<U>(self, handler: U) -> HttpServer<B, U> {
+        HttpServer {
+            builder: self.builder,
+            limits: self.limits,
+            on_upgrade: Some(Arc::new(handler)),
+        }
+    }
+}
+
+impl<B, H> HttpServer<B, H>
 where
     B: HyperConnServer,
 {
     /// Turn this `HttpServer` into a [`Service`] that can be used to serve
     /// IO Byte streams (e.g. a TCP Stream) as HTTP.
-    pub fn service<State, S, Response>(self, service: S) -> HttpService<B, S, State>
+    pub fn service<State, S, Response>(self, service: S) -> HttpService<B, S, State, H>
     where
+        State: Send + Sync + 'static,
         S: Service<State, Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<State, Upgraded, Response = (), Error = Infallible>,
     {
-        HttpService::new(self.builder, service)
+        HttpService::new(self.builder, service, self.limits, self.on_upgrade)
     }
 
     /// Serve a single IO Byte Stream (e.g. a TCP Stream) as HTTP.
@@ -620,10 +718,12 @@ where
         State: Send + Sync + 'static,
         S: Service<State, Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<State, Upgraded, Response = (), Error = Infallible>,
         IO: Stream,
     {
+        let service = OnUpgradeService::new(service, self.on_upgrade.clone());
         self.builder
-            .hyper_serve_connection(ctx, stream, service)
+            .hyper_serve_connection(ctx, stream, service, self.limits)
             .await
     }
 
@@ -634,6 +734,7 @@ where
     where
         S: Service<(), Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<(), Upgraded, Response = (), Error = Infallible>,
         A: ToSocketAddrs,
     {
         TcpListener::bind(addr)
@@ -658,6 +759,7 @@ where
     where
         S: Service<(), Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<(), Upgraded, Response = (), Error = Infallible>,
         A: ToSocketAddrs,
     {
         TcpListener::bind(addr)
@@ -683,6 +785,7 @@ where
         State: Send + Sync + 'static,
         S: Service<State, Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<State, Upgraded, Response = (), Error = Infallible>,
         A: ToSocketAddrs,
     {
         TcpListener::build_with_state(state)
@@ -710,6 +813,7 @@ where
         State: Send + Sync + 'static,
         S: Service<State, Request, Response = Response, Error = Infallible>,
         Response: IntoResponse + Send + 'static,
+        H: Service<State, Upgraded, Response = (), Error = Infallible>,
         A: ToSocketAddrs,
     {
         TcpListener::build_with_state(state)
@@ -722,44 +826,51 @@ where
 }
 
 /// A [`Service`] that can be used to serve IO Byte streams (e.g. a TCP Stream) as HTTP.
-pub struct HttpService<B, S, State> {
+pub struct HttpService<B, S, State, H = NoUpgrade> {
     builder: Arc<B>,
     service: Arc<S>,
+    limits: ConnectionLimits,
+    on_upgrade: Option<Arc<H>>,
     _phantom: std::marker::PhantomData<State>,
 }
 
-impl<B, S, State> std::fmt::Debug for HttpService<B, S, State> {
+impl<B, S, State, H> std::fmt::Debug for HttpService<B, S, State, H> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HttpService").finish()
     }
 }
 
-impl<B, S, State> HttpService<B, S, State> {
-    fn new(builder: B, service: S) -> Self {
+impl<B, S, State, H> HttpService<B, S, State, H> {
+    fn new(builder: B, service: S, limits: ConnectionLimits, on_upgrade: Option<Arc<H>>) -> Self {
         Self {
             builder: Arc::new(builder),
             service: Arc::new(service),
+            limits,
+            on_upgrade,
             _phantom: std::marker::PhantomData,
         }
     }
 }
 
-impl<B, S, State> Clone for HttpService<B, S, State> {
+impl<B, S, State, H> Clone for HttpService<B, S, State, H> {
     fn clone(&self) -> Self {
         Self {
             builder: self.builder.clone(),
             service: self.service.clone(),
+            limits: self.limits,
+            on_upgrade: self.on_upgrade.clone(),
             _phantom: std::marker::PhantomData,
         }
     }
 }
 
-impl<B, State, S, Response, IO> Service<State, IO> for HttpService<B, S, State>
+impl<B, State, S, Response, H, IO> Service<State, IO> for HttpService<B, S, State, H>
 where
     B: HyperConnServer,
     State: Send + Sync + 'static,
     S: Service<State, Request, Response = Response, Error = Infallible>,
     Response: IntoResponse + Send + 'static,
+    H: Service<State, Upgraded, Response = (), Error = Infallible>,
     IO: Stream,
 {
     type Response = ();
@@ -770,7 +881,431 @@ where
         ctx: Context<State>,
         stream: IO,
     ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
-        let service = self.service.clone();
-        self.builder.hyper_serve_connection(ctx, stream, service)
+        let service = OnUpgradeService::new(self.service.clone(), self.on_upgrade.clone());
+        self.builder
+            .hyper_serve_connection(ctx, stream, service, self.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::dep::http::request::Parts;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::http::matcher::ExpectContinueFilter;
+    use crate::http::{header, Body, Response, StatusCode};
+    use crate::service::{service_fn, Matcher};
+    use crate::test_helpers::net::read_http_head;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    /// Accepts bodies of at most 16 bytes for requests expecting a `100 Continue`
+    /// response, rejecting larger ones without reading their body.
+    async fn handler(req: Request) -> Result<Response, Infallible> {
+        if ExpectContinueFilter::new().matches(None, &Context::<()>::default(), &req) {
+            let too_large = req
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+                .map(|length| length > 16)
+                .unwrap_or(true);
+            if too_large {
+                return Ok(Response::builder()
+                    .status(StatusCode::EXPECTATION_FAILED)
+                    .body(Body::empty())
+                    .unwrap());
+            }
+        }
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        Ok(Response::new(Body::from(body)))
+    }
+
+    async fn serve(server_io: DuplexStream) {
+        HttpServer::http1()
+            .serve(Context::default(), server_io, service_fn(handler))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_interim_response() {
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+
+        let client = async move {
+            client_io
+                .write_all(
+                    b"POST / HTTP/1.1\r\nhost: example.com\r\ncontent-length: 5\r\n\
+                      expect: 100-continue\r\nconnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            // the interim response is sent before the body is sent (and read)
+            let head = read_http_head(&mut client_io).await;
+            assert_eq!(&head[..], b"HTTP/1.1 100 Continue\r\n\r\n");
+
+            client_io.write_all(b"hello").await.unwrap();
+            let mut response = String::new();
+            client_io.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.ends_with("hello"));
+        };
+
+        tokio::join!(serve(server_io), client);
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_rejected_without_interim_response() {
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+
+        let client = async move {
+            client_io
+                .write_all(
+                    b"POST / HTTP/1.1\r\nhost: example.com\r\ncontent-length: 1024\r\n\
+                      expect: 100-continue\r\nconnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            let mut response = String::new();
+            client_io.read_to_string(&mut response).await.unwrap();
+            assert!(
+                response.starts_with("HTTP/1.1 417 Expectation Failed\r\n"),
+                "{response}"
+            );
+            assert!(!response.contains("100 Continue"));
+        };
+
+        tokio::join!(serve(server_io), client);
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_declined_body() {
+        use crate::http::headers::ContentLength;
+        use crate::http::service::web::{
+            extract::{ContinueBody, TypedHeader},
+            WebService,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let body_read = Arc::new(AtomicBool::new(false));
+        let service = WebService::default().post("/", {
+            let body_read = body_read.clone();
+            move |TypedHeader(length): TypedHeader<ContentLength>, body: ContinueBody| {
+                let body_read = body_read.clone();
+                async move {
+                    assert!(body.expects_continue());
+                    if length.0 > 16 {
+                        return body.decline(StatusCode::PAYLOAD_TOO_LARGE);
+                    }
+                    body.accept().collect().await.unwrap();
+                    body_read.store(true, Ordering::SeqCst);
+                    StatusCode::OK.into_response()
+                }
+            }
+        });
+
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        let server = async move {
+            HttpServer::http1()
+                .serve(Context::default(), server_io, service)
+                .await
+                .unwrap();
+        };
+        let client = async move {
+            // the connection is not closed by the client,
+            // and the body is never sent
+            client_io
+                .write_all(
+                    b"POST / HTTP/1.1\r\nhost: example.com\r\ncontent-length: 1024\r\n\
+                      expect: 100-continue\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            let mut response = String::new();
+            client_io.read_to_string(&mut response).await.unwrap();
+            assert!(
+                response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+                "{response}"
+            );
+            assert!(response.contains("connection: close\r\n"), "{response}");
+            assert!(!response.contains("100 Continue"));
+        };
+
+        tokio::join!(server, client);
+        assert!(!body_read.load(Ordering::SeqCst));
+    }
+
+    /// Read a single http/2 frame, returning its type, stream id and payload.
+    async fn read_h2_frame(io: &mut DuplexStream) -> (u8, u32, Vec<u8>) {
+        let mut head = [0u8; 9];
+        io.read_exact(&mut head).await.unwrap();
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let stream_id = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+        let mut payload = vec![0u8; len];
+        io.read_exact(&mut payload).await.unwrap();
+        (head[3], stream_id, payload)
+    }
+
+    #[tokio::test]
+    async fn test_h2_advertises_configured_settings() {
+        const SETTINGS: u8 = 0x4;
+        const WINDOW_UPDATE: u8 = 0x8;
+        const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+        const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+
+        let mut server = HttpServer::h2(Executor::default());
+        server
+            .h2_mut()
+            .max_concurrent_streams(16)
+            .initial_stream_window_size(128 * 1024)
+            .initial_connection_window_size(1024 * 1024);
+
+        let (mut client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let server = tokio::spawn(async move {
+            server
+                .serve(Context::default(), server_io, service_fn(handler))
+                .await
+        });
+
+        // client connection preface, followed by empty settings
+        client_io
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0")
+            .await
+            .unwrap();
+
+        let mut settings = None;
+        let mut connection_window_increment = None;
+        while settings.is_none() || connection_window_increment.is_none() {
+            match read_h2_frame(&mut client_io).await {
+                (SETTINGS, 0, payload) if !payload.is_empty() => {
+                    let entries: Vec<(u16, u32)> = payload
+                        .chunks_exact(6)
+                        .map(|e| {
+                            (
+                                u16::from_be_bytes([e[0], e[1]]),
+                                u32::from_be_bytes([e[2], e[3], e[4], e[5]]),
+                            )
+                        })
+                        .collect();
+                    settings = Some(entries);
+                }
+                (WINDOW_UPDATE, 0, payload) => {
+                    connection_window_increment =
+                        Some(u32::from_be_bytes(payload[..4].try_into().unwrap()));
+                }
+                _ => (),
+            }
+        }
+
+        let settings = settings.unwrap();
+        assert!(
+            settings.contains(&(SETTINGS_MAX_CONCURRENT_STREAMS, 16)),
+            "{settings:?}"
+        );
+        assert!(
+            settings.contains(&(SETTINGS_INITIAL_WINDOW_SIZE, 128 * 1024)),
+            "{settings:?}"
+        );
+        // the connection window starts at the default of 65535 bytes
+        assert_eq!(connection_window_increment, Some(1024 * 1024 - 65_535));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_h2_rapid_reset_closes_connection() {
+        const HEADERS: u8 = 0x1;
+        const RST_STREAM: u8 = 0x3;
+        const GOAWAY: u8 = 0x7;
+        const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+        let mut server = HttpServer::h2(Executor::default());
+        server.h2_mut().max_pending_accept_reset_streams(5);
+
+        let (mut client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            server
+                .serve(Context::default(), server_io, service_fn(handler))
+                .await
+        });
+
+        let frame = |kind: u8, flags: u8, stream_id: u32, payload: &[u8]| {
+            let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+            frame.extend_from_slice(&[kind, flags]);
+            frame.extend_from_slice(&stream_id.to_be_bytes());
+            frame.extend_from_slice(payload);
+            frame
+        };
+
+        // open and immediately reset (CANCEL) a burst of streams
+        let mut burst = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        burst.extend(frame(0x4, 0, 0, &[]));
+        for stream_id in (1..100).step_by(2) {
+            // GET http://example.com/, using the static table of HPACK
+            burst.extend(frame(HEADERS, 0x5, stream_id, &[0x82, 0x86, 0x84]));
+            burst.extend(frame(RST_STREAM, 0, stream_id, &0x8u32.to_be_bytes()));
+        }
+        client_io.write_all(&burst).await.unwrap();
+
+        let reason = loop {
+            let (kind, _, payload) = read_h2_frame(&mut client_io).await;
+            if kind == GOAWAY {
+                break u32::from_be_bytes(payload[4..8].try_into().unwrap());
+            }
+        };
+        assert_eq!(reason, ENHANCE_YOUR_CALM);
+
+        // the connection is terminated
+        let mut rest = Vec::new();
+        client_io.read_to_end(&mut rest).await.unwrap();
+        let _ = server.await.unwrap();
+    }
+
+    /// Send a `GET` request over the given http/1.1 connection,
+    /// returning the (lowercased) head of its response.
+    async fn http1_get(client_io: &mut DuplexStream) -> String {
+        client_io
+            .write_all(b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n")
+            .await
+            .unwrap();
+        let head = read_http_head(client_io).await;
+        String::from_utf8(head).unwrap().to_lowercase()
+    }
+
+    #[tokio::test]
+    async fn test_max_requests_per_connection() {
+        let mut server = HttpServer::http1();
+        server.max_requests_per_connection(3);
+
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            server
+                .serve(Context::default(), server_io, service_fn(handler))
+                .await
+        });
+
+        for _ in 0..2 {
+            let head = http1_get(&mut client_io).await;
+            assert!(head.starts_with("http/1.1 200 ok\r\n"), "{head}");
+            assert!(!head.contains("connection: close"), "{head}");
+        }
+
+        // the last response announces the connection is closed
+        let head = http1_get(&mut client_io).await;
+        assert!(head.starts_with("http/1.1 200 ok\r\n"), "{head}");
+        assert!(head.contains("connection: close\r\n"), "{head}");
+
+        let mut rest = Vec::new();
+        client_io.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_on_upgrade_echo_protocol() {
+        let server = HttpServer::http1().on_upgrade(service_fn(
+            |ctx: Context<String>, upgraded: Upgraded| async move {
+                let parts = ctx.get::<Parts>().unwrap();
+                let greeting = format!("{} {}\n", ctx.state(), parts.uri.path());
+                let (mut reader, mut writer) = tokio::io::split(upgraded);
+                writer.write_all(greeting.as_bytes()).await.unwrap();
+                tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+                Ok::<_, Infallible>(())
+            },
+        ));
+
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            server
+                .serve(
+                    Context::with_state(Arc::new("echo".to_owned())),
+                    server_io,
+                    service_fn(|req: Request| async move {
+                        if req.headers().get(header::UPGRADE).is_none() {
+                            return Ok::<_, Infallible>(Response::new(Body::from("hello")));
+                        }
+                        Ok(Response::builder()
+                            .status(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(header::CONNECTION, "upgrade")
+                            .header(header::UPGRADE, "echo")
+                            .body(Body::empty())
+                            .unwrap())
+                    }),
+                )
+                .await
+        });
+
+        // a regular request is still served as http
+        let head = http1_get(&mut client_io).await;
+        assert!(head.starts_with("http/1.1 200 ok\r\n"), "{head}");
+        let mut body = [0u8; 5];
+        client_io.read_exact(&mut body).await.unwrap();
+        assert_eq!(&body, b"hello");
+
+        // bytes sent right after the upgrade request are part of the upgraded connection
+        client_io
+            .write_all(
+                b"GET /upgrade HTTP/1.1\r\nhost: example.com\r\nconnection: upgrade\r\n\
+                  upgrade: echo\r\n\r\nearly bytes",
+            )
+            .await
+            .unwrap();
+        let head = String::from_utf8(read_http_head(&mut client_io).await)
+            .unwrap()
+            .to_lowercase();
+        assert!(
+            head.starts_with("http/1.1 101 switching protocols\r\n"),
+            "{head}"
+        );
+        assert!(head.contains("upgrade: echo\r\n"), "{head}");
+
+        // the handler received the context and head of the request
+        let mut greeting = [0u8; 14];
+        client_io.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"echo /upgrade\n");
+
+        let mut echoed = [0u8; 11];
+        client_io.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"early bytes");
+
+        // no more http is processed on the upgraded connection
+        client_io
+            .write_all(b"GET / HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut echoed = [0u8; 18];
+        client_io.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"GET / HTTP/1.1\r\n\r\n");
+
+        drop(client_io);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_connection_age() {
+        let mut server = HttpServer::http1();
+        server.max_connection_age(Duration::from_secs(60));
+        let service = server.service(service_fn(handler));
+
+        // requests received before the age is reached keep the connection alive
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        let conn = tokio::spawn({
+            let service = service.clone();
+            async move { service.serve(Context::default(), server_io).await }
+        });
+        let head = http1_get(&mut client_io).await;
+        assert!(!head.contains("connection: close"), "{head}");
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let head = http1_get(&mut client_io).await;
+        assert!(!head.contains("connection: close"), "{head}");
+
+        // the idle connection is closed once the age is reached
+        tokio::time::advance(Duration::from_secs(31)).await;
+        let mut rest = Vec::new();
+        client_io.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        conn.await.unwrap().unwrap();
     }
 }
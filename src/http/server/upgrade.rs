@@ -0,0 +1,93 @@
+use crate::http::layer::upgrade::Upgraded;
+use crate::http::{header, IntoResponse, Request, Response, StatusCode, Version};
+use crate::service::{Context, Service};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// The upgrade handler of an [`HttpServer`] for which no handler is set
+/// using [`HttpServer::on_upgrade`], such that no connection is ever upgraded.
+///
+/// [`HttpServer`]: super::HttpServer
+/// [`HttpServer::on_upgrade`]: super::HttpServer::on_upgrade
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct NoUpgrade;
+
+impl<State> Service<State, Upgraded> for NoUpgrade
+where
+    State: Send + Sync + 'static,
+{
+    type Response = ();
+    type Error = Infallible;
+
+    async fn serve(&self, _ctx: Context<State>, _upgraded: Upgraded) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A [`Service`] handing over the http/1.1 connections for which the inner [`Service`]
+/// responded with `101 Switching Protocols` to the (optional) upgrade handler.
+pub(crate) struct OnUpgradeService<S, H> {
+    inner: S,
+    on_upgrade: Option<Arc<H>>,
+}
+
+impl<S, H> OnUpgradeService<S, H> {
+    pub(crate) fn new(inner: S, on_upgrade: Option<Arc<H>>) -> Self {
+        Self { inner, on_upgrade }
+    }
+}
+
+impl<State, S, R, H> Service<State, Request> for OnUpgradeService<S, H>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, Request, Response = R, Error = Infallible>,
+    R: IntoResponse + Send + 'static,
+    H: Service<State, Upgraded, Response = (), Error = Infallible>,
+{
+    type Response = Response;
+    type Error = Infallible;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        mut req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        // only requests carrying an `Upgrade` header can be upgraded (RFC 9110, section 7.8)
+        let upgrade = match &self.on_upgrade {
+            Some(_)
+                if req.version() == Version::HTTP_11
+                    && req.headers().contains_key(header::UPGRADE) =>
+            {
+                req.extensions_mut().remove::<hyper::upgrade::OnUpgrade>()
+            }
+            _ => None,
+        };
+        let on_upgrade = match (&self.on_upgrade, upgrade) {
+            (Some(handler), Some(upgrade)) => {
+                let (parts, body) = req.into_parts();
+                let mut upgrade_ctx = ctx.clone();
+                upgrade_ctx.insert(parts.clone());
+                req = Request::from_parts(parts, body);
+                Some((handler.clone(), upgrade, upgrade_ctx))
+            }
+            _ => None,
+        };
+        let exec = ctx.executor().clone();
+
+        let response = self.inner.serve(ctx, req).await?.into_response();
+        if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+            if let Some((handler, upgrade, ctx)) = on_upgrade {
+                exec.spawn_task(async move {
+                    match upgrade.await {
+                        Ok(upgraded) => {
+                            let _ = handler.serve(ctx, Upgraded::new(upgraded)).await;
+                        }
+                        Err(err) => tracing::error!(error = %err, "upgrade error"),
+                    }
+                });
+            }
+        }
+        Ok(response)
+    }
+}
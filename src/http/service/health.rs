@@ -0,0 +1,346 @@
+//! Health service exposing liveness and readiness endpoints.
+//!
+//! See [`HealthService`].
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::service::health::HealthService;
+//! use rama::http::{Body, Request, StatusCode};
+//! use rama::service::{Context, Service};
+//! use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let db_connected = Arc::new(AtomicBool::new(false));
+//!
+//! let service = HealthService::new().readiness_check("database", {
+//!     let db_connected = db_connected.clone();
+//!     move || db_connected.load(Ordering::SeqCst)
+//! });
+//!
+//! let req = Request::builder().uri("/readyz").body(Body::empty()).unwrap();
+//! let resp = service.serve(Context::default(), req).await.unwrap();
+//! assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+//!
+//! db_connected.store(true, Ordering::SeqCst);
+//!
+//! let req = Request::builder().uri("/readyz").body(Body::empty()).unwrap();
+//! let resp = service.serve(Context::default(), req).await.unwrap();
+//! assert_eq!(resp.status(), StatusCode::OK);
+//! # }
+//! ```
+
+use crate::graceful::ServiceShutdown;
+use crate::http::{response::Json, IntoResponse, Method, Request, Response, StatusCode};
+use crate::service::{Context, Service};
+use futures_util::FutureExt;
+use std::{convert::Infallible, fmt, sync::Arc};
+
+type ReadinessCheck = Arc<dyn Fn() -> bool + Send + Sync + 'static>;
+
+/// Service that exposes a `/healthz` (liveness) and `/readyz` (readiness) endpoint.
+///
+/// The liveness endpoint always returns 200 (OK), while the readiness endpoint
+/// returns 200 (OK) only if all registered readiness checks report ready,
+/// and 503 (Service Unavailable) otherwise.
+///
+/// Once the graceful shutdown is triggered the service is draining, and the readiness
+/// endpoint returns 503 (Service Unavailable) such that load balancers stop sending traffic,
+/// while the liveness endpoint keeps returning 200 (OK) until the process exits.
+/// By default the graceful shutdown of the [`Context`] is observed
+/// (see [`Context::shutdown_signalled`]), use [`HealthService::shutdown_token`]
+/// to observe a [`ServiceShutdown`] token instead.
+///
+/// [`Context`]: crate::service::Context
+/// [`Context::shutdown_signalled`]: crate::service::Context::shutdown_signalled
+///
+/// Only `GET` and `HEAD` requests are served, all other requests get a 404 (Not Found).
+///
+/// By default the responses have an empty body, use [`HealthService::detailed`]
+/// to get a JSON body with the status of each readiness check instead.
+#[derive(Clone, Default)]
+pub struct HealthService {
+    checks: Vec<(String, ReadinessCheck)>,
+    shutdown: Option<ServiceShutdown>,
+    detailed: bool,
+}
+
+impl HealthService {
+    /// Create a new [`HealthService`], without any readiness checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a readiness check with the given name,
+    /// which reports ready when the given closure returns `true`.
+    pub fn readiness_check<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.checks.push((name.into(), Arc::new(check)));
+        self
+    }
+
+    /// Report not ready once the given [`ServiceShutdown`] token is signalled,
+    /// instead of once the graceful shutdown of the [`Context`] is triggered.
+    ///
+    /// [`Context`]: crate::service::Context
+    pub fn shutdown_token(mut self, token: ServiceShutdown) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    /// Respond with a JSON body detailing the status of the service
+    /// and (for the readiness endpoint) the status of each readiness check.
+    pub fn detailed(mut self) -> Self {
+        self.detailed = true;
+        self
+    }
+
+    fn liveness(&self) -> Response {
+        if self.detailed {
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "status": "alive" })),
+            )
+                .into_response()
+        } else {
+            StatusCode::OK.into_response()
+        }
+    }
+
+    fn is_draining<State>(&self, ctx: &Context<State>) -> bool {
+        match &self.shutdown {
+            Some(token) => token.is_signalled(),
+            None => ctx.shutdown_signalled().now_or_never().is_some(),
+        }
+    }
+
+    fn readiness(&self, draining: bool) -> Response {
+        let results: Vec<_> = self
+            .checks
+            .iter()
+            .map(|(name, check)| (name.as_str(), check()))
+            .collect();
+        let ready = !draining && results.iter().all(|(_, ready)| *ready);
+
+        let status = if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        if self.detailed {
+            let checks: serde_json::Map<_, _> = results
+                .into_iter()
+                .map(|(name, ready)| (name.to_owned(), serde_json::Value::Bool(ready)))
+                .collect();
+            (
+                status,
+                Json(serde_json::json!({
+                    "status": if draining {
+                        "draining"
+                    } else if ready {
+                        "ready"
+                    } else {
+                        "not_ready"
+                    },
+                    "checks": checks,
+                })),
+            )
+                .into_response()
+        } else {
+            status.into_response()
+        }
+    }
+}
+
+impl fmt::Debug for HealthService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthService")
+            .field(
+                "checks",
+                &self.checks.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("shutdown", &self.shutdown)
+            .field("detailed", &self.detailed)
+            .finish()
+    }
+}
+
+impl<State, Body> Service<State, Request<Body>> for HealthService
+where
+    State: Send + Sync + 'static,
+    Body: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request<Body>,
+    ) -> Result<Self::Response, Self::Error> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return Ok(StatusCode::NOT_FOUND.into_response());
+        }
+        Ok(match req.uri().path().trim_end_matches('/') {
+            "/healthz" => self.liveness(),
+            "/readyz" => self.readiness(self.is_draining(&ctx)),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use crate::http::{dep::http_body_util::BodyExt, Body};
+    use crate::rt::Executor;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    async fn get_response(service: &HealthService, path: &str) -> Response {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        service.serve(Context::default(), req).await.unwrap()
+    }
+
+    async fn get_json(service: &HealthService, path: &str) -> (StatusCode, serde_json::Value) {
+        let res = get_response(service, path).await;
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health_service_liveness() {
+        let service = HealthService::new().readiness_check("never", || false);
+
+        let res = get_response(&service, "/healthz").await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = get_response(&service, "/").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/healthz")
+            .body(Body::empty())
+            .unwrap();
+        let res = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_health_service_readiness_toggle() {
+        let ready = Arc::new(AtomicBool::new(false));
+        let service = HealthService::new()
+            .readiness_check("always", || true)
+            .readiness_check("toggle", {
+                let ready = ready.clone();
+                move || ready.load(Ordering::SeqCst)
+            });
+
+        let res = get_response(&service, "/readyz").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        ready.store(true, Ordering::SeqCst);
+        let res = get_response(&service, "/readyz").await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        ready.store(false, Ordering::SeqCst);
+        let res = get_response(&service, "/readyz/").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_health_service_detailed() {
+        let ready = Arc::new(AtomicBool::new(false));
+        let service = HealthService::new()
+            .readiness_check("database", {
+                let ready = ready.clone();
+                move || ready.load(Ordering::SeqCst)
+            })
+            .detailed();
+
+        let (status, body) = get_json(&service, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({ "status": "alive" }));
+
+        let (status, body) = get_json(&service, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body,
+            serde_json::json!({ "status": "not_ready", "checks": { "database": false } })
+        );
+
+        ready.store(true, Ordering::SeqCst);
+        let (status, body) = get_json(&service, "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!({ "status": "ready", "checks": { "database": true } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_service_draining_on_shutdown() {
+        let service = HealthService::new().readiness_check("always", || true);
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = rx.await;
+        });
+        let ctx = Context::new(Arc::new(()), Executor::graceful(shutdown.guard()));
+
+        let serve = |path: &'static str| {
+            let req = Request::get(path).body(Body::empty()).unwrap();
+            service.serve(ctx.clone(), req)
+        };
+        assert_eq!(serve("/readyz").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(serve("/healthz").await.unwrap().status(), StatusCode::OK);
+
+        tx.send(()).unwrap();
+        ctx.shutdown_signalled().await;
+
+        assert_eq!(
+            serve("/readyz").await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(serve("/healthz").await.unwrap().status(), StatusCode::OK);
+
+        drop(ctx);
+        shutdown
+            .shutdown_with_limit(Duration::from_secs(5))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_health_service_draining_on_shutdown_token() {
+        let token = ServiceShutdown::new();
+        let service = HealthService::new()
+            .shutdown_token(token.clone())
+            .detailed();
+
+        let (status, body) = get_json(&service, "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({ "status": "ready", "checks": {} }));
+
+        token.signal();
+        let (status, body) = get_json(&service, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body,
+            serde_json::json!({ "status": "draining", "checks": {} })
+        );
+        let (status, _) = get_json(&service, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
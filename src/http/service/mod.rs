@@ -1,5 +1,7 @@
 //! Http Services provided by Rama.
 
 pub mod fs;
+pub mod health;
+pub mod proxy;
 pub mod redirect;
 pub mod web;
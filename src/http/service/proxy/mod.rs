@@ -0,0 +1,5 @@
+//! Proxy services provided by Rama.
+
+mod reverse;
+#[doc(inline)]
+pub use reverse::{EncodingMode, ReverseProxy};
@@ -0,0 +1,592 @@
+use crate::{
+    error::BoxError,
+    http::{
+        client::HttpClient,
+        header::{self, HeaderName},
+        utils::remove_hop_by_hop_headers,
+        HeaderMap, HeaderValue, IntoResponse, Request, Response, StatusCode, Uri, Version,
+    },
+    service::{Context, Service},
+    stream::SocketInfo,
+    tls::rustls::server::TlsConnectionInfo,
+};
+use std::{convert::Infallible, fmt};
+
+#[cfg(feature = "compression")]
+use crate::http::{
+    layer::{
+        compression::{compress_response, predicate::DefaultPredicate, CompressionLevel},
+        decompression::decompress_response,
+        util::{compression::AcceptEncoding, content_encoding::Encoding},
+    },
+    Body,
+};
+
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+static X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+static X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// A service which forwards the incoming requests to a configured upstream,
+/// streaming the response of the upstream back.
+///
+/// The request is forwarded to the scheme and authority of the upstream [`Uri`],
+/// with the path of the upstream (if any) prefixed to the path of the request.
+/// Hop-by-hop headers are removed from both the request and response,
+/// and the `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto` headers
+/// are added to the forwarded request. The protocol is `https` in case the incoming
+/// connection is secured using TLS (or the request uri has the `https` scheme).
+///
+/// The `X-Forwarded-*` headers sent by the client are replaced, as they can be spoofed
+/// by any client, unless [`ReverseProxy::trust_forwarded_headers`] is enabled.
+///
+/// The `Host` header of the forwarded request is set to the authority of the upstream,
+/// unless [`ReverseProxy::preserve_host`] is enabled.
+///
+/// In case the upstream could not be reached a `502 Bad Gateway` response is returned.
+///
+/// By default the `Accept-Encoding` header of the incoming request is forwarded as-is
+/// and the (possibly compressed) body of the upstream response is passed through unchanged,
+/// see [`EncodingMode`] and [`ReverseProxy::encoding_mode`] to configure this.
+///
+/// The proxy can be placed behind matchers to route requests to different upstreams,
+/// e.g. using [`match_service`].
+///
+/// # Example
+///
+/// ```
+/// use rama::http::{matcher::HttpMatcher, service::proxy::ReverseProxy, Request, Response};
+/// use rama::http::service::web::match_service;
+/// use rama::service::Service;
+/// use std::convert::Infallible;
+///
+/// fn router() -> impl Service<(), Request, Response = Response, Error = Infallible> {
+///     match_service! {
+///         HttpMatcher::path("/api/*") => ReverseProxy::new("http://127.0.0.1:8080".parse().unwrap()),
+///         _ => ReverseProxy::new("http://127.0.0.1:3000".parse().unwrap()),
+///     }
+/// }
+/// ```
+///
+/// [`match_service`]: crate::http::service::web::match_service
+pub struct ReverseProxy<C = HttpClient> {
+    client: C,
+    upstream: Uri,
+    preserve_host: bool,
+    trust_forwarded_headers: bool,
+    encoding_mode: EncodingMode,
+}
+
+/// How the [`ReverseProxy`] handles the content encoding of the upstream responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EncodingMode {
+    /// Forward the `Accept-Encoding` header of the incoming request as-is,
+    /// and pass the body of the upstream response through unchanged,
+    /// keeping its `Content-Encoding` intact.
+    #[default]
+    Passthrough,
+    /// Request the upstream response in any of the supported encodings,
+    /// decompress it and compress it again using the encoding preferred
+    /// by the `Accept-Encoding` header of the incoming request (if any).
+    ///
+    /// Responses in an encoding which is not supported are passed through unchanged,
+    /// such that they are never compressed twice.
+    #[cfg(feature = "compression")]
+    Renegotiate,
+}
+
+impl ReverseProxy {
+    /// Create a new [`ReverseProxy`] forwarding requests to the given upstream,
+    /// using the default [`HttpClient`].
+    pub fn new(upstream: Uri) -> Self {
+        Self::with_client(HttpClient::new(), upstream)
+    }
+}
+
+impl<C> ReverseProxy<C> {
+    /// Create a new [`ReverseProxy`] forwarding requests to the given upstream,
+    /// using the given http client.
+    pub fn with_client(client: C, upstream: Uri) -> Self {
+        Self {
+            client,
+            upstream,
+            preserve_host: false,
+            trust_forwarded_headers: false,
+            encoding_mode: EncodingMode::default(),
+        }
+    }
+
+    /// Forward the `Host` header of the incoming request as-is,
+    /// instead of setting it to the authority of the upstream.
+    pub fn preserve_host(mut self, preserve_host: bool) -> Self {
+        self.preserve_host = preserve_host;
+        self
+    }
+
+    /// Trust the `X-Forwarded-*` headers of the incoming request,
+    /// e.g. in case this proxy is only reachable through another trusted proxy.
+    ///
+    /// When trusted, the address of the peer is appended to the `X-Forwarded-For` header
+    /// of the incoming request, and its `X-Forwarded-Host` and `X-Forwarded-Proto` headers
+    /// are forwarded as-is. Otherwise these headers are replaced (the default).
+    pub fn trust_forwarded_headers(mut self, trust: bool) -> Self {
+        self.trust_forwarded_headers = trust;
+        self
+    }
+
+    /// Set the [`EncodingMode`], defining how the content encoding
+    /// of the upstream responses is handled.
+    pub fn encoding_mode(mut self, mode: EncodingMode) -> Self {
+        self.encoding_mode = mode;
+        self
+    }
+
+    /// Create the [`Uri`] of the forwarded request.
+    fn upstream_uri(&self, uri: &Uri) -> Result<Uri, BoxError> {
+        let prefix = self.upstream.path().trim_end_matches('/');
+        let path_and_query = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        let mut parts = self.upstream.clone().into_parts();
+        parts.path_and_query = Some(format!("{prefix}{path_and_query}").parse()?);
+        Ok(Uri::from_parts(parts)?)
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for ReverseProxy<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReverseProxy")
+            .field("client", &self.client)
+            .field("upstream", &self.upstream)
+            .field("preserve_host", &self.preserve_host)
+            .field("trust_forwarded_headers", &self.trust_forwarded_headers)
+            .field("encoding_mode", &self.encoding_mode)
+            .finish()
+    }
+}
+
+impl<C: Clone> Clone for ReverseProxy<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            upstream: self.upstream.clone(),
+            preserve_host: self.preserve_host,
+            trust_forwarded_headers: self.trust_forwarded_headers,
+            encoding_mode: self.encoding_mode,
+        }
+    }
+}
+
+impl<State, C> Service<State, Request> for ReverseProxy<C>
+where
+    State: Send + Sync + 'static,
+    C: Service<State, Request, Response = Response>,
+    C::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = Infallible;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let (mut parts, body) = req.into_parts();
+
+        // the protocol used by the client, rather than the one used towards the upstream
+        let secure =
+            ctx.get::<TlsConnectionInfo>().is_some() || parts.uri.scheme_str() == Some("https");
+
+        parts.uri = match self.upstream_uri(&parts.uri) {
+            Ok(uri) => uri,
+            Err(err) => {
+                tracing::error!(error = %err, "reverse proxy: invalid upstream uri");
+                return Ok(StatusCode::BAD_GATEWAY.into_response());
+            }
+        };
+        // the connection to the upstream is independent of the incoming connection
+        parts.version = Version::HTTP_11;
+
+        let headers = &mut parts.headers;
+        remove_hop_by_hop_headers(headers);
+        add_forwarded_headers(&ctx, headers, secure, self.trust_forwarded_headers);
+        if !self.preserve_host {
+            if let Some(authority) = self.upstream.authority() {
+                if let Ok(host) = HeaderValue::from_str(authority.as_str()) {
+                    headers.insert(header::HOST, host);
+                }
+            }
+        }
+
+        #[cfg(feature = "compression")]
+        let encoding = match self.encoding_mode {
+            EncodingMode::Passthrough => None,
+            EncodingMode::Renegotiate => {
+                let encoding = Encoding::from_headers(headers, AcceptEncoding::default());
+                match AcceptEncoding::default().to_header_value() {
+                    Some(accept) => headers.insert(header::ACCEPT_ENCODING, accept),
+                    None => headers.remove(header::ACCEPT_ENCODING),
+                };
+                Some(encoding)
+            }
+        };
+
+        let req = Request::from_parts(parts, body);
+        match self.client.serve(ctx, req).await {
+            Ok(mut response) => {
+                remove_hop_by_hop_headers(response.headers_mut());
+                #[cfg(feature = "compression")]
+                if let Some(encoding) = encoding {
+                    response = recompress_response(response, encoding);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                let err = err.into();
+                tracing::error!(error = %err, "reverse proxy: upstream request failed");
+                Ok(StatusCode::BAD_GATEWAY.into_response())
+            }
+        }
+    }
+}
+
+/// Decompress the given upstream response and compress it again using the given encoding.
+#[cfg(feature = "compression")]
+fn recompress_response(response: Response, encoding: Encoding) -> Response {
+    let response = decompress_response(AcceptEncoding::default(), response);
+    compress_response(
+        response,
+        encoding,
+        &DefaultPredicate::default(),
+        CompressionLevel::default(),
+        None,
+    )
+    .map(Body::new)
+}
+
+/// Add the `X-Forwarded-*` headers for the incoming request to the given headers,
+/// replacing the ones sent by the client unless these are trusted.
+fn add_forwarded_headers<State>(
+    ctx: &Context<State>,
+    headers: &mut HeaderMap,
+    secure: bool,
+    trusted: bool,
+) {
+    if !trusted {
+        headers.remove(&X_FORWARDED_FOR);
+        headers.remove(&X_FORWARDED_HOST);
+        headers.remove(&X_FORWARDED_PROTO);
+    }
+    if let Some(info) = ctx.get::<SocketInfo>() {
+        let ip = info.peer_addr().ip().to_string();
+        let value = match headers.get(&X_FORWARDED_FOR).and_then(|v| v.to_str().ok()) {
+            Some(forwarded) => format!("{forwarded}, {ip}"),
+            None => ip,
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(X_FORWARDED_FOR.clone(), value);
+        }
+    }
+    if !headers.contains_key(&X_FORWARDED_HOST) {
+        if let Some(host) = headers.get(header::HOST).cloned() {
+            headers.insert(X_FORWARDED_HOST.clone(), host);
+        }
+    }
+    if !headers.contains_key(&X_FORWARDED_PROTO) {
+        let proto = HeaderValue::from_static(if secure { "https" } else { "http" });
+        headers.insert(X_FORWARDED_PROTO.clone(), proto);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{
+            dep::http_body_util::BodyExt, matcher::HttpMatcher, service::web::match_service, Body,
+        },
+        test_helpers::net::read_http_head,
+    };
+    use std::net::SocketAddr;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    /// Spawn a HTTP/1.1 backend which responds with the head of the request it received
+    /// as its body, adding the given name to its response headers.
+    async fn spawn_echo_head_backend(name: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let head = read_http_head(&mut stream).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         connection: close, x-hop\r\n\
+                         keep-alive: timeout=5\r\n\
+                         x-hop: 1\r\n\
+                         x-backend: {name}\r\n\
+                         content-length: {}\r\n\r\n",
+                        head.len()
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    stream.write_all(&head).await.unwrap();
+                });
+            }
+        });
+        addr
+    }
+
+    async fn forwarded_head(response: Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap().to_lowercase()
+    }
+
+    fn request(uri: &str) -> Request {
+        Request::builder()
+            .uri(uri)
+            .header(header::HOST, "example.com")
+            .header(header::CONNECTION, "keep-alive, x-client-hop")
+            .header("keep-alive", "timeout=5")
+            .header("x-client-hop", "1")
+            .header("x-end-to-end", "1")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn context() -> Context<()> {
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, ([10, 0, 0, 1], 40000).into()));
+        ctx
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_strips_hop_by_hop_headers() {
+        let backend = spawn_echo_head_backend("a").await;
+        let proxy = ReverseProxy::new(format!("http://{backend}").parse().unwrap());
+
+        let response = proxy.serve(context(), request("/hello?a=b")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-backend"], "a");
+        assert!(!response.headers().contains_key(header::CONNECTION));
+        assert!(!response.headers().contains_key("keep-alive"));
+        assert!(!response.headers().contains_key("x-hop"));
+
+        let head = forwarded_head(response).await;
+        assert!(head.starts_with("get /hello?a=b http/1.1\r\n"), "{head}");
+        assert!(head.contains(&format!("host: {backend}\r\n")), "{head}");
+        assert!(head.contains("x-end-to-end: 1\r\n"), "{head}");
+        assert!(!head.contains("keep-alive"), "{head}");
+        assert!(!head.contains("x-client-hop"), "{head}");
+        assert!(head.contains("x-forwarded-for: 10.0.0.1\r\n"), "{head}");
+        assert!(head.contains("x-forwarded-host: example.com\r\n"), "{head}");
+        assert!(head.contains("x-forwarded-proto: http\r\n"), "{head}");
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_upstream_prefix_and_preserve_host() {
+        let backend = spawn_echo_head_backend("a").await;
+        let proxy = ReverseProxy::new(format!("http://{backend}/prefix/").parse().unwrap())
+            .preserve_host(true)
+            .trust_forwarded_headers(true);
+
+        let mut req = request("/hello");
+        req.headers_mut()
+            .insert(&X_FORWARDED_FOR, HeaderValue::from_static("192.168.0.1"));
+        let response = proxy.serve(context(), req).await.unwrap();
+
+        let head = forwarded_head(response).await;
+        assert!(head.starts_with("get /prefix/hello http/1.1\r\n"), "{head}");
+        assert!(head.contains("host: example.com\r\n"), "{head}");
+        assert!(
+            head.contains("x-forwarded-for: 192.168.0.1, 10.0.0.1\r\n"),
+            "{head}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_https_client() {
+        let backend = spawn_echo_head_backend("a").await;
+        let proxy = ReverseProxy::new(format!("http://{backend}").parse().unwrap());
+
+        // a client connected using tls, proxied to a plain http upstream
+        let mut ctx = context();
+        ctx.insert(TlsConnectionInfo::new());
+        let response = proxy.serve(ctx, request("/")).await.unwrap();
+        let head = forwarded_head(response).await;
+        assert!(head.contains("x-forwarded-proto: https\r\n"), "{head}");
+
+        // an absolute https request uri
+        let response = proxy
+            .serve(context(), request("https://example.com/"))
+            .await
+            .unwrap();
+        let head = forwarded_head(response).await;
+        assert!(head.contains("x-forwarded-proto: https\r\n"), "{head}");
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_spoofed_forwarded_headers() {
+        let backend = spawn_echo_head_backend("a").await;
+
+        let spoofed = || {
+            let mut req = request("/");
+            let headers = req.headers_mut();
+            headers.insert(&X_FORWARDED_FOR, HeaderValue::from_static("1.2.3.4"));
+            headers.insert(&X_FORWARDED_HOST, HeaderValue::from_static("evil.com"));
+            headers.insert(&X_FORWARDED_PROTO, HeaderValue::from_static("https"));
+            req
+        };
+
+        // replaced by default
+        let proxy = ReverseProxy::new(format!("http://{backend}").parse().unwrap());
+        let response = proxy.serve(context(), spoofed()).await.unwrap();
+        let head = forwarded_head(response).await;
+        assert!(head.contains("x-forwarded-for: 10.0.0.1\r\n"), "{head}");
+        assert!(head.contains("x-forwarded-host: example.com\r\n"), "{head}");
+        assert!(head.contains("x-forwarded-proto: http\r\n"), "{head}");
+        assert!(!head.contains("evil.com"), "{head}");
+
+        // kept when trusted
+        let proxy = proxy.trust_forwarded_headers(true);
+        let response = proxy.serve(context(), spoofed()).await.unwrap();
+        let head = forwarded_head(response).await;
+        assert!(
+            head.contains("x-forwarded-for: 1.2.3.4, 10.0.0.1\r\n"),
+            "{head}"
+        );
+        assert!(head.contains("x-forwarded-host: evil.com\r\n"), "{head}");
+        assert!(head.contains("x-forwarded-proto: https\r\n"), "{head}");
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_path_routing() {
+        let api = spawn_echo_head_backend("api").await;
+        let web = spawn_echo_head_backend("web").await;
+        let service = match_service! {
+            HttpMatcher::path("/api/*") => ReverseProxy::new(format!("http://{api}").parse().unwrap()),
+            _ => ReverseProxy::new(format!("http://{web}").parse().unwrap()),
+        };
+
+        for (path, backend) in [("/api/users", "api"), ("/index.html", "web")] {
+            let response = service.serve(context(), request(path)).await.unwrap();
+            assert_eq!(response.headers()["x-backend"], backend);
+        }
+    }
+
+    /// Spawn a HTTP/1.1 backend which responds with the given body, encoded as `gzip`,
+    /// echoing the `Accept-Encoding` header of the request it received.
+    async fn spawn_gzip_backend(body: Vec<u8>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let head = String::from_utf8(read_http_head(&mut stream).await).unwrap();
+                    let accept_encoding = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("accept-encoding: "))
+                        .unwrap_or("none");
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         content-type: text/plain\r\n\
+                         content-encoding: gzip\r\n\
+                         x-accept-encoding: {accept_encoding}\r\n\
+                         content-length: {}\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        addr
+    }
+
+    fn request_with_accept_encoding(accept_encoding: Option<&str>) -> Request {
+        let mut req = request("/");
+        if let Some(accept_encoding) = accept_encoding {
+            req.headers_mut().insert(
+                header::ACCEPT_ENCODING,
+                HeaderValue::from_str(accept_encoding).unwrap(),
+            );
+        }
+        req
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_encoding_passthrough() {
+        let backend = spawn_gzip_backend(b"opaque gzip bytes".to_vec()).await;
+        let proxy = ReverseProxy::new(format!("http://{backend}").parse().unwrap())
+            .encoding_mode(EncodingMode::Passthrough);
+
+        let response = proxy
+            .serve(context(), request_with_accept_encoding(Some("gzip, br")))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-accept-encoding"], "gzip, br");
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "17");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"opaque gzip bytes");
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_reverse_proxy_encoding_renegotiate() {
+        use async_compression::tokio::bufread::{BrotliDecoder, GzipEncoder};
+        use tokio::io::AsyncReadExt;
+
+        let text = "hello, world! ".repeat(64);
+        let mut gzipped = Vec::new();
+        GzipEncoder::new(text.as_bytes())
+            .read_to_end(&mut gzipped)
+            .await
+            .unwrap();
+
+        let backend = spawn_gzip_backend(gzipped).await;
+        let proxy = ReverseProxy::new(format!("http://{backend}").parse().unwrap())
+            .encoding_mode(EncodingMode::Renegotiate);
+
+        // recompressed using the encoding accepted by the client
+        let response = proxy
+            .serve(context(), request_with_accept_encoding(Some("br")))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["x-accept-encoding"],
+            AcceptEncoding::default().to_header_value().unwrap()
+        );
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let mut decoded = String::new();
+        BrotliDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(decoded, text);
+
+        // decompressed for clients which do not accept any encoding
+        let response = proxy
+            .serve(context(), request_with_accept_encoding(None))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], text.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy_bad_gateway() {
+        // bind and drop a listener, such that nothing is listening on its port
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let proxy = ReverseProxy::new(format!("http://{addr}").parse().unwrap());
+
+        let response = proxy.serve(context(), request("/")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
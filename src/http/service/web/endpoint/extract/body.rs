@@ -1,6 +1,13 @@
 use super::FromRequest;
-use crate::http::{self, dep::http_body_util::BodyExt, StatusCode};
-use crate::service::Context;
+use crate::http::{
+    self,
+    dep::http_body_util::{BodyExt, LengthLimitError, Limited},
+    header,
+    layer::body_limit::BodyLimit,
+    matcher::ExpectContinueFilter,
+    HeaderValue, IntoResponse, StatusCode, Version,
+};
+use crate::service::{Context, Matcher};
 use std::convert::Infallible;
 use std::ops::{Deref, DerefMut};
 
@@ -33,8 +40,111 @@ impl DerefMut for Body {
     }
 }
 
+/// Extractor to get the request body of a request which might carry an `Expect: 100-continue`
+/// header, allowing to either accept or decline the body.
+///
+/// The [`HttpServer`] sends the interim `100 Continue` response once the body is first read,
+/// such that a handler can inspect the request head (e.g. its `Content-Length`) first,
+/// and then either [`accept`] the body to read it, or [`decline`] it to respond with a
+/// final response without the client ever sending the body.
+///
+/// [`HttpServer`]: crate::http::server::HttpServer
+/// [`accept`]: ContinueBody::accept
+/// [`decline`]: ContinueBody::decline
+#[derive(Debug)]
+pub struct ContinueBody {
+    body: http::Body,
+    version: Version,
+    expect_continue: bool,
+}
+
+impl ContinueBody {
+    /// Returns `true` in case the client waits for a `100 Continue` response
+    /// before sending the body.
+    pub fn expects_continue(&self) -> bool {
+        self.expect_continue
+    }
+
+    /// Accept the body, returning it such that it can be read,
+    /// which sends the interim `100 Continue` response (if expected).
+    pub fn accept(self) -> http::Body {
+        self.body
+    }
+
+    /// Decline the body, dropping it without reading it, and turning the given value
+    /// into the final response to send instead.
+    ///
+    /// In case the client waits for a `100 Continue` response, a `Connection: close` header
+    /// is added to http/1 responses, as the connection cannot be reused
+    /// once the client decides to send the body anyway.
+    pub fn decline(self, response: impl IntoResponse) -> http::Response {
+        drop(self.body);
+        let mut response = response.into_response();
+        if self.expect_continue && self.version <= Version::HTTP_11 {
+            response
+                .headers_mut()
+                .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        }
+        response
+    }
+}
+
+impl<S> FromRequest<S> for ContinueBody
+where
+    S: Send + Sync + 'static,
+{
+    type Rejection = Infallible;
+
+    async fn from_request(ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
+        let expect_continue = ExpectContinueFilter::new().matches(None, &ctx, &req);
+        Ok(Self {
+            version: req.version(),
+            body: req.into_body(),
+            expect_continue,
+        })
+    }
+}
+
+/// Collect the entire body of the request, limited to the [`BodyLimit`] of the [`Context`], if any.
+///
+/// Fails with `413 Payload Too Large` in case the body exceeds the limit,
+/// and with `400 Bad Request` in case the body could not be read.
+async fn collect_limited<S>(
+    ctx: &Context<S>,
+    req: http::Request,
+) -> Result<bytes::Bytes, StatusCode> {
+    let Some(limit) = BodyLimit::from_context(ctx).map(|limit| limit.get()) else {
+        return match req.into_body().collect().await {
+            Ok(c) => Ok(c.to_bytes()),
+            Err(_) => Err(StatusCode::BAD_REQUEST),
+        };
+    };
+
+    // reject early in case the announced length already exceeds the limit
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if content_length
+        .map(|len| len > limit as u64)
+        .unwrap_or_default()
+    {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    match Limited::new(req.into_body(), limit).collect().await {
+        Ok(c) => Ok(c.to_bytes()),
+        Err(err) if err.is::<LengthLimitError>() => Err(StatusCode::PAYLOAD_TOO_LARGE),
+        Err(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
 /// Extractor to get the response body, collected as [`Bytes`].
 ///
+/// The body is limited to the [`BodyLimit`] of the [`Context`] (if any),
+/// and rejected with `413 Payload Too Large` in case it exceeds it.
+///
 /// [`Bytes`]: https://docs.rs/bytes/latest/bytes/struct.Bytes.html
 #[derive(Debug, Clone)]
 pub struct Bytes(pub bytes::Bytes);
@@ -45,12 +155,8 @@ where
 {
     type Rejection = StatusCode;
 
-    async fn from_request(_ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
-        let body = req.into_body();
-        match body.collect().await {
-            Ok(c) => Ok(Self(c.to_bytes())),
-            Err(_) => Err(StatusCode::BAD_REQUEST),
-        }
+    async fn from_request(ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
+        collect_limited(&ctx, req).await.map(Self)
     }
 }
 
@@ -69,6 +175,8 @@ impl DerefMut for Bytes {
 }
 
 /// Extractor to get the response body, collected as [`String`].
+///
+/// The body is limited in the same way as for the [`Bytes`] extractor.
 #[derive(Debug, Clone)]
 pub struct Text(pub String);
 
@@ -78,16 +186,10 @@ where
 {
     type Rejection = StatusCode;
 
-    async fn from_request(_ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
-        let body = req.into_body();
-        match body.collect().await {
-            Ok(c) => {
-                let b = c.to_bytes();
-                match String::from_utf8(b.to_vec()) {
-                    Ok(s) => Ok(Self(s)),
-                    Err(_) => Err(StatusCode::BAD_REQUEST),
-                }
-            }
+    async fn from_request(ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
+        let b = collect_limited(&ctx, req).await?;
+        match String::from_utf8(b.to_vec()) {
+            Ok(s) => Ok(Self(s)),
             Err(_) => Err(StatusCode::BAD_REQUEST),
         }
     }
@@ -116,16 +218,10 @@ where
 {
     type Rejection = StatusCode;
 
-    async fn from_request(_ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
-        let body = req.into_body();
-        match body.collect().await {
-            Ok(c) => {
-                let b = c.to_bytes();
-                match serde_json::from_slice(&b) {
-                    Ok(s) => Ok(Self(s)),
-                    Err(_) => Err(StatusCode::BAD_REQUEST),
-                }
-            }
+    async fn from_request(ctx: Context<S>, req: http::Request) -> Result<Self, Self::Rejection> {
+        let b = collect_limited(&ctx, req).await?;
+        match serde_json::from_slice(&b) {
+            Ok(s) => Ok(Self(s)),
             Err(_) => Err(StatusCode::BAD_REQUEST),
         }
     }
@@ -166,6 +262,65 @@ mod test {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_bytes_limit() {
+        let service = WebService::default().post("/", |Bytes(body): Bytes| async move {
+            assert_eq!(body, "test");
+        });
+
+        let serve = |limit: Option<usize>, body: http::Body| {
+            let mut ctx = Context::default();
+            if let Some(limit) = limit {
+                ctx.insert(BodyLimit::new(limit));
+            }
+            let req = http::Request::builder()
+                .method(http::Method::POST)
+                .body(body)
+                .unwrap();
+            let service = &service;
+            async move { service.serve(ctx, req).await.unwrap().status() }
+        };
+
+        // under (or at) the limit
+        assert_eq!(serve(None, "test".into()).await, StatusCode::OK);
+        assert_eq!(serve(Some(4), "test".into()).await, StatusCode::OK);
+
+        // over the limit, with or without a known length
+        assert_eq!(
+            serve(Some(3), "test".into()).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        let stream = futures::stream::iter([Ok::<_, std::io::Error>("te"), Ok("st")]);
+        assert_eq!(
+            serve(Some(3), http::Body::from_stream(stream)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .header(header::CONTENT_LENGTH, 5)
+            .body(http::Body::empty())
+            .unwrap();
+        let mut ctx = Context::default();
+        ctx.insert(BodyLimit::new(4));
+        let resp = service.serve(ctx, req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // without a limit the body is not limited
+        let service =
+            WebService::default().post(
+                "/",
+                |Bytes(body): Bytes| async move { body.len().to_string() },
+            );
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .body(vec![0u8; 4 * 1024 * 1024].into())
+            .unwrap();
+        let resp = service.serve(Context::default(), req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, (4 * 1024 * 1024).to_string());
+    }
+
     #[tokio::test]
     async fn test_text() {
         let service = WebService::default().get("/", |Text(body): Text| async move {
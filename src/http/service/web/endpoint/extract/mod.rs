@@ -33,7 +33,7 @@ mod typed_header;
 pub use typed_header::TypedHeader;
 
 mod body;
-pub use body::{Body, Bytes, Json, Text};
+pub use body::{Body, Bytes, ContinueBody, Json, Text};
 
 mod private {
     #[derive(Debug, Clone, Copy)]
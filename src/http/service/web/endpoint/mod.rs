@@ -1,16 +1,16 @@
 use crate::{
-    http::matcher::HttpMatcher,
     http::{IntoResponse, Request, Response},
     service::{BoxService, Context, Service, ServiceBuilder},
 };
 use std::convert::Infallible;
 use std::future::Future;
+use std::time::Duration;
 
 pub mod extract;
 
 pub(crate) struct Endpoint<State> {
-    pub(crate) matcher: HttpMatcher,
     pub(crate) service: BoxService<State, Request, Response, Infallible>,
+    pub(crate) timeout: Option<Duration>,
 }
 
 /// utility trait to accept multiple types as an endpoint service for [`super::WebService`]
@@ -1,14 +1,18 @@
 use super::{endpoint::Endpoint, IntoEndpointService};
 use crate::{
     http::{
-        matcher::{HttpMatcher, UriParams},
+        header::ALLOW,
+        matcher::{HttpMatcher, PathRouter, UriParams},
         service::fs::ServeDir,
-        IntoResponse, Request, Response, StatusCode, Uri,
+        HeaderValue, IntoResponse, Method, Request, Response, StatusCode, Uri,
+    },
+    service::{
+        context::Extensions, layer::budget::Deadline, service_fn, BoxService, Context, Matcher,
+        Service,
     },
-    service::{context::Extensions, service_fn, BoxService, Context, Matcher, Service},
 };
 use paste::paste;
-use std::{convert::Infallible, future::Future, marker::PhantomData, sync::Arc};
+use std::{convert::Infallible, future::Future, marker::PhantomData, sync::Arc, time::Duration};
 
 /// A basic web service that can be used to serve HTTP requests.
 ///
@@ -16,9 +20,22 @@ use std::{convert::Infallible, future::Future, marker::PhantomData, sync::Arc};
 /// For those locations where you need do not desire the convenience over performance,
 /// you can instead use a tuple of `(M, S)` tuples, where M is a matcher and S is a service,
 /// e.g. `((MethodFilter::GET, service_a), (MethodFilter::POST, service_b), service_fallback)`.
+///
+/// Routes added for a single method (e.g. using [`WebService::get`]) are resolved
+/// using a [`PathRouter`], such that the time it takes to find the route for a request
+/// does not grow with the number of routes. Where multiple of those routes match a path,
+/// literal segments take priority over params, which take priority over a glob.
+/// Routes added using a custom matcher (e.g. using [`WebService::on`]) are evaluated
+/// in order, and take priority over the method routes added after them.
 pub struct WebService<State> {
     endpoints: Vec<Arc<Endpoint<State>>>,
+    /// the method routes, resolving a path to the method and index of each of its endpoints
+    routes: Arc<PathRouter<Vec<(Method, usize)>>>,
+    /// the routes with a custom matcher, together with the index of their endpoint
+    matchers: Vec<(HttpMatcher, usize)>,
     not_found: Arc<BoxService<State, Request, Response, Infallible>>,
+    method_not_allowed: Arc<BoxService<State, Request, Response, Infallible>>,
+    timeout: Option<Duration>,
     _phantom: PhantomData<State>,
 }
 
@@ -32,7 +49,11 @@ impl<State> Clone for WebService<State> {
     fn clone(&self) -> Self {
         Self {
             endpoints: self.endpoints.clone(),
+            routes: self.routes.clone(),
+            matchers: self.matchers.clone(),
             not_found: self.not_found.clone(),
+            method_not_allowed: self.method_not_allowed.clone(),
+            timeout: self.timeout,
             _phantom: PhantomData,
         }
     }
@@ -46,20 +67,28 @@ where
     pub(crate) fn new() -> Self {
         Self {
             endpoints: Vec::new(),
+            routes: Arc::new(PathRouter::new()),
+            matchers: Vec::new(),
             not_found: Arc::new(
                 service_fn(|| async { Ok(StatusCode::NOT_FOUND.into_response()) }).boxed(),
             ),
+            method_not_allowed: Arc::new(
+                service_fn(|| async { Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()) }).boxed(),
+            ),
+            timeout: None,
             _phantom: PhantomData,
         }
     }
 
     /// add a GET route to the web service, using the given service.
+    ///
+    /// The route serves `HEAD` requests as well, unless a HEAD route
+    /// (see [`WebService::head`]) is added for the same path.
     pub fn get<I, T>(self, path: &str, service: I) -> Self
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_get().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::GET, path, service)
     }
 
     /// add a POST route to the web service, using the given service.
@@ -67,8 +96,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_post().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::POST, path, service)
     }
 
     /// add a PUT route to the web service, using the given service.
@@ -76,8 +104,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_put().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::PUT, path, service)
     }
 
     /// add a DELETE route to the web service, using the given service.
@@ -85,8 +112,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_delete().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::DELETE, path, service)
     }
 
     /// add a PATCH route to the web service, using the given service.
@@ -94,8 +120,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_patch().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::PATCH, path, service)
     }
 
     /// add a HEAD route to the web service, using the given service.
@@ -103,8 +128,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_head().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::HEAD, path, service)
     }
 
     /// add a OPTIONS route to the web service, using the given service.
@@ -112,8 +136,7 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_options().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::OPTIONS, path, service)
     }
 
     /// add a TRACE route to the web service, using the given service.
@@ -121,19 +144,22 @@ where
     where
         I: IntoEndpointService<State, T>,
     {
-        let matcher = HttpMatcher::method_trace().and_path(path);
-        self.on(matcher, service)
+        self.add_route(Method::TRACE, path, service)
     }
 
     /// nest a web service under the given path.
     ///
-    /// The nested service will receive a request with the path prefix removed.
+    /// The nested service will receive a request with the path prefix removed,
+    /// where a request for the prefix itself is received as a request for the root path (`/`).
+    ///
+    /// In case the nested service is a [`WebService`] as well, its own fallback
+    /// (see [`WebService::not_found`]) applies to all requests within the nest scope.
     pub fn nest<I, T>(self, prefix: &str, service: I) -> Self
     where
         I: IntoEndpointService<State, T>,
     {
-        let prefix = format!("{}/*", prefix.trim_end_matches(['/', '*']));
-        let matcher = HttpMatcher::path(prefix);
+        let prefix = prefix.trim_end_matches(['/', '*']);
+        let matcher = HttpMatcher::path(prefix).or_path(format!("{}/*", prefix));
         let service = NestedService(service.into_endpoint_service());
         self.on(matcher, service)
     }
@@ -145,19 +171,79 @@ where
     }
 
     /// add a route to the web service which matches the given matcher, using the given service.
-    pub fn on<I, T>(mut self, matcher: HttpMatcher, service: I) -> Self
+    pub fn on<I, T>(self, matcher: HttpMatcher, service: I) -> Self
+    where
+        I: IntoEndpointService<State, T>,
+    {
+        self.add_endpoint(matcher, service, None)
+    }
+
+    /// add a route to the web service which matches the given matcher, using the given service,
+    /// overriding the default timeout (see [`WebService::timeout`]) for this route.
+    ///
+    /// Requests which do not complete within the timeout get a `408 Request Timeout` response.
+    pub fn on_with_timeout<I, T>(self, matcher: HttpMatcher, service: I, timeout: Duration) -> Self
+    where
+        I: IntoEndpointService<State, T>,
+    {
+        self.add_endpoint(matcher, service, Some(timeout))
+    }
+
+    fn add_route<I, T>(mut self, method: Method, path: &str, service: I) -> Self
+    where
+        I: IntoEndpointService<State, T>,
+    {
+        let index = self.push_endpoint(service, None);
+        let routes = Arc::make_mut(&mut self.routes);
+        let mut methods = routes.insert(path, Vec::new()).unwrap_or_default();
+        methods.push((method, index));
+        routes.insert(path, methods);
+        self
+    }
+
+    fn add_endpoint<I, T>(
+        mut self,
+        matcher: HttpMatcher,
+        service: I,
+        timeout: Option<Duration>,
+    ) -> Self
+    where
+        I: IntoEndpointService<State, T>,
+    {
+        let index = self.push_endpoint(service, timeout);
+        self.matchers.push((matcher, index));
+        self
+    }
+
+    fn push_endpoint<I, T>(&mut self, service: I, timeout: Option<Duration>) -> usize
     where
         I: IntoEndpointService<State, T>,
     {
         let endpoint = Endpoint {
-            matcher,
             service: service.into_endpoint_service().boxed(),
+            timeout,
         };
         self.endpoints.push(Arc::new(endpoint));
+        self.endpoints.len() - 1
+    }
+
+    /// apply the given timeout to all routes of the web service,
+    /// except those with their own timeout (see [`WebService::on_with_timeout`]).
+    ///
+    /// Requests which do not complete within the timeout get a `408 Request Timeout` response.
+    /// The fallback service (see [`WebService::not_found`]) is not subject to this timeout.
+    ///
+    /// In case a [`Deadline`] is present in the [`Context`], the timeout of a route
+    /// is shrunk to the time remaining until that deadline.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
         self
     }
 
     /// use the given service in case no match could be found.
+    ///
+    /// Requests for the path of a method route (e.g. [`WebService::get`]) with another method
+    /// are handled by the [`WebService::method_not_allowed`] service instead.
     pub fn not_found<I, T>(mut self, service: I) -> Self
     where
         I: IntoEndpointService<State, T>,
@@ -165,6 +251,80 @@ where
         self.not_found = Arc::new(service.into_endpoint_service().boxed());
         self
     }
+
+    /// use the given service in case no match could be found, while the path of the request
+    /// does match one or more routes added for a single method (e.g. using [`WebService::get`]).
+    ///
+    /// By default a `405 Method Not Allowed` response is returned. An `Allow` header listing
+    /// the methods of the routes for the path best matching the request is added to the response,
+    /// unless the service already set one itself.
+    ///
+    /// Routes added using [`WebService::on`] are not taken into account,
+    /// as their matcher cannot be split into a path and method.
+    pub fn method_not_allowed<I, T>(mut self, service: I) -> Self
+    where
+        I: IntoEndpointService<State, T>,
+    {
+        self.method_not_allowed = Arc::new(service.into_endpoint_service().boxed());
+        self
+    }
+
+    /// the method route matching the request, if any,
+    /// together with the params captured for its path.
+    fn route(&self, req: &Request) -> Option<(usize, UriParams)> {
+        let method = req.method();
+        let (methods, params) = self.routes.at_matching(req.uri().path(), |methods| {
+            method_route(methods, method).is_some()
+        })?;
+        Some((method_route(methods, method)?, params))
+    }
+
+    /// the methods of the method routes matching the path of the request, if any.
+    fn allowed_methods(&self, req: &Request) -> Vec<&Method> {
+        let mut allowed = Vec::new();
+        if let Some((methods, _)) = self.routes.at(req.uri().path()) {
+            for (method, _) in methods {
+                if !allowed.contains(&method) {
+                    allowed.push(method);
+                }
+                // HEAD requests are served by the GET route
+                if method == Method::GET && !allowed.contains(&&Method::HEAD) {
+                    allowed.push(&Method::HEAD);
+                }
+            }
+        }
+        allowed
+    }
+
+    async fn serve_endpoint(
+        &self,
+        endpoint: &Endpoint<State>,
+        ctx: Context<State>,
+        req: Request,
+    ) -> Result<Response, Infallible> {
+        let timeout = endpoint
+            .timeout
+            .or(self.timeout)
+            .map(|timeout| Deadline::clamp_timeout(&ctx, timeout));
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, endpoint.service.serve(ctx, req))
+                .await
+                .unwrap_or_else(|_| Ok(StatusCode::REQUEST_TIMEOUT.into_response())),
+            None => endpoint.service.serve(ctx, req).await,
+        }
+    }
+}
+
+/// the index of the endpoint serving the given method, out of the method routes of a path.
+///
+/// A `HEAD` request is served by the `GET` route, unless there is a `HEAD` route as well.
+fn method_route(methods: &[(Method, usize)], method: &Method) -> Option<usize> {
+    let route = methods.iter().find(|(m, _)| m == method);
+    let route = match route {
+        None if method == Method::HEAD => methods.iter().find(|(m, _)| m == Method::GET),
+        route => route,
+    };
+    route.map(|(_, index)| *index)
 }
 
 #[derive(Debug, Clone)]
@@ -183,8 +343,11 @@ where
         ctx: Context<State>,
         req: Request,
     ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
-        // get nested path
-        let path = ctx.get::<UriParams>().unwrap().glob().unwrap();
+        // get nested path, the prefix itself is considered the root of the nested service
+        let path = ctx
+            .get::<UriParams>()
+            .and_then(UriParams::glob)
+            .unwrap_or("/");
 
         // set the nested path
         let (mut parts, body) = req.into_parts();
@@ -227,17 +390,42 @@ where
         mut ctx: Context<State>,
         req: Request,
     ) -> Result<Self::Response, Self::Error> {
+        let route = self.route(&req);
+
+        // custom matchers added before the method route (if any) take priority
+        let before = route.as_ref().map_or(usize::MAX, |(index, _)| *index);
         let mut ext = Extensions::new();
-        for endpoint in &self.endpoints {
-            if endpoint.matcher.matches(Some(&mut ext), &ctx, &req) {
+        for (matcher, index) in self.matchers.iter().take_while(|(_, i)| *i < before) {
+            if matcher.matches(Some(&mut ext), &ctx, &req) {
                 // insert the extensions that might be generated by the matcher(s) into the context
                 ctx.extend(ext);
-                return endpoint.service.serve(ctx, req).await;
+                return self.serve_endpoint(&self.endpoints[*index], ctx, req).await;
             }
             // clear the extensions for the next matcher
             ext.clear();
         }
-        self.not_found.serve(ctx, req).await
+
+        if let Some((index, params)) = route {
+            ctx.insert(params);
+            return self.serve_endpoint(&self.endpoints[index], ctx, req).await;
+        }
+
+        let allowed = self.allowed_methods(&req);
+        if allowed.is_empty() {
+            return self.not_found.serve(ctx, req).await;
+        }
+        let allow = allowed
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut res = self.method_not_allowed.serve(ctx, req).await?;
+        if !res.headers().contains_key(ALLOW) {
+            if let Ok(allow) = HeaderValue::from_str(&allow) {
+                res.headers_mut().insert(ALLOW, allow);
+            }
+        }
+        Ok(res)
     }
 }
 
@@ -290,10 +478,36 @@ all_the_tuples_no_last_special_case!(impl_matcher_service_tuple);
 /// Which is nothing more then a convenient wrapper to create a tuple of matcher-service tuples,
 /// with the last tuple being the fallback service. And all services implement
 /// the [`IntoEndpointService`] trait.
+///
+/// A timeout can be attached to a route using `; timeout = <Duration>`,
+/// in which case requests for that route which do not complete within the timeout
+/// get a `408 Request Timeout` response:
+///
+/// ```
+/// use rama::http::matcher::HttpMatcher;
+/// use rama::http::service::web::match_service;
+/// use rama::http::{Request, Response, StatusCode};
+/// use rama::service::Service;
+/// use std::{convert::Infallible, time::Duration};
+///
+/// fn service() -> impl Service<(), Request, Response = Response, Error = Infallible> {
+///     match_service! {
+///         HttpMatcher::get("/slow") => "slow"; timeout = Duration::from_secs(30),
+///         HttpMatcher::get("/fast") => "fast"; timeout = Duration::from_secs(1),
+///         _ => StatusCode::NOT_FOUND,
+///     }
+/// }
+/// ```
 macro_rules! __match_service {
-    ($($M:expr => $S:expr),+, _ => $F:expr $(,)?) => {{
+    (@service $S:expr) => {
+        $S.into_endpoint_service()
+    };
+    (@service $S:expr, $T:expr) => {
+        $crate::http::layer::timeout::Timeout::new($S.into_endpoint_service(), $T)
+    };
+    ($($M:expr => $S:expr $(; timeout = $T:expr)?),+, _ => $F:expr $(,)?) => {{
         use $crate::http::service::web::IntoEndpointService;
-        ($(($M, $S.into_endpoint_service())),+, $F.into_endpoint_service())
+        ($(($M, $crate::__match_service!(@service $S $(, $T)?))),+, $F.into_endpoint_service())
     }};
 }
 
@@ -349,12 +563,191 @@ mod test {
         assert_eq!(body, "world");
 
         let res = get_response(&svc, "https://www.test.io/world").await;
-        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "POST");
 
         let res = get_response(&svc, "https://www.test.io").await;
         assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_web_service_method_not_allowed() {
+        let svc = WebService::new()
+            .get("/hello", "hello")
+            .post("/hello", "hello")
+            .get("/hello", "duplicate")
+            .delete("/items/:id", "deleted")
+            .get("/world", "world");
+
+        let res = connect_response(&svc, "https://www.test.io/hello").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD, POST");
+
+        let res = post_response(&svc, "https://www.test.io/items/42").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "DELETE");
+
+        // an unknown path is still not found
+        let res = post_response(&svc, "https://www.test.io/items").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get(ALLOW).is_none());
+        let res = get_response(&svc, "https://www.test.io/unknown").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get(ALLOW).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_web_service_head() {
+        let svc = WebService::new()
+            .get("/hello", "hello")
+            .get("/world", "world")
+            .head("/world", "head world")
+            .post("/items", "items");
+
+        async fn head_response(svc: &WebService<()>, uri: &str) -> Response {
+            let req = Request::head(uri).body(Body::empty()).unwrap();
+            svc.serve(Context::default(), req).await.unwrap()
+        }
+
+        // served by the GET route
+        let res = head_response(&svc, "https://www.test.io/hello").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "hello");
+
+        // unless there is a HEAD route
+        let res = head_response(&svc, "https://www.test.io/world").await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "head world");
+
+        let res = head_response(&svc, "https://www.test.io/items").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "POST");
+
+        let res = post_response(&svc, "https://www.test.io/world").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_web_service_route_priority() {
+        let svc = WebService::new()
+            .get("/users/:id", "user")
+            .delete("/users/:id", "deleted")
+            .get("/users/me", "me")
+            .get("/files/*", "files")
+            .get("/files/:name", "file");
+
+        for (uri, expected) in [
+            ("https://www.test.io/users/42", "user"),
+            ("https://www.test.io/users/me", "me"),
+            ("https://www.test.io/files/a.txt", "file"),
+            ("https://www.test.io/files/a/b.txt", "files"),
+        ] {
+            let res = get_response(&svc, uri).await;
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected, "{uri}");
+        }
+
+        // a less specific route is used for a method the best matching route does not have
+        let req = Request::delete("https://www.test.io/users/me")
+            .body(Body::empty())
+            .unwrap();
+        let res = svc.serve(Context::default(), req).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "deleted");
+
+        // the allowed methods are those of the best matching route
+        let res = post_response(&svc, "https://www.test.io/users/me").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_web_service_route_params() {
+        let svc = WebService::new().get(
+            "/users/:id/posts/:post",
+            service_fn(|ctx: Context<()>, _req: Request| async move {
+                let params = ctx.get::<UriParams>().unwrap();
+                Ok::<_, Infallible>(format!(
+                    "{}-{}",
+                    params.get("id").unwrap(),
+                    params.get("post").unwrap()
+                ))
+            }),
+        );
+
+        let res = get_response(&svc, "https://www.test.io/users/42/posts/7").await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "42-7");
+    }
+
+    #[tokio::test]
+    async fn test_web_service_custom_matcher_order() {
+        let svc = WebService::new()
+            .on(HttpMatcher::get("/first"), "custom first")
+            .get("/first", "route first")
+            .get("/last", "route last")
+            .on(HttpMatcher::get("/last"), "custom last")
+            .on(HttpMatcher::get("/other"), "custom other");
+
+        for (uri, expected) in [
+            ("https://www.test.io/first", "custom first"),
+            ("https://www.test.io/last", "route last"),
+            ("https://www.test.io/other", "custom other"),
+        ] {
+            let res = get_response(&svc, uri).await;
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_web_service_many_routes() {
+        const SERVICES: usize = 500;
+
+        let mut svc = WebService::new();
+        for i in 0..SERVICES {
+            svc = svc
+                .get(&format!("/service{i}/items"), format!("items {i}"))
+                .get(&format!("/service{i}/items/:id"), format!("item {i}"));
+        }
+
+        for i in (0..SERVICES).step_by(13) {
+            let res = get_response(&svc, &format!("https://www.test.io/service{i}/items")).await;
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, format!("items {i}"));
+
+            let res = get_response(&svc, &format!("https://www.test.io/service{i}/items/1")).await;
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, format!("item {i}"));
+        }
+
+        let res = get_response(&svc, "https://www.test.io/service0/other").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_web_service_method_not_allowed_custom() {
+        let svc = WebService::new()
+            .get("/hello", "hello")
+            .on(HttpMatcher::post("/world"), "world")
+            .method_not_allowed("wrong method")
+            .not_found("not found");
+
+        let res = post_response(&svc, "https://www.test.io/hello").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "wrong method");
+
+        // custom matchers are not split into path and method
+        let res = get_response(&svc, "https://www.test.io/world").await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "not found");
+    }
+
     #[tokio::test]
     async fn test_web_service_not_found() {
         let svc = WebService::new().not_found("not found");
@@ -385,12 +778,69 @@ mod test {
         assert_eq!(body, "world");
 
         let res = get_response(&svc, "https://www.test.io/api/world").await;
-        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "POST");
 
         let res = get_response(&svc, "https://www.test.io").await;
         assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_web_service_nest_prefix_root() {
+        let svc = WebService::new().nest(
+            "/api/",
+            WebService::new().get("/", "root").get("/hello", "hello"),
+        );
+
+        for uri in ["https://www.test.io/api", "https://www.test.io/api/"] {
+            let res = get_response(&svc, uri).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "root");
+        }
+
+        let res = get_response(&svc, "https://www.test.io/api/hello?foo=bar").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "hello");
+
+        let res = get_response(&svc, "https://www.test.io/apis/hello").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_web_service_nest_not_found() {
+        let svc = WebService::new()
+            .get("/hello", "hello")
+            .nest(
+                "/api",
+                WebService::new()
+                    .get("/hello", "api hello")
+                    .nest("/v1", WebService::new().get("/hello", "api v1 hello"))
+                    .not_found("api not found"),
+            )
+            .not_found("not found");
+
+        for (uri, expected_body) in [
+            ("https://www.test.io/hello", "hello"),
+            ("https://www.test.io/api/hello", "api hello"),
+            ("https://www.test.io/api/v1/hello", "api v1 hello"),
+            ("https://www.test.io/api/world", "api not found"),
+            ("https://www.test.io/api", "api not found"),
+            ("https://www.test.io/world", "not found"),
+            ("https://www.test.io/v1/hello", "not found"),
+        ] {
+            let res = get_response(&svc, uri).await;
+            assert_eq!(res.status(), StatusCode::OK, "uri: {}", uri);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected_body, "uri: {}", uri);
+        }
+
+        // the nested service without a custom fallback uses the default one
+        let res = get_response(&svc, "https://www.test.io/api/v1/world").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_web_service_dir() {
         let tmp_dir = tempfile::tempdir().unwrap();
@@ -463,4 +913,49 @@ mod test {
         let res = get_response(&svc, "https://www.test.io").await;
         assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
+
+    async fn slow_response(delay: Duration) -> &'static str {
+        tokio::time::sleep(delay).await;
+        "done"
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_web_service_route_timeout() {
+        let svc = WebService::new()
+            .get("/fast", || slow_response(Duration::from_millis(50)))
+            .on_with_timeout(
+                HttpMatcher::get("/slow"),
+                || slow_response(Duration::from_millis(50)),
+                Duration::from_millis(100),
+            )
+            .timeout(Duration::from_millis(10));
+
+        let res = get_response(&svc, "https://www.test.io/fast").await;
+        assert_eq!(res.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let res = get_response(&svc, "https://www.test.io/slow").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "done");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_matcher_service_tuples_timeout() {
+        let svc = match_service! {
+            HttpMatcher::get("/fast") => || slow_response(Duration::from_millis(50)); timeout = Duration::from_millis(10),
+            HttpMatcher::get("/slow") => || slow_response(Duration::from_millis(50)); timeout = Duration::from_millis(100),
+            HttpMatcher::get("/none") => || slow_response(Duration::from_millis(50)),
+            _ => StatusCode::NOT_FOUND,
+        };
+
+        let res = get_response(&svc, "https://www.test.io/fast").await;
+        assert_eq!(res.status(), StatusCode::REQUEST_TIMEOUT);
+
+        for uri in ["https://www.test.io/slow", "https://www.test.io/none"] {
+            let res = get_response(&svc, uri).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "done");
+        }
+    }
 }
@@ -0,0 +1,24 @@
+/// Hash the given bytes using the 64-bit FNV-1a hash function.
+///
+/// Unlike the std [`RandomState`], the hash is stable across processes
+/// (and versions), such that it can be used for values that are exposed
+/// or have to be consistent between instances, e.g. `ETag`s.
+///
+/// [`RandomState`]: std::collections::hash_map::RandomState
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+}
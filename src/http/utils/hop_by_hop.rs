@@ -0,0 +1,113 @@
+use crate::http::{header, HeaderMap, HeaderName};
+
+/// The hop-by-hop headers, which are meaningful only for a single connection
+/// and must not be forwarded by proxies (RFC 9110, section 7.6.1).
+///
+/// `Proxy-Authenticate` and `Proxy-Authorization` are included as well,
+/// as they are intended for the proxy itself.
+static HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Returns `true` if the given header is a standard hop-by-hop header.
+///
+/// Note that a message can declare additional hop-by-hop headers,
+/// by listing them in its `Connection` header.
+pub fn is_hop_by_hop_header(name: &HeaderName) -> bool {
+    HOP_BY_HOP_HEADERS.contains(name)
+}
+
+/// Remove the hop-by-hop headers from the given headers, such that
+/// the remaining (end-to-end) headers can be forwarded to the next hop.
+///
+/// This removes the standard hop-by-hop headers (e.g. `Connection`, `Keep-Alive`,
+/// `Transfer-Encoding` and `Upgrade`), as well as any header listed in the `Connection` header.
+pub fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in listed.iter().chain(HOP_BY_HOP_HEADERS.iter()) {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test_remove_standard_hop_by_hop_headers() {
+        let mut map = headers(&[
+            ("connection", "keep-alive"),
+            ("keep-alive", "timeout=5"),
+            ("transfer-encoding", "chunked"),
+            ("upgrade", "websocket"),
+            ("te", "trailers"),
+            ("trailer", "x-checksum"),
+            ("proxy-authorization", "Basic am9objpzZWNyZXQ="),
+            ("content-type", "text/plain"),
+            ("x-request-id", "42"),
+        ]);
+        remove_hop_by_hop_headers(&mut map);
+
+        assert_eq!(
+            map,
+            headers(&[("content-type", "text/plain"), ("x-request-id", "42")])
+        );
+    }
+
+    #[test]
+    fn test_remove_connection_listed_headers() {
+        let mut map = headers(&[
+            ("connection", "close, X-Foo"),
+            ("connection", " x-bar ,, invalid header"),
+            ("x-foo", "1"),
+            ("x-bar", "2"),
+            ("x-bar", "3"),
+            ("x-baz", "4"),
+            ("accept", "*/*"),
+        ]);
+        remove_hop_by_hop_headers(&mut map);
+
+        assert_eq!(map, headers(&[("x-baz", "4"), ("accept", "*/*")]));
+    }
+
+    #[test]
+    fn test_end_to_end_headers_survive() {
+        let expected = headers(&[
+            ("content-length", "5"),
+            ("cache-control", "no-cache"),
+            ("authorization", "Bearer token"),
+        ]);
+        let mut map = expected.clone();
+        remove_hop_by_hop_headers(&mut map);
+        assert_eq!(map, expected);
+
+        assert!(is_hop_by_hop_header(&header::CONNECTION));
+        assert!(is_hop_by_hop_header(&HeaderName::from_static("keep-alive")));
+        assert!(!is_hop_by_hop_header(&header::CONTENT_LENGTH));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// A map bounded by a capacity, evicting its least recently used entry once full.
+///
+/// The recency of the entries is tracked using an increasing tick,
+/// such that the least recently used entry is found in logarithmic time.
+#[derive(Debug)]
+pub(crate) struct LruMap<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    recency: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K, V> Default for LruMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<K, V> LruMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Create a new, empty, [`LruMap`].
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries in the map.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get the value of the given key, marking it as the most recently used entry.
+    pub(crate) fn get<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (value, last_used) = self.entries.get_mut(key)?;
+        self.tick += 1;
+        if let Some(key) = self.recency.remove(last_used) {
+            self.recency.insert(self.tick, key);
+        }
+        *last_used = self.tick;
+        Some(value)
+    }
+
+    /// Insert the given value as the most recently used entry, returning the previous value
+    /// of its key (if any). The least recently used entry is evicted in case the map holds
+    /// `capacity` entries already, and nothing is inserted in case the capacity is `0`.
+    pub(crate) fn insert(&mut self, key: K, value: V, capacity: usize) -> Option<V> {
+        let previous = self.remove(&key);
+        if capacity == 0 {
+            return previous;
+        }
+        while self.entries.len() >= capacity {
+            match self.recency.pop_first() {
+                Some((_, evicted)) => self.entries.remove(&evicted),
+                None => break,
+            };
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+        previous
+    }
+
+    /// Remove the given key, returning its value (if any).
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (value, last_used) = self.entries.remove(key)?;
+        self.recency.remove(&last_used);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_map() {
+        let mut map = LruMap::new();
+        assert_eq!(map.insert("a".to_owned(), 1, 2), None);
+        assert_eq!(map.insert("b".to_owned(), 2, 2), None);
+
+        // use a, such that b is the least recently used entry
+        assert_eq!(map.get("a").copied(), Some(1));
+        assert_eq!(map.insert("c".to_owned(), 3, 2), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("b"), None);
+
+        // replacing a value does not evict another entry
+        assert_eq!(map.insert("c".to_owned(), 4, 2), Some(3));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("a").copied(), Some(1));
+        assert_eq!(map.get("c").copied(), Some(4));
+
+        assert_eq!(map.remove("a"), Some(1));
+        assert_eq!(map.len(), 1);
+
+        // a capacity of zero stores nothing
+        assert_eq!(map.insert("d".to_owned(), 5, 0), None);
+        assert_eq!(map.get("d"), None);
+    }
+}
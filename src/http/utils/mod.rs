@@ -1,4 +1,13 @@
 //! Utilities for HTTP.
 
+mod hash;
+pub(crate) use hash::fnv1a;
+
 mod header_value;
 pub use header_value::{HeaderValueErr, HeaderValueGetter};
+
+mod lru;
+pub(crate) use lru::LruMap;
+
+mod hop_by_hop;
+pub use hop_by_hop::{is_hop_by_hop_header, remove_hop_by_hop_headers};
@@ -121,6 +121,13 @@ pub mod stream;
 
 pub mod tcp;
 
+pub mod udp;
+
+#[cfg(unix)]
+pub mod uds;
+
+pub mod net;
+
 pub mod tls;
 
 pub mod http;
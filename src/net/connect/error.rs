@@ -0,0 +1,54 @@
+use crate::http::StatusCode;
+
+/// Error type returned by the connectors in this module.
+#[derive(Debug)]
+pub enum ConnectError {
+    /// An IO error occurred while connecting to the target or proxy,
+    /// or while performing the proxy handshake.
+    Io(std::io::Error),
+    /// The upstream HTTP proxy responded to the `CONNECT` request
+    /// with a non-2xx status code.
+    HttpProxyRejected(StatusCode),
+    /// The upstream HTTP proxy responded with an invalid `CONNECT` response.
+    InvalidHttpProxyResponse,
+    /// The SOCKS5 proxy did not accept any of the offered authentication methods,
+    /// or rejected the given credentials.
+    Socks5AuthFailed,
+    /// The SOCKS5 proxy replied to the `CONNECT` command
+    /// with the given (non-success) reply code.
+    Socks5Rejected(u8),
+    /// The SOCKS5 proxy responded with an invalid message.
+    InvalidSocks5Response,
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::Io(err) => write!(f, "io error: {}", err),
+            ConnectError::HttpProxyRejected(status) => {
+                write!(f, "http proxy rejected connect: {}", status)
+            }
+            ConnectError::InvalidHttpProxyResponse => write!(f, "invalid http proxy response"),
+            ConnectError::Socks5AuthFailed => write!(f, "socks5 proxy authentication failed"),
+            ConnectError::Socks5Rejected(reply) => {
+                write!(f, "socks5 proxy rejected connect: reply code {}", reply)
+            }
+            ConnectError::InvalidSocks5Response => write!(f, "invalid socks5 proxy response"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ConnectError {
+    fn from(err: std::io::Error) -> Self {
+        ConnectError::Io(err)
+    }
+}
@@ -0,0 +1,242 @@
+use super::{ConnectError, ConnectTarget, TcpConnector};
+use crate::{
+    http::{HeaderValue, StatusCode},
+    service::{Context, Service},
+};
+use headers::authorization::{Authorization, Credentials};
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The maximum size of the response head of a `CONNECT` request.
+const MAX_RESPONSE_HEAD_SIZE: usize = 8 * 1024;
+
+/// A connector which establishes the connection to the target
+/// through an upstream HTTP proxy, using a `CONNECT` request.
+///
+/// The connection to the proxy itself is established using the inner connector,
+/// which is a [`TcpConnector`] by default. Once the proxy accepted the `CONNECT` request,
+/// the tunneled stream to the target is returned.
+///
+/// A non-2xx response of the proxy results in a [`ConnectError::HttpProxyRejected`] error.
+#[derive(Clone)]
+pub struct HttpProxyConnector<C = TcpConnector> {
+    inner: C,
+    proxy: ConnectTarget,
+    authorization: Option<HeaderValue>,
+}
+
+impl HttpProxyConnector {
+    /// Create a new [`HttpProxyConnector`] which connects through the given proxy,
+    /// connecting to the proxy using a [`TcpConnector`].
+    pub fn new(proxy: ConnectTarget) -> Self {
+        Self::with_connector(TcpConnector::new(), proxy)
+    }
+}
+
+impl<C> HttpProxyConnector<C> {
+    /// Create a new [`HttpProxyConnector`] which connects through the given proxy,
+    /// connecting to the proxy using the given connector.
+    pub fn with_connector(inner: C, proxy: ConnectTarget) -> Self {
+        Self {
+            inner,
+            proxy,
+            authorization: None,
+        }
+    }
+
+    /// Authenticate with the proxy using the given Basic credentials.
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.authorization = Some(Authorization::basic(username, password).0.encode());
+        self
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for HttpProxyConnector<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpProxyConnector")
+            .field("inner", &self.inner)
+            .field("proxy", &self.proxy)
+            .field("authorization", &self.authorization.is_some())
+            .finish()
+    }
+}
+
+impl<State, C> Service<State, ConnectTarget> for HttpProxyConnector<C>
+where
+    State: Send + Sync + 'static,
+    C: Service<State, ConnectTarget, Error = ConnectError>,
+    C::Response: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = C::Response;
+    type Error = ConnectError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        target: ConnectTarget,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut stream = self.inner.serve(ctx, self.proxy.clone()).await?;
+
+        let mut request = format!(
+            "CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n",
+            target = target
+        )
+        .into_bytes();
+        if let Some(authorization) = &self.authorization {
+            request.extend_from_slice(b"Proxy-Authorization: ");
+            request.extend_from_slice(authorization.as_bytes());
+            request.extend_from_slice(b"\r\n");
+        }
+        request.extend_from_slice(b"\r\n");
+        stream.write_all(&request).await?;
+        stream.flush().await?;
+
+        let status = read_response_status(&mut stream).await?;
+        if !status.is_success() {
+            return Err(ConnectError::HttpProxyRejected(status));
+        }
+        Ok(stream)
+    }
+}
+
+/// Read the response head of the `CONNECT` request, returning its status code.
+///
+/// The head is read byte per byte, such that no data of the tunnel is consumed.
+async fn read_response_status<IO>(stream: &mut IO) -> Result<StatusCode, ConnectError>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut head = Vec::with_capacity(256);
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD_SIZE {
+            return Err(ConnectError::InvalidHttpProxyResponse);
+        }
+        head.push(stream.read_u8().await?);
+    }
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut response = httparse::Response::new(&mut headers);
+    match response.parse(&head) {
+        Ok(httparse::Status::Complete(_)) => response
+            .code
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .ok_or(ConnectError::InvalidHttpProxyResponse),
+        _ => Err(ConnectError::InvalidHttpProxyResponse),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::client::HttpClient;
+    use crate::http::dep::http_body_util::BodyExt;
+    use crate::http::{Body, Request};
+    use crate::stream::{self, AdaptiveBuffer};
+    use crate::test_helpers::net::{read_http_head, spawn_http_backend};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Spawn a fake proxy, which accepts `CONNECT` requests
+    /// with the given (optional) proxy authorization and bridges them to their target.
+    async fn spawn_proxy(authorization: Option<&'static str>) -> ConnectTarget {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let head = read_http_head(&mut stream).await;
+                    let mut headers = [httparse::EMPTY_HEADER; 16];
+                    let mut request = httparse::Request::new(&mut headers);
+                    request.parse(&head).unwrap();
+                    assert_eq!(request.method, Some("CONNECT"));
+
+                    let provided = request
+                        .headers
+                        .iter()
+                        .find(|h| h.name.eq_ignore_ascii_case("proxy-authorization"))
+                        .map(|h| std::str::from_utf8(h.value).unwrap());
+                    if provided != authorization {
+                        stream
+                            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                            .await
+                            .unwrap();
+                        return;
+                    }
+
+                    let mut backend = TcpStream::connect(request.path.unwrap()).await.unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await
+                        .unwrap();
+                    let _ = stream::copy_bidirectional(
+                        &mut stream,
+                        &mut backend,
+                        &mut AdaptiveBuffer::default(),
+                        &mut AdaptiveBuffer::default(),
+                    )
+                    .await;
+                });
+            }
+        });
+        addr.into()
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_connector() {
+        let proxy = spawn_proxy(None).await;
+        let backend: ConnectTarget = spawn_http_backend().await.into();
+
+        let mut stream = HttpProxyConnector::new(proxy)
+            .serve(Context::default(), backend)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_connector_basic_auth() {
+        let proxy = spawn_proxy(Some("Basic am9objpzZWNyZXQ=")).await;
+        let backend: ConnectTarget = spawn_http_backend().await.into();
+
+        HttpProxyConnector::new(proxy.clone())
+            .basic_auth("john", "secret")
+            .serve(Context::default(), backend.clone())
+            .await
+            .unwrap();
+
+        for connector in [
+            HttpProxyConnector::new(proxy.clone()),
+            HttpProxyConnector::new(proxy).basic_auth("john", "wrong"),
+        ] {
+            match connector.serve(Context::default(), backend.clone()).await {
+                Err(ConnectError::HttpProxyRejected(status)) => {
+                    assert_eq!(status, StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+                }
+                result => panic!("unexpected result: {:?}", result.map(|_| ())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_client_with_http_proxy_connector() {
+        let proxy = spawn_proxy(None).await;
+        let backend: ConnectTarget = spawn_http_backend().await.into();
+
+        let client = HttpClient::with_connector(HttpProxyConnector::new(proxy));
+        let request = Request::builder()
+            .uri(format!("http://{}/", backend))
+            .body(Body::empty())
+            .unwrap();
+        let response = client.serve(Context::default(), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"hello");
+    }
+}
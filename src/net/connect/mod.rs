@@ -0,0 +1,28 @@
+//! Connectors to establish (outbound) connections to a target.
+//!
+//! A connector is a [`Service`] which takes a [`ConnectTarget`] as its request
+//! and returns the established stream as its response, failing with a [`ConnectError`].
+//! This allows connectors to be layered and to be used by clients such as the [`HttpClient`].
+//!
+//! [`Service`]: crate::service::Service
+//! [`HttpClient`]: crate::http::client::HttpClient
+
+mod target;
+#[doc(inline)]
+pub use target::{ConnectTarget, InvalidConnectTarget};
+
+mod error;
+#[doc(inline)]
+pub use error::ConnectError;
+
+mod tcp;
+#[doc(inline)]
+pub use tcp::TcpConnector;
+
+mod http_proxy;
+#[doc(inline)]
+pub use http_proxy::HttpProxyConnector;
+
+mod socks5;
+#[doc(inline)]
+pub use socks5::Socks5Connector;
@@ -0,0 +1,419 @@
+use super::{ConnectError, ConnectTarget, TcpConnector};
+use crate::service::{Context, Service};
+use std::{fmt, io, net::IpAddr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_USERNAME_PASSWORD: u8 = 0x02;
+const METHOD_NOT_ACCEPTABLE: u8 = 0xff;
+
+const CMD_CONNECT: u8 = 0x01;
+
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+const REPLY_SUCCEEDED: u8 = 0x00;
+
+/// A connector which establishes the connection to the target
+/// through an upstream SOCKS5 proxy (RFC 1928), using the `CONNECT` command.
+///
+/// The connection to the proxy itself is established using the inner connector,
+/// which is a [`TcpConnector`] by default. Once the proxy accepted the `CONNECT` command,
+/// the tunneled stream to the target is returned.
+///
+/// By default domain names are resolved locally, and the proxy is asked to connect
+/// to the resolved IP address. Use [`Socks5Connector::resolve_on_proxy`]
+/// to let the proxy resolve the domain name instead (also known as `socks5h`).
+#[derive(Clone)]
+pub struct Socks5Connector<C = TcpConnector> {
+    inner: C,
+    proxy: ConnectTarget,
+    credentials: Option<(String, String)>,
+    resolve_on_proxy: bool,
+}
+
+impl Socks5Connector {
+    /// Create a new [`Socks5Connector`] which connects through the given proxy,
+    /// connecting to the proxy using a [`TcpConnector`].
+    pub fn new(proxy: ConnectTarget) -> Self {
+        Self::with_connector(TcpConnector::new(), proxy)
+    }
+}
+
+impl<C> Socks5Connector<C> {
+    /// Create a new [`Socks5Connector`] which connects through the given proxy,
+    /// connecting to the proxy using the given connector.
+    pub fn with_connector(inner: C, proxy: ConnectTarget) -> Self {
+        Self {
+            inner,
+            proxy,
+            credentials: None,
+            resolve_on_proxy: false,
+        }
+    }
+
+    /// Authenticate with the proxy using the given username and password (RFC 1929).
+    ///
+    /// Without credentials only the "no authentication" method is offered to the proxy.
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Let the proxy resolve the domain name of the target (`socks5h`),
+    /// instead of resolving it locally (`socks5`).
+    pub fn resolve_on_proxy(mut self, resolve_on_proxy: bool) -> Self {
+        self.resolve_on_proxy = resolve_on_proxy;
+        self
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for Socks5Connector<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Socks5Connector")
+            .field("inner", &self.inner)
+            .field("proxy", &self.proxy)
+            .field("credentials", &self.credentials.is_some())
+            .field("resolve_on_proxy", &self.resolve_on_proxy)
+            .finish()
+    }
+}
+
+impl<State, C> Service<State, ConnectTarget> for Socks5Connector<C>
+where
+    State: Send + Sync + 'static,
+    C: Service<State, ConnectTarget, Error = ConnectError>,
+    C::Response: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = C::Response;
+    type Error = ConnectError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        target: ConnectTarget,
+    ) -> Result<Self::Response, Self::Error> {
+        let address = self.target_address(&target).await?;
+
+        let mut stream = self.inner.serve(ctx, self.proxy.clone()).await?;
+        self.authenticate(&mut stream).await?;
+
+        let mut request = vec![VERSION, CMD_CONNECT, 0x00];
+        address.encode(&mut request);
+        request.extend_from_slice(&target.port().to_be_bytes());
+        stream.write_all(&request).await?;
+        stream.flush().await?;
+
+        let [version, reply, _reserved, atyp] = read_array(&mut stream).await?;
+        if version != VERSION {
+            return Err(ConnectError::InvalidSocks5Response);
+        }
+        if reply != REPLY_SUCCEEDED {
+            return Err(ConnectError::Socks5Rejected(reply));
+        }
+        // the bound address is of no use for a connect, but has to be consumed
+        let len = match atyp {
+            ATYP_IPV4 => 4,
+            ATYP_IPV6 => 16,
+            ATYP_DOMAIN => stream.read_u8().await? as usize,
+            _ => return Err(ConnectError::InvalidSocks5Response),
+        };
+        let mut bound = vec![0u8; len + 2];
+        stream.read_exact(&mut bound).await?;
+
+        Ok(stream)
+    }
+}
+
+impl<C> Socks5Connector<C> {
+    async fn target_address(&self, target: &ConnectTarget) -> Result<Address, ConnectError> {
+        if let Some(ip) = target.ip_addr() {
+            return Ok(Address::Ip(ip));
+        }
+        if self.resolve_on_proxy {
+            return if target.host().len() <= u8::MAX as usize {
+                Ok(Address::Domain(target.host().to_owned()))
+            } else {
+                Err(
+                    io::Error::new(io::ErrorKind::InvalidInput, "socks5: domain name too long")
+                        .into(),
+                )
+            };
+        }
+        tokio::net::lookup_host((target.host(), target.port()))
+            .await?
+            .next()
+            .map(|addr| Address::Ip(addr.ip()))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("socks5: no addresses found for {}", target.host()),
+                )
+                .into()
+            })
+    }
+
+    async fn authenticate<IO>(&self, stream: &mut IO) -> Result<(), ConnectError>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let greeting: &[u8] = match self.credentials {
+            Some(_) => &[VERSION, 2, METHOD_NO_AUTH, METHOD_USERNAME_PASSWORD],
+            None => &[VERSION, 1, METHOD_NO_AUTH],
+        };
+        stream.write_all(greeting).await?;
+        stream.flush().await?;
+
+        let [version, method] = read_array(stream).await?;
+        if version != VERSION {
+            return Err(ConnectError::InvalidSocks5Response);
+        }
+        match (method, &self.credentials) {
+            (METHOD_NO_AUTH, _) => Ok(()),
+            (METHOD_USERNAME_PASSWORD, Some((username, password))) => {
+                let (username, password) = (username.as_bytes(), password.as_bytes());
+                if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "socks5: username or password too long",
+                    )
+                    .into());
+                }
+                let mut request = Vec::with_capacity(3 + username.len() + password.len());
+                request.push(AUTH_VERSION);
+                request.push(username.len() as u8);
+                request.extend_from_slice(username);
+                request.push(password.len() as u8);
+                request.extend_from_slice(password);
+                stream.write_all(&request).await?;
+                stream.flush().await?;
+
+                match read_array(stream).await? {
+                    [AUTH_VERSION, 0x00] => Ok(()),
+                    [AUTH_VERSION, _] => Err(ConnectError::Socks5AuthFailed),
+                    _ => Err(ConnectError::InvalidSocks5Response),
+                }
+            }
+            (METHOD_NOT_ACCEPTABLE, _) => Err(ConnectError::Socks5AuthFailed),
+            _ => Err(ConnectError::InvalidSocks5Response),
+        }
+    }
+}
+
+/// The destination address of a SOCKS5 request.
+enum Address {
+    Ip(IpAddr),
+    Domain(String),
+}
+
+impl Address {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Address::Ip(IpAddr::V4(ip)) => {
+                buf.push(ATYP_IPV4);
+                buf.extend_from_slice(&ip.octets());
+            }
+            Address::Ip(IpAddr::V6(ip)) => {
+                buf.push(ATYP_IPV6);
+                buf.extend_from_slice(&ip.octets());
+            }
+            Address::Domain(domain) => {
+                buf.push(ATYP_DOMAIN);
+                buf.push(domain.len() as u8);
+                buf.extend_from_slice(domain.as_bytes());
+            }
+        }
+    }
+}
+
+async fn read_array<const N: usize, IO>(stream: &mut IO) -> io::Result<[u8; N]>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut buf = [0u8; N];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{
+        client::HttpClient, dep::http_body_util::BodyExt, Body, Request, StatusCode,
+    };
+    use crate::stream::{self, AdaptiveBuffer};
+    use crate::test_helpers::net::spawn_http_backend;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Spawn a fake SOCKS5 proxy, which requires the given (optional) credentials
+    /// and bridges the connections to the requested port on localhost.
+    ///
+    /// The requested destination addresses are recorded as `host:port`.
+    async fn spawn_proxy(
+        credentials: Option<(&'static str, &'static str)>,
+    ) -> (ConnectTarget, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let requests = requests.clone();
+                    tokio::spawn(async move {
+                        serve_socks5(&mut stream, credentials, requests).await;
+                    });
+                }
+            }
+        });
+        (addr.into(), requests)
+    }
+
+    async fn serve_socks5(
+        stream: &mut TcpStream,
+        credentials: Option<(&str, &str)>,
+        requests: Arc<Mutex<Vec<String>>>,
+    ) {
+        let [version, n] = read_array(stream).await.unwrap();
+        assert_eq!(version, VERSION);
+        let mut methods = vec![0u8; n as usize];
+        stream.read_exact(&mut methods).await.unwrap();
+
+        match credentials {
+            None => stream.write_all(&[VERSION, METHOD_NO_AUTH]).await.unwrap(),
+            Some(_) if !methods.contains(&METHOD_USERNAME_PASSWORD) => {
+                stream
+                    .write_all(&[VERSION, METHOD_NOT_ACCEPTABLE])
+                    .await
+                    .unwrap();
+                return;
+            }
+            Some((username, password)) => {
+                stream
+                    .write_all(&[VERSION, METHOD_USERNAME_PASSWORD])
+                    .await
+                    .unwrap();
+                let [_, len] = read_array(stream).await.unwrap();
+                let mut user = vec![0u8; len as usize];
+                stream.read_exact(&mut user).await.unwrap();
+                let len = stream.read_u8().await.unwrap();
+                let mut pass = vec![0u8; len as usize];
+                stream.read_exact(&mut pass).await.unwrap();
+                if user != username.as_bytes() || pass != password.as_bytes() {
+                    stream.write_all(&[AUTH_VERSION, 0x01]).await.unwrap();
+                    return;
+                }
+                stream.write_all(&[AUTH_VERSION, 0x00]).await.unwrap();
+            }
+        }
+
+        let [_, cmd, _, atyp] = read_array(stream).await.unwrap();
+        assert_eq!(cmd, CMD_CONNECT);
+        let host = match atyp {
+            ATYP_IPV4 => IpAddr::from(read_array::<4, _>(stream).await.unwrap()).to_string(),
+            ATYP_IPV6 => format!(
+                "[{}]",
+                IpAddr::from(read_array::<16, _>(stream).await.unwrap())
+            ),
+            ATYP_DOMAIN => {
+                let len = stream.read_u8().await.unwrap();
+                let mut domain = vec![0u8; len as usize];
+                stream.read_exact(&mut domain).await.unwrap();
+                String::from_utf8(domain).unwrap()
+            }
+            _ => panic!("unexpected address type"),
+        };
+        let port = stream.read_u16().await.unwrap();
+        requests.lock().unwrap().push(format!("{}:{}", host, port));
+
+        let mut backend = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream
+            .write_all(&[VERSION, REPLY_SUCCEEDED, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        let _ = stream::copy_bidirectional(
+            stream,
+            &mut backend,
+            &mut AdaptiveBuffer::default(),
+            &mut AdaptiveBuffer::default(),
+        )
+        .await;
+    }
+
+    async fn get_hello<C>(connector: C, target: ConnectTarget)
+    where
+        C: Service<(), ConnectTarget, Error = ConnectError>,
+        C::Response: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let client = HttpClient::with_connector(connector);
+        let request = Request::builder()
+            .uri(format!("http://{}/", target))
+            .body(Body::empty())
+            .unwrap();
+        let response = client.serve(Context::default(), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connector_no_auth() {
+        let (proxy, requests) = spawn_proxy(None).await;
+        let backend = spawn_http_backend().await;
+
+        get_hello(Socks5Connector::new(proxy), backend.into()).await;
+        assert_eq!(*requests.lock().unwrap(), vec![backend.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connector_username_password() {
+        let (proxy, requests) = spawn_proxy(Some(("john", "secret"))).await;
+        let backend = spawn_http_backend().await;
+
+        get_hello(
+            Socks5Connector::new(proxy.clone()).credentials("john", "secret"),
+            backend.into(),
+        )
+        .await;
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        for connector in [
+            Socks5Connector::new(proxy.clone()).credentials("john", "wrong"),
+            Socks5Connector::new(proxy),
+        ] {
+            let err = connector
+                .serve(Context::default(), backend.into())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ConnectError::Socks5AuthFailed), "{err:?}");
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connector_resolve_on_proxy() {
+        let (proxy, requests) = spawn_proxy(None).await;
+        let port = spawn_http_backend().await.port();
+
+        get_hello(
+            Socks5Connector::new(proxy.clone()).resolve_on_proxy(true),
+            ConnectTarget::new("localhost", port),
+        )
+        .await;
+        get_hello(
+            Socks5Connector::new(proxy),
+            ConnectTarget::new("localhost", port),
+        )
+        .await;
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0], format!("localhost:{}", port));
+        assert_ne!(requests[1], format!("localhost:{}", port));
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+/// The target (host and port) to establish a connection to.
+///
+/// The host is either a domain name or an IP address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectTarget {
+    host: String,
+    port: u16,
+}
+
+impl ConnectTarget {
+    /// Create a new [`ConnectTarget`] for the given host and port.
+    ///
+    /// The host can be a domain name or an IP address,
+    /// where IPv6 addresses are optionally enclosed in brackets.
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        let host = host.into();
+        let host = match host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            Some(host) => host.to_owned(),
+            None => host,
+        };
+        Self { host, port }
+    }
+
+    /// The host of this target, without brackets in case of an IPv6 address.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The host of this target as an IP address, if it is one.
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+
+    /// The port of this target.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl From<SocketAddr> for ConnectTarget {
+    fn from(addr: SocketAddr) -> Self {
+        Self::new(addr.ip().to_string(), addr.port())
+    }
+}
+
+impl fmt::Display for ConnectTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl FromStr for ConnectTarget {
+    type Err = InvalidConnectTarget;
+
+    /// Parse a target of the form `host:port`, where IPv6 hosts
+    /// have to be enclosed in brackets (e.g. `[::1]:443`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = s.rsplit_once(':').ok_or(InvalidConnectTarget)?;
+        if host.is_empty() || (host.contains(':') && !host.starts_with('[')) {
+            return Err(InvalidConnectTarget);
+        }
+        let port = port.parse().map_err(|_| InvalidConnectTarget)?;
+        Ok(Self::new(host, port))
+    }
+}
+
+/// The error returned when parsing an invalid [`ConnectTarget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidConnectTarget;
+
+impl fmt::Display for InvalidConnectTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid connect target: expected host:port")
+    }
+}
+
+impl std::error::Error for InvalidConnectTarget {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_target_parse() {
+        for (input, host, port, display) in [
+            ("example.com:443", "example.com", 443, "example.com:443"),
+            ("127.0.0.1:80", "127.0.0.1", 80, "127.0.0.1:80"),
+            ("[::1]:8080", "::1", 8080, "[::1]:8080"),
+        ] {
+            let target: ConnectTarget = input.parse().unwrap();
+            assert_eq!(target.host(), host);
+            assert_eq!(target.port(), port);
+            assert_eq!(target.to_string(), display);
+        }
+
+        for input in ["example.com", ":443", "::1:80", "example.com:http"] {
+            assert!(input.parse::<ConnectTarget>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_connect_target_from_socket_addr() {
+        let target = ConnectTarget::from("[::1]:80".parse::<SocketAddr>().unwrap());
+        assert_eq!(target.ip_addr(), Some("::1".parse().unwrap()));
+        assert_eq!(target.to_string(), "[::1]:80");
+    }
+}
@@ -0,0 +1,245 @@
+use super::{ConnectError, ConnectTarget};
+use crate::service::{layer::budget::Deadline, Context, Service};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::net::{TcpSocket, TcpStream};
+
+/// A connector which establishes a TCP connection to the target.
+///
+/// Domain names are resolved using the system resolver,
+/// trying each of the resolved addresses until a connection is established.
+///
+/// By default the local address is picked by the operating system
+/// and no connect timeout is applied. Use [`TcpConnector::bind_address`]
+/// and [`TcpConnector::connect_timeout`] to configure these,
+/// and the other builder methods to configure the options of the socket.
+#[derive(Debug, Clone, Default)]
+pub struct TcpConnector {
+    bind_address: Option<SocketAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    bind_device: Option<Vec<u8>>,
+    connect_timeout: Option<Duration>,
+    nodelay: Option<bool>,
+    keepalive: Option<bool>,
+    recv_buffer_size: Option<u32>,
+    send_buffer_size: Option<u32>,
+}
+
+impl TcpConnector {
+    /// Create a new [`TcpConnector`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind the socket to the given local address before connecting.
+    ///
+    /// Use port `0` to let the operating system pick the local port.
+    /// Only resolved addresses of the same family (IPv4 or IPv6)
+    /// as the bind address are connected to.
+    pub fn bind_address(mut self, addr: SocketAddr) -> Self {
+        self.bind_address = Some(addr);
+        self
+    }
+
+    /// Bind the socket to the given network interface (e.g. `eth0`) before connecting,
+    /// using the `SO_BINDTODEVICE` socket option.
+    ///
+    /// This usually requires elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn bind_device(mut self, interface: impl Into<Vec<u8>>) -> Self {
+        self.bind_device = Some(interface.into());
+        self
+    }
+
+    /// Fail with a [`io::ErrorKind::TimedOut`] error in case the connection
+    /// could not be established within the given duration.
+    ///
+    /// The timeout applies to the connection as a whole,
+    /// including the resolving of the target's domain name.
+    ///
+    /// In case a [`Deadline`] is present in the [`Context`], the connect timeout
+    /// is shrunk to the time remaining until that deadline,
+    /// which also applies when no connect timeout is configured.
+    ///
+    /// [`Deadline`]: crate::service::layer::budget::Deadline
+    /// [`Context`]: crate::service::Context
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the `TCP_NODELAY` option of the socket.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Set the `SO_KEEPALIVE` option of the socket.
+    pub fn keepalive(mut self, keepalive: bool) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Set the `SO_RCVBUF` option of the socket.
+    pub fn recv_buffer_size(mut self, size: u32) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the `SO_SNDBUF` option of the socket.
+    pub fn send_buffer_size(mut self, size: u32) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    async fn connect(&self, target: &ConnectTarget) -> io::Result<TcpStream> {
+        let addrs: Vec<SocketAddr> = match target.ip_addr() {
+            Some(ip) => vec![SocketAddr::new(ip, target.port())],
+            None => tokio::net::lookup_host((target.host(), target.port()))
+                .await?
+                .collect(),
+        };
+
+        let mut last_err = None;
+        for addr in addrs {
+            if let Some(bind_address) = self.bind_address {
+                if bind_address.is_ipv4() != addr.is_ipv4() {
+                    continue;
+                }
+            }
+            match self.connect_addr(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "no (compatible) address found for the connect target",
+            )
+        }))
+    }
+
+    async fn connect_addr(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let socket = match addr.ip() {
+            IpAddr::V4(_) => TcpSocket::new_v4()?,
+            IpAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(interface) = &self.bind_device {
+            socket.bind_device(Some(interface))?;
+        }
+        if let Some(bind_address) = self.bind_address {
+            socket.bind(bind_address)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            socket.set_keepalive(keepalive)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        let stream = socket.connect(addr).await?;
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+        Ok(stream)
+    }
+}
+
+impl<State> Service<State, ConnectTarget> for TcpConnector
+where
+    State: Send + Sync + 'static,
+{
+    type Response = TcpStream;
+    type Error = ConnectError;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        target: ConnectTarget,
+    ) -> Result<Self::Response, Self::Error> {
+        let timeout = match (self.connect_timeout, ctx.get::<Deadline>()) {
+            (Some(timeout), Some(deadline)) => Some(deadline.clamp(timeout)),
+            (None, Some(deadline)) => Some(deadline.remaining()),
+            (timeout, None) => timeout,
+        };
+        let stream = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.connect(&target))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "tcp connect timed out"))??,
+            None => self.connect(&target).await?,
+        };
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::Socket;
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_tcp_connector() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = TcpConnector::new()
+            .connect_timeout(Duration::from_secs(5))
+            .nodelay(true)
+            .keepalive(true)
+            .serve(Context::default(), addr.into())
+            .await
+            .unwrap();
+        let (accepted, peer_addr) = listener.accept().await.unwrap();
+
+        assert_eq!(Socket::peer_addr(&stream).unwrap(), addr);
+        assert_eq!(Socket::local_addr(&stream).unwrap(), peer_addr);
+        assert_eq!(accepted.peer_addr().unwrap(), peer_addr);
+        assert!(stream.nodelay().unwrap());
+
+        // domain names are resolved
+        let target = ConnectTarget::new("localhost", addr.port());
+        let connector = TcpConnector::new().bind_address((Ipv4Addr::LOCALHOST, 0).into());
+        let stream = connector.serve(Context::default(), target).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connector_bind_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // reserve a free local port to bind to
+        let bind_address = {
+            let reserved = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            reserved.local_addr().unwrap()
+        };
+        let stream = TcpConnector::new()
+            .bind_address(bind_address)
+            .serve(Context::default(), addr.into())
+            .await
+            .unwrap();
+        assert_eq!(stream.local_addr().unwrap(), bind_address);
+
+        let (_, peer_addr) = listener.accept().await.unwrap();
+        assert_eq!(peer_addr, bind_address);
+
+        // an IPv6 bind address cannot be used to connect to an IPv4 target
+        let result = TcpConnector::new()
+            .bind_address("[::1]:0".parse().unwrap())
+            .serve(Context::default(), addr.into())
+            .await;
+        match result {
+            Err(ConnectError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::AddrNotAvailable),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+//! Network utilities for Rama.
+
+pub mod connect;
@@ -3,8 +3,8 @@
 use super::{
     handler::{Factory, ServiceFn},
     layer::{
-        layer_fn, AndThenLayer, Identity, LayerFn, MapErrLayer, MapRequestLayer, MapResponseLayer,
-        MapResultLayer, MapStateLayer, Stack, ThenLayer, TraceErrLayer,
+        layer_fn, AndThenLayer, Identity, LayerFn, LayerVec, MapErrLayer, MapRequestLayer,
+        MapResponseLayer, MapResultLayer, MapStateLayer, Stack, ThenLayer, TraceErrLayer,
     },
     service_fn,
     util::combinators::Either,
@@ -76,6 +76,24 @@ impl<L> ServiceBuilder<L> {
         self.layer(layer)
     }
 
+    /// Add a homogeneous collection of layers into the [`ServiceBuilder`],
+    /// applied in the order in which they are yielded by the iterator.
+    ///
+    /// This is equivalent to fluently chaining [`ServiceBuilder::layer`] for each of the layers,
+    /// which is useful in case the layers are only known at runtime.
+    /// Use [`BoxLayer`] to collect layers of different types.
+    ///
+    /// See [`LayerVec`] for more details.
+    ///
+    /// [`BoxLayer`]: crate::service::layer::BoxLayer
+    /// [`LayerVec`]: crate::service::layer::LayerVec
+    pub fn layer_iter<I>(self, layers: I) -> ServiceBuilder<Stack<LayerVec<I::Item>, L>>
+    where
+        I: IntoIterator,
+    {
+        self.layer(LayerVec::new(layers))
+    }
+
     /// Add a [`Layer`] built from a function that accepts a service and returns another service.
     ///
     /// See the documentation for [`layer_fn`] for more details.
@@ -184,6 +202,36 @@ impl<L> ServiceBuilder<L> {
         self.layer(MapResultLayer::new(f))
     }
 
+    /// Describe the layers added to this [`ServiceBuilder`], ordered from the outermost
+    /// layer (the first one added, which sees the request first) to the innermost layer.
+    ///
+    /// This is useful to diagnose ordering issues in large stacks of layers,
+    /// see [`StackDescription`] for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rama::service::{layer::{BudgetLayer, TraceErrLayer}, ServiceBuilder};
+    /// use std::time::Duration;
+    ///
+    /// let builder = ServiceBuilder::new()
+    ///     .layer(TraceErrLayer::new())
+    ///     .layer(BudgetLayer::new(Duration::from_secs(30)));
+    ///
+    /// assert_eq!(
+    ///     builder.describe().to_string(),
+    ///     "TraceErrLayer -> BudgetLayer",
+    /// );
+    /// ```
+    pub fn describe(&self) -> StackDescription
+    where
+        L: DescribeLayers,
+    {
+        let mut layers = Vec::new();
+        L::describe_layers(&mut layers);
+        StackDescription { layers }
+    }
+
     /// Returns the underlying `Layer` implementation.
     pub fn into_inner(self) -> L {
         self.layer
@@ -252,6 +300,93 @@ impl<L: fmt::Debug> fmt::Debug for ServiceBuilder<L> {
     }
 }
 
+/// A stack of layers composed by a [`ServiceBuilder`],
+/// which can describe the layers it is made of.
+///
+/// See [`ServiceBuilder::describe`] for more details.
+pub trait DescribeLayers {
+    /// Append the type names of the layers of this stack to the given list,
+    /// from the outermost to the innermost layer.
+    fn describe_layers(layers: &mut Vec<&'static str>);
+}
+
+impl DescribeLayers for Identity {
+    fn describe_layers(_layers: &mut Vec<&'static str>) {}
+}
+
+impl<Inner, Outer> DescribeLayers for Stack<Inner, Outer>
+where
+    Outer: DescribeLayers,
+{
+    fn describe_layers(layers: &mut Vec<&'static str>) {
+        Outer::describe_layers(layers);
+        layers.push(std::any::type_name::<Inner>());
+    }
+}
+
+/// A description of the layers composed by a [`ServiceBuilder`],
+/// ordered from the outermost to the innermost layer.
+///
+/// The [`Display`] implementation lists the type names of the layers without
+/// their module paths (e.g. `TraceErrLayer -> BudgetLayer`),
+/// while [`StackDescription::layers`] returns their full type names.
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackDescription {
+    layers: Vec<&'static str>,
+}
+
+impl StackDescription {
+    /// The full type names of the layers, ordered from the outermost to the innermost layer.
+    pub fn layers(&self) -> &[&'static str] {
+        &self.layers
+    }
+
+    /// The type names of the layers without their module paths,
+    /// ordered from the outermost to the innermost layer.
+    pub fn short_names(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .map(|name| short_type_name(name))
+            .collect()
+    }
+}
+
+impl fmt::Display for StackDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, name) in self.layers.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" -> ")?;
+            }
+            f.write_str(&short_type_name(name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Strip the module paths of all types found in the given type name,
+/// e.g. `a::Foo<b::Bar>` becomes `Foo<Bar>`.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                short.truncate(segment_start);
+            }
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | '&' | ';' | '*' => {
+                short.push(c);
+                segment_start = short.len();
+            }
+            _ => short.push(c),
+        }
+    }
+    short
+}
+
 impl<S, L> Layer<S> for ServiceBuilder<L>
 where
     L: Layer<S>,
@@ -269,7 +404,7 @@ mod test {
     use std::convert::Infallible;
     use std::future::Future;
 
-    use crate::service::{Context, Service};
+    use crate::service::{layer::BoxLayer, Context, Service};
 
     use super::*;
 
@@ -337,4 +472,115 @@ mod test {
             .await;
         assert_eq!(res, Ok("OLA MUNDO".to_owned()));
     }
+
+    type Trail = Vec<&'static str>;
+
+    fn tag_request(
+        name: &'static str,
+    ) -> MapRequestLayer<impl FnOnce(Trail) -> Trail + Clone + Send + Sync + 'static> {
+        MapRequestLayer::new(move |mut trail: Trail| {
+            trail.push(name);
+            trail
+        })
+    }
+
+    fn tag_response(
+        name: &'static str,
+    ) -> MapResponseLayer<impl FnOnce(Trail) -> Trail + Clone + Send + Sync + 'static> {
+        MapResponseLayer::new(move |mut trail: Trail| {
+            trail.push(name);
+            trail
+        })
+    }
+
+    fn echo() -> BoxService<(), Trail, Trail, Infallible> {
+        service_fn(|trail: Trail| async move { Ok::<_, Infallible>(trail) }).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_layer_iter_order_matches_fluent_chaining() {
+        let fluent = ServiceBuilder::new()
+            .layer(tag_request("a"))
+            .layer(tag_response("x"))
+            .layer(tag_request("b"))
+            .layer(tag_response("y"))
+            .service(echo());
+        let expected = vec!["a", "b", "y", "x"];
+        let res = fluent.serve(Context::default(), Vec::new()).await;
+        assert_eq!(res, Ok(expected.clone()));
+
+        // build the same list of layers at runtime
+        let mut layers = Vec::new();
+        for name in ["a", "b"] {
+            layers.push(BoxLayer::new(tag_request(name)));
+            layers.push(BoxLayer::new(tag_response(match name {
+                "a" => "x",
+                _ => "y",
+            })));
+        }
+        let dynamic = ServiceBuilder::new().layer_iter(layers).service(echo());
+        let res = dynamic.serve(Context::default(), Vec::new()).await;
+        assert_eq!(res, Ok(expected));
+    }
+
+    #[tokio::test]
+    async fn test_layer_iter_combined_with_fluent_chaining() {
+        let layers: LayerVec<_> = ["b", "c"]
+            .into_iter()
+            .map(|name| BoxLayer::new(tag_request(name)))
+            .collect();
+        assert_eq!(layers.len(), 2);
+
+        let service = ServiceBuilder::new()
+            .layer(tag_request("a"))
+            .layer(layers.clone())
+            .layer(BoxLayer::new(tag_request("d")))
+            .service(echo());
+        let res = service.serve(Context::default(), Vec::new()).await;
+        assert_eq!(res, Ok(vec!["a", "b", "c", "d"]));
+
+        // an empty collection of layers is a no-op
+        let service = ServiceBuilder::new()
+            .layer_iter(Vec::<BoxLayer<_, _, _, _>>::new())
+            .service(echo());
+        let res = service.serve(Context::default(), vec!["z"]).await;
+        assert_eq!(res, Ok(vec!["z"]));
+    }
+
+    #[test]
+    fn test_describe() {
+        use crate::service::layer::{AddExtensionLayer, BudgetLayer, TimeoutLayer};
+        use std::time::Duration;
+
+        let builder = ServiceBuilder::new()
+            .trace_err()
+            .layer(BudgetLayer::new(Duration::from_secs(1)))
+            .layer_iter(vec![AddExtensionLayer::new(1u32)])
+            .option_layer(Some(TimeoutLayer::new(Duration::from_secs(1))));
+
+        let description = builder.describe();
+        assert_eq!(
+            description.short_names(),
+            [
+                "TraceErrLayer",
+                "BudgetLayer",
+                "LayerVec<AddExtensionLayer<u32>>",
+                "Either<TimeoutLayer<LayerErrorStatic<Elapsed>>, Identity>",
+            ]
+        );
+        assert_eq!(
+            description.to_string(),
+            "TraceErrLayer -> BudgetLayer -> LayerVec<AddExtensionLayer<u32>> \
+             -> Either<TimeoutLayer<LayerErrorStatic<Elapsed>>, Identity>"
+        );
+        assert_eq!(
+            description.layers()[..2],
+            [
+                "rama::service::layer::trace_err::TraceErrLayer",
+                "rama::service::layer::budget::BudgetLayer",
+            ]
+        );
+
+        assert_eq!(ServiceBuilder::new().describe().to_string(), "");
+    }
 }
@@ -125,6 +125,8 @@ mod extensions;
 pub use extensions::Extensions;
 
 mod state;
+#[doc(inline)]
+pub use state::SharedState;
 
 /// Context passed to and between services as input.
 ///
@@ -351,6 +353,45 @@ impl<S> Context<S> {
         self.executor.guard()
     }
 
+    /// Returns a future which resolves once the graceful shutdown is triggered,
+    /// such that custom services and accept loops can `select!` on it
+    /// alongside their own work.
+    ///
+    /// The future only holds a weak guard, so it does not delay the shutdown itself.
+    /// In case the context was not created within a graceful environment,
+    /// the future never resolves.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rama::graceful::Shutdown;
+    /// use rama::rt::Executor;
+    /// use rama::service::Context;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let shutdown = Shutdown::new(async {});
+    /// let ctx = Context::new(Arc::new(()), Executor::graceful(shutdown.guard()));
+    ///
+    /// tokio::select! {
+    ///     _ = tokio::time::sleep(Duration::from_secs(60)) => unreachable!("work outlived the shutdown"),
+    ///     _ = ctx.shutdown_signalled() => (),
+    /// }
+    /// drop(ctx);
+    /// shutdown.shutdown().await;
+    /// # }
+    /// ```
+    pub fn shutdown_signalled(&self) -> impl Future<Output = ()> + Send + 'static {
+        let guard = self.guard().map(ShutdownGuard::clone_weak);
+        async move {
+            match guard {
+                Some(guard) => guard.into_cancelled().await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+
     /// Turn this Context into a parent [`Context`].
     ///
     /// Naming is hard. Essentially it is meant to optimise the [`Context`] for cloning,
@@ -368,3 +409,46 @@ impl<S> Context<S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_signalled() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = rx.await;
+        });
+        let ctx = Context::new(Arc::new(()), Executor::graceful(shutdown.guard()));
+
+        let signalled = ctx.shutdown_signalled();
+        let task = ctx.spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(3600)) => false,
+                _ = signalled => true,
+            }
+        });
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!task.is_finished());
+        tx.send(()).unwrap();
+        assert!(task.await.unwrap());
+
+        // the (weak) shutdown future does not delay the shutdown
+        let pending = ctx.shutdown_signalled();
+        drop(ctx);
+        shutdown.shutdown().await;
+        pending.await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_signalled_without_guard() {
+        let ctx = Context::default();
+        let result =
+            tokio::time::timeout(Duration::from_secs(3600), ctx.shutdown_signalled()).await;
+        assert!(result.is_err());
+    }
+}
@@ -1,3 +1,124 @@
+use std::{
+    fmt,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Shared mutable state, which can be used as (part of) the state of a [`Context`].
+///
+/// The state of a [`Context`] is shared between all requests as read-only,
+/// this wrapper makes it convenient to share mutable state (e.g. counters or caches)
+/// between them, and with the code that created the state.
+/// Cloning a [`SharedState`] is cheap, and clones share the same value.
+///
+/// The value is protected by a (synchronous) [`RwLock`], which is not meant
+/// to be held across `.await` points. The closure-based accessors
+/// such as [`SharedState::with`] and [`SharedState::update`] make that easy to respect.
+///
+/// A poisoned lock (caused by a panic while it was held) is ignored,
+/// as the value itself remains usable.
+///
+/// # Example
+///
+/// ```
+/// use rama::service::{context::SharedState, Context, Service, service_fn};
+/// use std::{convert::Infallible, sync::Arc};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let hits = SharedState::new(0usize);
+///
+/// let service = service_fn(|ctx: Context<SharedState<usize>>, _: ()| async move {
+///     Ok::<_, Infallible>(ctx.state().update(|hits| {
+///         *hits += 1;
+///         *hits
+///     }))
+/// });
+///
+/// let ctx = Context::with_state(Arc::new(hits.clone()));
+/// assert_eq!(service.serve(ctx.clone(), ()).await.unwrap(), 1);
+/// assert_eq!(service.serve(ctx, ()).await.unwrap(), 2);
+/// assert_eq!(hits.get(), 2);
+/// # }
+/// ```
+///
+/// [`Context`]: crate::service::Context
+pub struct SharedState<T> {
+    inner: Arc<RwLock<T>>,
+}
+
+impl<T> SharedState<T> {
+    /// Create a new [`SharedState`] for the given value.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(value)),
+        }
+    }
+
+    /// Lock the value for reading, blocking until no writer holds the lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the value for writing, blocking until no other reader or writer holds the lock.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Call the given closure with a reference to the value.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Call the given closure with a mutable reference to the value,
+    /// which is locked for the duration of the closure.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.write())
+    }
+
+    /// Replace the value, returning the previous value.
+    pub fn replace(&self, value: T) -> T {
+        std::mem::replace(&mut self.write(), value)
+    }
+
+    /// Set the value, dropping the previous value.
+    pub fn set(&self, value: T) {
+        *self.write() = value;
+    }
+}
+
+impl<T: Clone> SharedState<T> {
+    /// Get a clone of the value.
+    pub fn get(&self) -> T {
+        self.read().clone()
+    }
+}
+
+impl<T> Clone for SharedState<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Default> Default for SharedState<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for SharedState<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedState<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedState").field(&*self.read()).finish()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::ops::Deref;
@@ -49,4 +170,66 @@ mod test {
         assert_database(&connection_state);
         assert_counter(&connection_state);
     }
+
+    #[derive(Debug, Default)]
+    struct Account {
+        balance: i64,
+        transactions: usize,
+    }
+
+    #[tokio::test]
+    async fn test_shared_state_concurrent_requests() {
+        use super::SharedState;
+        use crate::service::{service_fn, Context, Service};
+        use std::convert::Infallible;
+
+        const REQUESTS: usize = 100;
+
+        let state = SharedState::<Account>::default();
+        let service = Arc::new(service_fn(
+            |ctx: Context<SharedState<Account>>, amount: i64| async move {
+                // readers always observe both fields updated together
+                ctx.state()
+                    .with(|account| assert_eq!(account.balance, account.transactions as i64 * 10));
+                tokio::task::yield_now().await;
+                ctx.state().update(|account| {
+                    account.balance += amount;
+                    account.transactions += 1;
+                });
+                Ok::<_, Infallible>(())
+            },
+        ));
+
+        let ctx = Context::with_state(Arc::new(state.clone()));
+        let handles: Vec<_> = (0..REQUESTS)
+            .map(|_| {
+                let service = service.clone();
+                let ctx = ctx.clone();
+                tokio::spawn(async move { service.serve(ctx, 10).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let account = state.read();
+        assert_eq!(account.transactions, REQUESTS);
+        assert_eq!(account.balance, REQUESTS as i64 * 10);
+    }
+
+    #[test]
+    fn test_shared_state_accessors() {
+        use super::SharedState;
+
+        let state = SharedState::new(1);
+        let clone = state.clone();
+
+        clone.set(2);
+        assert_eq!(state.get(), 2);
+        assert_eq!(state.replace(3), 2);
+        assert_eq!(clone.with(|value| value + 1), 4);
+        *state.write() += 1;
+        assert_eq!(*clone.read(), 4);
+        assert_eq!(format!("{:?}", clone), "SharedState(4)");
+    }
 }
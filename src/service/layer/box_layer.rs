@@ -0,0 +1,59 @@
+use super::Layer;
+use crate::service::{BoxService, Service};
+use std::{fmt, sync::Arc};
+
+/// A boxed [`Layer`], wrapping a [`BoxService`] into another [`BoxService`],
+/// for where you require dynamic dispatch.
+///
+/// This allows layers of different types to be collected in a single [`LayerVec`],
+/// as long as the services they produce have the same request, response and error types.
+///
+/// [`LayerVec`]: super::LayerVec
+pub struct BoxLayer<State, Request, Response, Error> {
+    #[allow(clippy::type_complexity)]
+    layer: Arc<
+        dyn Fn(
+                BoxService<State, Request, Response, Error>,
+            ) -> BoxService<State, Request, Response, Error>
+            + Send
+            + Sync
+            + 'static,
+    >,
+}
+
+impl<State, Request, Response, Error> BoxLayer<State, Request, Response, Error> {
+    /// Create a new [`BoxLayer`] from the given layer.
+    pub fn new<L>(layer: L) -> Self
+    where
+        L: Layer<BoxService<State, Request, Response, Error>> + Send + Sync + 'static,
+        L::Service: Service<State, Request, Response = Response, Error = Error>,
+    {
+        Self {
+            layer: Arc::new(move |inner| BoxService::new(layer.layer(inner))),
+        }
+    }
+}
+
+impl<State, Request, Response, Error> Clone for BoxLayer<State, Request, Response, Error> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<State, Request, Response, Error> fmt::Debug for BoxLayer<State, Request, Response, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxLayer").finish()
+    }
+}
+
+impl<State, Request, Response, Error> Layer<BoxService<State, Request, Response, Error>>
+    for BoxLayer<State, Request, Response, Error>
+{
+    type Service = BoxService<State, Request, Response, Error>;
+
+    fn layer(&self, inner: BoxService<State, Request, Response, Error>) -> Self::Service {
+        (self.layer)(inner)
+    }
+}
@@ -0,0 +1,305 @@
+//! Middleware that enforces a total time budget for the requests it serves,
+//! shared with all middleware and services downstream.
+//!
+//! The [`Budget`] middleware, typically applied at the edge, starts the budget of each request
+//! and records its end as a [`Deadline`] in the [`Context`]. Downstream middleware and
+//! services which apply a timeout of their own, such as the [`Timeout`] middleware,
+//! the http [`Timeout`](crate::http::layer::timeout::Timeout) middleware and the
+//! [`TcpConnector`], shrink that timeout to the remaining budget. This way the work done
+//! for a request can never take longer than the budget as a whole, as opposed to each
+//! step applying its own fixed timeout, of which the latencies add up.
+//!
+//! In case a [`Deadline`] is already present (e.g. by an outer budget) the earliest of the two
+//! is kept, such that a nested budget can only shrink the remaining time.
+//!
+//! Once the deadline is reached the [`Budget`] middleware fails with a [`BudgetExceeded`] error.
+//!
+//! [`Context`]: crate::service::Context
+//! [`Timeout`]: crate::service::layer::Timeout
+//! [`TcpConnector`]: crate::net::connect::TcpConnector
+//!
+//! # Example
+//!
+//! ```
+//! use rama::error::BoxError;
+//! use rama::service::{
+//!     layer::{budget::Deadline, BudgetLayer, TimeoutLayer},
+//!     Context, Service, ServiceBuilder,
+//! };
+//! use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = ServiceBuilder::new()
+//!     .layer(BudgetLayer::new(Duration::from_millis(500)))
+//!     // shrinks to the remaining budget, as it exceeds the budget itself
+//!     .layer(TimeoutLayer::new(Duration::from_secs(30)))
+//!     .service_fn(|ctx: Context<()>, _: ()| async move {
+//!         let deadline = ctx.get::<Deadline>().unwrap();
+//!         Ok::<_, BoxError>(deadline.remaining())
+//!     });
+//!
+//! let remaining = service.serve(Context::default(), ()).await.unwrap();
+//! assert!(remaining <= Duration::from_millis(500));
+//! # }
+//! ```
+
+use crate::service::{Context, Layer, Service};
+use std::{error, fmt, time::Duration};
+use tokio::time::Instant;
+
+/// The point in time by which a request has to be served,
+/// added to the [`Context`] by the [`Budget`] middleware.
+///
+/// [`Context`]: crate::service::Context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Create a new [`Deadline`] at the given point in time.
+    pub fn at(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    /// Create a new [`Deadline`] which expires once the given duration has passed.
+    pub fn after(duration: Duration) -> Self {
+        Self(Instant::now() + duration)
+    }
+
+    /// The point in time at which the deadline expires.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// The time remaining until the deadline expires,
+    /// zero if it already expired.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Returns `true` if the deadline expired.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Shrink the given timeout to the remaining time, if it exceeds it.
+    pub fn clamp(&self, timeout: Duration) -> Duration {
+        timeout.min(self.remaining())
+    }
+
+    /// Shrink the given timeout to the time remaining until the [`Deadline`]
+    /// found in the given [`Context`], if any.
+    ///
+    /// [`Context`]: crate::service::Context
+    pub fn clamp_timeout<State>(ctx: &Context<State>, timeout: Duration) -> Duration {
+        match ctx.get::<Deadline>() {
+            Some(deadline) => deadline.clamp(timeout),
+            None => timeout,
+        }
+    }
+}
+
+/// The time budget of the [`Budget`] middleware was exceeded.
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded(Duration);
+
+impl BudgetExceeded {
+    /// The budget that was exceeded.
+    pub fn budget(&self) -> Duration {
+        self.0
+    }
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "time budget of {:?} exceeded", self.0)
+    }
+}
+
+impl error::Error for BudgetExceeded {}
+
+/// [`Layer`] that applies the [`Budget`] middleware.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetLayer {
+    budget: Duration,
+}
+
+impl BudgetLayer {
+    /// Create a new [`BudgetLayer`], serving each request within the given time budget.
+    pub fn new(budget: Duration) -> Self {
+        Self { budget }
+    }
+}
+
+impl<S> Layer<S> for BudgetLayer {
+    type Service = Budget<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Budget {
+            inner,
+            budget: self.budget,
+        }
+    }
+}
+
+/// Middleware that enforces a total time budget for the requests it serves.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct Budget<S> {
+    inner: S,
+    budget: Duration,
+}
+
+impl<S> Budget<S> {
+    /// Create a new [`Budget`] middleware, serving each request within the given time budget.
+    pub fn new(inner: S, budget: Duration) -> Self {
+        Self { inner, budget }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, Request, S> Service<State, Request> for Budget<S>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+    BudgetExceeded: Into<S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut deadline = Deadline::after(self.budget);
+        if let Some(outer) = ctx.get::<Deadline>() {
+            deadline = deadline.min(*outer);
+        }
+        ctx.insert(deadline);
+
+        tokio::select! {
+            // a response ready at the deadline is preferred over the error
+            biased;
+            res = self.inner.serve(ctx, req) => res,
+            _ = tokio::time::sleep_until(deadline.instant()) => {
+                tracing::trace!(budget = ?self.budget, "time budget exceeded");
+                Err(BudgetExceeded(self.budget).into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BoxError;
+    use crate::service::layer::{timeout::Elapsed, TimeoutLayer};
+    use crate::service::{service_fn, ServiceBuilder};
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_deadline_remaining() {
+        let service = BudgetLayer::new(Duration::from_secs(1)).layer(service_fn(
+            |ctx: Context<()>, work: Duration| async move {
+                tokio::time::sleep(work).await;
+                Ok::<_, BoxError>(ctx.get::<Deadline>().unwrap().remaining())
+            },
+        ));
+
+        for (work, remaining) in [(0, 1000), (300, 700), (900, 100)] {
+            let remaining_budget = service
+                .serve(Context::default(), Duration::from_millis(work))
+                .await
+                .unwrap();
+            assert_eq!(remaining_budget, Duration::from_millis(remaining));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_shrinks_downstream_timeout() {
+        async fn hang(_: ()) -> Result<(), BoxError> {
+            std::future::pending().await
+        }
+
+        let downstream = Arc::new(
+            ServiceBuilder::new()
+                .layer(TimeoutLayer::new(Duration::from_secs(10)))
+                .service_fn(hang),
+        );
+        let service = BudgetLayer::new(Duration::from_secs(1)).layer(service_fn(
+            move |ctx: Context<()>, work: Duration| {
+                let downstream = downstream.clone();
+                async move {
+                    // upstream work consuming (part of) the budget
+                    tokio::time::sleep(work).await;
+                    let start = Instant::now();
+                    let err = downstream.serve(ctx, ()).await.unwrap_err();
+                    assert!(err.downcast_ref::<Elapsed>().is_some());
+                    Ok::<_, BoxError>(start.elapsed())
+                }
+            },
+        ));
+
+        for (work, timeout) in [(0, 1000), (250, 750), (600, 400)] {
+            let effective_timeout = service
+                .serve(Context::default(), Duration::from_millis(work))
+                .await
+                .unwrap();
+            assert_eq!(effective_timeout, Duration::from_millis(timeout));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_exceeded() {
+        let service = BudgetLayer::new(Duration::from_secs(1)).layer(service_fn(
+            |work: Duration| async move {
+                tokio::time::sleep(work).await;
+                Ok::<_, BoxError>(())
+            },
+        ));
+
+        service
+            .serve(Context::default(), Duration::from_millis(500))
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        let err = service
+            .serve(Context::default(), Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        let err = err.downcast_ref::<BudgetExceeded>().unwrap();
+        assert_eq!(err.budget(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_nested_keeps_earliest_deadline() {
+        let inner = service_fn(|ctx: Context<()>, _: ()| async move {
+            Ok::<_, BoxError>(ctx.get::<Deadline>().unwrap().remaining())
+        });
+        let service = ServiceBuilder::new()
+            .layer(BudgetLayer::new(Duration::from_secs(1)))
+            .layer(BudgetLayer::new(Duration::from_secs(10)))
+            .service(inner.clone());
+        assert_eq!(
+            service.serve(Context::default(), ()).await.unwrap(),
+            Duration::from_secs(1)
+        );
+
+        let service = ServiceBuilder::new()
+            .layer(BudgetLayer::new(Duration::from_secs(10)))
+            .layer(BudgetLayer::new(Duration::from_secs(2)))
+            .service(inner);
+        assert_eq!(
+            service.serve(Context::default(), ()).await.unwrap(),
+            Duration::from_secs(2)
+        );
+    }
+}
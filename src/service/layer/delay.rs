@@ -0,0 +1,254 @@
+//! Middleware that delays the requests it serves by an artificial latency,
+//! before they are served by the inner service.
+//!
+//! This is useful to test how clients (e.g. a frontend) behave against slow services,
+//! without having to put sleeps in the services themselves.
+//! The delay is either fixed or drawn at random (uniformly) within the given bounds,
+//! for each request. Using [`DelayLayer::when`] only the requests which match
+//! the given [`Matcher`] are delayed, such that different routes can get different
+//! delays by composing multiple layers.
+//!
+//! A zero delay is a no-op, serving the request directly using the inner service.
+//!
+//! For delays which only apply to a fraction of the requests, see the
+//! [`FaultInjection`](super::fault_injection) middleware instead.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::http::{matcher::HttpMatcher, Body, Request, Response};
+//! use rama::service::{layer::DelayLayer, Context, Service, ServiceBuilder};
+//! use std::{convert::Infallible, time::Duration};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = ServiceBuilder::new()
+//!     .layer(DelayLayer::fixed(Duration::from_millis(50)).when(HttpMatcher::path("/slow")))
+//!     .layer(DelayLayer::random(Duration::ZERO, Duration::from_millis(10)))
+//!     .service_fn(|_: Request| async { Ok::<_, Infallible>(Response::new(Body::empty())) });
+//!
+//! let start = std::time::Instant::now();
+//! let req = Request::builder().uri("/slow").body(Body::empty()).unwrap();
+//! service.serve(Context::default(), req).await.unwrap();
+//! assert!(start.elapsed() >= Duration::from_millis(50));
+//! # }
+//! ```
+//!
+//! [`Matcher`]: crate::service::Matcher
+
+use crate::service::{
+    matcher::Always,
+    util::rng::{HasherRng, Rng},
+    Context, Layer, Matcher, Service,
+};
+use std::time::Duration;
+
+/// The delay applied by the [`Delay`] middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DelayKind {
+    Fixed(Duration),
+    Random { min: Duration, max: Duration },
+}
+
+impl DelayKind {
+    /// Get the delay for the next request.
+    fn next(&self) -> Duration {
+        match *self {
+            DelayKind::Fixed(delay) => delay,
+            DelayKind::Random { min, max } => {
+                let range = (max - min).as_nanos().min(u64::MAX as u128 - 1) as u64;
+                if range == 0 {
+                    return min;
+                }
+                min + Duration::from_nanos(HasherRng::new().next_range(0..range + 1))
+            }
+        }
+    }
+}
+
+/// [`Layer`] that applies the [`Delay`] middleware.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct DelayLayer<M = Always> {
+    delay: DelayKind,
+    matcher: M,
+}
+
+impl DelayLayer {
+    /// Create a new [`DelayLayer`], delaying all requests by the given fixed duration.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            delay: DelayKind::Fixed(delay),
+            matcher: Always::new(),
+        }
+    }
+
+    /// Create a new [`DelayLayer`], delaying each request by a random duration
+    /// within `min..=max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn random(min: Duration, max: Duration) -> Self {
+        assert!(min <= max, "delay: min delay must not exceed the max delay");
+        Self {
+            delay: DelayKind::Random { min, max },
+            matcher: Always::new(),
+        }
+    }
+}
+
+impl<M> DelayLayer<M> {
+    /// Only delay the requests which match the given [`Matcher`],
+    /// serving all other requests directly using the inner service.
+    ///
+    /// [`Matcher`]: crate::service::Matcher
+    pub fn when<T>(self, matcher: T) -> DelayLayer<T> {
+        DelayLayer {
+            delay: self.delay,
+            matcher,
+        }
+    }
+}
+
+impl<S, M> Layer<S> for DelayLayer<M>
+where
+    M: Clone,
+{
+    type Service = Delay<S, M>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Delay {
+            inner,
+            delay: self.delay,
+            matcher: self.matcher.clone(),
+        }
+    }
+}
+
+/// Middleware that delays the requests it serves by an artificial latency.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct Delay<S, M = Always> {
+    inner: S,
+    delay: DelayKind,
+    matcher: M,
+}
+
+impl<S> Delay<S> {
+    /// Create a new [`Delay`] middleware, delaying all requests by the given fixed duration.
+    pub fn fixed(inner: S, delay: Duration) -> Self {
+        DelayLayer::fixed(delay).layer(inner)
+    }
+
+    /// Create a new [`Delay`] middleware, delaying each request by a random duration
+    /// within `min..=max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn random(inner: S, min: Duration, max: Duration) -> Self {
+        DelayLayer::random(min, max).layer(inner)
+    }
+}
+
+impl<S, M> Delay<S, M> {
+    define_inner_service_accessors!();
+}
+
+impl<State, Request, S, M> Service<State, Request> for Delay<S, M>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+    M: Matcher<State, Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.matcher.matches(None, &ctx, &req) {
+            let delay = self.delay.next();
+            if !delay.is_zero() {
+                tracing::trace!(?delay, "delay request");
+                tokio::time::sleep(delay).await;
+            }
+        }
+        self.inner.serve(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{matcher::match_fn, service_fn};
+    use futures::FutureExt;
+    use std::convert::Infallible;
+    use tokio::time::Instant;
+
+    fn echo() -> impl Service<(), u8, Response = u8, Error = Infallible> + Clone {
+        service_fn(|req: u8| async move { Ok(req) })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_fixed() {
+        let service = DelayLayer::fixed(Duration::from_secs(3)).layer(echo());
+
+        let start = Instant::now();
+        assert_eq!(service.serve(Context::default(), 42).await.unwrap(), 42);
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_random() {
+        let service =
+            DelayLayer::random(Duration::from_secs(1), Duration::from_secs(2)).layer(echo());
+
+        for _ in 0..16 {
+            let start = Instant::now();
+            service.serve(Context::default(), 1).await.unwrap();
+            let elapsed = start.elapsed();
+            assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+            assert!(elapsed <= Duration::from_secs(2), "{elapsed:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_when() {
+        let service = DelayLayer::fixed(Duration::from_secs(1))
+            .when(match_fn(|req: &u8| req % 2 == 0))
+            .layer(echo());
+
+        let start = Instant::now();
+        service.serve(Context::default(), 2).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        let start = Instant::now();
+        service.serve(Context::default(), 3).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_zero_is_noop() {
+        for service in [
+            DelayLayer::fixed(Duration::ZERO).layer(echo()),
+            DelayLayer::random(Duration::ZERO, Duration::ZERO).layer(echo()),
+        ] {
+            // served without yielding to the timer at all
+            let result = service.serve(Context::default(), 7).now_or_never();
+            assert_eq!(result.unwrap().unwrap(), 7);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_delay_random_invalid_bounds() {
+        let _ = DelayLayer::random(Duration::from_secs(2), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,389 @@
+//! Middleware that injects faults into the requests it serves,
+//! to test the resilience of the services and clients around it.
+//!
+//! Using configurable probabilities, the [`FaultInjection`] middleware can:
+//!
+//! - delay a request by a fixed latency before it is served;
+//! - abort a request, dropping it without it being served
+//!   (which for a stream closes the connection);
+//! - fail a request with a synthetic error, without it being served.
+//!
+//! Aborted and failed requests result in the [`FaultInjectionError::Abort`]
+//! and [`FaultInjectionError::Error`] errors respectively, while the errors
+//! of the inner service are returned as [`FaultInjectionError::Service`],
+//! such that the caller can tell the three apart.
+//!
+//! The faults are drawn from a pseudo-random generator, which can be seeded
+//! using [`FaultInjectionLayer::seed`] to get a deterministic sequence of faults.
+//! A disabled middleware (see [`FaultInjectionLayer::enabled`]) serves all requests
+//! directly using the inner service, which makes it safe to leave it in the chain
+//! and only enable it for staging environments.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::service::layer::fault_injection::{FaultInjectionError, FaultInjectionLayer};
+//! use std::{convert::Infallible, time::Duration};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = ServiceBuilder::new()
+//!     .layer(
+//!         FaultInjectionLayer::new()
+//!             .latency(0.1, Duration::from_millis(5))
+//!             .error(1.0)
+//!             .seed(42),
+//!     )
+//!     .service_fn(|_ctx: Context<()>, _: ()| async move { Ok::<_, Infallible>(()) });
+//!
+//! let err = service.serve(Context::default(), ()).await.unwrap_err();
+//! assert!(matches!(err, FaultInjectionError::Error));
+//! # }
+//! ```
+
+use crate::service::{
+    util::rng::{HasherRng, Rng, StableState},
+    Context, Layer, Service,
+};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Errors returned by the [`FaultInjection`] middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultInjectionError<E> {
+    /// The request was aborted, and dropped without being served.
+    Abort,
+    /// The request failed with a synthetic error, without being served.
+    Error,
+    /// An error occurred while serving the request using the inner service.
+    Service(E),
+}
+
+impl<E> fmt::Display for FaultInjectionError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultInjectionError::Abort => write!(f, "fault injection: request aborted"),
+            FaultInjectionError::Error => write!(f, "fault injection: synthetic error"),
+            FaultInjectionError::Service(e) => write!(f, "service error: {}", e),
+        }
+    }
+}
+
+impl<E> std::error::Error for FaultInjectionError<E> where E: fmt::Debug + fmt::Display {}
+
+#[derive(Debug, Clone, Copy)]
+struct FaultConfig {
+    enabled: bool,
+    latency: Duration,
+    latency_probability: f64,
+    abort_probability: f64,
+    error_probability: f64,
+    seed: Option<u64>,
+}
+
+/// [`Layer`] that applies the [`FaultInjection`] middleware.
+///
+/// By default no faults are configured, such that all requests are served as-is.
+#[derive(Debug, Clone)]
+pub struct FaultInjectionLayer {
+    config: FaultConfig,
+}
+
+impl FaultInjectionLayer {
+    /// Create a new [`FaultInjectionLayer`], enabled but without any faults configured.
+    pub fn new() -> Self {
+        Self {
+            config: FaultConfig {
+                enabled: true,
+                latency: Duration::ZERO,
+                latency_probability: 0.0,
+                abort_probability: 0.0,
+                error_probability: 0.0,
+                seed: None,
+            },
+        }
+    }
+
+    /// Enable or disable the fault injection.
+    ///
+    /// A disabled middleware serves all requests directly using the inner service.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.config.enabled = enabled;
+        self
+    }
+
+    /// Delay requests with the given probability by the given latency,
+    /// before they are served (or aborted/failed).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the probability is not within `0.0..=1.0`.
+    pub fn latency(mut self, probability: f64, latency: Duration) -> Self {
+        self.config.latency_probability = checked_probability(probability);
+        self.config.latency = latency;
+        self
+    }
+
+    /// Abort requests with the given probability,
+    /// dropping them without them being served.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the probability is not within `0.0..=1.0`,
+    /// or if the combined abort and error probability exceeds `1.0`.
+    pub fn abort(mut self, probability: f64) -> Self {
+        self.config.abort_probability = checked_probability(probability);
+        check_fault_probabilities(&self.config);
+        self
+    }
+
+    /// Fail requests with the given probability with a synthetic error,
+    /// without them being served.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the probability is not within `0.0..=1.0`,
+    /// or if the combined abort and error probability exceeds `1.0`.
+    pub fn error(mut self, probability: f64) -> Self {
+        self.config.error_probability = checked_probability(probability);
+        check_fault_probabilities(&self.config);
+        self
+    }
+
+    /// Seed the pseudo-random generator used to draw the faults,
+    /// such that each service created by this layer injects the same sequence of faults.
+    ///
+    /// By default a random seed is used for each service.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+}
+
+impl Default for FaultInjectionLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for FaultInjectionLayer {
+    type Service = FaultInjection<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FaultInjection {
+            inner,
+            config: self.config,
+            rng: Arc::new(Mutex::new(HasherRng::with_hasher(StableState::new(
+                self.config.seed.unwrap_or_else(random_seed),
+            )))),
+        }
+    }
+}
+
+/// Middleware that injects latency, aborts and synthetic errors
+/// into the requests it serves.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct FaultInjection<S> {
+    inner: S,
+    config: FaultConfig,
+    rng: Arc<Mutex<HasherRng<StableState>>>,
+}
+
+impl<S> FaultInjection<S> {
+    define_inner_service_accessors!();
+
+    /// Returns a new [`Layer`] that wraps services with a [`FaultInjection`] middleware.
+    pub fn layer() -> FaultInjectionLayer {
+        FaultInjectionLayer::new()
+    }
+}
+
+impl<State, Request, S> Service<State, Request> for FaultInjection<S>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+{
+    type Response = S::Response;
+    type Error = FaultInjectionError<S::Error>;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.config.enabled {
+            return self
+                .inner
+                .serve(ctx, req)
+                .await
+                .map_err(FaultInjectionError::Service);
+        }
+
+        let (latency, fault) = {
+            let mut rng = self.rng.lock().unwrap();
+            (rng.next_f64(), rng.next_f64())
+        };
+
+        if latency < self.config.latency_probability {
+            tracing::trace!(latency = ?self.config.latency, "fault injection: delay request");
+            tokio::time::sleep(self.config.latency).await;
+        }
+
+        if fault < self.config.abort_probability {
+            tracing::trace!("fault injection: abort request");
+            drop(req);
+            return Err(FaultInjectionError::Abort);
+        }
+        if fault < self.config.abort_probability + self.config.error_probability {
+            tracing::trace!("fault injection: fail request");
+            return Err(FaultInjectionError::Error);
+        }
+
+        self.inner
+            .serve(ctx, req)
+            .await
+            .map_err(FaultInjectionError::Service)
+    }
+}
+
+fn checked_probability(probability: f64) -> f64 {
+    assert!(
+        (0.0..=1.0).contains(&probability),
+        "fault injection: probability must be within 0.0..=1.0"
+    );
+    probability
+}
+
+fn check_fault_probabilities(config: &FaultConfig) {
+    assert!(
+        config.abort_probability + config.error_probability <= 1.0,
+        "fault injection: combined abort and error probability must not exceed 1.0"
+    );
+}
+
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+
+    const REQUESTS: usize = 10_000;
+
+    type Result = std::result::Result<(), FaultInjectionError<&'static str>>;
+
+    fn ok_service() -> impl Service<(), (), Response = (), Error = &'static str> {
+        service_fn(|_ctx: Context<()>, _: ()| async move { Ok(()) })
+    }
+
+    async fn serve_all<S>(service: &S) -> Vec<Result>
+    where
+        S: Service<(), (), Response = (), Error = FaultInjectionError<&'static str>>,
+    {
+        let mut results = Vec::with_capacity(REQUESTS);
+        for _ in 0..REQUESTS {
+            results.push(service.serve(Context::default(), ()).await);
+        }
+        results
+    }
+
+    fn rate(results: &[Result], fault: FaultInjectionError<&'static str>) -> f64 {
+        results.iter().filter(|r| **r == Err(fault)).count() as f64 / results.len() as f64
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_rates() {
+        let service = FaultInjectionLayer::new()
+            .abort(0.1)
+            .error(0.25)
+            .seed(42)
+            .layer(ok_service());
+
+        let results = serve_all(&service).await;
+        let abort_rate = rate(&results, FaultInjectionError::Abort);
+        let error_rate = rate(&results, FaultInjectionError::Error);
+        assert!((abort_rate - 0.1).abs() < 0.02, "abort rate: {abort_rate}");
+        assert!((error_rate - 0.25).abs() < 0.02, "error rate: {error_rate}");
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_seed_is_deterministic() {
+        let layer = FaultInjectionLayer::new().abort(0.3).error(0.3).seed(7);
+
+        let results = serve_all(&layer.layer(ok_service())).await;
+        assert_eq!(results, serve_all(&layer.layer(ok_service())).await);
+
+        let other = FaultInjectionLayer::new().abort(0.3).error(0.3).seed(8);
+        assert_ne!(results, serve_all(&other.layer(ok_service())).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fault_injection_latency() {
+        let service = FaultInjectionLayer::new()
+            .latency(0.5, Duration::from_secs(1))
+            .seed(42)
+            .layer(ok_service());
+
+        let start = tokio::time::Instant::now();
+        let results = serve_all(&service).await;
+        assert!(results.iter().all(Result::is_ok));
+
+        let delayed = start.elapsed().as_secs() as f64 / REQUESTS as f64;
+        assert!((delayed - 0.5).abs() < 0.02, "latency rate: {delayed}");
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_disabled() {
+        let service = FaultInjectionLayer::new()
+            .latency(1.0, Duration::from_secs(60))
+            .error(1.0)
+            .enabled(false)
+            .layer(ok_service());
+
+        let results = serve_all(&service).await;
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_no_faults_configured() {
+        let service = FaultInjectionLayer::new().layer(ok_service());
+
+        let results = serve_all(&service).await;
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_service_error() {
+        let service = FaultInjectionLayer::new()
+            .abort(0.5)
+            .seed(42)
+            .layer(service_fn(|_ctx: Context<()>, _: ()| async move {
+                Err::<(), _>("inner")
+            }));
+
+        let results = serve_all(&service).await;
+        assert!(rate(&results, FaultInjectionError::Abort) > 0.0);
+        assert!(rate(&results, FaultInjectionError::Service("inner")) > 0.0);
+        assert_eq!(rate(&results, FaultInjectionError::Error), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fault_injection_invalid_probability() {
+        let _ = FaultInjectionLayer::new().abort(0.6).error(0.6);
+    }
+}
@@ -0,0 +1,173 @@
+//! Middleware that stops a service from accepting new requests
+//! once its [`ServiceShutdown`] token is signalled.
+//!
+//! This allows to shut down a subtree of services (e.g. an admin API)
+//! independently of the global [`Shutdown`], while the rest of the application
+//! keeps serving. Requests received after the token was signalled fail with a
+//! [`ServiceShutdownError`], while in-flight requests are allowed to finish,
+//! which can be awaited using [`ServiceShutdown::shutdown`].
+//!
+//! The token is also added to the [`Context`] of every served request,
+//! such that long-running handlers can observe it.
+//!
+//! [`Shutdown`]: crate::graceful::Shutdown
+//!
+//! # Example
+//!
+//! ```
+//! use rama::graceful::{ServiceShutdown, ServiceShutdownError};
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::service::layer::GracefulLayer;
+//! use rama::error::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let shutdown = ServiceShutdown::new();
+//!
+//! let admin = ServiceBuilder::new()
+//!     .layer(GracefulLayer::new(shutdown.clone()))
+//!     .service_fn(|_ctx: Context<()>, _: ()| async move { Ok::<_, BoxError>("admin") });
+//!
+//! assert_eq!(admin.serve(Context::default(), ()).await.unwrap(), "admin");
+//!
+//! shutdown.shutdown().await;
+//! let err = admin.serve(Context::default(), ()).await.unwrap_err();
+//! assert!(err.downcast_ref::<ServiceShutdownError>().is_some());
+//! # }
+//! ```
+
+use crate::{
+    graceful::{ServiceShutdown, ServiceShutdownError},
+    service::{Context, Layer, Service},
+};
+
+/// [`Layer`] that applies the [`Graceful`] middleware,
+/// observing the given [`ServiceShutdown`] token.
+#[derive(Debug, Clone)]
+pub struct GracefulLayer {
+    shutdown: ServiceShutdown,
+}
+
+impl GracefulLayer {
+    /// Create a new [`GracefulLayer`] observing the given [`ServiceShutdown`] token.
+    pub fn new(shutdown: ServiceShutdown) -> Self {
+        Self { shutdown }
+    }
+
+    /// Returns the [`ServiceShutdown`] token observed by this layer.
+    pub fn shutdown(&self) -> &ServiceShutdown {
+        &self.shutdown
+    }
+}
+
+impl<S> Layer<S> for GracefulLayer {
+    type Service = Graceful<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Graceful {
+            inner,
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+/// Middleware that rejects new requests once its [`ServiceShutdown`] token is signalled.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct Graceful<S> {
+    inner: S,
+    shutdown: ServiceShutdown,
+}
+
+impl<S> Graceful<S> {
+    /// Create a new [`Graceful`] middleware observing the given [`ServiceShutdown`] token.
+    pub fn new(inner: S, shutdown: ServiceShutdown) -> Self {
+        Self { inner, shutdown }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<S, State, Request> Service<State, Request> for Graceful<S>
+where
+    S: Service<State, Request>,
+    ServiceShutdownError: Into<S::Error>,
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let _guard = match self.shutdown.track() {
+            Some(guard) => guard,
+            None => return Err(ServiceShutdownError::new().into()),
+        };
+        ctx.insert(self.shutdown.clone());
+        self.inner.serve(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::BoxError, service::ServiceBuilder};
+    use std::time::Duration;
+
+    fn named_service(
+        name: &'static str,
+        shutdown: ServiceShutdown,
+    ) -> impl Service<(), (), Response = &'static str, Error = BoxError> {
+        ServiceBuilder::new()
+            .layer(GracefulLayer::new(shutdown))
+            .service_fn(move |_ctx: Context<()>, _: ()| async move { Ok::<_, BoxError>(name) })
+    }
+
+    #[tokio::test]
+    async fn test_graceful_only_signalled_subtree_stops() {
+        let admin_shutdown = ServiceShutdown::new();
+        let admin = named_service("admin", admin_shutdown.clone());
+        let api = named_service("api", ServiceShutdown::new());
+
+        assert_eq!(admin.serve(Context::default(), ()).await.unwrap(), "admin");
+        assert_eq!(api.serve(Context::default(), ()).await.unwrap(), "api");
+
+        admin_shutdown.shutdown().await;
+        assert!(admin_shutdown.is_signalled());
+
+        let err = admin.serve(Context::default(), ()).await.unwrap_err();
+        assert!(err.downcast_ref::<ServiceShutdownError>().is_some());
+        assert_eq!(api.serve(Context::default(), ()).await.unwrap(), "api");
+        assert_eq!(api.serve(Context::default(), ()).await.unwrap(), "api");
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_waits_for_in_flight() {
+        let shutdown = ServiceShutdown::new();
+        let service = ServiceBuilder::new()
+            .layer(GracefulLayer::new(shutdown.clone()))
+            .service_fn(|ctx: Context<()>, _: ()| async move {
+                let shutdown = ctx.get::<ServiceShutdown>().unwrap().clone();
+                shutdown.cancelled().await;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok::<_, BoxError>("done")
+            });
+
+        let (result, _) = tokio::join!(service.serve(Context::default(), ()), async {
+            while shutdown.in_flight() == 0 {
+                tokio::task::yield_now().await;
+            }
+            shutdown.shutdown().await;
+            assert_eq!(shutdown.in_flight(), 0);
+        });
+        assert_eq!(result.unwrap(), "done");
+
+        assert!(service.serve(Context::default(), ()).await.is_err());
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+}
@@ -0,0 +1,291 @@
+//! Middleware that keeps track of the number of in-flight requests.
+//!
+//! The current count can be read from within a handler or health endpoint
+//! using the [`InFlightHandle`], which is available in the [`Context`]
+//! of every request served by the [`InFlight`] middleware,
+//! or can be kept around separately by cloning it from the [`InFlightLayer`].
+//!
+//! [`Context`]: crate::service::Context
+//!
+//! # Example
+//!
+//! ```
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::service::layer::in_flight::{InFlightHandle, InFlightLayer};
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Infallible> {
+//! let layer = InFlightLayer::new();
+//! let handle = layer.handle();
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(layer)
+//!     .service_fn(|ctx: Context<()>, _: ()| async move {
+//!         // the request being served is counted as well
+//!         Ok::<_, Infallible>(ctx.get::<InFlightHandle>().unwrap().count())
+//!     });
+//!
+//! let count = service.serve(Context::default(), ()).await?;
+//! assert_eq!(count, 1);
+//! assert_eq!(handle.count(), 0);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::service::{Context, Layer, Service};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::Notify;
+
+/// A shared handle to read the number of in-flight requests
+/// tracked by an [`InFlight`] middleware.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightHandle {
+    inner: Arc<InFlightCount>,
+}
+
+#[derive(Debug, Default)]
+struct InFlightCount {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl InFlightHandle {
+    /// Create a new [`InFlightHandle`], with no requests in flight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn count(&self) -> usize {
+        self.inner.count.load(Ordering::Acquire)
+    }
+
+    /// Wait until no requests are in flight.
+    pub async fn idle(&self) {
+        loop {
+            // created before checking the count, such that a request
+            // finishing in between is not missed
+            let idle = self.inner.idle.notified();
+            if self.count() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Track a new in-flight request, until the returned guard is dropped.
+    pub(crate) fn track(&self) -> InFlightGuard {
+        self.inner.count.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Decrements the in-flight count when dropped,
+/// including when the inner service panics or its future is cancelled.
+#[derive(Debug)]
+pub(crate) struct InFlightGuard {
+    inner: Arc<InFlightCount>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.inner.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
+
+/// [`Layer`] that applies the [`InFlight`] middleware,
+/// keeping track of the number of in-flight requests.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightLayer {
+    handle: InFlightHandle,
+}
+
+impl InFlightLayer {
+    /// Create a new [`InFlightLayer`] with its own [`InFlightHandle`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [`InFlightLayer`] which tracks its requests
+    /// using the given [`InFlightHandle`].
+    ///
+    /// This allows multiple services to share the same in-flight count.
+    pub fn with_handle(handle: InFlightHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Returns the [`InFlightHandle`] used by this layer.
+    pub fn handle(&self) -> InFlightHandle {
+        self.handle.clone()
+    }
+}
+
+impl<S> Layer<S> for InFlightLayer {
+    type Service = InFlight<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InFlight {
+            inner,
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+/// Middleware that keeps track of the number of in-flight requests,
+/// inserting its [`InFlightHandle`] into the [`Context`] of each request.
+#[derive(Debug, Clone)]
+pub struct InFlight<S> {
+    inner: S,
+    handle: InFlightHandle,
+}
+
+impl<S> InFlight<S> {
+    /// Create a new [`InFlight`] middleware,
+    /// which tracks its requests using the given [`InFlightHandle`].
+    pub fn new(inner: S, handle: InFlightHandle) -> Self {
+        Self { inner, handle }
+    }
+
+    define_inner_service_accessors!();
+
+    /// Returns the [`InFlightHandle`] used by this middleware.
+    pub fn handle(&self) -> InFlightHandle {
+        self.handle.clone()
+    }
+
+    /// Returns a new [`Layer`] that wraps services with an [`InFlight`] middleware.
+    pub fn layer() -> InFlightLayer {
+        InFlightLayer::new()
+    }
+}
+
+impl<State, Request, S> Service<State, Request> for InFlight<S>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let _guard = self.handle.track();
+        ctx.insert(self.handle.clone());
+        self.inner.serve(ctx, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::service::{service_fn, ServiceBuilder};
+    use std::convert::Infallible;
+    use tokio::sync::Semaphore;
+
+    #[tokio::test]
+    async fn in_flight_concurrent_requests() {
+        let layer = InFlightLayer::new();
+        let handle = layer.handle();
+        let release = Arc::new(Semaphore::new(0));
+
+        let svc = Arc::new(ServiceBuilder::new().layer(layer).service(service_fn({
+            let release = release.clone();
+            move |ctx: Context<()>, _req: ()| {
+                let release = release.clone();
+                async move {
+                    let count = ctx.get::<InFlightHandle>().unwrap().count();
+                    release.acquire().await.unwrap().forget();
+                    Ok::<_, Infallible>(count)
+                }
+            }
+        })));
+
+        assert_eq!(handle.count(), 0);
+
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let svc = svc.clone();
+                tokio::spawn(async move { svc.serve(Context::default(), ()).await })
+            })
+            .collect();
+
+        while handle.count() < 3 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(handle.count(), 3);
+
+        release.add_permits(3);
+        for task in tasks {
+            let count = task.await.unwrap().unwrap();
+            assert!((1..=3).contains(&count));
+        }
+
+        assert_eq!(handle.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn in_flight_decrements_on_panic() {
+        let layer = InFlightLayer::new();
+        let handle = layer.handle();
+
+        let svc = ServiceBuilder::new().layer(layer).service(service_fn(
+            |_ctx: Context<()>, _req: ()| async move {
+                if true {
+                    panic!("handler panic");
+                }
+                Ok::<_, Infallible>(())
+            },
+        ));
+
+        let result = tokio::spawn(async move { svc.serve(Context::default(), ()).await }).await;
+        assert!(result.is_err());
+        assert_eq!(handle.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn in_flight_shared_handle() {
+        let handle = InFlightHandle::new();
+
+        let svc_a = InFlightLayer::with_handle(handle.clone()).layer(service_fn(
+            |ctx: Context<()>, _req: ()| async move {
+                Ok::<_, Infallible>(ctx.get::<InFlightHandle>().unwrap().count())
+            },
+        ));
+        let svc_b = InFlightLayer::with_handle(handle.clone()).layer(svc_a);
+
+        let count = svc_b.serve(Context::default(), ()).await.unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(handle.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn in_flight_idle() {
+        let handle = InFlightHandle::new();
+        // resolves immediately without requests in flight
+        handle.idle().await;
+
+        let guards = [handle.track(), handle.track()];
+        let idle = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.idle().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!idle.is_finished());
+
+        drop(guards);
+        idle.await.unwrap();
+    }
+}
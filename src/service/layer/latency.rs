@@ -0,0 +1,465 @@
+//! Middleware that records the latency of requests,
+//! exposing its percentiles over a sliding window.
+//!
+//! The percentiles can be read from within a handler or status endpoint
+//! using the [`LatencyHandle`], which is available in the [`Context`]
+//! of every request served by the [`Latency`] middleware,
+//! or can be kept around separately by cloning it from the [`LatencyLayer`].
+//!
+//! Latencies are recorded in a bounded histogram with logarithmic buckets,
+//! such that the reported percentiles are approximate, within about 1% of the actual value.
+//! Only requests that complete (successfully or not) are recorded,
+//! the latency of a request being the time it took the inner service to serve it.
+//!
+//! [`Context`]: crate::service::Context
+//!
+//! # Example
+//!
+//! ```
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::service::layer::latency::LatencyLayer;
+//! use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Infallible> {
+//! let layer = LatencyLayer::new();
+//! let handle = layer.handle();
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(layer)
+//!     .service_fn(|_: ()| async { Ok::<_, Infallible>(()) });
+//!
+//! assert!(handle.p50().is_none());
+//! service.serve(Context::default(), ()).await?;
+//! assert_eq!(handle.count(), 1);
+//! assert!(handle.p99().is_some());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::service::{Context, Layer, Service};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The number of slots the sliding window is divided in.
+const SLOTS: usize = 6;
+
+/// Values (in microseconds) below this bound get a bucket of their own.
+const LINEAR_BITS: u32 = 7;
+/// The number of buckets per power of two, above the linear range.
+const SUB_BUCKETS: u64 = 1 << (LINEAR_BITS - 1);
+/// Latencies are capped to about 19 hours.
+const MAX_VALUE: u64 = (1 << 36) - 1;
+const BUCKETS: usize = bucket_index(MAX_VALUE) + 1;
+
+const fn bucket_index(value: u64) -> usize {
+    let value = if value > MAX_VALUE { MAX_VALUE } else { value };
+    let bits = u64::BITS - value.leading_zeros();
+    if bits <= LINEAR_BITS {
+        value as usize
+    } else {
+        let shift = bits - LINEAR_BITS;
+        (shift as u64 * SUB_BUCKETS + (value >> shift)) as usize
+    }
+}
+
+/// The value (in microseconds) represented by the given bucket,
+/// which is the midpoint of the values it contains.
+fn bucket_value(index: usize) -> u64 {
+    let index = index as u64;
+    if index < 2 * SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let mantissa = index % SUB_BUCKETS + SUB_BUCKETS;
+    (mantissa << shift) + ((1 << shift) >> 1)
+}
+
+/// A shared handle to read the latency percentiles
+/// recorded by a [`Latency`] middleware.
+#[derive(Debug, Clone)]
+pub struct LatencyHandle {
+    window: Arc<Mutex<Window>>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started_at: Instant,
+    slot_duration: Duration,
+    slots: Vec<Slot>,
+}
+
+struct Slot {
+    epoch: u64,
+    total: u64,
+    counts: Vec<u32>,
+}
+
+impl std::fmt::Debug for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Slot")
+            .field("epoch", &self.epoch)
+            .field("total", &self.total)
+            .finish()
+    }
+}
+
+impl Window {
+    fn epoch(&self) -> u64 {
+        (self.started_at.elapsed().as_nanos() / self.slot_duration.as_nanos()) as u64
+    }
+
+    fn record(&mut self, latency: Duration) {
+        let epoch = self.epoch();
+        let slot = &mut self.slots[(epoch % SLOTS as u64) as usize];
+        if slot.epoch != epoch {
+            slot.epoch = epoch;
+            slot.total = 0;
+            slot.counts.iter_mut().for_each(|count| *count = 0);
+        }
+        let latency = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let count = &mut slot.counts[bucket_index(latency)];
+        *count = count.saturating_add(1);
+        slot.total += 1;
+    }
+
+    fn live_slots(&self) -> impl Iterator<Item = &Slot> {
+        let epoch = self.epoch();
+        self.slots
+            .iter()
+            .filter(move |slot| slot.total > 0 && slot.epoch + (SLOTS as u64) > epoch)
+    }
+
+    fn count(&self) -> u64 {
+        self.live_slots().map(|slot| slot.total).sum()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let mut total = 0;
+        let slots = self
+            .live_slots()
+            .map(|slot| {
+                total += slot.total;
+                slot.counts.clone()
+            })
+            .collect();
+        Snapshot { total, slots }
+    }
+}
+
+/// A copy of the counts of the live slots of a [`Window`],
+/// such that the percentiles can be computed without holding its lock.
+struct Snapshot {
+    total: u64,
+    slots: Vec<Vec<u32>>,
+}
+
+impl Snapshot {
+    fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank =
+            ((quantile.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).clamp(1, self.total);
+
+        let mut seen = 0;
+        for index in 0..BUCKETS {
+            seen += self
+                .slots
+                .iter()
+                .map(|counts| counts[index] as u64)
+                .sum::<u64>();
+            if seen >= rank {
+                return Some(Duration::from_micros(bucket_value(index)));
+            }
+        }
+        None
+    }
+}
+
+impl LatencyHandle {
+    /// Create a new [`LatencyHandle`], exposing the percentiles
+    /// of the latencies recorded in the last minute.
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Create a new [`LatencyHandle`], exposing the percentiles
+    /// of the latencies recorded within the given window.
+    ///
+    /// The window slides in steps of a sixth of its duration.
+    pub fn with_window(window: Duration) -> Self {
+        let slot_duration = (window / SLOTS as u32).max(Duration::from_millis(1));
+        let slots = (0..SLOTS)
+            .map(|_| Slot {
+                epoch: 0,
+                total: 0,
+                counts: vec![0; BUCKETS],
+            })
+            .collect();
+        Self {
+            window: Arc::new(Mutex::new(Window {
+                started_at: Instant::now(),
+                slot_duration,
+                slots,
+            })),
+        }
+    }
+
+    /// Record the given latency.
+    ///
+    /// This is done by the [`Latency`] middleware for each request it serves,
+    /// but can also be used to record latencies measured elsewhere.
+    pub fn record(&self, latency: Duration) {
+        self.window.lock().unwrap().record(latency);
+    }
+
+    /// Returns the number of latencies recorded within the window.
+    pub fn count(&self) -> u64 {
+        self.window.lock().unwrap().count()
+    }
+
+    /// Returns the (approximate) latency at the given quantile,
+    /// ranging from `0.0` to `1.0`, of the latencies recorded within the window.
+    ///
+    /// Returns `None` in case no latencies were recorded within the window.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let snapshot = self.window.lock().unwrap().snapshot();
+        snapshot.quantile(quantile)
+    }
+
+    /// Returns the median latency, see [`LatencyHandle::quantile`].
+    pub fn p50(&self) -> Option<Duration> {
+        self.quantile(0.5)
+    }
+
+    /// Returns the 90th percentile latency, see [`LatencyHandle::quantile`].
+    pub fn p90(&self) -> Option<Duration> {
+        self.quantile(0.9)
+    }
+
+    /// Returns the 99th percentile latency, see [`LatencyHandle::quantile`].
+    pub fn p99(&self) -> Option<Duration> {
+        self.quantile(0.99)
+    }
+}
+
+impl Default for LatencyHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// [`Layer`] that applies the [`Latency`] middleware,
+/// recording the latency of requests.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyLayer {
+    handle: LatencyHandle,
+}
+
+impl LatencyLayer {
+    /// Create a new [`LatencyLayer`] with its own [`LatencyHandle`],
+    /// using a window of one minute.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [`LatencyLayer`] which records the latency of its requests
+    /// using the given [`LatencyHandle`].
+    ///
+    /// This allows multiple services to share the same latency percentiles.
+    pub fn with_handle(handle: LatencyHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Returns the [`LatencyHandle`] used by this layer.
+    pub fn handle(&self) -> LatencyHandle {
+        self.handle.clone()
+    }
+}
+
+impl<S> Layer<S> for LatencyLayer {
+    type Service = Latency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Latency {
+            inner,
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+/// Middleware that records the latency of requests,
+/// inserting its [`LatencyHandle`] into the [`Context`] of each request.
+#[derive(Debug, Clone)]
+pub struct Latency<S> {
+    inner: S,
+    handle: LatencyHandle,
+}
+
+impl<S> Latency<S> {
+    /// Create a new [`Latency`] middleware,
+    /// which records the latency of its requests using the given [`LatencyHandle`].
+    pub fn new(inner: S, handle: LatencyHandle) -> Self {
+        Self { inner, handle }
+    }
+
+    define_inner_service_accessors!();
+
+    /// Returns the [`LatencyHandle`] used by this middleware.
+    pub fn handle(&self) -> LatencyHandle {
+        self.handle.clone()
+    }
+
+    /// Returns a new [`Layer`] that wraps services with a [`Latency`] middleware.
+    pub fn layer() -> LatencyLayer {
+        LatencyLayer::new()
+    }
+}
+
+impl<State, Request, S> Service<State, Request> for Latency<S>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        mut ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        ctx.insert(self.handle.clone());
+        let start = Instant::now();
+        let result = self.inner.serve(ctx, req).await;
+        self.handle.record(start.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::service::{service_fn, ServiceBuilder};
+
+    fn assert_close(actual: Option<Duration>, expected: Duration) {
+        let actual = actual.expect("some latency").as_secs_f64();
+        let expected = expected.as_secs_f64();
+        assert!(
+            (actual - expected).abs() <= expected * 0.01,
+            "{actual}s is not within 1% of {expected}s"
+        );
+    }
+
+    #[test]
+    fn test_bucket_roundtrip() {
+        for value in [0, 1, 63, 127, 128, 129, 1_000, 65_535, 1_000_000, MAX_VALUE] {
+            let bucket = bucket_value(bucket_index(value));
+            let error = (bucket as f64 - value as f64).abs();
+            assert!(error <= value as f64 * 0.01, "{value} => {bucket}");
+        }
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_snapshot() {
+        let handle = LatencyHandle::new();
+        handle.record(Duration::from_millis(10));
+
+        let snapshot = handle.window.lock().unwrap().snapshot();
+        // recording does not affect a snapshot taken before
+        handle.record(Duration::from_secs(1));
+        handle.record(Duration::from_secs(1));
+
+        assert_eq!(snapshot.total, 1);
+        assert_close(snapshot.quantile(0.99), Duration::from_millis(10));
+        assert_close(handle.p50(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_known_percentiles() {
+        let handle = LatencyHandle::new();
+        assert_eq!(handle.count(), 0);
+        assert!(handle.p50().is_none());
+
+        // record 1ms..=1000ms in a scrambled order
+        for i in 0..1000u64 {
+            let ms = (i * 337) % 1000 + 1;
+            handle.record(Duration::from_millis(ms));
+        }
+        assert_eq!(handle.count(), 1000);
+
+        assert_close(handle.p50(), Duration::from_millis(500));
+        assert_close(handle.p90(), Duration::from_millis(900));
+        assert_close(handle.p99(), Duration::from_millis(990));
+        assert_close(handle.quantile(0.0), Duration::from_millis(1));
+        assert_close(handle.quantile(1.0), Duration::from_millis(1000));
+
+        // an outlier only affects the highest percentiles
+        for _ in 0..20 {
+            handle.record(Duration::from_secs(30));
+        }
+        assert_close(handle.p50(), Duration::from_millis(510));
+        assert_close(handle.p99(), Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_sliding_window() {
+        let handle = LatencyHandle::with_window(Duration::from_secs(60));
+        for _ in 0..100 {
+            handle.record(Duration::from_millis(100));
+        }
+        assert_close(handle.p50(), Duration::from_millis(100));
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        for _ in 0..300 {
+            handle.record(Duration::from_millis(10));
+        }
+        assert_eq!(handle.count(), 400);
+        assert_close(handle.p50(), Duration::from_millis(10));
+        assert_close(handle.p99(), Duration::from_millis(100));
+
+        // the first latencies slide out of the window
+        tokio::time::advance(Duration::from_secs(35)).await;
+        assert_eq!(handle.count(), 300);
+        assert_close(handle.p99(), Duration::from_millis(10));
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(handle.count(), 0);
+        assert!(handle.p99().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_middleware() {
+        let layer = LatencyLayer::new();
+        let handle = layer.handle();
+
+        let svc = ServiceBuilder::new().layer(layer).service(service_fn(
+            |ctx: Context<()>, ms: u64| async move {
+                assert!(ctx.get::<LatencyHandle>().is_some());
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                if ms > 100 {
+                    return Err("too slow");
+                }
+                Ok(ms)
+            },
+        ));
+
+        for ms in [10, 20, 30, 40, 200] {
+            let _ = svc.serve(Context::default(), ms).await;
+        }
+
+        // errors are recorded as well
+        assert_eq!(handle.count(), 5);
+        assert_close(handle.p50(), Duration::from_millis(30));
+        assert_close(handle.p99(), Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,88 @@
+use super::Layer;
+use std::fmt;
+
+/// A homogeneous collection of layers, applied in order.
+///
+/// The first layer of the collection is the outermost one,
+/// meaning that applying a [`LayerVec`] of `[a, b, c]` is equivalent to
+/// fluently chaining `.layer(a).layer(b).layer(c)` on a [`ServiceBuilder`].
+///
+/// All layers have to be of the same type, producing the same type of service
+/// as the one they wrap. Use [`BoxLayer`] to collect layers of different types,
+/// for example when building the list of layers at runtime.
+///
+/// [`ServiceBuilder`]: crate::service::ServiceBuilder
+/// [`BoxLayer`]: super::BoxLayer
+pub struct LayerVec<L> {
+    layers: Vec<L>,
+}
+
+impl<L> LayerVec<L> {
+    /// Create a new [`LayerVec`] from the given layers.
+    pub fn new(layers: impl IntoIterator<Item = L>) -> Self {
+        Self {
+            layers: layers.into_iter().collect(),
+        }
+    }
+
+    /// Append a layer to the [`LayerVec`], becoming the innermost layer.
+    pub fn push(&mut self, layer: L) {
+        self.layers.push(layer);
+    }
+
+    /// Returns the number of layers in the [`LayerVec`].
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns `true` in case the [`LayerVec`] contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+impl<L> Default for LayerVec<L> {
+    fn default() -> Self {
+        Self { layers: Vec::new() }
+    }
+}
+
+impl<L> FromIterator<L> for LayerVec<L> {
+    fn from_iter<I: IntoIterator<Item = L>>(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+impl<L> From<Vec<L>> for LayerVec<L> {
+    fn from(layers: Vec<L>) -> Self {
+        Self { layers }
+    }
+}
+
+impl<L: Clone> Clone for LayerVec<L> {
+    fn clone(&self) -> Self {
+        Self {
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for LayerVec<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.layers).finish()
+    }
+}
+
+impl<S, L> Layer<S> for LayerVec<L>
+where
+    L: Layer<S, Service = S>,
+{
+    type Service = S;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.layers
+            .iter()
+            .rev()
+            .fold(inner, |service, layer| layer.layer(service))
+    }
+}
@@ -0,0 +1,299 @@
+//! A policy that partitions an inner policy by a key extracted from the request.
+//!
+//! See [`KeyedPolicy`].
+//!
+//! # Examples
+//!
+//! ```
+//! use rama::service::{
+//!     layer::limit::{Limit, policy::{ConcurrentPolicy, KeyedPolicy}},
+//!     Context, Service, service_fn,
+//! };
+//! # use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//!
+//! let service = service_fn(|_, _: &'static str| async {
+//!     Ok::<_, Infallible>(())
+//! });
+//!
+//! // allow up to 2 concurrent requests per tenant
+//! let policy = KeyedPolicy::new(
+//!     |_: &Context<()>, tenant: &&'static str| tenant.to_string(),
+//!     || ConcurrentPolicy::new(2),
+//! );
+//! let mut service = Limit::new(service, policy);
+//!
+//! let response = service.serve(Context::default(), "tenant-a").await;
+//! assert!(response.is_ok());
+//! # }
+//! ```
+
+use super::{Policy, PolicyOutput, PolicyResult};
+use crate::service::Context;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A policy that partitions an inner [`Policy`] by a key,
+/// extracted from the [`Context`] and request using a closure.
+///
+/// Each key gets its own independent inner policy, created using the given policy factory,
+/// such that for example a [`ConcurrentPolicy`] can be applied per API key, tenant or IP address.
+///
+/// Keys which haven't been used for the configured idle timeout (5 minutes by default)
+/// and have no requests in flight are evicted, after which the next request
+/// for that key starts again with a fresh inner policy.
+///
+/// [`ConcurrentPolicy`]: super::ConcurrentPolicy
+pub struct KeyedPolicy<K, P, F, M> {
+    key_fn: F,
+    make_policy: M,
+    idle_timeout: Duration,
+    state: Arc<Mutex<KeyedState<K, P>>>,
+}
+
+struct KeyedState<K, P> {
+    entries: HashMap<K, KeyedEntry<P>>,
+    last_eviction: Instant,
+}
+
+struct KeyedEntry<P> {
+    policy: Arc<P>,
+    last_used: Instant,
+}
+
+impl<K, P> KeyedState<K, P> {
+    fn evict_idle(&mut self, now: Instant, idle_timeout: Duration) {
+        if now.duration_since(self.last_eviction) < idle_timeout {
+            return;
+        }
+        self.last_eviction = now;
+        self.entries.retain(|_, entry| {
+            // a policy still referenced by a guard has requests in flight
+            Arc::strong_count(&entry.policy) > 1
+                || now.duration_since(entry.last_used) < idle_timeout
+        });
+    }
+}
+
+impl<K, P, F, M> KeyedPolicy<K, P, F, M> {
+    /// Create a new keyed policy,
+    /// which uses the key returned by `key_fn` to pick the inner policy for a request,
+    /// creating a new one using `make_policy` for each key not seen before.
+    pub fn new(key_fn: F, make_policy: M) -> Self {
+        KeyedPolicy {
+            key_fn,
+            make_policy,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            state: Arc::new(Mutex::new(KeyedState {
+                entries: HashMap::new(),
+                last_eviction: Instant::now(),
+            })),
+        }
+    }
+
+    /// Set the duration after which a key without requests in flight
+    /// is considered idle and evicted, dropping its inner policy.
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+}
+
+impl<K, P, F, M> Clone for KeyedPolicy<K, P, F, M>
+where
+    F: Clone,
+    M: Clone,
+{
+    fn clone(&self) -> Self {
+        KeyedPolicy {
+            key_fn: self.key_fn.clone(),
+            make_policy: self.make_policy.clone(),
+            idle_timeout: self.idle_timeout,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<K, P, F, M> fmt::Debug for KeyedPolicy<K, P, F, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedPolicy")
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
+}
+
+/// The guard of a [`KeyedPolicy`],
+/// wrapping the guard of the inner policy used for the request.
+///
+/// The key of the request is not evicted for as long as this guard is alive.
+pub struct KeyedGuard<P, G> {
+    _policy: Arc<P>,
+    guard: G,
+}
+
+impl<P, G> KeyedGuard<P, G> {
+    /// Get a reference to the guard of the inner policy.
+    pub fn inner(&self) -> &G {
+        &self.guard
+    }
+}
+
+impl<P, G: fmt::Debug> fmt::Debug for KeyedGuard<P, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedGuard")
+            .field("guard", &self.guard)
+            .finish()
+    }
+}
+
+impl<K, P, F, M, State, Request> Policy<State, Request> for KeyedPolicy<K, P, F, M>
+where
+    K: Eq + Hash + Send + 'static,
+    P: Policy<State, Request>,
+    F: Fn(&Context<State>, &Request) -> K + Send + Sync + 'static,
+    M: Fn() -> P + Send + Sync + 'static,
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+{
+    type Guard = KeyedGuard<P, P::Guard>;
+    type Error = P::Error;
+
+    async fn check(
+        &self,
+        ctx: Context<State>,
+        request: Request,
+    ) -> PolicyResult<State, Request, Self::Guard, Self::Error> {
+        let key = (self.key_fn)(&ctx, &request);
+
+        let policy = {
+            let now = Instant::now();
+            let mut state = self.state.lock().unwrap();
+            state.evict_idle(now, self.idle_timeout);
+            let entry = state.entries.entry(key).or_insert_with(|| KeyedEntry {
+                policy: Arc::new((self.make_policy)()),
+                last_used: now,
+            });
+            entry.last_used = now;
+            entry.policy.clone()
+        };
+
+        let result = policy.check(ctx, request).await;
+        let output = match result.output {
+            PolicyOutput::Ready(guard) => PolicyOutput::Ready(KeyedGuard {
+                _policy: policy,
+                guard,
+            }),
+            PolicyOutput::Abort(err) => PolicyOutput::Abort(err),
+            PolicyOutput::Retry => PolicyOutput::Retry,
+        };
+
+        PolicyResult {
+            ctx: result.ctx,
+            request: result.request,
+            output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::layer::limit::policy::ConcurrentPolicy;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn assert_ready<S, R, G, E>(result: PolicyResult<S, R, G, E>) -> G {
+        match result.output {
+            PolicyOutput::Ready(guard) => guard,
+            _ => panic!("unexpected output, expected ready"),
+        }
+    }
+
+    fn assert_abort<S, R, G, E>(result: PolicyResult<S, R, G, E>) {
+        match result.output {
+            PolicyOutput::Abort(_) => (),
+            _ => panic!("unexpected output, expected abort"),
+        }
+    }
+
+    fn key_fn(_: &Context<()>, key: &&'static str) -> &'static str {
+        key
+    }
+
+    #[tokio::test]
+    async fn keyed_policy_independent_keys() {
+        let policy = KeyedPolicy::new(key_fn, || ConcurrentPolicy::new(1));
+
+        let guard_a = assert_ready(policy.check(Context::default(), "a").await);
+        assert_abort(policy.check(Context::default(), "a").await);
+
+        let guard_b = assert_ready(policy.check(Context::default(), "b").await);
+        assert_abort(policy.check(Context::default(), "b").await);
+
+        drop(guard_a);
+        let _guard_a = assert_ready(policy.check(Context::default(), "a").await);
+        assert_abort(policy.check(Context::default(), "b").await);
+
+        drop(guard_b);
+        assert_ready(policy.check(Context::default(), "b").await);
+    }
+
+    #[tokio::test]
+    async fn keyed_policy_clone() {
+        let policy = KeyedPolicy::new(key_fn, || ConcurrentPolicy::new(1));
+        let policy_clone = policy.clone();
+
+        let guard = assert_ready(policy.check(Context::default(), "a").await);
+        assert_abort(policy_clone.check(Context::default(), "a").await);
+        assert_ready(policy_clone.check(Context::default(), "b").await);
+
+        drop(guard);
+        assert_ready(policy_clone.check(Context::default(), "a").await);
+    }
+
+    #[tokio::test]
+    async fn keyed_policy_evicts_idle_keys() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let policy = KeyedPolicy::new(key_fn, {
+            let created = created.clone();
+            move || {
+                created.fetch_add(1, Ordering::SeqCst);
+                ConcurrentPolicy::new(1)
+            }
+        })
+        .idle_timeout(Duration::from_millis(50));
+
+        let guard_a = assert_ready(policy.check(Context::default(), "a").await);
+        drop(assert_ready(policy.check(Context::default(), "b").await));
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // "b" is idle and evicted, "a" is kept as it still has a request in flight
+        drop(assert_ready(policy.check(Context::default(), "c").await));
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+        {
+            let state = policy.state.lock().unwrap();
+            assert!(state.entries.contains_key("a"));
+            assert!(!state.entries.contains_key("b"));
+            assert!(state.entries.contains_key("c"));
+        }
+        assert_abort(policy.check(Context::default(), "a").await);
+
+        // an evicted key starts again with a fresh policy
+        drop(assert_ready(policy.check(Context::default(), "b").await));
+        assert_eq!(created.load(Ordering::SeqCst), 4);
+
+        drop(guard_a);
+        assert_ready(policy.check(Context::default(), "a").await);
+        assert_eq!(created.load(Ordering::SeqCst), 4);
+    }
+}
@@ -36,8 +36,16 @@ mod concurrent;
 #[doc(inline)]
 pub use concurrent::{ConcurrentPolicy, LimitReached};
 
+mod keyed;
+#[doc(inline)]
+pub use keyed::{KeyedGuard, KeyedPolicy};
+
 mod matcher;
 
+mod rate;
+#[doc(inline)]
+pub use rate::{RateLimitReached, TokenBucketPolicy};
+
 #[derive(Debug)]
 /// The full result of a limit policy.
 pub struct PolicyResult<State, Request, Guard, Error> {
@@ -0,0 +1,215 @@
+//! A policy that limits the rate of requests using a token bucket.
+//!
+//! See [`TokenBucketPolicy`].
+//!
+//! # Examples
+//!
+//! ```
+//! use rama::service::{
+//!     layer::limit::{Limit, policy::{KeyedPolicy, TokenBucketPolicy}},
+//!     Context, Service, service_fn,
+//! };
+//! # use std::convert::Infallible;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//!
+//! let service = service_fn(|_, _: &'static str| async {
+//!     Ok::<_, Infallible>(())
+//! });
+//!
+//! // allow up to 10 requests per second per tenant
+//! let policy = KeyedPolicy::new(
+//!     |_: &Context<()>, tenant: &&'static str| tenant.to_string(),
+//!     || TokenBucketPolicy::new(10),
+//! );
+//! let service = Limit::new(service, policy);
+//!
+//! let response = service.serve(Context::default(), "tenant-a").await;
+//! assert!(response.is_ok());
+//! # }
+//! ```
+
+use super::{Policy, PolicyOutput, PolicyResult};
+use crate::service::Context;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// A policy that limits the rate of requests using a token bucket.
+///
+/// The bucket holds up to `burst` tokens (by default as many as the rate), and is refilled
+/// at the configured rate of tokens per second. Each request takes a token, and requests
+/// arriving while the bucket is empty are aborted with a [`RateLimitReached`] error.
+///
+/// Cloning the policy returns a handle to the same bucket,
+/// such that its rate is shared by all services using (a clone of) it.
+#[derive(Debug, Clone)]
+pub struct TokenBucketPolicy {
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+impl TokenBucketPolicy {
+    /// Create a new token bucket policy, allowing the given amount of requests per second,
+    /// with a burst of as many requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is `0`.
+    pub fn new(rate: u32) -> Self {
+        Self::with_burst(rate, rate)
+    }
+
+    /// Create a new token bucket policy, allowing the given amount of requests per second,
+    /// with the given maximum amount of requests in a single burst,
+    /// i.e. the capacity of the token bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate or burst is `0`.
+    pub fn with_burst(rate: u32, burst: u32) -> Self {
+        assert!(rate > 0, "token bucket: rate must be non-zero");
+        assert!(burst > 0, "token bucket: burst must be non-zero");
+        Self {
+            bucket: Arc::new(Mutex::new(TokenBucket {
+                rate,
+                burst,
+                tokens: burst as f64,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: u32,
+    burst: u32,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Take a token from the bucket, returning the time
+    /// until a token is available in case it is empty.
+    fn try_acquire(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = elapsed
+            .mul_add(self.rate as f64, self.tokens)
+            .min(self.burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.rate as f64,
+            ))
+        }
+    }
+}
+
+impl<State, Request> Policy<State, Request> for TokenBucketPolicy
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+{
+    type Guard = ();
+    type Error = RateLimitReached;
+
+    async fn check(
+        &self,
+        ctx: Context<State>,
+        request: Request,
+    ) -> PolicyResult<State, Request, Self::Guard, Self::Error> {
+        let output = match self.bucket.lock().unwrap().try_acquire() {
+            Ok(()) => PolicyOutput::Ready(()),
+            Err(retry_after) => PolicyOutput::Abort(RateLimitReached { retry_after }),
+        };
+        PolicyResult {
+            ctx,
+            request,
+            output,
+        }
+    }
+}
+
+/// The error that indicates the request is aborted,
+/// because the token bucket of a [`TokenBucketPolicy`] is empty.
+#[derive(Debug, Clone)]
+pub struct RateLimitReached {
+    retry_after: Duration,
+}
+
+impl RateLimitReached {
+    /// Returns the time after which a token is available again.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+impl fmt::Display for RateLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RateLimitReached (retry after {:?})", self.retry_after)
+    }
+}
+
+impl std::error::Error for RateLimitReached {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::layer::limit::policy::KeyedPolicy;
+
+    async fn check<P: Policy<(), &'static str>>(policy: &P, req: &'static str) -> bool {
+        matches!(
+            policy.check(Context::default(), req).await.output,
+            PolicyOutput::Ready(_)
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn token_bucket_policy() {
+        let policy = TokenBucketPolicy::with_burst(2, 3);
+        for _ in 0..3 {
+            assert!(check(&policy, "a").await);
+        }
+        match policy.check(Context::default(), "a").await.output {
+            PolicyOutput::Abort(err) => assert_eq!(err.retry_after(), Duration::from_millis(500)),
+            _ => panic!("unexpected output, expected abort"),
+        }
+
+        // a token is available again after 1 / rate seconds
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert!(check(&policy, "a").await);
+        assert!(!check(&policy, "a").await);
+
+        // the bucket is shared by its clones
+        tokio::time::advance(Duration::from_secs(60)).await;
+        let clone = policy.clone();
+        for _ in 0..3 {
+            assert!(check(&clone, "a").await);
+        }
+        assert!(!check(&policy, "a").await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn token_bucket_policy_keyed() {
+        let policy = KeyedPolicy::new(
+            |_: &Context<()>, key: &&'static str| *key,
+            || TokenBucketPolicy::new(1),
+        );
+        assert!(check(&policy, "a").await);
+        assert!(!check(&policy, "a").await);
+        // each key has its own bucket
+        assert!(check(&policy, "b").await);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(check(&policy, "a").await);
+    }
+}
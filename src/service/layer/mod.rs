@@ -29,6 +29,14 @@ mod stack;
 #[doc(inline)]
 pub use stack::Stack;
 
+mod layer_vec;
+#[doc(inline)]
+pub use layer_vec::LayerVec;
+
+mod box_layer;
+#[doc(inline)]
+pub use box_layer::BoxLayer;
+
 mod map_state;
 #[doc(inline)]
 pub use map_state::{MapState, MapStateLayer};
@@ -69,6 +77,10 @@ pub mod timeout;
 #[doc(inline)]
 pub use timeout::{Timeout, TimeoutLayer};
 
+pub mod budget;
+#[doc(inline)]
+pub use budget::{Budget, BudgetLayer};
+
 pub mod limit;
 #[doc(inline)]
 pub use limit::{Limit, LimitLayer};
@@ -76,3 +88,27 @@ pub use limit::{Limit, LimitLayer};
 pub mod add_extension;
 #[doc(inline)]
 pub use add_extension::{AddExtension, AddExtensionLayer};
+
+pub mod in_flight;
+#[doc(inline)]
+pub use in_flight::{InFlight, InFlightLayer};
+
+pub mod latency;
+#[doc(inline)]
+pub use latency::{Latency, LatencyLayer};
+
+pub mod fault_injection;
+#[doc(inline)]
+pub use fault_injection::{FaultInjection, FaultInjectionLayer};
+
+pub mod delay;
+#[doc(inline)]
+pub use delay::{Delay, DelayLayer};
+
+pub mod single_flight;
+#[doc(inline)]
+pub use single_flight::{SingleFlight, SingleFlightLayer};
+
+pub mod graceful;
+#[doc(inline)]
+pub use graceful::{Graceful, GracefulLayer};
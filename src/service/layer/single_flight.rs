@@ -0,0 +1,476 @@
+//! Middleware that coalesces concurrent identical requests,
+//! such that only one of them is served by the inner service.
+//!
+//! Requests are identified by a key, computed by a user-provided closure.
+//! The first request for a key becomes the leader and is served by the inner service,
+//! while all concurrent requests with the same key (the followers) await the leader
+//! and share its response. Once the leader finished, the next request for that key
+//! is served by the inner service again: the middleware does not cache responses.
+//!
+//! This is intended for expensive idempotent requests (e.g. `GET` requests),
+//! the closure can return `None` to never coalesce a request.
+//!
+//! When the leader fails (or gets cancelled) its followers fail with a [`LeaderFailed`] error,
+//! or retry their own request in case [`LeaderErrorPolicy::Retry`] is configured,
+//! in which case one of them becomes the new leader.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::service::{Context, Service, ServiceBuilder};
+//! use rama::service::layer::single_flight::SingleFlightLayer;
+//! use rama::error::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let service = ServiceBuilder::new()
+//!     .layer(SingleFlightLayer::new(|path: &String| Some(path.clone())))
+//!     .service_fn(|_ctx: Context<()>, path: String| async move {
+//!         // some expensive computation
+//!         Ok::<_, BoxError>(path.len())
+//!     });
+//!
+//! let (a, b) = tokio::join!(
+//!     service.serve(Context::default(), "/report".to_owned()),
+//!     service.serve(Context::default(), "/report".to_owned()),
+//! );
+//! assert_eq!(a?, b?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::service::{Context, Layer, Service};
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+
+/// The error returned to the followers of a leader request which failed.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct LeaderFailed;
+
+impl LeaderFailed {
+    /// Create a new [`LeaderFailed`] error.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl fmt::Display for LeaderFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "single flight: leader request failed")
+    }
+}
+
+impl std::error::Error for LeaderFailed {}
+
+/// What the followers of a leader request do when that leader fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LeaderErrorPolicy {
+    /// Fail the followers with a [`LeaderFailed`] error.
+    #[default]
+    Fail,
+    /// Retry the requests of the followers,
+    /// such that one of them becomes the new leader.
+    Retry,
+}
+
+/// [`Layer`] that applies the [`SingleFlight`] middleware.
+///
+/// `K` is the type of the keys computed by the closure,
+/// and `T` the type of the (shared) response of the inner service,
+/// both of which are usually inferred.
+pub struct SingleFlightLayer<F, K, T> {
+    key_fn: F,
+    policy: LeaderErrorPolicy,
+    _phantom: PhantomData<fn() -> (K, T)>,
+}
+
+impl<F, K, T> SingleFlightLayer<F, K, T> {
+    /// Create a new [`SingleFlightLayer`], coalescing requests
+    /// by the key computed with the given closure.
+    ///
+    /// Requests for which the closure returns `None` are never coalesced.
+    pub fn new(key_fn: F) -> Self {
+        Self {
+            key_fn,
+            policy: LeaderErrorPolicy::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Define what the followers of a leader request do when that leader fails.
+    ///
+    /// By default the followers fail as well.
+    pub fn on_leader_error(mut self, policy: LeaderErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<F, K, T> fmt::Debug for SingleFlightLayer<F, K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleFlightLayer")
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<F: Clone, K, T> Clone for SingleFlightLayer<F, K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            key_fn: self.key_fn.clone(),
+            policy: self.policy,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S, F: Clone, K, T> Layer<S> for SingleFlightLayer<F, K, T> {
+    type Service = SingleFlight<S, F, K, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SingleFlight {
+            inner,
+            key_fn: self.key_fn.clone(),
+            policy: self.policy,
+            flights: Arc::new(Flights::default()),
+        }
+    }
+}
+
+/// The outcome of a leader request, `None` in case the leader failed.
+type Outcome<T> = Option<Option<T>>;
+
+/// Middleware that coalesces concurrent identical requests.
+///
+/// See the [module docs](self) for more information.
+pub struct SingleFlight<S, F, K, T> {
+    inner: S,
+    key_fn: F,
+    policy: LeaderErrorPolicy,
+    flights: Arc<Flights<K, T>>,
+}
+
+impl<S, F, K, T> SingleFlight<S, F, K, T> {
+    define_inner_service_accessors!();
+}
+
+impl<S: fmt::Debug, F, K, T> fmt::Debug for SingleFlight<S, F, K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleFlight")
+            .field("inner", &self.inner)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<S: Clone, F: Clone, K, T> Clone for SingleFlight<S, F, K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            key_fn: self.key_fn.clone(),
+            policy: self.policy,
+            flights: self.flights.clone(),
+        }
+    }
+}
+
+/// The leader requests which are currently in flight, by key,
+/// which the requests with the same key can join as a follower.
+pub(crate) struct Flights<K, T> {
+    in_flight: Mutex<HashMap<K, watch::Receiver<Outcome<T>>>>,
+}
+
+impl<K, T> Default for Flights<K, T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K, T> fmt::Debug for Flights<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Flights").finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, T: Clone> Flights<K, T> {
+    /// Join the flight of the given key, as its leader in case no request
+    /// with that key is in flight, or as a follower of its leader otherwise.
+    pub(crate) fn join(&self, key: K) -> Flight<'_, K, T> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.get(&key) {
+            Some(rx) => Flight::Follower(Follower { rx: rx.clone() }),
+            None => {
+                let (tx, rx) = watch::channel(None);
+                in_flight.insert(key.clone(), rx);
+                Flight::Leader(Leader {
+                    key: Some(key),
+                    tx,
+                    flights: self,
+                })
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.in_flight.lock().unwrap().is_empty()
+    }
+}
+
+/// The role of a request which joined the flight of its key.
+pub(crate) enum Flight<'a, K: Hash + Eq, T> {
+    Leader(Leader<'a, K, T>),
+    Follower(Follower<T>),
+}
+
+/// The leader of a flight, removing its key from the flights when dropped,
+/// including when the leader gets cancelled, which fails its followers.
+pub(crate) struct Leader<'a, K: Hash + Eq, T> {
+    key: Option<K>,
+    tx: watch::Sender<Outcome<T>>,
+    flights: &'a Flights<K, T>,
+}
+
+impl<'a, K: Hash + Eq, T> Leader<'a, K, T> {
+    /// Complete the flight, sharing the given outcome with the followers,
+    /// where `None` fails the followers.
+    pub(crate) fn complete(self, outcome: Option<T>) {
+        let _ = self.tx.send(Some(outcome));
+    }
+}
+
+impl<'a, K: Hash + Eq, T> Drop for Leader<'a, K, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.flights.in_flight.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// A follower of the leader of a flight.
+pub(crate) struct Follower<T> {
+    rx: watch::Receiver<Outcome<T>>,
+}
+
+impl<T: Clone> Follower<T> {
+    /// Wait for the leader to complete, returning its shared outcome,
+    /// or `None` in case the leader failed.
+    pub(crate) async fn outcome(mut self) -> Option<T> {
+        // a dropped sender means that the leader got cancelled
+        self.rx
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|outcome| outcome.clone().flatten())
+    }
+}
+
+impl<State, Request, S, F, K> Service<State, Request> for SingleFlight<S, F, K, S::Response>
+where
+    State: Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request>,
+    S::Response: Clone + Send + Sync,
+    F: Fn(&Request) -> Option<K> + Send + Sync + 'static,
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    LeaderFailed: Into<S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn serve(
+        &self,
+        ctx: Context<State>,
+        req: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let key = match (self.key_fn)(&req) {
+            Some(key) => key,
+            None => return self.inner.serve(ctx, req).await,
+        };
+
+        loop {
+            match self.flights.join(key.clone()) {
+                Flight::Leader(leader) => {
+                    let result = self.inner.serve(ctx, req).await;
+                    leader.complete(result.as_ref().ok().cloned());
+                    return result;
+                }
+                Flight::Follower(follower) => match follower.outcome().await {
+                    Some(response) => return Ok(response),
+                    None if self.policy == LeaderErrorPolicy::Retry => {
+                        tracing::trace!("single flight: leader failed, retry request");
+                    }
+                    None => return Err(LeaderFailed.into()),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::BoxError, service::service_fn};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    const REQUESTS: usize = 50;
+
+    /// Create a slow service, which counts its calls and fails the first `failures` calls.
+    fn counting_service(
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    ) -> impl Service<(), String, Response = String, Error = BoxError> + Clone {
+        let calls = calls.clone();
+        service_fn(move |_ctx: Context<()>, req: String| {
+            let calls = calls.clone();
+            async move {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                if call < failures {
+                    return Err(BoxError::from("inner failure"));
+                }
+                Ok(format!("response for {req}"))
+            }
+        })
+    }
+
+    #[allow(clippy::ptr_arg)]
+    fn key_fn(req: &String) -> Option<String> {
+        (!req.starts_with("/uncached")).then(|| req.clone())
+    }
+
+    async fn serve_concurrently<S>(service: S, req: &str) -> Vec<Result<String, BoxError>>
+    where
+        S: Service<(), String, Response = String, Error = BoxError> + Clone,
+    {
+        let handles: Vec<_> = (0..REQUESTS)
+            .map(|_| {
+                let service = service.clone();
+                let req = req.to_owned();
+                tokio::spawn(async move { service.serve(Context::default(), req).await })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(REQUESTS);
+        for handle in handles {
+            results.push(handle.await.unwrap());
+        }
+        results
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_single_flight_coalesces_requests() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = SingleFlightLayer::new(key_fn).layer(counting_service(calls.clone(), 0));
+
+        let results = serve_concurrently(service.clone(), "/report").await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for result in results {
+            assert_eq!(result.unwrap(), "response for /report");
+        }
+
+        // once finished, the next request is served again
+        service
+            .serve(Context::default(), "/report".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_single_flight_distinct_keys() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = SingleFlightLayer::new(key_fn).layer(counting_service(calls.clone(), 0));
+
+        let (a, b) = tokio::join!(
+            serve_concurrently(service.clone(), "/a"),
+            serve_concurrently(service.clone(), "/b"),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(a.into_iter().all(|r| r.unwrap() == "response for /a"));
+        assert!(b.into_iter().all(|r| r.unwrap() == "response for /b"));
+
+        // requests without a key are never coalesced
+        serve_concurrently(service, "/uncached").await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2 + REQUESTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_single_flight_leader_failure() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = SingleFlightLayer::new(key_fn).layer(counting_service(calls.clone(), 1));
+
+        let results = serve_concurrently(service, "/report").await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for result in results {
+            let err = result.unwrap_err();
+            assert!(
+                err.is::<LeaderFailed>() || err.to_string() == "inner failure",
+                "unexpected error: {err}"
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_single_flight_leader_failure_retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = SingleFlightLayer::new(key_fn)
+            .on_leader_error(LeaderErrorPolicy::Retry)
+            .layer(counting_service(calls.clone(), 1));
+
+        let results = serve_concurrently(service, "/report").await;
+        // the failed leader and the leader elected among the retrying followers
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let failures = results.iter().filter(|r| r.is_err()).count();
+        assert_eq!(failures, 1);
+        for result in results.into_iter().filter_map(Result::ok) {
+            assert_eq!(result, "response for /report");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_single_flight_leader_cancelled() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = SingleFlightLayer::new(key_fn).layer(counting_service(calls.clone(), 0));
+
+        let leader = {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service
+                    .serve(Context::default(), "/report".to_owned())
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        let follower = {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service
+                    .serve(Context::default(), "/report".to_owned())
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+
+        leader.abort();
+        let err = follower.await.unwrap().unwrap_err();
+        assert!(err.is::<LeaderFailed>());
+
+        // the key of the cancelled leader is no longer in flight
+        service
+            .serve(Context::default(), "/report".to_owned())
+            .await
+            .unwrap();
+    }
+}
@@ -2,8 +2,14 @@
 //!
 //! If the response does not complete within the specified timeout, the response
 //! will be aborted.
+//!
+//! In case a [`Deadline`] is present in the [`Context`], the timeout
+//! is shrunk to the time remaining until that deadline.
+//!
+//! [`Deadline`]: crate::service::layer::budget::Deadline
+//! [`Context`]: crate::service::Context
 
-use super::{LayerErrorFn, LayerErrorStatic, MakeLayerError};
+use super::{budget::Deadline, LayerErrorFn, LayerErrorStatic, MakeLayerError};
 use crate::service::{Context, Service};
 use std::time::Duration;
 
@@ -106,9 +112,10 @@ where
         ctx: Context<S>,
         request: Request,
     ) -> Result<Self::Response, Self::Error> {
+        let timeout = Deadline::clamp_timeout(&ctx, self.timeout);
         tokio::select! {
             res = self.inner.serve(ctx, request) => res,
-            _ = tokio::time::sleep(self.timeout) => Err(self.into_error.make_layer_error().into()),
+            _ = tokio::time::sleep(timeout) => Err(self.into_error.make_layer_error().into()),
         }
     }
 }
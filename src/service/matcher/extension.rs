@@ -0,0 +1,75 @@
+use crate::service::{context::Extensions, Context};
+use std::{fmt, marker::PhantomData};
+
+use super::Matcher;
+
+/// Filter based on an extension of type `T` found in the [`Context`],
+/// matching only if the given predicate returns `true` for that extension.
+///
+/// This allows to match on data inserted into the [`Context`] by previous services,
+/// such as an authenticated principal.
+pub struct ExtensionFilter<T, F> {
+    predicate: F,
+    optional: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, F> ExtensionFilter<T, F> {
+    /// Create a new filter matching only if the extension is found in the [`Context`]
+    /// and the predicate returns `true` for it.
+    ///
+    /// This filter will not match in case the extension could not be found,
+    /// if you want to match in case it could not be found,
+    /// use the [`ExtensionFilter::optional`] constructor.
+    pub fn new(predicate: F) -> Self {
+        Self {
+            predicate,
+            optional: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a new filter matching only if the predicate returns `true` for the extension,
+    /// or the extension could not be found in the [`Context`].
+    ///
+    /// Use the [`ExtensionFilter::new`] constructor if you do not want
+    /// to match in case the extension could not be found.
+    pub fn optional(predicate: F) -> Self {
+        Self {
+            predicate,
+            optional: true,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F: Clone> Clone for ExtensionFilter<T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            predicate: self.predicate.clone(),
+            optional: self.optional,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for ExtensionFilter<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionFilter")
+            .field("extension", &std::any::type_name::<T>())
+            .field("optional", &self.optional)
+            .finish()
+    }
+}
+
+impl<State, Request, T, F> Matcher<State, Request> for ExtensionFilter<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> bool + Send + Sync + 'static,
+{
+    fn matches(&self, _ext: Option<&mut Extensions>, ctx: &Context<State>, _req: &Request) -> bool {
+        ctx.get::<T>()
+            .map(|extension| (self.predicate)(extension))
+            .unwrap_or(self.optional)
+    }
+}
@@ -10,6 +10,24 @@
 //! - And finally there is [`MatchFn`], easily created using [`match_fn`] to create a [`Matcher`]
 //!   from any compatible [`Fn`].
 //!
+//! # Evaluation order
+//!
+//! Combined matchers are evaluated lazily, in the order they are declared,
+//! short-circuiting as soon as the outcome is known:
+//!
+//! - [`And`] (and [`IteratorMatcherExt::matches_and`]) stops at the first matcher that does not match;
+//! - [`Or`] (and [`IteratorMatcherExt::matches_or`]) stops at the first matcher that matches;
+//! - [`Not`] evaluates its inner matcher exactly once, only when it is itself evaluated.
+//!
+//! Matchers which are skipped this way are never evaluated at all.
+//! Declare cheap matchers (e.g. on the method or a header) before expensive ones
+//! (e.g. a lookup in a shared table), such that the latter only run when needed,
+//! including when they are negated.
+//!
+//! Only the extensions of a combined matcher which matched as a whole are recorded.
+//! As a negated matcher only matches when its inner matcher does not,
+//! [`Not`] never records the extensions of its inner matcher.
+//!
 //! Implementation Examples:
 //!
 //! - [`http::matcher`]: [`Matcher`] implementations for [`http::Request`]s.
@@ -48,6 +66,10 @@ mod iter;
 #[doc(inline)]
 pub use iter::IteratorMatcherExt;
 
+mod extension;
+#[doc(inline)]
+pub use extension::ExtensionFilter;
+
 /// A condition to decide whether `Request` within the given [`Context`] matches for
 /// router or other middleware purposes.
 pub trait Matcher<State, Request>: Send + Sync + 'static {
@@ -2,6 +2,10 @@ use super::Matcher;
 use crate::service::{context::Extensions, Context};
 
 /// A matcher that matches if all of the inner matchers match.
+///
+/// The inner matchers are evaluated in order, stopping at the first one
+/// that does not match, such that the remaining ones are never evaluated.
+/// See the [module docs](crate::service::matcher#evaluation-order) for more information.
 pub struct And<T>(T);
 
 impl<T: std::fmt::Debug> std::fmt::Debug for And<T> {
@@ -2,6 +2,11 @@ use super::Matcher;
 use crate::service::{context::Extensions, Context};
 
 /// A matcher that matches if the inner matcher does not match.
+///
+/// The inner matcher is evaluated exactly once, only when the [`Not`] matcher
+/// itself is evaluated. Its extensions are never recorded, as they describe
+/// a match which the [`Not`] matcher rejects.
+/// See the [module docs](crate::service::matcher#evaluation-order) for more information.
 pub struct Not<T>(T);
 
 impl<T: std::fmt::Debug> std::fmt::Debug for Not<T> {
@@ -27,7 +32,7 @@ impl<State, Request, T> Matcher<State, Request> for Not<T>
 where
     T: Matcher<State, Request>,
 {
-    fn matches(&self, ext: Option<&mut Extensions>, ctx: &Context<State>, req: &Request) -> bool {
-        !self.0.matches(ext, ctx, req)
+    fn matches(&self, _ext: Option<&mut Extensions>, ctx: &Context<State>, req: &Request) -> bool {
+        !self.0.matches(None, ctx, req)
     }
 }
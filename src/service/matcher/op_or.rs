@@ -2,6 +2,10 @@ use super::Matcher;
 use crate::service::{context::Extensions, Context};
 
 /// A matcher that matches if any of the inner matchers match.
+///
+/// The inner matchers are evaluated in order, stopping at the first one
+/// that matches, such that the remaining ones are never evaluated.
+/// See the [module docs](crate::service::matcher#evaluation-order) for more information.
 pub struct Or<T>(T);
 
 impl<T: std::fmt::Debug> std::fmt::Debug for Or<T> {
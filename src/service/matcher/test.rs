@@ -491,3 +491,173 @@ fn test_ext_insert_and_revert_op_and() {
     assert!(ext.get::<marker::Const>().is_none());
     assert!(ext.get::<marker::Odd>().is_none());
 }
+
+#[derive(Debug, Clone)]
+struct Principal {
+    name: &'static str,
+    admin: bool,
+}
+
+#[test]
+fn test_extension_filter() {
+    let filter = ExtensionFilter::new(|principal: &Principal| principal.admin);
+
+    let mut ctx = Context::default();
+    assert!(!filter.matches(None, &ctx, &()));
+
+    ctx.insert(Principal {
+        name: "john",
+        admin: false,
+    });
+    assert!(!filter.matches(None, &ctx, &()));
+
+    ctx.insert(Principal {
+        name: "jane",
+        admin: true,
+    });
+    assert!(filter.matches(None, &ctx, &()));
+
+    let filter = ExtensionFilter::new(|principal: &Principal| principal.name == "john");
+    assert!(!filter.matches(None, &ctx, &()));
+}
+
+#[test]
+fn test_extension_filter_optional() {
+    let filter = ExtensionFilter::optional(|principal: &Principal| principal.admin);
+
+    let mut ctx = Context::default();
+    assert!(filter.matches(None, &ctx, &()));
+
+    ctx.insert(Principal {
+        name: "john",
+        admin: false,
+    });
+    assert!(!filter.matches(None, &ctx, &()));
+}
+
+/// A matcher which counts how many times it is evaluated.
+#[derive(Debug, Clone)]
+struct CountingMatcher {
+    result: bool,
+    count: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl CountingMatcher {
+    fn new(result: bool) -> Self {
+        Self {
+            result,
+            count: Default::default(),
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.count.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+impl<State> Matcher<State, u8> for CountingMatcher {
+    fn matches(&self, ext: Option<&mut Extensions>, _ctx: &Context<State>, _req: &u8) -> bool {
+        self.count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if self.result {
+            if let Some(ext) = ext {
+                ext.insert(marker::Const);
+            }
+        }
+        self.result
+    }
+}
+
+#[test]
+fn test_and_short_circuits_negated_matcher() {
+    let expensive = CountingMatcher::new(false);
+
+    let matcher = and!(ConstMatcher(1), Not::new(expensive.clone()));
+    assert!(!matcher.matches(None, &Context::default(), &0));
+    assert!(!matcher.matches(Some(&mut Extensions::new()), &Context::default(), &0));
+    assert_eq!(expensive.count(), 0);
+
+    assert!(matcher.matches(None, &Context::default(), &1));
+    assert_eq!(expensive.count(), 1);
+
+    let matcher = ConstMatcher(1).and(expensive.clone().not());
+    assert!(!matcher.matches(None, &Context::default(), &0));
+    assert_eq!(expensive.count(), 1);
+}
+
+#[test]
+fn test_or_short_circuits_negated_matcher() {
+    let expensive = CountingMatcher::new(true);
+
+    let matcher = or!(ConstMatcher(1), Not::new(expensive.clone()));
+    assert!(matcher.matches(None, &Context::default(), &1));
+    assert!(matcher.matches(Some(&mut Extensions::new()), &Context::default(), &1));
+    assert_eq!(expensive.count(), 0);
+
+    assert!(!matcher.matches(None, &Context::default(), &0));
+    assert_eq!(expensive.count(), 1);
+
+    let matcher = ConstMatcher(1).or(expensive.clone().not());
+    assert!(matcher.matches(None, &Context::default(), &1));
+    assert_eq!(expensive.count(), 1);
+}
+
+#[test]
+fn test_evaluation_order() {
+    let first = CountingMatcher::new(false);
+    let second = CountingMatcher::new(true);
+    let third = CountingMatcher::new(true);
+
+    // and: stops at the first matcher that does not match
+    let matcher = and!(second.clone(), first.clone(), third.clone());
+    assert!(!matcher.matches(None, &Context::default(), &0));
+    assert_eq!((first.count(), second.count(), third.count()), (1, 1, 0));
+
+    // or: stops at the first matcher that matches
+    let matcher = or!(first.clone(), second.clone(), third.clone());
+    assert!(matcher.matches(None, &Context::default(), &0));
+    assert_eq!((first.count(), second.count(), third.count()), (2, 2, 0));
+
+    // not: evaluates the inner matcher exactly once
+    assert!(!Not::new(second.clone()).matches(None, &Context::default(), &0));
+    assert_eq!(second.count(), 3);
+}
+
+#[test]
+fn test_iter_short_circuits() {
+    let matchers = [
+        CountingMatcher::new(true),
+        CountingMatcher::new(false),
+        CountingMatcher::new(true),
+    ];
+
+    assert!(!matchers.iter().matches_and(None, &Context::default(), &0));
+    assert_eq!(
+        matchers
+            .iter()
+            .map(CountingMatcher::count)
+            .collect::<Vec<_>>(),
+        [1, 1, 0]
+    );
+
+    assert!(matchers.iter().matches_or(None, &Context::default(), &0));
+    assert_eq!(
+        matchers
+            .iter()
+            .map(CountingMatcher::count)
+            .collect::<Vec<_>>(),
+        [2, 1, 0]
+    );
+}
+
+#[test]
+fn test_not_does_not_record_extensions() {
+    let matcher = and!(ConstMatcher(0), Not::new(CountingMatcher::new(false)));
+    let mut ext = Extensions::new();
+    assert!(matcher.matches(Some(&mut ext), &Context::default(), &0));
+    assert!(ext.get::<marker::Const>().is_some());
+
+    let matcher = Not::new(OddMatcher);
+    let mut ext = Extensions::new();
+    assert!(!matcher.matches(Some(&mut ext), &Context::default(), &1));
+    assert!(ext.get::<marker::Odd>().is_none());
+}
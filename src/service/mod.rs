@@ -23,7 +23,7 @@ pub mod layer;
 pub use layer::Layer;
 
 mod builder;
-pub use builder::ServiceBuilder;
+pub use builder::{DescribeLayers, ServiceBuilder, StackDescription};
 
 mod identity;
 pub use identity::IdentityService;
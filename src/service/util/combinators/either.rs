@@ -124,3 +124,142 @@ create_either!(Either6, A, B, C, D, E, F,);
 create_either!(Either7, A, B, C, D, E, F, G,);
 create_either!(Either8, A, B, C, D, E, F, G, H,);
 create_either!(Either9, A, B, C, D, E, F, G, H, I,);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::layer::limit::policy::{ConcurrentPolicy, LimitReached, PolicyOutput};
+    use crate::service::layer::limit::Policy;
+    use std::convert::Infallible;
+
+    /// A service which responds with its branch index.
+    #[derive(Debug, Clone)]
+    struct Branch(usize);
+
+    impl Service<(), ()> for Branch {
+        type Response = usize;
+        type Error = Infallible;
+
+        async fn serve(&self, _ctx: Context<()>, _req: ()) -> Result<Self::Response, Self::Error> {
+            Ok(self.0)
+        }
+    }
+
+    /// A layer which offsets the branch index of the wrapped service.
+    #[derive(Debug, Clone)]
+    struct Offset(usize);
+
+    impl Layer<Branch> for Offset {
+        type Service = Branch;
+
+        fn layer(&self, inner: Branch) -> Self::Service {
+            Branch(inner.0 + self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_either5_service() {
+        type E = Either5<Branch, Branch, Branch, Branch, Branch>;
+        let branches: [E; 5] = [
+            Either5::A(Branch(0)),
+            Either5::B(Branch(1)),
+            Either5::C(Branch(2)),
+            Either5::D(Branch(3)),
+            Either5::E(Branch(4)),
+        ];
+        for (expected, branch) in branches.into_iter().enumerate() {
+            assert_eq!(
+                branch.serve(Context::default(), ()).await.unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_either6_service() {
+        type E = Either6<Branch, Branch, Branch, Branch, Branch, Branch>;
+        let branches: [E; 6] = [
+            Either6::A(Branch(0)),
+            Either6::B(Branch(1)),
+            Either6::C(Branch(2)),
+            Either6::D(Branch(3)),
+            Either6::E(Branch(4)),
+            Either6::F(Branch(5)),
+        ];
+        for (expected, branch) in branches.into_iter().enumerate() {
+            assert_eq!(
+                branch.serve(Context::default(), ()).await.unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_either6_layer() {
+        type E = Either6<Offset, Offset, Offset, Offset, Offset, Offset>;
+        let layers: [E; 6] = [
+            Either6::A(Offset(10)),
+            Either6::B(Offset(20)),
+            Either6::C(Offset(30)),
+            Either6::D(Offset(40)),
+            Either6::E(Offset(50)),
+            Either6::F(Offset(60)),
+        ];
+        for (index, layer) in layers.into_iter().enumerate() {
+            let service = layer.layer(Branch(1));
+            // the layered service is wrapped in the same variant
+            let variant = match &service {
+                Either6::A(_) => 0,
+                Either6::B(_) => 1,
+                Either6::C(_) => 2,
+                Either6::D(_) => 3,
+                Either6::E(_) => 4,
+                Either6::F(_) => 5,
+            };
+            assert_eq!(variant, index);
+            assert_eq!(
+                service.serve(Context::default(), ()).await.unwrap(),
+                (index + 1) * 10 + 1
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_either5_policy() {
+        type E = Either5<
+            ConcurrentPolicy<()>,
+            ConcurrentPolicy<()>,
+            ConcurrentPolicy<()>,
+            ConcurrentPolicy<()>,
+            ConcurrentPolicy<()>,
+        >;
+        // only the selected branch is limited
+        let policies: [(E, bool); 5] = [
+            (Either5::A(ConcurrentPolicy::new(0)), false),
+            (Either5::B(ConcurrentPolicy::new(1)), true),
+            (Either5::C(ConcurrentPolicy::new(0)), false),
+            (Either5::D(ConcurrentPolicy::new(1)), true),
+            (Either5::E(ConcurrentPolicy::new(0)), false),
+        ];
+        for (index, (policy, allowed)) in policies.into_iter().enumerate() {
+            let result = policy.check(Context::default(), ()).await;
+            match result.output {
+                PolicyOutput::Ready(guard) => {
+                    assert!(allowed, "branch {index} should be aborted");
+                    let variant = match guard {
+                        Either5::A(_) => 0,
+                        Either5::B(_) => 1,
+                        Either5::C(_) => 2,
+                        Either5::D(_) => 3,
+                        Either5::E(_) => 4,
+                    };
+                    assert_eq!(variant, index);
+                }
+                PolicyOutput::Abort(LimitReached) => {
+                    assert!(!allowed, "branch {index} should be ready")
+                }
+                PolicyOutput::Retry => panic!("unexpected retry for branch {index}"),
+            }
+        }
+    }
+}
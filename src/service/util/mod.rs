@@ -2,4 +2,5 @@
 
 pub mod backoff;
 pub mod combinators;
+pub mod retry_budget;
 pub mod rng;
@@ -0,0 +1,291 @@
+//! A [`RetryBudget`] limits the amount of retries relative to the amount of requests,
+//! such that retries are throttled globally when the failure rate is high.
+//!
+//! Retrying failed requests improves the success rate when failures are sporadic,
+//! but amplifies the load on an upstream which is struggling already, as every
+//! failed request gets sent multiple times. A budget shared by all requests
+//! towards an upstream caps that amplification: each request deposits a fraction
+//! of a token (the retry ratio), and each retry has to withdraw a whole token.
+//! A minimum amount of retries per second is always allowed, such that
+//! low-traffic services can still retry.
+//!
+//! Deposits and withdrawals are only taken into account for a limited time (the ttl),
+//! such that the budget reflects the recent failure rate.
+//!
+//! # Example
+//!
+//! ```
+//! use rama::service::util::retry_budget::RetryBudget;
+//!
+//! // allow 10% of the requests to be retried, and at least 1 retry per second
+//! let budget = RetryBudget::new(0.1, 1).unwrap();
+//!
+//! // the budget is shared by all clones
+//! let shared = budget.clone();
+//! for _ in 0..100 {
+//!     shared.deposit();
+//! }
+//!
+//! let mut retries = 0;
+//! while budget.withdraw() {
+//!     retries += 1;
+//! }
+//! // 10 retries for the 100 requests, and the 10 retries of the minimum reserve
+//! assert_eq!(retries, 20);
+//! ```
+
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// The fixed point scale used to account for fractional tokens.
+const SCALE: u64 = 1000;
+
+/// A budget limiting the amount of retries relative to the amount of requests.
+///
+/// Cloning a [`RetryBudget`] shares the budget with the clone.
+///
+/// See the [module docs](self) for more information.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Tokens deposited per request, scaled by [`SCALE`].
+    deposit: u64,
+    /// Tokens always available within a ttl, scaled by [`SCALE`].
+    reserve: u64,
+    /// Duration of a generation, being half of the ttl.
+    generation: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    current: Generation,
+    previous: Generation,
+}
+
+/// The deposits and withdrawals made within a single generation, scaled by [`SCALE`].
+#[derive(Debug, Default, Clone, Copy)]
+struct Generation {
+    deposited: u64,
+    withdrawn: u64,
+}
+
+impl RetryBudget {
+    /// The default time that deposits and withdrawals are taken into account for.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(10);
+
+    /// Create a new [`RetryBudget`], allowing `ratio` retries per request
+    /// (e.g. `0.2` for 20% of the requests) and at least `min_per_sec` retries per second.
+    ///
+    /// # Error
+    ///
+    /// Returns a config validation error if:
+    /// - `ratio` is not finite
+    /// - `ratio` < `0.0`
+    /// - `ratio` > `1000.0`
+    pub fn new(ratio: f64, min_per_sec: u32) -> Result<Self, InvalidRetryBudget> {
+        Self::with_ttl(ratio, min_per_sec, Self::DEFAULT_TTL)
+    }
+
+    /// Create a new [`RetryBudget`] similar to [`RetryBudget::new`],
+    /// taking deposits and withdrawals into account for the given `ttl`
+    /// instead of [`RetryBudget::DEFAULT_TTL`].
+    ///
+    /// # Error
+    ///
+    /// Returns a config validation error if:
+    /// - `ratio` is not finite
+    /// - `ratio` < `0.0`
+    /// - `ratio` > `1000.0`
+    /// - `ttl` < 1 second
+    /// - `ttl` > 60 seconds
+    pub fn with_ttl(
+        ratio: f64,
+        min_per_sec: u32,
+        ttl: Duration,
+    ) -> Result<Self, InvalidRetryBudget> {
+        if !ratio.is_finite() {
+            return Err(InvalidRetryBudget("ratio must be finite"));
+        }
+        if ratio < 0.0 {
+            return Err(InvalidRetryBudget("ratio must not be negative"));
+        }
+        if ratio > 1000.0 {
+            return Err(InvalidRetryBudget("ratio must not be greater than 1000"));
+        }
+        if ttl < Duration::from_secs(1) {
+            return Err(InvalidRetryBudget("ttl must be at least 1 second"));
+        }
+        if ttl > Duration::from_secs(60) {
+            return Err(InvalidRetryBudget(
+                "ttl must not be greater than 60 seconds",
+            ));
+        }
+
+        let reserve = (min_per_sec as f64 * ttl.as_secs_f64()) as u64 * SCALE;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                deposit: (ratio * SCALE as f64) as u64,
+                reserve,
+                generation: ttl / 2,
+                state: Mutex::new(State {
+                    started: Instant::now(),
+                    current: Generation::default(),
+                    previous: Generation::default(),
+                }),
+            }),
+        })
+    }
+
+    /// Record a request, depositing its share of tokens into the budget.
+    ///
+    /// This is to be called once for every original request, not for its retries.
+    pub fn deposit(&self) {
+        let mut state = self.state();
+        state.current.deposited += self.inner.deposit;
+    }
+
+    /// Try to withdraw a token for a retry from the budget.
+    ///
+    /// Returns `true` if the retry is allowed, in which case the token is withdrawn,
+    /// or `false` if the budget is exhausted, in which case the retry should not be made.
+    pub fn withdraw(&self) -> bool {
+        let mut state = self.state();
+        if state.balance(self.inner.reserve) < SCALE {
+            return false;
+        }
+        state.current.withdrawn += SCALE;
+        true
+    }
+
+    /// The amount of whole retries which can currently be withdrawn from the budget.
+    pub fn available(&self) -> u64 {
+        self.state().balance(self.inner.reserve) / SCALE
+    }
+
+    /// Lock the state, expiring the generations which outlived the ttl.
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        let mut state = self.inner.state.lock().unwrap();
+        let elapsed = state.started.elapsed();
+        if elapsed >= self.inner.generation {
+            state.previous = if elapsed >= self.inner.generation * 2 {
+                Generation::default()
+            } else {
+                state.current
+            };
+            state.current = Generation::default();
+            state.started = Instant::now();
+        }
+        state
+    }
+}
+
+impl Default for RetryBudget {
+    /// A budget allowing 20% of the requests to be retried, and at least 10 retries per second.
+    fn default() -> Self {
+        Self::new(0.2, 10).expect("Unable to create RetryBudget")
+    }
+}
+
+impl State {
+    fn balance(&self, reserve: u64) -> u64 {
+        let deposited = self.current.deposited + self.previous.deposited + reserve;
+        let withdrawn = self.current.withdrawn + self.previous.withdrawn;
+        deposited.saturating_sub(withdrawn)
+    }
+}
+
+/// Retry budget validation error.
+#[derive(Debug)]
+pub struct InvalidRetryBudget(&'static str);
+
+impl Display for InvalidRetryBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid retry budget: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRetryBudget {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_budget_invalid() {
+        assert!(RetryBudget::new(f64::NAN, 0).is_err());
+        assert!(RetryBudget::new(-0.1, 0).is_err());
+        assert!(RetryBudget::new(1000.1, 0).is_err());
+        assert!(RetryBudget::with_ttl(0.1, 0, Duration::from_millis(999)).is_err());
+        assert!(RetryBudget::with_ttl(0.1, 0, Duration::from_secs(61)).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_budget_under_budget() {
+        let budget = RetryBudget::new(0.5, 0).unwrap();
+        assert!(!budget.withdraw());
+
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert_eq!(budget.available(), 5);
+        for _ in 0..5 {
+            assert!(budget.withdraw());
+        }
+        assert!(!budget.withdraw());
+
+        // each request makes room for another retry once it adds up to a whole token
+        budget.deposit();
+        assert!(!budget.withdraw());
+        budget.deposit();
+        assert!(budget.withdraw());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_budget_exhausted() {
+        // 10% of the requests, as well as 1 retry per second (10 for the default ttl)
+        let budget = RetryBudget::new(0.1, 1).unwrap();
+        let shared = budget.clone();
+
+        for _ in 0..100 {
+            shared.deposit();
+        }
+        for _ in 0..20 {
+            assert!(budget.withdraw());
+        }
+        // exhausted for all clones
+        assert!(!budget.withdraw());
+        assert!(!shared.withdraw());
+        assert_eq!(shared.available(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_budget_expires() {
+        let budget = RetryBudget::with_ttl(1.0, 0, Duration::from_secs(2)).unwrap();
+        for _ in 0..4 {
+            budget.deposit();
+        }
+        assert!(budget.withdraw());
+        assert!(budget.withdraw());
+
+        // still within the ttl
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(budget.available(), 2);
+
+        // deposits expire, and so do withdrawals
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(budget.available(), 0);
+        budget.deposit();
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+    }
+}
@@ -1,7 +1,8 @@
 //! [PRNG] utilities for middleware.
 //!
 //! This module provides a generic [`Rng`] trait and a [`HasherRng`] that
-//! implements the trait based on [`RandomState`] or any other [`Hasher`].
+//! implements the trait based on [`RandomState`] or any other [`Hasher`],
+//! such as the seeded [`StableState`] for a reproducible sequence.
 //!
 //! [PRNG]: https://en.wikipedia.org/wiki/Pseudorandom_number_generator
 
@@ -105,11 +106,78 @@ where
     }
 }
 
+/// A [`BuildHasher`] whose hashes only depend on its seed, and not on the process
+/// (unlike [`RandomState`]), such that a [`HasherRng`] using it generates
+/// the same sequence for the same seed, and hashes are stable across restarts.
+///
+/// The hashes are computed using FNV-1a, followed by the SplitMix64 finalizer
+/// to spread the bits evenly. This is not a cryptographic hash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StableState {
+    seed: u64,
+}
+
+impl StableState {
+    /// Create a new [`StableState`] using the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for StableState {
+    type Hasher = StableHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        StableHasher {
+            hash: FNV_OFFSET_BASIS ^ self.seed,
+        }
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// The [`Hasher`] built by a [`StableState`].
+#[derive(Debug, Clone)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = self.hash;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^ (hash >> 31)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use quickcheck::*;
 
+    #[test]
+    fn stable_state_hashes() {
+        // hashes of the same bytes only depend on the seed
+        let hash = StableState::new(1).hash_one(b"key");
+        assert_eq!(hash, StableState::new(1).hash_one(b"key"));
+        assert_ne!(hash, StableState::new(2).hash_one(b"key"));
+        assert_ne!(hash, StableState::new(1).hash_one(b"other key"));
+
+        let mut a = HasherRng::with_hasher(StableState::new(7));
+        let mut b = HasherRng::with_hasher(StableState::new(7));
+        let sequence: Vec<_> = (0..8).map(|_| a.next_u64()).collect();
+        assert_eq!(sequence, (0..8).map(|_| b.next_u64()).collect::<Vec<_>>());
+    }
+
     quickcheck! {
         fn next_f64(counter: u64) -> TestResult {
             let mut rng = HasherRng {
@@ -0,0 +1,321 @@
+//! Copy utilities for [`Stream`] types, using an [`AdaptiveBuffer`].
+//!
+//! [`Stream`]: crate::stream::Stream
+
+use std::{pin::Pin, task::Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// The default initial (and minimum) size of an [`AdaptiveBuffer`].
+const DEFAULT_INITIAL_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The default maximum size of an [`AdaptiveBuffer`].
+const DEFAULT_MAX_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A copy buffer which adapts its size to the observed throughput.
+///
+/// The buffer starts at its initial size and doubles (up to its max size)
+/// each time a read fills it completely, reducing the amount of syscalls
+/// for high-throughput copies. When a read uses no more than a quarter of the buffer,
+/// it is halved again (down to its initial size), releasing the memory
+/// for connections that turn out to be mostly idle or chatty.
+#[derive(Debug)]
+pub struct AdaptiveBuffer {
+    buf: Vec<u8>,
+    initial_size: usize,
+    max_size: usize,
+}
+
+impl AdaptiveBuffer {
+    /// Create a new [`AdaptiveBuffer`] starting at `initial_size` bytes,
+    /// growing up to at most `max_size` bytes.
+    ///
+    /// The `initial_size` is also the minimum size the buffer shrinks to.
+    /// A `max_size` smaller than the `initial_size` results in a buffer of fixed size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_size` is zero.
+    pub fn new(initial_size: usize, max_size: usize) -> Self {
+        assert!(initial_size > 0, "initial buffer size must be non-zero");
+        Self {
+            buf: vec![0; initial_size],
+            initial_size,
+            max_size: max_size.max(initial_size),
+        }
+    }
+
+    /// Returns the current size of the buffer.
+    pub fn size(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the initial (and minimum) size of the buffer.
+    pub fn initial_size(&self) -> usize {
+        self.initial_size
+    }
+
+    /// Returns the maximum size of the buffer.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the buffer to read into.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+
+    /// Records that a read of `n` bytes was done into the buffer,
+    /// growing or shrinking the buffer accordingly.
+    pub fn record(&mut self, n: usize) {
+        let size = self.buf.len();
+        if n >= size && size < self.max_size {
+            let size = size.saturating_mul(2).min(self.max_size);
+            self.buf.resize(size, 0);
+        } else if n <= size / 4 && size > self.initial_size {
+            let size = (size / 2).max(self.initial_size);
+            self.buf.truncate(size);
+            self.buf.shrink_to_fit();
+        }
+    }
+}
+
+impl Default for AdaptiveBuffer {
+    /// Create a new [`AdaptiveBuffer`] starting at 8 KiB, growing up to at most 64 KiB.
+    fn default() -> Self {
+        Self::new(DEFAULT_INITIAL_BUFFER_SIZE, DEFAULT_MAX_BUFFER_SIZE)
+    }
+}
+
+/// Copies all bytes from the reader into the writer, using the given [`AdaptiveBuffer`],
+/// until the reader reaches EOF.
+///
+/// The writer is flushed whenever the reader has no data readily available,
+/// such that buffered data does not linger in the writer while waiting on the reader,
+/// as well as once the reader reaches EOF.
+///
+/// Returns the total amount of bytes copied.
+pub async fn copy<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer: &mut AdaptiveBuffer,
+) -> std::io::Result<u64>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut total = 0u64;
+    let mut flushed = true;
+    loop {
+        let n = match try_read(reader, buffer.as_mut_slice()).await {
+            Some(result) => result?,
+            None => {
+                if !flushed {
+                    writer.flush().await?;
+                    flushed = true;
+                }
+                reader.read(buffer.as_mut_slice()).await?
+            }
+        };
+        if n == 0 {
+            if !flushed {
+                writer.flush().await?;
+            }
+            return Ok(total);
+        }
+        writer.write_all(&buffer.as_mut_slice()[..n]).await?;
+        flushed = false;
+        total += n as u64;
+        buffer.record(n);
+    }
+}
+
+/// Copies bytes in both directions between `a` and `b`, using an [`AdaptiveBuffer`]
+/// per direction, until both reach EOF.
+///
+/// When one side reaches EOF, the write side of the other is shut down,
+/// while the copy in the opposite direction continues.
+/// Returns as soon as either direction fails.
+///
+/// Returns the total amount of bytes copied from `a` to `b` and from `b` to `a`.
+pub async fn copy_bidirectional<A, B>(
+    a: &mut A,
+    b: &mut B,
+    a_to_b: &mut AdaptiveBuffer,
+    b_to_a: &mut AdaptiveBuffer,
+) -> std::io::Result<(u64, u64)>
+where
+    A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    let (mut a_reader, mut a_writer) = tokio::io::split(a);
+    let (mut b_reader, mut b_writer) = tokio::io::split(b);
+    tokio::try_join!(
+        async {
+            let n = copy(&mut a_reader, &mut b_writer, a_to_b).await?;
+            b_writer.shutdown().await?;
+            Ok(n)
+        },
+        async {
+            let n = copy(&mut b_reader, &mut a_writer, b_to_a).await?;
+            a_writer.shutdown().await?;
+            Ok(n)
+        },
+    )
+}
+
+/// Reads into the given buffer in case the reader has data readily available,
+/// returning `None` in case the read would block.
+async fn try_read<R>(reader: &mut R, buf: &mut [u8]) -> Option<std::io::Result<usize>>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut buf = ReadBuf::new(buf);
+    std::future::poll_fn(|cx| match Pin::new(&mut *reader).poll_read(cx, &mut buf) {
+        Poll::Ready(result) => Poll::Ready(Some(result.map(|_| buf.filled().len()))),
+        Poll::Pending => Poll::Ready(None),
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_test::io::Builder;
+
+    #[test]
+    fn test_adaptive_buffer_grows_up_to_max() {
+        let mut buffer = AdaptiveBuffer::new(1024, 8 * 1024);
+        assert_eq!(buffer.size(), 1024);
+
+        let mut sizes = Vec::new();
+        for _ in 0..6 {
+            let size = buffer.size();
+            buffer.record(size);
+            sizes.push(buffer.size());
+        }
+        assert_eq!(
+            sizes,
+            vec![2 * 1024, 4 * 1024, 8 * 1024, 8 * 1024, 8 * 1024, 8 * 1024]
+        );
+    }
+
+    #[test]
+    fn test_adaptive_buffer_shrinks_down_to_initial() {
+        let mut buffer = AdaptiveBuffer::new(1024, 8 * 1024);
+        for _ in 0..3 {
+            let size = buffer.size();
+            buffer.record(size);
+        }
+        assert_eq!(buffer.size(), 8 * 1024);
+
+        // partially used reads keep the size as is
+        buffer.record(4 * 1024);
+        assert_eq!(buffer.size(), 8 * 1024);
+
+        let mut sizes = Vec::new();
+        for _ in 0..5 {
+            buffer.record(10);
+            sizes.push(buffer.size());
+        }
+        assert_eq!(sizes, vec![4 * 1024, 2 * 1024, 1024, 1024, 1024]);
+    }
+
+    #[test]
+    fn test_adaptive_buffer_fixed_size() {
+        let mut buffer = AdaptiveBuffer::new(1024, 512);
+        assert_eq!(buffer.max_size(), 1024);
+        buffer.record(1024);
+        assert_eq!(buffer.size(), 1024);
+        buffer.record(1);
+        assert_eq!(buffer.size(), 1024);
+    }
+
+    #[tokio::test]
+    async fn test_copy_grows_buffer_under_sustained_full_reads() {
+        let data = [42u8; 16];
+        let mut reader = Builder::new()
+            .read(&data[..4])
+            .read(&data[..8])
+            .read(&data[..16])
+            .read(&data[..16])
+            .build();
+        let mut writer = Builder::new()
+            .write(&data[..4])
+            .write(&data[..8])
+            .write(&data[..16])
+            .write(&data[..16])
+            .build();
+
+        let mut buffer = AdaptiveBuffer::new(4, 16);
+        let n = copy(&mut reader, &mut writer, &mut buffer).await.unwrap();
+        assert_eq!(n, 44);
+        assert_eq!(buffer.size(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_copy_flushes_when_reader_is_pending() {
+        let (mut client, mut reader) = tokio::io::duplex(64);
+        let (writer, mut server) = tokio::io::duplex(64);
+        let mut writer = tokio::io::BufWriter::new(writer);
+
+        let handle = tokio::spawn(async move {
+            let mut buffer = AdaptiveBuffer::default();
+            copy(&mut reader, &mut writer, &mut buffer).await
+        });
+
+        // the data is received while the reader is still open
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            server.read_exact(&mut buf),
+        )
+        .await
+        .expect("data to be flushed")
+        .unwrap();
+        assert_eq!(&buf, b"hello");
+
+        client.write_all(b"world").await.unwrap();
+        drop(client);
+        assert_eq!(handle.await.unwrap().unwrap(), 10);
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"world");
+    }
+
+    #[tokio::test]
+    async fn test_copy_bidirectional() {
+        let (mut client, mut a) = tokio::io::duplex(64);
+        let (mut b, mut server) = tokio::io::duplex(64);
+
+        let handle = tokio::spawn(async move {
+            let mut a_to_b = AdaptiveBuffer::new(4, 16);
+            let mut b_to_a = AdaptiveBuffer::new(4, 16);
+            let result = copy_bidirectional(&mut a, &mut b, &mut a_to_b, &mut b_to_a).await;
+            (result, a_to_b.size(), b_to_a.size())
+        });
+
+        client.write_all(b"hello, server").await.unwrap();
+        let mut buf = [0; 13];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello, server");
+
+        // the client is done writing, while the server still responds
+        client.shutdown().await.unwrap();
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.unwrap();
+        assert!(buf.is_empty());
+
+        server.write_all(b"hi").await.unwrap();
+        drop(server);
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hi");
+
+        let (result, a_to_b_size, b_to_a_size) = handle.await.unwrap();
+        assert_eq!(result.unwrap(), (13, 2));
+        assert!(a_to_b_size > 4);
+        assert_eq!(b_to_a_size, 4);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    service::{Context, Layer, Service},
+    stream::Stream,
+};
+use std::{future::Future, time::Duration};
+
+mod stream;
+pub use stream::IoTimeoutStream;
+
+/// A [`Service`] that wraps a [`Service`]'s input IO [`Stream`] with per-operation
+/// read and/or write timeouts, see [`IoTimeoutStream`] for more information.
+///
+/// Once a single read or write operation stalls for longer than its timeout,
+/// it fails with an [`std::io::ErrorKind::TimedOut`] error,
+/// which usually results in the connection being closed.
+///
+/// [`Service`]: crate::service::Service
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug, Clone)]
+pub struct IoTimeoutService<S> {
+    inner: S,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl<S> IoTimeoutService<S> {
+    define_inner_service_accessors!();
+}
+
+impl<State, S, IO> Service<State, IO> for IoTimeoutService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, IoTimeoutStream<IO>>,
+    IO: Stream,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    fn serve(
+        &self,
+        ctx: Context<State>,
+        stream: IO,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        let stream = IoTimeoutStream::new(stream, self.read_timeout, self.write_timeout);
+        self.inner.serve(ctx, stream)
+    }
+}
+
+/// A [`Layer`] that wraps a [`Service`]'s input IO [`Stream`] with per-operation
+/// read and/or write timeouts.
+///
+/// No timeouts are applied by default,
+/// use [`IoTimeoutLayer::read_timeout`] and [`IoTimeoutLayer::write_timeout`]
+/// to configure them independently.
+///
+/// [`Layer`]: crate::service::Layer
+/// [`Service`]: crate::service::Service
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug, Clone, Default)]
+pub struct IoTimeoutLayer {
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl IoTimeoutLayer {
+    /// Create a new [`IoTimeoutLayer`], without any timeouts configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail a single read operation in case it does not complete within the given timeout.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail a single write (or flush) operation in case it does not complete
+    /// within the given timeout.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+}
+
+impl<S> Layer<S> for IoTimeoutLayer {
+    type Service = IoTimeoutService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IoTimeoutService {
+            inner,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::{convert::Infallible, io};
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_io_timeout_layer() {
+        let service = IoTimeoutLayer::new()
+            .read_timeout(Duration::from_secs(5))
+            .layer(service_fn(
+                |mut stream: IoTimeoutStream<tokio::io::DuplexStream>| async move {
+                    let mut buf = [0u8; 8];
+                    Ok::<_, Infallible>(stream.read(&mut buf).await)
+                },
+            ));
+
+        let (_client, server) = tokio::io::duplex(64);
+        let result = service.serve(Context::default(), server).await.unwrap();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+}
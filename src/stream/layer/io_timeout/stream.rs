@@ -0,0 +1,228 @@
+//! Provides [`IoTimeoutStream`] which wraps a [`AsyncRead`] and/or [`AsyncWrite`]
+//! in order to apply a timeout to each individual read and/or write operation.
+//!
+//! [`AsyncRead`]: crate::stream::AsyncRead
+//! [`AsyncWrite`]: crate::stream::AsyncWrite
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+use crate::stream::Socket;
+
+pin_project! {
+    /// A wrapper around a [`AsyncRead`] and/or [`AsyncWrite`] that fails
+    /// a single read or write operation with an [`io::ErrorKind::TimedOut`] error
+    /// in case it does not make progress within the configured timeout.
+    ///
+    /// The timeout applies to each operation individually, starting the moment
+    /// the operation is first polled without being ready, such that idle streams
+    /// which are not being read from or written to never time out.
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    #[derive(Debug)]
+    pub struct IoTimeoutStream<S> {
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+        read_deadline: Option<Pin<Box<Sleep>>>,
+        write_deadline: Option<Pin<Box<Sleep>>>,
+        #[pin]
+        stream: S,
+    }
+}
+
+impl<S> IoTimeoutStream<S> {
+    /// Create a new [`IoTimeoutStream`] that wraps the given [`AsyncRead`] and/or [`AsyncWrite`],
+    /// applying the given (optional) timeouts to its read and write operations.
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    pub fn new(stream: S, read_timeout: Option<Duration>, write_timeout: Option<Duration>) -> Self {
+        Self {
+            read_timeout,
+            write_timeout,
+            read_deadline: None,
+            write_deadline: None,
+            stream,
+        }
+    }
+
+    /// Get a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get the inner [`AsyncRead`] and/or [`AsyncWrite`] stream,
+    /// dropping the timeouts applied to it.
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// Poll the result of an IO operation, failing it in case it is pending
+/// for longer than the (optional) timeout.
+fn poll_timeout<T>(
+    result: Poll<io::Result<T>>,
+    timeout: Option<Duration>,
+    deadline: &mut Option<Pin<Box<Sleep>>>,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<T>> {
+    let timeout = match (result, timeout) {
+        (Poll::Pending, Some(timeout)) => timeout,
+        (result, _) => {
+            *deadline = None;
+            return result;
+        }
+    };
+
+    let sleep = deadline.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+    match sleep.as_mut().poll(cx) {
+        Poll::Ready(()) => {
+            *deadline = None;
+            Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "io operation timed out",
+            )))
+        }
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+impl<S> AsyncRead for IoTimeoutStream<S>
+where
+    S: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let result = this.stream.poll_read(cx, buf);
+        poll_timeout(result, *this.read_timeout, this.read_deadline, cx)
+    }
+}
+
+impl<S> AsyncWrite for IoTimeoutStream<S>
+where
+    S: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_write(cx, buf);
+        poll_timeout(result, *this.write_timeout, this.write_deadline, cx)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_flush(cx);
+        poll_timeout(result, *this.write_timeout, this.write_deadline, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_shutdown(cx);
+        poll_timeout(result, *this.write_timeout, this.write_deadline, cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_write_vectored(cx, bufs);
+        poll_timeout(result, *this.write_timeout, this.write_deadline, cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+}
+
+impl<S> Socket for IoTimeoutStream<S>
+where
+    S: Socket,
+{
+    fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_timeout() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut stream = IoTimeoutStream::new(server, Some(Duration::from_secs(1)), None);
+        let mut client = client;
+
+        client.write_all(b"foo").await.unwrap();
+        let mut buf = [0u8; 3];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"foo");
+
+        // the peer stalls
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        // a new operation starts a new timeout
+        let read = tokio::spawn(async move {
+            let mut buf = [0u8; 3];
+            stream.read_exact(&mut buf).await.map(|_| buf)
+        });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        client.write_all(b"bar").await.unwrap();
+        assert_eq!(&read.await.unwrap().unwrap(), b"bar");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_timeout() {
+        let (client, server) = tokio::io::duplex(4);
+        let mut stream = IoTimeoutStream::new(server, None, Some(Duration::from_secs(1)));
+
+        // the buffer of the peer is full and it does not read
+        stream.write_all(b"foo!").await.unwrap();
+        let err = stream.write_all(b"bar").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        drop(client);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_timeouts() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut stream = IoTimeoutStream::new(server, None, None);
+
+        let read = tokio::spawn(async move {
+            let mut buf = [0u8; 3];
+            stream.read_exact(&mut buf).await.map(|_| buf)
+        });
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        client.write_all(b"baz").await.unwrap();
+        assert_eq!(&read.await.unwrap().unwrap(), b"baz");
+    }
+}
@@ -3,4 +3,15 @@
 //! Examples are services that can operate directly on a `TCP`, `TLS` or `UDP` stream.
 
 mod tracker;
-pub use tracker::{BytesRWTrackerHandle, BytesTrackerLayer, BytesTrackerService};
+pub use tracker::{
+    BytesRWTrackerHandle, BytesTrackerLayer, BytesTrackerService, PeerBytes, PeerBytesTracker,
+};
+
+mod io_timeout;
+pub use io_timeout::{IoTimeoutLayer, IoTimeoutService, IoTimeoutStream};
+
+mod tee;
+pub use tee::{TeeChunk, TeeDirection, TeeLayer, TeeService, TeeSink, TeeStream, TeeStreamId};
+
+mod trace;
+pub use trace::{StreamTraceLayer, StreamTraceService};
@@ -0,0 +1,117 @@
+use crate::{
+    service::{Context, Layer, Service},
+    stream::Stream,
+};
+use std::future::Future;
+
+mod stream;
+pub use stream::{TeeChunk, TeeDirection, TeeSink, TeeStream, TeeStreamId};
+
+/// A [`Service`] that wraps a [`Service`]'s input IO [`Stream`] with a [`TeeStream`],
+/// copying all bytes read from and written to it to a secondary [`TeeSink`]
+/// (e.g. for traffic capture or auditing).
+///
+/// The main path is never blocked by the sink: in case its observer is too slow,
+/// chunks are dropped instead, see [`TeeSink`] for more information.
+///
+/// [`Service`]: crate::service::Service
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug, Clone)]
+pub struct TeeService<S> {
+    inner: S,
+    sink: TeeSink,
+}
+
+impl<S> TeeService<S> {
+    define_inner_service_accessors!();
+}
+
+impl<State, S, IO> Service<State, IO> for TeeService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, TeeStream<IO>>,
+    IO: Stream,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    fn serve(
+        &self,
+        ctx: Context<State>,
+        stream: IO,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        let stream = TeeStream::new(stream, self.sink.clone());
+        self.inner.serve(ctx, stream)
+    }
+}
+
+/// A [`Layer`] that wraps a [`Service`]'s input IO [`Stream`] with a [`TeeStream`],
+/// copying all bytes of the streams it serves to the same [`TeeSink`].
+///
+/// [`Layer`]: crate::service::Layer
+/// [`Service`]: crate::service::Service
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug, Clone)]
+pub struct TeeLayer {
+    sink: TeeSink,
+}
+
+impl TeeLayer {
+    /// Create a new [`TeeLayer`] copying the bytes of its streams to the given [`TeeSink`].
+    pub fn new(sink: TeeSink) -> Self {
+        Self { sink }
+    }
+
+    /// Get a reference to the [`TeeSink`] of this layer,
+    /// e.g. to inspect the number of dropped chunks.
+    pub fn sink(&self) -> &TeeSink {
+        &self.sink
+    }
+}
+
+impl<S> Layer<S> for TeeLayer {
+    type Service = TeeService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TeeService {
+            inner,
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::convert::Infallible;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_tee_layer() {
+        let (sink, mut receiver) = TeeSink::channel(8);
+        let service = TeeLayer::new(sink).layer(service_fn(
+            |mut stream: TeeStream<tokio::io::DuplexStream>| async move {
+                let mut buf = [0u8; 5];
+                stream.read_exact(&mut buf).await.unwrap();
+                stream.write_all(&buf).await.unwrap();
+                Ok::<_, Infallible>(())
+            },
+        ));
+
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(b"hello").await.unwrap();
+        service.serve(Context::default(), server).await.unwrap();
+
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        let read = receiver.recv().await.unwrap();
+        assert_eq!(read.direction(), TeeDirection::Read);
+        assert_eq!(read.bytes(), "hello");
+        let written = receiver.recv().await.unwrap();
+        assert_eq!(written.direction(), TeeDirection::Write);
+        assert_eq!(written.into_bytes(), "hello");
+    }
+}
@@ -0,0 +1,426 @@
+//! Provides [`TeeStream`] which wraps a [`AsyncRead`] and/or [`AsyncWrite`]
+//! in order to copy all bytes read from and written to it to a [`TeeSink`].
+//!
+//! [`AsyncRead`]: crate::stream::AsyncRead
+//! [`AsyncWrite`]: crate::stream::AsyncWrite
+
+use std::io::IoSlice;
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+
+use crate::stream::Socket;
+
+/// The direction of the bytes captured in a [`TeeChunk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TeeDirection {
+    /// The bytes were read from the stream.
+    Read,
+    /// The bytes were written to the stream.
+    Write,
+}
+
+/// The identifier of a [`TeeStream`], unique among the streams sharing the same [`TeeSink`],
+/// such that the chunks of concurrent streams can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TeeStreamId(u64);
+
+impl TeeStreamId {
+    /// Get the identifier as a number, assigned in the order
+    /// in which the streams of a [`TeeSink`] were created, starting at `0`.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A chunk of bytes read from or written to a [`TeeStream`],
+/// as received by the observer of its [`TeeSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeeChunk {
+    stream: TeeStreamId,
+    direction: TeeDirection,
+    bytes: Bytes,
+}
+
+impl TeeChunk {
+    /// The identifier of the stream the bytes were captured from.
+    pub fn stream_id(&self) -> TeeStreamId {
+        self.stream
+    }
+
+    /// The direction of the captured bytes.
+    pub fn direction(&self) -> TeeDirection {
+        self.direction
+    }
+
+    /// The captured bytes.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// Consume the chunk, returning the captured bytes.
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+}
+
+/// The secondary sink to which a [`TeeStream`] copies its bytes,
+/// backed by a bounded [`mpsc`] channel.
+///
+/// Copying never blocks the stream itself: chunks are dropped in case the
+/// channel is full (e.g. because the observer is too slow), and the number of
+/// dropped chunks is counted. Once the receiver is dropped, nothing is copied anymore.
+///
+/// Cloning the sink returns a handle to the same channel and counters.
+#[derive(Debug, Clone)]
+pub struct TeeSink {
+    sender: mpsc::Sender<TeeChunk>,
+    dropped: Arc<AtomicU64>,
+    next_stream_id: Arc<AtomicU64>,
+}
+
+impl TeeSink {
+    /// Create a new [`TeeSink`] sending its chunks to the given channel.
+    pub fn new(sender: mpsc::Sender<TeeChunk>) -> Self {
+        Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+            next_stream_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Create a new [`TeeSink`] together with the receiver of its chunks,
+    /// buffering at most `capacity` chunks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is `0`.
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<TeeChunk>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self::new(sender), receiver)
+    }
+
+    /// Returns the number of chunks dropped so far because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn stream_id(&self) -> TeeStreamId {
+        TeeStreamId(self.next_stream_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn send(&self, stream: TeeStreamId, direction: TeeDirection, bytes: &[u8]) {
+        if bytes.is_empty() || self.sender.is_closed() {
+            return;
+        }
+        self.send_bytes(stream, direction, Bytes::copy_from_slice(bytes));
+    }
+
+    /// Send the first `n` bytes of the given buffers as a single chunk.
+    fn send_vectored(
+        &self,
+        stream: TeeStreamId,
+        direction: TeeDirection,
+        bufs: &[IoSlice<'_>],
+        mut n: usize,
+    ) {
+        if n == 0 || self.sender.is_closed() {
+            return;
+        }
+        let mut bytes = BytesMut::with_capacity(n);
+        for buf in bufs {
+            let len = buf.len().min(n);
+            bytes.put_slice(&buf[..len]);
+            n -= len;
+            if n == 0 {
+                break;
+            }
+        }
+        self.send_bytes(stream, direction, bytes.freeze());
+    }
+
+    fn send_bytes(&self, stream: TeeStreamId, direction: TeeDirection, bytes: Bytes) {
+        let chunk = TeeChunk {
+            stream,
+            direction,
+            bytes,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.sender.try_send(chunk) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+pin_project! {
+    /// A wrapper around a [`AsyncRead`] and/or [`AsyncWrite`] that copies
+    /// all bytes read from and written to it to a [`TeeSink`],
+    /// without affecting the stream itself.
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    #[derive(Debug)]
+    pub struct TeeStream<S> {
+        id: TeeStreamId,
+        sink: TeeSink,
+        #[pin]
+        stream: S,
+    }
+}
+
+impl<S> TeeStream<S> {
+    /// Create a new [`TeeStream`] that wraps the given [`AsyncRead`] and/or [`AsyncWrite`],
+    /// copying its bytes to the given [`TeeSink`].
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    pub fn new(stream: S, sink: TeeSink) -> Self {
+        Self {
+            id: sink.stream_id(),
+            sink,
+            stream,
+        }
+    }
+
+    /// Get the identifier of this stream, as found in the chunks it copies to its [`TeeSink`].
+    pub fn id(&self) -> TeeStreamId {
+        self.id
+    }
+
+    /// Get a reference to the [`TeeSink`] of this stream.
+    pub fn sink(&self) -> &TeeSink {
+        &self.sink
+    }
+
+    /// Get a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get the inner [`AsyncRead`] and/or [`AsyncWrite`] stream,
+    /// no longer copying its bytes.
+    ///
+    /// [`AsyncRead`]: crate::stream::AsyncRead
+    /// [`AsyncWrite`]: crate::stream::AsyncWrite
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> AsyncRead for TeeStream<S>
+where
+    S: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+        let result = this.stream.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            this.sink
+                .send(*this.id, TeeDirection::Read, &buf.filled()[filled..]);
+        }
+        result
+    }
+}
+
+impl<S> AsyncWrite for TeeStream<S>
+where
+    S: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            this.sink.send(*this.id, TeeDirection::Write, &buf[..n]);
+        }
+        result
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        let result = this.stream.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = result {
+            this.sink
+                .send_vectored(*this.id, TeeDirection::Write, bufs, n);
+        }
+        result
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.project().stream.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.project().stream.poll_shutdown(cx)
+    }
+}
+
+impl<S> Socket for TeeStream<S>
+where
+    S: Socket,
+{
+    fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn collect(receiver: &mut mpsc::Receiver<TeeChunk>, direction: TeeDirection) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Ok(chunk) = receiver.try_recv() {
+            if chunk.direction() == direction {
+                bytes.extend_from_slice(chunk.bytes());
+            }
+        }
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_tee_stream() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let (sink, mut receiver) = TeeSink::channel(16);
+        let mut stream = TeeStream::new(server, sink);
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        stream.write_all(b"pong").await.unwrap();
+        let mut buf = [0u8; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        assert_eq!(stream.sink().dropped(), 0);
+        drop(stream);
+        let chunks: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(
+            chunks,
+            [
+                TeeChunk {
+                    stream: TeeStreamId(0),
+                    direction: TeeDirection::Read,
+                    bytes: Bytes::from_static(b"ping")
+                },
+                TeeChunk {
+                    stream: TeeStreamId(0),
+                    direction: TeeDirection::Write,
+                    bytes: Bytes::from_static(b"pong")
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tee_stream_slow_sink() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let (sink, mut receiver) = TeeSink::channel(2);
+        let mut stream = TeeStream::new(server, sink);
+
+        // the observer does not receive any chunks while the stream is used
+        for i in 0..5u8 {
+            stream.write_all(&[i; 8]).await.unwrap();
+        }
+        let mut buf = [0u8; 40];
+        client.read_exact(&mut buf).await.unwrap();
+        let expected: Vec<u8> = (0..5u8).flat_map(|i| [i; 8]).collect();
+        assert_eq!(&buf[..], &expected[..]);
+
+        // only the chunks which fit the buffer are captured
+        assert_eq!(stream.sink().dropped(), 3);
+        assert_eq!(
+            collect(&mut receiver, TeeDirection::Write),
+            [[0u8; 8], [1u8; 8]].concat()
+        );
+
+        // once the observer catches up, new chunks are captured again
+        stream.write_all(b"again").await.unwrap();
+        assert_eq!(collect(&mut receiver, TeeDirection::Write), b"again");
+
+        // a dropped observer does not affect the stream
+        drop(receiver);
+        stream.write_all(b"closed").await.unwrap();
+        let mut buf = [0u8; 11];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"againclosed");
+        assert_eq!(stream.sink().dropped(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_tee_stream_vectored_write() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let (sink, mut receiver) = TeeSink::channel(16);
+        let mut stream = TeeStream::new(server, sink);
+        assert_eq!(
+            stream.is_write_vectored(),
+            stream.get_ref().is_write_vectored()
+        );
+
+        let bufs = [IoSlice::new(b"hello "), IoSlice::new(b"world")];
+        let n = stream.write_vectored(&bufs).await.unwrap();
+        let mut buf = vec![0u8; n];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(collect(&mut receiver, TeeDirection::Write), buf);
+    }
+
+    #[tokio::test]
+    async fn test_tee_stream_ids() {
+        let (sink, mut receiver) = TeeSink::channel(16);
+        let (_client_a, server_a) = tokio::io::duplex(64);
+        let (_client_b, server_b) = tokio::io::duplex(64);
+        let mut a = TeeStream::new(server_a, sink.clone());
+        let mut b = TeeStream::new(server_b, sink);
+        assert_ne!(a.id(), b.id());
+
+        a.write_all(b"a").await.unwrap();
+        b.write_all(b"b").await.unwrap();
+        a.write_all(b"a").await.unwrap();
+
+        let chunks: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|chunk| (chunk.stream_id(), chunk.into_bytes()))
+            .collect();
+        assert_eq!(
+            chunks,
+            [
+                (a.id(), Bytes::from_static(b"a")),
+                (b.id(), Bytes::from_static(b"b")),
+                (a.id(), Bytes::from_static(b"a")),
+            ]
+        );
+    }
+}
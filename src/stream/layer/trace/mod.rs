@@ -0,0 +1,234 @@
+use crate::{
+    service::{Context, Layer, Service},
+    stream::{SocketInfo, Stream},
+};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::Instrument;
+
+/// The id of the next connection traced by a [`StreamTraceService`],
+/// unique within the process such that it can be used to correlate
+/// the logs of a single connection.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A [`Service`] that serves its input IO [`Stream`] within a tracing span,
+/// see [`StreamTraceLayer`] for more information.
+///
+/// [`Service`]: crate::service::Service
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug, Clone)]
+pub struct StreamTraceService<S> {
+    inner: S,
+}
+
+impl<S> StreamTraceService<S> {
+    /// Create a new [`StreamTraceService`].
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    define_inner_service_accessors!();
+}
+
+impl<State, S, IO> Service<State, IO> for StreamTraceService<S>
+where
+    State: Send + Sync + 'static,
+    S: Service<State, IO>,
+    IO: Stream,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    fn serve(
+        &self,
+        ctx: Context<State>,
+        stream: IO,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        let span = tracing::debug_span!(
+            "connection",
+            conn.id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            peer.addr = tracing::field::Empty,
+            local.addr = tracing::field::Empty,
+        );
+        if let Some(info) = ctx.get::<SocketInfo>() {
+            span.record("peer.addr", tracing::field::display(info.peer_addr()));
+            if let Some(local_addr) = info.local_addr() {
+                span.record("local.addr", tracing::field::display(local_addr));
+            }
+        }
+        self.inner.serve(ctx, stream).instrument(span)
+    }
+}
+
+/// A [`Layer`] that serves each connection (IO [`Stream`]) within a `connection` tracing span,
+/// entered for the lifetime of the connection, such that all logs of the services
+/// serving the connection are correlated to it.
+///
+/// The span (at the `DEBUG` level) records the following fields:
+///
+/// - `conn.id`: the id of the connection, unique within the process;
+/// - `peer.addr`: the peer address of the connection;
+/// - `local.addr`: the local address of the connection.
+///
+/// The addresses are taken from the [`SocketInfo`] found in the [`Context`]
+/// (e.g. as inserted by the [`TcpListener`]), and left empty if not available.
+///
+/// Tasks spawned by the inner services are not part of the span,
+/// unless they are instrumented with the current span themselves.
+///
+/// [`Layer`]: crate::service::Layer
+/// [`Stream`]: crate::stream::Stream
+/// [`SocketInfo`]: crate::stream::SocketInfo
+/// [`Context`]: crate::service::Context
+/// [`TcpListener`]: crate::tcp::server::TcpListener
+#[derive(Debug, Clone, Default)]
+pub struct StreamTraceLayer;
+
+impl StreamTraceLayer {
+    /// Create a new [`StreamTraceLayer`].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for StreamTraceLayer {
+    type Service = StreamTraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StreamTraceService::new(inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Subscriber,
+    };
+    use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Layer as _};
+
+    #[derive(Debug, Default)]
+    struct Captured {
+        spans: HashMap<u64, HashMap<String, String>>,
+        events: Vec<(String, Option<u64>)>,
+    }
+
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_owned(), format!("{:?}", value));
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct CaptureLayer(Arc<Mutex<Captured>>);
+
+    impl<S> tracing_subscriber::Layer<S> for CaptureLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &span::Attributes<'_>,
+            id: &span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            fields
+                .0
+                .insert("name".to_owned(), attrs.metadata().name().to_owned());
+            self.0.lock().unwrap().spans.insert(id.into_u64(), fields.0);
+        }
+
+        fn on_record(
+            &self,
+            id: &span::Id,
+            values: &span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Fields::default();
+            values.record(&mut fields);
+            if let Some(span) = self.0.lock().unwrap().spans.get_mut(&id.into_u64()) {
+                span.extend(fields.0);
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let message = fields.0.remove("message").unwrap_or_default();
+            let span = ctx
+                .event_scope(event)
+                .and_then(|scope| scope.from_root().find(|span| span.name() == "connection"))
+                .map(|span| span.id().into_u64());
+            self.0.lock().unwrap().events.push((message, span));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_trace_layer() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(
+            capture
+                .clone()
+                .with_filter(tracing_subscriber::filter::LevelFilter::DEBUG),
+        );
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let service =
+            StreamTraceLayer::new().layer(service_fn(|_: tokio::io::DuplexStream| async move {
+                tracing::info!("serving connection");
+                tokio::task::yield_now().await;
+                tracing::info!("connection served");
+                Ok::<_, std::convert::Infallible>(())
+            }));
+
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(
+            Some("127.0.0.1:8080".parse().unwrap()),
+            "10.0.0.1:54321".parse().unwrap(),
+        ));
+        let (_client, server) = tokio::io::duplex(64);
+        service.serve(ctx, server).await.unwrap();
+
+        let (_client, server) = tokio::io::duplex(64);
+        service.serve(Context::default(), server).await.unwrap();
+        tracing::info!("outside of any connection");
+
+        let captured = capture.0.lock().unwrap();
+        let events = &captured.events;
+        assert_eq!(events.len(), 5, "{events:?}");
+
+        let first = events[0].1.expect("event within connection span");
+        assert_eq!(events[1].1, Some(first));
+        let span = &captured.spans[&first];
+        assert_eq!(span["name"], "connection");
+        assert_eq!(span["peer.addr"], "10.0.0.1:54321");
+        assert_eq!(span["local.addr"], "127.0.0.1:8080");
+        let first_id: u64 = span["conn.id"].parse().unwrap();
+
+        let second = events[2].1.expect("event within connection span");
+        assert_ne!(second, first);
+        assert_eq!(events[3].1, Some(second));
+        let span = &captured.spans[&second];
+        assert!(!span.contains_key("peer.addr"));
+        assert!(!span.contains_key("local.addr"));
+        let second_id: u64 = span["conn.id"].parse().unwrap();
+        assert!(second_id > first_id);
+
+        assert_eq!(events[4], ("outside of any connection".to_owned(), None));
+    }
+}
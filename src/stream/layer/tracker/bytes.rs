@@ -23,6 +23,9 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 use pin_project_lite::pin_project;
 
+use super::http::{HttpBytesClassifier, HttpBytesCounters};
+use super::peer::PeerConnection;
+
 pin_project! {
     /// A wrapper around a [`AsyncRead`] and/or [`AsyncWrite`] that tracks the number
     /// of bytes read and/or written.
@@ -37,6 +40,9 @@ pin_project! {
     pub struct BytesRWTracker<S> {
         read: Arc<AtomicUsize>,
         written: Arc<AtomicUsize>,
+        http: Arc<HttpBytesCounters>,
+        classifier: Option<HttpBytesClassifier>,
+        peer: Option<PeerConnection>,
         #[pin]
         stream: S,
     }
@@ -51,10 +57,30 @@ impl<S> BytesRWTracker<S> {
         Self {
             read: Arc::new(AtomicUsize::new(0)),
             written: Arc::new(AtomicUsize::new(0)),
+            http: Arc::new(HttpBytesCounters::default()),
+            classifier: None,
+            peer: None,
             stream,
         }
     }
 
+    /// Also attribute the bytes read and/or written to the given peer connection.
+    pub(super) fn with_peer(mut self, peer: PeerConnection) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Also break out the bytes read and/or written into HTTP header and body bytes,
+    /// available using [`BytesRWTracker::header_read`] and co.
+    ///
+    /// See [`BytesTrackerLayer::with_http_tracking`] for more details.
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn with_http_tracking(mut self) -> Self {
+        self.classifier = Some(HttpBytesClassifier::new(self.http.clone()));
+        self
+    }
+
     /// Get the number of bytes read (so far).
     pub fn read(&self) -> usize {
         self.read.load(Ordering::SeqCst)
@@ -65,6 +91,42 @@ impl<S> BytesRWTracker<S> {
         self.written.load(Ordering::SeqCst)
     }
 
+    /// Get the number of HTTP header bytes read (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn header_read(&self) -> usize {
+        self.http.header_read()
+    }
+
+    /// Get the number of HTTP body bytes read (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn body_read(&self) -> usize {
+        self.http.body_read()
+    }
+
+    /// Get the number of HTTP header bytes written (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn header_written(&self) -> usize {
+        self.http.header_written()
+    }
+
+    /// Get the number of HTTP body bytes written (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn body_written(&self) -> usize {
+        self.http.body_written()
+    }
+
     /// Get a [`BytesRWTrackerHandle`] that can be used to get the number of bytes
     /// read and/or written even though the tracker is consumed by a protocol
     /// consumer in a later stage.
@@ -72,6 +134,7 @@ impl<S> BytesRWTracker<S> {
         BytesRWTrackerHandle {
             read: self.read.clone(),
             written: self.written.clone(),
+            http: self.http.clone(),
         }
     }
 
@@ -107,6 +170,12 @@ where
                 std::cmp::Ordering::Greater => {
                     let bytes_read = new_size - size;
                     this.read.fetch_add(bytes_read, Ordering::SeqCst);
+                    if let Some(peer) = this.peer {
+                        peer.add_read(bytes_read);
+                    }
+                    if let Some(classifier) = this.classifier {
+                        classifier.on_read(&buf.filled()[size..]);
+                    }
                 }
                 std::cmp::Ordering::Less => {
                     tracing::error!(
@@ -134,6 +203,12 @@ where
         let res: Poll<Result<usize, io::Error>> = this.stream.poll_write(cx, buf);
         if let Poll::Ready(Ok(bytes_written)) = res {
             this.written.fetch_add(bytes_written, Ordering::SeqCst);
+            if let Some(peer) = this.peer {
+                peer.add_written(bytes_written);
+            }
+            if let Some(classifier) = this.classifier {
+                classifier.on_written(&buf[..bytes_written]);
+            }
         }
         res
     }
@@ -155,6 +230,20 @@ where
         let res: Poll<Result<usize, io::Error>> = this.stream.poll_write_vectored(cx, bufs);
         if let Poll::Ready(Ok(bytes_written)) = res {
             this.written.fetch_add(bytes_written, Ordering::SeqCst);
+            if let Some(peer) = this.peer {
+                peer.add_written(bytes_written);
+            }
+            if let Some(classifier) = this.classifier {
+                let mut remaining = bytes_written;
+                for buf in bufs {
+                    let n = remaining.min(buf.len());
+                    classifier.on_written(&buf[..n]);
+                    remaining -= n;
+                    if remaining == 0 {
+                        break;
+                    }
+                }
+            }
         }
         res
     }
@@ -171,6 +260,7 @@ where
 pub struct BytesRWTrackerHandle {
     read: Arc<AtomicUsize>,
     written: Arc<AtomicUsize>,
+    http: Arc<HttpBytesCounters>,
 }
 
 impl BytesRWTrackerHandle {
@@ -183,6 +273,42 @@ impl BytesRWTrackerHandle {
     pub fn written(&self) -> usize {
         self.written.load(Ordering::SeqCst)
     }
+
+    /// Get the number of HTTP header bytes read (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn header_read(&self) -> usize {
+        self.http.header_read()
+    }
+
+    /// Get the number of HTTP body bytes read (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn body_read(&self) -> usize {
+        self.http.body_read()
+    }
+
+    /// Get the number of HTTP header bytes written (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn header_written(&self) -> usize {
+        self.http.header_written()
+    }
+
+    /// Get the number of HTTP body bytes written (so far).
+    ///
+    /// Always `0` unless enabled using [`BytesTrackerLayer::with_http_tracking`].
+    ///
+    /// [`BytesTrackerLayer::with_http_tracking`]: crate::stream::layer::BytesTrackerLayer::with_http_tracking
+    pub fn body_written(&self) -> usize {
+        self.http.body_written()
+    }
 }
 
 #[cfg(test)]
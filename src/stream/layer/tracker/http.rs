@@ -0,0 +1,593 @@
+//! Provides [`HttpBytesClassifier`], used by a [`BytesRWTracker`] to break out
+//! the bytes of a plaintext HTTP connection into header and body bytes.
+//!
+//! The tracked stream is expected to be the server side of the connection,
+//! reading requests and writing responses. Both http/1 (including keep-alive,
+//! chunked bodies and `HEAD` requests) and http/2 (with prior knowledge) are supported:
+//!
+//! - for http/1 the request line, status line and header fields are header bytes,
+//!   the message body (including its chunked framing and trailers) are body bytes;
+//! - for http/2 the `HEADERS`, `PUSH_PROMISE` and `CONTINUATION` frames are header bytes,
+//!   the `DATA` frames are body bytes, all other frames are neither.
+//!
+//! Once the connection is upgraded (e.g. for a `CONNECT` tunnel or websocket),
+//! or when the bytes are not recognised as HTTP (e.g. because they are encrypted),
+//! the bytes are no longer classified.
+//!
+//! [`BytesRWTracker`]: super::BytesRWTracker
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// The maximum size of a http/1 message head that is buffered in order to classify it.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// The maximum number of header fields parsed for a http/1 message head.
+const MAX_HEADERS: usize = 128;
+
+/// The first line of the http/2 connection preface, which parses as a http/1 message head.
+const H2_PREFACE_HEAD: &[u8] = b"PRI * HTTP/2.0\r\n\r\n";
+
+/// The remainder of the http/2 connection preface, following [`H2_PREFACE_HEAD`].
+const H2_PREFACE_TAIL: &[u8] = b"SM\r\n\r\n";
+
+/// The size of the header of a http/2 frame.
+const H2_FRAME_HEADER_SIZE: usize = 9;
+
+/// The atomic counters of the header and body bytes read and written,
+/// shared between the tracker and its handles.
+#[derive(Debug, Default)]
+pub(super) struct HttpBytesCounters {
+    header_read: AtomicUsize,
+    body_read: AtomicUsize,
+    header_written: AtomicUsize,
+    body_written: AtomicUsize,
+}
+
+impl HttpBytesCounters {
+    pub(super) fn header_read(&self) -> usize {
+        self.header_read.load(Ordering::SeqCst)
+    }
+
+    pub(super) fn body_read(&self) -> usize {
+        self.body_read.load(Ordering::SeqCst)
+    }
+
+    pub(super) fn header_written(&self) -> usize {
+        self.header_written.load(Ordering::SeqCst)
+    }
+
+    pub(super) fn body_written(&self) -> usize {
+        self.body_written.load(Ordering::SeqCst)
+    }
+}
+
+/// Classifies the bytes read and written on a HTTP connection into header and body bytes.
+#[derive(Debug)]
+pub(super) struct HttpBytesClassifier {
+    counters: Arc<HttpBytesCounters>,
+    requests: Framing,
+    responses: Framing,
+    /// The requests that are still waiting for their (final) response.
+    pending: VecDeque<RequestKind>,
+    /// Whether or not any http/1 message head was seen on the connection.
+    http1: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Request,
+    Response,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    Head,
+    Connect,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Header,
+    Body,
+    Other,
+}
+
+#[derive(Debug)]
+enum Framing {
+    /// Buffering a http/1 message head.
+    Head(Vec<u8>),
+    /// A http/1 body of which the given number of bytes remain.
+    Body(u64),
+    /// A http/1 chunked body.
+    Chunked(Chunk),
+    /// A http/1 (response) body delimited by the end of the connection.
+    UntilClose,
+    /// The remaining number of bytes of the http/2 connection preface.
+    Preface(usize),
+    /// Buffering a http/2 frame header.
+    FrameHeader(Vec<u8>),
+    /// A http/2 frame payload of which the given number of bytes remain.
+    FramePayload(usize, FrameKind),
+    /// The bytes are no longer classified.
+    Opaque,
+}
+
+#[derive(Debug)]
+enum Chunk {
+    /// Buffering a chunk size line.
+    Size(Vec<u8>),
+    /// Chunk data (including its trailing CRLF) of which the given number of bytes remain.
+    Data(u64),
+    /// Buffering a trailer field line.
+    Trailer(Vec<u8>),
+}
+
+/// The number of header and body bytes classified, for a single direction.
+#[derive(Debug, Default)]
+struct Classified {
+    header: usize,
+    body: usize,
+}
+
+impl Classified {
+    fn add(&mut self, kind: FrameKind, n: usize) {
+        match kind {
+            FrameKind::Header => self.header += n,
+            FrameKind::Body => self.body += n,
+            FrameKind::Other => (),
+        }
+    }
+}
+
+impl HttpBytesClassifier {
+    pub(super) fn new(counters: Arc<HttpBytesCounters>) -> Self {
+        Self {
+            counters,
+            requests: Framing::Head(Vec::new()),
+            responses: Framing::Head(Vec::new()),
+            pending: VecDeque::new(),
+            http1: false,
+        }
+    }
+
+    /// Classify the given bytes read from the connection.
+    pub(super) fn on_read(&mut self, data: &[u8]) {
+        let classified = self.consume(Side::Request, data);
+        self.counters
+            .header_read
+            .fetch_add(classified.header, Ordering::SeqCst);
+        self.counters
+            .body_read
+            .fetch_add(classified.body, Ordering::SeqCst);
+    }
+
+    /// Classify the given bytes written to the connection.
+    pub(super) fn on_written(&mut self, data: &[u8]) {
+        let classified = self.consume(Side::Response, data);
+        self.counters
+            .header_written
+            .fetch_add(classified.header, Ordering::SeqCst);
+        self.counters
+            .body_written
+            .fetch_add(classified.body, Ordering::SeqCst);
+    }
+
+    fn framing_mut(&mut self, side: Side) -> &mut Framing {
+        match side {
+            Side::Request => &mut self.requests,
+            Side::Response => &mut self.responses,
+        }
+    }
+
+    fn consume(&mut self, side: Side, mut data: &[u8]) -> Classified {
+        let mut classified = Classified::default();
+        while !data.is_empty() {
+            let framing = std::mem::replace(self.framing_mut(side), Framing::Opaque);
+            let next = match framing {
+                Framing::Opaque => {
+                    data = &[];
+                    Framing::Opaque
+                }
+                Framing::UntilClose => {
+                    classified.body += data.len();
+                    data = &[];
+                    Framing::UntilClose
+                }
+                Framing::Body(remaining) => {
+                    let n = take(&mut data, remaining);
+                    classified.body += n;
+                    if n as u64 == remaining {
+                        Framing::Head(Vec::new())
+                    } else {
+                        Framing::Body(remaining - n as u64)
+                    }
+                }
+                Framing::Chunked(chunk) => consume_chunk(chunk, &mut data, &mut classified),
+                Framing::Head(mut buf) => {
+                    if buf.is_empty() && !data[0].is_ascii_uppercase() {
+                        // a server speaking h2 (with prior knowledge) can send its
+                        // preface (a SETTINGS frame) prior to the preface of the client
+                        if side == Side::Response && !self.http1 && self.pending.is_empty() {
+                            Framing::FrameHeader(Vec::new())
+                        } else {
+                            Framing::Opaque
+                        }
+                    } else {
+                        match find_head_end(&mut buf, &mut data) {
+                            false if buf.len() > MAX_HEAD_SIZE => Framing::Opaque,
+                            false => Framing::Head(buf),
+                            true if side == Side::Request
+                                && !self.http1
+                                && buf == H2_PREFACE_HEAD =>
+                            {
+                                Framing::Preface(H2_PREFACE_TAIL.len())
+                            }
+                            true => match self.on_head(side, &buf) {
+                                Some(next) => {
+                                    classified.header += buf.len();
+                                    next
+                                }
+                                None => Framing::Opaque,
+                            },
+                        }
+                    }
+                }
+                Framing::Preface(remaining) => {
+                    let n = take(&mut data, remaining as u64);
+                    if n == remaining {
+                        Framing::FrameHeader(Vec::new())
+                    } else {
+                        Framing::Preface(remaining - n)
+                    }
+                }
+                Framing::FrameHeader(mut buf) => {
+                    let n = (H2_FRAME_HEADER_SIZE - buf.len()).min(data.len());
+                    buf.extend_from_slice(&data[..n]);
+                    data = &data[n..];
+                    if buf.len() < H2_FRAME_HEADER_SIZE {
+                        Framing::FrameHeader(buf)
+                    } else {
+                        let length = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]) as usize;
+                        let kind = match buf[3] {
+                            0x0 => FrameKind::Body,
+                            0x1 | 0x5 | 0x9 => FrameKind::Header,
+                            _ => FrameKind::Other,
+                        };
+                        classified.add(kind, H2_FRAME_HEADER_SIZE);
+                        if length == 0 {
+                            Framing::FrameHeader(Vec::new())
+                        } else {
+                            Framing::FramePayload(length, kind)
+                        }
+                    }
+                }
+                Framing::FramePayload(remaining, kind) => {
+                    let n = take(&mut data, remaining as u64);
+                    classified.add(kind, n);
+                    if n == remaining {
+                        Framing::FrameHeader(Vec::new())
+                    } else {
+                        Framing::FramePayload(remaining - n, kind)
+                    }
+                }
+            };
+            *self.framing_mut(side) = next;
+        }
+        classified
+    }
+
+    /// Handle a complete http/1 message head, returning the framing of what follows it,
+    /// or `None` in case it is not a valid message head.
+    fn on_head(&mut self, side: Side, head: &[u8]) -> Option<Framing> {
+        match side {
+            Side::Request => self.on_request_head(head),
+            Side::Response => self.on_response_head(head),
+        }
+    }
+
+    fn on_request_head(&mut self, head: &[u8]) -> Option<Framing> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut request = httparse::Request::new(&mut headers);
+        if !matches!(request.parse(head), Ok(httparse::Status::Complete(_))) {
+            return None;
+        }
+        self.http1 = true;
+
+        let kind = match request.method {
+            Some("HEAD") => RequestKind::Head,
+            Some("CONNECT") => RequestKind::Connect,
+            _ => RequestKind::Other,
+        };
+        self.pending.push_back(kind);
+
+        let upgrade = request
+            .headers
+            .iter()
+            .any(|header| header.name.eq_ignore_ascii_case("upgrade"));
+        if kind == RequestKind::Connect || upgrade {
+            // whatever follows is most likely no longer http/1
+            return Some(Framing::Opaque);
+        }
+
+        Some(body_framing(request.headers).unwrap_or(Framing::Head(Vec::new())))
+    }
+
+    fn on_response_head(&mut self, head: &[u8]) -> Option<Framing> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut response = httparse::Response::new(&mut headers);
+        let code = match response.parse(head) {
+            Ok(httparse::Status::Complete(_)) => response.code.unwrap_or_default(),
+            _ => return None,
+        };
+        self.http1 = true;
+
+        if code == 101 {
+            self.requests = Framing::Opaque;
+            return Some(Framing::Opaque);
+        }
+        if (100..200).contains(&code) {
+            // an informational response, the final response is still to follow
+            return Some(Framing::Head(Vec::new()));
+        }
+
+        match self.pending.pop_front() {
+            Some(RequestKind::Connect) if (200..300).contains(&code) => {
+                self.requests = Framing::Opaque;
+                return Some(Framing::Opaque);
+            }
+            Some(RequestKind::Head) => return Some(Framing::Head(Vec::new())),
+            _ => (),
+        }
+        if code == 204 || code == 304 {
+            return Some(Framing::Head(Vec::new()));
+        }
+
+        Some(body_framing(response.headers).unwrap_or(Framing::UntilClose))
+    }
+}
+
+/// Consume the given data as part of a http/1 chunked body.
+fn consume_chunk(chunk: Chunk, data: &mut &[u8], classified: &mut Classified) -> Framing {
+    match chunk {
+        Chunk::Size(mut line) => {
+            let n = take_line(&mut line, data);
+            classified.body += n;
+            if !line.ends_with(b"\n") {
+                return if line.len() > MAX_HEAD_SIZE {
+                    Framing::Opaque
+                } else {
+                    Framing::Chunked(Chunk::Size(line))
+                };
+            }
+            let size = line
+                .split(|b| *b == b';')
+                .next()
+                .and_then(|size| std::str::from_utf8(size).ok())
+                .and_then(|size| u64::from_str_radix(size.trim(), 16).ok());
+            match size {
+                Some(0) => Framing::Chunked(Chunk::Trailer(Vec::new())),
+                Some(size) => Framing::Chunked(Chunk::Data(size.saturating_add(2))),
+                None => Framing::Opaque,
+            }
+        }
+        Chunk::Data(remaining) => {
+            let n = take(data, remaining);
+            classified.body += n;
+            if n as u64 == remaining {
+                Framing::Chunked(Chunk::Size(Vec::new()))
+            } else {
+                Framing::Chunked(Chunk::Data(remaining - n as u64))
+            }
+        }
+        Chunk::Trailer(mut line) => {
+            let n = take_line(&mut line, data);
+            classified.body += n;
+            if !line.ends_with(b"\n") {
+                if line.len() > MAX_HEAD_SIZE {
+                    Framing::Opaque
+                } else {
+                    Framing::Chunked(Chunk::Trailer(line))
+                }
+            } else if line == b"\r\n" || line == b"\n" {
+                Framing::Head(Vec::new())
+            } else {
+                Framing::Chunked(Chunk::Trailer(Vec::new()))
+            }
+        }
+    }
+}
+
+/// The framing of a http/1 body as defined by the given headers,
+/// or `None` in case the body is not delimited by them.
+fn body_framing(headers: &[httparse::Header<'_>]) -> Option<Framing> {
+    let chunked = headers.iter().any(|header| {
+        header.name.eq_ignore_ascii_case("transfer-encoding")
+            && std::str::from_utf8(header.value)
+                .ok()
+                .and_then(|value| value.rsplit(',').next())
+                .map(|encoding| encoding.trim().eq_ignore_ascii_case("chunked"))
+                .unwrap_or_default()
+    });
+    if chunked {
+        return Some(Framing::Chunked(Chunk::Size(Vec::new())));
+    }
+
+    let length = headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-length"))?;
+    match std::str::from_utf8(length.value)
+        .ok()
+        .and_then(|length| length.trim().parse::<u64>().ok())
+    {
+        Some(0) => Some(Framing::Head(Vec::new())),
+        Some(length) => Some(Framing::Body(length)),
+        None => Some(Framing::Opaque),
+    }
+}
+
+/// Consume up to `max` bytes of the given data, returning the number of bytes consumed.
+fn take(data: &mut &[u8], max: u64) -> usize {
+    let n = max.min(data.len() as u64) as usize;
+    *data = &data[n..];
+    n
+}
+
+/// Move the given data into the (partial) http/1 message head, up to and including
+/// the empty line that ends it, returning `true` in case the head is complete.
+fn find_head_end(head: &mut Vec<u8>, data: &mut &[u8]) -> bool {
+    for (i, b) in data.iter().enumerate() {
+        head.push(*b);
+        if head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n") {
+            *data = &data[i + 1..];
+            return true;
+        }
+    }
+    *data = &[];
+    false
+}
+
+/// Move the given data into the (partial) line, up to and including its line feed,
+/// returning the number of bytes moved.
+fn take_line(line: &mut Vec<u8>, data: &mut &[u8]) -> usize {
+    let n = data
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(data.len());
+    line.extend_from_slice(&data[..n]);
+    *data = &data[n..];
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_classifier() -> (HttpBytesClassifier, Arc<HttpBytesCounters>) {
+        let counters = Arc::new(HttpBytesCounters::default());
+        (HttpBytesClassifier::new(counters.clone()), counters)
+    }
+
+    fn counts(counters: &HttpBytesCounters) -> [usize; 4] {
+        [
+            counters.header_read(),
+            counters.body_read(),
+            counters.header_written(),
+            counters.body_written(),
+        ]
+    }
+
+    #[test]
+    fn test_http1_keep_alive() {
+        let (mut classifier, counters) = new_classifier();
+
+        let request_1 = b"POST /echo HTTP/1.1\r\nhost: example.com\r\ncontent-length: 5\r\n\r\n";
+        let request_2 = b"HEAD /echo HTTP/1.1\r\nhost: example.com\r\n\r\n";
+        let response_1 = b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n";
+        let response_1_body = b"3\r\nhel\r\n2;ext=1\r\nlo\r\n0\r\nx-trailer: 1\r\n\r\n";
+        let response_2 = b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n";
+
+        // split the bytes at arbitrary points to cover the partial states
+        let mut read = request_1.to_vec();
+        read.extend_from_slice(b"hello");
+        read.extend_from_slice(request_2);
+        for chunk in read.chunks(7) {
+            classifier.on_read(chunk);
+        }
+
+        let mut written = response_1.to_vec();
+        written.extend_from_slice(response_1_body);
+        written.extend_from_slice(response_2);
+        for chunk in written.chunks(3) {
+            classifier.on_written(chunk);
+        }
+
+        assert_eq!(
+            counts(&counters),
+            [
+                request_1.len() + request_2.len(),
+                5,
+                response_1.len() + response_2.len(),
+                response_1_body.len(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_http1_response_until_close() {
+        let (mut classifier, counters) = new_classifier();
+
+        let request = b"GET / HTTP/1.0\r\n\r\n";
+        let informational = b"HTTP/1.1 100 Continue\r\n\r\n";
+        let response = b"HTTP/1.0 200 OK\r\n\r\n";
+        classifier.on_read(request);
+        classifier.on_written(informational);
+        classifier.on_written(response);
+        classifier.on_written(b"hello");
+        classifier.on_written(b"world");
+
+        assert_eq!(
+            counts(&counters),
+            [request.len(), 0, informational.len() + response.len(), 10]
+        );
+    }
+
+    #[test]
+    fn test_http1_upgrade_and_opaque() {
+        let (mut classifier, counters) = new_classifier();
+
+        let request = b"CONNECT example.com:443 HTTP/1.1\r\nhost: example.com:443\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\n\r\n";
+        classifier.on_read(request);
+        classifier.on_written(response);
+        classifier.on_read(b"\x16\x03\x01 tunneled bytes");
+        classifier.on_written(b"\x16\x03\x03 tunneled bytes");
+        assert_eq!(counts(&counters), [request.len(), 0, response.len(), 0]);
+
+        let (mut classifier, counters) = new_classifier();
+        classifier.on_read(b"\x16\x03\x01\x02\x00\x01\x00\x01\xfc\x03\x03");
+        classifier.on_read(b"GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(counts(&counters), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_http2_frames() {
+        let (mut classifier, counters) = new_classifier();
+
+        fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+            let length = (payload.len() as u32).to_be_bytes();
+            let mut frame = vec![length[1], length[2], length[3], kind, 0, 0, 0, 0, 1];
+            frame.extend_from_slice(payload);
+            frame
+        }
+
+        // the server preface can be written before the client preface is read
+        classifier.on_written(&frame(0x4, &[0; 6]));
+
+        let mut read = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        read.extend(frame(0x4, &[0; 12]));
+        read.extend(frame(0x1, &[1; 20]));
+        read.extend(frame(0x9, &[2; 5]));
+        read.extend(frame(0x0, &[3; 100]));
+        read.extend(frame(0x8, &[0; 4]));
+        for chunk in read.chunks(4) {
+            classifier.on_read(chunk);
+        }
+
+        classifier.on_written(&frame(0x1, &[1; 10]));
+        classifier.on_written(&frame(0x0, &[]));
+        classifier.on_written(&frame(0x0, &[3; 42]));
+
+        assert_eq!(
+            counts(&counters),
+            [9 + 20 + 9 + 5, 9 + 100, 9 + 10, 9 + 9 + 42]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     service::{Context, Layer, Service},
-    stream::Stream,
+    stream::{SocketInfo, Stream},
 };
 use std::future::Future;
 
@@ -8,6 +8,11 @@ mod bytes;
 use bytes::BytesRWTracker;
 pub use bytes::BytesRWTrackerHandle;
 
+mod http;
+
+mod peer;
+pub use peer::{PeerBytes, PeerBytesTracker};
+
 /// A [`Service`] that wraps a [`Service`]'s input IO [`Stream`] with an atomic R/W tracker.
 ///
 /// [`Service`]: crate::service::Service
@@ -15,6 +20,8 @@ pub use bytes::BytesRWTrackerHandle;
 #[derive(Debug)]
 pub struct BytesTrackerService<S> {
     inner: S,
+    peers: Option<PeerBytesTracker>,
+    http: bool,
 }
 
 impl<S> Clone for BytesTrackerService<S>
@@ -24,6 +31,8 @@ where
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            peers: self.peers.clone(),
+            http: self.http,
         }
     }
 }
@@ -42,7 +51,18 @@ where
         mut ctx: Context<State>,
         stream: IO,
     ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
-        let tracked_stream = BytesRWTracker::new(stream);
+        let mut tracked_stream = BytesRWTracker::new(stream);
+        if self.http {
+            tracked_stream = tracked_stream.with_http_tracking();
+        }
+        if let Some(peers) = &self.peers {
+            match ctx.get::<SocketInfo>() {
+                Some(info) => {
+                    tracked_stream = tracked_stream.with_peer(peers.connect(info.peer_addr().ip()));
+                }
+                None => tracing::trace!("BytesTrackerService: no socket info: peer not tracked"),
+            }
+        }
         let handle = tracked_stream.handle();
         ctx.insert(handle);
         self.inner.serve(ctx, tracked_stream)
@@ -54,14 +74,54 @@ where
 /// [`Layer`]: crate::service::Layer
 /// [`Service`]: crate::service::Service
 /// [`Stream`]: crate::stream::Stream
+///
+/// Use [`BytesTrackerLayer::with_peer_tracker`] to also aggregate
+/// the bytes read and/or written per peer IP address, and
+/// [`BytesTrackerLayer::with_http_tracking`] to break them out
+/// into HTTP header and body bytes.
 #[derive(Debug, Clone)]
 #[non_exhaustive]
-pub struct BytesTrackerLayer;
+pub struct BytesTrackerLayer {
+    peers: Option<PeerBytesTracker>,
+    http: bool,
+}
 
 impl BytesTrackerLayer {
     /// Create a new [`BytesTrackerLayer`].
     pub fn new() -> Self {
-        Self
+        Self {
+            peers: None,
+            http: false,
+        }
+    }
+
+    /// Also aggregate the bytes read and/or written per peer IP address
+    /// into the given [`PeerBytesTracker`], across all connections of that peer.
+    ///
+    /// The peer is identified using the [`SocketInfo`] found in the [`Context`],
+    /// connections without it are not attributed to any peer.
+    ///
+    /// [`SocketInfo`]: crate::stream::SocketInfo
+    /// [`Context`]: crate::service::Context
+    pub fn with_peer_tracker(mut self, peers: PeerBytesTracker) -> Self {
+        self.peers = Some(peers);
+        self
+    }
+
+    /// Also break out the bytes read and/or written into HTTP header and body bytes,
+    /// available using [`BytesRWTrackerHandle::header_read`] and co.
+    ///
+    /// The stream is expected to be the server side of a plaintext http/1 or http/2
+    /// connection, reading requests and writing responses. For http/1 the request line,
+    /// status line and header fields are header bytes, and the message bodies
+    /// (including their chunked framing) are body bytes. For http/2 the `HEADERS`
+    /// (and `CONTINUATION`) frames are header bytes, and the `DATA` frames are body bytes.
+    ///
+    /// Bytes following a protocol upgrade (e.g. a `CONNECT` tunnel), or bytes
+    /// that are not recognised as HTTP (e.g. TLS), are neither header nor body bytes.
+    pub fn with_http_tracking(mut self) -> Self {
+        self.http = true;
+        self
     }
 }
 
@@ -75,6 +135,139 @@ impl<S> Layer<S> for BytesTrackerLayer {
     type Service = BytesTrackerService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        BytesTrackerService { inner }
+        BytesTrackerService {
+            inner,
+            peers: self.peers.clone(),
+            http: self.http,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::service_fn;
+    use std::{convert::Infallible, net::SocketAddr, time::Duration};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve a single connection of the given peer,
+    /// reading the given request and writing the given response.
+    async fn serve_peer<S>(service: &S, peer: &str, request: &[u8], response: &'static [u8])
+    where
+        S: Service<(), tokio::io::DuplexStream, Response = (), Error = Infallible>,
+    {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut ctx = Context::default();
+        ctx.insert(SocketInfo::new(None, peer.parse::<SocketAddr>().unwrap()));
+
+        client.write_all(request).await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut received = Vec::new();
+        let (result, _) = tokio::join!(service.serve(ctx, server), async {
+            let _ = client.read_to_end(&mut received).await;
+        });
+        result.unwrap();
+        assert_eq!(received, response);
+    }
+
+    fn service(
+        peers: PeerBytesTracker,
+    ) -> impl Service<(), tokio::io::DuplexStream, Response = (), Error = Infallible> {
+        BytesTrackerLayer::new()
+            .with_peer_tracker(peers)
+            .layer(service_fn(
+                |mut stream: BytesRWTracker<tokio::io::DuplexStream>| async move {
+                    let mut request = Vec::new();
+                    stream.read_to_end(&mut request).await.unwrap();
+                    let response: &[u8] = if request.len() > 3 { b"long" } else { b"ok" };
+                    stream.write_all(response).await.unwrap();
+                    Ok(())
+                },
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_http_bytes_tracker() {
+        const REQUEST: &[u8] =
+            b"POST /items HTTP/1.1\r\nhost: example.com\r\ncontent-length: 10\r\n\r\n{\"id\": 42}";
+        const RESPONSE: &[u8] =
+            b"HTTP/1.1 201 Created\r\ncontent-type: text/plain\r\ncontent-length: 7\r\n\r\ncreated";
+
+        let service = BytesTrackerLayer::new()
+            .with_http_tracking()
+            .layer(service_fn(
+                |ctx: Context<()>, mut stream: BytesRWTracker<tokio::io::DuplexStream>| async move {
+                    let mut request = vec![0; REQUEST.len()];
+                    stream.read_exact(&mut request).await.unwrap();
+                    stream.write_all(RESPONSE).await.unwrap();
+                    Ok::<_, Infallible>(ctx.get::<BytesRWTrackerHandle>().unwrap().clone())
+                },
+            ));
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        client.write_all(REQUEST).await.unwrap();
+        let handle = service.serve(Context::default(), server).await.unwrap();
+
+        assert_eq!(handle.read(), REQUEST.len());
+        assert_eq!(handle.header_read(), REQUEST.len() - 10);
+        assert_eq!(handle.body_read(), 10);
+        assert_eq!(handle.written(), RESPONSE.len());
+        assert_eq!(handle.header_written(), RESPONSE.len() - 7);
+        assert_eq!(handle.body_written(), 7);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_peer_bytes_tracker() {
+        let peers = PeerBytesTracker::new(Duration::from_secs(60));
+        let service = service(peers.clone());
+
+        serve_peer(&service, "10.0.0.1:1000", b"foo", b"ok").await;
+        serve_peer(&service, "10.0.0.1:1001", b"hello", b"long").await;
+        serve_peer(&service, "10.0.0.2:1000", b"bar", b"ok").await;
+
+        let snapshot = peers.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let peer_1 = snapshot[&"10.0.0.1".parse::<std::net::IpAddr>().unwrap()];
+        assert_eq!(peer_1.read(), 8);
+        assert_eq!(peer_1.written(), 6);
+        assert_eq!(peer_1.active_connections(), 0);
+
+        let peer_2 = snapshot[&"10.0.0.2".parse::<std::net::IpAddr>().unwrap()];
+        assert_eq!(peer_2.read(), 3);
+        assert_eq!(peer_2.written(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_peer_bytes_tracker_idle_eviction() {
+        let peers = PeerBytesTracker::new(Duration::from_secs(60));
+        let service = service(peers.clone());
+        let peer_1 = "10.0.0.1".parse().unwrap();
+        let peer_2 = "10.0.0.2".parse().unwrap();
+
+        serve_peer(&service, "10.0.0.1:1000", b"foo", b"ok").await;
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        serve_peer(&service, "10.0.0.2:1000", b"bar", b"ok").await;
+
+        // a peer with an active connection is never evicted
+        let active = peers.connect(peer_2);
+
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert!(peers.get(&peer_1).is_none());
+        assert_eq!(peers.get(&peer_2).unwrap().active_connections(), 1);
+
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(peers.get(&peer_2).unwrap().read(), 3);
+
+        // the idle timeout starts once the last connection is finished
+        drop(active);
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(peers.get(&peer_2).is_some());
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(peers.snapshot().is_empty());
+
+        // an evicted peer starts from zero again
+        serve_peer(&service, "10.0.0.1:1000", b"hello", b"long").await;
+        assert_eq!(peers.get(&peer_1).unwrap().read(), 5);
     }
 }
@@ -0,0 +1,203 @@
+//! Provides [`PeerBytesTracker`], a shared handle which aggregates the number of bytes
+//! read and/or written per peer IP address, across all connections of that peer.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// A shared handle which aggregates the number of bytes read and/or written
+/// per peer IP address, across all connections of that peer.
+///
+/// Add it to a [`BytesTrackerLayer`] using [`BytesTrackerLayer::with_peer_tracker`],
+/// after which the totals can be inspected at any time using [`PeerBytesTracker::snapshot`],
+/// e.g. for billing or monitoring purposes.
+///
+/// A peer without any active connections is evicted once it has been idle
+/// for the configured idle timeout, dropping its totals. Idle peers are swept
+/// at most once per idle timeout while connecting new peers,
+/// and before taking a [`PeerBytesTracker::snapshot`].
+///
+/// [`BytesTrackerLayer`]: crate::stream::layer::BytesTrackerLayer
+/// [`BytesTrackerLayer::with_peer_tracker`]: crate::stream::layer::BytesTrackerLayer::with_peer_tracker
+#[derive(Debug, Clone)]
+pub struct PeerBytesTracker {
+    inner: Arc<PeerBytesTrackerInner>,
+}
+
+#[derive(Debug)]
+struct PeerBytesTrackerInner {
+    idle_timeout: Duration,
+    peers: Mutex<Peers>,
+}
+
+#[derive(Debug)]
+struct Peers {
+    entries: HashMap<IpAddr, PeerEntry>,
+    last_eviction: Instant,
+}
+
+#[derive(Debug)]
+struct PeerEntry {
+    counters: Arc<PeerCounters>,
+    active_connections: usize,
+    last_active: Instant,
+}
+
+#[derive(Debug, Default)]
+struct PeerCounters {
+    read: AtomicUsize,
+    written: AtomicUsize,
+}
+
+/// The number of bytes read and/or written by a single peer,
+/// as returned by [`PeerBytesTracker::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerBytes {
+    read: usize,
+    written: usize,
+    active_connections: usize,
+}
+
+impl PeerBytes {
+    /// Get the number of bytes read from the peer, across all its connections.
+    pub fn read(&self) -> usize {
+        self.read
+    }
+
+    /// Get the number of bytes written to the peer, across all its connections.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Get the number of connections of the peer that are still active.
+    pub fn active_connections(&self) -> usize {
+        self.active_connections
+    }
+}
+
+impl PeerBytesTracker {
+    /// Create a new [`PeerBytesTracker`], evicting peers without any active connections
+    /// once they have been idle for the given duration.
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(PeerBytesTrackerInner {
+                idle_timeout,
+                peers: Mutex::new(Peers {
+                    entries: HashMap::new(),
+                    last_eviction: Instant::now(),
+                }),
+            }),
+        }
+    }
+
+    /// Get the totals of all peers currently tracked,
+    /// evicting the idle peers first.
+    pub fn snapshot(&self) -> HashMap<IpAddr, PeerBytes> {
+        let mut peers = self.inner.peers.lock().unwrap();
+        self.inner.evict_idle(&mut peers);
+        peers
+            .entries
+            .iter()
+            .map(|(ip, entry)| (*ip, entry.bytes()))
+            .collect()
+    }
+
+    /// Get the totals of a single peer, if it is currently tracked,
+    /// evicting that peer first in case it is idle.
+    pub fn get(&self, ip: &IpAddr) -> Option<PeerBytes> {
+        let mut peers = self.inner.peers.lock().unwrap();
+        let entry = peers.entries.get(ip)?;
+        if self.inner.is_idle(entry, Instant::now()) {
+            peers.entries.remove(ip);
+            return None;
+        }
+        Some(entry.bytes())
+    }
+
+    /// Evict all peers without any active connections
+    /// which have been idle for longer than the idle timeout.
+    pub fn evict_idle(&self) {
+        let mut peers = self.inner.peers.lock().unwrap();
+        self.inner.evict_idle(&mut peers);
+    }
+
+    /// Register a new connection for the given peer,
+    /// returning the connection which attributes its bytes to that peer.
+    pub(super) fn connect(&self, ip: IpAddr) -> PeerConnection {
+        let mut peers = self.inner.peers.lock().unwrap();
+        if Instant::now().duration_since(peers.last_eviction) >= self.inner.idle_timeout {
+            self.inner.evict_idle(&mut peers);
+        }
+        let entry = peers.entries.entry(ip).or_insert_with(|| PeerEntry {
+            counters: Arc::new(PeerCounters::default()),
+            active_connections: 0,
+            last_active: Instant::now(),
+        });
+        entry.active_connections += 1;
+        entry.last_active = Instant::now();
+        PeerConnection {
+            ip,
+            counters: entry.counters.clone(),
+            tracker: self.inner.clone(),
+        }
+    }
+}
+
+impl PeerBytesTrackerInner {
+    fn evict_idle(&self, peers: &mut Peers) {
+        let now = Instant::now();
+        peers.last_eviction = now;
+        peers.entries.retain(|_, entry| !self.is_idle(entry, now));
+    }
+
+    fn is_idle(&self, entry: &PeerEntry, now: Instant) -> bool {
+        entry.active_connections == 0 && now.duration_since(entry.last_active) >= self.idle_timeout
+    }
+}
+
+impl PeerEntry {
+    fn bytes(&self) -> PeerBytes {
+        PeerBytes {
+            read: self.counters.read.load(Ordering::SeqCst),
+            written: self.counters.written.load(Ordering::SeqCst),
+            active_connections: self.active_connections,
+        }
+    }
+}
+
+/// A single connection of a peer tracked by a [`PeerBytesTracker`],
+/// marking the connection as finished when dropped.
+#[derive(Debug)]
+pub(super) struct PeerConnection {
+    ip: IpAddr,
+    counters: Arc<PeerCounters>,
+    tracker: Arc<PeerBytesTrackerInner>,
+}
+
+impl PeerConnection {
+    pub(super) fn add_read(&self, n: usize) {
+        self.counters.read.fetch_add(n, Ordering::SeqCst);
+    }
+
+    pub(super) fn add_written(&self, n: usize) {
+        self.counters.written.fetch_add(n, Ordering::SeqCst);
+    }
+}
+
+impl Drop for PeerConnection {
+    fn drop(&mut self) {
+        let mut peers = self.tracker.peers.lock().unwrap();
+        if let Some(entry) = peers.entries.get_mut(&self.ip) {
+            entry.active_connections = entry.active_connections.saturating_sub(1);
+            entry.last_active = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use http::Request;
+use std::{collections::HashSet, net::IpAddr, sync::Arc};
+
+use crate::{
+    service::{context::Extensions, Context},
+    stream::SocketInfo,
+};
+
+/// An implementation of [`AsnResolver`] is used to resolve an [`IpAddr`]
+/// to the autonomous system number (ASN) it belongs to.
+///
+/// Resolving is done synchronously, as it is used by the [`AsnFilter`] matcher,
+/// which is expected to be backed by an in-memory database (e.g. a loaded ASN table).
+pub trait AsnResolver: Send + Sync + 'static {
+    /// Resolve the given [`IpAddr`] to an ASN,
+    /// returning `None` in case the ASN is unknown.
+    fn resolve_asn(&self, ip: IpAddr) -> Option<u32>;
+}
+
+impl<F> AsnResolver for F
+where
+    F: Fn(IpAddr) -> Option<u32> + Send + Sync + 'static,
+{
+    fn resolve_asn(&self, ip: IpAddr) -> Option<u32> {
+        (self)(ip)
+    }
+}
+
+impl<R> AsnResolver for Arc<R>
+where
+    R: AsnResolver,
+{
+    fn resolve_asn(&self, ip: IpAddr) -> Option<u32> {
+        (**self).resolve_asn(ip)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Filter based on the autonomous system number (ASN) of the ip part of the [`SocketAddr`] of the peer,
+/// resolved using the given [`AsnResolver`].
+///
+/// The filter matches only if the resolved ASN is one of the ASNs of the filter,
+/// a peer ip for which no ASN could be resolved does not match.
+///
+/// [`SocketAddr`]: std::net::SocketAddr
+pub struct AsnFilter<R> {
+    resolver: R,
+    asns: Arc<HashSet<u32>>,
+    optional: bool,
+}
+
+impl<R> AsnFilter<R> {
+    /// create a new ASN filter to filter on the ASN of the ip part a [`SocketAddr`],
+    /// matching only if the ASN is one of the given ASNs.
+    ///
+    /// This filter will not match in case socket address could not be found,
+    /// if you want to match in case socket address could not be found,
+    /// use the [`AsnFilter::optional`] constructor..
+    ///
+    /// [`SocketAddr`]: std::net::SocketAddr
+    pub fn new(resolver: R, asns: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            resolver,
+            asns: Arc::new(asns.into_iter().collect()),
+            optional: false,
+        }
+    }
+
+    /// create a new ASN filter to filter on the ASN of the ip part a [`SocketAddr`],
+    /// matching only if the ASN is one of the given ASNs or no socket address could be found.
+    ///
+    /// This filter will match in case socket address could not be found.
+    /// Use the [`AsnFilter::new`] constructor if you want do not want
+    /// to match in case socket address could not be found.
+    ///
+    /// [`SocketAddr`]: std::net::SocketAddr
+    pub fn optional(resolver: R, asns: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            resolver,
+            asns: Arc::new(asns.into_iter().collect()),
+            optional: true,
+        }
+    }
+}
+
+impl<R: AsnResolver> AsnFilter<R> {
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        self.resolver
+            .resolve_asn(ip)
+            .map(|asn| self.asns.contains(&asn))
+            .unwrap_or_default()
+    }
+}
+
+impl<R, State, Body> crate::service::Matcher<State, Request<Body>> for AsnFilter<R>
+where
+    R: AsnResolver,
+{
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<SocketInfo>()
+            .map(|info| self.matches_ip(info.peer_addr().ip()))
+            .unwrap_or(self.optional)
+    }
+}
+
+impl<R, State, Socket> crate::service::Matcher<State, Socket> for AsnFilter<R>
+where
+    R: AsnResolver,
+    Socket: crate::stream::Socket,
+{
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        stream: &Socket,
+    ) -> bool {
+        stream
+            .peer_addr()
+            .map(|addr| self.matches_ip(addr.ip()))
+            .unwrap_or(self.optional)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{http::Body, service::Matcher};
+    use std::net::SocketAddr;
+
+    use super::*;
+
+    fn mock_resolver(ip: IpAddr) -> Option<u32> {
+        match ip {
+            IpAddr::V4(ip) if ip.octets() == [1, 1, 1, 1] => Some(13335),
+            IpAddr::V4(ip) if ip.octets() == [8, 8, 8, 8] => Some(15169),
+            IpAddr::V6(ip) if ip.segments() == [0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111] => {
+                Some(13335)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_asn_filter_http() {
+        let filter = AsnFilter::new(mock_resolver, [13335]);
+
+        let mut ctx = Context::default();
+        let req = Request::builder()
+            .method("GET")
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+
+        // test #1: no match: test with no socket info registered
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #2: no match: test with ip of unknown ASN
+        ctx.insert(SocketInfo::new(None, ([192, 168, 0, 1], 8080).into()));
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #3: no match: test with ip of another ASN
+        ctx.insert(SocketInfo::new(None, ([8, 8, 8, 8], 8080).into()));
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #4: match: test with ip of matching ASN (ipv4)
+        ctx.insert(SocketInfo::new(None, ([1, 1, 1, 1], 8080).into()));
+        assert!(filter.matches(None, &ctx, &req));
+
+        // test #5: match: test with ip of matching ASN (ipv6)
+        ctx.insert(SocketInfo::new(
+            None,
+            ([0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111], 8080).into(),
+        ));
+        assert!(filter.matches(None, &ctx, &req));
+
+        // test #6: match: test with multiple ASNs
+        let filter = AsnFilter::new(Arc::new(mock_resolver), [13335, 15169]);
+        ctx.insert(SocketInfo::new(None, ([8, 8, 8, 8], 8080).into()));
+        assert!(filter.matches(None, &ctx, &req));
+
+        // test #7: match: test with missing socket info, but it's seen as optional
+        let filter = AsnFilter::optional(mock_resolver, [13335]);
+        let ctx = Context::default();
+        assert!(filter.matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_asn_filter_socket_trait() {
+        let filter = AsnFilter::new(mock_resolver, [13335]);
+
+        let ctx = Context::default();
+
+        struct FakeSocket {
+            local_addr: Option<SocketAddr>,
+            peer_addr: Option<SocketAddr>,
+        }
+
+        impl crate::stream::Socket for FakeSocket {
+            fn local_addr(&self) -> std::io::Result<SocketAddr> {
+                match &self.local_addr {
+                    Some(addr) => Ok(*addr),
+                    None => Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)),
+                }
+            }
+
+            fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+                match &self.peer_addr {
+                    Some(addr) => Ok(*addr),
+                    None => Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)),
+                }
+            }
+        }
+
+        let mut socket = FakeSocket {
+            local_addr: None,
+            peer_addr: None,
+        };
+
+        // test #1: no match: test with no socket info registered
+        assert!(!filter.matches(None, &ctx, &socket));
+
+        // test #2: no match: test with ip of unknown ASN
+        socket.peer_addr = Some(([192, 168, 0, 1], 8080).into());
+        assert!(!filter.matches(None, &ctx, &socket));
+
+        // test #3: no match: test with ip of another ASN
+        socket.peer_addr = Some(([8, 8, 8, 8], 8080).into());
+        assert!(!filter.matches(None, &ctx, &socket));
+
+        // test #4: match: test with ip of matching ASN
+        socket.peer_addr = Some(([1, 1, 1, 1], 8080).into());
+        assert!(filter.matches(None, &ctx, &socket));
+
+        // test #5: match: test with missing socket info, but it's seen as optional
+        let filter = AsnFilter::optional(mock_resolver, [13335]);
+        socket.peer_addr = None;
+        assert!(filter.matches(None, &ctx, &socket));
+    }
+}
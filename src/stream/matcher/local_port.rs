@@ -0,0 +1,180 @@
+use http::Request;
+
+use crate::{
+    service::{context::Extensions, Context},
+    stream::SocketInfo,
+};
+
+#[derive(Debug, Clone)]
+/// Filter based on the port part of the local [`SocketAddr`],
+/// being the (server) port a connection arrived on.
+///
+/// This allows a service listening on multiple ports to run different logic
+/// per listening port. Use the [`PortFilter`] to filter on the port of the peer instead.
+///
+/// [`SocketAddr`]: std::net::SocketAddr
+/// [`PortFilter`]: crate::stream::matcher::PortFilter
+pub struct LocalPortFilter {
+    port: u16,
+    optional: bool,
+}
+
+impl LocalPortFilter {
+    /// create a new local port filter to filter on the port part of the local [`SocketAddr`]
+    ///
+    /// This filter will not match in case the local socket address could not be found,
+    /// if you want to match in case the local socket address could not be found,
+    /// use the [`LocalPortFilter::optional`] constructor.
+    ///
+    /// [`SocketAddr`]: std::net::SocketAddr
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            optional: false,
+        }
+    }
+
+    /// create a new local port filter to filter on the port part of the local [`SocketAddr`]
+    ///
+    /// This filter will match in case the local socket address could not be found.
+    /// Use the [`LocalPortFilter::new`] constructor if you want do not want
+    /// to match in case the local socket address could not be found.
+    ///
+    /// [`SocketAddr`]: std::net::SocketAddr
+    pub fn optional(port: u16) -> Self {
+        Self {
+            port,
+            optional: true,
+        }
+    }
+}
+
+impl<State, Body> crate::service::Matcher<State, Request<Body>> for LocalPortFilter {
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        ctx: &Context<State>,
+        _req: &Request<Body>,
+    ) -> bool {
+        ctx.get::<SocketInfo>()
+            .and_then(|info| info.local_addr())
+            .map(|addr| addr.port() == self.port)
+            .unwrap_or(self.optional)
+    }
+}
+
+impl<State, Socket> crate::service::Matcher<State, Socket> for LocalPortFilter
+where
+    Socket: crate::stream::Socket,
+{
+    fn matches(
+        &self,
+        _ext: Option<&mut Extensions>,
+        _ctx: &Context<State>,
+        stream: &Socket,
+    ) -> bool {
+        stream
+            .local_addr()
+            .map(|addr| addr.port() == self.port)
+            .unwrap_or(self.optional)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{http::Body, service::Matcher};
+    use std::net::SocketAddr;
+
+    use super::*;
+
+    #[test]
+    fn test_local_port_filter_http() {
+        let filter = LocalPortFilter::new(8080);
+
+        let mut ctx = Context::default();
+        let req = Request::builder()
+            .method("GET")
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+
+        // test #1: no match: test with no socket info registered
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #2: no match: test with socket info without a local address
+        ctx.insert(SocketInfo::new(None, ([127, 0, 0, 1], 8080).into()));
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #3: no match: test with a different local port, same peer port
+        ctx.insert(SocketInfo::new(
+            Some(([127, 0, 0, 1], 8443).into()),
+            ([127, 0, 0, 1], 8080).into(),
+        ));
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #4: match: test with matching local port
+        ctx.insert(SocketInfo::new(
+            Some(([127, 0, 0, 1], 8080).into()),
+            ([127, 0, 0, 2], 50123).into(),
+        ));
+        assert!(filter.matches(None, &ctx, &req));
+
+        // test #5: match: test with missing socket info, but it's seen as optional
+        let filter = LocalPortFilter::optional(8080);
+        let ctx = Context::default();
+        assert!(filter.matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_local_port_filter_socket_trait() {
+        let filter = LocalPortFilter::new(8080);
+
+        let ctx = Context::default();
+
+        struct FakeSocket {
+            local_addr: Option<SocketAddr>,
+            peer_addr: Option<SocketAddr>,
+        }
+
+        impl crate::stream::Socket for FakeSocket {
+            fn local_addr(&self) -> std::io::Result<SocketAddr> {
+                match &self.local_addr {
+                    Some(addr) => Ok(*addr),
+                    None => Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)),
+                }
+            }
+
+            fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+                match &self.peer_addr {
+                    Some(addr) => Ok(*addr),
+                    None => Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)),
+                }
+            }
+        }
+
+        let mut socket = FakeSocket {
+            local_addr: Some(([127, 0, 0, 1], 8443).into()),
+            peer_addr: Some(([127, 0, 0, 1], 8080).into()),
+        };
+
+        // test #1: no match: test with a different local port (peer port is ignored)
+        assert!(!filter.matches(None, &ctx, &socket));
+
+        // test #2: match: test with correct local port
+        socket.local_addr = Some(([0, 0, 0, 0], 8080).into());
+        assert!(filter.matches(None, &ctx, &socket));
+
+        // test #3: match: test with another local address, same port
+        socket.local_addr = Some(([127, 0, 0, 1], 8080).into());
+        socket.peer_addr = Some(([127, 0, 0, 1], 50123).into());
+        assert!(filter.matches(None, &ctx, &socket));
+
+        // test #4: no match: test with missing local address
+        socket.local_addr = None;
+        assert!(!filter.matches(None, &ctx, &socket));
+
+        // test #5: match: test with missing local address, but it's seen as optional
+        let filter = LocalPortFilter::optional(8080);
+        assert!(filter.matches(None, &ctx, &socket));
+    }
+}
@@ -14,6 +14,10 @@ mod port;
 #[doc(inline)]
 pub use port::PortFilter;
 
+mod local_port;
+#[doc(inline)]
+pub use local_port::LocalPortFilter;
+
 mod loopback;
 #[doc(inline)]
 pub use loopback::LoopbackFilter;
@@ -22,6 +26,10 @@ mod ip;
 #[doc(inline)]
 pub use ip::IpNetFilter;
 
+mod asn;
+#[doc(inline)]
+pub use asn::{AsnFilter, AsnResolver};
+
 use crate::{
     http::Request,
     service::{context::Extensions, matcher::IteratorMatcherExt, Context},
@@ -49,6 +57,10 @@ enum SocketFilterKind {
     ///
     /// [`SocketAddr`]: std::net::SocketAddr
     Port(PortFilter),
+    /// [`LocalPortFilter`], a filter based on the port part of the local [`SocketAddr`].
+    ///
+    /// [`SocketAddr`]: std::net::SocketAddr
+    LocalPort(LocalPortFilter),
     /// [`IpNetFilter`], a filter to match on whether or not
     /// the [`IpNet`] contains the [`SocketAddr`] of the peer.
     ///
@@ -237,6 +249,67 @@ impl SocketMatcher {
         self
     }
 
+    /// create a new local port filter to filter on the port part of the local
+    /// [`SocketAddr`](std::net::SocketAddr), being the port the connection arrived on.
+    ///
+    /// See [`LocalPortFilter::new`] for more information.
+    pub fn local_port(port: u16) -> Self {
+        Self {
+            kind: SocketFilterKind::LocalPort(LocalPortFilter::new(port)),
+            negate: false,
+        }
+    }
+
+    /// Create a new optional local port filter to filter on the port part of the local
+    /// [`SocketAddr`](std::net::SocketAddr),
+    /// this filter will match in case the local socket address could not be found.
+    ///
+    /// See [`LocalPortFilter::optional`] for more information.
+    pub fn optional_local_port(port: u16) -> Self {
+        Self {
+            kind: SocketFilterKind::LocalPort(LocalPortFilter::optional(port)),
+            negate: false,
+        }
+    }
+
+    /// Add a new local port filter to the existing [`SocketMatcher`] to
+    /// also filter on the port part of the local [`SocketAddr`](std::net::SocketAddr).
+    ///
+    /// See [`LocalPortFilter::new`] for more information.
+    pub fn and_local_port(mut self, port: u16) -> Self {
+        match &mut self.kind {
+            SocketFilterKind::All(filters) => {
+                filters.push(SocketFilterKind::LocalPort(LocalPortFilter::new(port)));
+            }
+            _ => {
+                self.kind = SocketFilterKind::All(vec![
+                    self.kind,
+                    SocketFilterKind::LocalPort(LocalPortFilter::new(port)),
+                ]);
+            }
+        }
+        self
+    }
+
+    /// Add a new local port filter to the existing [`SocketMatcher`] as an alternative filter
+    /// to match on the port part of the local [`SocketAddr`](std::net::SocketAddr).
+    ///
+    /// See [`LocalPortFilter::new`] for more information.
+    pub fn or_local_port(mut self, port: u16) -> Self {
+        match &mut self.kind {
+            SocketFilterKind::Any(filters) => {
+                filters.push(SocketFilterKind::LocalPort(LocalPortFilter::new(port)));
+            }
+            _ => {
+                self.kind = SocketFilterKind::Any(vec![
+                    self.kind,
+                    SocketFilterKind::LocalPort(LocalPortFilter::new(port)),
+                ]);
+            }
+        }
+        self
+    }
+
     /// create a new IP network filter to filter on an IP Network.
     ///
     /// See [`IpNetFilter::new`] for more information.
@@ -303,6 +376,51 @@ impl SocketMatcher {
     }
 }
 
+impl From<SocketAddressFilter> for SocketMatcher {
+    fn from(filter: SocketAddressFilter) -> Self {
+        Self {
+            kind: SocketFilterKind::SocketAddress(filter),
+            negate: false,
+        }
+    }
+}
+
+impl From<LoopbackFilter> for SocketMatcher {
+    fn from(filter: LoopbackFilter) -> Self {
+        Self {
+            kind: SocketFilterKind::Loopback(filter),
+            negate: false,
+        }
+    }
+}
+
+impl From<PortFilter> for SocketMatcher {
+    fn from(filter: PortFilter) -> Self {
+        Self {
+            kind: SocketFilterKind::Port(filter),
+            negate: false,
+        }
+    }
+}
+
+impl From<LocalPortFilter> for SocketMatcher {
+    fn from(filter: LocalPortFilter) -> Self {
+        Self {
+            kind: SocketFilterKind::LocalPort(filter),
+            negate: false,
+        }
+    }
+}
+
+impl From<IpNetFilter> for SocketMatcher {
+    fn from(filter: IpNetFilter) -> Self {
+        Self {
+            kind: SocketFilterKind::IpNet(filter),
+            negate: false,
+        }
+    }
+}
+
 impl<State, Body> crate::service::Matcher<State, Request<Body>> for SocketFilterKind {
     fn matches(
         &self,
@@ -317,6 +435,7 @@ impl<State, Body> crate::service::Matcher<State, Request<Body>> for SocketFilter
             SocketFilterKind::All(filters) => filters.iter().matches_and(ext, ctx, req),
             SocketFilterKind::Any(filters) => filters.iter().matches_or(ext, ctx, req),
             SocketFilterKind::Port(filter) => filter.matches(ext, ctx, req),
+            SocketFilterKind::LocalPort(filter) => filter.matches(ext, ctx, req),
         }
     }
 }
@@ -347,6 +466,7 @@ where
             SocketFilterKind::IpNet(filter) => filter.matches(ext, ctx, stream),
             SocketFilterKind::Loopback(filter) => filter.matches(ext, ctx, stream),
             SocketFilterKind::Port(filter) => filter.matches(ext, ctx, stream),
+            SocketFilterKind::LocalPort(filter) => filter.matches(ext, ctx, stream),
             SocketFilterKind::All(filters) => filters.iter().matches_and(ext, ctx, stream),
             SocketFilterKind::Any(filters) => filters.iter().matches_or(ext, ctx, stream),
         }
@@ -7,6 +7,8 @@ pub mod matcher;
 pub mod layer;
 pub mod service;
 
+pub mod testing;
+
 /// A stream is a type that implements `AsyncRead`, `AsyncWrite` and `Send`.
 /// This is specific to Rama and is directly linked to the supertraits of `Tokio`.
 pub trait Stream: AsyncRead + AsyncWrite + Send + Sync + 'static {}
@@ -16,6 +18,9 @@ impl<T> Stream for T where T: AsyncRead + AsyncWrite + Send + Sync + 'static {}
 mod socket;
 pub use socket::{Socket, SocketInfo};
 
+mod copy;
+pub use copy::{copy, copy_bidirectional, AdaptiveBuffer};
+
 pub mod dep {
     //! Dependencies for rama stream modules.
     //!
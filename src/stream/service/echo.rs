@@ -3,11 +3,15 @@
 use crate::{
     error::Error,
     service::{Context, Service},
-    stream::Stream,
+    stream::{self, AdaptiveBuffer, Stream},
 };
 
 /// An async service which echoes the incoming bytes back on the same stream.
 ///
+/// The bytes are copied using an [`AdaptiveBuffer`], starting at 8 KiB
+/// and growing up to 64 KiB under sustained throughput by default,
+/// use [`EchoService::buffer_size`] to configure these sizes.
+///
 /// # Example
 ///
 /// ```rust
@@ -25,13 +29,25 @@ use crate::{
 /// ```
 #[derive(Debug, Clone)]
 pub struct EchoService {
-    _phantom: (),
+    buffer_size: Option<(usize, usize)>,
 }
 
 impl EchoService {
     /// Creates a new [`EchoService`],
     pub fn new() -> Self {
-        Self { _phantom: () }
+        Self { buffer_size: None }
+    }
+
+    /// Set the initial (and minimum) and maximum size of the [`AdaptiveBuffer`]
+    /// used to echo the bytes, see [`AdaptiveBuffer::new`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_size` is zero.
+    pub fn buffer_size(mut self, initial_size: usize, max_size: usize) -> Self {
+        assert!(initial_size > 0, "initial buffer size must be non-zero");
+        self.buffer_size = Some((initial_size, max_size));
+        self
     }
 }
 
@@ -50,8 +66,12 @@ where
     type Error = Error;
 
     async fn serve(&self, _ctx: Context<T>, stream: S) -> Result<Self::Response, Self::Error> {
+        let mut buffer = match self.buffer_size {
+            Some((initial_size, max_size)) => AdaptiveBuffer::new(initial_size, max_size),
+            None => AdaptiveBuffer::default(),
+        };
         let (mut reader, mut writer) = tokio::io::split(stream);
-        tokio::io::copy(&mut reader, &mut writer)
+        stream::copy(&mut reader, &mut writer, &mut buffer)
             .await
             .map_err(Error::new)
     }
@@ -77,4 +97,24 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_echo_buffer_size() {
+        let stream = Builder::new()
+            .read(b"abcd")
+            .write(b"abcd")
+            .read(b"efghijkl")
+            .write(b"efghijkl")
+            .read(b"mnopqrstuvwxyz")
+            .write(b"mnopqrstuvwx")
+            .write(b"yz")
+            .build();
+
+        let bytes_copied = EchoService::new()
+            .buffer_size(4, 12)
+            .serve(Context::default(), stream)
+            .await
+            .unwrap();
+        assert_eq!(bytes_copied, 26);
+    }
 }
@@ -0,0 +1,168 @@
+//! In-memory transports, to test stream services end-to-end
+//! without binding to an actual network socket.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rama::{service::{Context, Service}, stream::{service::EchoService, testing, Socket}};
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let (server, mut client) = testing::duplex(
+//!     ([127, 0, 0, 1], 40000).into(),
+//!     ([127, 0, 0, 1], 8080).into(),
+//! );
+//! assert_eq!(server.peer_addr().unwrap(), client.local_addr().unwrap());
+//!
+//! tokio::spawn(async move { EchoService::new().serve(Context::default(), server).await });
+//!
+//! client.write_all(b"hello").await.unwrap();
+//! let mut buf = [0u8; 5];
+//! client.read_exact(&mut buf).await.unwrap();
+//! assert_eq!(&buf, b"hello");
+//! # }
+//! ```
+
+use super::Socket;
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+
+/// The default buffer size of the in-memory transport created by [`duplex`].
+const DEFAULT_MAX_BUF_SIZE: usize = 64 * 1024;
+
+/// Create a connected pair of in-memory sockets.
+///
+/// The first socket is the server side of the connection,
+/// for which the given `peer` address is the remote address of the (fake) client
+/// and `local` the address the (fake) server is bound to.
+/// The second socket is the client side of the connection,
+/// which has these addresses reversed.
+///
+/// Data written to one of the sockets can be read from the other,
+/// and dropping one of the sockets closes the connection.
+pub fn duplex(peer: SocketAddr, local: SocketAddr) -> (DuplexSocket, DuplexSocket) {
+    let (server, client) = tokio::io::duplex(DEFAULT_MAX_BUF_SIZE);
+    (
+        DuplexSocket {
+            inner: server,
+            local_addr: local,
+            peer_addr: peer,
+        },
+        DuplexSocket {
+            inner: client,
+            local_addr: peer,
+            peer_addr: local,
+        },
+    )
+}
+
+/// One side of an in-memory connection, created using [`duplex`].
+///
+/// It implements [`AsyncRead`], [`AsyncWrite`] and [`Socket`],
+/// and can as such be served by any service which accepts a [`Stream`].
+///
+/// [`Stream`]: crate::stream::Stream
+#[derive(Debug)]
+pub struct DuplexSocket {
+    inner: DuplexStream,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl Socket for DuplexSocket {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr)
+    }
+}
+
+impl AsyncRead for DuplexSocket {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for DuplexSocket {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{Context, Matcher, Service},
+        stream::{matcher::LoopbackFilter, service::EchoService},
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_duplex_socket_addresses() {
+        let peer: SocketAddr = ([127, 0, 0, 1], 40000).into();
+        let local: SocketAddr = ([10, 0, 0, 1], 8080).into();
+        let (server, client) = duplex(peer, local);
+
+        assert_eq!(server.peer_addr().unwrap(), peer);
+        assert_eq!(server.local_addr().unwrap(), local);
+        assert_eq!(client.peer_addr().unwrap(), local);
+        assert_eq!(client.local_addr().unwrap(), peer);
+
+        // the sockets can be used with the socket matchers
+        let filter = LoopbackFilter::new();
+        let ctx = Context::default();
+        assert!(filter.matches(None, &ctx, &server));
+        assert!(!filter.matches(None, &ctx, &client));
+    }
+
+    #[tokio::test]
+    async fn test_duplex_echo_service() {
+        let (server, mut client) = duplex(
+            ([127, 0, 0, 1], 40000).into(),
+            ([127, 0, 0, 1], 8080).into(),
+        );
+
+        let echo =
+            tokio::spawn(async move { EchoService::new().serve(Context::default(), server).await });
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        client.write_all(b" world").await.unwrap();
+        let mut buf = [0u8; 6];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b" world");
+
+        // closing the client side finishes the echo service
+        drop(client);
+        let bytes_copied = echo.await.unwrap().unwrap();
+        assert_eq!(bytes_copied, 11);
+    }
+}
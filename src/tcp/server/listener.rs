@@ -1,14 +1,24 @@
-use crate::graceful::ShutdownGuard;
+use super::observer::{CloseGuard, CloseReason, ConnectionInfo, ConnectionObserver};
+use crate::graceful::{ShutdownEscalation, ShutdownGuard};
 use crate::rt::Executor;
 use crate::service::handler::{Factory, FromContextRequest};
 use crate::service::Context;
 use crate::service::Service;
 use crate::stream::SocketInfo;
-use std::future::Future;
+use futures_util::FutureExt;
+use std::future::{poll_fn, Future};
 use std::pin::pin;
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
 use std::{io, net::SocketAddr};
 use tokio::net::{TcpListener as TokioTcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+/// The duration for which the previous socket of a rebound [`TcpListener`]
+/// keeps accepting the connections queued on it.
+const REBIND_GRACE_PERIOD: Duration = Duration::from_secs(1);
 
 /// Builder for `TcpListener`.
 #[derive(Debug)]
@@ -79,19 +89,58 @@ where
             inner.set_ttl(ttl)?;
         }
 
+        let (rebind_tx, rebind_rx) = mpsc::unbounded_channel();
         Ok(TcpListener {
             inner,
+            draining: Vec::new(),
             state: self.state.clone(),
+            connections: ConnectionTracker::new(),
+            accept_threshold: None,
+            paused: Arc::new(watch::channel(false).0),
+            ttl: self.ttl,
+            rebind_tx,
+            rebind_rx,
+            observer: None,
+            next_connection_id: 0,
+            escalation: None,
         })
     }
 }
 
 /// A TCP socket server, listening for incoming connections once served
 /// using one of the `serve` methods such as [`TcpListener::serve`].
-#[derive(Debug)]
 pub struct TcpListener<S> {
     inner: TokioTcpListener,
+    draining: Vec<Draining>,
     state: Arc<S>,
+    connections: ConnectionTracker,
+    accept_threshold: Option<usize>,
+    paused: Arc<watch::Sender<bool>>,
+    ttl: Option<u32>,
+    rebind_tx: mpsc::UnboundedSender<Rebind>,
+    rebind_rx: mpsc::UnboundedReceiver<Rebind>,
+    observer: Option<Arc<dyn ConnectionObserver>>,
+    next_connection_id: u64,
+    escalation: Option<ShutdownEscalation>,
+}
+
+impl<S> std::fmt::Debug for TcpListener<S>
+where
+    S: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpListener")
+            .field("inner", &self.inner)
+            .field("draining", &self.draining)
+            .field("state", &self.state)
+            .field("connections", &self.connections)
+            .field("accept_threshold", &self.accept_threshold)
+            .field("paused", &self.paused)
+            .field("ttl", &self.ttl)
+            .field("observer", &self.observer.is_some())
+            .field("escalation", &self.escalation)
+            .finish()
+    }
 }
 
 impl TcpListener<()> {
@@ -144,6 +193,202 @@ impl<S> TcpListener<S> {
     pub fn state(&self) -> &S {
         &self.state
     }
+
+    /// Returns a [`watch::Receiver`] of the number of connections
+    /// that are currently being served by this listener.
+    ///
+    /// The count is updated as connections are accepted and finished,
+    /// which allows for example to log the progress or wait for
+    /// the remaining connections during a graceful shutdown.
+    /// As the `serve` methods consume the listener,
+    /// the receiver has to be created prior to serving.
+    pub fn active_connections(&self) -> watch::Receiver<usize> {
+        self.connections.subscribe()
+    }
+
+    /// Apply backpressure once the given number of connections is being served.
+    ///
+    /// When the threshold is reached the listener pauses accepting new connections,
+    /// leaving them in the accept queue of the OS, and resumes accepting
+    /// once the number of active connections drops below the threshold again.
+    /// This is different from rejecting the connections, as clients
+    /// are merely delayed until there is capacity to serve them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the threshold is `0`, as no connection would ever be accepted.
+    pub fn with_accept_backpressure(mut self, threshold: usize) -> Self {
+        assert!(
+            threshold > 0,
+            "accept backpressure threshold must be non-zero"
+        );
+        self.accept_threshold = Some(threshold);
+        self
+    }
+
+    /// Register a [`ConnectionObserver`], notified of the lifecycle
+    /// of each connection served by this listener.
+    pub fn with_observer(mut self, observer: impl ConnectionObserver) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Register a [`ShutdownEscalation`], observed by each connection served by this listener.
+    ///
+    /// The escalation is inserted into the [`Context`] of each connection, such that
+    /// the http server closes kept-alive connections once the soft deadline is reached,
+    /// and connections which are still being served once the hard deadline is reached are aborted.
+    pub fn with_shutdown_escalation(mut self, escalation: ShutdownEscalation) -> Self {
+        self.escalation = Some(escalation);
+        self
+    }
+
+    /// Returns a [`TcpListenerHandle`] which can be used to rebind
+    /// this listener to a new address while it is being served.
+    ///
+    /// As the `serve` methods consume the listener,
+    /// the handle has to be created prior to serving.
+    pub fn handle(&self) -> TcpListenerHandle {
+        TcpListenerHandle {
+            ttl: self.ttl,
+            tx: self.rebind_tx.clone(),
+        }
+    }
+
+    /// Returns a [`ListenerControl`] which can be used to pause and resume
+    /// accepting connections while this listener is being served.
+    ///
+    /// As the `serve` methods consume the listener (and only return once it is shut down),
+    /// the control has to be created prior to serving.
+    pub fn control(&self) -> ListenerControl {
+        ListenerControl {
+            paused: self.paused.clone(),
+        }
+    }
+
+    /// Create the [`ConnectionInfo`] of a newly accepted connection,
+    /// notifying the [`ConnectionObserver`] (if any) that it got accepted.
+    fn on_accept(&mut self, socket: &TcpStream, peer_addr: SocketAddr) -> ConnectionInfo {
+        let id = self.next_connection_id;
+        self.next_connection_id += 1;
+        let info = ConnectionInfo::new(id, peer_addr, socket.local_addr().ok());
+        if let Some(observer) = &self.observer {
+            observer.on_accept(&info);
+        }
+        info
+    }
+
+    /// Accept the next connection, waiting first for the listener to be resumed (if paused)
+    /// and for the number of active connections to drop below the backpressure threshold
+    /// (if configured).
+    ///
+    /// Sockets that got swapped out by a [`TcpListenerHandle`] keep accepting
+    /// the connections queued on them for a grace period, after which the connections
+    /// still queued are accepted, and the socket is closed.
+    async fn accept(&mut self) -> io::Result<(TcpStream, SocketAddr)> {
+        let mut paused = self.paused.subscribe();
+        'accept: loop {
+            if *paused.borrow_and_update() {
+                tracing::trace!("TCP accept paused: listener paused");
+            }
+            // the sender is owned by the listener itself, so this cannot fail
+            let _ = paused.wait_for(|paused| !*paused).await;
+
+            if let Some(threshold) = self.accept_threshold {
+                let mut connections = self.connections.subscribe();
+                if *connections.borrow() >= threshold {
+                    tracing::trace!(
+                        threshold,
+                        "TCP accept paused: backpressure threshold reached"
+                    );
+                }
+                // the sender is owned by the listener itself, so this cannot fail
+                let _ = connections.wait_for(|count| *count < threshold).await;
+            }
+
+            loop {
+                let grace_deadline = self.draining.iter().map(|draining| draining.deadline).min();
+                tokio::select! {
+                    result = self.inner.accept() => return result,
+                    result = poll_fn(|cx| poll_accept_draining(&self.draining, cx)) => return result,
+                    _ = sleep_until(grace_deadline) => {
+                        if let Some(conn) = self.close_drained() {
+                            return Ok(conn);
+                        }
+                    }
+                    _ = paused.wait_for(|paused| *paused) => continue 'accept,
+                    Some(rebind) = self.rebind_rx.recv() => {
+                        let previous = std::mem::replace(&mut self.inner, rebind.listener);
+                        tracing::trace!(
+                            previous = ?previous.local_addr().ok(),
+                            current = ?self.inner.local_addr().ok(),
+                            "TCP listener rebound"
+                        );
+                        self.draining.push(Draining {
+                            listener: previous,
+                            deadline: Instant::now() + REBIND_GRACE_PERIOD,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Close the swapped out sockets of which the grace period expired,
+    /// returning the first connection still queued on any of them (if any),
+    /// in which case the socket is only closed once no more connections are queued on it.
+    fn close_drained(&mut self) -> Option<(TcpStream, SocketAddr)> {
+        let now = Instant::now();
+        let mut queued = None;
+        self.draining.retain(|draining| {
+            if queued.is_some() || draining.deadline > now {
+                return true;
+            }
+            match draining.listener.accept().now_or_never() {
+                Some(Ok(conn)) => {
+                    queued = Some(conn);
+                    true
+                }
+                _ => {
+                    tracing::trace!(
+                        addr = ?draining.listener.local_addr().ok(),
+                        "TCP listener drained: closing previous socket"
+                    );
+                    false
+                }
+            }
+        });
+        queued
+    }
+}
+
+/// A socket swapped out by a [`TcpListenerHandle`],
+/// still accepting the connections queued on it until its deadline.
+#[derive(Debug)]
+struct Draining {
+    listener: TokioTcpListener,
+    deadline: Instant,
+}
+
+/// Accept a connection from any of the given swapped out sockets.
+fn poll_accept_draining(
+    draining: &[Draining],
+    cx: &mut std::task::Context<'_>,
+) -> Poll<io::Result<(TcpStream, SocketAddr)>> {
+    for draining in draining {
+        if let Poll::Ready(result) = draining.listener.poll_accept(cx) {
+            return Poll::Ready(result);
+        }
+    }
+    Poll::Pending
+}
+
+/// Sleep until the given deadline, or forever if there is none.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
 }
 
 impl<State> TcpListener<State>
@@ -154,15 +399,15 @@ where
     ///
     /// This method will block the current listener for each incoming connection,
     /// the underlying service can choose to spawn a task to handle the accepted stream.
-    pub async fn serve<S>(self, service: S)
+    pub async fn serve<S>(mut self, service: S)
     where
         S: Service<State, TcpStream>,
     {
-        let ctx = Context::new(self.state, Executor::new());
+        let ctx = Context::new(self.state.clone(), Executor::new());
         let service = Arc::new(service);
 
         loop {
-            let (socket, peer_addr) = match self.inner.accept().await {
+            let (socket, peer_addr) = match self.accept().await {
                 Ok(stream) => stream,
                 Err(err) => {
                     handle_accept_err(err).await;
@@ -170,15 +415,17 @@ where
                 }
             };
 
-            let service = service.clone();
-            let mut ctx = ctx.clone();
-
-            tokio::spawn(async move {
-                let local_addr = socket.local_addr().ok();
-                ctx.insert(SocketInfo::new(local_addr, peer_addr));
-
-                let _ = service.serve(ctx, socket).await;
-            });
+            let info = self.on_accept(&socket, peer_addr);
+            let connection = self.connections.track();
+            tokio::spawn(serve_connection(
+                service.clone(),
+                ctx.clone(),
+                socket,
+                info,
+                connection,
+                self.observer.clone(),
+                self.escalation.clone(),
+            ));
         }
     }
 
@@ -202,11 +449,12 @@ where
     /// This method does the same as [`Self::serve`] but it
     /// will respect the given [`crate::graceful::ShutdownGuard`], and also pass
     /// it to the service.
-    pub async fn serve_graceful<S>(self, guard: ShutdownGuard, service: S)
+    pub async fn serve_graceful<S>(mut self, guard: ShutdownGuard, service: S)
     where
         S: Service<State, TcpStream>,
     {
-        let ctx: Context<State> = Context::new(self.state, Executor::graceful(guard.clone()));
+        let ctx: Context<State> =
+            Context::new(self.state.clone(), Executor::graceful(guard.clone()));
         let service = Arc::new(service);
         let mut cancelled_fut = pin!(guard.cancelled());
 
@@ -216,18 +464,20 @@ where
                     tracing::trace!("signal received: initiate graceful shutdown");
                     break;
                 }
-                result = self.inner.accept() => {
+                result = self.accept() => {
                     match result {
                         Ok((socket, peer_addr)) => {
-                            let service = service.clone();
-                            let mut ctx = ctx.clone();
-
-                            guard.spawn_task(async move {
-                                let local_addr = socket.local_addr().ok();
-                                ctx.insert(SocketInfo::new(local_addr, peer_addr));
-
-                                let _ = service.serve(ctx, socket).await;
-                            });
+                            let info = self.on_accept(&socket, peer_addr);
+                            let connection = self.connections.track();
+                            guard.spawn_task(serve_connection(
+                                service.clone(),
+                                ctx.clone(),
+                                socket,
+                                info,
+                                connection,
+                                self.observer.clone(),
+                                self.escalation.clone(),
+                            ));
                         }
                         Err(err) => {
                             handle_accept_err(err).await;
@@ -254,6 +504,177 @@ where
     }
 }
 
+/// Serve a single accepted connection with the given service,
+/// notifying the [`ConnectionObserver`] (if any) of its lifecycle,
+/// and aborting it once the hard deadline of the [`ShutdownEscalation`] (if any) is reached.
+async fn serve_connection<State, S>(
+    service: Arc<S>,
+    mut ctx: Context<State>,
+    socket: TcpStream,
+    info: ConnectionInfo,
+    connection: ConnectionGuard,
+    observer: Option<Arc<dyn ConnectionObserver>>,
+    escalation: Option<ShutdownEscalation>,
+) where
+    State: Send + Sync + 'static,
+    S: Service<State, TcpStream>,
+{
+    let _connection = connection;
+    ctx.insert(SocketInfo::new(
+        info.local_addr().copied(),
+        *info.peer_addr(),
+    ));
+
+    if let Some(observer) = &observer {
+        observer.on_ready(&info);
+    }
+    let guard = CloseGuard::new(observer, info);
+    let reason = match escalation {
+        Some(escalation) => {
+            ctx.insert(escalation.clone());
+            tokio::select! {
+                result = service.serve(ctx, socket) => CloseReason::from_result(&result),
+                _ = escalation.hard_deadline() => {
+                    tracing::trace!("hard shutdown deadline reached: abort TCP connection");
+                    CloseReason::Aborted
+                }
+            }
+        }
+        None => CloseReason::from_result(&service.serve(ctx, socket).await),
+    };
+    guard.close(reason);
+}
+
+/// A handle to a [`TcpListener`], created using [`TcpListener::handle`],
+/// which can be used to rebind the listener while it is being served.
+///
+/// Rebinding swaps the socket the listener accepts new connections on,
+/// for example to move to a different port or to change socket options,
+/// without interrupting the service. Connections that were already accepted
+/// keep being served, and the previous socket keeps accepting the connections
+/// queued on it for a grace period of 1 second, after which the connections
+/// still queued are accepted and the previous socket is closed.
+#[derive(Debug, Clone)]
+pub struct TcpListenerHandle {
+    ttl: Option<u32>,
+    tx: mpsc::UnboundedSender<Rebind>,
+}
+
+impl TcpListenerHandle {
+    /// Rebind the listener to the given address,
+    /// using the same options it was originally built with.
+    ///
+    /// Returns the local address of the new socket, which is listening once returned.
+    /// This does not wait for the listener to be served: the connections
+    /// queued on the new socket are accepted once the listener is (or resumes) serving.
+    pub async fn rebind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<SocketAddr> {
+        let listener = TokioTcpListener::bind(addr).await?;
+        if let Some(ttl) = self.ttl {
+            listener.set_ttl(ttl)?;
+        }
+        self.swap(listener).await
+    }
+
+    /// Swap the socket of the listener for the given (bound) [`tokio::net::TcpListener`],
+    /// allowing it to be configured with any socket options.
+    ///
+    /// Returns the local address of the new socket.
+    /// See [`TcpListenerHandle::rebind`] for more details.
+    pub async fn swap(&self, listener: TokioTcpListener) -> io::Result<SocketAddr> {
+        let addr = listener.local_addr()?;
+        self.tx
+            .send(Rebind { listener })
+            .map_err(|_| listener_gone())?;
+        Ok(addr)
+    }
+}
+
+/// A control for a [`TcpListener`], created using [`TcpListener::control`],
+/// which can be used to pause and resume accepting connections while it is being served,
+/// for example during maintenance, without shutting it down.
+///
+/// While paused, connections that were already accepted keep being served,
+/// and new connections are left in the accept queue of the OS until the listener is resumed
+/// (or until they time out, or the queue is full, in which case they are refused).
+/// The socket of the listener is not closed while paused. A [`TcpListenerHandle`]
+/// can rebind a paused listener, in which case the connections queued on both
+/// the previous and the new socket are accepted once resumed.
+#[derive(Debug, Clone)]
+pub struct ListenerControl {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl ListenerControl {
+    /// Pause accepting new connections.
+    ///
+    /// A connection that is being accepted at the time of pausing may still be accepted.
+    pub fn pause(&self) {
+        if !self.paused.send_replace(true) {
+            tracing::trace!("TCP listener paused");
+        }
+    }
+
+    /// Resume accepting new connections.
+    pub fn resume(&self) {
+        if self.paused.send_replace(false) {
+            tracing::trace!("TCP listener resumed");
+        }
+    }
+
+    /// Returns `true` in case the listener is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+}
+
+fn listener_gone() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotConnected,
+        "TCP listener is no longer served",
+    )
+}
+
+#[derive(Debug)]
+/// A request to swap the socket of a [`TcpListener`].
+struct Rebind {
+    listener: TokioTcpListener,
+}
+
+#[derive(Debug, Clone)]
+/// Keeps track of the number of active connections of a [`TcpListener`].
+struct ConnectionTracker {
+    tx: Arc<watch::Sender<usize>>,
+}
+
+impl ConnectionTracker {
+    fn new() -> Self {
+        let (tx, _) = watch::channel(0);
+        Self { tx: Arc::new(tx) }
+    }
+
+    fn subscribe(&self) -> watch::Receiver<usize> {
+        self.tx.subscribe()
+    }
+
+    fn track(&self) -> ConnectionGuard {
+        self.tx.send_modify(|count| *count += 1);
+        ConnectionGuard {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+/// Marks a connection as finished when dropped.
+struct ConnectionGuard {
+    tx: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.tx.send_modify(|count| *count -= 1);
+    }
+}
+
 async fn handle_accept_err(err: io::Error) {
     if crate::tcp::utils::is_connection_error(&err) {
         tracing::trace!(
@@ -276,3 +697,477 @@ async fn handle_accept_err(err: io::Error) {
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn wait_for_count(rx: &mut watch::Receiver<usize>, expected: usize) {
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            rx.wait_for(|count| *count == expected),
+        )
+        .await
+        .expect("active connections to reach the expected count")
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_active_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut active_connections = listener.active_connections();
+        assert_eq!(*active_connections.borrow(), 0);
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        shutdown.spawn_task_fn(|guard| async move {
+            listener
+                .serve_fn_graceful(guard, |mut stream: TcpStream| async move {
+                    // serve the connection until the client closes it
+                    let mut buf = [0u8; 1];
+                    let _ = stream.read(&mut buf).await;
+                    Ok::<_, std::convert::Infallible>(())
+                })
+                .await;
+        });
+
+        let client_1 = TcpStream::connect(addr).await.unwrap();
+        wait_for_count(&mut active_connections, 1).await;
+
+        let client_2 = TcpStream::connect(addr).await.unwrap();
+        wait_for_count(&mut active_connections, 2).await;
+
+        drop(client_1);
+        wait_for_count(&mut active_connections, 1).await;
+
+        // the remaining connection is still tracked while shutting down
+        shutdown_tx.send(()).unwrap();
+        let shutdown = tokio::spawn(shutdown.shutdown_with_limit(Duration::from_secs(5)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*active_connections.borrow(), 1);
+
+        drop(client_2);
+        wait_for_count(&mut active_connections, 0).await;
+
+        shutdown.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_accept_backpressure() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_accept_backpressure(1);
+        let addr = listener.local_addr().unwrap();
+        let mut active_connections = listener.active_connections();
+
+        tokio::spawn(listener.serve_fn(|mut stream: TcpStream| async move {
+            // signal that the connection got accepted,
+            // and serve it until the client closes it
+            stream.write_all(b"a").await?;
+            let mut buf = [0u8; 1];
+            let _ = stream.read(&mut buf).await;
+            Ok::<_, io::Error>(())
+        }));
+
+        let mut client_1 = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        client_1.read_exact(&mut buf).await.unwrap();
+        wait_for_count(&mut active_connections, 1).await;
+
+        // the threshold is reached: the connection is queued but not accepted
+        let mut client_2 = TcpStream::connect(addr).await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), client_2.read_exact(&mut buf))
+                .await
+                .is_err()
+        );
+        assert_eq!(*active_connections.borrow(), 1);
+
+        // accepting resumes once the first connection is finished
+        drop(client_1);
+        tokio::time::timeout(Duration::from_secs(5), client_2.read_exact(&mut buf))
+            .await
+            .expect("queued connection to be accepted")
+            .unwrap();
+        wait_for_count(&mut active_connections, 1).await;
+
+        drop(client_2);
+        wait_for_count(&mut active_connections, 0).await;
+    }
+
+    /// Serve the given listener, answering each byte with the port
+    /// of the listener that accepted the connection.
+    fn serve_port_echo(listener: TcpListener<()>) {
+        tokio::spawn(listener.serve_fn(|mut stream: TcpStream| async move {
+            let port = stream.local_addr()?.port();
+            let mut buf = [0u8; 1];
+            while stream.read(&mut buf).await? > 0 {
+                stream.write_all(&port.to_be_bytes()).await?;
+            }
+            Ok::<_, io::Error>(())
+        }));
+    }
+
+    async fn ping(stream: &mut TcpStream) -> u16 {
+        stream.write_all(b"x").await.unwrap();
+        let mut buf = [0u8; 2];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut buf))
+            .await
+            .expect("connection to be served")
+            .unwrap();
+        u16::from_be_bytes(buf)
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_rebind() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let old_addr = listener.local_addr().unwrap();
+        let handle = listener.handle();
+        let control = listener.control();
+        let mut active_connections = listener.active_connections();
+        serve_port_echo(listener);
+
+        let mut client_old = TcpStream::connect(old_addr).await.unwrap();
+        assert_eq!(ping(&mut client_old).await, old_addr.port());
+
+        // connections queued on the old socket, while paused
+        control.pause();
+        let mut queued = Vec::new();
+        for _ in 0..3 {
+            queued.push(TcpStream::connect(old_addr).await.unwrap());
+        }
+
+        // rebinding completes while the listener is paused
+        let new_addr = handle.rebind("127.0.0.1:0").await.unwrap();
+        assert_ne!(new_addr, old_addr);
+        let mut client_new = TcpStream::connect(new_addr).await.unwrap();
+        control.resume();
+
+        // the queued connections are served by the old socket,
+        // and new connections by the new socket
+        for stream in &mut queued {
+            assert_eq!(ping(stream).await, old_addr.port());
+        }
+        assert_eq!(ping(&mut client_new).await, new_addr.port());
+        wait_for_count(&mut active_connections, 5).await;
+
+        // the connection accepted by the old listener is still served
+        assert_eq!(ping(&mut client_old).await, old_addr.port());
+
+        // the old listener no longer accepts connections once drained
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Ok(mut stream) = TcpStream::connect(old_addr).await {
+                // a connection queued prior to it being closed is still served
+                assert_eq!(ping(&mut stream).await, old_addr.port());
+            }
+        })
+        .await
+        .expect("old listener to be closed");
+
+        drop((client_old, client_new, queued));
+        wait_for_count(&mut active_connections, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_rebind_before_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let old_addr = listener.local_addr().unwrap();
+        let handle = listener.handle();
+
+        // completes without the listener being served
+        let mut client_old = TcpStream::connect(old_addr).await.unwrap();
+        let new_addr = handle.rebind("127.0.0.1:0").await.unwrap();
+        let mut client_new = TcpStream::connect(new_addr).await.unwrap();
+
+        serve_port_echo(listener);
+        assert_eq!(ping(&mut client_old).await, old_addr.port());
+        assert_eq!(ping(&mut client_new).await, new_addr.port());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_pause_resume() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let control = listener.control();
+        let mut active_connections = listener.active_connections();
+
+        tokio::spawn(listener.serve_fn(|mut stream: TcpStream| async move {
+            // signal that the connection got accepted,
+            // and echo until the client closes it
+            stream.write_all(b"a").await?;
+            let mut buf = [0u8; 1];
+            while stream.read(&mut buf).await? > 0 {
+                stream.write_all(&buf).await?;
+            }
+            Ok::<_, io::Error>(())
+        }));
+
+        let mut client_1 = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        client_1.read_exact(&mut buf).await.unwrap();
+        wait_for_count(&mut active_connections, 1).await;
+
+        control.pause();
+        assert!(control.is_paused());
+
+        // paused: the connection is queued but not accepted
+        let mut client_2 = TcpStream::connect(addr).await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), client_2.read_exact(&mut buf))
+                .await
+                .is_err()
+        );
+        assert_eq!(*active_connections.borrow(), 1);
+
+        // the existing connection continues to be served
+        client_1.write_all(b"x").await.unwrap();
+        client_1.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"x");
+
+        // resumed: the queued connection is accepted, and so are new ones
+        control.resume();
+        assert!(!control.is_paused());
+        tokio::time::timeout(Duration::from_secs(5), client_2.read_exact(&mut buf))
+            .await
+            .expect("queued connection to be accepted")
+            .unwrap();
+        let mut client_3 = TcpStream::connect(addr).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), client_3.read_exact(&mut buf))
+            .await
+            .expect("new connection to be accepted")
+            .unwrap();
+        wait_for_count(&mut active_connections, 3).await;
+
+        drop((client_1, client_2, client_3));
+        wait_for_count(&mut active_connections, 0).await;
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct RecordingObserver {
+        events: Arc<std::sync::Mutex<Vec<(u64, String)>>>,
+    }
+
+    impl RecordingObserver {
+        fn record(&self, info: &ConnectionInfo, event: String) {
+            self.events.lock().unwrap().push((info.id(), event));
+        }
+
+        fn events(&self) -> Vec<(u64, String)> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    impl ConnectionObserver for RecordingObserver {
+        fn on_accept(&self, info: &ConnectionInfo) {
+            self.record(info, format!("accept {}", info.peer_addr()));
+        }
+
+        fn on_ready(&self, info: &ConnectionInfo) {
+            self.record(info, format!("ready {}", info.local_addr().unwrap()));
+        }
+
+        fn on_close(&self, info: &ConnectionInfo, reason: CloseReason) {
+            self.record(info, format!("close {reason:?}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_observer() {
+        let observer = RecordingObserver::default();
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_observer(observer.clone());
+        let addr = listener.local_addr().unwrap();
+        let mut active_connections = listener.active_connections();
+
+        tokio::spawn(listener.serve_fn(|mut stream: TcpStream| async move {
+            // the first byte sent by the client determines how the connection ends
+            match stream.read_u8().await? {
+                b'o' => Ok(()),
+                b't' => Err(crate::error::Error::new(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "read timeout",
+                ))),
+                _ => Err(crate::error::Error::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpected byte",
+                ))),
+            }
+        }));
+
+        let mut peers = Vec::new();
+        for byte in [b'o', b'x', b't'] {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            peers.push(client.local_addr().unwrap());
+            client.write_all(&[byte]).await.unwrap();
+            // wait for the connection to be closed by the server
+            let mut buf = Vec::new();
+            let _ = client.read_to_end(&mut buf).await;
+        }
+        wait_for_count(&mut active_connections, 0).await;
+
+        let events = observer.events();
+        let expected: Vec<(u64, String)> = [
+            CloseReason::Graceful,
+            CloseReason::Error,
+            CloseReason::Timeout,
+        ]
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, reason)| {
+            let id = i as u64;
+            [
+                (id, format!("accept {}", peers[i])),
+                (id, format!("ready {addr}")),
+                (id, format!("close {reason:?}")),
+            ]
+        })
+        .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_observer_panic() {
+        let observer = RecordingObserver::default();
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_observer(observer.clone());
+        let addr = listener.local_addr().unwrap();
+        let mut active_connections = listener.active_connections();
+
+        tokio::spawn(listener.serve_fn(|mut stream: TcpStream| async move {
+            if stream.read_u8().await? == b'p' {
+                panic!("service panicked");
+            }
+            Ok::<_, io::Error>(())
+        }));
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"p").await.unwrap();
+        let mut buf = Vec::new();
+        let _ = client.read_to_end(&mut buf).await;
+        wait_for_count(&mut active_connections, 0).await;
+
+        assert_eq!(
+            observer.events().last().unwrap(),
+            &(0, format!("close {:?}", CloseReason::Panic))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_shutdown_escalation() {
+        let observer = RecordingObserver::default();
+        let escalation = ShutdownEscalation::new();
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_observer(observer.clone())
+            .with_shutdown_escalation(escalation.clone());
+        let addr = listener.local_addr().unwrap();
+        let mut active_connections = listener.active_connections();
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        shutdown.spawn_task_fn(|guard| async move {
+            listener
+                .serve_fn_graceful(guard, |mut stream: TcpStream| async move {
+                    // signal that the connection got accepted,
+                    // and serve it until the client closes it, ignoring the shutdown
+                    stream.write_all(b"a").await?;
+                    let mut buf = [0u8; 1];
+                    while stream.read(&mut buf).await? > 0 {}
+                    Ok::<_, io::Error>(())
+                })
+                .await;
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        client.read_exact(&mut buf).await.unwrap();
+        wait_for_count(&mut active_connections, 1).await;
+
+        shutdown_tx.send(()).unwrap();
+        let start = tokio::time::Instant::now();
+        let shutdown = tokio::spawn({
+            let escalation = escalation.clone();
+            async move {
+                escalation
+                    .shutdown(
+                        shutdown,
+                        Duration::from_millis(100),
+                        Duration::from_millis(300),
+                    )
+                    .await
+            }
+        });
+
+        // the connection ignores the soft deadline
+        escalation.soft_deadline().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(!escalation.is_hard_deadline_reached());
+        assert_eq!(*active_connections.borrow(), 1);
+
+        // and is aborted at the hard deadline
+        let mut rest = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut rest))
+            .await
+            .expect("connection to be aborted")
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(escalation.is_hard_deadline_reached());
+        wait_for_count(&mut active_connections, 0).await;
+
+        let err = shutdown.await.unwrap().unwrap_err();
+        assert_eq!(err.deadline(), Duration::from_millis(300));
+        assert_eq!(
+            observer.events().last().unwrap(),
+            &(0, format!("close {:?}", CloseReason::Aborted))
+        );
+    }
+
+    #[test]
+    fn test_close_reason_from_error() {
+        let timeout = || io::Error::new(io::ErrorKind::TimedOut, "timeout");
+        let other = || io::Error::other("other");
+
+        assert_eq!(CloseReason::from_error(&timeout()), CloseReason::Timeout);
+        assert_eq!(CloseReason::from_error(&other()), CloseReason::Error);
+        assert_eq!(
+            CloseReason::from_error(&crate::service::layer::timeout::Elapsed::new(
+                Duration::from_secs(1)
+            )),
+            CloseReason::Timeout
+        );
+        assert_eq!(
+            CloseReason::from_error(&crate::error::Error::new(timeout())),
+            CloseReason::Timeout
+        );
+        // a timeout as the cause of another error
+        assert_eq!(
+            CloseReason::from_error(&io::Error::other(timeout())),
+            CloseReason::Timeout
+        );
+        assert_eq!(CloseReason::from_error(&"opaque"), CloseReason::Error);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_rebind_not_served() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let handle = listener.handle();
+        drop(listener);
+
+        let err = handle.rebind("127.0.0.1:0").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    }
+}
@@ -38,4 +38,10 @@
 //! ```
 
 mod listener;
-pub use listener::{TcpListener, TcpListenerBuilder};
+pub use listener::{ListenerControl, TcpListener, TcpListenerBuilder, TcpListenerHandle};
+
+mod multi;
+pub use multi::{serve_all, MaybeTlsStream, ServeListener};
+
+mod observer;
+pub use observer::{CloseReason, ConnectionInfo, ConnectionObserver};
@@ -0,0 +1,306 @@
+use super::TcpListener;
+use crate::graceful::ShutdownGuard;
+use crate::service::layer::MapRequest;
+use crate::service::{Layer, Service};
+use crate::tls::rustls::{
+    dep::tokio_rustls::server::TlsStream,
+    server::{TlsAcceptorLayer, TlsAcceptorService},
+};
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// A [`TcpListener`] to be served by [`serve_all`],
+/// optionally terminating TLS for the connections it accepts
+/// using a [`TlsAcceptorLayer`] with client config handler `H`.
+#[derive(Debug)]
+pub struct ServeListener<State, H = ()> {
+    listener: TcpListener<State>,
+    tls: Option<TlsAcceptorLayer<H>>,
+}
+
+impl<State, H> ServeListener<State, H> {
+    /// Serve the plain TCP connections of the given listener.
+    pub fn plain(listener: TcpListener<State>) -> Self {
+        Self {
+            listener,
+            tls: None,
+        }
+    }
+
+    /// Serve the connections of the given listener over TLS,
+    /// terminated using the given [`TlsAcceptorLayer`].
+    pub fn tls(listener: TcpListener<State>, tls: TlsAcceptorLayer<H>) -> Self {
+        Self {
+            listener,
+            tls: Some(tls),
+        }
+    }
+}
+
+impl<State> From<TcpListener<State>> for ServeListener<State> {
+    fn from(listener: TcpListener<State>) -> Self {
+        Self::plain(listener)
+    }
+}
+
+/// A stream accepted by a listener served using [`serve_all`],
+/// which is either a plain [`TcpStream`] or a [`TlsStream`] in case
+/// the listener was created using [`ServeListener::tls`].
+#[derive(Debug)]
+pub enum MaybeTlsStream {
+    /// A plain TCP stream.
+    Plain(TcpStream),
+    /// A TCP stream over which TLS is terminated.
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl MaybeTlsStream {
+    fn tls(stream: TlsStream<TcpStream>) -> Self {
+        Self::Tls(Box::new(stream))
+    }
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Self::Tls(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Self::Plain(stream) => stream.is_write_vectored(),
+            Self::Tls(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The service serving the TLS streams of the listeners created using [`ServeListener::tls`].
+type TlsService<S, H> =
+    TlsAcceptorService<MapRequest<Arc<S>, fn(TlsStream<TcpStream>) -> MaybeTlsStream>, H>;
+
+/// Serve gracefully connections from all the given listeners with the same service,
+/// e.g. to serve http on port `80` and https on port `443` from a single setup.
+///
+/// The service is shared by all listeners, and is given a [`MaybeTlsStream`],
+/// which is a [`TcpStream`] for the plain listeners and a [`TlsStream`]
+/// for the listeners created using [`ServeListener::tls`].
+/// Each listener is served as using [`TcpListener::serve_graceful`] with the given
+/// [`ShutdownGuard`], such that they all shut down together.
+/// This function returns once all listeners stopped accepting connections.
+///
+/// # Example
+///
+/// ```no_run
+/// use rama::graceful::Shutdown;
+/// use rama::http::{server::HttpServer, Response};
+/// use rama::rt::Executor;
+/// use rama::service::service_fn;
+/// use rama::tcp::server::{serve_all, ServeListener, TcpListener};
+/// use rama::tls::rustls::{dep::rustls::ServerConfig, server::TlsAcceptorLayer};
+/// use std::convert::Infallible;
+///
+/// # async fn run(tls_config: ServerConfig) {
+/// let shutdown = Shutdown::default();
+///
+/// let http = TcpListener::bind("0.0.0.0:80").await.unwrap();
+/// let https = TcpListener::bind("0.0.0.0:443").await.unwrap();
+///
+/// shutdown.spawn_task_fn(|guard| async move {
+///     let exec = Executor::graceful(guard.clone());
+///     let service = HttpServer::auto(exec).service(service_fn(|_| async {
+///         Ok::<_, Infallible>(Response::new("hello".to_owned()))
+///     }));
+///
+///     serve_all(
+///         guard,
+///         [
+///             ServeListener::plain(http),
+///             ServeListener::tls(https, TlsAcceptorLayer::new(tls_config)),
+///         ],
+///         service,
+///     )
+///     .await;
+/// });
+///
+/// shutdown.shutdown().await;
+/// # }
+/// ```
+pub async fn serve_all<State, H, S>(
+    guard: ShutdownGuard,
+    listeners: impl IntoIterator<Item = ServeListener<State, H>>,
+    service: S,
+) where
+    State: Send + Sync + 'static,
+    H: Clone,
+    S: Service<State, MaybeTlsStream>,
+    TlsService<S, H>: Service<State, TcpStream>,
+{
+    let service = Arc::new(service);
+    let plain_service = MapRequest::new(
+        service.clone(),
+        MaybeTlsStream::Plain as fn(TcpStream) -> MaybeTlsStream,
+    );
+    let tls_service = MapRequest::new(
+        service,
+        MaybeTlsStream::tls as fn(TlsStream<TcpStream>) -> MaybeTlsStream,
+    );
+    let mut plain = Vec::new();
+    let mut tls = Vec::new();
+    for ServeListener {
+        listener,
+        tls: layer,
+    } in listeners
+    {
+        match layer {
+            Some(layer) => {
+                tls.push(listener.serve_graceful(guard.clone(), layer.layer(tls_service.clone())))
+            }
+            None => plain.push(listener.serve_graceful(guard.clone(), plain_service.clone())),
+        }
+    }
+    tokio::join!(
+        futures::future::join_all(plain),
+        futures::future::join_all(tls)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use crate::stream::Stream;
+    use crate::test_helpers::tls::{
+        client_config, server_config, tls_connect, RecordingServerCertVerifier,
+    };
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A service which greets any accepted stream, mentioning whether it is over TLS.
+    #[derive(Debug)]
+    struct HelloService;
+
+    impl Service<(), MaybeTlsStream> for HelloService {
+        type Response = ();
+        type Error = std::io::Error;
+
+        async fn serve(
+            &self,
+            _ctx: crate::service::Context<()>,
+            mut stream: MaybeTlsStream,
+        ) -> Result<(), Self::Error> {
+            let greeting: &[u8] = match stream {
+                MaybeTlsStream::Plain(_) => b"hello plain",
+                MaybeTlsStream::Tls(_) => b"hello tls",
+            };
+            stream.write_all(greeting).await?;
+            stream.shutdown().await
+        }
+    }
+
+    async fn read_all(mut stream: impl Stream + Unpin) -> String {
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).await.unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_serve_all() {
+        let plain = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let plain_addr = plain.local_addr().unwrap();
+        let tls = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tls_addr = tls.local_addr().unwrap();
+        assert_ne!(plain_addr, tls_addr);
+
+        let (config, _) = server_config(&["localhost"]);
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        shutdown.spawn_task_fn(|guard| async move {
+            serve_all(
+                guard,
+                [
+                    ServeListener::plain(plain),
+                    ServeListener::tls(tls, TlsAcceptorLayer::new(config)),
+                ],
+                HelloService,
+            )
+            .await;
+            let _ = done_tx.send(());
+        });
+
+        // both listeners serve the same service
+        let stream = TcpStream::connect(plain_addr).await.unwrap();
+        assert_eq!(read_all(stream).await, "hello plain");
+
+        let client_config = Arc::new(client_config(Arc::new(
+            RecordingServerCertVerifier::default(),
+        )));
+        let stream = TcpStream::connect(tls_addr).await.unwrap();
+        let stream = tls_connect(client_config, "localhost", stream)
+            .await
+            .unwrap();
+        assert_eq!(read_all(stream).await, "hello tls");
+
+        // both listeners shut down together
+        shutdown_tx.send(()).unwrap();
+        shutdown
+            .shutdown_with_limit(Duration::from_secs(5))
+            .await
+            .unwrap();
+        done_rx.await.unwrap();
+        assert!(TcpStream::connect(plain_addr).await.is_err());
+        assert!(TcpStream::connect(tls_addr).await.is_err());
+    }
+}
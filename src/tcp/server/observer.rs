@@ -0,0 +1,190 @@
+use crate::service::layer::timeout::Elapsed;
+use std::{
+    any::Any, error::Error as StdError, io, net::SocketAddr, sync::Arc, thread, time::Instant,
+};
+
+/// Observer of the lifecycle of the connections served by a [`TcpListener`],
+/// registered using [`TcpListener::with_observer`].
+///
+/// This allows to plug in any kind of telemetry (e.g. metrics or structured logs)
+/// for the connections of a listener. All callbacks have a default no-op implementation,
+/// such that only the relevant ones have to be implemented. The callbacks are invoked inline,
+/// and should therefore not block.
+///
+/// For each connection the callbacks are invoked in the following order:
+///
+/// 1. [`ConnectionObserver::on_accept`]: the connection got accepted by the listener;
+/// 2. [`ConnectionObserver::on_ready`]: the connection is handed to the service;
+/// 3. [`ConnectionObserver::on_close`]: the service finished serving the connection,
+///    which gets closed. This callback is also invoked in case the service panicked,
+///    or the task serving the connection got cancelled.
+///
+/// [`TcpListener`]: crate::tcp::server::TcpListener
+/// [`TcpListener::with_observer`]: crate::tcp::server::TcpListener::with_observer
+pub trait ConnectionObserver: Send + Sync + 'static {
+    /// Called when the connection got accepted by the listener.
+    fn on_accept(&self, info: &ConnectionInfo) {
+        let _ = info;
+    }
+
+    /// Called when the connection is handed to the service.
+    fn on_ready(&self, info: &ConnectionInfo) {
+        let _ = info;
+    }
+
+    /// Called when the service finished serving the connection,
+    /// with the reason it is closed for.
+    fn on_close(&self, info: &ConnectionInfo, reason: CloseReason) {
+        let _ = (info, reason);
+    }
+}
+
+/// Metadata of a connection served by a [`TcpListener`],
+/// passed to the callbacks of a [`ConnectionObserver`].
+///
+/// [`TcpListener`]: crate::tcp::server::TcpListener
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    id: u64,
+    peer_addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+    accepted_at: Instant,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn new(id: u64, peer_addr: SocketAddr, local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            id,
+            peer_addr,
+            local_addr,
+            accepted_at: Instant::now(),
+        }
+    }
+
+    /// Get the id of the connection, unique within the listener.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the peer address of the connection.
+    pub fn peer_addr(&self) -> &SocketAddr {
+        &self.peer_addr
+    }
+
+    /// Get the local address of the connection, if known.
+    pub fn local_addr(&self) -> Option<&SocketAddr> {
+        self.local_addr.as_ref()
+    }
+
+    /// Get the moment the connection got accepted.
+    pub fn accepted_at(&self) -> Instant {
+        self.accepted_at
+    }
+}
+
+/// The reason a connection served by a [`TcpListener`] is closed,
+/// passed to [`ConnectionObserver::on_close`].
+///
+/// [`TcpListener`]: crate::tcp::server::TcpListener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CloseReason {
+    /// The service finished serving the connection successfully.
+    Graceful,
+    /// The service failed with an error.
+    Error,
+    /// The service failed because of a timeout, i.e. with an [`Elapsed`] error
+    /// or an [`io::Error`] of kind [`io::ErrorKind::TimedOut`] (as a cause).
+    Timeout,
+    /// The connection got aborted, as the hard deadline of a
+    /// [`ShutdownEscalation`] was reached while it was being served,
+    /// or as the task serving it got cancelled (e.g. as the runtime shut down).
+    ///
+    /// [`ShutdownEscalation`]: crate::graceful::ShutdownEscalation
+    Aborted,
+    /// The service panicked while serving the connection.
+    Panic,
+}
+
+/// Guard notifying the [`ConnectionObserver`] (if any) of the close of a connection
+/// once dropped, such that it is notified as well in case the service panics
+/// or the task serving the connection gets cancelled.
+pub(crate) struct CloseGuard {
+    observer: Option<Arc<dyn ConnectionObserver>>,
+    info: ConnectionInfo,
+    reason: Option<CloseReason>,
+}
+
+impl CloseGuard {
+    pub(crate) fn new(observer: Option<Arc<dyn ConnectionObserver>>, info: ConnectionInfo) -> Self {
+        Self {
+            observer,
+            info,
+            reason: None,
+        }
+    }
+
+    /// Record the reason the connection is closed for, once the service finished serving it.
+    pub(crate) fn close(mut self, reason: CloseReason) {
+        self.reason = Some(reason);
+    }
+}
+
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        let reason = self.reason.unwrap_or(if thread::panicking() {
+            CloseReason::Panic
+        } else {
+            CloseReason::Aborted
+        });
+        observer.on_close(&self.info, reason);
+    }
+}
+
+impl CloseReason {
+    /// Classify the result the service finished with.
+    pub(crate) fn from_result<T, E: 'static>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => CloseReason::Graceful,
+            Err(err) => CloseReason::from_error(err),
+        }
+    }
+
+    /// Classify the error the service failed with.
+    pub(crate) fn from_error<E: 'static>(err: &E) -> Self {
+        let err = err as &dyn Any;
+        let err: &(dyn StdError + 'static) = if let Some(err) = err.downcast_ref::<io::Error>() {
+            err
+        } else if let Some(err) = err.downcast_ref::<Elapsed>() {
+            err
+        } else if let Some(err) = err.downcast_ref::<crate::error::Error>() {
+            &**err
+        } else if let Some(err) = err.downcast_ref::<crate::error::BoxError>() {
+            &**err
+        } else {
+            return CloseReason::Error;
+        };
+
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if err.is::<Elapsed>() {
+                return CloseReason::Timeout;
+            }
+            source = match err.downcast_ref::<io::Error>() {
+                Some(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    return CloseReason::Timeout;
+                }
+                // the source of a custom io error skips the error it wraps
+                Some(err) => match err.get_ref() {
+                    Some(inner) => Some(inner as &(dyn StdError + 'static)),
+                    None => err.source(),
+                },
+                None => err.source(),
+            };
+        }
+        CloseReason::Error
+    }
+}
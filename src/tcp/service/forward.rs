@@ -4,7 +4,7 @@ use tokio::net::TcpStream;
 
 use crate::{
     service::{Context, Service},
-    stream::Stream,
+    stream::{self, AdaptiveBuffer, Stream},
     tcp::utils::is_connection_error,
 };
 
@@ -35,9 +35,14 @@ enum ForwarderKind {
 }
 
 /// A TCP forwarder.
+///
+/// The bytes are copied in each direction using an [`AdaptiveBuffer`], starting at 8 KiB
+/// and growing up to 64 KiB under sustained throughput by default,
+/// use [`Forwarder::buffer_size`] to configure these sizes.
 #[derive(Debug, Clone)]
 pub struct Forwarder {
     kind: ForwarderKind,
+    buffer_size: Option<(usize, usize)>,
 }
 
 impl Forwarder {
@@ -50,6 +55,7 @@ impl Forwarder {
     pub fn target(target: SocketAddr) -> Self {
         Self {
             kind: ForwarderKind::Static(target),
+            buffer_size: None,
         }
     }
 
@@ -61,6 +67,26 @@ impl Forwarder {
     pub fn dynamic() -> Self {
         Self {
             kind: ForwarderKind::Dynamic,
+            buffer_size: None,
+        }
+    }
+
+    /// Set the initial (and minimum) and maximum size of the [`AdaptiveBuffer`]s
+    /// used to forward the bytes, see [`AdaptiveBuffer::new`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_size` is zero.
+    pub fn buffer_size(mut self, initial_size: usize, max_size: usize) -> Self {
+        assert!(initial_size > 0, "initial buffer size must be non-zero");
+        self.buffer_size = Some((initial_size, max_size));
+        self
+    }
+
+    fn new_buffer(&self) -> AdaptiveBuffer {
+        match self.buffer_size {
+            Some((initial_size, max_size)) => AdaptiveBuffer::new(initial_size, max_size),
+            None => AdaptiveBuffer::default(),
         }
     }
 }
@@ -88,7 +114,15 @@ where
             }
         };
 
-        match tokio::io::copy_bidirectional(&mut source, &mut target).await {
+        let (mut source_to_target, mut target_to_source) = (self.new_buffer(), self.new_buffer());
+        match stream::copy_bidirectional(
+            &mut source,
+            &mut target,
+            &mut source_to_target,
+            &mut target_to_source,
+        )
+        .await
+        {
             Ok(_) => Ok(()),
             Err(err) => {
                 if is_connection_error(&err) {
@@ -2,3 +2,7 @@
 
 pub(crate) fn assert_send<T: Send>() {}
 pub(crate) fn assert_sync<T: Sync>() {}
+
+pub(crate) mod tls;
+
+pub(crate) mod net;
@@ -0,0 +1,34 @@
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Read a HTTP/1.1 (request or response) head, up to and including the empty line.
+pub(crate) async fn read_http_head<IO: AsyncRead + Unpin>(stream: &mut IO) -> Vec<u8> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    head
+}
+
+/// Spawn a HTTP/1.1 backend which responds to each request with `hello`,
+/// closing the connection afterwards.
+pub(crate) async fn spawn_http_backend() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                read_http_head(&mut stream).await;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello")
+                    .await
+                    .unwrap();
+            });
+        }
+    });
+    addr
+}
@@ -0,0 +1,167 @@
+use crate::service::{Context, Layer, Service};
+use crate::tls::rustls::dep::{
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+    rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme,
+    },
+    tokio_rustls::{client::TlsStream, server::TlsStream as ServerTlsStream, TlsConnector},
+};
+use crate::tls::rustls::verify::NoServerCertVerifier;
+use std::sync::{Arc, Mutex};
+use tokio::io::{duplex, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+
+/// Generate a self signed certificate (and its private key) for the given names.
+pub(crate) fn self_signed_cert(
+    names: &[&str],
+) -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+    let cert = rcgen::generate_simple_self_signed(
+        names
+            .iter()
+            .map(|name| (*name).to_owned())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    (
+        CertificateDer::from(cert.serialize_der().unwrap()),
+        PrivatePkcs8KeyDer::from(cert.serialize_private_key_der()).into(),
+    )
+}
+
+/// Create a server config using a single self signed certificate for the given names.
+pub(crate) fn server_config(names: &[&str]) -> (ServerConfig, CertificateDer<'static>) {
+    let (cert, key) = self_signed_cert(names);
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)
+        .unwrap();
+    (config, cert)
+}
+
+/// A [`ServerCertVerifier`] which accepts all certificates,
+/// keeping track of the certificates it was asked to verify.
+#[derive(Debug, Default)]
+pub(crate) struct RecordingServerCertVerifier {
+    certs: Mutex<Vec<CertificateDer<'static>>>,
+}
+
+impl RecordingServerCertVerifier {
+    /// The number of (full) handshakes in which a certificate was verified.
+    pub(crate) fn count(&self) -> usize {
+        self.certs.lock().unwrap().len()
+    }
+
+    /// The last certificate that was verified.
+    pub(crate) fn last_cert(&self) -> Option<CertificateDer<'static>> {
+        self.certs.lock().unwrap().last().cloned()
+    }
+}
+
+impl ServerCertVerifier for RecordingServerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.certs
+            .lock()
+            .unwrap()
+            .push(end_entity.clone().into_owned());
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        NoServerCertVerifier.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        NoServerCertVerifier.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        NoServerCertVerifier.supported_verify_schemes()
+    }
+}
+
+/// Create a client config which uses the given verifier.
+pub(crate) fn client_config(verifier: Arc<RecordingServerCertVerifier>) -> ClientConfig {
+    ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth()
+}
+
+/// Establish a client TLS connection over the given stream.
+pub(crate) async fn tls_connect<IO>(
+    config: Arc<ClientConfig>,
+    server_name: &str,
+    stream: IO,
+) -> std::io::Result<TlsStream<IO>>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    let server_name = ServerName::try_from(server_name.to_owned()).unwrap();
+    TlsConnector::from(config)
+        .connect(server_name, stream)
+        .await
+}
+
+/// A service which writes `hello` to the accepted TLS stream.
+#[derive(Debug, Clone)]
+pub(crate) struct HelloService;
+
+impl Service<(), ServerTlsStream<DuplexStream>> for HelloService {
+    type Response = ();
+    type Error = std::io::Error;
+
+    async fn serve(
+        &self,
+        _ctx: Context<()>,
+        mut stream: ServerTlsStream<DuplexStream>,
+    ) -> Result<Self::Response, Self::Error> {
+        stream.write_all(b"hello").await?;
+        stream.flush().await
+    }
+}
+
+/// Complete a TLS handshake between a client using the given config
+/// and the acceptor created by the given layer, wrapping a [`HelloService`].
+///
+/// An error is returned in case the client failed to complete the handshake.
+pub(crate) async fn handshake<L>(
+    layer: &L,
+    client_config: Arc<ClientConfig>,
+    server_name: &str,
+) -> std::io::Result<()>
+where
+    L: Layer<HelloService>,
+    L::Service: Service<(), DuplexStream>,
+    <L::Service as Service<(), DuplexStream>>::Error: std::fmt::Debug,
+{
+    let service = layer.layer(HelloService);
+
+    let (client_io, server_io) = duplex(16 * 1024);
+    let server = tokio::spawn(async move { service.serve(Context::default(), server_io).await });
+
+    let mut stream = tls_connect(client_config, server_name, client_io).await?;
+    // reading the application data ensures the session tickets are received as well
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"hello");
+
+    server.await.unwrap().unwrap();
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use crate::tls::rustls::dep::rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// A trait for resolving the server certificate to use for a TLS handshake,
+/// based on the server name indicator (SNI) sent by the client.
+///
+/// Used by the [`SniCertResolver`] to allow a single acceptor
+/// to serve many domains with different certificates.
+pub trait CertResolver: Send + Sync + 'static {
+    /// Returns the certificate (chain and key) to use for the given server name,
+    /// or `None` in case no certificate is known for it.
+    ///
+    /// The server name is `None` in case the client did not supply a SNI.
+    fn resolve_cert(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>>;
+}
+
+impl<F> CertResolver for F
+where
+    F: Fn(Option<&str>) -> Option<Arc<CertifiedKey>> + Send + Sync + 'static,
+{
+    fn resolve_cert(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        (self)(server_name)
+    }
+}
+
+/// A [`CertResolver`] mapping server names to their certificate.
+///
+/// Server names are case-insensitive, and are therefore lowercased
+/// both when inserted and when resolved.
+#[derive(Debug, Clone, Default)]
+pub struct CertMap {
+    certs: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl CertMap {
+    /// Create a new, empty, [`CertMap`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert the certificate to use for the given server name,
+    /// returning the certificate previously inserted for it, if any.
+    pub fn insert(
+        &mut self,
+        server_name: impl AsRef<str>,
+        cert: Arc<CertifiedKey>,
+    ) -> Option<Arc<CertifiedKey>> {
+        self.certs.insert(server_name.as_ref().to_lowercase(), cert)
+    }
+}
+
+impl<N: AsRef<str>> FromIterator<(N, Arc<CertifiedKey>)> for CertMap {
+    fn from_iter<I: IntoIterator<Item = (N, Arc<CertifiedKey>)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (server_name, cert) in iter {
+            map.insert(server_name, cert);
+        }
+        map
+    }
+}
+
+impl CertResolver for CertMap {
+    fn resolve_cert(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        self.certs.get(&server_name?.to_lowercase()).cloned()
+    }
+}
+
+/// A [`ResolvesServerCert`] implementation which selects the server certificate
+/// based on the SNI of the client, using the given [`CertResolver`].
+///
+/// Handshakes for which the [`CertResolver`] returns no certificate are rejected,
+/// unless a fallback certificate is configured using [`SniCertResolver::with_fallback`].
+///
+/// [`ResolvesServerCert`]: crate::tls::rustls::dep::rustls::server::ResolvesServerCert
+pub struct SniCertResolver<R> {
+    resolver: R,
+    fallback: Option<Arc<CertifiedKey>>,
+}
+
+impl<R> SniCertResolver<R> {
+    /// Create a new [`SniCertResolver`] using the given [`CertResolver`],
+    /// rejecting handshakes for an unknown SNI.
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            fallback: None,
+        }
+    }
+
+    /// Use the given certificate for handshakes for which
+    /// the [`CertResolver`] returns no certificate, instead of rejecting them.
+    pub fn with_fallback(mut self, fallback: Arc<CertifiedKey>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+}
+
+impl<R> fmt::Debug for SniCertResolver<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SniCertResolver")
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl<R> ResolvesServerCert for SniCertResolver<R>
+where
+    R: CertResolver,
+{
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.resolver
+            .resolve_cert(client_hello.server_name())
+            .or_else(|| self.fallback.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::tls::{
+            client_config, handshake, self_signed_cert, RecordingServerCertVerifier,
+        },
+        tls::rustls::{
+            dep::rustls::crypto::ring::sign::any_supported_type, server::TlsAcceptorLayer,
+        },
+    };
+    use pki_types::CertificateDer;
+
+    fn certified_key(name: &str) -> (Arc<CertifiedKey>, CertificateDer<'static>) {
+        let (cert, key) = self_signed_cert(&[name]);
+        let key = any_supported_type(&key).unwrap();
+        (Arc::new(CertifiedKey::new(vec![cert.clone()], key)), cert)
+    }
+
+    fn resolver() -> (CertMap, CertificateDer<'static>, CertificateDer<'static>) {
+        let (key_a, cert_a) = certified_key("a.example.com");
+        let (key_b, cert_b) = certified_key("b.example.com");
+        let mut resolver = CertMap::new();
+        resolver.insert("a.example.com", key_a);
+        resolver.insert("B.Example.com", key_b);
+        (resolver, cert_a, cert_b)
+    }
+
+    #[tokio::test]
+    async fn test_sni_cert_resolver() {
+        let (resolver, cert_a, cert_b) = resolver();
+        let layer = TlsAcceptorLayer::with_cert_resolver(SniCertResolver::new(resolver));
+
+        let verifier = Arc::new(RecordingServerCertVerifier::default());
+        let client_config = Arc::new(client_config(verifier.clone()));
+
+        handshake(&layer, client_config.clone(), "a.example.com")
+            .await
+            .unwrap();
+        assert_eq!(verifier.last_cert(), Some(cert_a));
+
+        handshake(&layer, client_config.clone(), "b.example.com")
+            .await
+            .unwrap();
+        assert_eq!(verifier.last_cert(), Some(cert_b));
+
+        // unknown SNI is rejected
+        assert!(handshake(&layer, client_config, "c.example.com")
+            .await
+            .is_err());
+        assert_eq!(verifier.count(), 2);
+    }
+
+    #[test]
+    fn test_cert_map_case_insensitive() {
+        let (key, _) = certified_key("a.example.com");
+        let map: CertMap = [("A.Example.COM", key)].into_iter().collect();
+
+        assert!(map.resolve_cert(Some("a.example.com")).is_some());
+        assert!(map.resolve_cert(Some("A.EXAMPLE.com")).is_some());
+        assert!(map.resolve_cert(Some("b.example.com")).is_none());
+        assert!(map.resolve_cert(None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sni_cert_resolver_fallback() {
+        let (resolver, cert_a, _) = resolver();
+        let (fallback, fallback_cert) = certified_key("default.example.com");
+        let layer = TlsAcceptorLayer::with_cert_resolver(
+            SniCertResolver::new(resolver).with_fallback(fallback),
+        );
+
+        let verifier = Arc::new(RecordingServerCertVerifier::default());
+        let client_config = Arc::new(client_config(verifier.clone()));
+
+        handshake(&layer, client_config.clone(), "a.example.com")
+            .await
+            .unwrap();
+        assert_eq!(verifier.last_cert(), Some(cert_a));
+
+        handshake(&layer, client_config, "c.example.com")
+            .await
+            .unwrap();
+        assert_eq!(verifier.last_cert(), Some(fallback_cert));
+    }
+
+    #[test]
+    fn test_cert_resolver_fn() {
+        let (key, _) = certified_key("a.example.com");
+        let resolver = move |server_name: Option<&str>| match server_name {
+            Some("a.example.com") => Some(key.clone()),
+            _ => None,
+        };
+
+        assert!(resolver.resolve_cert(Some("a.example.com")).is_some());
+        assert!(resolver.resolve_cert(Some("b.example.com")).is_none());
+        assert!(resolver.resolve_cert(None).is_none());
+    }
+}
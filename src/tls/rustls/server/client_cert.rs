@@ -0,0 +1,171 @@
+use crate::{
+    service::{context::Extensions, Context, Matcher},
+    tls::rustls::dep::{pki_types::CertificateDer, rustls::ServerConnection},
+};
+
+/// The certificate chain presented by the client during the TLS handshake,
+/// added to the [`Context`] by the [`TlsAcceptorService`] for each accepted connection.
+///
+/// The chain is only present in case the client presented a certificate which was
+/// accepted by the client certificate verifier of the [`ServerConfig`],
+/// and is empty otherwise (e.g. when client authentication is not enabled).
+///
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+/// [`ServerConfig`]: https://docs.rs/rustls/latest/rustls/server/struct.ServerConfig.html
+#[derive(Debug, Clone, Default)]
+pub struct ClientCertificates {
+    certs: Vec<CertificateDer<'static>>,
+}
+
+impl ClientCertificates {
+    /// Create a new [`ClientCertificates`] for the given (verified) certificate chain.
+    pub fn new(certs: Vec<CertificateDer<'static>>) -> Self {
+        Self { certs }
+    }
+
+    /// The verified certificate chain of the client, starting with its end-entity certificate.
+    pub fn certs(&self) -> &[CertificateDer<'static>] {
+        &self.certs
+    }
+
+    /// Returns `true` if the client did not present a (verified) certificate.
+    pub fn is_empty(&self) -> bool {
+        self.certs.is_empty()
+    }
+}
+
+impl From<&ServerConnection> for ClientCertificates {
+    fn from(conn: &ServerConnection) -> Self {
+        Self::new(
+            conn.peer_certificates()
+                .map(|certs| certs.iter().map(|cert| cert.clone().into_owned()).collect())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Filter which matches only if the client presented a verified certificate (mTLS),
+/// without inspecting the certificate itself.
+///
+/// The filter relies on the [`ClientCertificates`] found in the [`Context`].
+pub struct ClientCertPresentFilter {
+    optional: bool,
+}
+
+impl ClientCertPresentFilter {
+    /// Create a new filter matching only if the client presented a verified certificate.
+    ///
+    /// This filter will not match in case no TLS connection info could be found,
+    /// if you want to match in case it could not be found,
+    /// use the [`ClientCertPresentFilter::optional`] constructor.
+    pub fn new() -> Self {
+        Self { optional: false }
+    }
+
+    /// Create a new filter matching only if the client presented a verified certificate,
+    /// or no TLS connection info could be found.
+    ///
+    /// Use the [`ClientCertPresentFilter::new`] constructor if you do not want
+    /// to match in case no TLS connection info could be found.
+    pub fn optional() -> Self {
+        Self { optional: true }
+    }
+}
+
+impl Default for ClientCertPresentFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State, Request> Matcher<State, Request> for ClientCertPresentFilter {
+    fn matches(&self, _ext: Option<&mut Extensions>, ctx: &Context<State>, _req: &Request) -> bool {
+        ctx.get::<ClientCertificates>()
+            .map(|certs| !certs.is_empty())
+            .unwrap_or(self.optional)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        http::{Body, Request},
+        service::{service_fn, Layer, Service},
+        test_helpers::tls::{
+            client_config, server_config, tls_connect, RecordingServerCertVerifier,
+        },
+        tls::rustls::server::TlsAcceptorLayer,
+    };
+    use std::sync::Arc;
+
+    fn fake_client_certificates() -> ClientCertificates {
+        ClientCertificates::new(vec![CertificateDer::from(vec![0x30, 0x00])])
+    }
+
+    #[test]
+    fn test_client_cert_present_filter() {
+        let filter = ClientCertPresentFilter::new();
+
+        let mut ctx = Context::default();
+        let req = Request::builder()
+            .method("GET")
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+
+        // test #1: no match: test with no tls connection info registered
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #2: no match: test with no client certificate presented
+        ctx.insert(ClientCertificates::default());
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // test #3: match: test with a client certificate presented
+        ctx.insert(fake_client_certificates());
+        assert!(filter.matches(None, &ctx, &req));
+    }
+
+    #[test]
+    fn test_client_cert_present_filter_optional() {
+        let filter = ClientCertPresentFilter::optional();
+
+        let mut ctx = Context::default();
+
+        // test #1: match: test with no tls connection info registered
+        assert!(filter.matches(None, &ctx, &()));
+
+        // test #2: no match: test with no client certificate presented
+        ctx.insert(ClientCertificates::default());
+        assert!(!filter.matches(None, &ctx, &()));
+
+        // test #3: match: test with a client certificate presented
+        ctx.insert(fake_client_certificates());
+        assert!(filter.matches(None, &ctx, &()));
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_adds_client_certificates() {
+        let (config, _) = server_config(&["localhost"]);
+        let service = TlsAcceptorLayer::new(config).layer(service_fn(
+            |ctx: Context<()>, _stream| async move {
+                Ok::<_, std::convert::Infallible>(ctx.get::<ClientCertificates>().cloned())
+            },
+        ));
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let server =
+            tokio::spawn(async move { service.serve(Context::default(), server_io).await });
+        let client_config = Arc::new(client_config(Arc::new(
+            RecordingServerCertVerifier::default(),
+        )));
+        let _stream = tls_connect(client_config, "localhost", client_io)
+            .await
+            .unwrap();
+
+        // no client authentication configured: no certificates presented
+        let certs = server.await.unwrap().unwrap().unwrap();
+        assert!(certs.is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use crate::tls::rustls::dep::rustls::{
     server::ClientHello, CipherSuite, ServerConfig, SignatureScheme,
 };
 use std::{future::Future, sync::Arc};
+use tokio::sync::watch;
 
 /// A struct containing the information of the accepted client hello.
 #[derive(Debug, Clone)]
@@ -98,6 +99,20 @@ where
     }
 }
 
+/// Provides the latest [`ServerConfig`] sent over the watch channel,
+/// allowing the config to be swapped (e.g. to rotate certificates) without a restart.
+///
+/// Only new handshakes are affected by a swapped config,
+/// connections that are already established keep using their config.
+impl ServerConfigProvider for watch::Receiver<Arc<ServerConfig>> {
+    async fn get_server_config(
+        &self,
+        _client_hello: IncomingClientHello,
+    ) -> Result<Option<Arc<ServerConfig>>, std::io::Error> {
+        Ok(Some(self.borrow().clone()))
+    }
+}
+
 impl TlsClientConfigHandler<()> {
     /// Creates a new [`TlsClientConfigHandler`] with the default configuration.
     pub fn new() -> Self {
@@ -0,0 +1,115 @@
+use crate::tls::rustls::dep::rustls::{CipherSuite, ProtocolVersion, ServerConnection};
+
+/// Information about the TLS session negotiated with the client,
+/// added to the [`Context`] by the [`TlsAcceptorService`] for each accepted connection.
+///
+/// [`Context`]: crate::service::Context
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConnectionInfo {
+    protocol_version: Option<ProtocolVersion>,
+    cipher_suite: Option<CipherSuite>,
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+}
+
+impl TlsConnectionInfo {
+    /// Create a new, empty, [`TlsConnectionInfo`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the negotiated TLS protocol version.
+    pub fn with_protocol_version(mut self, version: ProtocolVersion) -> Self {
+        self.protocol_version = Some(version);
+        self
+    }
+
+    /// Set the negotiated cipher suite.
+    pub fn with_cipher_suite(mut self, suite: CipherSuite) -> Self {
+        self.cipher_suite = Some(suite);
+        self
+    }
+
+    /// Set the server name (SNI) requested by the client.
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Set the negotiated ALPN protocol.
+    pub fn with_alpn_protocol(mut self, protocol: impl Into<Vec<u8>>) -> Self {
+        self.alpn_protocol = Some(protocol.into());
+        self
+    }
+
+    /// The negotiated TLS protocol version.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version
+    }
+
+    /// The negotiated cipher suite.
+    pub fn cipher_suite(&self) -> Option<CipherSuite> {
+        self.cipher_suite
+    }
+
+    /// The server name (SNI) requested by the client.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// The negotiated ALPN protocol.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+}
+
+impl From<&ServerConnection> for TlsConnectionInfo {
+    fn from(conn: &ServerConnection) -> Self {
+        Self {
+            protocol_version: conn.protocol_version(),
+            cipher_suite: conn.negotiated_cipher_suite().map(|suite| suite.suite()),
+            server_name: conn.server_name().map(ToOwned::to_owned),
+            alpn_protocol: conn.alpn_protocol().map(ToOwned::to_owned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        service::{service_fn, Context, Layer, Service},
+        test_helpers::tls::{
+            client_config, server_config, tls_connect, RecordingServerCertVerifier,
+        },
+        tls::rustls::server::TlsAcceptorLayer,
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_acceptor_adds_tls_connection_info() {
+        let (config, _) = server_config(&["localhost"]);
+        let service = TlsAcceptorLayer::new(config).layer(service_fn(
+            |ctx: Context<()>, _stream| async move {
+                Ok::<_, std::convert::Infallible>(ctx.get::<TlsConnectionInfo>().cloned())
+            },
+        ));
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let server =
+            tokio::spawn(async move { service.serve(Context::default(), server_io).await });
+        let client_config = Arc::new(client_config(Arc::new(
+            RecordingServerCertVerifier::default(),
+        )));
+        let _stream = tls_connect(client_config, "localhost", client_io)
+            .await
+            .unwrap();
+
+        let info = server.await.unwrap().unwrap().unwrap();
+        assert!(info.protocol_version().is_some());
+        assert!(info.cipher_suite().is_some());
+        assert_eq!(info.server_name(), Some("localhost"));
+        assert_eq!(info.alpn_protocol(), None);
+    }
+}
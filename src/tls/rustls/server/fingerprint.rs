@@ -0,0 +1,680 @@
+use crate::{
+    service::{context::Extensions, Context, Matcher},
+    tls::rustls::dep::rustls::{self, InvalidMessage},
+};
+use md5::{Digest, Md5};
+use ring::digest::{digest, SHA256};
+use std::{collections::HashSet, fmt::Write as _, io};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The maximum size of a `ClientHello` read by the [`TlsAcceptorService`],
+/// in order to compute its [`ClientHelloFingerprint`].
+///
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+const MAX_CLIENT_HELLO_SIZE: usize = 64 * 1024;
+
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+const EXTENSION_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXTENSION_EC_POINT_FORMATS: u16 = 0x000b;
+const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXTENSION_ALPN: u16 = 0x0010;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+/// The [JA3] and [JA4] fingerprints of the `ClientHello` sent by a client,
+/// added to the [`Context`] by the [`TlsAcceptorService`] in case fingerprinting is enabled,
+/// see [`TlsAcceptorService::capture_fingerprint`].
+///
+/// Clients using the same TLS library (and configuration) send similar `ClientHello`
+/// messages, such that these fingerprints can be used to identify the kind of client
+/// (e.g. a browser, a bot or a scripted http client), regardless of what it claims to be.
+///
+/// [JA3]: https://github.com/salesforce/ja3
+/// [JA4]: https://github.com/FoxIO-LLC/ja4
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+/// [`TlsAcceptorService::capture_fingerprint`]: crate::tls::rustls::server::TlsAcceptorService::capture_fingerprint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHelloFingerprint {
+    ja3: String,
+    ja3_hash: String,
+    ja4: String,
+}
+
+impl ClientHelloFingerprint {
+    /// Create a new [`ClientHelloFingerprint`] from the given (full) JA3 string
+    /// and JA4 fingerprint, e.g. as computed by another TLS terminator.
+    pub fn new(ja3: impl Into<String>, ja4: impl Into<String>) -> Self {
+        let ja3 = ja3.into();
+        Self {
+            ja3_hash: hex(&Md5::digest(ja3.as_bytes())),
+            ja3,
+            ja4: ja4.into(),
+        }
+    }
+
+    /// Compute the [`ClientHelloFingerprint`] of the given `ClientHello` handshake message,
+    /// starting with its handshake header.
+    ///
+    /// Returns `None` in case the message is not a valid `ClientHello`.
+    pub fn from_client_hello(message: &[u8]) -> Option<Self> {
+        let hello = ParsedClientHello::parse(message)?;
+        Some(Self::new(hello.ja3(), hello.ja4()))
+    }
+
+    /// The full JA3 string, e.g. `771,4865-4866,0-10-11,29-23,0`.
+    pub fn ja3(&self) -> &str {
+        &self.ja3
+    }
+
+    /// The JA3 fingerprint, which is the (hex encoded) MD5 hash of the full JA3 string.
+    pub fn ja3_hash(&self) -> &str {
+        &self.ja3_hash
+    }
+
+    /// The JA4 fingerprint, e.g. `t13d1516h2_8daaf6152771_02713d6af862`.
+    pub fn ja4(&self) -> &str {
+        &self.ja4
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Filter matching on the [`ClientHelloFingerprint`] found in the [`Context`],
+/// against a set of allowed and denied fingerprints.
+///
+/// A fingerprint can be given as JA3 (either the full string or its hash) or as JA4.
+/// The filter matches in case the fingerprint of the client is not denied,
+/// and is allowed in case any allowed fingerprints are configured.
+///
+/// This filter will not match in case no fingerprint could be found,
+/// use the [`JaFingerprintFilter::optional`] constructor to match in that case.
+///
+/// # Example
+///
+/// ```
+/// use rama::service::{Context, Matcher};
+/// use rama::tls::rustls::server::{ClientHelloFingerprint, JaFingerprintFilter};
+///
+/// let filter = JaFingerprintFilter::new().deny("t13d0307h2_5559582ccdc4_38dbf9c86be1");
+///
+/// let mut ctx = Context::default();
+/// ctx.insert(ClientHelloFingerprint::new(
+///     "771,4865-4866-49195,0-10-11-13-16-43-23,29-23,0",
+///     "t13d0307h2_5559582ccdc4_38dbf9c86be1",
+/// ));
+/// assert!(!filter.matches(None, &ctx, &()));
+/// ```
+pub struct JaFingerprintFilter {
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+    optional: bool,
+}
+
+impl JaFingerprintFilter {
+    /// Create a new filter matching all fingerprints, until fingerprints are allowed or denied.
+    ///
+    /// This filter will not match in case no fingerprint could be found,
+    /// if you want to match in case it could not be found,
+    /// use the [`JaFingerprintFilter::optional`] constructor.
+    pub fn new() -> Self {
+        Self {
+            allow: HashSet::new(),
+            deny: HashSet::new(),
+            optional: false,
+        }
+    }
+
+    /// Create a new filter matching all fingerprints, until fingerprints are allowed or denied,
+    /// or in case no fingerprint could be found.
+    ///
+    /// Use the [`JaFingerprintFilter::new`] constructor if you do not want
+    /// to match in case no fingerprint could be found.
+    pub fn optional() -> Self {
+        Self {
+            optional: true,
+            ..Self::new()
+        }
+    }
+
+    /// Allow the given JA3 or JA4 fingerprint,
+    /// such that only allowed fingerprints match.
+    pub fn allow(mut self, fingerprint: impl AsRef<str>) -> Self {
+        self.allow.insert(fingerprint.as_ref().to_ascii_lowercase());
+        self
+    }
+
+    /// Deny the given JA3 or JA4 fingerprint, such that it never matches.
+    pub fn deny(mut self, fingerprint: impl AsRef<str>) -> Self {
+        self.deny.insert(fingerprint.as_ref().to_ascii_lowercase());
+        self
+    }
+
+    fn matches_fingerprint(&self, fingerprint: &ClientHelloFingerprint) -> bool {
+        let ids = [fingerprint.ja3(), fingerprint.ja3_hash(), fingerprint.ja4()]
+            .map(str::to_ascii_lowercase);
+        if ids.iter().any(|id| self.deny.contains(id)) {
+            return false;
+        }
+        self.allow.is_empty() || ids.iter().any(|id| self.allow.contains(id))
+    }
+}
+
+impl Default for JaFingerprintFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State, Request> Matcher<State, Request> for JaFingerprintFilter {
+    fn matches(&self, _ext: Option<&mut Extensions>, ctx: &Context<State>, _req: &Request) -> bool {
+        ctx.get::<ClientHelloFingerprint>()
+            .map(|fingerprint| self.matches_fingerprint(fingerprint))
+            .unwrap_or(self.optional)
+    }
+}
+
+/// The `ClientHello` as read from the stream by the [`TlsAcceptorService`].
+///
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+pub(super) struct ClientHelloRecords {
+    /// The raw TLS records containing the `ClientHello`.
+    pub(super) records: Vec<u8>,
+    /// The `ClientHello` handshake message.
+    pub(super) message: Vec<u8>,
+}
+
+/// Read the TLS records containing the `ClientHello` from the given stream,
+/// without reading any further.
+///
+/// Fails with the same error as rustls in case the client does not initiate a TLS handshake,
+/// such that it can be detected as such.
+pub(super) async fn read_client_hello<IO>(stream: &mut IO) -> io::Result<ClientHelloRecords>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut records = Vec::new();
+    let mut message = Vec::new();
+    loop {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).await?;
+        if header[0] != 0x16 {
+            return Err(invalid_message(InvalidMessage::InvalidContentType));
+        }
+        if header[1] != 0x03 {
+            return Err(invalid_message(InvalidMessage::UnknownProtocolVersion));
+        }
+        let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if length == 0 || records.len() + length > MAX_CLIENT_HELLO_SIZE {
+            return Err(invalid_message(InvalidMessage::MessageTooLarge));
+        }
+
+        records.extend_from_slice(&header);
+        let start = records.len();
+        records.resize(start + length, 0);
+        stream.read_exact(&mut records[start..]).await?;
+        message.extend_from_slice(&records[start..]);
+
+        if message.len() >= 4 {
+            if message[0] != 0x01 {
+                return Err(invalid_message(InvalidMessage::UnexpectedMessage(
+                    "expected a ClientHello",
+                )));
+            }
+            let size = 4 + u32::from_be_bytes([0, message[1], message[2], message[3]]) as usize;
+            if size > MAX_CLIENT_HELLO_SIZE {
+                return Err(invalid_message(InvalidMessage::MessageTooLarge));
+            }
+            if message.len() >= size {
+                message.truncate(size);
+                return Ok(ClientHelloRecords { records, message });
+            }
+        }
+    }
+}
+
+fn invalid_message(err: InvalidMessage) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        rustls::Error::InvalidMessage(err),
+    )
+}
+
+/// The parts of a `ClientHello` used to compute its fingerprints.
+#[derive(Debug, Default)]
+struct ParsedClientHello {
+    version: u16,
+    cipher_suites: Vec<u16>,
+    extensions: Vec<u16>,
+    supported_groups: Vec<u16>,
+    ec_point_formats: Vec<u8>,
+    signature_algorithms: Vec<u16>,
+    alpn: Option<Vec<u8>>,
+    supported_versions: Vec<u16>,
+}
+
+/// A cursor over the bytes of a TLS message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.take(3)
+            .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize)
+    }
+
+    /// Read a vector prefixed by its length, encoded in the given number of bytes.
+    fn vec(&mut self, length_size: usize) -> Option<Reader<'a>> {
+        let length = match length_size {
+            1 => self.u8()? as usize,
+            2 => self.u16()? as usize,
+            _ => self.u24()?,
+        };
+        self.take(length).map(Reader)
+    }
+
+    fn u16_list(mut self) -> Option<Vec<u16>> {
+        let mut values = Vec::with_capacity(self.0.len() / 2);
+        while !self.0.is_empty() {
+            values.push(self.u16()?);
+        }
+        Some(values)
+    }
+}
+
+/// GREASE values (RFC 8701) are ignored by both JA3 and JA4.
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn without_grease(values: &[u16]) -> impl Iterator<Item = u16> + '_ {
+    values.iter().copied().filter(|value| !is_grease(*value))
+}
+
+impl ParsedClientHello {
+    fn parse(message: &[u8]) -> Option<Self> {
+        let mut message = Reader(message);
+        if message.u8()? != 0x01 {
+            return None;
+        }
+        let mut body = message.vec(3)?;
+
+        let mut hello = Self {
+            version: body.u16()?,
+            ..Self::default()
+        };
+        body.take(32)?; // random
+        body.vec(1)?; // legacy session id
+        hello.cipher_suites = body.vec(2)?.u16_list()?;
+        body.vec(1)?; // legacy compression methods
+        if body.0.is_empty() {
+            return Some(hello);
+        }
+
+        let mut extensions = body.vec(2)?;
+        while !extensions.0.is_empty() {
+            let extension = extensions.u16()?;
+            let mut data = extensions.vec(2)?;
+            hello.extensions.push(extension);
+            match extension {
+                EXTENSION_SUPPORTED_GROUPS => hello.supported_groups = data.vec(2)?.u16_list()?,
+                EXTENSION_EC_POINT_FORMATS => hello.ec_point_formats = data.vec(1)?.0.to_vec(),
+                EXTENSION_SIGNATURE_ALGORITHMS => {
+                    hello.signature_algorithms = data.vec(2)?.u16_list()?
+                }
+                EXTENSION_ALPN => {
+                    let mut protocols = data.vec(2)?;
+                    hello.alpn = Some(protocols.vec(1)?.0.to_vec());
+                }
+                EXTENSION_SUPPORTED_VERSIONS => {
+                    hello.supported_versions = data.vec(1)?.u16_list()?
+                }
+                _ => (),
+            }
+        }
+        Some(hello)
+    }
+
+    fn ja3(&self) -> String {
+        fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+            values.map(|v| v.to_string()).collect::<Vec<_>>().join("-")
+        }
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(without_grease(&self.cipher_suites)),
+            join(without_grease(&self.extensions)),
+            join(without_grease(&self.supported_groups)),
+            join(self.ec_point_formats.iter()),
+        )
+    }
+
+    fn ja4(&self) -> String {
+        let version = without_grease(&self.supported_versions)
+            .max()
+            .unwrap_or(self.version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let sni = if self.extensions.contains(&EXTENSION_SERVER_NAME) {
+            'd'
+        } else {
+            'i'
+        };
+        let alpn = match self.alpn.as_deref() {
+            Some([first, .., last] | [first @ last]) => {
+                if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+                    format!("{}{}", *first as char, *last as char)
+                } else {
+                    let first = hex(&[*first]);
+                    let last = hex(&[*last]);
+                    format!("{}{}", &first[..1], &last[1..])
+                }
+            }
+            _ => "00".to_owned(),
+        };
+
+        let mut cipher_suites: Vec<_> = without_grease(&self.cipher_suites).collect();
+        let mut extensions: Vec<_> = without_grease(&self.extensions).collect();
+        let ja4_a = format!(
+            "t{version}{sni}{:02}{:02}{alpn}",
+            cipher_suites.len().min(99),
+            extensions.len().min(99),
+        );
+
+        cipher_suites.sort_unstable();
+        let ja4_b = truncated_sha256(&hex_list(&cipher_suites));
+
+        extensions.retain(|ext| *ext != EXTENSION_SERVER_NAME && *ext != EXTENSION_ALPN);
+        extensions.sort_unstable();
+        let mut ja4_c = hex_list(&extensions);
+        if !extensions.is_empty() && !self.signature_algorithms.is_empty() {
+            ja4_c.push('_');
+            ja4_c.push_str(&hex_list(
+                &without_grease(&self.signature_algorithms).collect::<Vec<_>>(),
+            ));
+        }
+        let ja4_c = truncated_sha256(&ja4_c);
+
+        format!("{ja4_a}_{ja4_b}_{ja4_c}")
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+fn hex_list(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{value:04x}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The first 12 characters of the (hex encoded) SHA-256 hash of the given list,
+/// or all zeros in case the list is empty, as used by JA4.
+fn truncated_sha256(list: &str) -> String {
+    if list.is_empty() {
+        return "000000000000".to_owned();
+    }
+    let mut hash = hex(digest(&SHA256, list.as_bytes()).as_ref());
+    hash.truncate(12);
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        http::{Body, Request},
+        service::{service_fn, Layer, Service},
+        test_helpers::tls::{
+            client_config, server_config, tls_connect, RecordingServerCertVerifier,
+        },
+        tls::rustls::server::TlsAcceptorLayer,
+    };
+    use std::sync::Arc;
+
+    const JA3: &str = "771,4865-4866-49195,0-10-11-13-16-43-23,29-23,0";
+    const JA4: &str = "t13d0307h2_5559582ccdc4_38dbf9c86be1";
+
+    fn extension(ty: u16, data: &[u8]) -> Vec<u8> {
+        let mut ext = ty.to_be_bytes().to_vec();
+        ext.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        ext.extend_from_slice(data);
+        ext
+    }
+
+    fn u16_vec(values: &[u16], length_size: usize) -> Vec<u8> {
+        let len = values.len() * 2;
+        let mut out = (len as u16).to_be_bytes()[2 - length_size..].to_vec();
+        for value in values {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        out
+    }
+
+    /// A `ClientHello` handshake message with the given version, cipher suites and extensions.
+    fn handshake(version: u16, cipher_suites: &[u16], extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = version.to_be_bytes().to_vec();
+        body.extend_from_slice(&[0; 32]);
+        body.push(0);
+        body.extend(u16_vec(cipher_suites, 2));
+        body.extend_from_slice(&[1, 0]);
+
+        let extensions = extensions.concat();
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend(extensions);
+
+        let mut message = vec![0x01];
+        message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        message.extend(body);
+        message
+    }
+
+    fn sni_extension() -> Vec<u8> {
+        let mut sni = vec![0, 14, 0, 0, 11];
+        sni.extend_from_slice(b"example.com");
+        extension(0x0000, &sni)
+    }
+
+    /// A `ClientHello` handshake message, including GREASE values.
+    fn client_hello() -> Vec<u8> {
+        let alpn = [
+            0, 12, 2, b'h', b'2', 8, b'h', b't', b't', b'p', b'/', b'1', b'.', b'1',
+        ];
+        let extensions = [
+            extension(0x1a1a, &[]),
+            sni_extension(),
+            extension(0x000a, &u16_vec(&[0x2a2a, 0x001d, 0x0017], 2)),
+            extension(0x000b, &[1, 0]),
+            extension(0x000d, &u16_vec(&[0x0403, 0x0804], 2)),
+            extension(0x0010, &alpn),
+            extension(0x002b, &u16_vec(&[0x3a3a, 0x0304, 0x0303], 1)),
+            extension(0x0017, &[]),
+        ];
+        handshake(0x0303, &[0x0a0a, 0x1301, 0x1302, 0xc02b], &extensions)
+    }
+
+    #[test]
+    fn test_ja3_known_answer() {
+        // the example of https://github.com/salesforce/ja3
+        let extensions = [
+            sni_extension(),
+            extension(0x000a, &u16_vec(&[23, 24, 25], 2)),
+            extension(0x000b, &[1, 0]),
+        ];
+        let message = handshake(
+            0x0301,
+            &[47, 53, 5, 10, 49161, 49162, 49171, 49172, 50, 56, 19, 4],
+            &extensions,
+        );
+        let fingerprint = ClientHelloFingerprint::from_client_hello(&message).unwrap();
+        assert_eq!(
+            fingerprint.ja3(),
+            "769,47-53-5-10-49161-49162-49171-49172-50-56-19-4,0-10-11,23-24-25,0"
+        );
+        assert_eq!(fingerprint.ja3_hash(), "ada70206e40642a3e4461f35503241d5");
+    }
+
+    #[test]
+    fn test_ja4_known_answer() {
+        // the (Chrome) example of https://github.com/FoxIO-LLC/ja4/blob/main/technical_details/JA4.md
+        let cipher_suites = [
+            0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014,
+            0x009c, 0x009d, 0x002f, 0x0035,
+        ];
+        let alpn = [0, 3, 2, b'h', b'2'];
+        let signature_algorithms = [
+            0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601,
+        ];
+        let extensions = [
+            extension(0x0a0a, &[]),
+            sni_extension(),
+            extension(0x0017, &[]),
+            extension(0xff01, &[0]),
+            extension(0x000a, &u16_vec(&[0x001d, 0x0017, 0x0018], 2)),
+            extension(0x000b, &[1, 0]),
+            extension(0x0023, &[]),
+            extension(0x0010, &alpn),
+            extension(0x0005, &[1, 0, 0, 0, 0]),
+            extension(0x000d, &u16_vec(&signature_algorithms, 2)),
+            extension(0x0012, &[]),
+            extension(0x0033, &[0, 0]),
+            extension(0x002d, &[1, 1]),
+            extension(0x002b, &u16_vec(&[0x0304, 0x0303], 1)),
+            extension(0x001b, &[2, 0, 2]),
+            extension(0x4469, &[0, 3, 2, b'h', b'2']),
+            extension(0x0015, &[0; 8]),
+        ];
+        let message = handshake(0x0303, &cipher_suites, &extensions);
+        let fingerprint = ClientHelloFingerprint::from_client_hello(&message).unwrap();
+        assert_eq!(fingerprint.ja4(), "t13d1516h2_8daaf6152771_e5627efa2ab1");
+    }
+
+    #[test]
+    fn test_client_hello_fingerprint() {
+        let fingerprint = ClientHelloFingerprint::from_client_hello(&client_hello()).unwrap();
+        assert_eq!(fingerprint.ja3(), JA3);
+        assert_eq!(fingerprint.ja3_hash(), hex(&Md5::digest(JA3.as_bytes())));
+        assert_eq!(fingerprint.ja4(), JA4);
+        assert_eq!(fingerprint, ClientHelloFingerprint::new(JA3, JA4));
+
+        // truncated messages are not fingerprinted
+        let message = client_hello();
+        assert!(ClientHelloFingerprint::from_client_hello(&message[..message.len() - 1]).is_none());
+        assert!(ClientHelloFingerprint::from_client_hello(&[0x02, 0, 0, 0]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_client_hello() {
+        let message = client_hello();
+        // split the message over two records, followed by data which is not to be read
+        let (first, second) = message.split_at(20);
+        let mut input = Vec::new();
+        for fragment in [first, second] {
+            input.extend_from_slice(&[0x16, 0x03, 0x01]);
+            input.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+            input.extend_from_slice(fragment);
+        }
+        let records_len = input.len();
+        input.extend_from_slice(b"next");
+
+        let mut stream = input.as_slice();
+        let hello = read_client_hello(&mut stream).await.unwrap();
+        assert_eq!(hello.message, message);
+        assert_eq!(hello.records, &input[..records_len]);
+        assert_eq!(stream, b"next");
+
+        let mut stream: &[u8] = b"GET / HTTP/1.1\r\n\r\n";
+        let err = read_client_hello(&mut stream).await.err().unwrap();
+        assert!(super::super::plaintext::is_not_tls(&err));
+    }
+
+    #[test]
+    fn test_ja_fingerprint_filter() {
+        let req = Request::builder().body(Body::empty()).unwrap();
+        let mut ctx = Context::default();
+
+        // no fingerprint: only the optional filter matches
+        assert!(!JaFingerprintFilter::new().matches(None, &ctx, &req));
+        assert!(JaFingerprintFilter::optional().matches(None, &ctx, &req));
+
+        ctx.insert(ClientHelloFingerprint::new(JA3, JA4));
+        let ja3_hash = hex(&Md5::digest(JA3.as_bytes()));
+
+        // no allowed or denied fingerprints: match
+        assert!(JaFingerprintFilter::new().matches(None, &ctx, &req));
+
+        // denied by any of its fingerprints
+        for denied in [JA3, ja3_hash.as_str(), JA4, &JA4.to_uppercase()] {
+            let filter = JaFingerprintFilter::new().deny(denied);
+            assert!(!filter.matches(None, &ctx, &req), "{denied}");
+        }
+        let filter = JaFingerprintFilter::new().deny("t13d1516h2_8daaf6152771_02713d6af862");
+        assert!(filter.matches(None, &ctx, &req));
+
+        // allowed by any of its fingerprints
+        for allowed in [JA3, ja3_hash.as_str(), JA4] {
+            let filter = JaFingerprintFilter::new()
+                .allow("t13d1516h2_8daaf6152771_02713d6af862")
+                .allow(allowed);
+            assert!(filter.matches(None, &ctx, &req), "{allowed}");
+        }
+        let filter = JaFingerprintFilter::new().allow("t13d1516h2_8daaf6152771_02713d6af862");
+        assert!(!filter.matches(None, &ctx, &req));
+
+        // deny takes precedence over allow
+        let filter = JaFingerprintFilter::new().allow(JA4).deny(JA3);
+        assert!(!filter.matches(None, &ctx, &req));
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_adds_fingerprint() {
+        let (config, _) = server_config(&["localhost"]);
+        let service = TlsAcceptorLayer::new(config)
+            .capture_fingerprint(true)
+            .layer(service_fn(|ctx: Context<()>, _stream| async move {
+                Ok::<_, std::convert::Infallible>(ctx.get::<ClientHelloFingerprint>().cloned())
+            }));
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let server =
+            tokio::spawn(async move { service.serve(Context::default(), server_io).await });
+        let client_config = Arc::new(client_config(Arc::new(
+            RecordingServerCertVerifier::default(),
+        )));
+        let _stream = tls_connect(client_config, "localhost", client_io)
+            .await
+            .unwrap();
+
+        let fingerprint = server.await.unwrap().unwrap().unwrap();
+        // rustls client: TLS 1.3, with SNI and without ALPN
+        assert!(fingerprint.ja4().starts_with("t13d"), "{:?}", fingerprint);
+        assert_eq!(&fingerprint.ja4()[8..10], "00");
+        assert!(fingerprint.ja3().starts_with("771,"), "{:?}", fingerprint);
+        assert_eq!(fingerprint.ja3_hash().len(), 32);
+    }
+}
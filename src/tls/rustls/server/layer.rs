@@ -1,12 +1,18 @@
-use super::{TlsAcceptorService, TlsClientConfigHandler};
+use super::{
+    CertResolver, PlaintextFallback, SessionResumption, SniCertResolver, TlsAcceptorService,
+    TlsClientConfigHandler,
+};
 use crate::{service::Layer, tls::rustls::dep::rustls::ServerConfig};
 use std::sync::Arc;
+use tokio::sync::watch;
 
 /// A [`Layer`] which wraps the given service with a [`TlsAcceptorService`].
 #[derive(Clone)]
 pub struct TlsAcceptorLayer<H> {
     config: Arc<ServerConfig>,
     client_config_handler: H,
+    plaintext_fallback: Option<PlaintextFallback>,
+    capture_fingerprint: bool,
 }
 
 impl<H> std::fmt::Debug for TlsAcceptorLayer<H> {
@@ -24,8 +30,23 @@ impl TlsAcceptorLayer<()> {
         Self {
             config: Arc::new(config),
             client_config_handler: (),
+            plaintext_fallback: None,
+            capture_fingerprint: false,
         }
     }
+
+    /// Creates a new [`TlsAcceptorLayer`] using a default [`ServerConfig`]
+    /// without client authentication, which selects the server certificate
+    /// based on the SNI of the client using the given [`SniCertResolver`].
+    ///
+    /// [`ServerConfig`]: https://docs.rs/rustls/latest/rustls/server/struct.ServerConfig.html
+    pub fn with_cert_resolver<R: CertResolver>(resolver: SniCertResolver<R>) -> Self {
+        Self::new(
+            ServerConfig::builder()
+                .with_no_client_auth()
+                .with_cert_resolver(Arc::new(resolver)),
+        )
+    }
 }
 
 impl<F> TlsAcceptorLayer<TlsClientConfigHandler<F>> {
@@ -39,19 +60,87 @@ impl<F> TlsAcceptorLayer<TlsClientConfigHandler<F>> {
         Self {
             config: Arc::new(config),
             client_config_handler,
+            plaintext_fallback: None,
+            capture_fingerprint: false,
+        }
+    }
+}
+
+impl TlsAcceptorLayer<TlsClientConfigHandler<watch::Receiver<Arc<ServerConfig>>>> {
+    /// Creates a new [`TlsAcceptorLayer`] which uses the latest [`ServerConfig`]
+    /// sent over the given watch channel for each new handshake.
+    ///
+    /// This allows to reload the TLS configuration (e.g. rotate certificates)
+    /// without a restart: swapping the config only affects new handshakes,
+    /// while connections that are already established continue with their old config.
+    ///
+    /// [`ServerConfig`]: https://docs.rs/rustls/latest/rustls/server/struct.ServerConfig.html
+    pub fn with_config_watch(config: watch::Receiver<Arc<ServerConfig>>) -> Self {
+        let initial_config = config.borrow().clone();
+        Self {
+            config: initial_config,
+            client_config_handler: TlsClientConfigHandler::default().server_config_provider(config),
+            plaintext_fallback: None,
+            capture_fingerprint: false,
         }
     }
 }
 
+impl<H> TlsAcceptorLayer<H> {
+    /// Configure session resumption for the [`ServerConfig`] of this layer,
+    /// allowing repeat clients to skip the full TLS handshake.
+    ///
+    /// See [`SessionResumption`] for more information.
+    ///
+    /// [`ServerConfig`]: https://docs.rs/rustls/latest/rustls/server/struct.ServerConfig.html
+    pub fn with_session_resumption(
+        mut self,
+        session_resumption: SessionResumption,
+    ) -> Result<Self, rustls::Error> {
+        session_resumption.apply(Arc::make_mut(&mut self.config))?;
+        Ok(self)
+    }
+
+    /// Handle clients which do not initiate a TLS handshake (e.g. a plain http request
+    /// sent to a TLS port) using the given [`PlaintextFallback`],
+    /// for example by redirecting them to the `https` URI of the service.
+    ///
+    /// By default no fallback is configured, in which case such connections
+    /// are closed and fail with a [`TlsAcceptorError::Accept`] error.
+    ///
+    /// [`TlsAcceptorError::Accept`]: super::TlsAcceptorError::Accept
+    pub fn plaintext_fallback(mut self, fallback: PlaintextFallback) -> Self {
+        self.plaintext_fallback = Some(fallback);
+        self
+    }
+
+    /// Compute the [`ClientHelloFingerprint`] of each client,
+    /// adding it to the [`Context`] of the inner service.
+    ///
+    /// See [`TlsAcceptorService::capture_fingerprint`] for more information.
+    ///
+    /// [`ClientHelloFingerprint`]: super::ClientHelloFingerprint
+    /// [`Context`]: crate::service::Context
+    pub fn capture_fingerprint(mut self, capture: bool) -> Self {
+        self.capture_fingerprint = capture;
+        self
+    }
+}
+
 impl<H: Clone, S> Layer<S> for TlsAcceptorLayer<H> {
     type Service = TlsAcceptorService<S, H>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        TlsAcceptorService::new(
+        let service = TlsAcceptorService::new(
             self.config.clone(),
             inner,
             self.client_config_handler.clone(),
-        )
+        );
+        match &self.plaintext_fallback {
+            Some(fallback) => service.plaintext_fallback(fallback.clone()),
+            None => service,
+        }
+        .capture_fingerprint(self.capture_fingerprint)
     }
 }
 
@@ -73,4 +162,134 @@ mod tests {
 
         assert_sync::<TlsAcceptorLayer<TlsClientConfigHandler<()>>>();
     }
+
+    mod session_resumption {
+        use super::*;
+        use crate::{
+            test_helpers::tls::{
+                client_config, handshake, server_config, RecordingServerCertVerifier,
+            },
+            tls::rustls::dep::rustls::{version::TLS12, ClientConfig},
+        };
+
+        #[tokio::test]
+        async fn resume_with_ticket() {
+            let (config, _) = server_config(&["localhost"]);
+            let layer = TlsAcceptorLayer::new(config)
+                .with_session_resumption(SessionResumption::new())
+                .unwrap();
+
+            let verifier = Arc::new(RecordingServerCertVerifier::default());
+            let client_config = Arc::new(client_config(verifier.clone()));
+
+            handshake(&layer, client_config.clone(), "localhost")
+                .await
+                .unwrap();
+            assert_eq!(verifier.count(), 1);
+
+            // resumed session: the server certificate is not sent (and verified) again
+            handshake(&layer, client_config.clone(), "localhost")
+                .await
+                .unwrap();
+            handshake(&layer, client_config, "localhost").await.unwrap();
+            assert_eq!(verifier.count(), 1);
+        }
+
+        #[tokio::test]
+        async fn resume_with_session_id() {
+            let (config, _) = server_config(&["localhost"]);
+            let layer = TlsAcceptorLayer::new(config)
+                .with_session_resumption(SessionResumption::new().tickets(false))
+                .unwrap();
+
+            let verifier = Arc::new(RecordingServerCertVerifier::default());
+            let client_config = Arc::new(
+                ClientConfig::builder_with_protocol_versions(&[&TLS12])
+                    .dangerous()
+                    .with_custom_certificate_verifier(verifier.clone())
+                    .with_no_client_auth(),
+            );
+
+            handshake(&layer, client_config.clone(), "localhost")
+                .await
+                .unwrap();
+            assert_eq!(verifier.count(), 1);
+
+            handshake(&layer, client_config, "localhost").await.unwrap();
+            assert_eq!(verifier.count(), 1);
+        }
+
+        #[tokio::test]
+        async fn resumption_disabled() {
+            let (config, _) = server_config(&["localhost"]);
+            let layer = TlsAcceptorLayer::new(config)
+                .with_session_resumption(SessionResumption::disabled())
+                .unwrap();
+
+            let verifier = Arc::new(RecordingServerCertVerifier::default());
+            let client_config = Arc::new(client_config(verifier.clone()));
+
+            handshake(&layer, client_config.clone(), "localhost")
+                .await
+                .unwrap();
+            handshake(&layer, client_config, "localhost").await.unwrap();
+            assert_eq!(verifier.count(), 2);
+        }
+    }
+
+    mod config_watch {
+        use super::*;
+        use crate::{
+            service::{service_fn, Context, Service},
+            test_helpers::tls::{
+                client_config, handshake, server_config, tls_connect, RecordingServerCertVerifier,
+            },
+            tls::rustls::dep::rustls::server::TlsStream,
+        };
+        use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+        #[tokio::test]
+        async fn swap_config() {
+            let (config_a, cert_a) = server_config(&["localhost"]);
+            let (config_b, cert_b) = server_config(&["localhost"]);
+            let (config_tx, config_rx) = watch::channel(Arc::new(config_a));
+            let layer = TlsAcceptorLayer::with_config_watch(config_rx);
+
+            let verifier = Arc::new(RecordingServerCertVerifier::default());
+            let client_config = Arc::new(client_config(verifier.clone()));
+
+            // establish a connection, which echoes until the client closes it
+            let echo_service =
+                layer.layer(service_fn(|stream: TlsStream<DuplexStream>| async move {
+                    let (mut reader, mut writer) = tokio::io::split(stream);
+                    tokio::io::copy(&mut reader, &mut writer).await?;
+                    Ok::<_, std::io::Error>(())
+                }));
+            let (client_io, server_io) = duplex(16 * 1024);
+            let server =
+                tokio::spawn(
+                    async move { echo_service.serve(Context::default(), server_io).await },
+                );
+            let mut stream = tls_connect(client_config.clone(), "localhost", client_io)
+                .await
+                .unwrap();
+            assert_eq!(verifier.last_cert(), Some(cert_a.clone()));
+
+            // swap the config: new handshakes use the new certificate
+            config_tx.send(Arc::new(config_b)).unwrap();
+            handshake(&layer, client_config, "localhost").await.unwrap();
+            assert_eq!(verifier.last_cert(), Some(cert_b));
+
+            // the existing connection continues with the old config
+            stream.write_all(b"ping").await.unwrap();
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+            assert_eq!(stream.get_ref().1.peer_certificates(), Some(&[cert_a][..]));
+
+            stream.shutdown().await.unwrap();
+            drop(stream);
+            server.await.unwrap().unwrap();
+        }
+    }
 }
@@ -19,5 +19,23 @@ pub use service::{TlsAcceptorError, TlsAcceptorService};
 mod client_config;
 pub use client_config::{IncomingClientHello, ServerConfigProvider, TlsClientConfigHandler};
 
+mod cert_resolver;
+pub use cert_resolver::{CertMap, CertResolver, SniCertResolver};
+
+mod client_cert;
+pub use client_cert::{ClientCertPresentFilter, ClientCertificates};
+
+mod connection_info;
+pub use connection_info::TlsConnectionInfo;
+
+mod session;
+pub use session::SessionResumption;
+
+mod fingerprint;
+pub use fingerprint::{ClientHelloFingerprint, JaFingerprintFilter};
+
+mod plaintext;
+pub use plaintext::PlaintextFallback;
+
 mod layer;
 pub use layer::TlsAcceptorLayer;
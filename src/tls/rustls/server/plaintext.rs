@@ -0,0 +1,149 @@
+use crate::{
+    http::Uri,
+    tls::rustls::dep::rustls::{self, InvalidMessage},
+};
+use bytes::Bytes;
+use std::io;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// The behaviour of the [`TlsAcceptorService`] when a client sends plain text
+/// (e.g. a plain http request) instead of a TLS `ClientHello`,
+/// usually because it connected to a TLS port using the wrong scheme.
+///
+/// In all cases the connection is closed and the acceptor fails with a
+/// [`TlsAcceptorError::NotTls`] error, rather than the generic handshake error
+/// returned when no fallback is configured. The fallback only defines what, if anything,
+/// is written to the client before closing the connection.
+///
+/// [`TlsAcceptorService`]: crate::tls::rustls::server::TlsAcceptorService
+/// [`TlsAcceptorError::NotTls`]: crate::tls::rustls::server::TlsAcceptorError::NotTls
+#[derive(Debug, Clone)]
+pub enum PlaintextFallback {
+    /// Close the connection without writing anything to the client.
+    Close,
+    /// Write the given (plain text) bytes to the client before closing the connection.
+    Respond(Bytes),
+}
+
+impl PlaintextFallback {
+    /// Respond with a `308 Permanent Redirect` http response to the given location,
+    /// e.g. the `https` URI of the service, before closing the connection.
+    pub fn http_redirect(location: Uri) -> Self {
+        Self::Respond(Bytes::from(format!(
+            "HTTP/1.1 308 Permanent Redirect\r\nlocation: {location}\r\n\
+             content-length: 0\r\nconnection: close\r\n\r\n"
+        )))
+    }
+
+    /// Respond with a `400 Bad Request` http response, explaining that
+    /// a TLS connection is required, before closing the connection.
+    pub fn http_bad_request() -> Self {
+        const BODY: &str = "this port only accepts TLS connections, use https instead\n";
+        Self::Respond(Bytes::from(format!(
+            "HTTP/1.1 400 Bad Request\r\ncontent-type: text/plain; charset=utf-8\r\n\
+             content-length: {}\r\nconnection: close\r\n\r\n{BODY}",
+            BODY.len()
+        )))
+    }
+
+    /// Handle the plain text client of the given stream, according to this fallback.
+    pub(super) async fn handle<IO>(&self, mut stream: IO) -> io::Result<()>
+    where
+        IO: AsyncWrite + Unpin,
+    {
+        if let Self::Respond(bytes) = self {
+            stream.write_all(bytes).await?;
+            stream.flush().await?;
+        }
+        stream.shutdown().await
+    }
+}
+
+/// Returns `true` if the given handshake error is caused by the client
+/// sending something else than a TLS record (e.g. a plain http request).
+pub(super) fn is_not_tls(err: &io::Error) -> bool {
+    matches!(
+        err.get_ref()
+            .and_then(|err| err.downcast_ref::<rustls::Error>()),
+        Some(rustls::Error::InvalidMessage(
+            InvalidMessage::InvalidContentType | InvalidMessage::UnknownProtocolVersion
+        ))
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        service::{service_fn, Context, Layer, Service},
+        test_helpers::tls::{client_config, handshake, server_config, RecordingServerCertVerifier},
+        tls::rustls::server::{TlsAcceptorError, TlsAcceptorLayer},
+    };
+    use std::{convert::Infallible, sync::Arc};
+    use tokio::io::{duplex, AsyncReadExt};
+
+    const PLAIN_HTTP_REQUEST: &[u8] = b"GET /foo HTTP/1.1\r\nhost: example.com\r\n\r\n";
+
+    async fn send_plain_http_request(
+        layer: TlsAcceptorLayer<()>,
+    ) -> (Vec<u8>, Result<(), TlsAcceptorError<Infallible>>) {
+        let service = layer.layer(service_fn(|_stream| async { Ok::<_, Infallible>(()) }));
+
+        let (mut client_io, server_io) = duplex(16 * 1024);
+        let server =
+            tokio::spawn(async move { service.serve(Context::default(), server_io).await });
+
+        client_io.write_all(PLAIN_HTTP_REQUEST).await.unwrap();
+        let mut response = Vec::new();
+        client_io.read_to_end(&mut response).await.unwrap();
+
+        (response, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_fallback_http_redirect() {
+        let (config, _) = server_config(&["example.com"]);
+        let layer = TlsAcceptorLayer::new(config).plaintext_fallback(
+            PlaintextFallback::http_redirect("https://example.com/".parse().unwrap()),
+        );
+
+        let (response, result) = send_plain_http_request(layer).await;
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 308 Permanent Redirect\r\n"));
+        assert!(response.contains("\r\nlocation: https://example.com/\r\n"));
+        assert!(matches!(result, Err(TlsAcceptorError::NotTls)));
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_fallback_close() {
+        let (config, _) = server_config(&["example.com"]);
+        let layer = TlsAcceptorLayer::new(config).plaintext_fallback(PlaintextFallback::Close);
+
+        let (response, result) = send_plain_http_request(layer).await;
+        assert!(response.is_empty());
+        assert!(matches!(result, Err(TlsAcceptorError::NotTls)));
+    }
+
+    #[tokio::test]
+    async fn test_no_plaintext_fallback() {
+        let (config, _) = server_config(&["example.com"]);
+        let layer = TlsAcceptorLayer::new(config);
+
+        let (response, result) = send_plain_http_request(layer).await;
+        // only a TLS alert record is sent back to the client
+        assert_eq!(response.first(), Some(&0x15));
+        assert!(matches!(result, Err(TlsAcceptorError::Accept(_))));
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_fallback_tls_client() {
+        let (config, _) = server_config(&["localhost"]);
+        let layer =
+            TlsAcceptorLayer::new(config).plaintext_fallback(PlaintextFallback::http_bad_request());
+
+        let client_config = Arc::new(client_config(Arc::new(
+            RecordingServerCertVerifier::default(),
+        )));
+        handshake(&layer, client_config, "localhost").await.unwrap();
+    }
+}
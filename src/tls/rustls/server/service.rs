@@ -2,18 +2,39 @@ use crate::{
     service::{Context, Service},
     stream::Stream,
     tls::rustls::dep::tokio_rustls::{server::TlsStream, TlsAcceptor},
-    tls::rustls::dep::{rustls::server::Acceptor, tokio_rustls::LazyConfigAcceptor},
+    tls::rustls::dep::{
+        rustls::server::{Accepted, Acceptor, ClientHello},
+        tokio_rustls::{Accept, LazyConfigAcceptor, StartHandshake},
+    },
 };
 use rustls::ServerConfig;
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
-use super::{client_config::IncomingClientHello, ServerConfigProvider, TlsClientConfigHandler};
+use super::{
+    client_config::IncomingClientHello,
+    fingerprint::{read_client_hello, ClientHelloRecords},
+    plaintext::{is_not_tls, PlaintextFallback},
+    ClientCertificates, ClientHelloFingerprint, ServerConfigProvider, TlsClientConfigHandler,
+    TlsConnectionInfo,
+};
 
 /// A [`Service`] which accepts TLS connections and delegates the underlying transport
 /// stream to the given service.
+///
+/// The [`ClientCertificates`] and [`TlsConnectionInfo`] of each accepted connection
+/// are added to the [`Context`].
+///
+/// Clients which do not initiate a TLS handshake (e.g. a plain http request
+/// sent to a TLS port) can be handled using a [`PlaintextFallback`],
+/// see [`TlsAcceptorService::plaintext_fallback`].
+///
+/// The [`ClientHelloFingerprint`] of each client can be added to the [`Context`] as well,
+/// see [`TlsAcceptorService::capture_fingerprint`].
 pub struct TlsAcceptorService<S, H> {
     config: Arc<ServerConfig>,
     client_config_handler: H,
+    plaintext_fallback: Option<PlaintextFallback>,
+    capture_fingerprint: bool,
     inner: S,
 }
 
@@ -23,9 +44,165 @@ impl<S, H> TlsAcceptorService<S, H> {
         Self {
             config,
             client_config_handler,
+            plaintext_fallback: None,
+            capture_fingerprint: false,
             inner,
         }
     }
+
+    /// Handle clients which do not initiate a TLS handshake using the given [`PlaintextFallback`].
+    ///
+    /// By default no fallback is configured, in which case such connections
+    /// are closed and fail with a [`TlsAcceptorError::Accept`] error.
+    pub fn plaintext_fallback(mut self, fallback: PlaintextFallback) -> Self {
+        self.plaintext_fallback = Some(fallback);
+        self
+    }
+
+    /// Compute the [`ClientHelloFingerprint`] of each client,
+    /// adding it to the [`Context`] of the inner service.
+    ///
+    /// Computing the fingerprint requires the `ClientHello` to be read and buffered
+    /// by the acceptor itself, which is why it is disabled by default.
+    pub fn capture_fingerprint(mut self, capture: bool) -> Self {
+        self.capture_fingerprint = capture;
+        self
+    }
+
+    /// Read the `ClientHello` of the given stream, applying the [`PlaintextFallback`]
+    /// (if configured) in case the client did not initiate a TLS handshake,
+    /// and adding the [`ClientHelloFingerprint`] to the [`Context`] if enabled.
+    async fn accept_client_hello<T, IO, E>(
+        &self,
+        ctx: &mut Context<T>,
+        stream: IO,
+    ) -> Result<ClientHelloStart<IO>, TlsAcceptorError<E>>
+    where
+        IO: Stream + Unpin,
+    {
+        if self.capture_fingerprint {
+            let start = self.accept_buffered_client_hello(stream).await?;
+            if let ClientHelloStart::Buffered { message, .. } = &start {
+                match ClientHelloFingerprint::from_client_hello(message) {
+                    Some(fingerprint) => {
+                        ctx.insert(fingerprint);
+                    }
+                    None => tracing::debug!("failed to compute the fingerprint of the ClientHello"),
+                }
+            }
+            return Ok(start);
+        }
+
+        let mut acceptor = LazyConfigAcceptor::new(Acceptor::default(), stream);
+        match (&mut acceptor).await {
+            Ok(start) => Ok(ClientHelloStart::Lazy(start)),
+            Err(err) => Err(self.reject_client_hello(err, acceptor.take_io()).await),
+        }
+    }
+
+    /// Reject the `ClientHello` which failed to be read with the given error,
+    /// applying the [`PlaintextFallback`] (if configured) to the stream (if recovered)
+    /// in case the client did not initiate a TLS handshake.
+    async fn reject_client_hello<IO, E>(
+        &self,
+        err: io::Error,
+        stream: Option<IO>,
+    ) -> TlsAcceptorError<E>
+    where
+        IO: Stream + Unpin,
+    {
+        match (&self.plaintext_fallback, stream) {
+            (Some(fallback), Some(stream)) if is_not_tls(&err) => {
+                tracing::debug!(error = %err, "client did not initiate a TLS handshake: apply plaintext fallback");
+                if let Err(err) = fallback.handle(stream).await {
+                    tracing::trace!(error = %err, "failed to apply plaintext fallback");
+                }
+                TlsAcceptorError::NotTls
+            }
+            _ => TlsAcceptorError::Accept(err),
+        }
+    }
+
+    /// Read and buffer the `ClientHello` of the given stream,
+    /// such that its raw bytes are available to compute its fingerprint.
+    async fn accept_buffered_client_hello<IO, E>(
+        &self,
+        mut stream: IO,
+    ) -> Result<ClientHelloStart<IO>, TlsAcceptorError<E>>
+    where
+        IO: Stream + Unpin,
+    {
+        let ClientHelloRecords { records, message } = match read_client_hello(&mut stream).await {
+            Ok(client_hello) => client_hello,
+            Err(err) => return Err(self.reject_client_hello(err, Some(stream)).await),
+        };
+
+        let mut acceptor = Acceptor::default();
+        let mut input = records.as_slice();
+        while !input.is_empty() {
+            acceptor
+                .read_tls(&mut input)
+                .map_err(TlsAcceptorError::Accept)?;
+        }
+        match acceptor.accept() {
+            Ok(Some(accepted)) => Ok(ClientHelloStart::Buffered {
+                accepted,
+                records,
+                message,
+                stream,
+            }),
+            Ok(None) => Err(TlsAcceptorError::Accept(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete ClientHello",
+            ))),
+            Err(err) => Err(TlsAcceptorError::Accept(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                err,
+            ))),
+        }
+    }
+}
+
+/// A TLS handshake of which the `ClientHello` is read,
+/// either by rustls or buffered by the [`TlsAcceptorService`] itself.
+enum ClientHelloStart<IO> {
+    Lazy(StartHandshake<IO>),
+    Buffered {
+        accepted: Accepted,
+        records: Vec<u8>,
+        message: Vec<u8>,
+        stream: IO,
+    },
+}
+
+impl<IO> ClientHelloStart<IO>
+where
+    IO: Stream + Unpin,
+{
+    fn client_hello(&self) -> ClientHello<'_> {
+        match self {
+            Self::Lazy(start) => start.client_hello(),
+            Self::Buffered { accepted, .. } => accepted.client_hello(),
+        }
+    }
+
+    fn into_stream(self, config: Arc<ServerConfig>) -> Accept<IO> {
+        match self {
+            Self::Lazy(start) => start.into_stream(config),
+            Self::Buffered {
+                records, stream, ..
+            } => TlsAcceptor::from(config).accept_with(stream, move |conn| {
+                // replay the buffered ClientHello, already read from the stream,
+                // any error is reported again while completing the handshake
+                let mut input = records.as_slice();
+                while !input.is_empty() {
+                    if conn.read_tls(&mut input).is_err() || conn.process_new_packets().is_err() {
+                        break;
+                    }
+                }
+            }),
+        }
+    }
 }
 
 impl<S, H> std::fmt::Debug for TlsAcceptorService<S, H> {
@@ -43,6 +220,8 @@ where
         Self {
             config: self.config.clone(),
             client_config_handler: self.client_config_handler.clone(),
+            plaintext_fallback: self.plaintext_fallback.clone(),
+            capture_fingerprint: self.capture_fingerprint,
             inner: self.inner.clone(),
         }
     }
@@ -57,13 +236,24 @@ where
     type Response = S::Response;
     type Error = TlsAcceptorError<S::Error>;
 
-    async fn serve(&self, ctx: Context<T>, stream: IO) -> Result<Self::Response, Self::Error> {
-        let acceptor = TlsAcceptor::from(self.config.clone());
-
-        let stream = acceptor
-            .accept(stream)
-            .await
-            .map_err(TlsAcceptorError::Accept)?;
+    async fn serve(&self, mut ctx: Context<T>, stream: IO) -> Result<Self::Response, Self::Error> {
+        let stream = if self.plaintext_fallback.is_some() || self.capture_fingerprint {
+            // the client hello has to be read first, in order to be able
+            // to recover the stream in case the client does not speak TLS,
+            // or to compute its fingerprint
+            self.accept_client_hello(&mut ctx, stream)
+                .await?
+                .into_stream(self.config.clone())
+                .await
+                .map_err(TlsAcceptorError::Accept)?
+        } else {
+            TlsAcceptor::from(self.config.clone())
+                .accept(stream)
+                .await
+                .map_err(TlsAcceptorError::Accept)?
+        };
+        ctx.insert(ClientCertificates::from(stream.get_ref().1));
+        ctx.insert(TlsConnectionInfo::from(stream.get_ref().1));
 
         self.inner
             .serve(ctx, stream)
@@ -82,9 +272,7 @@ where
     type Error = TlsAcceptorError<S::Error>;
 
     async fn serve(&self, mut ctx: Context<T>, stream: IO) -> Result<Self::Response, Self::Error> {
-        let acceptor = LazyConfigAcceptor::new(Acceptor::default(), stream);
-
-        let start = acceptor.await.map_err(TlsAcceptorError::Accept)?;
+        let start = self.accept_client_hello(&mut ctx, stream).await?;
 
         if self.client_config_handler.store_client_hello {
             let accepted_client_hello = IncomingClientHello::from(start.client_hello());
@@ -95,6 +283,8 @@ where
             .into_stream(self.config.clone())
             .await
             .map_err(TlsAcceptorError::Accept)?;
+        ctx.insert(ClientCertificates::from(stream.get_ref().1));
+        ctx.insert(TlsConnectionInfo::from(stream.get_ref().1));
 
         self.inner
             .serve(ctx, stream)
@@ -114,9 +304,7 @@ where
     type Error = TlsAcceptorError<S::Error>;
 
     async fn serve(&self, mut ctx: Context<T>, stream: IO) -> Result<Self::Response, Self::Error> {
-        let acceptor = LazyConfigAcceptor::new(Acceptor::default(), stream);
-
-        let start = acceptor.await.map_err(TlsAcceptorError::Accept)?;
+        let start = self.accept_client_hello(&mut ctx, stream).await?;
 
         let accepted_client_hello = IncomingClientHello::from(start.client_hello());
 
@@ -136,6 +324,8 @@ where
             .into_stream(config)
             .await
             .map_err(TlsAcceptorError::Accept)?;
+        ctx.insert(ClientCertificates::from(stream.get_ref().1));
+        ctx.insert(TlsConnectionInfo::from(stream.get_ref().1));
 
         self.inner
             .serve(ctx, stream)
@@ -146,9 +336,13 @@ where
 
 /// Errors that can happen when using [`TlsAcceptorService`].
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum TlsAcceptorError<E> {
     /// An error occurred while accepting a TLS connection.
     Accept(std::io::Error),
+    /// The client did not initiate a TLS handshake (e.g. it sent a plain http request),
+    /// and was handled using the configured [`PlaintextFallback`].
+    NotTls,
     /// An error occurred while serving the underlying transport stream
     /// using the inner service.
     Service(E),
@@ -161,6 +355,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TlsAcceptorError::Accept(e) => write!(f, "accept error: {}", e),
+            TlsAcceptorError::NotTls => write!(f, "client did not initiate a TLS handshake"),
             TlsAcceptorError::Service(e) => write!(f, "service error: {}", e),
         }
     }
@@ -173,7 +368,7 @@ where
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             TlsAcceptorError::Accept(e) => Some(e),
-            TlsAcceptorError::Service(_) => None,
+            TlsAcceptorError::NotTls | TlsAcceptorError::Service(_) => None,
         }
     }
 }
@@ -0,0 +1,108 @@
+use crate::tls::rustls::dep::rustls::{
+    crypto::ring::Ticketer,
+    server::{NoServerSessionStorage, ServerSessionMemoryCache},
+    ServerConfig,
+};
+use std::sync::Arc;
+
+const DEFAULT_SESSION_CACHE_SIZE: usize = 256;
+
+/// Configuration for TLS session resumption,
+/// allowing repeat clients to skip the full TLS handshake.
+///
+/// Sessions can be resumed using:
+///
+/// - session tickets: the session state is encrypted by the server and stored by the client,
+///   using a [`Ticketer`] which rotates its keys (every 6 hours);
+/// - session IDs: the session state is stored in a server-side in-memory cache,
+///   bounded to the configured size.
+///
+/// Both mechanisms are enabled by default.
+///
+/// [`Ticketer`]: crate::tls::rustls::dep::rustls::crypto::ring::Ticketer
+#[derive(Debug, Clone)]
+pub struct SessionResumption {
+    tickets: bool,
+    session_cache_size: usize,
+}
+
+impl Default for SessionResumption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionResumption {
+    /// Create a new [`SessionResumption`] configuration,
+    /// with both session tickets and session IDs enabled.
+    pub fn new() -> Self {
+        Self {
+            tickets: true,
+            session_cache_size: DEFAULT_SESSION_CACHE_SIZE,
+        }
+    }
+
+    /// Create a new [`SessionResumption`] configuration,
+    /// which disables session resumption altogether.
+    pub fn disabled() -> Self {
+        Self {
+            tickets: false,
+            session_cache_size: 0,
+        }
+    }
+
+    /// Enable or disable session resumption using session tickets.
+    pub fn tickets(mut self, enabled: bool) -> Self {
+        self.tickets = enabled;
+        self
+    }
+
+    /// Set the maximum amount of sessions stored in the server-side cache,
+    /// used for session resumption using session IDs.
+    ///
+    /// A size of `0` disables session resumption using session IDs.
+    pub fn session_cache_size(mut self, size: usize) -> Self {
+        self.session_cache_size = size;
+        self
+    }
+
+    /// Apply this session resumption configuration to the given [`ServerConfig`].
+    ///
+    /// An error is returned in case the (rotating) ticketer could not be created.
+    pub fn apply(&self, config: &mut ServerConfig) -> Result<(), rustls::Error> {
+        config.ticketer = if self.tickets {
+            Ticketer::new()?
+        } else {
+            Arc::new(NeverProducesTickets)
+        };
+        config.session_storage = if self.session_cache_size > 0 {
+            ServerSessionMemoryCache::new(self.session_cache_size)
+        } else {
+            Arc::new(NoServerSessionStorage {})
+        };
+        Ok(())
+    }
+}
+
+/// rustls does not expose its own `NeverProducesTickets`,
+/// which is the default ticketer of a [`ServerConfig`].
+#[derive(Debug)]
+struct NeverProducesTickets;
+
+impl rustls::server::ProducesTickets for NeverProducesTickets {
+    fn enabled(&self) -> bool {
+        false
+    }
+
+    fn lifetime(&self) -> u32 {
+        0
+    }
+
+    fn encrypt(&self, _plain: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn decrypt(&self, _cipher: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+}
@@ -0,0 +1,3 @@
+//! UDP module for Rama.
+
+pub mod server;
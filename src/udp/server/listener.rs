@@ -0,0 +1,431 @@
+use crate::graceful::ShutdownGuard;
+use crate::rt::Executor;
+use crate::service::handler::{Factory, FromContextRequest};
+use crate::service::Context;
+use crate::service::Service;
+use crate::stream::SocketInfo;
+use bytes::Bytes;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::{io, net::SocketAddr};
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+/// The maximum size of a datagram read by a [`UdpListener`] by default,
+/// which is the maximum payload of a UDP datagram over IPv4.
+const DEFAULT_MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// Builder for `UdpListener`.
+#[derive(Debug)]
+pub struct UdpListenerBuilder<S> {
+    ttl: Option<u32>,
+    max_datagram_size: usize,
+    state: Arc<S>,
+}
+
+impl UdpListenerBuilder<()> {
+    /// Create a new `UdpListenerBuilder` without a state.
+    pub fn new() -> Self {
+        Self {
+            ttl: None,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            state: Arc::new(()),
+        }
+    }
+}
+
+impl Default for UdpListenerBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Clone for UdpListenerBuilder<S> {
+    fn clone(&self) -> Self {
+        Self {
+            ttl: self.ttl,
+            max_datagram_size: self.max_datagram_size,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S> UdpListenerBuilder<S> {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet sent
+    /// from this socket.
+    pub fn ttl(&mut self, ttl: u32) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the maximum size of the datagrams read by the listener,
+    /// any excess bytes of a larger datagram are discarded.
+    ///
+    /// Defaults to 65507 bytes, the maximum payload of a UDP datagram over IPv4.
+    pub fn max_datagram_size(&mut self, size: usize) -> &mut Self {
+        self.max_datagram_size = size;
+        self
+    }
+}
+
+impl<S> UdpListenerBuilder<S>
+where
+    S: Send + Sync + 'static,
+{
+    /// Create a new `UdpListenerBuilder` with the given state.
+    pub fn with_state(state: S) -> Self {
+        Self {
+            ttl: None,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            state: Arc::new(state),
+        }
+    }
+
+    /// Creates a new UdpListener, which will be bound to the specified address.
+    ///
+    /// The returned listener is ready for receiving datagrams.
+    ///
+    /// Binding with a port number of 0 will request that the OS assigns a port
+    /// to this listener. The port allocated can be queried via the `local_addr`
+    /// method.
+    pub async fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<UdpListener<S>> {
+        let inner = UdpSocket::bind(addr).await?;
+
+        if let Some(ttl) = self.ttl {
+            inner.set_ttl(ttl)?;
+        }
+
+        Ok(UdpListener {
+            inner: Arc::new(inner),
+            max_datagram_size: self.max_datagram_size,
+            state: self.state.clone(),
+        })
+    }
+}
+
+/// A datagram received by a [`UdpListener`],
+/// which can be replied to using [`Datagram::reply`].
+#[derive(Debug, Clone)]
+pub struct Datagram {
+    data: Bytes,
+    peer_addr: SocketAddr,
+    socket: Arc<UdpSocket>,
+}
+
+impl Datagram {
+    /// The payload of the datagram.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Consume the datagram, returning its payload.
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+
+    /// The address of the peer which sent the datagram.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Send the given data back to the peer which sent the datagram,
+    /// from the socket of the listener which received it.
+    pub async fn reply(&self, data: &[u8]) -> io::Result<usize> {
+        self.socket.send_to(data, self.peer_addr).await
+    }
+}
+
+/// A UDP socket server, serving incoming datagrams once served
+/// using one of the `serve` methods such as [`UdpListener::serve`].
+#[derive(Debug)]
+pub struct UdpListener<S> {
+    inner: Arc<UdpSocket>,
+    max_datagram_size: usize,
+    state: Arc<S>,
+}
+
+impl UdpListener<()> {
+    /// Create a new `UdpListenerBuilder` without a state,
+    /// which can be used to configure a `UdpListener`.
+    pub fn build() -> UdpListenerBuilder<()> {
+        UdpListenerBuilder::new()
+    }
+
+    /// Create a new `UdpListenerBuilder` with the given state,
+    /// which can be used to configure a `UdpListener`.
+    pub fn build_with_state<S>(state: S) -> UdpListenerBuilder<S>
+    where
+        S: Send + Sync + 'static,
+    {
+        UdpListenerBuilder::with_state(state)
+    }
+
+    /// Creates a new UdpListener, which will be bound to the specified address.
+    ///
+    /// The returned listener is ready for receiving datagrams.
+    ///
+    /// Binding with a port number of 0 will request that the OS assigns a port
+    /// to this listener. The port allocated can be queried via the `local_addr`
+    /// method.
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        UdpListenerBuilder::default().bind(addr).await
+    }
+}
+
+impl<S> UdpListener<S> {
+    /// Returns the local address that this listener is bound to.
+    ///
+    /// This can be useful, for example, when binding to port 0 to figure out
+    /// which port was actually bound.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    ///
+    /// For more information about this option, see [`set_ttl`].
+    ///
+    /// [`set_ttl`]: UdpListenerBuilder::ttl
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.inner.ttl()
+    }
+
+    /// Gets a reference to the listener's state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Receive the next datagram.
+    async fn recv(&self, buf: &mut [u8]) -> io::Result<Datagram> {
+        let (n, peer_addr) = self.inner.recv_from(buf).await?;
+        Ok(Datagram {
+            data: Bytes::copy_from_slice(&buf[..n]),
+            peer_addr,
+            socket: self.inner.clone(),
+        })
+    }
+}
+
+impl<State> UdpListener<State>
+where
+    State: Send + Sync + 'static,
+{
+    /// Serve datagrams from this listener with the given service.
+    ///
+    /// Each incoming datagram is served in its own task.
+    pub async fn serve<S>(self, service: S)
+    where
+        S: Service<State, Datagram>,
+    {
+        let ctx = Context::new(self.state.clone(), Executor::new());
+        let local_addr = self.inner.local_addr().ok();
+        let service = Arc::new(service);
+        let mut buf = vec![0u8; self.max_datagram_size];
+
+        loop {
+            let datagram = match self.recv(&mut buf).await {
+                Ok(datagram) => datagram,
+                Err(err) => {
+                    handle_recv_err(err).await;
+                    continue;
+                }
+            };
+
+            let service = service.clone();
+            let mut ctx = ctx.clone();
+
+            tokio::spawn(async move {
+                ctx.insert(SocketInfo::new(local_addr, datagram.peer_addr));
+                let _ = service.serve(ctx, datagram).await;
+            });
+        }
+    }
+
+    /// Serve datagrams from this listener with the given service function.
+    ///
+    /// See [`Self::serve`] for more details.
+    pub async fn serve_fn<F, T, R, O, E>(self, f: F)
+    where
+        F: Factory<T, R, O, E>,
+        R: Future<Output = Result<O, E>> + Send + Sync + 'static,
+        O: Send + Sync + 'static,
+        E: Send + Sync + 'static,
+        T: FromContextRequest<State, Datagram>,
+    {
+        let service = crate::service::service_fn(f);
+        self.serve(service).await
+    }
+
+    /// Serve gracefully datagrams from this listener with the given service.
+    ///
+    /// This method does the same as [`Self::serve`] but it
+    /// will respect the given [`crate::graceful::ShutdownGuard`], and also pass
+    /// it to the service. Once the shutdown is signalled the listener stops
+    /// reading new datagrams, which are dropped from then on, while the datagrams
+    /// already being served are tracked by the guard, such that the shutdown awaits them.
+    pub async fn serve_graceful<S>(self, guard: ShutdownGuard, service: S)
+    where
+        S: Service<State, Datagram>,
+    {
+        let ctx: Context<State> =
+            Context::new(self.state.clone(), Executor::graceful(guard.clone()));
+        let local_addr = self.inner.local_addr().ok();
+        let service = Arc::new(service);
+        let mut buf = vec![0u8; self.max_datagram_size];
+        let mut cancelled_fut = pin!(guard.cancelled());
+
+        loop {
+            tokio::select! {
+                _ = cancelled_fut.as_mut() => {
+                    tracing::trace!("signal received: initiate graceful shutdown");
+                    break;
+                }
+                result = self.recv(&mut buf) => {
+                    match result {
+                        Ok(datagram) => {
+                            let service = service.clone();
+                            let mut ctx = ctx.clone();
+
+                            guard.spawn_task(async move {
+                                ctx.insert(SocketInfo::new(local_addr, datagram.peer_addr));
+                                let _ = service.serve(ctx, datagram).await;
+                            });
+                        }
+                        Err(err) => {
+                            handle_recv_err(err).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Serve gracefully datagrams from this listener with the given service function.
+    ///
+    /// See [`Self::serve_graceful`] for more details.
+    pub async fn serve_fn_graceful<F, T, R, O, E>(self, guard: ShutdownGuard, service: F)
+    where
+        F: Factory<T, R, O, E>,
+        R: Future<Output = Result<O, E>> + Send + Sync + 'static,
+        O: Send + Sync + 'static,
+        E: Send + Sync + 'static,
+        T: FromContextRequest<State, Datagram>,
+    {
+        let service = crate::service::service_fn(service);
+        self.serve_graceful(guard, service).await
+    }
+}
+
+async fn handle_recv_err(err: io::Error) {
+    if crate::tcp::utils::is_connection_error(&err) {
+        // errors such as `ConnectionReset` (caused by an ICMP port unreachable message
+        // for a previously sent datagram on some platforms) do not affect the socket itself
+        tracing::trace!(
+            error = &err as &dyn std::error::Error,
+            "UDP recv error: connection error"
+        );
+    } else {
+        // other errors (e.g. `ENOBUFS` or `ENOMEM`) are likely to persist for a while,
+        // back off such that the listener does not spin on them, as is done for TCP accept errors
+        tracing::error!(error = &err as &dyn std::error::Error, "UDP recv error");
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, Notify};
+
+    async fn client() -> UdpSocket {
+        UdpSocket::bind("127.0.0.1:0").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_udp_listener_echo() {
+        let listener = UdpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            listener.serve_fn(|ctx: Context<()>, datagram: Datagram| async move {
+                assert_eq!(
+                    *ctx.get::<SocketInfo>().unwrap().peer_addr(),
+                    datagram.peer_addr()
+                );
+                datagram.reply(datagram.data()).await?;
+                Ok::<_, io::Error>(())
+            }),
+        );
+
+        let client = client().await;
+        let mut buf = [0u8; 16];
+        for msg in [&b"hello"[..], b"world"] {
+            client.send_to(msg, addr).await.unwrap();
+            let (n, from) =
+                tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+                    .await
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(from, addr);
+            assert_eq!(&buf[..n], msg);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_udp_listener_graceful_shutdown() {
+        let listener = UdpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        let release = Arc::new(Notify::new());
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        shutdown.spawn_task_fn({
+            let release = release.clone();
+            |guard| async move {
+                listener
+                    .serve_fn_graceful(guard, move |datagram: Datagram| {
+                        let started_tx = started_tx.clone();
+                        let done_tx = done_tx.clone();
+                        let release = release.clone();
+                        async move {
+                            started_tx.send(datagram.data().clone()).unwrap();
+                            // keep the datagram in flight until released
+                            release.notified().await;
+                            done_tx.send(datagram.into_data()).unwrap();
+                            Ok::<_, std::convert::Infallible>(())
+                        }
+                    })
+                    .await;
+            }
+        });
+
+        let client = client().await;
+        client.send_to(b"in-flight", addr).await.unwrap();
+        assert_eq!(started_rx.recv().await.unwrap(), "in-flight");
+
+        // shutdown while the first datagram is still being processed
+        shutdown_tx.send(()).unwrap();
+        let shutdown = tokio::spawn(shutdown.shutdown_with_limit(Duration::from_secs(5)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // datagrams received after the signal are no longer served
+        client.send_to(b"dropped", addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(started_rx.try_recv().is_err());
+        assert!(!shutdown.is_finished());
+
+        // the in-flight datagram is finished before the shutdown completes
+        release.notify_one();
+        shutdown.await.unwrap().unwrap();
+        assert_eq!(done_rx.recv().await.unwrap(), "in-flight");
+        assert!(done_rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,25 @@
+//! UDP server module for Rama.
+//!
+//! The UDP server is used to create a [`UdpListener`] and serve incoming datagrams.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rama::udp::server::{Datagram, UdpListener};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     UdpListener::bind("127.0.0.1:9000")
+//!         .await
+//!         .expect("bind UDP Listener")
+//!         .serve_fn(|datagram: Datagram| async move {
+//!             // echo the datagram back to its sender
+//!             datagram.reply(datagram.data()).await?;
+//!             Ok::<_, std::io::Error>(())
+//!         })
+//!         .await;
+//! }
+//! ```
+
+mod listener;
+pub use listener::{Datagram, UdpListener, UdpListenerBuilder};
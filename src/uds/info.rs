@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use tokio::net::unix::UCred;
+
+#[derive(Debug, Clone)]
+/// Connected Unix domain socket information,
+/// the counterpart of [`SocketInfo`] for Unix domain sockets.
+///
+/// [`SocketInfo`]: crate::stream::SocketInfo
+pub struct UnixSocketInfo {
+    local_path: Option<PathBuf>,
+    peer_path: Option<PathBuf>,
+    peer_cred: Option<UCred>,
+}
+
+impl UnixSocketInfo {
+    /// Create a new `UnixSocketInfo`.
+    pub(crate) fn new(
+        local_path: Option<PathBuf>,
+        peer_path: Option<PathBuf>,
+        peer_cred: Option<UCred>,
+    ) -> Self {
+        Self {
+            local_path,
+            peer_path,
+            peer_cred,
+        }
+    }
+
+    /// Get the path of the local socket, if it is bound to a path.
+    pub fn local_path(&self) -> Option<&Path> {
+        self.local_path.as_deref()
+    }
+
+    /// Get the path of the peer socket, if it is bound to a path.
+    ///
+    /// Clients usually connect using an unnamed socket,
+    /// in which case this is `None`.
+    pub fn peer_path(&self) -> Option<&Path> {
+        self.peer_path.as_deref()
+    }
+
+    /// Get the credentials (uid, gid and where available pid)
+    /// of the process which connected the peer socket,
+    /// or `None` in case they couldn't be retrieved.
+    pub fn peer_cred(&self) -> Option<&UCred> {
+        self.peer_cred.as_ref()
+    }
+}
@@ -0,0 +1,10 @@
+//! [`service::Matcher`]s implementations to match on Unix domain socket connections.
+//!
+//! See [`service::matcher` module] for more information.
+//!
+//! [`service::Matcher`]: crate::service::Matcher
+//! [`service::matcher` module]: crate::service::matcher
+
+mod peer_cred;
+#[doc(inline)]
+pub use peer_cred::PeerCredFilter;
@@ -0,0 +1,148 @@
+use crate::{
+    service::{context::Extensions, Context, Matcher},
+    uds::UnixSocketInfo,
+};
+
+#[derive(Debug, Clone)]
+/// Filter based on the credentials of the process which connected to a Unix domain socket,
+/// as retrieved using `SO_PEERCRED` (or the platform equivalent), which can be used
+/// to authorize local clients.
+///
+/// The credentials are read from the [`UnixSocketInfo`] found in the [`Context`],
+/// as inserted by the [`UnixListener`] for each connection, and as such this filter
+/// can be used for both the connections and the (http) requests served over them.
+/// A filter matches only if all of its configured credentials match,
+/// connections without (known) credentials never match.
+///
+/// [`UnixListener`]: crate::uds::server::UnixListener
+pub struct PeerCredFilter {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    pid: Option<i32>,
+}
+
+impl PeerCredFilter {
+    /// Create a new filter matching the peer processes running as the given user id.
+    pub fn uid(uid: u32) -> Self {
+        Self {
+            uid: Some(uid),
+            gid: None,
+            pid: None,
+        }
+    }
+
+    /// Create a new filter matching the peer processes running as the given group id.
+    pub fn gid(gid: u32) -> Self {
+        Self {
+            uid: None,
+            gid: Some(gid),
+            pid: None,
+        }
+    }
+
+    /// Create a new filter matching the peer process with the given process id.
+    ///
+    /// The process id is not available on all platforms,
+    /// in which case this filter never matches.
+    pub fn pid(pid: i32) -> Self {
+        Self {
+            uid: None,
+            gid: None,
+            pid: Some(pid),
+        }
+    }
+
+    /// Also require the peer process to run as the given user id.
+    pub fn and_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Also require the peer process to run as the given group id.
+    pub fn and_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Also require the peer process to have the given process id.
+    pub fn and_pid(mut self, pid: i32) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Returns `true` if the credentials of the given [`UnixSocketInfo`] match this filter.
+    pub fn matches_info(&self, info: &UnixSocketInfo) -> bool {
+        let cred = match info.peer_cred() {
+            Some(cred) => cred,
+            None => return false,
+        };
+        self.uid.map(|uid| cred.uid() == uid).unwrap_or(true)
+            && self.gid.map(|gid| cred.gid() == gid).unwrap_or(true)
+            && self.pid.map(|pid| cred.pid() == Some(pid)).unwrap_or(true)
+    }
+}
+
+impl<State, Request> Matcher<State, Request> for PeerCredFilter {
+    fn matches(&self, _ext: Option<&mut Extensions>, ctx: &Context<State>, _req: &Request) -> bool {
+        ctx.get::<UnixSocketInfo>()
+            .map(|info| self.matches_info(info))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uds::server::UnixListener;
+    use tokio::net::UnixStream;
+
+    /// Connect to a [`UnixListener`] from this process,
+    /// returning the [`UnixSocketInfo`] captured for the connection.
+    async fn connect() -> UnixSocketInfo {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peer_cred.sock");
+        let listener = UnixListener::bind(&path).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let server = tokio::spawn(listener.serve_fn(move |ctx: Context<()>, _: UnixStream| {
+            let _ = tx.send(ctx.get::<UnixSocketInfo>().cloned());
+            async move { Ok::<_, std::convert::Infallible>(()) }
+        }));
+
+        let _client = UnixStream::connect(&path).await.unwrap();
+        let info = rx.recv().await.unwrap().expect("unix socket info");
+        server.abort();
+        info
+    }
+
+    #[tokio::test]
+    async fn test_peer_cred_filter() {
+        let info = connect().await;
+        let cred = *info.peer_cred().expect("peer credentials");
+        let (uid, gid) = (cred.uid(), cred.gid());
+        if let Some(pid) = cred.pid() {
+            assert_eq!(pid as u32, std::process::id());
+        }
+
+        let mut ctx = Context::default();
+        assert!(!PeerCredFilter::uid(uid).matches(None, &ctx, &()));
+        ctx.insert(info.clone());
+
+        assert!(PeerCredFilter::uid(uid).matches(None, &ctx, &()));
+        assert!(PeerCredFilter::gid(gid).matches(None, &ctx, &()));
+        assert!(PeerCredFilter::uid(uid)
+            .and_gid(gid)
+            .matches(None, &ctx, &()));
+        assert!(!PeerCredFilter::uid(uid.wrapping_add(1)).matches(None, &ctx, &()));
+        assert!(!PeerCredFilter::uid(uid)
+            .and_gid(gid.wrapping_add(1))
+            .matches(None, &ctx, &()));
+
+        if let Some(pid) = cred.pid() {
+            assert!(PeerCredFilter::pid(pid)
+                .and_uid(uid)
+                .matches(None, &ctx, &()));
+            assert!(!PeerCredFilter::pid(pid + 1).matches(None, &ctx, &()));
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Unix domain socket (UDS) module for Rama.
+//!
+//! Only available on unix platforms (e.g. Linux and macOS).
+
+pub mod matcher;
+pub mod server;
+
+mod info;
+#[doc(inline)]
+pub use info::UnixSocketInfo;
@@ -0,0 +1,377 @@
+use crate::graceful::ShutdownGuard;
+use crate::rt::Executor;
+use crate::service::handler::{Factory, FromContextRequest};
+use crate::service::Context;
+use crate::service::Service;
+use crate::uds::UnixSocketInfo;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::net::unix::SocketAddr;
+use tokio::net::{UnixListener as TokioUnixListener, UnixStream};
+
+/// Builder for `UnixListener`.
+#[derive(Debug)]
+pub struct UnixListenerBuilder<S> {
+    cleanup: bool,
+    state: Arc<S>,
+}
+
+impl UnixListenerBuilder<()> {
+    /// Create a new `UnixListenerBuilder` without a state.
+    pub fn new() -> Self {
+        Self {
+            cleanup: true,
+            state: Arc::new(()),
+        }
+    }
+}
+
+impl Default for UnixListenerBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Clone for UnixListenerBuilder<S> {
+    fn clone(&self) -> Self {
+        Self {
+            cleanup: self.cleanup,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S> UnixListenerBuilder<S> {
+    /// Sets whether or not the socket file is removed once the listener is dropped,
+    /// which is the case when it is done serving (e.g. after a graceful shutdown).
+    ///
+    /// Enabled by default.
+    pub fn cleanup(&mut self, cleanup: bool) -> &mut Self {
+        self.cleanup = cleanup;
+        self
+    }
+}
+
+impl<S> UnixListenerBuilder<S>
+where
+    S: Send + Sync + 'static,
+{
+    /// Create a new `UnixListenerBuilder` with the given state.
+    pub fn with_state(state: S) -> Self {
+        Self {
+            cleanup: true,
+            state: Arc::new(state),
+        }
+    }
+
+    /// Creates a new UnixListener, which will be bound to the specified path.
+    ///
+    /// The returned listener is ready for accepting connections.
+    ///
+    /// Binding fails if a file already exists at the given path,
+    /// for example the socket file of a previous listener that was not cleaned up.
+    pub async fn bind<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixListener<S>> {
+        let path = path.as_ref();
+        let inner = TokioUnixListener::bind(path)?;
+
+        Ok(UnixListener {
+            inner,
+            state: self.state.clone(),
+            cleanup: self.cleanup.then(|| path.to_path_buf()),
+        })
+    }
+}
+
+/// A Unix domain socket server, listening for incoming connections once served
+/// using one of the `serve` methods such as [`UnixListener::serve`].
+///
+/// The [`UnixSocketInfo`] of each connection is inserted into its [`Context`].
+///
+/// [`Context`]: crate::service::Context
+#[derive(Debug)]
+pub struct UnixListener<S> {
+    inner: TokioUnixListener,
+    state: Arc<S>,
+    cleanup: Option<PathBuf>,
+}
+
+impl UnixListener<()> {
+    /// Create a new `UnixListenerBuilder` without a state,
+    /// which can be used to configure a `UnixListener`.
+    pub fn build() -> UnixListenerBuilder<()> {
+        UnixListenerBuilder::new()
+    }
+
+    /// Create a new `UnixListenerBuilder` with the given state,
+    /// which can be used to configure a `UnixListener`.
+    pub fn build_with_state<S>(state: S) -> UnixListenerBuilder<S>
+    where
+        S: Send + Sync + 'static,
+    {
+        UnixListenerBuilder::with_state(state)
+    }
+
+    /// Creates a new UnixListener, which will be bound to the specified path.
+    ///
+    /// The returned listener is ready for accepting connections.
+    ///
+    /// See [`UnixListenerBuilder::bind`] for more details.
+    pub async fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        UnixListenerBuilder::default().bind(path).await
+    }
+}
+
+impl<S> UnixListener<S> {
+    /// Returns the local address that this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Gets a reference to the listener's state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+}
+
+impl<State> UnixListener<State>
+where
+    State: Send + Sync + 'static,
+{
+    /// Serve connections from this listener with the given service.
+    ///
+    /// This method will block the current listener for each incoming connection,
+    /// the underlying service can choose to spawn a task to handle the accepted stream.
+    pub async fn serve<S>(self, service: S)
+    where
+        S: Service<State, UnixStream>,
+    {
+        let ctx = Context::new(self.state.clone(), Executor::new());
+        let service = Arc::new(service);
+
+        loop {
+            let (socket, peer_addr) = match self.inner.accept().await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    handle_accept_err(err).await;
+                    continue;
+                }
+            };
+
+            let service = service.clone();
+            let mut ctx = ctx.clone();
+
+            tokio::spawn(async move {
+                ctx.insert(socket_info(&socket, &peer_addr));
+
+                let _ = service.serve(ctx, socket).await;
+            });
+        }
+    }
+
+    /// Serve connections from this listener with the given service function.
+    ///
+    /// See [`Self::serve`] for more details.
+    pub async fn serve_fn<F, T, R, O, E>(self, f: F)
+    where
+        F: Factory<T, R, O, E>,
+        R: Future<Output = Result<O, E>> + Send + Sync + 'static,
+        O: Send + Sync + 'static,
+        E: Send + Sync + 'static,
+        T: FromContextRequest<State, UnixStream>,
+    {
+        let service = crate::service::service_fn(f);
+        self.serve(service).await
+    }
+
+    /// Serve gracefully connections from this listener with the given service.
+    ///
+    /// This method does the same as [`Self::serve`] but it
+    /// will respect the given [`crate::graceful::ShutdownGuard`], and also pass
+    /// it to the service. Once the shutdown is initiated the listener stops accepting
+    /// connections and is dropped, removing its socket file (unless disabled
+    /// using [`UnixListenerBuilder::cleanup`]).
+    pub async fn serve_graceful<S>(self, guard: ShutdownGuard, service: S)
+    where
+        S: Service<State, UnixStream>,
+    {
+        let ctx: Context<State> =
+            Context::new(self.state.clone(), Executor::graceful(guard.clone()));
+        let service = Arc::new(service);
+        let mut cancelled_fut = pin!(guard.cancelled());
+
+        loop {
+            tokio::select! {
+                _ = cancelled_fut.as_mut() => {
+                    tracing::trace!("signal received: initiate graceful shutdown");
+                    break;
+                }
+                result = self.inner.accept() => {
+                    match result {
+                        Ok((socket, peer_addr)) => {
+                            let service = service.clone();
+                            let mut ctx = ctx.clone();
+
+                            guard.spawn_task(async move {
+                                ctx.insert(socket_info(&socket, &peer_addr));
+
+                                let _ = service.serve(ctx, socket).await;
+                            });
+                        }
+                        Err(err) => {
+                            handle_accept_err(err).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Serve gracefully connections from this listener with the given service function.
+    ///
+    /// See [`Self::serve_graceful`] for more details.
+    pub async fn serve_fn_graceful<F, T, R, O, E>(self, guard: ShutdownGuard, service: F)
+    where
+        F: Factory<T, R, O, E>,
+        R: Future<Output = Result<O, E>> + Send + Sync + 'static,
+        O: Send + Sync + 'static,
+        E: Send + Sync + 'static,
+        T: FromContextRequest<State, UnixStream>,
+    {
+        let service = crate::service::service_fn(service);
+        self.serve_graceful(guard, service).await
+    }
+}
+
+impl<S> Drop for UnixListener<S> {
+    fn drop(&mut self) {
+        if let Some(path) = self.cleanup.take() {
+            match std::fs::remove_file(&path) {
+                Ok(()) => tracing::trace!(path = %path.display(), "UDS socket file removed"),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => tracing::debug!(
+                    error = &err as &dyn std::error::Error,
+                    path = %path.display(),
+                    "UDS socket file cleanup error"
+                ),
+            }
+        }
+    }
+}
+
+fn socket_info(socket: &UnixStream, peer_addr: &SocketAddr) -> UnixSocketInfo {
+    let local_path = socket
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(Path::to_path_buf));
+    let peer_path = peer_addr.as_pathname().map(Path::to_path_buf);
+    let peer_cred = match socket.peer_cred() {
+        Ok(cred) => Some(cred),
+        Err(err) => {
+            tracing::trace!(
+                error = &err as &dyn std::error::Error,
+                "UDS peer credentials not available"
+            );
+            None
+        }
+    };
+    UnixSocketInfo::new(local_path, peer_path, peer_cred)
+}
+
+async fn handle_accept_err(err: io::Error) {
+    if crate::tcp::utils::is_connection_error(&err) {
+        tracing::trace!(
+            error = &err as &dyn std::error::Error,
+            "UDS accept error: connect error"
+        );
+    } else {
+        // see the TCP listener for the reason of this backoff,
+        // e.g. the process having hit the max open files allowed
+        tracing::error!(error = &err as &dyn std::error::Error, "UDS accept error");
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graceful::Shutdown;
+    use crate::stream::service::EchoService;
+    use std::os::unix::fs::MetadataExt;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_unix_listener_echo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.sock");
+
+        let listener = UnixListener::bind(&path).await.unwrap();
+        assert_eq!(
+            listener.local_addr().unwrap().as_pathname(),
+            Some(path.as_path())
+        );
+
+        let (info_tx, mut info_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        shutdown.spawn_task_fn(|guard| async move {
+            listener
+                .serve_fn_graceful(guard, move |ctx: Context<()>, stream: UnixStream| {
+                    let _ = info_tx.send(ctx.get::<UnixSocketInfo>().cloned());
+                    async move { EchoService::new().serve(ctx, stream).await }
+                })
+                .await;
+        });
+
+        for message in [&b"hello"[..], &b"world"[..]] {
+            let mut client = UnixStream::connect(&path).await.unwrap();
+            client.write_all(message).await.unwrap();
+            client.shutdown().await.unwrap();
+            let mut echoed = Vec::new();
+            client.read_to_end(&mut echoed).await.unwrap();
+            assert_eq!(echoed, message);
+
+            let info = info_rx.recv().await.unwrap().expect("unix socket info");
+            assert_eq!(info.local_path(), Some(path.as_path()));
+            assert_eq!(info.peer_path(), None);
+            // the peer is this process, which also owns the socket file
+            let cred = info.peer_cred().expect("peer credentials");
+            assert_eq!(cred.uid(), std::fs::metadata(&path).unwrap().uid());
+        }
+
+        // the socket file is removed as part of the graceful shutdown
+        assert!(path.exists());
+        shutdown_tx.send(()).unwrap();
+        shutdown
+            .shutdown_with_limit(Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(!path.exists());
+        assert!(UnixStream::connect(&path).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unix_listener_no_cleanup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kept.sock");
+
+        let listener = UnixListener::build()
+            .cleanup(false)
+            .bind(&path)
+            .await
+            .unwrap();
+        drop(listener);
+        assert!(path.exists());
+
+        // binding to an existing socket file fails
+        assert!(UnixListener::bind(&path).await.is_err());
+        std::fs::remove_file(&path).unwrap();
+        drop(UnixListener::bind(&path).await.unwrap());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,32 @@
+//! Unix domain socket (UDS) server module for Rama.
+//!
+//! The UDS server is used to create a [`UnixListener`] and accept incoming connections,
+//! in the same way as a [`TcpListener`] does for TCP.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rama::uds::server::UnixListener;
+//! use tokio::{io::AsyncWriteExt, net::UnixStream};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     UnixListener::bind("/tmp/rama.sock")
+//!         .await
+//!         .expect("bind Unix Listener")
+//!         .serve_fn(|mut stream: UnixStream| async move {
+//!             stream
+//!                 .write_all(b"hello from rama")
+//!                 .await
+//!                 .expect("write to stream");
+//!
+//!             Ok::<_, std::convert::Infallible>(())
+//!         })
+//!         .await;
+//! }
+//! ```
+//!
+//! [`TcpListener`]: crate::tcp::server::TcpListener
+
+mod listener;
+pub use listener::{UnixListener, UnixListenerBuilder};